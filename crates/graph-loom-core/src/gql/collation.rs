@@ -0,0 +1,147 @@
+//! Case-insensitive matching and locale-aware ordering for the query language.
+//!
+//! A session-wide default comes from the settings; a single query can override it with a
+//! `CYPHER caseInsensitive=true collation=locale` prefix. The engine reads the effective
+//! options through [`options`] while the query runs.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+
+/// How strings are ordered by ORDER BY and range comparisons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// Plain code point order (the historical behaviour).
+    #[default]
+    Binary,
+    /// Dictionary order: accents and case only break ties, so `Émile` sorts next to `emma`.
+    Locale,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Labels, relationship types, pattern properties and string operators ignore case.
+    pub case_insensitive: bool,
+    pub collation: Collation,
+}
+
+static SESSION: Lazy<RwLock<QueryOptions>> = Lazy::new(|| RwLock::new(QueryOptions::default()));
+
+thread_local! {
+    // Per-query override installed by `with_options` for the duration of one execution
+    static ACTIVE: Cell<Option<QueryOptions>> = const { Cell::new(None) };
+}
+
+pub fn set_session_options(opts: QueryOptions) {
+    if let Ok(mut s) = SESSION.write() { *s = opts; }
+}
+
+pub fn session_options() -> QueryOptions {
+    SESSION.read().map(|s| *s).unwrap_or_default()
+}
+
+/// Options in effect for the query running on this thread.
+pub fn options() -> QueryOptions {
+    ACTIVE.with(|a| a.get()).unwrap_or_else(session_options)
+}
+
+/// Run `f` with `opts` in effect on this thread, restoring the previous options afterwards.
+pub fn with_options<T>(opts: QueryOptions, f: impl FnOnce() -> T) -> T {
+    let prev = ACTIVE.with(|a| a.replace(Some(opts)));
+    let out = f();
+    ACTIVE.with(|a| a.set(prev));
+    out
+}
+
+/// Split a leading `CYPHER key=value ...` prefix off `query`. Keys not given keep their
+/// session value. Returns `None` options when there is no prefix.
+pub fn strip_options_prefix(query: &str) -> Result<(Option<QueryOptions>, &str)> {
+    let q = query.trim_start();
+    let has_prefix = q.get(..7).is_some_and(|p| p.eq_ignore_ascii_case("CYPHER ") || p.eq_ignore_ascii_case("CYPHER\n"));
+    if !has_prefix { return Ok((None, query)); }
+    let mut opts = session_options();
+    let mut rest = q[7..].trim_start();
+    loop {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let Some((key, value)) = rest[..end].split_once('=') else { break };
+        match key.to_ascii_lowercase().as_str() {
+            "caseinsensitive" => {
+                opts.case_insensitive = value.parse().map_err(|_| anyhow!("caseInsensitive expects true or false, got '{}'", value))?;
+            }
+            "collation" => {
+                opts.collation = match value.trim_matches(['\'', '"']).to_ascii_lowercase().as_str() {
+                    "binary" => Collation::Binary,
+                    "locale" => Collation::Locale,
+                    other => return Err(anyhow!("unknown collation '{}' (expected binary or locale)", other)),
+                };
+            }
+            other => return Err(anyhow!("unknown query option '{}'", other)),
+        }
+        rest = rest[end..].trim_start();
+    }
+    if rest.is_empty() { return Err(anyhow!("CYPHER options must be followed by a query")); }
+    Ok((Some(opts), rest))
+}
+
+/// Lowercase `s` when case-insensitive matching is on.
+pub fn fold(s: &str) -> Cow<'_, str> {
+    if options().case_insensitive { Cow::Owned(s.to_lowercase()) } else { Cow::Borrowed(s) }
+}
+
+/// Equality for labels, types and string values under the active options.
+pub fn text_eq(a: &str, b: &str) -> bool {
+    if options().case_insensitive { a == b || a.to_lowercase() == b.to_lowercase() } else { a == b }
+}
+
+/// Ordering for string values under the active options.
+pub fn text_cmp(a: &str, b: &str) -> Ordering {
+    let opts = options();
+    match opts.collation {
+        Collation::Binary if opts.case_insensitive => a.to_lowercase().cmp(&b.to_lowercase()),
+        Collation::Binary => a.cmp(b),
+        Collation::Locale => {
+            let (la, lb) = (a.to_lowercase(), b.to_lowercase());
+            let primary = base_letters(&la).cmp(&base_letters(&lb));
+            let secondary = primary.then_with(|| la.cmp(&lb));
+            if opts.case_insensitive { secondary } else { secondary.then_with(|| a.cmp(b)) }
+        }
+    }
+}
+
+// Strip accents from Latin letters so they sort with their base letter
+fn base_letters(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            'à'..='å' | 'ā' | 'ă' | 'ą' => out.push('a'),
+            'æ' => out.push_str("ae"),
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => out.push('c'),
+            'ď' | 'đ' | 'ð' => out.push('d'),
+            'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => out.push('e'),
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => out.push('g'),
+            'ĥ' | 'ħ' => out.push('h'),
+            'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => out.push('i'),
+            'ĵ' => out.push('j'),
+            'ķ' => out.push('k'),
+            'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => out.push('l'),
+            'ñ' | 'ń' | 'ņ' | 'ň' => out.push('n'),
+            'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => out.push('o'),
+            'œ' => out.push_str("oe"),
+            'ŕ' | 'ŗ' | 'ř' => out.push('r'),
+            'ś' | 'ŝ' | 'ş' | 'š' => out.push('s'),
+            'ß' => out.push_str("ss"),
+            'ţ' | 'ť' | 'ŧ' => out.push('t'),
+            'þ' => out.push_str("th"),
+            'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => out.push('u'),
+            'ŵ' => out.push('w'),
+            'ý' | 'ÿ' | 'ŷ' => out.push('y'),
+            'ź' | 'ż' | 'ž' => out.push('z'),
+            other => out.push(other),
+        }
+    }
+    out
+}
@@ -0,0 +1,2577 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::graph_utils::graph::{GraphDatabase, Node, Relationship};
+use super::query_interface::QueryResultRow;
+use super::collation::{fold, text_cmp, text_eq};
+use super::temporal;
+
+// NOTE: This is a pragmatic Cypher parser/executor focused on common forms:
+// - MATCH (n:Label {k:"v"}), (m:Label) [WHERE ...] RETURN n [, m [, n.prop ...]]
+// - MATCH (a:Label)-[r:TYPE]->(b:Label) RETURN a, r, b
+// - CREATE (n:Label { ... }) [RETURN n]
+// - MERGE (a)-[:TYPE]->(b) with a/b bound by preceding MATCH
+// - UNWIND $list AS x CREATE (:Item {name: x})
+// - MATCH ... WITH n, n.prop AS p [WHERE ...] MATCH ... RETURN ... (multi-stage)
+// - MATCH p = (a)-[:TYPE*..4]->(b) RETURN p, nodes(p), relationships(p), length(p)
+// It is not a complete implementation of OpenCypher.
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Var(String),
+    Prop(Box<Expr>, String),
+    FuncId(String),
+    Str(String),
+    Param(String),
+    // `expr AS name` in WITH/RETURN projections
+    Alias(Box<Expr>, String),
+    // Scalar function call; name is lowercased (see FUNCTIONS)
+    Func(String, Vec<Expr>),
+    // CASE [subject] WHEN ... THEN ... [ELSE ...] END
+    Case { subject: Option<Box<Expr>>, whens: Vec<(CaseCond, Expr)>, otherwise: Option<Box<Expr>> },
+    // a + b / a - b over numbers, strings (+ only) and dates, datetimes and durations
+    BinOp(Box<Expr>, char, Box<Expr>),
+    Null,
+}
+
+#[derive(Debug, Clone)]
+enum CaseCond {
+    // Generic form: CASE WHEN <predicate> THEN ...
+    Predicate(String),
+    // Simple form: CASE <subject> WHEN <value> THEN ...
+    Value(Expr),
+}
+
+// Scalar functions usable in RETURN/WITH/WHERE expressions (lowercase)
+pub(crate) const FUNCTIONS: &[&str] = &[
+    "toupper", "tolower", "trim", "ltrim", "rtrim", "size", "coalesce",
+    "abs", "round", "ceil", "floor", "tostring", "tointeger", "tofloat",
+    // Path functions, evaluated against a bound path variable
+    "nodes", "relationships", "length",
+    // Temporal functions over ISO-8601 strings
+    "datetime", "date", "duration", "timestamp",
+    // Timestamps the graph keeps for a bound node or relationship
+    "createdat", "updatedat",
+];
+
+#[derive(Debug, Clone, Default)]
+struct NodePattern {
+    var: Option<String>,
+    label: Option<String>,
+    props: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RelPattern {
+    var: Option<String>,
+    typ: Option<String>,
+    // direction: true if ->, false if <-, None for undirected (not supported yet)
+    right: bool,
+    props: HashMap<String, String>,
+    // Variable-length specification (if present): min..=max hops. None => exactly 1 hop
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Node(NodePattern),
+    // `path` is the variable of a named path: p = (a)-[...]->(b)
+    Path { left: NodePattern, rel: RelPattern, right: NodePattern, path: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+enum Clause {
+    Match { optional: bool, patterns: Vec<Pattern> },
+    Where(String), // raw, limited support
+    Return { items: Vec<Expr>, distinct: bool, order_by: Vec<(Expr, bool)>, skip: Option<usize>, limit: Option<usize> },
+    With { items: Vec<Expr>, distinct: bool, order_by: Vec<(Expr, bool)>, skip: Option<usize>, limit: Option<usize> },
+    Create { patterns: Vec<Pattern> },
+    Merge { pattern: Pattern },
+    Delete { vars: Vec<String>, detach: bool },
+    Set { items: Vec<String> },
+    Remove { items: Vec<String> },
+    Unwind { expr: String, var: String },
+}
+
+// Find a clause keyword at a token boundary (start or preceded by whitespace) and
+// followed by end-of-string or whitespace. Case-insensitive: caller should pass
+// an uppercased haystack and uppercase keyword. Returns the byte index in haystack.
+fn find_keyword_boundary(hay_up: &str, kw_up: &str) -> Option<usize> {
+    let bytes = hay_up.as_bytes();
+    let kwb = kw_up.as_bytes();
+    if kwb.is_empty() { return None; }
+    let n = bytes.len();
+    let m = kwb.len();
+    if m > n { return None; }
+    let mut i = 0;
+    while i + m <= n {
+        // boundary at start or previous is whitespace
+        let prev_ok = if i == 0 { true } else { bytes[i-1].is_ascii_whitespace() };
+        if prev_ok && &bytes[i..i+m] == kwb {
+            // next boundary ok (end or whitespace)
+            let next_ok = if i + m >= n { true } else { bytes[i+m].is_ascii_whitespace() };
+            if next_ok { return Some(i); }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn trim_quotes(s: &str) -> String {
+    let t = s.trim();
+    // A lone quote both starts and ends `t`, so it is left as it is
+    if t.len() >= 2 && ((t.starts_with('"') && t.ends_with('"')) || (t.starts_with('\'') && t.ends_with('\''))) {
+        t[1..t.len() - 1].to_string()
+    } else {
+        t.to_string()
+    }
+}
+
+fn parse_props(block: &str) -> Result<HashMap<String, String>> {
+    let mut map = HashMap::new();
+    let inner = block.trim();
+    if inner.is_empty() { return Ok(map); }
+    for part in inner.split(',') {
+        let kv = part.splitn(2, ':').collect::<Vec<_>>();
+        if kv.len() != 2 { return Err(anyhow!("invalid property: {}", part)); }
+        map.insert(kv[0].trim().to_string(), trim_quotes(kv[1].trim()));
+    }
+    Ok(map)
+}
+
+fn parse_node_pattern(s: &str) -> Result<NodePattern> {
+    // (var:Label {k:"v"}) | (:Label) | (var)
+    if !s.starts_with('(') || !s.ends_with(')') { return Err(anyhow!("invalid node pattern: {}", s)); }
+    let inner = &s[1..s.len()-1];
+    let mut np = NodePattern::default();
+    // split off props if any
+    let (body, props) = if let Some(b) = inner.find('{') {
+        let e = inner.rfind('}').ok_or_else(|| anyhow!("unclosed properties"))?;
+        (&inner[..b], Some(&inner[b+1..e]))
+    } else { (inner, None) };
+
+    // var and label
+    let body = body.trim();
+    if body.is_empty() {
+        // anonymous
+    } else if let Some(col) = body.find(':') {
+        // Standard openCypher form: (var:Label)
+        let v = body[..col].trim();
+        if !v.is_empty() { np.var = Some(v.to_string()); }
+        let lab = body[col+1..].trim();
+        if !lab.is_empty() { np.label = Some(lab.to_string()); }
+    } else if let Some(dot) = body.find('.') {
+        // Support alternative shorthand form: (var.Label)
+        let v = body[..dot].trim();
+        let lab = body[dot+1..].trim();
+        if !v.is_empty() { np.var = Some(v.to_string()); }
+        if !lab.is_empty() { np.label = Some(lab.to_string()); }
+    } else {
+        // Only variable name
+        np.var = Some(body.to_string());
+    }
+    if let Some(p) = props { np.props = parse_props(p)?; }
+    Ok(np)
+}
+
+fn parse_rel_pattern(s: &str) -> Result<RelPattern> {
+    // -[r:TYPE {k:"v"}]-> or -[:TYPE]-> or -[r]-> or undirected -(r)-
+    if !s.starts_with("-[") || !s.ends_with("]-") && !s.ends_with("]->") && !s.ends_with("-]") { return Err(anyhow!("invalid rel pattern: {}", s)); }
+    let right = s.ends_with("]->");
+    let mid = &s[2..s.len()- if right { 3 } else { 2 }];
+    let mut rp = RelPattern { var: None, typ: None, right, props: HashMap::new(), min_len: None, max_len: None };
+    let rest = mid.trim();
+    // Split off props if present
+    let (before_props, props_block) = if let Some(b) = rest.find('{') {
+        let e = rest.rfind('}').ok_or_else(|| anyhow!("unclosed relationship properties"))?;
+        (&rest[..b], Some(&rest[b+1..e]))
+    } else { (rest, None) };
+    // Split off variable-length suffix like *3 or *1..3 or *..3 or *1..
+    let (head, range_part) = if let Some(star) = before_props.rfind('*') {
+        // Only treat as range if '*' appears after any ':' type spec, not at beginning
+        let head = before_props[..star].trim();
+        let rng = before_props[star+1..].trim();
+        if !rng.is_empty() {
+            Some((head, rng))
+        } else {
+            Some((head, ""))
+        }
+    } else { None }
+    .map(|(h, r)| (h, Some(r)))
+    .unwrap_or((before_props, None));
+
+    if let Some(col) = head.find(':') {
+        let v = head[..col].trim();
+        if !v.is_empty() { rp.var = Some(v.to_string()); }
+        let t = head[col+1..].trim();
+        if !t.is_empty() { rp.typ = Some(t.to_string()); }
+    } else if !head.is_empty() {
+        rp.var = Some(head.to_string());
+    }
+
+    // parse range if present
+    if let Some(rng) = range_part {
+        if !rng.is_empty() {
+            // forms: N | min..max | ..max | min.. | (empty -> treat as 1..MAX)
+            if rng.contains("..") {
+                let parts: Vec<&str> = rng.split("..").collect();
+                if parts.len() != 2 { return Err(anyhow!("invalid variable-length range: *{}", rng)); }
+                let min = if parts[0].trim().is_empty() { None } else { Some(parts[0].trim().parse::<usize>().map_err(|_| anyhow!("invalid min in *{}", rng))?) };
+                let max = if parts[1].trim().is_empty() { None } else { Some(parts[1].trim().parse::<usize>().map_err(|_| anyhow!("invalid max in *{}", rng))?) };
+                rp.min_len = min;
+                rp.max_len = max;
+            } else {
+                // single number
+                let n = rng.parse::<usize>().map_err(|_| anyhow!("invalid length in *{}", rng))?;
+                rp.min_len = Some(n);
+                rp.max_len = Some(n);
+            }
+        } else {
+            // bare * means 1..=INF (we will cap later)
+            rp.min_len = Some(1);
+            rp.max_len = None;
+        }
+    }
+    if let Some(p) = props_block { rp.props = parse_props(p)?; }
+    Ok(rp)
+}
+
+fn split_top_level_comma(s: &str) -> Vec<String> {
+    // naive split by commas not inside braces
+    let mut out = Vec::new();
+    let mut level = 0i32;
+    let mut start = 0usize;
+    let bytes = s.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b as char {
+            '{' => level += 1,
+            '}' => level -= 1,
+            ',' if level == 0 => {
+                out.push(s[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(s[start..].trim().to_string());
+    out
+}
+
+// `var = map` / `var += map` in a SET item, as (var, merge, map). Property assignments
+// (`var.prop = ...`) and label changes are left to the caller.
+fn split_map_assignment(s: &str) -> Option<(&str, bool, &str)> {
+    let eq = s.find('=')?;
+    let (lhs, merge) = match s[..eq].strip_suffix('+') { Some(l) => (l.trim(), true), None => (s[..eq].trim(), false) };
+    if lhs.is_empty() || !lhs.bytes().all(is_ident_byte) { return None; }
+    let rhs = s[eq+1..].trim();
+    let is_map = rhs.starts_with('{') || rhs.starts_with('$') || (!rhs.is_empty() && rhs.bytes().all(is_ident_byte));
+    if is_map { Some((lhs, merge, rhs)) } else { None }
+}
+
+fn parse_pattern(s: &str) -> Result<Pattern> {
+    let mut s = s.trim();
+    // Named path: p = (a)-[...]->(b)
+    let mut path_var: Option<String> = None;
+    if let Some(eq) = s.find('=')
+        && s[..eq].trim().bytes().all(is_ident_byte)
+        && !s[..eq].trim().is_empty()
+        && s[eq+1..].trim_start().starts_with('(')
+    {
+        path_var = Some(s[..eq].trim().to_string());
+        s = s[eq+1..].trim_start();
+    }
+    // Defensive: if a node pattern is followed by a clause on the same string (e.g., due to upstream splitting),
+    // truncate at the first closing ')' to keep just the node pattern.
+    // This helps for inputs like "(m:Movie)\nWHERE ..." accidentally passed as one pattern string.
+    if s.starts_with('(') {
+        if let Some(pidx) = s.find(')') {
+            // If there appears to be clause text after the node, drop it
+            let after = s[pidx+1..].to_uppercase();
+            if after.contains("WHERE") || after.contains("RETURN") || after.contains("DELETE") || after.contains("DETACH DELETE") || after.contains("CREATE") || after.contains("MERGE") || after.contains("SET ") || after.contains("REMOVE ") {
+                s = &s[..=pidx];
+            }
+        }
+    }
+    // path like (a:Label)-[r:TYPE]->(b:Label) or undirected (a)-[r]-(b)
+    if let Some(mid_start) = s.find("-[") {
+        // Find the end of the left node by locating the last ')' before the rel start
+        let left_end = s[..mid_start]
+            .rfind(')')
+            .ok_or_else(|| anyhow!("bad path left"))?;
+        let left = &s[..=left_end];
+
+        // From the rel start, find the closing ']' of the relationship spec
+        let after_rel_bracket = s[mid_start..]
+            .find(']')
+            .map(|k| mid_start + k)
+            .ok_or_else(|| anyhow!("bad relationship pattern (no closing ]): {}", s))?;
+
+        // Determine direction by looking at chars after ']'
+        // Expect either "]->(" or "]-(" (we will locate the '(' explicitly next)
+        let after_br = after_rel_bracket + 1;
+        let right_dir = s.get(after_br..after_br + 2).map(|t| t == "->").unwrap_or(false);
+
+        // Locate the start of the right node pattern: the next '(' after ']' (skipping '-' or '>' if present)
+        let right_paren_idx = s[after_br..]
+            .find('(')
+            .map(|k| after_br + k)
+            .ok_or_else(|| anyhow!("bad path right (no right node)") )?;
+
+        // Relationship slice is between mid_start and the start of right node
+        let rel_slice = &s[mid_start..right_paren_idx];
+        let right = &s[right_paren_idx..];
+
+        let mut rp = parse_rel_pattern(rel_slice)?;
+        // Ensure the direction flag matches what we detected
+        rp.right = right_dir;
+
+        let np_left = parse_node_pattern(left)?;
+        let np_right = parse_node_pattern(right)?;
+        Ok(Pattern::Path { left: np_left, rel: rp, right: np_right, path: path_var })
+    } else if let Some(v) = path_var {
+        Err(anyhow!("path variable '{}' needs a relationship pattern", v))
+    } else {
+        Ok(Pattern::Node(parse_node_pattern(s)?))
+    }
+}
+
+// Positions of single-word keywords outside quotes, brackets and nested CASE ... END blocks.
+pub(crate) fn top_level_keywords(s: &str, kws: &[&'static str]) -> Vec<(usize, &'static str)> {
+    let up = s.to_ascii_uppercase();
+    let b = up.as_bytes();
+    let mut out = Vec::new();
+    let (mut depth, mut case_depth) = (0i32, 0i32);
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            i += 1;
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ if depth == 0 && is_ident_byte(c) && (i == 0 || !is_ident_byte(b[i-1])) => {
+                let mut j = i;
+                while j < b.len() && is_ident_byte(b[j]) { j += 1; }
+                let word = &up[i..j];
+                if word == "CASE" {
+                    case_depth += 1;
+                } else if word == "END" && case_depth > 0 {
+                    case_depth -= 1;
+                } else if case_depth == 0 && let Some(kw) = kws.iter().find(|k| **k == word) {
+                    out.push((i, *kw));
+                }
+                i = j;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    out
+}
+
+// Split on commas outside quotes and brackets (function arguments, projection items).
+fn split_args(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut start = 0usize;
+    for (i, &c) in s.as_bytes().iter().enumerate() {
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                out.push(s[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(s[start..].trim().to_string());
+    out
+}
+
+// Index of the ')' closing the '(' at `open`, skipping quoted text.
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    for (i, &c) in s.as_bytes().iter().enumerate().skip(open) {
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 { return Some(i); }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Split a predicate into (lhs, op, rhs) at its top-level comparison operator.
+// Keyword operators are returned as CONTAINS / STARTS / ENDS.
+fn split_comparison(s: &str) -> Option<(String, String, String)> {
+    for (i, kw) in top_level_keywords(s, &["CONTAINS", "STARTS", "ENDS"]) {
+        let after = s[i + kw.len()..].trim_start();
+        let rhs = if kw == "CONTAINS" {
+            after
+        } else if after.to_ascii_uppercase().starts_with("WITH") {
+            &after[4..]
+        } else {
+            continue;
+        };
+        return Some((s[..i].trim().to_string(), kw.to_string(), rhs.trim().to_string()));
+    }
+    let b = s.as_bytes();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    for i in 0..b.len() {
+        let c = b[i];
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ if depth == 0 => {
+                for op in ["<=", ">=", "<>", "=", "<", ">"] {
+                    if s[i..].starts_with(op) {
+                        return Some((s[..i].trim().to_string(), op.to_string(), s[i+op.len()..].trim().to_string()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_case(body: &str) -> Result<Expr> {
+    let marks = top_level_keywords(body, &["WHEN", "THEN", "ELSE"]);
+    let first_when = marks.iter().find(|m| m.1 == "WHEN").map(|m| m.0).ok_or_else(|| anyhow!("CASE requires at least one WHEN"))?;
+    let subject_str = body[..first_when].trim();
+    let subject = if subject_str.is_empty() { None } else { Some(Box::new(parse_expr(subject_str)?)) };
+    let mut whens: Vec<(CaseCond, Expr)> = Vec::new();
+    let mut otherwise = None;
+    for (k, &(pos, kw)) in marks.iter().enumerate() {
+        let seg_end = marks.get(k + 1).map(|m| m.0).unwrap_or(body.len());
+        let seg = body[pos + kw.len()..seg_end].trim();
+        match kw {
+            "WHEN" => {
+                if marks.get(k + 1).map(|m| m.1) != Some("THEN") { return Err(anyhow!("CASE: WHEN without THEN")); }
+                let cond = if subject.is_some() { CaseCond::Value(parse_expr(seg)?) } else { CaseCond::Predicate(seg.to_string()) };
+                whens.push((cond, Expr::Null));
+            }
+            "THEN" => {
+                let last = whens.last_mut().ok_or_else(|| anyhow!("CASE: THEN without WHEN"))?;
+                last.1 = parse_expr(seg)?;
+            }
+            _ => otherwise = Some(Box::new(parse_expr(seg)?)),
+        }
+    }
+    Ok(Expr::Case { subject, whens, otherwise })
+}
+
+// Parse a single projection/comparison operand.
+// Last top-level binary `+` / `-` (left-associative). A `-` only counts as an operator after
+// whitespace, `)`, a quote or a digit, so hyphenated property keys keep working.
+fn find_additive_op(s: &str) -> Option<(usize, char)> {
+    let b = s.as_bytes();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut found = None;
+    for i in 0..b.len() {
+        let c = b[i];
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'+' | b'-' if depth == 0 => {
+                let prev = s[..i].trim_end();
+                let Some(&pb) = prev.as_bytes().last() else { continue };
+                // unary sign after another operator or at the start
+                if matches!(pb, b'+' | b'-' | b'*' | b'/' | b'=' | b'<' | b'>' | b',' | b'(') { continue; }
+                if c == b'-' && !(b[i-1].is_ascii_whitespace() || matches!(pb, b')' | b'\'' | b'"') || pb.is_ascii_digit()) { continue; }
+                // exponent of a number literal such as 1e-5
+                if matches!(pb, b'e' | b'E') && prev[..prev.len()-1].bytes().last().is_some_and(|d| d.is_ascii_digit()) { continue; }
+                found = Some((i, c as char));
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn parse_expr(s: &str) -> Result<Expr> {
+    let p = s.trim();
+    let up = p.to_ascii_uppercase();
+    if up.starts_with("CASE") && up.ends_with("END") && p.len() >= 7 && !is_ident_byte(up.as_bytes()[4]) {
+        return parse_case(&p[4..p.len()-3]);
+    }
+    if let Some((i, op)) = find_additive_op(p) {
+        return Ok(Expr::BinOp(Box::new(parse_expr(&p[..i])?), op, Box::new(parse_expr(&p[i+1..])?)));
+    }
+    if let Some(name) = p.strip_prefix('$') { return Ok(Expr::Param(name.to_string())); }
+    if let Some(q) = p.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        if p.len() < 2 || !p.ends_with(q) { return Err(anyhow!("Unterminated string literal: {}", p)); }
+        return Ok(Expr::Str(trim_quotes(p)));
+    }
+    if p.parse::<f64>().is_ok() { return Ok(Expr::Str(p.to_string())); }
+    if up == "NULL" { return Ok(Expr::Null); }
+    if let Some(open) = p.find('(')
+        && open > 0
+        && p[..open].trim_end().bytes().all(is_ident_byte)
+        && matching_paren(p, open) == Some(p.len() - 1)
+    {
+        let name = p[..open].trim_end().to_ascii_lowercase();
+        let inner = p[open+1..p.len()-1].trim();
+        if name == "id" { return Ok(Expr::FuncId(inner.to_string())); }
+        if !FUNCTIONS.contains(&name.as_str()) { return Err(anyhow!("unknown function: {}()", p[..open].trim_end())); }
+        let args = if inner.is_empty() {
+            Vec::new()
+        } else {
+            split_args(inner).iter().map(|a| parse_expr(a)).collect::<Result<Vec<Expr>>>()?
+        };
+        return Ok(Expr::Func(name, args));
+    }
+    if let Some(dot) = p.find('.') {
+        return Ok(Expr::Prop(Box::new(Expr::Var(p[..dot].trim().to_string())), p[dot+1..].trim().to_string()));
+    }
+    Ok(Expr::Var(p.to_string()))
+}
+
+fn parse_return_items(s: &str) -> Result<Vec<Expr>> {
+    let mut items = Vec::new();
+    for part in split_args(s) {
+        let p = part.trim();
+        if let Some(&(i, _)) = top_level_keywords(p, &["AS"]).last() {
+            let alias = p[i+2..].trim();
+            if alias.is_empty() { return Err(anyhow!("missing alias after AS: {}", p)); }
+            items.push(Expr::Alias(Box::new(parse_expr(&p[..i])?), alias.to_string()));
+        } else {
+            items.push(parse_expr(p)?);
+        }
+    }
+    Ok(items)
+}
+
+fn fmt_num(x: f64) -> String {
+    if x.fract() == 0.0 && x.abs() < 1e15 { format!("{}", x as i64) } else { x.to_string() }
+}
+
+// Evaluate a scalar function; null (None) arguments propagate to a null result except in coalesce.
+fn call_function(name: &str, args: &[Option<String>]) -> Option<String> {
+    if name == "coalesce" { return args.iter().flatten().next().cloned(); }
+    // Temporal functions default to "now" when called without arguments
+    match (name, args.first()) {
+        ("datetime", None) => return Some(temporal::now()),
+        ("date", None) => return Some(temporal::today()),
+        ("timestamp", _) => return Some(temporal::timestamp_millis().to_string()),
+        ("datetime", Some(a)) => return temporal::parse_datetime(a.as_ref()?).map(temporal::format_datetime),
+        ("date", Some(a)) => return temporal::parse_datetime(a.as_ref()?).map(|d| temporal::format_date(d.date())),
+        ("duration", Some(a)) => return temporal::parse_duration(a.as_ref()?).map(|d| d.to_iso()),
+        _ => {}
+    }
+    let a = args.first()?.as_ref()?;
+    let num = || a.trim().parse::<f64>().ok();
+    match name {
+        "toupper" => Some(a.to_uppercase()),
+        "tolower" => Some(a.to_lowercase()),
+        "trim" => Some(a.trim().to_string()),
+        "ltrim" => Some(a.trim_start().to_string()),
+        "rtrim" => Some(a.trim_end().to_string()),
+        "size" => Some(match serde_json::from_str::<Vec<serde_json::Value>>(a) {
+            Ok(list) => list.len(),
+            Err(_) => a.chars().count(),
+        }.to_string()),
+        "abs" => num().map(|x| fmt_num(x.abs())),
+        "round" => {
+            let digits = args.get(1).and_then(|d| d.as_ref()).and_then(|d| d.trim().parse::<i32>().ok()).unwrap_or(0);
+            let scale = 10f64.powi(digits);
+            num().map(|x| fmt_num((x * scale).round() / scale))
+        }
+        "ceil" => num().map(|x| fmt_num(x.ceil())),
+        "floor" => num().map(|x| fmt_num(x.floor())),
+        "tostring" => Some(a.clone()),
+        "tointeger" => num().map(|x| fmt_num(x.trunc())),
+        "tofloat" => num().map(|x| x.to_string()),
+        _ => None,
+    }
+}
+
+// `a + b` / `a - b`: numeric, temporal, or string concatenation for `+`. Fails only when a
+// temporal result is out of range.
+fn arithmetic(a: &str, op: char, b: &str) -> Result<Option<String>> {
+    if let (Ok(x), Ok(y)) = (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        return Ok(Some(fmt_num(if op == '+' { x + y } else { x - y })));
+    }
+    if let Some(v) = temporal::arithmetic(a, op, b)? { return Ok(Some(v)); }
+    Ok(if op == '+' { Some(format!("{}{}", a, b)) } else { None })
+}
+
+fn parse_order_by(s: &str) -> Result<Vec<(Expr, bool)>> {
+    // returns list of (expr, asc=true/false)
+    let mut out = Vec::new();
+    for part in split_args(s) {
+        let p = part.trim();
+        let mut asc = true;
+        let pu = p.to_uppercase();
+        let (expr_str, dir_part) = if let Some(idx) = pu.rfind(" DESC") {
+            if idx + 5 == p.len() || p[idx+5..].trim().is_empty() {
+                asc = false;
+                (&p[..idx], Some("DESC"))
+            } else { (&p[..], None) }
+        } else if let Some(idx) = pu.rfind(" ASC") {
+            if idx + 4 == p.len() || p[idx+4..].trim().is_empty() {
+                asc = true;
+                (&p[..idx], Some("ASC"))
+            } else { (&p[..], None) }
+        } else { (&p[..], None) };
+        let expr = parse_expr(expr_str)?;
+        let _ = dir_part; // not used beyond detection
+        out.push((expr, asc));
+    }
+    Ok(out)
+}
+
+// (distinct, items, order_by, skip, limit)
+type Projection = (bool, Vec<Expr>, Vec<(Expr, bool)>, Option<usize>, Option<usize>);
+
+// Parse `[DISTINCT] items [ORDER BY ...] [SKIP n] [LIMIT n]` following RETURN or WITH.
+fn parse_projection(body: &str) -> Result<Projection> {
+    let mut body = body.trim();
+    let mut distinct = false;
+    if body.to_ascii_uppercase().starts_with("DISTINCT ") {
+        distinct = true;
+        body = body[9..].trim();
+    }
+    let mut limit: Option<usize> = None;
+    let mut skip: Option<usize> = None;
+    let mut working = body.replace(['\n', '\r', '\t'], " ");
+    loop {
+        let up = working.to_ascii_uppercase();
+        if let Some(idx) = up.rfind(" LIMIT ") {
+            let tail = working[idx+7..].trim();
+            if let Ok(n) = tail.parse::<usize>() { limit = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+        }
+        if let Some(idx) = up.rfind(" SKIP ") {
+            let tail = working[idx+6..].trim();
+            if let Ok(n) = tail.parse::<usize>() { skip = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+        }
+        break;
+    }
+    let up2 = working.to_ascii_uppercase();
+    let (items_part, order_part_opt) = if let Some(i) = up2.rfind(" ORDER BY ") {
+        (&working[..i], Some(&working[i+10..]))
+    } else { (&working[..], None) };
+    let order_by = match order_part_opt { Some(op) => parse_order_by(op.trim())?, None => Vec::new() };
+    let items = parse_return_items(items_part.trim())?;
+    Ok((distinct, items, order_by, skip, limit))
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+// Split a statement into stages at top-level UNWIND / WITH / [OPTIONAL] MATCH keywords so
+// multi-stage queries can be parsed piecewise. Keywords inside quotes or brackets, and the
+// WITH of STARTS WITH / ENDS WITH, do not start a new stage.
+fn split_stages(q: &str) -> Vec<&str> {
+    let up = q.to_ascii_uppercase();
+    let ub = up.as_bytes();
+    let mut cuts: Vec<usize> = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    for i in 0..ub.len() {
+        let c = ub[i];
+        if let Some(qc) = quote {
+            if c == qc { quote = None; }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ if depth == 0 && i > 0 && !is_ident_byte(ub[i-1]) => {
+                for kw in ["UNWIND", "WITH", "OPTIONAL", "MATCH"] {
+                    let end = i + kw.len();
+                    if !ub[i..].starts_with(kw.as_bytes()) || end >= ub.len() || !ub[end].is_ascii_whitespace() { continue; }
+                    let prev = up[..i].trim_end();
+                    if kw == "WITH" && (prev.ends_with("STARTS") || prev.ends_with("ENDS")) { continue; }
+                    if kw == "OPTIONAL" && !up[end..].trim_start().starts_with("MATCH") { continue; }
+                    if kw == "MATCH" && prev.ends_with("OPTIONAL") { continue; }
+                    cuts.push(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut stages = Vec::new();
+    let mut start = 0;
+    for c in cuts {
+        stages.push(q[start..c].trim());
+        start = c;
+    }
+    stages.push(q[start..].trim());
+    stages.retain(|s| !s.is_empty());
+    stages
+}
+
+// UNWIND <list> AS <var> [rest...]
+fn parse_unwind(stage: &str) -> Result<Vec<Clause>> {
+    let body = stage[6..].trim();
+    let as_idx = find_keyword_boundary(&body.to_ascii_uppercase(), "AS")
+        .ok_or_else(|| anyhow!("UNWIND requires `AS <variable>`"))?;
+    let expr = body[..as_idx].trim();
+    let after = body[as_idx+2..].trim_start();
+    let var_end = after.find(char::is_whitespace).unwrap_or(after.len());
+    let var = &after[..var_end];
+    if expr.is_empty() || var.is_empty() { return Err(anyhow!("invalid UNWIND: {}", stage)); }
+    let mut clauses = vec![Clause::Unwind { expr: expr.to_string(), var: var.to_string() }];
+    let rest = after[var_end..].trim();
+    if !rest.is_empty() { clauses.extend(parse_stage(rest)?); }
+    Ok(clauses)
+}
+
+// WITH <projection> [WHERE ...] [rest...]
+fn parse_with(stage: &str) -> Result<Vec<Clause>> {
+    let body = stage[4..].trim();
+    let up = body.to_ascii_uppercase();
+    let end = ["OPTIONAL MATCH", "MATCH", "CREATE", "MERGE", "SET", "REMOVE", "DETACH DELETE", "DELETE", "RETURN"]
+        .iter()
+        .filter_map(|kw| find_keyword_boundary(&up, kw))
+        .min()
+        .unwrap_or(body.len());
+    let (proj, rest) = body.split_at(end);
+    let (proj, where_part) = match find_keyword_boundary(&proj.to_ascii_uppercase(), "WHERE") {
+        Some(i) => (&proj[..i], Some(proj[i+5..].trim())),
+        None => (proj, None),
+    };
+    let (distinct, items, order_by, skip, limit) = parse_projection(proj)?;
+    let mut clauses = vec![Clause::With { items, distinct, order_by, skip, limit }];
+    if let Some(w) = where_part { clauses.push(Clause::Where(w.to_string())); }
+    let rest = rest.trim();
+    if !rest.is_empty() { clauses.extend(parse_stage(rest)?); }
+    Ok(clauses)
+}
+
+fn parse(query: &str) -> Result<Vec<Clause>> {
+    let mut clauses = Vec::new();
+    for stage in split_stages(query.trim()) {
+        let up = stage.to_ascii_uppercase();
+        if up.starts_with("UNWIND") {
+            clauses.extend(parse_unwind(stage)?);
+        } else if up.starts_with("WITH") {
+            clauses.extend(parse_with(stage)?);
+        } else {
+            clauses.extend(parse_stage(stage)?);
+        }
+    }
+    Ok(clauses)
+}
+
+// Parsed clause lists keyed by query text. Parameters are resolved at execution time, so
+// one entry serves every parameter set; the oldest entry is evicted when full.
+const PLAN_CACHE_CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct PlanCache {
+    plans: HashMap<String, Vec<Clause>>,
+    order: VecDeque<String>,
+    hits: u64,
+    misses: u64,
+}
+
+static PLAN_CACHE: Lazy<Mutex<PlanCache>> = Lazy::new(|| Mutex::new(PlanCache::default()));
+
+/// Counters for the parsed-query cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlanCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+fn parse_cached(query: &str) -> Result<Vec<Clause>> {
+    let span = tracing::info_span!("gql.parse", cached = tracing::field::Empty).entered();
+    let key = query.trim();
+    if let Ok(mut cache) = PLAN_CACHE.lock()
+        && let Some(plan) = cache.plans.get(key).cloned()
+    {
+        cache.hits += 1;
+        span.record("cached", true);
+        return Ok(plan);
+    }
+    span.record("cached", false);
+    let plan = parse(key)?;
+    if let Ok(mut cache) = PLAN_CACHE.lock() {
+        cache.misses += 1;
+        if cache.plans.len() >= PLAN_CACHE_CAPACITY
+            && let Some(old) = cache.order.pop_front()
+        {
+            cache.plans.remove(&old);
+        }
+        if cache.plans.insert(key.to_string(), plan.clone()).is_none() {
+            cache.order.push_back(key.to_string());
+        }
+    }
+    Ok(plan)
+}
+
+/// Parse a statement into the plan cache without running it, reporting syntax errors early.
+pub fn prepare(query: &str) -> Result<()> {
+    parse_cached(query).map(|_| ())
+}
+
+/// How full the parsed-query cache is and how often it saved a parse (see `db.planCache`).
+pub fn plan_cache_stats() -> PlanCacheStats {
+    PLAN_CACHE
+        .lock()
+        .map(|c| PlanCacheStats { entries: c.plans.len(), hits: c.hits, misses: c.misses })
+        .unwrap_or_default()
+}
+
+/// Unfiltered MATCH scans larger than this are flagged when RETURN has no LIMIT.
+pub const LINT_SCAN_THRESHOLD: usize = 10_000;
+
+/// Flag likely mistakes in one Cypher statement without running it: variables used before
+/// they are bound, relationship CREATE/MERGE with unbound endpoints, DELETE without DETACH on
+/// connected nodes and large scans returned without LIMIT. Unparseable statements yield no
+/// warnings; execution reports the error.
+pub fn lint(db: &GraphDatabase, query: &str) -> Vec<String> {
+    let Ok(clauses) = parse_cached(query) else { return Vec::new() };
+    let mut warnings: Vec<String> = Vec::new();
+    let mut bound: std::collections::HashSet<String> = std::collections::HashSet::new();
+    // Node patterns that introduced each variable, to estimate what DELETE and RETURN touch
+    let mut node_pats: HashMap<String, NodePattern> = HashMap::new();
+    // Largest unfiltered node scan of the current stage: (pattern text, candidate count)
+    let mut scan: Option<(String, usize)> = None;
+    let mut filtered = false;
+
+    for cl in &clauses {
+        match cl {
+            Clause::Match { patterns, .. } => {
+                for p in patterns {
+                    let nodes: Vec<&NodePattern> = match p {
+                        Pattern::Node(np) => vec![np],
+                        Pattern::Path { left, right, .. } => vec![left, right],
+                    };
+                    for np in nodes {
+                        if let Some(v) = &np.var
+                            && !bound.contains(v)
+                        {
+                            node_pats.insert(v.clone(), np.clone());
+                        }
+                    }
+                    if let Pattern::Node(np) = p
+                        && np.props.is_empty()
+                        && !np.var.as_ref().is_some_and(|v| bound.contains(v))
+                    {
+                        let n = lint_candidates(db, np).len();
+                        if scan.as_ref().is_none_or(|(_, m)| n > *m) {
+                            let label = np.label.as_ref().map(|l| format!(":{}", l)).unwrap_or_default();
+                            scan = Some((format!("({}{})", np.var.clone().unwrap_or_default(), label), n));
+                        }
+                    }
+                }
+                bound.extend(pattern_vars(patterns));
+            }
+            Clause::Where(w) => {
+                for v in lint_text_vars(w) { lint_use(&v, &bound, &mut warnings); }
+                filtered = true;
+            }
+            Clause::Unwind { var, .. } => { bound.insert(var.clone()); }
+            Clause::With { items, order_by, limit, .. } => {
+                let mut next = std::collections::HashSet::new();
+                for it in items {
+                    let mut used = Vec::new();
+                    lint_expr_vars(it, &mut used);
+                    for v in used { lint_use(&v, &bound, &mut warnings); }
+                    match it {
+                        Expr::Alias(_, a) => { next.insert(a.clone()); }
+                        Expr::Var(v) => { next.insert(v.clone()); }
+                        _ => {}
+                    }
+                }
+                let mut scope = bound.clone();
+                scope.extend(next.iter().cloned());
+                for (e, _) in order_by {
+                    let mut used = Vec::new();
+                    lint_expr_vars(e, &mut used);
+                    for v in used { lint_use(&v, &scope, &mut warnings); }
+                }
+                node_pats.retain(|k, _| next.contains(k));
+                bound = next;
+                if limit.is_some() { scan = None; }
+            }
+            Clause::Return { items, order_by, limit, .. } => {
+                let mut scope = bound.clone();
+                for it in items {
+                    let mut used = Vec::new();
+                    lint_expr_vars(it, &mut used);
+                    for v in used { lint_use(&v, &bound, &mut warnings); }
+                    if let Expr::Alias(_, a) = it { scope.insert(a.clone()); }
+                }
+                for (e, _) in order_by {
+                    let mut used = Vec::new();
+                    lint_expr_vars(e, &mut used);
+                    for v in used { lint_use(&v, &scope, &mut warnings); }
+                }
+                if limit.is_none()
+                    && !filtered
+                    && let Some((pat, n)) = &scan
+                    && *n > LINT_SCAN_THRESHOLD
+                {
+                    warnings.push(format!("MATCH {} scans {} nodes and RETURN has no LIMIT", pat, n));
+                }
+            }
+            Clause::Create { patterns } => {
+                // Later patterns of the same CREATE may use nodes created by earlier ones
+                for p in patterns {
+                    lint_rel_endpoints("CREATE", p, &bound, &mut warnings);
+                    bound.extend(pattern_vars(std::slice::from_ref(p)));
+                }
+            }
+            Clause::Merge { pattern } => {
+                lint_rel_endpoints("MERGE", pattern, &bound, &mut warnings);
+                bound.extend(pattern_vars(std::slice::from_ref(pattern)));
+            }
+            Clause::Delete { vars, detach } => {
+                for v in vars {
+                    lint_use(v, &bound, &mut warnings);
+                    if *detach { continue; }
+                    let Some(np) = node_pats.get(v) else { continue };
+                    let endpoints: std::collections::HashSet<Uuid> =
+                        db.relationships.values().flat_map(|r| [r.from_node, r.to_node]).collect();
+                    let connected = lint_candidates(db, np).into_iter().filter(|id| endpoints.contains(id)).count();
+                    if connected > 0 {
+                        warnings.push(format!(
+                            "DELETE {} without DETACH fails for nodes with relationships; up to {} matching node(s) have some (use DETACH DELETE)",
+                            v, connected
+                        ));
+                    }
+                }
+            }
+            Clause::Set { items } | Clause::Remove { items } => {
+                for it in items {
+                    let it = it.trim();
+                    let end = it.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(it.len());
+                    lint_use(&it[..end], &bound, &mut warnings);
+                }
+            }
+        }
+    }
+    warnings
+}
+
+fn lint_use(name: &str, bound: &std::collections::HashSet<String>, warnings: &mut Vec<String>) {
+    let is_ident = name.bytes().next().is_some_and(|b| b.is_ascii_alphabetic() || b == b'_') && name.bytes().all(is_ident_byte);
+    if is_ident && !bound.contains(name) {
+        let msg = format!("variable `{}` is not bound by an earlier clause", name);
+        if !warnings.contains(&msg) { warnings.push(msg); }
+    }
+}
+
+// Node ids a pattern could match before any WHERE filtering
+fn lint_candidates(db: &GraphDatabase, np: &NodePattern) -> Vec<Uuid> {
+    db.nodes
+        .values()
+        .filter(|n| np.label.as_ref().is_none_or(|l| text_eq(&n.label, l)))
+        .filter(|n| np.props.iter().all(|(k, v)| v.starts_with('$') || n.metadata.get(k).is_some_and(|x| text_eq(x, v))))
+        .map(|n| n.id)
+        .collect()
+}
+
+// CREATE/MERGE of a relationship only connects endpoints bound by an earlier clause
+fn lint_rel_endpoints(kw: &str, p: &Pattern, bound: &std::collections::HashSet<String>, warnings: &mut Vec<String>) {
+    if let Pattern::Path { left, right, .. } = p {
+        for np in [left, right] {
+            let ok = np.var.as_ref().is_some_and(|v| bound.contains(v));
+            if !ok {
+                let name = np.var.clone().unwrap_or_else(|| "()".to_string());
+                warnings.push(format!("{} of a relationship needs endpoint {} bound by an earlier MATCH or CREATE; nothing is created for it", kw, name));
+            }
+        }
+    }
+}
+
+fn lint_expr_vars(e: &Expr, out: &mut Vec<String>) {
+    match e {
+        Expr::Var(v) | Expr::FuncId(v) => out.push(v.clone()),
+        Expr::Prop(inner, _) | Expr::Alias(inner, _) => lint_expr_vars(inner, out),
+        Expr::Func(_, args) => { for a in args { lint_expr_vars(a, out); } }
+        Expr::Case { subject, whens, otherwise } => {
+            if let Some(s) = subject { lint_expr_vars(s, out); }
+            for (cond, then) in whens {
+                match cond {
+                    CaseCond::Value(v) => lint_expr_vars(v, out),
+                    CaseCond::Predicate(p) => out.extend(lint_text_vars(p)),
+                }
+                lint_expr_vars(then, out);
+            }
+            if let Some(o) = otherwise { lint_expr_vars(o, out); }
+        }
+        Expr::BinOp(a, _, b) => { lint_expr_vars(a, out); lint_expr_vars(b, out); }
+        Expr::Str(_) | Expr::Param(_) | Expr::Null => {}
+    }
+}
+
+// Variables referenced as `var.prop` in raw predicate text, skipping string literals and
+// EXISTS { ... } bodies, which bind their own variables.
+fn lint_text_vars(s: &str) -> Vec<String> {
+    let b = s.as_bytes();
+    let mut out = Vec::new();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0i32;
+    let mut i = 0usize;
+    while i < b.len() {
+        let c = b[i];
+        if let Some(q) = quote {
+            if c == q { quote = None; }
+            i += 1;
+            continue;
+        }
+        match c {
+            b'\'' | b'"' => quote = Some(c),
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            _ if depth == 0
+                && (c.is_ascii_alphabetic() || c == b'_')
+                && (i == 0 || !(is_ident_byte(b[i-1]) || b[i-1] == b'$' || b[i-1] == b'.')) =>
+            {
+                let start = i;
+                while i < b.len() && is_ident_byte(b[i]) { i += 1; }
+                if b.get(i) == Some(&b'.') { out.push(s[start..i].to_string()); }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    out
+}
+
+/// True if the statement contains a clause that writes to the graph.
+pub(crate) fn writes_graph(query: &str) -> bool {
+    let up = query.to_ascii_uppercase();
+    ["CREATE", "MERGE", "SET", "REMOVE", "DELETE"].iter().any(|kw| find_keyword_boundary(&up, kw).is_some())
+}
+
+fn parse_stage(query: &str) -> Result<Vec<Clause>> {
+    // Very small parser: MATCH ... [WHERE ...] RETURN ... | CREATE ... [RETURN ...] | MERGE ...
+    let q = query.trim();
+    let mut clauses = Vec::new();
+    let up = q.to_uppercase();
+    if up.starts_with("MATCH ") || up.starts_with("OPTIONAL MATCH ") {
+        let optional = up.starts_with("OPTIONAL MATCH ");
+        let pstart = if optional { 15 } else { 6 };
+        // find RETURN or WHERE or end
+        let rest = q[pstart..].trim();
+        let mut where_part: Option<&str> = None;
+        // Defer SET/REMOVE so they execute AFTER MATCH/WHERE
+        let mut deferred_set: Option<Vec<String>> = None;
+        let mut deferred_remove: Option<Vec<String>> = None;
+        let rest_up = rest.to_uppercase();
+        let (mut patterns_str, tail) = if let Some(i) = find_keyword_boundary(&rest_up, "RETURN") {
+            // There is a RETURN later; but there may also be WHERE/SET/REMOVE before it.
+            let head = &rest[..i];
+            let head_up = head.to_uppercase();
+            // Determine earliest clause (WHERE/SET/REMOVE) position to cut patterns region
+            let where_pos = find_keyword_boundary(&head_up, "WHERE");
+            let set_pos = find_keyword_boundary(&head_up, "SET");
+            let rem_pos = find_keyword_boundary(&head_up, "REMOVE");
+            let mut cut_idx = head.len();
+            for p in [where_pos, set_pos, rem_pos].into_iter().flatten() { if p < cut_idx { cut_idx = p; } }
+            let patterns_str = &head[..cut_idx];
+            // Extract WHERE if present
+            if let Some(wi) = where_pos {
+                let after_kw = &head[wi..];
+                let w_body = after_kw.strip_prefix("WHERE").map(|s| s.trim_start()).unwrap_or(after_kw);
+                // If SET/REMOVE also exist after WHERE within head, truncate WHERE body accordingly
+                let w_up = w_body.to_uppercase();
+                let w_trimmed = if let Some(si) = find_keyword_boundary(&w_up, "SET") {
+                    &w_body[..si]
+                } else if let Some(ri) = find_keyword_boundary(&w_up, "REMOVE") {
+                    &w_body[..ri]
+                } else { w_body };
+                where_part = Some(w_trimmed.trim());
+            }
+            // Extract SET if present (prior to RETURN) — defer execution
+            if let Some(si) = set_pos {
+                let after_kw = &head[si..];
+                let s_body = after_kw.strip_prefix("SET").map(|s| s.trim_start()).unwrap_or(after_kw);
+                // Truncate at REMOVE if it appears after SET in head
+                let sb_up = s_body.to_uppercase();
+                let s_items_str = if let Some(ri) = find_keyword_boundary(&sb_up, "REMOVE") { &s_body[..ri] } else { s_body };
+                let items = split_top_level_comma(s_items_str);
+                deferred_set = Some(items);
+            }
+            // Extract REMOVE if present (prior to RETURN) — defer execution
+            if let Some(ri) = rem_pos {
+                let after_kw = &head[ri..];
+                let r_body = after_kw.strip_prefix("REMOVE").map(|s| s.trim_start()).unwrap_or(after_kw);
+                let items = split_top_level_comma(r_body);
+                deferred_remove = Some(items);
+            }
+            (patterns_str, Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "WHERE") {
+            let patterns_str = &rest[..i];
+            // "WHERE" is 5 chars; skip keyword and following space if present
+            let after_kw = &rest[i..];
+            let w_and_tail = after_kw.strip_prefix("WHERE").map(|s| s.trim_start()).unwrap_or(after_kw);
+            // check if there is RETURN after where
+            let w_up = w_and_tail.to_uppercase();
+            if let Some(k) = find_keyword_boundary(&w_up, "RETURN") {
+                where_part = Some(&w_and_tail[..k]);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "SET") {
+                // Defer SET then continue parsing tail after it
+                let set_items = split_top_level_comma(&w_and_tail[..k]);
+                deferred_set = Some(set_items);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "REMOVE") {
+                let rem_items = split_top_level_comma(&w_and_tail[..k]);
+                deferred_remove = Some(rem_items);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "MERGE") {
+                where_part = Some(&w_and_tail[..k]);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "CREATE") {
+                where_part = Some(&w_and_tail[..k]);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "DETACH DELETE") {
+                where_part = Some(&w_and_tail[..k]);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else if let Some(k) = find_keyword_boundary(&w_up, "DELETE") {
+                where_part = Some(&w_and_tail[..k]);
+                (patterns_str, Some(&w_and_tail[k..]))
+            } else {
+                where_part = Some(w_and_tail);
+                (patterns_str, None)
+            }
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "MERGE") {
+            (&rest[..i], Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "CREATE") {
+            (&rest[..i], Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "SET") {
+            // Defer SET that appears between MATCH and end
+            let head = &rest[..i];
+            let after_kw = &rest[i..];
+            let s_body = after_kw.strip_prefix("SET").map(|s| s.trim_start()).unwrap_or(after_kw);
+            // Truncate at REMOVE if it appears after SET
+            let sb_up = s_body.to_uppercase();
+            let s_items_str = if let Some(ri) = find_keyword_boundary(&sb_up, "REMOVE") { &s_body[..ri] } else { s_body };
+            deferred_set = Some(split_top_level_comma(s_items_str));
+            (head, Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "REMOVE") {
+            let head = &rest[..i];
+            let after_kw = &rest[i..];
+            let r_body = after_kw.strip_prefix("REMOVE").map(|s| s.trim_start()).unwrap_or(after_kw);
+            deferred_remove = Some(split_top_level_comma(r_body));
+            (head, Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "DETACH DELETE") {
+            (&rest[..i], Some(&rest[i..]))
+        } else if let Some(i) = find_keyword_boundary(&rest_up, "DELETE") {
+            (&rest[..i], Some(&rest[i..]))
+        } else { (rest, None) };
+
+        // Fallback: if no WHERE/RETURN tail detected but the text still contains a WHERE token
+        // (e.g., due to unusual whitespace/newline placement), split on the first "WHERE" occurrence.
+        if tail.is_none() {
+            if let Some(i) = rest_up.find("WHERE") {
+                where_part = Some(rest[i+5..].trim());
+                patterns_str = &rest[..i];
+            }
+        }
+
+        // Defensive: if patterns_str accidentally contains trailing clause text (SET/REMOVE/RETURN/DELETE),
+        // truncate at the earliest occurrence before splitting by commas.
+        let pat_up = patterns_str.to_uppercase();
+        let mut cut = patterns_str.len();
+        for kw in [" DETACH DELETE ", " DELETE ", " RETURN ", " SET ", " REMOVE "] {
+            if let Some(i) = pat_up.find(kw) { if i < cut { cut = i; } }
+        }
+        let patterns_region = &patterns_str[..cut];
+        let mut patterns = Vec::new();
+        for pat in split_top_level_comma(patterns_region) { if !pat.is_empty() { patterns.push(parse_pattern(&pat)?); } }
+        clauses.push(Clause::Match { optional, patterns });
+        if let Some(w) = where_part { clauses.push(Clause::Where(w.trim().to_string())); }
+        if let Some(items) = deferred_set.take() { clauses.push(Clause::Set { items }); }
+        if let Some(items) = deferred_remove.take() { clauses.push(Clause::Remove { items }); }
+        if let Some(t) = tail {
+            let t = t.trim();
+            let tup = t.to_uppercase();
+            if tup.starts_with("RETURN ") {
+                // Support RETURN [DISTINCT] ... [ORDER BY ...] [SKIP n] [LIMIT n]
+                let mut body = t[7..].trim();
+                let mut distinct = false;
+                let bu = body.to_uppercase();
+                if bu.starts_with("DISTINCT ") {
+                    distinct = true;
+                    body = body[9..].trim();
+                }
+                let _body_up = body.to_uppercase();
+                // Extract LIMIT and SKIP from the end if present (order-insensitive between them)
+                let mut limit: Option<usize> = None;
+                let mut skip: Option<usize> = None;
+                // We'll iteratively peel off from the end
+                let mut working = body.to_string();
+                loop {
+                    let up = working.to_uppercase();
+                    if let Some(idx) = up.rfind(" LIMIT ") {
+                        let tail = working[idx+7..].trim();
+                        if let Some(_space) = tail.find(' ') { /* keep only last segment */ }
+                        if let Ok(n) = tail.parse::<usize>() { limit = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+                    }
+                    if let Some(idx) = up.rfind(" SKIP ") {
+                        let tail = working[idx+6..].trim();
+                        if let Ok(n) = tail.parse::<usize>() { skip = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+                    }
+                    break;
+                }
+                // Extract ORDER BY if present
+                let mut order_by: Vec<(Expr, bool)> = Vec::new();
+                let up2 = working.to_uppercase();
+                let (items_part, order_part_opt) = if let Some(i) = up2.rfind(" ORDER BY ") {
+                    (&working[..i], Some(&working[i+10..]))
+                } else { (&working[..], None) };
+                if let Some(op) = order_part_opt { order_by = parse_order_by(op.trim())?; }
+                let items = parse_return_items(items_part.trim())?;
+                clauses.push(Clause::Return { items, distinct, order_by, skip, limit });
+            } else if tup.starts_with("CREATE ") {
+                let pats = &t[6..].trim();
+                let mut patterns = Vec::new();
+                for pat in split_top_level_comma(pats) { if !pat.is_empty() { patterns.push(parse_pattern(&pat)?); } }
+                clauses.push(Clause::Create { patterns });
+            } else if tup.starts_with("MERGE ") {
+                let body = &t[6..].trim();
+                let pattern = parse_pattern(body)?;
+                clauses.push(Clause::Merge { pattern });
+            } else if tup.starts_with("DELETE ") {
+                let vars_str = &t[7..];
+                let vars = split_top_level_comma(vars_str).into_iter().map(|s| s.trim().to_string()).collect();
+                clauses.push(Clause::Delete { vars, detach: false });
+            } else if tup.starts_with("DETACH DELETE ") {
+                let vars_str = &t[14..];
+                let vars = split_top_level_comma(vars_str).into_iter().map(|s| s.trim().to_string()).collect();
+                clauses.push(Clause::Delete { vars, detach: true });
+            } else if tup.starts_with("SET ") {
+                // Allow SET ... followed by RETURN ...
+                let body = &t[4..].trim();
+                let upb = body.to_uppercase();
+                if let Some(i) = upb.find(" RETURN ") {
+                    let items_str = &body[..i];
+                    let items = split_top_level_comma(items_str);
+                    clauses.push(Clause::Set { items });
+                    let ret_part = &body[i+8..];
+                    let items = parse_return_items(ret_part.trim())?;
+                    clauses.push(Clause::Return { items, distinct: false, order_by: Vec::new(), skip: None, limit: None });
+                } else {
+                    let items = split_top_level_comma(body);
+                    clauses.push(Clause::Set { items });
+                }
+            } else if tup.starts_with("REMOVE ") {
+                // Allow REMOVE ... followed by RETURN ...
+                let body = &t[7..].trim();
+                let upb = body.to_uppercase();
+                if let Some(i) = upb.find(" RETURN ") {
+                    let items_str = &body[..i];
+                    let items = split_top_level_comma(items_str);
+                    clauses.push(Clause::Remove { items });
+                    let ret_part = &body[i+8..];
+                    let items = parse_return_items(ret_part.trim())?;
+                    clauses.push(Clause::Return { items, distinct: false, order_by: Vec::new(), skip: None, limit: None });
+                } else {
+                    let items = split_top_level_comma(body);
+                    clauses.push(Clause::Remove { items });
+                }
+            }
+        }
+        return Ok(clauses);
+    } else if up.starts_with("CREATE") {
+        // Support CREATE followed by any whitespace/newlines before patterns
+        let body = &q[6..].trim();
+        let mut parts = body.splitn(2, " RETURN ");
+        let pats = match parts.next() {
+            Some(s) => s,
+            None => return Err(anyhow!("missing CREATE patterns")),
+        };
+        let mut patterns = Vec::new();
+        for pat in split_top_level_comma(pats) { if !pat.is_empty() { patterns.push(parse_pattern(&pat)?); } }
+        clauses.push(Clause::Create { patterns });
+        if let Some(ret) = parts.next() {
+            // Allow ORDER BY/LIMIT/SKIP after RETURN even in CREATE ... RETURN
+            let ret_trim = ret.trim();
+            let body = ret_trim;
+            let mut limit: Option<usize> = None;
+            let mut skip: Option<usize> = None;
+            let mut working = body.to_string();
+            loop {
+                let up = working.to_uppercase();
+                if let Some(idx) = up.rfind(" LIMIT ") {
+                    let tail = working[idx+7..].trim();
+                    if let Ok(n) = tail.parse::<usize>() { limit = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+                }
+                if let Some(idx) = up.rfind(" SKIP ") {
+                    let tail = working[idx+6..].trim();
+                    if let Ok(n) = tail.parse::<usize>() { skip = Some(n); working = working[..idx].trim_end().to_string(); continue; }
+                }
+                break;
+            }
+            let up2 = working.to_uppercase();
+            let (items_part, order_part_opt) = if let Some(i) = up2.rfind(" ORDER BY ") {
+                (&working[..i], Some(&working[i+10..]))
+            } else { (&working[..], None) };
+            let mut order_by: Vec<(Expr, bool)> = Vec::new();
+            if let Some(op) = order_part_opt { order_by = parse_order_by(op.trim())?; }
+            let items = parse_return_items(items_part.trim())?;
+            clauses.push(Clause::Return { items, distinct: false, order_by, skip, limit });
+        }
+        return Ok(clauses);
+    } else if up.starts_with("MERGE ") {
+        let body = &q[6..].trim();
+        let pattern = parse_pattern(body)?;
+        clauses.push(Clause::Merge { pattern });
+        return Ok(clauses);
+    } else if up.starts_with("RETURN ") {
+        // Projection-only stage, e.g. the tail of `WITH ... RETURN ...` or `UNWIND ... RETURN ...`
+        let (distinct, items, order_by, skip, limit) = parse_projection(&q[7..])?;
+        clauses.push(Clause::Return { items, distinct, order_by, skip, limit });
+        return Ok(clauses);
+    } else if up.starts_with("SET ") {
+        let items_str = &q[4..];
+        let items = split_top_level_comma(items_str);
+        clauses.push(Clause::Set { items });
+        return Ok(clauses);
+    } else if up.starts_with("REMOVE ") {
+        let items_str = &q[7..];
+        let items = split_top_level_comma(items_str);
+        clauses.push(Clause::Remove { items });
+        return Ok(clauses);
+    } else if up.starts_with("DELETE ") {
+        let vars_str = &q[7..];
+        let vars = split_top_level_comma(vars_str).into_iter().map(|s| s.trim().to_string()).collect();
+        clauses.push(Clause::Delete { vars, detach: false });
+        return Ok(clauses);
+    } else if up.starts_with("DETACH DELETE ") {
+        let vars_str = &q[14..];
+        let vars = split_top_level_comma(vars_str).into_iter().map(|s| s.trim().to_string()).collect();
+        clauses.push(Clause::Delete { vars, detach: true });
+        return Ok(clauses);
+    }
+    Err(anyhow!("Unsupported or unrecognized Cypher statement"))
+}
+
+fn resolve_param(raw: &str, params: &HashMap<String, String>) -> Result<String> {
+    let t = raw.trim();
+    if t.starts_with('$') {
+        let key = &t[1..];
+        params.get(key).cloned().ok_or_else(|| anyhow!("Missing parameter: ${}", key))
+    } else {
+        Ok(trim_quotes(t))
+    }
+}
+
+// Variables introduced by a list of patterns.
+fn pattern_vars(patterns: &[Pattern]) -> Vec<String> {
+    let mut out = Vec::new();
+    for p in patterns {
+        match p {
+            Pattern::Node(np) => out.extend(np.var.clone()),
+            Pattern::Path { left, rel, right, path } => {
+                out.extend(left.var.clone());
+                out.extend(rel.var.clone());
+                out.extend(right.var.clone());
+                out.extend(path.clone());
+            }
+        }
+    }
+    out
+}
+
+// Parse a list value: a JSON array (as sent in API params) or a `[a, 'b', 3]` literal.
+fn parse_list_value(s: &str) -> Result<Vec<String>> {
+    let t = s.trim();
+    if let Ok(items) = serde_json::from_str::<Vec<serde_json::Value>>(t) {
+        return Ok(items
+            .into_iter()
+            .map(|v| match v { serde_json::Value::String(s) => s, other => other.to_string() })
+            .collect());
+    }
+    if t.starts_with('[') && t.ends_with(']') {
+        return Ok(split_top_level_comma(&t[1..t.len()-1])
+            .into_iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| trim_quotes(&p))
+            .collect());
+    }
+    Err(anyhow!("UNWIND expects a list, got: {}", t))
+}
+
+// Evaluate the list expression of an UNWIND: $param, list literal, or range(start, end[, step]).
+fn eval_list_expr(expr: &str, params: &HashMap<String, String>) -> Result<Vec<String>> {
+    let t = expr.trim();
+    if t.starts_with('$') {
+        return parse_list_value(&resolve_param(t, params)?);
+    }
+    if t.to_ascii_uppercase().starts_with("RANGE(") && t.ends_with(')') {
+        let args = t[6..t.len()-1]
+            .split(',')
+            .map(|a| resolve_param(a, params)?.trim().parse::<i64>().map_err(|_| anyhow!("range() expects integers: {}", t)))
+            .collect::<Result<Vec<i64>>>()?;
+        let (start, end, step) = match args[..] {
+            [a, b] => (a, b, 1),
+            [a, b, st] if st != 0 => (a, b, st),
+            _ => return Err(anyhow!("range() expects (start, end[, step]) with a non-zero step")),
+        };
+        let mut out = Vec::new();
+        let mut i = start;
+        while (step > 0 && i <= end) || (step < 0 && i >= end) {
+            out.push(i.to_string());
+            i += step;
+        }
+        return Ok(out);
+    }
+    parse_list_value(t)
+}
+
+// Compare two property values: numerically when both parse as numbers, otherwise as strings.
+fn compare_values(sv: &str, op: &str, lit: &str) -> bool {
+    let as_num = |s: &str| s.parse::<f64>().ok();
+    if let (Some(a), Some(b)) = (as_num(sv), as_num(lit)) {
+        match op { "<"=>a<b, "<="=>a<=b, ">"=>a>b, ">="=>a>=b, "="=> a==b, "<>"=> a!=b, _=>true }
+    } else if let (Some(a), Some(b)) = (temporal::parse_datetime(sv), temporal::parse_datetime(lit)) {
+        // ISO-8601 dates/datetimes compare as instants, whatever their offsets
+        match op { "<"=>a<b, "<="=>a<=b, ">"=>a>b, ">="=>a>=b, "="=> a==b, "<>"=> a!=b, _=>true }
+    } else {
+        // Strings follow the active case / collation options
+        match op { "="=> text_eq(sv, lit), "<>"=> !text_eq(sv, lit), _ => {
+            let ord = text_cmp(sv, lit);
+            match op { "<"=> ord.is_lt(), ">"=> ord.is_gt(), "<="=> ord.is_le(), ">="=> ord.is_ge(), _=> true }
+        } }
+    }
+}
+
+pub fn execute_cypher_with_params(db: &mut GraphDatabase, query: &str, params: &HashMap<String, String>) -> Result<Vec<QueryResultRow>> {
+    let clauses = parse_cached(query)?;
+    // binding map: var -> either Node or Relationship id
+    // binding map: var -> Node id, Relationship id, a scalar from UNWIND / WITH ... AS,
+    // a named path, or null for an OPTIONAL MATCH pattern that did not match
+    #[derive(Clone)]
+    enum Val { NodeId(Uuid), RelId(Uuid), Scalar(String), Path { nodes: Vec<Uuid>, rels: Vec<Uuid> }, Null }
+    let mut rows: Vec<HashMap<String, Val>> = vec![HashMap::new()];
+
+    fn prop_of(db: &GraphDatabase, val: Option<&Val>, prop: &str) -> Option<String> {
+        match val {
+            Some(Val::NodeId(id)) => db.get_node(*id).and_then(|n| n.metadata.get(prop).cloned()),
+            Some(Val::RelId(id)) => db.get_relationship(*id).and_then(|r| r.metadata.get(prop).cloned()),
+            _ => None,
+        }
+    }
+
+    // Evaluate a projection expression to a bindable value (None when it yields null); fails
+    // when date arithmetic overflows
+    fn eval_val(db: &GraphDatabase, expr: &Expr, row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<Option<Val>> {
+        let scalar = |e: &Expr| eval_val(db, e, row, params).map(|v| v.and_then(scalar_of));
+        Ok(match expr {
+            Expr::Var(v) => row.get(v).cloned(),
+            Expr::Prop(inner, prop) => match &**inner {
+                Expr::Var(v) => prop_of(db, row.get(v), prop).map(Val::Scalar),
+                _ => None,
+            },
+            Expr::FuncId(v) => match row.get(v) {
+                Some(Val::NodeId(id)) | Some(Val::RelId(id)) => Some(Val::Scalar(id.to_string())),
+                _ => None,
+            },
+            Expr::Str(s) => Some(Val::Scalar(s.clone())),
+            Expr::Param(p) => params.get(p).cloned().map(Val::Scalar),
+            Expr::Alias(inner, _) => eval_val(db, inner, row, params)?,
+            Expr::Func(name, args) if matches!(name.as_str(), "nodes" | "relationships" | "length") => {
+                let Some(Expr::Var(v)) = args.first() else { return Ok(None) };
+                let Some(Val::Path { nodes, rels }) = row.get(v) else { return Ok(None) };
+                let ids = |ids: &[Uuid]| serde_json::to_string(&ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()).unwrap_or_default();
+                Some(Val::Scalar(match name.as_str() {
+                    "nodes" => ids(nodes),
+                    "relationships" => ids(rels),
+                    _ => rels.len().to_string(),
+                }))
+            }
+            Expr::Func(name, args) if matches!(name.as_str(), "createdat" | "updatedat") => {
+                let Some(Expr::Var(v)) = args.first() else { return Ok(None) };
+                let times = match row.get(v) {
+                    Some(Val::NodeId(id)) => db.get_node(*id).map(|n| (&n.created_at, &n.updated_at)),
+                    Some(Val::RelId(id)) => db.get_relationship(*id).map(|r| (&r.created_at, &r.updated_at)),
+                    _ => None,
+                };
+                let Some((created, updated)) = times else { return Ok(None) };
+                let at = if name == "createdat" { created } else { updated };
+                (!at.is_empty()).then(|| Val::Scalar(at.clone()))
+            }
+            Expr::Func(name, args) => {
+                let vals: Vec<Option<String>> = args.iter().map(scalar).collect::<Result<_>>()?;
+                call_function(name, &vals).map(Val::Scalar)
+            }
+            Expr::Case { subject, whens, otherwise } => {
+                let subj = subject.as_ref().map(|e| scalar(e)).transpose()?;
+                for (cond, result) in whens {
+                    let hit = match (cond, &subj) {
+                        (CaseCond::Value(v), Some(Some(sv))) => scalar(v)?.as_ref() == Some(sv),
+                        (CaseCond::Predicate(p), None) => filter_where(db, p, std::slice::from_ref(row), params).map(|r| !r.is_empty()).unwrap_or(false),
+                        _ => false,
+                    };
+                    if hit { return eval_val(db, result, row, params); }
+                }
+                match otherwise {
+                    Some(e) => eval_val(db, e, row, params)?,
+                    None => None,
+                }
+            }
+            Expr::BinOp(l, op, r) => {
+                let (Some(a), Some(b)) = (scalar(l)?, scalar(r)?) else { return Ok(None) };
+                arithmetic(&a, *op, &b)?.map(Val::Scalar)
+            }
+            Expr::Null => None,
+        })
+    }
+
+    fn scalar_of(v: Val) -> Option<String> {
+        match v {
+            Val::NodeId(id) | Val::RelId(id) => Some(id.to_string()),
+            Val::Scalar(s) => Some(s),
+            Val::Path { rels, .. } => Some(rels.len().to_string()),
+            Val::Null => None,
+        }
+    }
+
+    // String sort key for ORDER BY
+    fn sort_key(db: &GraphDatabase, expr: &Expr, row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<String> {
+        Ok(match eval_val(db, expr, row, params)? {
+            Some(Val::NodeId(id)) | Some(Val::RelId(id)) => id.to_string(),
+            Some(Val::Scalar(s)) => s,
+            Some(Val::Path { rels, .. }) => rels.len().to_string(),
+            Some(Val::Null) | None => String::new(),
+        })
+    }
+
+    // Property value in CREATE: bound scalar variable, var.prop of a bound element, $param,
+    // a function call such as datetime(), or a literal
+    fn row_value(db: &GraphDatabase, raw: &str, row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<String> {
+        let t = raw.trim();
+        if let Some(Val::Scalar(s)) = row.get(t) { return Ok(s.clone()); }
+        // Pattern property values arrive with quotes stripped, so only calls are evaluated here
+        if t.contains('(')
+            && let Ok(e @ (Expr::Func(..) | Expr::BinOp(..))) = parse_expr(t)
+        {
+            return Ok(eval_val(db, &e, row, params)?.and_then(scalar_of).unwrap_or_default());
+        }
+        if let Some((v, prop)) = t.split_once('.')
+            && matches!(row.get(v.trim()), Some(Val::NodeId(_)) | Some(Val::RelId(_)))
+        {
+            return Ok(prop_of(db, row.get(v.trim()), prop.trim()).unwrap_or_default());
+        }
+        resolve_param(t, params)
+    }
+
+    // Properties for SET n = / += : a {k: v} literal (a null value removes the key), a
+    // JSON-object parameter, or the properties of another bound node or relationship
+    fn map_entries(db: &GraphDatabase, rhs: &str, row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<Vec<(String, Option<String>)>> {
+        if rhs.starts_with('{') && rhs.ends_with('}') {
+            let mut out = Vec::new();
+            for part in split_top_level_comma(&rhs[1..rhs.len()-1]) {
+                if part.is_empty() { continue; }
+                let (k, v) = part.split_once(':').ok_or_else(|| anyhow!("invalid property: {}", part))?;
+                let v = v.trim();
+                let value = if v.eq_ignore_ascii_case("null") { None } else { Some(row_value(db, v, row, params)?) };
+                out.push((trim_quotes(k.trim()), value));
+            }
+            return Ok(out);
+        }
+        if rhs.starts_with('$') {
+            let json = resolve_param(rhs, params)?;
+            let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)
+                .map_err(|_| anyhow!("parameter {} must be a JSON object to SET a property map", rhs))?;
+            return Ok(map.into_iter().map(|(k, v)| {
+                let v = match v {
+                    serde_json::Value::Null => None,
+                    serde_json::Value::String(s) => Some(s),
+                    other => Some(other.to_string()),
+                };
+                (k, v)
+            }).collect());
+        }
+        let meta = match row.get(rhs) {
+            Some(Val::NodeId(id)) => db.get_node(*id).map(|n| n.metadata.clone()),
+            Some(Val::RelId(id)) => db.get_relationship(*id).map(|r| r.metadata.clone()),
+            _ => None,
+        };
+        meta.map(|m| m.into_iter().map(|(k, v)| (k, Some(v))).collect())
+            .ok_or_else(|| anyhow!("SET expects a map, a map parameter or a node/relationship: {}", rhs))
+    }
+
+    // helpers
+    let get_node = |db: &GraphDatabase, id: &Uuid| -> Option<Node> { db.get_node(*id).cloned() };
+    let get_rel = |db: &GraphDatabase, id: &Uuid| -> Option<Relationship> { db.get_relationship(*id).cloned() };
+
+    // WHERE support: conjunctive clauses with AND; supports
+    // - id(a) <op> id(b)
+    // - var.prop <op> literal
+    // - var.prop CONTAINS 'substr'
+    // - var IS [NOT] NULL
+    // - comparisons over function calls and CASE expressions, e.g. toLower(n.name) = 'ada'
+    // - [NOT] EXISTS { MATCH (n)-[:OWNS]->(:Asset) [WHERE ...] }
+    fn filter_where(db: &GraphDatabase, w: &str, rows: &[HashMap<String, Val>], params: &HashMap<String, String>) -> Result<Vec<HashMap<String, Val>>> {
+        fn split_where_and(s: &str) -> Vec<String> {
+            let mut out = Vec::new();
+            let mut start = 0usize;
+            let mut i = 0usize;
+            let bytes = s.as_bytes();
+            let n = bytes.len();
+            let mut in_sq = false;
+            let mut in_dq = false;
+            // AND inside an EXISTS { ... } body belongs to the subquery
+            let mut braces = 0i32;
+            while i < n {
+                let c = bytes[i] as char;
+                if c == '\'' && !in_dq { in_sq = !in_sq; i += 1; continue; }
+                if c == '"' && !in_sq { in_dq = !in_dq; i += 1; continue; }
+                if !in_sq && !in_dq && c == '{' { braces += 1; }
+                if !in_sq && !in_dq && c == '}' { braces -= 1; }
+                if !in_sq && !in_dq && braces == 0 {
+                    // check for AND with boundaries
+                    if i + 3 <= n {
+                        let seg = &s[i..i+3];
+                        if seg.eq("AND") || seg.eq_ignore_ascii_case("AND") {
+                            // ensure boundaries are whitespace around
+                            let prev_ws = i == 0 || bytes[i-1].is_ascii_whitespace();
+                            let next_ws = i+3 >= n || bytes[i+3].is_ascii_whitespace();
+                            if prev_ws && next_ws {
+                                out.push(s[start..i].trim().to_string());
+                                start = i+3;
+                                i += 3;
+                                continue;
+                            }
+                        }
+                    }
+                }
+                i += 1;
+            }
+            out.push(s[start..].trim().to_string());
+            out.retain(|x| !x.is_empty());
+            out
+        }
+
+        fn trim_quotes_owned(s: &str) -> String { trim_quotes(s) }
+
+        fn parse_id_compare(expr: &str) -> Option<(String, String, String)> {
+            let mut s = expr.trim().to_string();
+            s = s.replace('\n', " ");
+            s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            let s = s.replace(' ', "");
+            let ops = ["<=", ">=", "<>", "<", ">", "="];
+            for op in ops {
+                if let Some(i) = s.find(op) {
+                    let lhs = &s[..i];
+                    let rhs = &s[i+op.len()..];
+                    if lhs.starts_with("id(") && lhs.ends_with(")") && rhs.starts_with("id(") && rhs.ends_with(")") {
+                        let lv = lhs[3..lhs.len()-1].to_string();
+                        let rv = rhs[3..rhs.len()-1].to_string();
+                        return Some((lv, op.to_string(), rv));
+                    }
+                }
+            }
+            None
+        }
+
+        fn parse_var_prop_comp(expr: &str) -> Option<(String, String, String, String)> {
+            let ops = ["<=", ">=", "<>", "=", "<", ">"]; // order matters
+            for op in ops {
+                if let Some(i) = expr.find(op) {
+                    let lhs = expr[..i].trim();
+                    let rhs = expr[i+op.len()..].trim();
+                    if let Some(dot) = lhs.find('.') {
+                        let var = lhs[..dot].trim();
+                        let prop = lhs[dot+1..].trim();
+                        return Some((var.to_string(), prop.to_string(), op.to_string(), rhs.to_string()));
+                    }
+                }
+            }
+            None
+        }
+
+        fn parse_contains(expr: &str) -> Option<(String, String, String)> {
+            let up = expr.to_uppercase();
+            if let Some(i) = up.find(" CONTAINS ") {
+                let lhs = expr[..i].trim();
+                let rhs = expr[i+10..].trim();
+                if let Some(dot) = lhs.find('.') {
+                    let var = lhs[..dot].trim().to_string();
+                    let prop = lhs[dot+1..].trim().to_string();
+                    return Some((var, prop, rhs.to_string()));
+                }
+            }
+            None
+        }
+
+        fn parse_starts_with(expr: &str) -> Option<(String, String, String)> {
+            let up = expr.to_uppercase();
+            if let Some(i) = up.find(" STARTS WITH ") {
+                let lhs = expr[..i].trim();
+                let rhs = expr[i+13..].trim();
+                if let Some(dot) = lhs.find('.') {
+                    let var = lhs[..dot].trim().to_string();
+                    let prop = lhs[dot+1..].trim().to_string();
+                    return Some((var, prop, rhs.to_string()));
+                }
+            }
+            None
+        }
+
+        fn parse_ends_with(expr: &str) -> Option<(String, String, String)> {
+            let up = expr.to_uppercase();
+            if let Some(i) = up.find(" ENDS WITH ") {
+                let lhs = expr[..i].trim();
+                let rhs = expr[i+10..].trim();
+                if let Some(dot) = lhs.find('.') {
+                    let var = lhs[..dot].trim().to_string();
+                    let prop = lhs[dot+1..].trim().to_string();
+                    return Some((var, prop, rhs.to_string()));
+                }
+            }
+            None
+        }
+
+        // bare variable (e.g. a WITH alias or UNWIND item) compared to a literal
+        fn parse_var_comp(expr: &str) -> Option<(String, String, String)> {
+            let ops = ["<=", ">=", "<>", "=", "<", ">"];
+            for op in ops {
+                if let Some(i) = expr.find(op) {
+                    let lhs = expr[..i].trim();
+                    if !lhs.is_empty() && lhs.bytes().all(is_ident_byte) {
+                        return Some((lhs.to_string(), op.to_string(), expr[i+op.len()..].trim().to_string()));
+                    }
+                }
+            }
+            None
+        }
+
+        let clauses = split_where_and(w);
+        let mut filtered: Vec<HashMap<String, Val>> = Vec::new();
+        'rowloop: for row in rows {
+            // each clause must pass
+            for clause in &clauses {
+                let c = clause.trim();
+                let cu = c.to_ascii_uppercase();
+                // [NOT] EXISTS { [MATCH] pattern [WHERE ...] }
+                let (negated, ex) = if cu.starts_with("NOT ") { (true, c[4..].trim_start()) } else { (false, c) };
+                if ex.to_ascii_uppercase().starts_with("EXISTS") {
+                    let inner = ex[6..].trim();
+                    if inner.starts_with('{') && inner.ends_with('}') {
+                        let found = exists_subquery(db, &inner[1..inner.len()-1], row, params)?;
+                        if found == negated { continue 'rowloop; }
+                        continue;
+                    }
+                }
+                // var IS [NOT] NULL / var.prop IS [NOT] NULL
+                if let Some(i) = cu.find(" IS ") {
+                    let tail = cu[i+4..].trim();
+                    if tail == "NULL" || tail == "NOT NULL" {
+                        let lhs = c[..i].trim();
+                        let is_null = match parse_expr(lhs) {
+                            Ok(e) => eval_val(db, &e, row, params)?.and_then(scalar_of).is_none(),
+                            Err(_) => true,
+                        };
+                        if is_null != (tail == "NULL") { continue 'rowloop; }
+                        continue;
+                    }
+                }
+                // comparisons involving function calls or CASE expressions
+                if ((c.contains('(') && parse_id_compare(c).is_none()) || cu.starts_with("CASE") || find_additive_op(c).is_some())
+                    && let Some((lhs, op, rhs)) = split_comparison(c)
+                {
+                    let side = |e: &str| match parse_expr(e) {
+                        Ok(x) => eval_val(db, &x, row, params).map(|v| v.and_then(scalar_of)),
+                        Err(_) => Ok(None),
+                    };
+                    let pass = match (side(&lhs)?, side(&rhs)?) {
+                        (Some(a), Some(b)) => match op.as_str() {
+                            "CONTAINS" => fold(&a).contains(fold(&b).as_ref()),
+                            "STARTS" => fold(&a).starts_with(fold(&b).as_ref()),
+                            "ENDS" => fold(&a).ends_with(fold(&b).as_ref()),
+                            _ => compare_values(&a, &op, &b),
+                        },
+                        _ => false,
+                    };
+                    if !pass { continue 'rowloop; }
+                    continue;
+                }
+                // id compare
+                if let Some((lv, op, rv)) = parse_id_compare(c) {
+                    if let (Some(Val::NodeId(a)), Some(Val::NodeId(b))) = (row.get(&lv), row.get(&rv)) {
+                        let la = a.as_u128(); let lb = b.as_u128();
+                        let pass = match op.as_str() { "<"=>la<lb, "<="=>la<=lb, ">"=>la>lb, ">="=>la>=lb, "="=>la==lb, "<>"=>la!=lb, _=>true };
+                        if !pass { continue 'rowloop; }
+                    } else { continue 'rowloop; }
+                    continue;
+                }
+                // CONTAINS
+                if let Some((var, prop, rhs)) = parse_contains(c) {
+                    let val = if rhs.starts_with('"') || rhs.starts_with('\'') { trim_quotes_owned(&rhs) } else { resolve_param(&rhs, params)? };
+                    // Only node props for now
+                    if let Some(Val::NodeId(id)) = row.get(&var) {
+                        if let Some(n) = db.get_node(*id) {
+                            let sv = n.metadata.get(&prop).cloned().unwrap_or_default();
+                            if !fold(&sv).contains(fold(&val).as_ref()) { continue 'rowloop; }
+                        } else { continue 'rowloop; }
+                    } else { continue 'rowloop; }
+                    continue;
+                }
+                // STARTS WITH
+                if let Some((var, prop, rhs)) = parse_starts_with(c) {
+                    let val = if rhs.starts_with('"') || rhs.starts_with('\'') { trim_quotes_owned(&rhs) } else { resolve_param(&rhs, params)? };
+                    if let Some(Val::NodeId(id)) = row.get(&var) {
+                        if let Some(n) = db.get_node(*id) {
+                            let sv = n.metadata.get(&prop).cloned().unwrap_or_default();
+                            if !fold(&sv).starts_with(fold(&val).as_ref()) { continue 'rowloop; }
+                        } else { continue 'rowloop; }
+                    } else { continue 'rowloop; }
+                    continue;
+                }
+                // ENDS WITH
+                if let Some((var, prop, rhs)) = parse_ends_with(c) {
+                    let val = if rhs.starts_with('"') || rhs.starts_with('\'') { trim_quotes_owned(&rhs) } else { resolve_param(&rhs, params)? };
+                    if let Some(Val::NodeId(id)) = row.get(&var) {
+                        if let Some(n) = db.get_node(*id) {
+                            let sv = n.metadata.get(&prop).cloned().unwrap_or_default();
+                            if !fold(&sv).ends_with(fold(&val).as_ref()) { continue 'rowloop; }
+                        } else { continue 'rowloop; }
+                    } else { continue 'rowloop; }
+                    continue;
+                }
+                // var.prop op literal
+                if let Some((var, prop, op, rhs)) = parse_var_prop_comp(c) {
+                    let lit = if rhs.starts_with('"') || rhs.starts_with('\'') { trim_quotes_owned(&rhs) } else { resolve_param(&rhs, params)? };
+                    // Only node props for now
+                    if let Some(Val::NodeId(id)) = row.get(&var) {
+                        if let Some(n) = db.get_node(*id) {
+                            let sv = n.metadata.get(&prop).cloned().unwrap_or_default();
+                            if !compare_values(&sv, &op, &lit) { continue 'rowloop; }
+                        } else { continue 'rowloop; }
+                    } else { continue 'rowloop; }
+                    continue;
+                }
+                if let Some((var, op, rhs)) = parse_var_comp(c) {
+                    let lit = if rhs.starts_with('"') || rhs.starts_with('\'') { trim_quotes_owned(&rhs) } else { resolve_param(&rhs, params)? };
+                    match row.get(&var) {
+                        Some(Val::Scalar(sv)) if compare_values(sv, &op, &lit) => {}
+                        _ => continue 'rowloop,
+                    }
+                    continue;
+                }
+                // unsupported clause -> fail-safe: do not filter this row out
+            }
+            filtered.push(row.clone());
+        }
+        Ok(filtered)
+    }
+
+    // Existential subquery: true if the MATCH (and WHERE) yields any binding extending `row`
+    fn exists_subquery(db: &GraphDatabase, body: &str, row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<bool> {
+        let body = body.trim();
+        let up = body.to_ascii_uppercase();
+        let q = if up.starts_with("MATCH ") || up.starts_with("OPTIONAL MATCH ") { body.to_string() } else { format!("MATCH {}", body) };
+        let mut rows = vec![row.clone()];
+        for cl in parse_cached(&q)? {
+            rows = match cl {
+                Clause::Match { patterns, .. } => {
+                    let mut next = Vec::new();
+                    for r in &rows { next.extend(match_row(db, &patterns, r, params)?); }
+                    next
+                }
+                Clause::Where(w) => filter_where(db, &w, &rows, params)?,
+                Clause::Return { .. } => rows,
+                _ => return Err(anyhow!("EXISTS subqueries may only contain MATCH and WHERE")),
+            };
+            if rows.is_empty() { return Ok(false); }
+        }
+        Ok(true)
+    }
+
+    // Expand the patterns for one incoming row (AND semantics), returning every consistent binding.
+    fn match_row(db: &GraphDatabase, patterns: &[Pattern], row: &HashMap<String, Val>, params: &HashMap<String, String>) -> Result<Vec<HashMap<String, Val>>> {
+        let mut partials = vec![row.clone()];
+        for p in patterns {
+            let mut new_partials: Vec<HashMap<String, Val>> = Vec::new();
+            match p {
+                Pattern::Node(np) => {
+                    for (nid, n) in &db.nodes {
+                        if let Some(l) = &np.label { if !text_eq(&n.label, l) { continue; } }
+                        // property exact matches
+                        let mut ok = true;
+                        for (k, vraw) in &np.props {
+                            let v = resolve_param(vraw, params)?;
+                            if !n.metadata.get(k).is_some_and(|x| text_eq(x, &v)) { ok = false; break; }
+                        }
+                        if !ok { continue; }
+                        for part in &partials {
+                            // bind var if present and consistent
+                            let mut m = part.clone();
+                            if let Some(v) = &np.var {
+                                if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if pid == nid) { continue; } }
+                                m.insert(v.clone(), Val::NodeId(*nid));
+                            }
+                            new_partials.push(m);
+                        }
+                    }
+                }
+                Pattern::Path { left, rel, right, path } => {
+                    // Variable-length?
+                    if rel.min_len.is_some() || rel.max_len.is_some() {
+                        if !rel.props.is_empty() {
+                            return Err(anyhow!("variable-length relationships with properties not supported yet"));
+                        }
+                        // Determine allowed hop range
+                        let min_hops = rel.min_len.unwrap_or(1);
+                        // Cap open-ended max to avoid infinite traversal
+                        let cap = 8usize; // conservative cap
+                        let max_hops = rel.max_len.unwrap_or(cap).min(cap);
+
+                        // Helper to test node metadata against NodePattern
+                        let node_ok = |n: &Node, pat: &NodePattern| -> bool {
+                            if let Some(lab) = &pat.label { if !text_eq(&n.label, lab) { return false; } }
+                            for (k, vraw) in &pat.props {
+                                // In closures, we cannot use resolve_param easily; patterns here should not include params for variable-length endpoints in our current usage. Fallback to exact.
+                                if !n.metadata.get(k).is_some_and(|x| text_eq(x, vraw)) { return false; }
+                            }
+                            true
+                        };
+
+                        // Pre-collect candidate left and right node ids
+                        let mut left_ids: Vec<Uuid> = Vec::new();
+                        let mut right_ids: Vec<Uuid> = Vec::new();
+                        for (nid, n) in &db.nodes { if node_ok(n, left) { left_ids.push(*nid); } }
+                        for (nid, n) in &db.nodes { if node_ok(n, right) { right_ids.push(*nid); } }
+
+                        // Build adjacency (relationship id, neighbour) filtered by type and direction
+                        let mut adj_fwd: HashMap<Uuid, Vec<(Uuid, Uuid)>> = HashMap::new();
+                        let mut adj_back: HashMap<Uuid, Vec<(Uuid, Uuid)>> = HashMap::new();
+                        for (rid, r) in &db.relationships {
+                            if let Some(t) = &rel.typ { if !text_eq(&r.label, t) { continue; } }
+                            adj_fwd.entry(r.from_node).or_default().push((*rid, r.to_node));
+                            // Self-loops are already in the forward list; walking them "back" would repeat them
+                            if r.from_node != r.to_node { adj_back.entry(r.to_node).or_default().push((*rid, r.from_node)); }
+                        }
+
+                        // For each partial row, expand combinations
+                        for part in &partials {
+                            for &lid in &left_ids {
+                                // Check existing binding consistency for left.var
+                                if let Some(v) = &left.var { if let Some(prev) = part.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == lid) { continue; } } }
+
+                                // BFS limited by hop bounds; each queue entry carries the path walked so far
+                                use std::collections::{VecDeque, HashSet};
+                                let mut qd: VecDeque<(Vec<Uuid>, Vec<Uuid>)> = VecDeque::new();
+                                let mut seen: HashSet<Uuid> = HashSet::new();
+                                qd.push_back((vec![lid], Vec::new()));
+                                seen.insert(lid);
+
+                                while let Some((nodes, rels)) = qd.pop_front() {
+                                    let cur = *nodes.last().unwrap_or(&lid);
+                                    let d = rels.len();
+                                    if d >= min_hops && d <= max_hops && right_ids.contains(&cur) {
+                                        let mut m = part.clone();
+                                        if let Some(v) = &left.var { m.insert(v.clone(), Val::NodeId(lid)); }
+                                        if let Some(v) = &right.var { m.insert(v.clone(), Val::NodeId(cur)); }
+                                        if let Some(pv) = path { m.insert(pv.clone(), Val::Path { nodes: nodes.clone(), rels: rels.clone() }); }
+                                        new_partials.push(m);
+                                    }
+                                    if d == max_hops { continue; }
+                                    // advance; undirected patterns follow relationships both ways
+                                    let fwd = adj_fwd.get(&cur).map(|v| v.as_slice()).unwrap_or(&[]);
+                                    let back = if rel.right { &[][..] } else { adj_back.get(&cur).map(|v| v.as_slice()).unwrap_or(&[]) };
+                                    for &(rid, nx) in fwd.iter().chain(back) {
+                                        // Nodes are visited once, but a self-loop may be walked (once) in place
+                                        let self_loop = nx == cur && !rels.contains(&rid);
+                                        if self_loop || seen.insert(nx) {
+                                            let mut nn = nodes.clone();
+                                            nn.push(nx);
+                                            let mut nr = rels.clone();
+                                            nr.push(rid);
+                                            qd.push_back((nn, nr));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    } else {
+                        // enumerate triples (a)-[r:TYPE]->(b) or undirected (a)-[r]-(b)
+                        for (_rid, r) in &db.relationships {
+                            if let Some(t) = &rel.typ { if !text_eq(&r.label, t) { continue; } }
+                            // relationship properties exact match (with param resolution)
+                            let mut ok_rel_props = true;
+                            for (k, vraw) in &rel.props {
+                                let v = resolve_param(vraw, params)?;
+                                if !r.metadata.get(k).is_some_and(|x| text_eq(x, &v)) { ok_rel_props = false; break; }
+                            }
+                            if !ok_rel_props { continue; }
+                            let (Some(from), Some(to)) = (
+                                db.nodes.get(&r.from_node),
+                                db.nodes.get(&r.to_node),
+                            ) else { continue; };
+
+                            // Helper to try match given (L,R) node order
+                            let try_match = |left_np: &NodePattern, right_np: &NodePattern, a: &Node, b: &Node| -> bool {
+                                if let Some(lab) = &left_np.label { if !text_eq(&a.label, lab) { return false; } }
+                                for (k, vraw) in &left_np.props { if !a.metadata.get(k).is_some_and(|x| text_eq(x, vraw)) { return false; } }
+                                if let Some(lab) = &right_np.label { if !text_eq(&b.label, lab) { return false; } }
+                                for (k, vraw) in &right_np.props { if !b.metadata.get(k).is_some_and(|x| text_eq(x, vraw)) { return false; } }
+                                true
+                            };
+
+                            // directed pattern: only from->to
+                            if rel.right {
+                                if !try_match(left, right, from, to) { continue; }
+                                for part in &partials {
+                                    let mut m = part.clone();
+                                    if let Some(v) = &left.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == from.id) { continue; } } m.insert(v.clone(), Val::NodeId(from.id)); }
+                                    if let Some(rv) = &rel.var { if let Some(prev) = m.get(rv) { if !matches!(prev, Val::RelId(pid) if *pid == r.id) { continue; } } m.insert(rv.clone(), Val::RelId(r.id)); }
+                                    if let Some(v) = &right.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == to.id) { continue; } } m.insert(v.clone(), Val::NodeId(to.id)); }
+                                    if let Some(pv) = path { m.insert(pv.clone(), Val::Path { nodes: vec![from.id, to.id], rels: vec![r.id] }); }
+                                    new_partials.push(m);
+                                }
+                            } else {
+                                // undirected: try from->to mapping
+                                if try_match(left, right, from, to) {
+                                    for part in &partials {
+                                        let mut m = part.clone();
+                                        if let Some(v) = &left.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == from.id) { continue; } } m.insert(v.clone(), Val::NodeId(from.id)); }
+                                        if let Some(rv) = &rel.var { if let Some(prev) = m.get(rv) { if !matches!(prev, Val::RelId(pid) if *pid == r.id) { continue; } } m.insert(rv.clone(), Val::RelId(r.id)); }
+                                        if let Some(v) = &right.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == to.id) { continue; } } m.insert(v.clone(), Val::NodeId(to.id)); }
+                                        if let Some(pv) = path { m.insert(pv.clone(), Val::Path { nodes: vec![from.id, to.id], rels: vec![r.id] }); }
+                                        new_partials.push(m);
+                                    }
+                                }
+                                // also try swapped mapping to support -(r)- patterns; a self-loop
+                                // reads the same both ways and must match only once
+                                if r.from_node != r.to_node && try_match(left, right, to, from) {
+                                    for part in &partials {
+                                        let mut m = part.clone();
+                                        if let Some(v) = &left.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == to.id) { continue; } } m.insert(v.clone(), Val::NodeId(to.id)); }
+                                        if let Some(rv) = &rel.var { if let Some(prev) = m.get(rv) { if !matches!(prev, Val::RelId(pid) if *pid == r.id) { continue; } } m.insert(rv.clone(), Val::RelId(r.id)); }
+                                        if let Some(v) = &right.var { if let Some(prev) = m.get(v) { if !matches!(prev, Val::NodeId(pid) if *pid == from.id) { continue; } } m.insert(v.clone(), Val::NodeId(from.id)); }
+                                        if let Some(pv) = path { m.insert(pv.clone(), Val::Path { nodes: vec![to.id, from.id], rels: vec![r.id] }); }
+                                        new_partials.push(m);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            partials = new_partials;
+        }
+        Ok(partials)
+    }
+
+    let mut clauses = clauses.into_iter().peekable();
+    while let Some(cl) = clauses.next() {
+        match cl {
+            Clause::Match { optional, patterns } => {
+                // A WHERE directly after OPTIONAL MATCH filters the optional pattern, not the row
+                let optional_where = match clauses.peek() {
+                    Some(Clause::Where(_)) if optional => match clauses.next() { Some(Clause::Where(w)) => Some(w), _ => None },
+                    _ => None,
+                };
+                let mut next_rows: Vec<HashMap<String, Val>> = Vec::new();
+                for row in &rows {
+                    let mut partials = match_row(db, &patterns, row, params)?;
+                    if optional {
+                        if let Some(w) = &optional_where { partials = filter_where(db, w, &partials, params)?; }
+                        if partials.is_empty() {
+                            // Keep the row, binding the pattern's new variables to null
+                            let mut m = row.clone();
+                            for v in pattern_vars(&patterns) { m.entry(v).or_insert(Val::Null); }
+                            partials.push(m);
+                        }
+                    }
+                    next_rows.extend(partials);
+                }
+                rows = next_rows;
+            }
+            Clause::Where(w) => {
+                rows = filter_where(db, &w, &rows, params)?;
+            }
+            Clause::With { items, distinct, order_by, skip, limit } => {
+                // Project rows to the listed variables and aliases, then apply DISTINCT/ORDER BY/SKIP/LIMIT
+                let mut keyed_rows: Vec<(Vec<String>, HashMap<String, Val>)> = Vec::new();
+                let mut seen: std::collections::HashSet<Vec<(String, String)>> = std::collections::HashSet::new();
+                for r in &rows {
+                    let mut proj: HashMap<String, Val> = HashMap::new();
+                    for it in &items {
+                        match it {
+                            Expr::Var(v) => { if let Some(val) = r.get(v) { proj.insert(v.clone(), val.clone()); } }
+                            Expr::Alias(inner, name) => { if let Some(val) = eval_val(db, inner, r, params)? { proj.insert(name.clone(), val); } }
+                            _ => {}
+                        }
+                    }
+                    if distinct {
+                        let mut sig: Vec<(String, String)> = proj.iter().map(|(k, v)| (k.clone(), match v {
+                            Val::NodeId(id) | Val::RelId(id) => id.to_string(),
+                            Val::Scalar(s) => s.clone(),
+                            Val::Path { nodes, rels } => format!("{:?}{:?}", nodes, rels),
+                            Val::Null => "null".to_string(),
+                        })).collect();
+                        sig.sort();
+                        if !seen.insert(sig) { continue; }
+                    }
+                    // ORDER BY may refer to incoming variables as well as the new aliases
+                    let mut scope = r.clone();
+                    scope.extend(proj.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    let key_vals: Vec<String> = order_by.iter().map(|(expr, _asc)| sort_key(db, expr, &scope, params)).collect::<Result<_>>()?;
+                    keyed_rows.push((key_vals, proj));
+                }
+                // Sort if requested
+                if !order_by.is_empty() {
+                    keyed_rows.sort_by(|a, b| {
+                        let ka = &a.0; let kb = &b.0;
+                        let mut ord = std::cmp::Ordering::Equal;
+                        let len = ka.len().min(kb.len()).min(order_by.len());
+                        for i in 0..len {
+                            let asc = order_by[i].1;
+                            // numeric compare first
+                            let (na, nb) = (ka[i].parse::<f64>().ok(), kb[i].parse::<f64>().ok());
+                            ord = match (na, nb) {
+                                (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+                                _ => text_cmp(&ka[i], &kb[i]),
+                            };
+                            if !asc { ord = ord.reverse(); }
+                            if ord != std::cmp::Ordering::Equal { break; }
+                        }
+                        ord
+                    });
+                }
+                // Apply SKIP/LIMIT
+                let mut start = skip.unwrap_or(0);
+                let mut remaining = limit.unwrap_or(usize::MAX);
+                let mut new_rows: Vec<HashMap<String, Val>> = Vec::new();
+                for (_keys, proj) in keyed_rows.into_iter() {
+                    if start > 0 { start -= 1; continue; }
+                    if remaining == 0 { break; }
+                    new_rows.push(proj);
+                    remaining = remaining.saturating_sub(1);
+                }
+                rows = new_rows;
+            }
+            Clause::Unwind { expr, var } => {
+                let mut next_rows: Vec<HashMap<String, Val>> = Vec::new();
+                for row in &rows {
+                    // A variable bound earlier (e.g. WITH $names AS names) holds the list as text
+                    let list = match row.get(expr.trim()) {
+                        Some(Val::Scalar(s)) => parse_list_value(s)?,
+                        _ => eval_list_expr(&expr, params)?,
+                    };
+                    for item in list {
+                        let mut m = row.clone();
+                        m.insert(var.clone(), Val::Scalar(item));
+                        next_rows.push(m);
+                    }
+                }
+                rows = next_rows;
+            }
+            Clause::Delete { vars, detach } => {
+                use std::collections::HashSet;
+                let mut rel_to_del: HashSet<Uuid> = HashSet::new();
+                let mut nodes_to_del: HashSet<Uuid> = HashSet::new();
+                for row in &rows {
+                    for v in &vars {
+                        if let Some(val) = row.get(v) {
+                            match val {
+                                Val::RelId(rid) => { rel_to_del.insert(*rid); }
+                                Val::NodeId(nid) => {
+                                    if detach {
+                                        nodes_to_del.insert(*nid);
+                                    } else {
+                                        // Only allow delete if node has no relationships
+                                        let has_incident = db.relationships.values().any(|r| r.from_node == *nid || r.to_node == *nid);
+                                        if has_incident {
+                                            return Err(anyhow!("Cannot DELETE node {} with existing relationships; use DETACH DELETE", nid));
+                                        }
+                                        nodes_to_del.insert(*nid);
+                                    }
+                                }
+                                // Deleting a path removes its relationships and nodes
+                                Val::Path { nodes, rels } => {
+                                    rel_to_del.extend(rels.iter().copied());
+                                    for nid in nodes {
+                                        if !detach && db.relationships.values().any(|r| (r.from_node == *nid || r.to_node == *nid) && !rels.contains(&r.id)) {
+                                            return Err(anyhow!("Cannot DELETE node {} with existing relationships; use DETACH DELETE", nid));
+                                        }
+                                        nodes_to_del.insert(*nid);
+                                    }
+                                }
+                                Val::Scalar(_) | Val::Null => {}
+                            }
+                        }
+                    }
+                }
+                // Delete relationships first
+                for rid in rel_to_del { let _ = db.remove_relationship(rid); }
+                // Then delete nodes
+                for nid in nodes_to_del { let _ = db.remove_node(nid); }
+            }
+            Clause::Set { items } => {
+                // Supported minimal forms:
+                // - SET n.prop = <literal>
+                // - SET r.prop = <literal>
+                // - SET n:NewLabel (node) or r:NewType (relationship)
+                // - SET n = {k: v, ...} replaces all properties, SET n += {...} merges them;
+                //   the map may also be a JSON-object $param or another node/relationship
+                // Literals: quoted strings or bare numbers (stored as string)
+                for row in &rows {
+                    for raw in &items {
+                        let s = raw.trim();
+                        if s.is_empty() { continue; }
+                        if let Some((var, merge, rhs)) = split_map_assignment(s) {
+                            let entries = map_entries(db, rhs, row, params)?;
+                            let current = match row.get(var) {
+                                Some(Val::NodeId(nid)) => db.get_node(*nid).map(|n| n.metadata.clone()),
+                                Some(Val::RelId(rid)) => db.get_relationship(*rid).map(|r| r.metadata.clone()),
+                                _ => None,
+                            };
+                            let Some(current) = current else { continue };
+                            let mut meta = if merge { current } else { HashMap::new() };
+                            for (k, v) in entries {
+                                match v {
+                                    Some(v) => { meta.insert(k, v); }
+                                    None => { meta.remove(&k); }
+                                }
+                            }
+                            match row.get(var) {
+                                Some(Val::NodeId(nid)) => { let _ = db.set_node_metadata(*nid, meta); }
+                                Some(Val::RelId(rid)) => { let _ = db.set_relationship_metadata(*rid, meta); }
+                                _ => {}
+                            }
+                            continue;
+                        }
+                        // Label change? var:Label (a ':' after '=' belongs to the value, e.g. a time)
+                        if let Some(col) = s.find(':')
+                            && s.find('=').is_none_or(|eq| col < eq)
+                        {
+                            let (var, lbl) = s.split_at(col);
+                            let var = var.trim();
+                            let label = lbl[1..].trim();
+                            if label.is_empty() { continue; }
+                            if let Some(val) = row.get(var) {
+                                match val {
+                                    Val::NodeId(nid) => { let _ = db.update_node_label(*nid, label.to_string()); }
+                                    Val::RelId(rid) => { let _ = db.update_relationship_label(*rid, label.to_string()); }
+                                    Val::Scalar(_) | Val::Path { .. } | Val::Null => {}
+                                }
+                            }
+                            continue;
+                        }
+                        // Property set: var.prop = value
+                        if let Some(eq) = s.find('=') {
+                            let (lhs, rhs) = s.split_at(eq);
+                            let rhs = rhs[1..].trim();
+                            let (var, prop) = if let Some(dot) = lhs.find('.') {
+                                (lhs[..dot].trim(), lhs[dot+1..].trim())
+                            } else { (lhs.trim(), "") };
+                            if prop.is_empty() { continue; }
+                            // parse literal value (or a scalar bound by UNWIND / WITH ... AS)
+                            let value = if (rhs.starts_with('"') && rhs.ends_with('"')) || (rhs.starts_with('\'') && rhs.ends_with('\'')) {
+                                trim_quotes(rhs)
+                            } else if let Some(Val::Scalar(sv)) = row.get(rhs) {
+                                sv.clone()
+                            } else if (rhs.contains('(') || find_additive_op(rhs).is_some())
+                                && let Ok(e @ (Expr::Func(..) | Expr::BinOp(..))) = parse_expr(rhs)
+                            {
+                                eval_val(db, &e, row, params)?.and_then(scalar_of).unwrap_or_default()
+                            } else {
+                                rhs.to_string()
+                            };
+                            if let Some(val) = row.get(var) {
+                                match val {
+                                    Val::NodeId(nid) => { let _ = db.upsert_node_metadata(*nid, prop.to_string(), value.clone()); }
+                                    Val::RelId(rid) => { let _ = db.upsert_relationship_metadata(*rid, prop.to_string(), value.clone()); }
+                                    Val::Scalar(_) | Val::Path { .. } | Val::Null => {}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Clause::Remove { items } => {
+                // Supported minimal forms:
+                // - REMOVE n.prop
+                // - REMOVE r.prop
+                for row in &rows {
+                    for raw in &items {
+                        let s = raw.trim();
+                        if s.is_empty() { continue; }
+                        if let Some(dot) = s.find('.') {
+                            let var = s[..dot].trim();
+                            let prop = s[dot+1..].trim();
+                            if let Some(val) = row.get(var) {
+                                match val {
+                                    Val::NodeId(nid) => { let _ = db.remove_node_metadata_key(*nid, prop); }
+                                    Val::RelId(rid) => { let _ = db.remove_relationship_metadata_key(*rid, prop); }
+                                    Val::Scalar(_) | Val::Path { .. } | Val::Null => {}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Clause::Create { patterns } => {
+                for p in patterns {
+                    match p {
+                        Pattern::Node(np) => {
+                            // One node per incoming row, so UNWIND ... CREATE creates one per list item
+                            let label = np.label.unwrap_or_else(|| "_".to_string());
+                            for row in rows.iter_mut() {
+                                let mut meta = HashMap::new();
+                                for (k, vraw) in &np.props { meta.insert(k.clone(), row_value(db, vraw, row, params)?); }
+                                let id = db.add_node(label.clone(), meta);
+                                if let Some(v) = &np.var { row.insert(v.clone(), Val::NodeId(id)); }
+                            }
+                        }
+                        Pattern::Path { left, rel, right, path } => {
+                            // Require left/right var bound in current rows
+                            let mut new_rows = Vec::new();
+                            for row in &rows {
+                                let from_id = match &left.var { Some(v) => match row.get(v) { Some(Val::NodeId(id)) => *id, _ => continue }, None => continue };
+                                let to_id = match &right.var { Some(v) => match row.get(v) { Some(Val::NodeId(id)) => *id, _ => continue }, None => continue };
+                                let typ = rel.typ.clone().unwrap_or_else(|| "_".to_string());
+                                let mut meta = HashMap::new();
+                                for (k, vraw) in &rel.props { meta.insert(k.clone(), row_value(db, vraw, row, params)?); }
+                                if let Some(rid) = db.add_relationship(from_id, to_id, typ.clone(), meta) {
+                                    let mut m = row.clone();
+                                    if let Some(rv) = &rel.var { m.insert(rv.clone(), Val::RelId(rid)); }
+                                    if let Some(pv) = &path { m.insert(pv.clone(), Val::Path { nodes: vec![from_id, to_id], rels: vec![rid] }); }
+                                    new_rows.push(m);
+                                }
+                            }
+                            if !new_rows.is_empty() { rows = new_rows; }
+                        }
+                    }
+                }
+            }
+            Clause::Merge { pattern } => {
+                // only support relationship merge between bound vars
+                if let Pattern::Path { left, rel, right, path } = pattern {
+                    let mut new_rows = Vec::new();
+                    for row in &rows {
+                        let from_id = match &left.var { Some(v) => match row.get(v) { Some(Val::NodeId(id)) => *id, _ => continue }, None => continue };
+                        let to_id = match &right.var { Some(v) => match row.get(v) { Some(Val::NodeId(id)) => *id, _ => continue }, None => continue };
+                        let typ = rel.typ.clone().unwrap_or_else(|| "_".to_string());
+                        // check exists
+                        let mut rid_opt = None;
+                        for r in db.relationships.values() {
+                            if r.from_node == from_id && r.to_node == to_id && text_eq(&r.label, &typ) {
+                                // if MERGE specified properties, ensure all match
+                                let mut all_match = true;
+                                for (k, vraw) in &rel.props { let v = resolve_param(vraw, params)?; if !r.metadata.get(k).is_some_and(|x| text_eq(x, &v)) { all_match = false; break; } }
+                                if all_match { rid_opt = Some(r.id); break; }
+                            }
+                        }
+                        let rid = if let Some(rid) = rid_opt { rid } else {
+                            let mut meta = HashMap::new();
+                            for (k, vraw) in &rel.props { meta.insert(k.clone(), resolve_param(vraw, params)?); }
+                            match db.add_relationship(from_id, to_id, typ.clone(), meta) {
+                                Some(r) => r,
+                                None => {
+                                    // If either endpoint is missing (unexpected), skip creating this rel to avoid panic.
+                                    continue;
+                                }
+                            }
+                        };
+                        let mut m = row.clone();
+                        if let Some(rv) = &rel.var { m.insert(rv.clone(), Val::RelId(rid)); }
+                        if let Some(pv) = &path { m.insert(pv.clone(), Val::Path { nodes: vec![from_id, to_id], rels: vec![rid] }); }
+                        new_rows.push(m);
+                    }
+                    rows = new_rows;
+                } else {
+                    return Err(anyhow!("MERGE currently supports only single relationship patterns"));
+                }
+            }
+            Clause::Return { items, distinct, order_by, skip, limit } => {
+                // Evaluate per-row projections first into a vector of tuples (keys for sorting, projected rows)
+                // Minimal semantics: if multiple items, we still flatten as before but sort only when a single item is returned.
+                let single_item = items.len() == 1;
+                let mut projected: Vec<(Option<Vec<String>>, Vec<QueryResultRow>)> = Vec::new();
+                for r in &rows {
+                    let mut out_rows: Vec<QueryResultRow> = Vec::new();
+                    for it in &items {
+                        let it = if let Expr::Alias(inner, _) = it { &**inner } else { it };
+                        match it {
+                            Expr::Var(v) => {
+                                if let Some(Val::Scalar(s)) = r.get(v) {
+                                    out_rows.push(QueryResultRow::Info(s.clone()));
+                                } else if let Some(Val::Null) = r.get(v) {
+                                    out_rows.push(QueryResultRow::Info("null".to_string()));
+                                } else if let Some(Val::NodeId(id)) = r.get(v) {
+                                    if let Some(n) = get_node(db, id) {
+                                        out_rows.push(QueryResultRow::Node { id: n.id, label: n.label, metadata: n.metadata });
+                                    }
+                                } else if let Some(Val::RelId(id)) = r.get(v) {
+                                    if let Some(rel) = get_rel(db, id) {
+                                        out_rows.push(QueryResultRow::Relationship { id: rel.id, from: rel.from_node, to: rel.to_node, label: rel.label, metadata: rel.metadata });
+                                    }
+                                } else if let Some(Val::Path { nodes, rels }) = r.get(v) {
+                                    out_rows.push(QueryResultRow::Path { nodes: nodes.clone(), relationships: rels.clone() });
+                                }
+                            }
+                            // nodes(p) / relationships(p) expand to the path's elements
+                            Expr::Func(name, args) if matches!(name.as_str(), "nodes" | "relationships")
+                                && matches!(args.first(), Some(Expr::Var(v)) if matches!(r.get(v), Some(Val::Path { .. }))) =>
+                            {
+                                let Some(Expr::Var(v)) = args.first() else { continue };
+                                let Some(Val::Path { nodes, rels }) = r.get(v) else { continue };
+                                if name == "nodes" {
+                                    for id in nodes {
+                                        if let Some(n) = get_node(db, id) { out_rows.push(QueryResultRow::Node { id: n.id, label: n.label, metadata: n.metadata }); }
+                                    }
+                                } else {
+                                    for id in rels {
+                                        if let Some(rel) = get_rel(db, id) { out_rows.push(QueryResultRow::Relationship { id: rel.id, from: rel.from_node, to: rel.to_node, label: rel.label, metadata: rel.metadata }); }
+                                    }
+                                }
+                            }
+                            Expr::Prop(expr, key) => {
+                                if let Expr::Var(v) = &**expr {
+                                    if let Some(Val::Null) = r.get(v) {
+                                        out_rows.push(QueryResultRow::Info("null".to_string()));
+                                    } else if let Some(Val::NodeId(id)) = r.get(v) {
+                                        if let Some(n) = get_node(db, id) {
+                                            if let Some(val) = n.metadata.get(key) {
+                                                out_rows.push(QueryResultRow::Info(val.clone()));
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            Expr::FuncId(v) => {
+                                if let Some(Val::NodeId(id)) = r.get(v) { out_rows.push(QueryResultRow::Info(id.to_string())); }
+                                else if let Some(Val::RelId(id)) = r.get(v) { out_rows.push(QueryResultRow::Info(id.to_string())); }
+                            }
+                            Expr::Str(s) => out_rows.push(QueryResultRow::Info(s.clone())),
+                            Expr::Param(p) => { if let Some(v) = params.get(p) { out_rows.push(QueryResultRow::Info(v.clone())); } }
+                            Expr::Func(..) | Expr::Case { .. } | Expr::BinOp(..) | Expr::Null => {
+                                let v = eval_val(db, it, r, params)?.and_then(scalar_of);
+                                out_rows.push(QueryResultRow::Info(v.unwrap_or_else(|| "null".to_string())));
+                            }
+                            Expr::Alias(..) => {}
+                        }
+                    }
+                    // Build sort keys (as strings) if needed and only for single-item
+                    let keys = if single_item && (!order_by.is_empty()) {
+                        Some(order_by.iter().map(|(expr, _asc)| sort_key(db, expr, r, params)).collect::<Result<Vec<String>>>()?)
+                    } else { None };
+                    projected.push((keys, out_rows));
+                }
+                // DISTINCT (single-item only for now): deduplicate by the single projected value
+                if distinct && single_item {
+                    use std::collections::HashSet;
+                    let mut seen: HashSet<String> = HashSet::new();
+                    let mut deduped: Vec<(Option<Vec<String>>, Vec<QueryResultRow>)> = Vec::new();
+                    for (keys, outs) in projected.into_iter() {
+                        if outs.is_empty() { continue; }
+                        let k = outs.iter().map(|o| match o {
+                            QueryResultRow::Node { id, .. } => id.to_string(),
+                            QueryResultRow::Relationship { id, .. } => id.to_string(),
+                            QueryResultRow::Path { nodes, relationships } => format!("{:?}{:?}", nodes, relationships),
+                            QueryResultRow::Info(s) => s.clone(),
+                        }).collect::<Vec<_>>().join(",");
+                        if seen.insert(k) {
+                            deduped.push((keys, outs));
+                        }
+                    }
+                    projected = deduped;
+                }
+                // Flatten now or after sorting when applicable
+                if single_item && !order_by.is_empty() {
+                    projected.sort_by(|a, b| {
+                        let ka = a.0.as_ref().map(|v| v.as_slice()).unwrap_or(&[]);
+                        let kb = b.0.as_ref().map(|v| v.as_slice()).unwrap_or(&[]);
+                        let mut ord = std::cmp::Ordering::Equal;
+                        let len = ka.len().min(kb.len()).min(order_by.len());
+                        for i in 0..len {
+                            let asc = order_by[i].1;
+                            // try numeric compare first
+                            let (na, nb) = (ka[i].parse::<f64>().ok(), kb[i].parse::<f64>().ok());
+                            ord = match (na, nb) {
+                                (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+                                _ => text_cmp(&ka[i], &kb[i]),
+                            };
+                            if !asc { ord = ord.reverse(); }
+                            if ord != std::cmp::Ordering::Equal { break; }
+                        }
+                        ord
+                    });
+                }
+                // Apply SKIP/LIMIT (row-wise; each entry corresponds to one RETURNed row when single item)
+                let mut flat: Vec<QueryResultRow> = Vec::new();
+                if single_item {
+                    let mut start = skip.unwrap_or(0);
+                    let mut remaining = limit.unwrap_or(usize::MAX);
+                    for (_k, rows_for_item) in projected.into_iter() {
+                        if rows_for_item.is_empty() { continue; }
+                        if start > 0 { start -= 1; continue; }
+                        if remaining == 0 { break; }
+                        // Usually one value; nodes(p) / relationships(p) expand to several
+                        flat.extend(rows_for_item);
+                        remaining = remaining.saturating_sub(1);
+                    }
+                } else {
+                    // No ordering or pagination supported in multi-item mode; flatten directly
+                    for (_k, rows_for_item) in projected.into_iter() { for rr in rows_for_item { flat.push(rr); } }
+                }
+                return Ok(flat);
+            }
+        }
+    }
+
+    // default: return all bound nodes as rows
+    let mut out = Vec::new();
+    for r in rows {
+        for (_k, v) in r {
+            match v { Val::NodeId(id) => {
+                if let Some(n) = db.get_node(id).cloned() { out.push(QueryResultRow::Node { id: n.id, label: n.label, metadata: n.metadata }); }
+            }, Val::RelId(id) => {
+                if let Some(rel) = db.get_relationship(id).cloned() { out.push(QueryResultRow::Relationship { id: rel.id, from: rel.from_node, to: rel.to_node, label: rel.label, metadata: rel.metadata }); }
+            }, Val::Scalar(_) | Val::Path { .. } | Val::Null => {} }
+        }
+    }
+    Ok(out)
+}
+
+pub fn execute_cypher(db: &mut GraphDatabase, query: &str) -> Result<Vec<QueryResultRow>> {
+    let empty: HashMap<String, String> = HashMap::new();
+    execute_cypher_with_params(db, query, &empty)
+}
@@ -0,0 +1,7 @@
+pub mod query_interface;
+pub mod cypher_spec;
+pub mod procedures;
+pub mod temporal;
+pub mod collation;
+pub mod reference;
+pub mod views;
@@ -0,0 +1,438 @@
+//! `CALL` procedure registry. Procedures are looked up by their dotted name
+//! (`db.labels`, `algo.pageRank`, ...) and are reachable from every entry point that
+//! runs queries: the GUI console, the HTTP API and gRPC.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+use crate::graph_utils::changes::Since;
+use crate::graph_utils::computed::{self, ComputedProperty};
+use crate::graph_utils::derived::{self, DerivedEdgeRule};
+use super::views::{self, GraphView};
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+use crate::graph_utils::schema::Schema;
+use crate::graph_utils::validation::PropertyRule;
+use super::query_interface::QueryResultRow;
+
+/// Signature shared by all procedures: the graph and the already-resolved arguments.
+pub type ProcedureFn = fn(&mut GraphDatabase, &[String]) -> Result<Vec<QueryResultRow>>;
+
+#[derive(Clone)]
+pub struct Procedure {
+    pub name: String,
+    pub signature: &'static str,
+    pub description: &'static str,
+    // Whether the procedure can change the graph (marks the outcome as mutated)
+    pub writes: bool,
+    run: ProcedureFn,
+}
+
+// Keyed by lowercase name so CALL is case-insensitive like the rest of the language
+static REGISTRY: Lazy<RwLock<BTreeMap<String, Procedure>>> = Lazy::new(|| {
+    let reg = RwLock::new(BTreeMap::new());
+    {
+        let mut map = reg.write().unwrap_or_else(|e| e.into_inner());
+        for p in builtins() { map.insert(p.name.to_ascii_lowercase(), p); }
+    }
+    reg
+});
+
+fn builtins() -> Vec<Procedure> {
+    let p = |name: &str, signature, description, run| Procedure { name: name.to_string(), signature, description, writes: false, run };
+    vec![
+        p("db.labels", "db.labels()", "Distinct node labels", db_labels),
+        p("db.relationshipTypes", "db.relationshipTypes()", "Distinct relationship labels", db_relationship_types),
+        p("db.propertyKeys", "db.propertyKeys()", "Distinct metadata keys on nodes and relationships", db_property_keys),
+        p("db.schema", "db.schema()", "Node labels with counts and keys, and the label patterns relationships connect", db_schema),
+        p("db.info", "db.info()", "Graph metadata (title, description, owner, custom keys) and element counts", db_info),
+        p("db.planCache", "db.planCache()", "Entries in the parsed-query cache and how many queries it served or had to parse", db_plan_cache),
+        p("db.changes", "db.changes([since])", "Logged writes after a sequence number or since an ISO-8601 time, one JSON event per row", db_changes),
+        p("db.constraints", "db.constraints()", "Relationship labels limited to one edge per node pair and direction", db_constraints),
+        Procedure {
+            writes: true,
+            ..p("db.constraints.setUnique", "db.constraints.setUnique(type, [unique])", "Turn the uniqueness constraint for a relationship label on (default) or off", db_constraints_set_unique)
+        },
+        p("db.constraints.audit", "db.constraints.audit()", "Existing duplicate edges for unique relationship labels", db_constraints_audit),
+        p("db.constraints.properties", "db.constraints.properties()", "Rules on node metadata per label", db_constraints_properties),
+        Procedure {
+            writes: true,
+            ..p("db.constraints.setProperty", "db.constraints.setProperty(label, key, [rule])", "Set the rule for a metadata key of a node label, e.g. 'required, in=a|b, min=0, max=9, regex=...'; without a rule it is removed", db_constraints_set_property)
+        },
+        p("db.constraints.validate", "db.constraints.validate()", "Nodes whose metadata breaks a property rule", db_constraints_validate),
+        p("db.computed", "db.computed()", "Node metadata keys computed from formulas per label", db_computed),
+        Procedure {
+            writes: true,
+            ..p("db.computed.set", "db.computed.set(label, key, [formula], [everySeconds])", "Compute a metadata key of a node label from a formula, e.g. 'pagerank * 100' or 'price * quantity'; without a formula it is no longer computed", db_computed_set)
+        },
+        p("db.derived", "db.derived()", "Rules deriving relationships between nodes with equal metadata values", db_derived),
+        Procedure {
+            writes: true,
+            ..p("db.derived.set", "db.derived.set(type, [key], [nodeLabel], [write])", "Connect nodes sharing a value of key with type edges, written into the graph when write is true or only drawn; without a key the rule and its edges are removed", db_derived_set)
+        },
+        Procedure {
+            writes: true,
+            ..p("db.derived.refresh", "db.derived.refresh()", "Rewrite the edges of the derived edge rules that write them", db_derived_refresh)
+        },
+        p("db.views", "db.views()", "Named views: saved read-only queries shown and exported as graphs of their own", db_views),
+        Procedure {
+            writes: true,
+            ..p("db.views.set", "db.views.set(name, [query], [aggregateKey])", "Save a read-only query as a named view, optionally rolled up into one node per value of a key; without a query the view is removed", db_views_set)
+        },
+        p("db.views.show", "db.views.show(name)", "Nodes and relationships of a named view as of now", db_views_show),
+        p("dbms.procedures", "dbms.procedures()", "List the registered procedures", dbms_procedures),
+        p("algo.degree", "algo.degree()", "Degree (in + out) of every node, highest first", algo_degree),
+        p("algo.pageRank", "algo.pageRank([iterations], [damping])", "PageRank score of every node, highest first", algo_page_rank),
+        p("algo.connectedComponents", "algo.connectedComponents()", "Weakly connected component index of every node", algo_connected_components),
+        p("algo.shortestPath", "algo.shortestPath(fromId, toId, [type])", "Shortest directed path between two nodes", algo_shortest_path),
+    ]
+}
+
+/// Add or replace a procedure. Names are matched case-insensitively.
+#[allow(dead_code)]
+pub fn register(name: &str, signature: &'static str, description: &'static str, writes: bool, run: ProcedureFn) {
+    let mut map = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    map.insert(name.to_ascii_lowercase(), Procedure { name: name.to_string(), signature, description, writes, run });
+}
+
+/// All registered procedures, sorted by name.
+pub fn list() -> Vec<Procedure> {
+    REGISTRY.read().map(|m| m.values().cloned().collect()).unwrap_or_default()
+}
+
+fn parse_arg(raw: &str, params: &HashMap<String, String>) -> Result<String> {
+    let t = raw.trim();
+    if let Some(name) = t.strip_prefix('$') {
+        return params.get(name).cloned().ok_or_else(|| anyhow!("missing parameter: ${}", name));
+    }
+    if t.len() >= 2 && ((t.starts_with('"') && t.ends_with('"')) || (t.starts_with('\'') && t.ends_with('\''))) {
+        return Ok(t[1..t.len() - 1].to_string());
+    }
+    Ok(t.to_string())
+}
+
+/// Run `CALL name(args...)`. Returns the rows and whether the procedure writes to the graph.
+pub fn call(db: &mut GraphDatabase, stmt: &str, params: &HashMap<String, String>) -> Result<(Vec<QueryResultRow>, bool)> {
+    let body = stmt.trim().trim_end_matches(';').trim();
+    let body = body.get(..5).filter(|h| h.eq_ignore_ascii_case("CALL ")).map(|_| body[5..].trim()).ok_or_else(|| anyhow!("expected CALL"))?;
+    let (name, args) = match body.find('(') {
+        Some(open) => {
+            let close = body.rfind(')').ok_or_else(|| anyhow!("missing ')' in CALL {}", body))?;
+            if close < open { return Err(anyhow!("missing ')' in CALL {}", body)); }
+            let rest = body[close + 1..].trim();
+            if !rest.is_empty() { return Err(anyhow!("unexpected text after procedure call: {}", rest)); }
+            (body[..open].trim(), &body[open + 1..close])
+        }
+        None => (body, ""),
+    };
+    let proc = REGISTRY
+        .read()
+        .ok()
+        .and_then(|m| m.get(&name.to_ascii_lowercase()).cloned())
+        .ok_or_else(|| anyhow!("unknown procedure: {} (CALL dbms.procedures() lists them)", name))?;
+    let args = if args.trim().is_empty() {
+        Vec::new()
+    } else {
+        split_args(args).iter().map(|a| parse_arg(a, params)).collect::<Result<Vec<String>>>()?
+    };
+    let rows = (proc.run)(db, &args)?;
+    Ok((rows, proc.writes))
+}
+
+// Comma split that respects quotes
+fn split_args(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut quote: Option<char> = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => { quote = Some(c); cur.push(c); }
+            (Some(q), _) if c == q => { quote = None; cur.push(c); }
+            (None, ',') => out.push(std::mem::take(&mut cur)),
+            _ => cur.push(c),
+        }
+    }
+    out.push(cur);
+    out
+}
+
+fn info_rows<I: IntoIterator<Item = String>>(items: I) -> Vec<QueryResultRow> {
+    items.into_iter().map(QueryResultRow::Info).collect()
+}
+
+fn node_line(db: &GraphDatabase, id: &NodeId, value: impl std::fmt::Display) -> String {
+    let label = db.nodes.get(id).map(|n| n.label.as_str()).unwrap_or("");
+    format!("{} {} {}", id, label, value)
+}
+
+fn db_labels(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.labels()))
+}
+
+fn db_relationship_types(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.relationship_types()))
+}
+
+fn db_property_keys(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.property_keys()))
+}
+
+fn db_schema(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let schema = Schema::infer(db);
+    let mut out: Vec<String> = schema
+        .labels
+        .into_iter()
+        .map(|l| format!("(:{}) count={} keys=[{}]", l.label, l.count, l.keys.join(", ")))
+        .collect();
+    out.extend(schema.relationships.into_iter().map(|r| format!("(:{})-[:{}]->(:{}) count={}", r.from, r.label, r.to, r.count)));
+    Ok(info_rows(out))
+}
+
+fn db_info(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let mut out: Vec<String> = db.metadata.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    out.push(format!("nodes={}", db.nodes.len()));
+    out.push(format!("relationships={}", db.relationships.len()));
+    Ok(info_rows(out))
+}
+
+fn db_plan_cache(_db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let stats = super::cypher_spec::plan_cache_stats();
+    Ok(info_rows([format!("entries={}", stats.entries), format!("hits={}", stats.hits), format!("misses={}", stats.misses)]))
+}
+
+fn db_changes(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let since = Since::parse(args.first().map(String::as_str).unwrap_or(""))?;
+    let events = db.changes().since(&since)?;
+    Ok(info_rows(events.iter().map(serde_json::to_string).collect::<serde_json::Result<Vec<String>>>()?))
+}
+
+fn db_constraints(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.unique_relationship_labels.iter().map(|l| format!("UNIQUE ()-[:{}]->()", l))))
+}
+
+fn db_constraints_set_unique(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let label = args.first().map(|s| s.trim()).filter(|s| !s.is_empty()).ok_or_else(|| anyhow!("db.constraints.setUnique expects a relationship type"))?;
+    let unique = match args.get(1) {
+        Some(v) => v.trim().parse::<bool>().map_err(|_| anyhow!("unique expects true or false, got '{}'", v))?,
+        None => true,
+    };
+    db.set_relationship_unique(label, unique);
+    let existing = db.uniqueness_violations().iter().filter(|v| v.label == label).count();
+    let mut out = vec![format!("{} {}", label, if unique { "unique" } else { "not unique" })];
+    if existing > 0 {
+        out.push(format!("{} node pair(s) already have duplicate {} edges (CALL db.constraints.audit())", existing, label));
+    }
+    Ok(info_rows(out))
+}
+
+fn db_constraints_audit(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.uniqueness_violations().into_iter().map(|v| {
+        let ids: Vec<String> = v.relationships.iter().map(|id| id.to_string()).collect();
+        format!("{} {} -> {} count={} ids=[{}]", v.label, v.from, v.to, ids.len(), ids.join(", "))
+    })))
+}
+
+fn db_constraints_properties(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.property_rules.iter().flat_map(|(label, rules)| rules.iter().map(move |r| format!("(:{}) {}: {}", label, r.key, r)))))
+}
+
+fn db_constraints_set_property(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let (Some(label), Some(key)) = (args.first().map(|s| s.trim()).filter(|s| !s.is_empty()), args.get(1).map(|s| s.trim()).filter(|s| !s.is_empty())) else {
+        return Err(anyhow!("db.constraints.setProperty expects a node label and a metadata key"));
+    };
+    let spec = args.get(2).map(|s| s.trim()).unwrap_or("");
+    if spec.is_empty() {
+        let removed = db.remove_property_rule(label, key);
+        return Ok(info_rows([format!("(:{}) {}: {}", label, key, if removed { "rule removed" } else { "no rule" })]));
+    }
+    let rule = PropertyRule::parse(key, spec)?;
+    let line = format!("(:{}) {}: {}", label, key, rule);
+    db.set_property_rule(label, rule);
+    let mut out = vec![line];
+    let existing = db.rule_violations().iter().filter(|v| v.label == label && v.key == key).count();
+    if existing > 0 {
+        out.push(format!("{} existing node(s) break this rule (CALL db.constraints.validate())", existing));
+    }
+    Ok(info_rows(out))
+}
+
+fn db_constraints_validate(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(db.rule_violations().into_iter().map(|v| v.to_string())))
+}
+
+fn db_computed(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(computed::describe(&db.computed_properties)))
+}
+
+fn db_computed_set(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let (Some(label), Some(key)) = (args.first().map(|s| s.trim()).filter(|s| !s.is_empty()), args.get(1).map(|s| s.trim()).filter(|s| !s.is_empty())) else {
+        return Err(anyhow!("db.computed.set expects a node label and a metadata key"));
+    };
+    let formula = args.get(2).map(|s| s.trim()).unwrap_or("");
+    if formula.is_empty() {
+        let removed = db.remove_computed_property(label, key);
+        return Ok(info_rows([format!("(:{}) {}: {}", label, key, if removed { "no longer computed" } else { "not computed" })]));
+    }
+    let every = match args.get(3).map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        Some(a) => Some(a.parse::<u64>().map_err(|_| anyhow!("everySeconds must be a whole number: {}", a))?),
+        None => None,
+    };
+    let prop = ComputedProperty::new(key, formula, every)?;
+    let line = format!("(:{}) {} = {}", label, key, prop);
+    db.set_computed_property(label, prop);
+    Ok(info_rows([line]))
+}
+
+fn db_derived(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(derived::describe(&db.derived_edges)))
+}
+
+fn db_derived_set(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let arg = |i: usize| args.get(i).map(|s| s.trim()).filter(|s| !s.is_empty());
+    let Some(label) = arg(0) else { return Err(anyhow!("db.derived.set expects a relationship type")) };
+    let Some(key) = arg(1) else {
+        return Ok(info_rows([match derived::remove_rule(db, label) {
+            Some(removed) => format!("{}: no longer derived, {} edge(s) removed", label, removed),
+            None => format!("{}: not derived", label),
+        }]));
+    };
+    let write = match arg(3) {
+        Some(a) => a.parse::<bool>().map_err(|_| anyhow!("write expects true or false, got '{}'", a))?,
+        None => false,
+    };
+    let rule = DerivedEdgeRule::new(label, key, arg(2), write)?;
+    let line = rule.to_string();
+    let refresh = derived::set_rule(db, rule);
+    Ok(info_rows([if refresh.changed() { format!("{}; {}", line, refresh) } else { line }]))
+}
+
+fn db_derived_refresh(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows([format!("Derived edges: {}", derived::refresh(db))]))
+}
+
+fn db_views(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(views::describe(&db.views)))
+}
+
+fn db_views_set(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let arg = |i: usize| args.get(i).map(|s| s.trim()).filter(|s| !s.is_empty());
+    let Some(name) = arg(0) else { return Err(anyhow!("db.views.set expects a view name")) };
+    let Some(query) = arg(1) else {
+        let removed = db.remove_view(name);
+        return Ok(info_rows([format!("{}: {}", name, if removed { "removed" } else { "no such view" })]));
+    };
+    let view = GraphView::new(name, query, arg(2))?;
+    let line = view.to_string();
+    db.set_view(view);
+    Ok(info_rows([line]))
+}
+
+fn db_views_show(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let name = args.first().map(|s| s.trim()).filter(|s| !s.is_empty()).ok_or_else(|| anyhow!("db.views.show expects a view name"))?;
+    let graph = views::evaluate_named(db, name)?;
+    let mut nodes: Vec<_> = graph.nodes.into_values().collect();
+    nodes.sort_by_key(|n| n.id);
+    let mut rels: Vec<_> = graph.relationships.into_values().collect();
+    rels.sort_by_key(|r| r.id);
+    let nodes = nodes.into_iter().map(|n| QueryResultRow::Node { id: n.id, label: n.label, metadata: n.metadata });
+    let rels = rels.into_iter().map(|r| QueryResultRow::Relationship { id: r.id, from: r.from_node, to: r.to_node, label: r.label, metadata: r.metadata });
+    Ok(nodes.chain(rels).collect())
+}
+
+fn dbms_procedures(_db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(info_rows(list().into_iter().map(|p| format!("{} - {}", p.signature, p.description))))
+}
+
+// Rank (id, score) pairs highest first, breaking ties by id for stable output
+fn ranked<T: PartialOrd + std::fmt::Display>(db: &GraphDatabase, mut scores: Vec<(NodeId, T)>) -> Vec<QueryResultRow> {
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+    info_rows(scores.iter().map(|(id, s)| node_line(db, id, s)))
+}
+
+fn algo_degree(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    Ok(ranked(db, computed::degrees(db).into_iter().map(|(id, (indeg, outdeg))| (id, indeg + outdeg)).collect()))
+}
+
+fn algo_page_rank(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let iterations: usize = match args.first() {
+        Some(a) => a.trim().parse().map_err(|_| anyhow!("iterations must be a whole number: {}", a))?,
+        None => 20,
+    };
+    let damping: f64 = match args.get(1) {
+        Some(a) => a.trim().parse().map_err(|_| anyhow!("damping must be a number: {}", a))?,
+        None => 0.85,
+    };
+    let rank = computed::page_rank(db, iterations, damping);
+    Ok(ranked(db, rank.into_iter().map(|(id, r)| (id, (r * 1e6).round() / 1e6)).collect()))
+}
+
+fn algo_connected_components(db: &mut GraphDatabase, _args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let mut adj: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for r in db.relationships.values() {
+        adj.entry(r.from_node).or_default().push(r.to_node);
+        adj.entry(r.to_node).or_default().push(r.from_node);
+    }
+    // Visit nodes in id order so component numbering is deterministic
+    let mut ids: Vec<NodeId> = db.nodes.keys().copied().collect();
+    ids.sort();
+    let mut component: HashMap<NodeId, usize> = HashMap::new();
+    let mut next = 0usize;
+    for start in &ids {
+        if component.contains_key(start) { continue; }
+        let mut queue = VecDeque::from([*start]);
+        component.insert(*start, next);
+        while let Some(cur) = queue.pop_front() {
+            for nb in adj.get(&cur).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if db.nodes.contains_key(nb) && !component.contains_key(nb) {
+                    component.insert(*nb, next);
+                    queue.push_back(*nb);
+                }
+            }
+        }
+        next += 1;
+    }
+    Ok(info_rows(ids.iter().map(|id| node_line(db, id, component[id]))))
+}
+
+fn algo_shortest_path(db: &mut GraphDatabase, args: &[String]) -> Result<Vec<QueryResultRow>> {
+    let id_arg = |i: usize, what: &str| -> Result<NodeId> {
+        let raw = args.get(i).ok_or_else(|| anyhow!("algo.shortestPath needs a {} node id", what))?;
+        let id = Uuid::parse_str(raw.trim()).map_err(|_| anyhow!("invalid {} node id: {}", what, raw))?;
+        if !db.nodes.contains_key(&id) { return Err(anyhow!("{} node not found: {}", what, id)); }
+        Ok(id)
+    };
+    let (from, to) = (id_arg(0, "start")?, id_arg(1, "end")?);
+    let typ = args.get(2).map(|t| t.trim()).filter(|t| !t.is_empty());
+    let mut adj: HashMap<NodeId, Vec<(Uuid, NodeId)>> = HashMap::new();
+    for r in db.relationships.values() {
+        if typ.is_some_and(|t| r.label != t) { continue; }
+        adj.entry(r.from_node).or_default().push((r.id, r.to_node));
+    }
+    // BFS remembering how each node was reached
+    let mut prev: HashMap<NodeId, (Uuid, NodeId)> = HashMap::new();
+    let mut seen: HashSet<NodeId> = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(cur) = queue.pop_front() {
+        if cur == to { break; }
+        for &(rid, nb) in adj.get(&cur).map(|v| v.as_slice()).unwrap_or(&[]) {
+            if seen.insert(nb) {
+                prev.insert(nb, (rid, cur));
+                queue.push_back(nb);
+            }
+        }
+    }
+    if !seen.contains(&to) { return Ok(Vec::new()); }
+    let (mut nodes, mut rels) = (vec![to], Vec::new());
+    let mut cur = to;
+    while let Some(&(rid, p)) = prev.get(&cur) {
+        rels.push(rid);
+        nodes.push(p);
+        cur = p;
+    }
+    nodes.reverse();
+    rels.reverse();
+    Ok(vec![QueryResultRow::Path { nodes, relationships: rels }])
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
@@ -7,12 +7,21 @@ use time::{macros::format_description, OffsetDateTime};
 use uuid::Uuid;
 
 use crate::graph_utils::graph::{GraphDatabase, NodeId};
-use super::cypher_spec::{execute_cypher, execute_cypher_with_params};
-
+use crate::graph_utils::{computed, permissions, validation};
+use super::collation;
+use super::cypher_spec;
+use super::procedures;
+use super::cypher_spec::{execute_cypher, execute_cypher_with_params, top_level_keywords, writes_graph};
+
+/// One row of a query result. New kinds of rows may be added, so matches outside this crate
+/// need a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum QueryResultRow {
     Node { id: NodeId, label: String, metadata: HashMap<String, String> },
     Relationship { id: Uuid, from: NodeId, to: NodeId, label: String, metadata: HashMap<String, String> },
+    // A bound path variable: nodes in traversal order and the relationships between them
+    Path { nodes: Vec<NodeId>, relationships: Vec<Uuid> },
     #[allow(dead_code)]
     Info(String),
 }
@@ -23,6 +32,8 @@ pub struct QueryOutcome {
     pub affected_nodes: usize,
     pub affected_relationships: usize,
     pub mutated: bool,
+    // Lint findings for the query as submitted (see `lint`)
+    pub warnings: Vec<String>,
 }
 
 fn log_path_for_now() -> PathBuf {
@@ -74,11 +85,182 @@ fn _split_statements(input: &str) -> Vec<String> {
     parts.into_iter().filter(|s| !s.trim().is_empty()).collect()
 }
 
+// Split `q1 UNION [ALL] q2 ...` into its parts. Returns None when there is no top-level UNION;
+// the flag is true for UNION ALL. Mixing UNION and UNION ALL is rejected like in Cypher.
+fn split_union(stmt: &str) -> Result<Option<(Vec<String>, bool)>> {
+    let marks = top_level_keywords(stmt, &["UNION"]);
+    if marks.is_empty() { return Ok(None); }
+    let mut parts = Vec::new();
+    let mut all: Option<bool> = None;
+    let mut start = 0usize;
+    for (pos, kw) in marks {
+        parts.push(stmt[start..pos].trim().to_string());
+        let after = &stmt[pos + kw.len()..];
+        let trimmed = after.trim_start();
+        let is_all = trimmed.to_ascii_uppercase().starts_with("ALL") && trimmed[3..].starts_with(char::is_whitespace);
+        if all.is_some_and(|a| a != is_all) {
+            return Err(anyhow!("cannot mix UNION and UNION ALL in one query"));
+        }
+        all = Some(is_all);
+        start = pos + kw.len() + if is_all { after.len() - trimmed.len() + 3 } else { 0 };
+    }
+    parts.push(stmt[start..].trim().to_string());
+    if parts.iter().any(|p| p.is_empty()) { return Err(anyhow!("UNION requires a query on both sides")); }
+    Ok(Some((parts, all.unwrap_or(false))))
+}
+
+// True when MATCH is followed by a Cypher pattern: `(n...)` or a named path `p = (a)-...`.
+fn starts_pattern(rest: &str) -> bool {
+    let t = rest.trim_start();
+    t.starts_with('(')
+        || t.split_once('=').is_some_and(|(v, after)| {
+            let v = v.trim();
+            !v.is_empty() && v.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') && after.trim_start().starts_with('(')
+        })
+}
+
+// Combine the outcomes of the UNION parts; plain UNION drops duplicate rows.
+fn union_outcomes(outcomes: Vec<QueryOutcome>, all: bool) -> QueryOutcome {
+    let mut out = QueryOutcome::default();
+    let mut seen: HashSet<String> = HashSet::new();
+    for o in outcomes {
+        out.affected_nodes += o.affected_nodes;
+        out.affected_relationships += o.affected_relationships;
+        out.mutated |= o.mutated;
+        for row in o.rows {
+            let key = match &row {
+                QueryResultRow::Node { id, .. } => format!("n:{}", id),
+                QueryResultRow::Relationship { id, .. } => format!("r:{}", id),
+                QueryResultRow::Path { nodes, relationships } => format!("p:{:?}:{:?}", nodes, relationships),
+                QueryResultRow::Info(s) => format!("i:{}", s),
+            };
+            if all || seen.insert(key) { out.rows.push(row); }
+        }
+    }
+    out
+}
+
+/// Lint the Cypher statements of `query` against the current graph without running them.
+/// Legacy commands and statements that fail to parse produce no warnings.
+pub fn lint(db: &GraphDatabase, query: &str) -> Vec<String> {
+    let Ok((_, trimmed)) = collation::strip_options_prefix(query.trim()) else { return Vec::new() };
+    let mut warnings = Vec::new();
+    for stmt in trimmed.split(';') {
+        let stmt = stmt.trim();
+        if stmt.is_empty() { continue; }
+        let parts = match split_union(stmt) {
+            Ok(Some((parts, _))) => parts,
+            Ok(None) => vec![stmt.to_string()],
+            Err(_) => continue,
+        };
+        for part in parts {
+            if is_cypher_statement(&part) {
+                for w in cypher_spec::lint(db, &part) {
+                    if !warnings.contains(&w) { warnings.push(w); }
+                }
+            }
+        }
+    }
+    warnings
+}
+
+// Same routing as execute_query_with_params; only Cypher statements have a plan
+fn is_cypher_statement(stmt: &str) -> bool {
+    let upper = stmt.to_uppercase();
+    let legacy = upper.starts_with("MATCH (") && upper.contains(" MERGE ");
+    let is_cypher = (upper.starts_with("MATCH ") && starts_pattern(&stmt[6..])) ||
+        (upper.starts_with("OPTIONAL MATCH ") && starts_pattern(&stmt[15..])) ||
+        ["MERGE ", "RETURN ", "UNWIND ", "WITH ", "DETACH DELETE "].iter().any(|kw| upper.starts_with(kw)) ||
+        (upper.starts_with("DELETE ") && !upper.starts_with("DELETE NODE ") && !upper.starts_with("DELETE REL ")) ||
+        (upper.starts_with("CREATE ") && stmt[7..].trim_start().starts_with('('));
+    is_cypher && !legacy
+}
+
+/// Parse the Cypher statements of `query` into the plan cache without touching a graph, so
+/// later executions with any parameters skip parsing. Legacy commands are accepted as-is.
+pub fn prepare(query: &str) -> Result<()> {
+    let trimmed = query.trim();
+    if trimmed.is_empty() {
+        return Err(anyhow!("empty query"));
+    }
+    let (_, trimmed) = collation::strip_options_prefix(trimmed)?;
+    for stmt in trimmed.split(';') {
+        let stmt = stmt.trim();
+        if stmt.is_empty() { continue; }
+        let parts = match split_union(stmt)? {
+            Some((parts, _)) => parts,
+            None => vec![stmt.to_string()],
+        };
+        for part in parts {
+            if is_cypher_statement(&part) { cypher_spec::prepare(&part)?; }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `query` may change the graph: it has a writing clause or calls a procedure.
+pub fn may_write(query: &str) -> bool {
+    writes_graph(query) || query.to_ascii_uppercase().contains("CALL")
+}
+
+/// Run the write `f` under every check at once: the caller's role, the property rules and
+/// the computed keys. The graph is copied once beforehand and put back when `f` fails or a
+/// check refuses what it did. Without a role, rules or computed keys this is just `f(db)`.
+pub fn checked<T>(db: &mut GraphDatabase, f: impl FnOnce(&mut GraphDatabase) -> Result<T>) -> Result<T> {
+    let role = permissions::current_role();
+    if role.is_none() && db.property_rules.is_empty() && db.computed_properties.is_empty() { return f(db); }
+    db.with_rollback(f, |before, after| {
+        if let Some(role) = &role { permissions::check(role, before, after)?; }
+        validation::check(before, after)?;
+        computed::check(before, after)
+    })
+}
+
+// Computed properties are brought up to date first. Writes are checked against the graph's
+// property rules, its computed keys and the caller's role, and undone when they break any
+// (see `checked`)
 pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome> {
+    traced(query, || {
+        computed::refresh(db);
+        if !may_write(query) { return run_query(db, query); }
+        checked(db, |db| run_query(db, query))
+    })
+}
+
+// Run `f` in a span that ends up holding the outcome; the query text stays out of it, since
+// it may carry data
+fn traced(query: &str, f: impl FnOnce() -> Result<QueryOutcome>) -> Result<QueryOutcome> {
+    let span = tracing::info_span!(
+        "gql.execute",
+        query_len = query.len(),
+        rows = tracing::field::Empty,
+        mutated = tracing::field::Empty,
+        error = tracing::field::Empty,
+    )
+    .entered();
+    let out = f();
+    match &out {
+        Ok(o) => {
+            span.record("rows", o.rows.len());
+            span.record("mutated", o.mutated);
+        }
+        Err(e) => {
+            span.record("error", tracing::field::display(e));
+        }
+    }
+    out
+}
+
+fn run_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome> {
     let trimmed = query.trim();
     if trimmed.is_empty() {
         return Err(anyhow!("empty query"));
     }
+    // A leading `CYPHER caseInsensitive=... collation=...` applies to every statement
+    if let (Some(opts), rest) = collation::strip_options_prefix(trimmed)? {
+        return collation::with_options(opts, || run_query(db, rest));
+    }
+    let warnings = lint(db, trimmed);
 
     // We allow multiple statements separated by semicolons; execute sequentially
     let mut outcome = QueryOutcome::default();
@@ -86,6 +268,15 @@ pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome
     for stmt in trimmed.split(';') {
         let stmt = stmt.trim();
         if stmt.is_empty() { continue; }
+        if let Some((parts, all)) = split_union(stmt)? {
+            let outcomes = parts.iter().map(|p| run_query(db, p)).collect::<Result<Vec<_>>>()?;
+            let o = union_outcomes(outcomes, all);
+            any_mut = any_mut || o.mutated;
+            outcome.affected_nodes += o.affected_nodes;
+            outcome.affected_relationships += o.affected_relationships;
+            outcome.rows.extend(o.rows);
+            continue;
+        }
         let upper = stmt.to_uppercase();
         // First: legacy minimal Cypher-style handler for pairwise MATCH...MERGE in one statement
         let res = if upper.starts_with("MATCH (") && upper.contains(" MERGE ") {
@@ -93,13 +284,15 @@ pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome
             exec_cypher_match_merge(db, stmt)
         // If the statement appears to be OpenCypher, route to the Cypher engine.
         // Detect by keywords and forms that are NOT the legacy custom commands.
-        } else if (upper.starts_with("MATCH ") && stmt[6..].trim_start().starts_with('(')) ||
+        } else if (upper.starts_with("MATCH ") && starts_pattern(&stmt[6..])) ||
         // OPTIONAL MATCH with '(' only
-        (upper.starts_with("OPTIONAL MATCH ") && stmt[15..].trim_start().starts_with('(')) ||
+        (upper.starts_with("OPTIONAL MATCH ") && starts_pattern(&stmt[15..])) ||
         // MERGE is Cypher-only
         upper.starts_with("MERGE ") ||
         // RETURN is Cypher-only
         upper.starts_with("RETURN ") ||
+        // UNWIND / WITH start multi-stage Cypher statements
+        upper.starts_with("UNWIND ") || upper.starts_with("WITH ") ||
         // SET / REMOVE are Cypher-only
         upper.starts_with("SET ") || upper.starts_with("REMOVE ") ||
         // DELETE / DETACH DELETE are Cypher-only, but avoid legacy DELETE NODE/REL
@@ -108,8 +301,9 @@ pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome
         // CREATE with '(' pattern (avoid legacy CREATE NODE/REL)
         (upper.starts_with("CREATE") && stmt[6..].trim_start().starts_with('(')) {
             let rows = execute_cypher(db, stmt)?;
-            // conservatively mark mutated if statement starts with CREATE or MERGE
-            let mutated = upper.starts_with("CREATE")
+            // conservatively mark mutated if statement starts with a write clause or contains one in a later stage
+            let mutated = writes_graph(stmt)
+                || upper.starts_with("CREATE")
                 || upper.starts_with("MERGE ")
                 || upper.starts_with("SET ")
                 || upper.starts_with("REMOVE ")
@@ -120,6 +314,9 @@ pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome
             exec_create_node(db, &stmt[12..])
         } else if upper.starts_with("CREATE REL ") {
             exec_create_rel(db, &stmt[11..])
+        } else if upper.starts_with("CALL ") {
+            let (rows, writes) = procedures::call(db, stmt, &HashMap::new())?;
+            Ok((rows, 0, 0, writes))
         } else if upper.starts_with("MATCH NODE ") {
             exec_match_node(db, &stmt[11..])
         } else if upper.starts_with("MATCH REL ") {
@@ -139,6 +336,7 @@ pub fn execute_query(db: &mut GraphDatabase, query: &str) -> Result<QueryOutcome
         any_mut = any_mut || mutated;
     }
     outcome.mutated = any_mut;
+    outcome.warnings = warnings;
     Ok(outcome)
 }
 
@@ -155,37 +353,66 @@ pub fn execute_query_with_params(
     db: &mut GraphDatabase,
     query: &str,
     params: &HashMap<String, String>,
+) -> Result<QueryOutcome> {
+    traced(query, || {
+        computed::refresh(db);
+        if !may_write(query) { return run_query_with_params(db, query, params); }
+        checked(db, |db| run_query_with_params(db, query, params))
+    })
+}
+
+fn run_query_with_params(
+    db: &mut GraphDatabase,
+    query: &str,
+    params: &HashMap<String, String>,
 )
 -> Result<QueryOutcome> {
     let trimmed = query.trim();
     if trimmed.is_empty() {
         return Err(anyhow!("empty query"));
     }
+    if let (Some(opts), rest) = collation::strip_options_prefix(trimmed)? {
+        return collation::with_options(opts, || run_query_with_params(db, rest, params));
+    }
+    let warnings = lint(db, trimmed);
 
     let mut outcome = QueryOutcome::default();
     let mut any_mut = false;
     for stmt in trimmed.split(';') {
         let stmt = stmt.trim();
         if stmt.is_empty() { continue; }
+        if let Some((parts, all)) = split_union(stmt)? {
+            let outcomes = parts.iter().map(|p| run_query_with_params(db, p, params)).collect::<Result<Vec<_>>>()?;
+            let o = union_outcomes(outcomes, all);
+            any_mut = any_mut || o.mutated;
+            outcome.affected_nodes += o.affected_nodes;
+            outcome.affected_relationships += o.affected_relationships;
+            outcome.rows.extend(o.rows);
+            continue;
+        }
         let upper = stmt.to_uppercase();
         // First: legacy minimal Cypher-style handler for pairwise MATCH...MERGE
         let res = if upper.starts_with("MATCH (") && upper.contains(" MERGE ") {
             exec_cypher_match_merge(db, stmt)
         // True Cypher engine path
-        } else if (upper.starts_with("MATCH ") && stmt[6..].trim_start().starts_with('(')) ||
-        (upper.starts_with("OPTIONAL MATCH ") && stmt[15..].trim_start().starts_with('(')) ||
+        } else if (upper.starts_with("MATCH ") && starts_pattern(&stmt[6..])) ||
+        (upper.starts_with("OPTIONAL MATCH ") && starts_pattern(&stmt[15..])) ||
         upper.starts_with("MERGE ") ||
         upper.starts_with("RETURN ") ||
+        upper.starts_with("UNWIND ") || upper.starts_with("WITH ") ||
         (upper.starts_with("DELETE ") && !upper.starts_with("DELETE NODE ") && !upper.starts_with("DELETE REL ")) ||
         upper.starts_with("DETACH DELETE ") ||
         (upper.starts_with("CREATE ") && stmt[7..].trim_start().starts_with('(')) {
             let rows = execute_cypher_with_params(db, stmt, params)?;
-            let mutated = upper.starts_with("CREATE ") || upper.starts_with("MERGE ") || (upper.starts_with("DELETE ") && !upper.starts_with("DELETE NODE ") && !upper.starts_with("DELETE REL ")) || upper.starts_with("DETACH DELETE ");
+            let mutated = writes_graph(stmt) || upper.starts_with("CREATE ") || upper.starts_with("MERGE ") || (upper.starts_with("DELETE ") && !upper.starts_with("DELETE NODE ") && !upper.starts_with("DELETE REL ")) || upper.starts_with("DETACH DELETE ");
             Ok((rows, 0, 0, mutated))
         } else if upper.starts_with("CREATE NODE ") {
             exec_create_node(db, &stmt[12..])
         } else if upper.starts_with("CREATE REL ") {
             exec_create_rel(db, &stmt[11..])
+        } else if upper.starts_with("CALL ") {
+            let (rows, writes) = procedures::call(db, stmt, params)?;
+            Ok((rows, 0, 0, writes))
         } else if upper.starts_with("MATCH NODE ") {
             exec_match_node(db, &stmt[11..])
         } else if upper.starts_with("MATCH REL ") {
@@ -205,6 +432,7 @@ pub fn execute_query_with_params(
         any_mut = any_mut || mutated;
     }
     outcome.mutated = any_mut;
+    outcome.warnings = warnings;
     Ok(outcome)
 }
 
@@ -0,0 +1,130 @@
+//! Query language reference shown under Help → Query Reference. Functions are listed from
+//! the parser's own function table and procedures from the `CALL` registry, so neither can
+//! be documented without existing; clauses are described here and every example is a query
+//! the engine accepts.
+
+use super::cypher_spec::FUNCTIONS;
+use super::procedures;
+
+/// One documented construct with runnable examples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub syntax: String,
+    pub summary: String,
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub title: &'static str,
+    pub entries: Vec<Entry>,
+}
+
+// (name, syntax, summary, examples)
+type Doc = (&'static str, &'static str, &'static str, &'static [&'static str]);
+
+const CLAUSES: &[Doc] = &[
+    ("MATCH", "MATCH (a:Label {key: 'value'})-[r:TYPE]->(b)", "Find nodes and relationships by label, metadata and direction. Several comma-separated patterns are joined.",
+        &["MATCH (n) RETURN n", "MATCH (p:Person)-[r:KNOWS]->(q:Person) RETURN p, r, q"]),
+    ("OPTIONAL MATCH", "OPTIONAL MATCH pattern", "Like MATCH, but keeps the row with nulls when nothing matches.",
+        &["OPTIONAL MATCH (p:Person {name: 'Nobody'}) RETURN p"]),
+    ("Variable-length paths", "MATCH p = (a)-[:TYPE*min..max]->(b)", "Follow between min and max hops; a named path can be passed to nodes(), relationships() and length().",
+        &["MATCH p = (a:Person)-[:KNOWS*1..3]->(b) RETURN length(p), nodes(p)"]),
+    ("WHERE", "WHERE cond [AND cond ...]", "Filter with =, <>, <, <=, >, >=, CONTAINS, STARTS WITH, ENDS WITH, IS [NOT] NULL and [NOT] EXISTS { pattern }.",
+        &["MATCH (p:Person) WHERE p.name STARTS WITH 'A' RETURN p", "MATCH (p:Person) WHERE NOT EXISTS { MATCH (p)-[:KNOWS]->() } RETURN p.name"]),
+    ("RETURN", "RETURN [DISTINCT] expr [AS alias], ...", "Choose the columns of the result.",
+        &["MATCH (p:Person) RETURN DISTINCT p.name AS name"]),
+    ("ORDER BY / SKIP / LIMIT", "... ORDER BY expr [ASC|DESC] SKIP n LIMIT n", "Sort and page RETURN or WITH output.",
+        &["MATCH (p:Person) RETURN p.name ORDER BY p.name DESC SKIP 1 LIMIT 10"]),
+    ("WITH", "WITH expr [AS alias], ... [WHERE cond]", "End a stage of the query and pass the listed values to the next one.",
+        &["MATCH (p:Person) WITH p, toUpper(p.name) AS shout WHERE shout CONTAINS 'A' RETURN shout"]),
+    ("UNWIND", "UNWIND list AS x", "Turn a list into one row per element.",
+        &["UNWIND ['a', 'b'] AS x RETURN x"]),
+    ("CREATE", "CREATE (n:Label {key: 'value'})", "Create nodes, or relationships between bound or new nodes.",
+        &["CREATE (n:Person {name: 'Grace'}) RETURN n"]),
+    ("MERGE", "MERGE (a)-[:TYPE]->(b)", "Create a pattern unless it already exists.",
+        &["MATCH (a:Person {name: 'Ada'}), (b:Person {name: 'Alan'}) MERGE (a)-[:KNOWS]->(b)"]),
+    ("SET", "SET n.key = value | n += {map}", "Set metadata on bound nodes or relationships.",
+        &["MATCH (p:Person {name: 'Ada'}) SET p.role = 'engineer' RETURN p"]),
+    ("REMOVE", "REMOVE n.key", "Remove a metadata key.",
+        &["MATCH (p:Person {name: 'Ada'}) REMOVE p.role"]),
+    ("DELETE / DETACH DELETE", "[DETACH] DELETE n", "Delete bound elements; DETACH also removes a node's relationships.",
+        &["MATCH (p:Person {name: 'Grace'}) DETACH DELETE p"]),
+    ("UNION", "query UNION [ALL] query", "Concatenate the rows of two queries, without duplicates unless ALL is given.",
+        &["MATCH (p:Person) RETURN p.name UNION MATCH (c:Company) RETURN c.name"]),
+    ("CASE", "CASE [subject] WHEN x THEN y ... [ELSE z] END", "Pick a value by condition or by comparing a subject.",
+        &["MATCH (p:Person) RETURN p.name, CASE WHEN p.age >= 40 THEN 'senior' ELSE 'junior' END AS band"]),
+    ("CALL", "CALL procedure(args)", "Run a built-in procedure; see Procedures below.",
+        &["CALL db.labels()"]),
+    ("CYPHER options", "CYPHER caseInsensitive=true collation=locale query", "Compare text case-insensitively or by locale for the rest of the query.",
+        &["CYPHER caseInsensitive=true MATCH (p:Person) WHERE p.name = 'ada' RETURN p"]),
+    ("Multiple statements", "statement; statement", "Statements separated by semicolons run in order.",
+        &["CREATE (:Tag {name: 'x'}); MATCH (t:Tag) RETURN t"]),
+];
+
+// Matched case-insensitively against the parser's lowercase function table
+const FUNCTION_DOCS: &[Doc] = &[
+    ("toUpper", "toUpper(text)", "Upper-case text.", &["RETURN toUpper('graph')"]),
+    ("toLower", "toLower(text)", "Lower-case text.", &["RETURN toLower('Graph')"]),
+    ("trim", "trim(text)", "Strip surrounding whitespace.", &["RETURN trim('  loom  ')"]),
+    ("ltrim", "ltrim(text)", "Strip leading whitespace.", &["RETURN ltrim('  loom')"]),
+    ("rtrim", "rtrim(text)", "Strip trailing whitespace.", &["RETURN rtrim('loom  ')"]),
+    ("size", "size(text)", "Length of a text value.", &["MATCH (p:Person) RETURN p.name, size(p.name)"]),
+    ("coalesce", "coalesce(a, b, ...)", "The first argument that is not null.", &["MATCH (p:Person) RETURN coalesce(p.nickname, p.name)"]),
+    ("abs", "abs(number)", "Absolute value.", &["RETURN abs('-3')"]),
+    ("round", "round(number)", "Round to the nearest integer.", &["RETURN round('2.5')"]),
+    ("ceil", "ceil(number)", "Round up.", &["RETURN ceil('2.1')"]),
+    ("floor", "floor(number)", "Round down.", &["RETURN floor('2.9')"]),
+    ("toString", "toString(value)", "Value as text.", &["MATCH (p:Person) RETURN toString(p.age)"]),
+    ("toInteger", "toInteger(value)", "Value as an integer, or null.", &["RETURN toInteger('42')"]),
+    ("toFloat", "toFloat(value)", "Value as a float, or null.", &["RETURN toFloat('4.2')"]),
+    ("nodes", "nodes(path)", "Nodes along a named path.", &["MATCH p = (a)-[:KNOWS*1..2]->(b) RETURN nodes(p)"]),
+    ("relationships", "relationships(path)", "Relationships along a named path.", &["MATCH p = (a)-[:KNOWS*1..2]->(b) RETURN relationships(p)"]),
+    ("length", "length(path)", "Number of hops in a named path.", &["MATCH p = (a)-[:KNOWS*1..2]->(b) RETURN length(p)"]),
+    ("datetime", "datetime([iso])", "An ISO-8601 date-time, or now without an argument.", &["RETURN datetime('2024-05-01T12:00:00Z')"]),
+    ("date", "date([iso])", "An ISO-8601 date, or today without an argument.", &["RETURN date('2024-05-01') + duration('P1D')"]),
+    ("duration", "duration(iso)", "An ISO-8601 duration for date arithmetic.", &["RETURN duration('P1DT2H')"]),
+    ("timestamp", "timestamp()", "Milliseconds since the Unix epoch.", &["RETURN timestamp()"]),
+    ("createdAt", "createdAt(n)", "When a node or relationship was created.", &["MATCH (p:Person) RETURN p.name, createdAt(p)"]),
+    ("updatedAt", "updatedAt(n)", "When a node or relationship was last changed.", &["MATCH (p:Person) RETURN p.name, updatedAt(p)"]),
+];
+
+fn entry(doc: &Doc) -> Entry {
+    let (name, syntax, summary, examples) = doc;
+    Entry {
+        name: name.to_string(),
+        syntax: syntax.to_string(),
+        summary: summary.to_string(),
+        examples: examples.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+/// Clauses, scalar functions and procedures, in that order. A function the parser knows but
+/// which has no description here is still listed by name.
+pub fn sections() -> Vec<Section> {
+    let functions = FUNCTIONS.iter().map(|f| {
+        FUNCTION_DOCS.iter().find(|d| d.0.eq_ignore_ascii_case(f)).map(entry)
+            .unwrap_or_else(|| Entry { name: f.to_string(), syntax: format!("{}(...)", f), summary: String::new(), examples: Vec::new() })
+    }).collect();
+    let procedures = procedures::list().into_iter().map(|p| Entry {
+        // Optional arguments are written in brackets, so only argument-free signatures run as-is
+        examples: if p.signature.ends_with("()") { vec![format!("CALL {}", p.signature)] } else { Vec::new() },
+        name: p.name,
+        syntax: format!("CALL {}", p.signature),
+        summary: p.description.to_string(),
+    }).collect();
+    vec![
+        Section { title: "Clauses", entries: CLAUSES.iter().map(entry).collect() },
+        Section { title: "Functions", entries: functions },
+        Section { title: "Procedures", entries: procedures },
+    ]
+}
+
+/// Parser functions without a description here, and described functions the parser does not
+/// know. Empty unless this reference has fallen behind the parser.
+pub fn undocumented_or_unknown_functions() -> Vec<String> {
+    let missing = FUNCTIONS.iter().filter(|f| !FUNCTION_DOCS.iter().any(|d| d.0.eq_ignore_ascii_case(f))).map(|f| f.to_string());
+    let unknown = FUNCTION_DOCS.iter().filter(|d| !FUNCTIONS.iter().any(|f| d.0.eq_ignore_ascii_case(f))).map(|d| d.0.to_string());
+    missing.chain(unknown).collect()
+}
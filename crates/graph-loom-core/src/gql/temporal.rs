@@ -0,0 +1,247 @@
+//! ISO-8601 dates, datetimes and durations for the query language.
+//!
+//! Values stay plain strings (metadata is `String -> String`); these helpers parse them on
+//! demand. Datetimes are normalized to UTC (`2024-05-01T09:30:00Z`) so that normalized values
+//! also sort correctly as text.
+
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+/// A calendar-aware duration: months are applied on the calendar, the rest as exact time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IsoDuration {
+    pub months: i64,
+    pub days: i64,
+    pub seconds: i64,
+    pub nanos: i64,
+}
+
+/// Current time as a normalized datetime string (whole seconds).
+pub fn now() -> String {
+    let now = OffsetDateTime::now_utc();
+    format_datetime(now.replace_nanosecond(0).unwrap_or(now))
+}
+
+pub fn today() -> String {
+    format_date(OffsetDateTime::now_utc().date())
+}
+
+/// Milliseconds since the Unix epoch.
+pub fn timestamp_millis() -> i128 {
+    OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000
+}
+
+pub fn format_date(d: Date) -> String {
+    format!("{:04}-{:02}-{:02}", d.year(), d.month() as u8, d.day())
+}
+
+pub fn format_datetime(dt: OffsetDateTime) -> String {
+    let dt = dt.to_offset(UtcOffset::UTC);
+    let mut s = format!("{}T{:02}:{:02}:{:02}", format_date(dt.date()), dt.hour(), dt.minute(), dt.second());
+    if dt.nanosecond() != 0 {
+        let frac = format!("{:09}", dt.nanosecond());
+        s.push('.');
+        s.push_str(frac.trim_end_matches('0'));
+    }
+    s.push('Z');
+    s
+}
+
+fn num<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    s.parse().ok()
+}
+
+/// Parse `YYYY-MM-DD`.
+pub fn parse_date(s: &str) -> Option<Date> {
+    let s = s.trim();
+    let mut parts = s.splitn(3, '-');
+    let y: i32 = num(parts.next()?, 4)?;
+    let m: u8 = num(parts.next()?, 2)?;
+    let d: u8 = num(parts.next()?, 2)?;
+    Date::from_calendar_date(y, Month::try_from(m).ok()?, d).ok()
+}
+
+fn parse_offset(s: &str) -> Option<UtcOffset> {
+    if s.is_empty() || s.eq_ignore_ascii_case("Z") { return Some(UtcOffset::UTC); }
+    let sign: i8 = match s.as_bytes()[0] { b'+' => 1, b'-' => -1, _ => return None };
+    let digits = s[1..].replace(':', "");
+    let h: i8 = num(digits.get(..2)?, 2)?;
+    let m: i8 = if digits.len() > 2 { num(&digits[2..], 2)? } else { 0 };
+    UtcOffset::from_hms(sign * h, sign * m, 0).ok()
+}
+
+fn parse_time(s: &str) -> Option<Time> {
+    let (hms, frac) = match s.split_once('.') { Some((a, b)) => (a, Some(b)), None => (s, None) };
+    let mut parts = hms.split(':');
+    let h: u8 = num(parts.next()?, 2)?;
+    let m: u8 = num(parts.next()?, 2)?;
+    let sec: u8 = match parts.next() { Some(p) => num(p, 2)?, None => 0 };
+    if parts.next().is_some() { return None; }
+    let nanos: u32 = match frac {
+        Some(f) if !f.is_empty() && f.len() <= 9 && f.bytes().all(|b| b.is_ascii_digit()) => format!("{:0<9}", f).parse().ok()?,
+        Some(_) => return None,
+        None => 0,
+    };
+    Time::from_hms_nano(h, m, sec, nanos).ok()
+}
+
+/// Parse an ISO-8601 datetime (`T` or space separated, optional `Z` / `±HH:MM` offset,
+/// defaulting to UTC). A bare date is read as midnight UTC.
+pub fn parse_datetime(s: &str) -> Option<OffsetDateTime> {
+    let s = s.trim();
+    if s.len() == 10 { return parse_date(s).map(|d| d.midnight().assume_utc()); }
+    let date = parse_date(s.get(..10)?)?;
+    if !matches!(s.as_bytes().get(10), Some(b'T' | b't' | b' ')) { return None; }
+    let rest = &s[11..];
+    let split = rest.find(['Z', 'z', '+', '-']).unwrap_or(rest.len());
+    let time = parse_time(&rest[..split])?;
+    let offset = parse_offset(&rest[split..])?;
+    Some(PrimitiveDateTime::new(date, time).assume_offset(offset))
+}
+
+/// Parse `P[nY][nM][nW][nD][T[nH][nM][n[.f]S]]`, optionally negated with a leading `-`.
+pub fn parse_duration(s: &str) -> Option<IsoDuration> {
+    let s = s.trim();
+    let (neg, s) = match s.strip_prefix('-') { Some(r) => (true, r), None => (false, s) };
+    let body = s.strip_prefix(['P', 'p'])?;
+    if body.is_empty() { return None; }
+    let mut d = IsoDuration::default();
+    let mut in_time = false;
+    let mut n = String::new();
+    for c in body.chars() {
+        match c.to_ascii_uppercase() {
+            'T' if !in_time && n.is_empty() => in_time = true,
+            c if c.is_ascii_digit() || c == '.' => n.push(c),
+            unit => {
+                if n.is_empty() { return None; }
+                let v: f64 = n.parse().ok()?;
+                // Past i64 the cast would saturate instead of failing
+                if v >= i64::MAX as f64 { return None; }
+                let whole = v.trunc() as i64;
+                let (field, amount) = match (unit, in_time) {
+                    ('Y', false) => (&mut d.months, whole.checked_mul(12)?),
+                    ('M', false) => (&mut d.months, whole),
+                    ('W', false) => (&mut d.days, whole.checked_mul(7)?),
+                    ('D', false) => (&mut d.days, whole),
+                    ('H', true) => (&mut d.seconds, whole.checked_mul(3600)?),
+                    ('M', true) => (&mut d.seconds, whole.checked_mul(60)?),
+                    ('S', true) => {
+                        d.nanos += ((v - v.trunc()) * 1e9).round() as i64;
+                        (&mut d.seconds, whole)
+                    }
+                    _ => return None,
+                };
+                *field = field.checked_add(amount)?;
+                // Only seconds may be fractional
+                if v.fract() != 0.0 && !(unit == 'S' && in_time) { return None; }
+                n.clear();
+            }
+        }
+    }
+    if !n.is_empty() { return None; }
+    if neg { d.negate() } else { Some(d) }
+}
+
+impl IsoDuration {
+    /// The duration backwards; `None` if a component has no negative counterpart.
+    pub fn negate(self) -> Option<Self> {
+        Some(IsoDuration { months: self.months.checked_neg()?, days: self.days.checked_neg()?, seconds: self.seconds.checked_neg()?, nanos: self.nanos.checked_neg()? })
+    }
+
+    /// Sum of two durations; `None` on overflow.
+    pub fn plus(self, o: IsoDuration) -> Option<Self> {
+        Some(IsoDuration {
+            months: self.months.checked_add(o.months)?,
+            days: self.days.checked_add(o.days)?,
+            seconds: self.seconds.checked_add(o.seconds)?,
+            nanos: self.nanos.checked_add(o.nanos)?,
+        })
+    }
+
+    fn exact(self) -> Option<Duration> {
+        Duration::seconds(self.days.checked_mul(86_400)?).checked_add(Duration::seconds(self.seconds))?.checked_add(Duration::nanoseconds(self.nanos))
+    }
+
+    /// ISO-8601 text, e.g. `P1M7DT2H30M`; zero is `PT0S`.
+    pub fn to_iso(self) -> String {
+        let neg = self.months < 0 || self.days < 0 || self.seconds < 0 || self.nanos < 0;
+        // Magnitudes, so that i64::MIN has one too
+        let (months, days) = (self.months.unsigned_abs(), self.days.unsigned_abs());
+        let (y, mo) = (months / 12, months % 12);
+        let total_nanos = (self.seconds as i128 * 1_000_000_000 + self.nanos as i128).abs();
+        let (h, rem) = (total_nanos / 3_600_000_000_000, total_nanos % 3_600_000_000_000);
+        let (mi, rem) = (rem / 60_000_000_000, rem % 60_000_000_000);
+        let mut s = String::from(if neg { "-P" } else { "P" });
+        if y != 0 { s.push_str(&format!("{}Y", y)); }
+        if mo != 0 { s.push_str(&format!("{}M", mo)); }
+        if days != 0 { s.push_str(&format!("{}D", days)); }
+        if total_nanos != 0 {
+            s.push('T');
+            if h != 0 { s.push_str(&format!("{}H", h)); }
+            if mi != 0 { s.push_str(&format!("{}M", mi)); }
+            if rem != 0 {
+                let secs = format!("{}.{:09}", rem / 1_000_000_000, rem % 1_000_000_000);
+                s.push_str(secs.trim_end_matches('0').trim_end_matches('.'));
+                s.push('S');
+            }
+        }
+        if s.ends_with('P') { s.push_str("T0S"); }
+        s
+    }
+}
+
+fn add_months(date: Date, months: i64) -> Option<Date> {
+    let idx = date.year() as i64 * 12 + (date.month() as i64 - 1) + months;
+    let year = i32::try_from(idx.div_euclid(12)).ok()?;
+    let month = Month::try_from((idx.rem_euclid(12) + 1) as u8).ok()?;
+    // Clamp the day so Jan 31 + 1 month lands on the last day of February
+    let day = date.day().min(month.length(year));
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+pub fn shift_datetime(dt: OffsetDateTime, d: IsoDuration) -> Option<OffsetDateTime> {
+    let date = add_months(dt.date(), d.months)?;
+    dt.replace_date(date).checked_add(d.exact()?)
+}
+
+pub fn shift_date(date: Date, d: IsoDuration) -> Option<Date> {
+    // Like openCypher, components smaller than a day do not move a date
+    add_months(date, d.months)?.checked_add(Duration::seconds(d.days.checked_add(d.seconds / 86_400)?.checked_mul(86_400)?))
+}
+
+/// Exact difference `a - b` as days and seconds.
+pub fn between(a: OffsetDateTime, b: OffsetDateTime) -> IsoDuration {
+    let diff = a - b;
+    let days = diff.whole_days();
+    let rest = diff - Duration::days(days);
+    IsoDuration { months: 0, days, seconds: rest.whole_seconds(), nanos: rest.subsec_nanoseconds() as i64 }
+}
+
+/// Evaluate `a + b` / `a - b` where at least one side is temporal. `Ok(None)` when the
+/// operands are not a supported temporal combination, an error when the result is out of range.
+pub fn arithmetic(a: &str, op: char, b: &str) -> anyhow::Result<Option<String>> {
+    let overflow = || anyhow::anyhow!("{} {} {} is out of range", a.trim(), op, b.trim());
+    let sign = |d: IsoDuration| if op == '-' { d.negate() } else { Some(d) };
+    let is_date = |s: &str| s.trim().len() == 10;
+    // Move the date or datetime `t` by `d`, which is `None` when negating it overflowed
+    let shift = |t: &str, d: Option<IsoDuration>| {
+        let shifted = if is_date(t) {
+            let Some(date) = parse_date(t) else { return Ok(None) };
+            d.and_then(|d| shift_date(date, d)).map(format_date)
+        } else {
+            let Some(dt) = parse_datetime(t) else { return Ok(None) };
+            d.and_then(|d| shift_datetime(dt, d)).map(format_datetime)
+        };
+        shifted.map(Some).ok_or_else(overflow)
+    };
+    match (parse_duration(a), parse_duration(b)) {
+        (Some(x), Some(y)) => return sign(y).and_then(|y| x.plus(y)).map(|d| Some(d.to_iso())).ok_or_else(overflow),
+        (None, Some(y)) => return shift(a, sign(y)),
+        (Some(x), None) if op == '+' => return shift(b, Some(x)),
+        _ => {}
+    }
+    if op == '-' && let (Some(x), Some(y)) = (parse_datetime(a), parse_datetime(b)) {
+        return Ok(Some(between(x, y).to_iso()));
+    }
+    Ok(None)
+}
@@ -0,0 +1,124 @@
+//! Named views: a read-only query saved with the graph, optionally rolled up by a metadata
+//! key, that yields a graph of its own to show or export. A view keeps only its definition;
+//! [`evaluate`] works its graph out from the current data each time.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::query_interface::{execute_query, may_write, prepare, QueryResultRow};
+use crate::graph_utils::graph::{GraphDatabase, Node, NodeId, Relationship};
+
+/// Value of the aggregate key for nodes without it.
+pub const NO_VALUE: &str = "(none)";
+
+/// A query that picks the nodes and relationships of a view.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphView {
+    pub name: String,
+    pub query: String,
+    /// Roll the matches up into one node per value of this key, with relationships between
+    /// the groups counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<String>,
+}
+
+impl GraphView {
+    /// A view named `name` over a query that parses and only reads the graph.
+    pub fn new(name: &str, query: &str, aggregate: Option<&str>) -> Result<Self> {
+        let (name, query) = (name.trim(), query.trim());
+        if name.is_empty() { bail!("a view needs a name"); }
+        if may_write(query) { bail!("a view query must only read the graph"); }
+        prepare(query)?;
+        let aggregate = aggregate.map(str::trim).filter(|k| !k.is_empty()).map(str::to_string);
+        Ok(GraphView { name: name.to_string(), query: query.to_string(), aggregate })
+    }
+}
+
+impl fmt::Display for GraphView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.query)?;
+        if let Some(key) = &self.aggregate { write!(f, " (by {})", key)?; }
+        Ok(())
+    }
+}
+
+/// The graph of `view` as of now: the nodes and relationships its query returns, with the
+/// ends of each relationship. A query returning nodes alone keeps the relationships between
+/// them. Takes the graph mutably only because queries bring computed properties up to date.
+pub fn evaluate(db: &mut GraphDatabase, view: &GraphView) -> Result<GraphDatabase> {
+    if may_write(&view.query) { bail!("view {} writes to the graph", view.name); }
+    let outcome = execute_query(db, &view.query).map_err(|e| anyhow!("view {}: {}", view.name, e))?;
+    let mut nodes = HashSet::new();
+    let mut relationships = HashSet::new();
+    for row in outcome.rows {
+        match row {
+            QueryResultRow::Node { id, .. } => { nodes.insert(id); }
+            QueryResultRow::Relationship { id, .. } => { relationships.insert(id); }
+            QueryResultRow::Path { nodes: ns, relationships: rs } => {
+                nodes.extend(ns);
+                relationships.extend(rs);
+            }
+            QueryResultRow::Info(_) => {}
+        }
+    }
+    if relationships.is_empty() { relationships = db.relationships_within(&nodes).into_iter().collect(); }
+    let graph = db.subgraph(&nodes, &relationships);
+    Ok(match &view.aggregate {
+        Some(key) => aggregate(&graph, key),
+        None => graph,
+    })
+}
+
+/// The view named `name` of `db`, evaluated.
+pub fn evaluate_named(db: &mut GraphDatabase, name: &str) -> Result<GraphDatabase> {
+    let view = db.views.iter().find(|v| v.name == name).cloned().ok_or_else(|| anyhow!("no view named {}", name))?;
+    evaluate(db, &view)
+}
+
+// Same id for the same group every time, so a refreshed view keeps its layout
+fn group_id(parts: &[&str]) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, parts.join("\u{1f}").as_bytes())
+}
+
+/// One node per value of `key` (labelled `key`, with the value under `name` and `key` and
+/// the number of nodes under `count`) and one relationship per label between two groups,
+/// with the number of relationships under `count`. Relationships within a group are left out.
+pub fn aggregate(db: &GraphDatabase, key: &str) -> GraphDatabase {
+    let mut out = GraphDatabase::new();
+    let mut group_of: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut counts: BTreeMap<&str, Vec<NodeId>> = BTreeMap::new();
+    for n in db.nodes.values() {
+        counts.entry(n.metadata.get(key).map_or(NO_VALUE, |v| v.as_str())).or_default().push(n.id);
+    }
+    for (value, members) in counts {
+        let id = group_id(&[key, value]);
+        let metadata = HashMap::from([
+            ("name".to_string(), value.to_string()),
+            (key.to_string(), value.to_string()),
+            ("count".to_string(), members.len().to_string()),
+        ]);
+        out.nodes.insert(id, Node { id, label: key.to_string(), metadata, description: String::new(), created_at: String::new(), updated_at: String::new() });
+        group_of.extend(members.into_iter().map(|m| (m, id)));
+    }
+    let mut edges: BTreeMap<(NodeId, NodeId, &str), usize> = BTreeMap::new();
+    for r in db.relationships.values() {
+        let (Some(from), Some(to)) = (group_of.get(&r.from_node), group_of.get(&r.to_node)) else { continue };
+        if from != to { *edges.entry((*from, *to, r.label.as_str())).or_default() += 1; }
+    }
+    for ((from, to, label), count) in edges {
+        let id = group_id(&[&from.to_string(), &to.to_string(), label]);
+        let metadata = HashMap::from([("count".to_string(), count.to_string())]);
+        let rel = Relationship { id, from_node: from, to_node: to, label: label.to_string(), metadata, description: String::new(), created_at: String::new(), updated_at: String::new() };
+        out.relationships.insert(id, rel);
+    }
+    out
+}
+
+/// One line per view, e.g. `Platform: MATCH (s:Service) RETURN s (by team)`.
+pub fn describe(views: &[GraphView]) -> Vec<String> {
+    views.iter().map(|v| v.to_string()).collect()
+}
@@ -0,0 +1,275 @@
+//! Change data capture: every write to a graph becomes an event saying which element changed,
+//! how, when and by whom, with the element as it was before and after. Events are numbered in
+//! order and kept with the graph until the owner saves, when they are appended to a JSON-lines
+//! file next to the state file. `CALL db.changes(since)` and `/v1/changes` read them back, so
+//! an external copy can follow the graph by asking for everything after the last event it saw.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::graph::{GraphDatabase, Node, NodeId, Relationship};
+use crate::gql::temporal;
+
+/// Who writes when nobody said otherwise: the person at the GUI.
+pub const LOCAL_ACTOR: &str = "local";
+
+thread_local! {
+    static ACTOR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with the writes it makes attributed to `actor` (e.g. `api:42` for an API request).
+pub fn as_actor<T>(actor: impl Into<String>, f: impl FnOnce() -> T) -> T {
+    let previous = ACTOR.with(|a| a.replace(Some(actor.into())));
+    let out = f();
+    ACTOR.with(|a| *a.borrow_mut() = previous);
+    out
+}
+
+/// The actor writes on this thread are attributed to.
+pub fn current_actor() -> String {
+    ACTOR.with(|a| a.borrow().clone()).unwrap_or_else(|| LOCAL_ACTOR.to_string())
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Element {
+    Node,
+    Relationship,
+}
+
+/// The recorded part of a node or relationship; timestamps are left out since the event
+/// carries its own.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElementState {
+    pub label: String,
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Endpoints, for relationships
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<NodeId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<NodeId>,
+}
+
+impl From<&Node> for ElementState {
+    fn from(n: &Node) -> Self {
+        ElementState {
+            label: n.label.clone(),
+            metadata: n.metadata.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            description: n.description.clone(),
+            from: None,
+            to: None,
+        }
+    }
+}
+
+impl From<&Relationship> for ElementState {
+    fn from(r: &Relationship) -> Self {
+        ElementState {
+            label: r.label.clone(),
+            metadata: r.metadata.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            description: r.description.clone(),
+            from: Some(r.from_node),
+            to: Some(r.to_node),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Position in the log, starting at 1 and never reused
+    pub seq: u64,
+    pub at: String,
+    pub actor: String,
+    pub op: Operation,
+    pub element: Element,
+    pub id: Uuid,
+    /// Absent for creations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<ElementState>,
+    /// Absent for deletions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<ElementState>,
+}
+
+/// Where to start reading the log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Since {
+    /// Events after this sequence number (0 reads everything)
+    Seq(u64),
+    /// Events at or after this ISO-8601 time
+    Time(String),
+}
+
+impl Since {
+    /// A sequence number or an ISO-8601 date/time; empty means from the start.
+    pub fn parse(text: &str) -> Result<Since> {
+        let t = text.trim();
+        if t.is_empty() { return Ok(Since::Seq(0)); }
+        if let Ok(seq) = t.parse::<u64>() { return Ok(Since::Seq(seq)); }
+        let at = temporal::parse_datetime(t).ok_or_else(|| anyhow!("since expects a sequence number or an ISO-8601 time, got '{}'", t))?;
+        Ok(Since::Time(temporal::format_datetime(at)))
+    }
+
+    fn includes(&self, e: &ChangeEvent) -> bool {
+        match self {
+            Since::Seq(seq) => e.seq > *seq,
+            Since::Time(at) => temporal::parse_datetime(&e.at).zip(temporal::parse_datetime(at)).is_some_and(|(e, at)| e >= at),
+        }
+    }
+}
+
+/// The change log of one graph. Only the last sequence number is stored with the graph;
+/// events wait in memory until [`ChangeLog::flush`] appends them to the attached file.
+/// A copy of the graph carries its pending events, so putting a copy back (as a refused
+/// write does) also takes back the events of the writes it undoes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChangeLog {
+    seq: u64,
+    #[serde(skip)]
+    pending: Vec<ChangeEvent>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
+}
+
+impl ChangeLog {
+    /// Log that `id` went from `before` to `after`; `None` on one side makes it a creation or
+    /// a deletion. Nothing is logged when both sides are equal.
+    pub fn record(&mut self, element: Element, id: Uuid, before: Option<ElementState>, after: Option<ElementState>) {
+        let op = match (&before, &after) {
+            (None, None) => return,
+            (None, Some(_)) => Operation::Create,
+            (Some(_), None) => Operation::Delete,
+            (Some(b), Some(a)) if a == b => return,
+            _ => Operation::Update,
+        };
+        self.seq += 1;
+        self.pending.push(ChangeEvent { seq: self.seq, at: temporal::now(), actor: current_actor(), op, element, id, before, after });
+    }
+
+    /// Log every difference between `old` and `new`, as when one graph replaces another.
+    pub fn record_diff(&mut self, old: &GraphDatabase, new: &GraphDatabase) {
+        let mut rels: Vec<(&Uuid, &Relationship)> = old.relationships.iter().filter(|(id, _)| !new.relationships.contains_key(id)).collect();
+        rels.sort_by_key(|(id, _)| **id);
+        for (id, r) in rels { self.record(Element::Relationship, *id, Some(r.into()), None); }
+        let mut nodes: Vec<(&NodeId, &Node)> = old.nodes.iter().filter(|(id, _)| !new.nodes.contains_key(id)).collect();
+        nodes.sort_by_key(|(id, _)| **id);
+        for (id, n) in nodes { self.record(Element::Node, *id, Some(n.into()), None); }
+        // Unchanged elements are compared in place rather than copied into states
+        let mut nodes: Vec<(&NodeId, &Node)> = new
+            .nodes
+            .iter()
+            .filter(|(id, n)| old.nodes.get(id).is_none_or(|o| (&o.label, &o.metadata, &o.description) != (&n.label, &n.metadata, &n.description)))
+            .collect();
+        nodes.sort_by_key(|(id, _)| **id);
+        for (id, n) in nodes { self.record(Element::Node, *id, old.nodes.get(id).map(ElementState::from), Some(n.into())); }
+        let mut rels: Vec<(&Uuid, &Relationship)> = new
+            .relationships
+            .iter()
+            .filter(|(id, r)| {
+                old.relationships.get(id).is_none_or(|o| {
+                    (&o.label, &o.metadata, &o.description, o.from_node, o.to_node) != (&r.label, &r.metadata, &r.description, r.from_node, r.to_node)
+                })
+            })
+            .collect();
+        rels.sort_by_key(|(id, _)| **id);
+        for (id, r) in rels { self.record(Element::Relationship, *id, old.relationships.get(id).map(ElementState::from), Some(r.into())); }
+    }
+
+    /// Sequence number of the newest event.
+    pub fn last_seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Events not yet written to the file, oldest first.
+    pub fn pending(&self) -> &[ChangeEvent] {
+        &self.pending
+    }
+
+    /// Append events to `path` from now on. Numbering continues after the newest event
+    /// already in the file.
+    pub fn attach(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.seq = self.seq.max(written_through(&path));
+        self.file = Some(path);
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Append the pending events to the attached file and return how many were written.
+    /// Events the file already holds (from another copy of this graph) are skipped. Without
+    /// a file the events stay in memory.
+    pub fn flush(&mut self) -> Result<usize> {
+        let Some(path) = &self.file else { return Ok(0) };
+        if self.pending.is_empty() { return Ok(0); }
+        let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+        let through = *written.entry(path.clone()).or_insert_with(|| last_seq_in(path));
+        let fresh: Vec<&ChangeEvent> = self.pending.iter().filter(|e| e.seq > through).collect();
+        let mut lines = String::new();
+        for e in &fresh {
+            lines.push_str(&serde_json::to_string(e)?);
+            lines.push('\n');
+        }
+        if let Some(last) = fresh.last() {
+            if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+            OpenOptions::new().create(true).append(true).open(path)?.write_all(lines.as_bytes())?;
+            written.insert(path.clone(), last.seq);
+        }
+        let count = fresh.len();
+        self.pending.clear();
+        Ok(count)
+    }
+
+    /// Events selected by `since`, oldest first: those in the file followed by the pending ones.
+    pub fn since(&self, since: &Since) -> Result<Vec<ChangeEvent>> {
+        let mut out = Vec::new();
+        // The file is only read when the pending events do not reach back far enough
+        let covered = matches!(since, Since::Seq(seq) if self.pending.first().map_or(self.seq, |e| e.seq - 1) <= *seq);
+        if !covered
+            && let Some(path) = &self.file
+            && path.exists()
+        {
+            for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+                if line.trim().is_empty() { continue; }
+                let e: ChangeEvent = serde_json::from_str(line).map_err(|e| anyhow!("{} line {}: {}", path.display(), i + 1, e))?;
+                if since.includes(&e) { out.push(e); }
+            }
+        }
+        let last = out.last().map_or(0, |e| e.seq);
+        out.extend(self.pending.iter().filter(|e| e.seq > last && since.includes(e)).cloned());
+        Ok(out)
+    }
+}
+
+// Newest sequence number appended to each log file by this process
+static WRITTEN: Lazy<Mutex<HashMap<PathBuf, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn written_through(path: &Path) -> u64 {
+    let mut written = WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    *written.entry(path.to_path_buf()).or_insert_with(|| last_seq_in(path))
+}
+
+fn last_seq_in(path: &Path) -> u64 {
+    let Ok(text) = fs::read_to_string(path) else { return 0 };
+    text.lines().rev().find_map(|l| serde_json::from_str::<ChangeEvent>(l).ok()).map_or(0, |e| e.seq)
+}
@@ -0,0 +1,374 @@
+//! Computed properties: node metadata keys the graph works out itself, per label, from a
+//! formula over the node's degree, its PageRank and its other keys. Values are stored on the
+//! nodes like any other key, so queries, lanes and edge text read them. [`refresh`] brings
+//! them up to date after the graph changes, right away or at most every few seconds for
+//! costly formulas on large graphs, and queries that set a computed key are refused.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::graph::{GraphDatabase, NodeId};
+
+/// Keys listed in the error of a refused write
+const MAX_LISTED: usize = 5;
+/// Deepest nesting of parentheses and minus signs in a formula
+const MAX_NESTING: usize = 32;
+/// Most numbers, names and operators in a formula; also bounds chains like `a + b + c + ...`
+const MAX_TOKENS: usize = 256;
+
+/// A metadata key of the nodes with a label whose value comes from `formula`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ComputedProperty {
+    pub key: String,
+    /// Arithmetic (`+ - * /`, parentheses) over numbers, other keys and the measures
+    /// `degree`, `in_degree`, `out_degree` and `pagerank`, e.g. `price * quantity`
+    pub formula: String,
+    /// Refresh at most once per this many seconds instead of after every change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<u64>,
+    // Change-log sequence number and time of the last refresh
+    #[serde(skip)]
+    last_run: Option<(u64, Instant)>,
+}
+
+impl ComputedProperty {
+    /// A property for `key` with a formula that parses.
+    pub fn new(key: &str, formula: &str, every: Option<u64>) -> Result<Self> {
+        let key = key.trim();
+        if key.is_empty() { bail!("a computed property needs a key"); }
+        parse(formula)?;
+        Ok(ComputedProperty { key: key.to_string(), formula: formula.trim().to_string(), every: every.filter(|s| *s > 0), last_run: None })
+    }
+
+    // Due when the graph changed since the last refresh and, with `every`, that long has passed
+    fn wait(&self, seq: u64, now: Instant) -> Option<Duration> {
+        match (self.last_run, self.every) {
+            (None, _) => Some(Duration::ZERO),
+            (Some((last, _)), _) if last == seq => None,
+            (Some((_, at)), Some(every)) => Some(Duration::from_secs(every).saturating_sub(now.duration_since(at))),
+            (Some(_), None) => Some(Duration::ZERO),
+        }
+    }
+}
+
+impl fmt::Display for ComputedProperty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.formula)?;
+        if let Some(every) = self.every { write!(f, " (every {}s)", every)?; }
+        Ok(())
+    }
+}
+
+/// What a formula can read of a node besides its metadata.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Measure {
+    Degree,
+    InDegree,
+    OutDegree,
+    PageRank,
+}
+
+impl Measure {
+    fn named(name: &str) -> Option<Measure> {
+        match name {
+            "degree" => Some(Measure::Degree),
+            "in_degree" => Some(Measure::InDegree),
+            "out_degree" => Some(Measure::OutDegree),
+            "pagerank" => Some(Measure::PageRank),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed formula.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Key(String),
+    Measure(Measure),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut s = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit() || **d == '.') { s.push(d); chars.next(); }
+            tokens.push(Token::Number(s.parse().map_err(|_| anyhow!("'{}' is not a number", s))?));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut s = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_alphanumeric() || **d == '_') { s.push(d); chars.next(); }
+            tokens.push(Token::Name(s));
+        } else if c == '`' {
+            // Keys with other characters are quoted: `unit price`
+            chars.next();
+            let s: String = chars.by_ref().take_while(|d| *d != '`').collect();
+            if s.is_empty() { bail!("empty quoted key"); }
+            tokens.push(Token::Name(format!("`{}", s)));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else {
+            bail!("unexpected '{}' in formula", c);
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.at) {
+            Some(Token::Op(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    // Parse one level deeper, refusing formulas nested deep enough to exhaust the stack
+    fn nested(&mut self, rule: fn(&mut Self) -> Result<Expr>) -> Result<Expr> {
+        if self.depth == MAX_NESTING { bail!("formula is nested more than {} deep", MAX_NESTING); }
+        self.depth += 1;
+        let expr = rule(self);
+        self.depth -= 1;
+        expr
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr> {
+        let mut left = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.at += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek_op() {
+            self.at += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    // unary := '-' unary | number | name | '(' sum ')'
+    fn unary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.at).cloned().ok_or_else(|| anyhow!("formula ends too early"))?;
+        self.at += 1;
+        match token {
+            Token::Op('-') => Ok(Expr::Neg(Box::new(self.nested(Self::unary)?))),
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Name(name) => Ok(match name.strip_prefix('`') {
+                Some(quoted) => Expr::Key(quoted.to_string()),
+                None => Measure::named(&name).map_or(Expr::Key(name), Expr::Measure),
+            }),
+            Token::Op('(') => {
+                let inner = self.nested(Self::sum)?;
+                if self.peek_op() != Some(')') { bail!("missing ')' in formula"); }
+                self.at += 1;
+                Ok(inner)
+            }
+            Token::Op(c) => bail!("unexpected '{}' in formula", c),
+        }
+    }
+}
+
+/// Parse a formula, e.g. `pagerank * 100` or `` `unit price` * quantity ``. Names other than
+/// the measures are metadata keys.
+pub fn parse(formula: &str) -> Result<Expr> {
+    let mut parser = Parser { tokens: tokenize(formula)?, at: 0, depth: 0 };
+    if parser.tokens.is_empty() { bail!("a computed property needs a formula"); }
+    if parser.tokens.len() > MAX_TOKENS { bail!("formula is longer than {} terms", MAX_TOKENS); }
+    let expr = parser.sum()?;
+    if parser.at < parser.tokens.len() { bail!("unexpected text after the formula"); }
+    Ok(expr)
+}
+
+// Measures of every node, worked out once per refresh and only when a formula reads them
+#[derive(Default)]
+struct Measures {
+    degrees: Option<HashMap<NodeId, (usize, usize)>>,
+    page_rank: Option<HashMap<NodeId, f64>>,
+}
+
+impl Expr {
+    fn uses(&self, measure: Measure) -> bool {
+        match self {
+            Expr::Measure(m) => *m == measure,
+            Expr::Neg(e) => e.uses(measure),
+            Expr::Binary(a, _, b) => a.uses(measure) || b.uses(measure),
+            Expr::Number(_) | Expr::Key(_) => false,
+        }
+    }
+
+    // `None` when a key is missing or not a number, or on division by zero
+    fn eval(&self, id: NodeId, metadata: &HashMap<String, String>, measures: &Measures) -> Option<f64> {
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Key(k) => metadata.get(k)?.trim().parse().ok()?,
+            Expr::Measure(m) => {
+                let (indeg, outdeg) = measures.degrees.as_ref().and_then(|d| d.get(&id)).copied().unwrap_or_default();
+                match m {
+                    Measure::Degree => (indeg + outdeg) as f64,
+                    Measure::InDegree => indeg as f64,
+                    Measure::OutDegree => outdeg as f64,
+                    Measure::PageRank => *measures.page_rank.as_ref()?.get(&id)?,
+                }
+            }
+            Expr::Neg(e) => -e.eval(id, metadata, measures)?,
+            Expr::Binary(a, op, b) => {
+                let (a, b) = (a.eval(id, metadata, measures)?, b.eval(id, metadata, measures)?);
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ if b == 0.0 => return None,
+                    _ => a / b,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+/// Incoming and outgoing relationships of every node.
+pub fn degrees(db: &GraphDatabase) -> HashMap<NodeId, (usize, usize)> {
+    let mut degree: HashMap<NodeId, (usize, usize)> = db.nodes.keys().map(|id| (*id, (0, 0))).collect();
+    for r in db.relationships.values() {
+        if let Some(d) = degree.get_mut(&r.from_node) { d.1 += 1; }
+        if let Some(d) = degree.get_mut(&r.to_node) { d.0 += 1; }
+    }
+    degree
+}
+
+/// PageRank score of every node after `iterations` rounds with `damping`.
+pub fn page_rank(db: &GraphDatabase, iterations: usize, damping: f64) -> HashMap<NodeId, f64> {
+    let n = db.nodes.len();
+    if n == 0 { return HashMap::new(); }
+    let mut out_links: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for r in db.relationships.values() {
+        if db.nodes.contains_key(&r.to_node) { out_links.entry(r.from_node).or_default().push(r.to_node); }
+    }
+    let base = (1.0 - damping) / n as f64;
+    let mut rank: HashMap<NodeId, f64> = db.nodes.keys().map(|id| (*id, 1.0 / n as f64)).collect();
+    for _ in 0..iterations {
+        // Rank held by nodes without outgoing links is spread evenly over all nodes
+        let dangling: f64 = rank.iter().filter(|(id, _)| !out_links.contains_key(id)).map(|(_, r)| r).sum();
+        let mut next: HashMap<NodeId, f64> = db.nodes.keys().map(|id| (*id, base + damping * dangling / n as f64)).collect();
+        for (from, targets) in &out_links {
+            let share = damping * rank.get(from).copied().unwrap_or(0.0) / targets.len() as f64;
+            for t in targets { *next.entry(*t).or_default() += share; }
+        }
+        rank = next;
+    }
+    rank
+}
+
+/// A computed value as stored: whole numbers without a fraction, others to six places.
+pub fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 { return format!("{}", value as i64); }
+    let s = format!("{:.6}", value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Work out the computed properties that are due and store their values; returns how many
+/// values changed. Nodes the formula has no value for lose the key. Values are derived, so
+/// writing them is neither logged as a change nor stamped on the node.
+pub fn refresh(db: &mut GraphDatabase) -> usize {
+    let (seq, now) = (db.changes().last_seq(), Instant::now());
+    let mut due: Vec<(String, String, Expr)> = Vec::new();
+    for (label, props) in db.computed_properties.iter_mut() {
+        for p in props.iter_mut().filter(|p| p.wait(seq, now) == Some(Duration::ZERO)) {
+            p.last_run = Some((seq, now));
+            if let Ok(expr) = parse(&p.formula) { due.push((label.clone(), p.key.clone(), expr)); }
+        }
+    }
+    if due.is_empty() { return 0; }
+    let mut measures = Measures::default();
+    if due.iter().any(|(_, _, e)| e.uses(Measure::Degree) || e.uses(Measure::InDegree) || e.uses(Measure::OutDegree)) {
+        measures.degrees = Some(degrees(db));
+    }
+    if due.iter().any(|(_, _, e)| e.uses(Measure::PageRank)) { measures.page_rank = Some(page_rank(db, 20, 0.85)); }
+
+    let mut changed = 0;
+    let mut keys_changed = false;
+    for n in db.nodes.values_mut() {
+        for (_, key, expr) in due.iter().filter(|(label, _, _)| *label == n.label) {
+            let value = expr.eval(n.id, &n.metadata, &measures).map(format_value);
+            if n.metadata.get(key) == value.as_ref() { continue; }
+            changed += 1;
+            match value {
+                Some(v) => keys_changed |= n.metadata.insert(key.clone(), v).is_none(),
+                None => keys_changed |= n.metadata.remove(key).is_some(),
+            }
+        }
+    }
+    if keys_changed { db.invalidate_vocabulary(); }
+    changed
+}
+
+/// How long until a computed property waiting on its `every` is due, if one is.
+pub fn next_refresh(db: &GraphDatabase) -> Option<Duration> {
+    let (seq, now) = (db.changes().last_seq(), Instant::now());
+    db.computed_properties.values().flatten().filter_map(|p| p.wait(seq, now)).min()
+}
+
+/// Computed keys of `label` whose value differs between `before` (`None` for a new node) and
+/// `after`.
+pub fn hand_set<'a>(db: &'a GraphDatabase, label: &str, before: Option<&HashMap<String, String>>, after: &HashMap<String, String>) -> Vec<&'a str> {
+    let props = db.computed_properties.get(label).map(Vec::as_slice).unwrap_or_default();
+    props.iter().filter(|p| before.and_then(|b| b.get(&p.key)) != after.get(&p.key)).map(|p| p.key.as_str()).collect()
+}
+
+/// Run the write `f`, undoing it when it fails or sets or removes a computed key by hand.
+pub fn protected<T>(db: &mut GraphDatabase, f: impl FnOnce(&mut GraphDatabase) -> Result<T>) -> Result<T> {
+    if db.computed_properties.is_empty() { return f(db); }
+    db.with_rollback(f, check)
+}
+
+/// Refuse computed keys set or removed by hand between `before` and `after`.
+pub fn check(before: &GraphDatabase, after: &GraphDatabase) -> Result<()> {
+    if after.computed_properties.is_empty() { return Ok(()); }
+    let mut touched: Vec<String> = after
+        .nodes
+        .values()
+        .flat_map(|n| hand_set(after, &n.label, before.nodes.get(&n.id).map(|b| &b.metadata), &n.metadata).into_iter().map(|k| format!("(:{}) {}", n.label, k)))
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if touched.is_empty() { return Ok(()); }
+    if touched.len() > MAX_LISTED {
+        let more = touched.len() - MAX_LISTED;
+        touched.truncate(MAX_LISTED);
+        touched.push(format!("and {} more", more));
+    }
+    Err(anyhow!("computed properties cannot be set by hand: {}", touched.join(", ")))
+}
+
+/// Computed properties as `(:Label) key = formula` lines, for listing.
+pub fn describe(props: &BTreeMap<String, Vec<ComputedProperty>>) -> Vec<String> {
+    props.iter().flat_map(|(label, ps)| ps.iter().map(move |p| format!("(:{}) {} = {}", label, p.key, p))).collect()
+}
@@ -0,0 +1,375 @@
+//! Conflict-free replication of a graph between peers. Each write becomes an operation with a
+//! Lamport stamp: node and relationship existence is an add-wins set (a delete only removes
+//! the additions it had seen, so a concurrent re-add survives) and each label, description and
+//! metadata key is a last-writer-wins register. Registers keep their values, so a write that
+//! arrives before its element's addition shows once the addition does, and a relationship
+//! waits until both its ends exist. Peers that have applied the same operations thus hold the
+//! same graph, whatever order they arrived in. Local writes are read from the change log (see
+//! `changes`); writes applied from a peer are logged as made by `peer:<replica>`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::changes::{self, ChangeEvent, Element, ElementState, Operation, Since};
+use super::graph::{GraphDatabase, Node, Relationship};
+use crate::gql::temporal;
+
+/// Actor prefix of writes applied from a peer; they are not sent on again as local writes.
+pub const PEER_ACTOR_PREFIX: &str = "peer:";
+
+/// Lamport time of an operation; the replica breaks ties so every peer orders stamps alike.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    pub counter: u64,
+    pub replica: String,
+}
+
+/// One last-writer-wins register of an element.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Label,
+    Description,
+    /// Relationship endpoints
+    From,
+    To,
+    Metadata(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum SyncOp {
+    Add { element: Element, id: Uuid, stamp: Stamp, state: ElementState },
+    /// `value: None` removes a metadata key
+    Set { element: Element, id: Uuid, field: Field, value: Option<String>, stamp: Stamp },
+    /// Remove the additions in `observed`
+    Remove { element: Element, id: Uuid, observed: Vec<Stamp>, stamp: Stamp },
+}
+
+// The latest write to a register; `None` is a removed metadata key
+#[derive(Clone, Debug)]
+struct Register {
+    stamp: Stamp,
+    value: Option<String>,
+}
+
+// Replication state of one element
+#[derive(Clone, Debug)]
+struct Entry {
+    element: Element,
+    adds: BTreeSet<Stamp>,
+    removed: BTreeSet<Stamp>,
+    fields: BTreeMap<Field, Register>,
+}
+
+impl Entry {
+    fn new(element: Element) -> Self {
+        Entry { element, adds: BTreeSet::new(), removed: BTreeSet::new(), fields: BTreeMap::new() }
+    }
+
+    fn alive(&self) -> bool {
+        self.adds.iter().any(|s| !self.removed.contains(s))
+    }
+
+    // Write `value` to `field` if `stamp` is newer than the current writer
+    fn wins(&mut self, field: &Field, stamp: &Stamp, value: &Option<String>) -> bool {
+        if self.fields.get(field).is_some_and(|r| r.stamp >= *stamp) { return false; }
+        self.fields.insert(field.clone(), Register { stamp: stamp.clone(), value: value.clone() });
+        true
+    }
+
+    // Registers written at `stamp` from a whole state
+    fn write_all(&mut self, state: &ElementState, stamp: &Stamp) {
+        for (f, v) in fields_of(state) { self.fields.insert(f, Register { stamp: stamp.clone(), value: v }); }
+    }
+
+    // The element as its registers hold it
+    fn state(&self) -> ElementState {
+        let text = |f: &Field| self.fields.get(f).and_then(|r| r.value.clone());
+        let end = |f: &Field| text(f).and_then(|v| Uuid::parse_str(&v).ok());
+        ElementState {
+            label: text(&Field::Label).unwrap_or_default(),
+            metadata: self
+                .fields
+                .iter()
+                .filter_map(|(f, r)| match f {
+                    Field::Metadata(k) => Some((k.clone(), r.value.clone()?)),
+                    _ => None,
+                })
+                .collect(),
+            description: text(&Field::Description).unwrap_or_default(),
+            from: end(&Field::From),
+            to: end(&Field::To),
+        }
+    }
+}
+
+fn fields_of(state: &ElementState) -> Vec<(Field, Option<String>)> {
+    let mut out = vec![(Field::Label, Some(state.label.clone())), (Field::Description, Some(state.description.clone()))];
+    if let (Some(from), Some(to)) = (state.from, state.to) {
+        out.push((Field::From, Some(from.to_string())));
+        out.push((Field::To, Some(to.to_string())));
+    }
+    out.extend(state.metadata.iter().map(|(k, v)| (Field::Metadata(k.clone()), Some(v.clone()))));
+    out
+}
+
+// Registers that differ between two states of an element
+fn changed_fields(before: &ElementState, after: &ElementState) -> Vec<(Field, Option<String>)> {
+    let mut out: Vec<(Field, Option<String>)> = fields_of(after)
+        .into_iter()
+        .filter(|(f, v)| fields_of(before).iter().all(|(bf, bv)| bf != f || bv != v))
+        .collect();
+    out.extend(before.metadata.keys().filter(|k| !after.metadata.contains_key(*k)).map(|k| (Field::Metadata(k.clone()), None)));
+    out
+}
+
+/// This peer's side of the replication: its clock and the stamps seen for every element.
+#[derive(Clone, Debug)]
+pub struct Replica {
+    id: String,
+    clock: u64,
+    // Newest change log event already turned into operations
+    seen_seq: u64,
+    entries: HashMap<Uuid, Entry>,
+    // Live relationships left out of the graph until both their ends exist
+    pending: BTreeSet<Uuid>,
+}
+
+impl Replica {
+    /// Start replicating `db`. Elements it already holds count as added at time zero.
+    pub fn new(id: impl Into<String>, db: &GraphDatabase) -> Replica {
+        let id = id.into();
+        let origin = Stamp { counter: 0, replica: id.clone() };
+        let mut entries = HashMap::new();
+        let states = db
+            .nodes
+            .values()
+            .map(|n| (n.id, Element::Node, ElementState::from(n)))
+            .chain(db.relationships.values().map(|r| (r.id, Element::Relationship, ElementState::from(r))));
+        for (eid, element, state) in states {
+            let mut entry = Entry::new(element);
+            entry.adds.insert(origin.clone());
+            entry.write_all(&state, &origin);
+            entries.insert(eid, entry);
+        }
+        Replica { id, clock: 0, seen_seq: db.changes().last_seq(), entries, pending: BTreeSet::new() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp { counter: self.clock, replica: self.id.clone() }
+    }
+
+    /// Operations that rebuild `db` on a peer: an addition per element (nodes first), then
+    /// the registers written after it. Relationships still waiting for an end come last, then
+    /// writes still waiting for their element's addition.
+    pub fn snapshot(&self, db: &GraphDatabase) -> Vec<SyncOp> {
+        let states = db
+            .nodes
+            .values()
+            .map(|n| (n.id, ElementState::from(n)))
+            .chain(db.relationships.values().map(|r| (r.id, ElementState::from(r))))
+            .chain(self.pending.iter().filter_map(|id| Some((*id, self.entries.get(id)?.state()))));
+        let mut out = Vec::new();
+        for (id, state) in states {
+            let Some(entry) = self.entries.get(&id) else { continue };
+            let Some(stamp) = entry.adds.iter().filter(|s| !entry.removed.contains(*s)).max().cloned() else { continue };
+            let later: Vec<SyncOp> = fields_of(&state)
+                .into_iter()
+                .filter_map(|(field, value)| {
+                    let at = entry.fields.get(&field).filter(|r| r.stamp > stamp)?.stamp.clone();
+                    Some(SyncOp::Set { element: entry.element, id, field, value, stamp: at })
+                })
+                .collect();
+            out.push(SyncOp::Add { element: entry.element, id, stamp, state });
+            out.extend(later);
+        }
+        for (id, entry) in self.entries.iter().filter(|(_, e)| e.adds.is_empty()) {
+            out.extend(entry.fields.iter().map(|(field, r)| SyncOp::Set { element: entry.element, id: *id, field: field.clone(), value: r.value.clone(), stamp: r.stamp.clone() }));
+        }
+        out
+    }
+
+    /// Operations for the writes made to `db` since the last call, leaving out those that
+    /// came from a peer.
+    pub fn local_ops(&mut self, db: &GraphDatabase) -> anyhow::Result<Vec<SyncOp>> {
+        if db.changes().last_seq() <= self.seen_seq { return Ok(Vec::new()); }
+        let events = db.changes().since(&Since::Seq(self.seen_seq))?;
+        let mut out = Vec::new();
+        for e in events {
+            self.seen_seq = self.seen_seq.max(e.seq);
+            if !e.actor.starts_with(PEER_ACTOR_PREFIX) { self.op_for(e, &mut out); }
+        }
+        Ok(out)
+    }
+
+    fn op_for(&mut self, e: ChangeEvent, out: &mut Vec<SyncOp>) {
+        match (e.op, e.before, e.after) {
+            (Operation::Create, _, Some(state)) => {
+                let stamp = self.tick();
+                let mut entry = Entry::new(e.element);
+                entry.adds.insert(stamp.clone());
+                entry.write_all(&state, &stamp);
+                self.entries.insert(e.id, entry);
+                out.push(SyncOp::Add { element: e.element, id: e.id, stamp, state });
+            }
+            (Operation::Update, Some(before), Some(after)) => {
+                for (field, value) in changed_fields(&before, &after) {
+                    let stamp = self.tick();
+                    let register = Register { stamp: stamp.clone(), value: value.clone() };
+                    self.entries.entry(e.id).or_insert_with(|| Entry::new(e.element)).fields.insert(field.clone(), register);
+                    out.push(SyncOp::Set { element: e.element, id: e.id, field, value, stamp });
+                }
+            }
+            (Operation::Delete, _, _) => {
+                let Some(entry) = self.entries.get(&e.id) else { return };
+                let observed: Vec<Stamp> = entry.adds.difference(&entry.removed).cloned().collect();
+                let stamp = self.tick();
+                if let Some(entry) = self.entries.get_mut(&e.id) { entry.removed.extend(observed.iter().cloned()); }
+                out.push(SyncOp::Remove { element: e.element, id: e.id, observed, stamp });
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply an operation from a peer. Returns whether it told this replica anything new;
+    /// only those are worth passing on to other peers.
+    pub fn apply(&mut self, db: &mut GraphDatabase, op: SyncOp) -> bool {
+        let (SyncOp::Add { stamp, .. } | SyncOp::Set { stamp, .. } | SyncOp::Remove { stamp, .. }) = &op;
+        self.clock = self.clock.max(stamp.counter);
+        changes::as_actor(format!("{}{}", PEER_ACTOR_PREFIX, stamp.replica), || self.apply_op(db, op))
+    }
+
+    fn apply_op(&mut self, db: &mut GraphDatabase, op: SyncOp) -> bool {
+        match op {
+            SyncOp::Add { element, id, stamp, state } => {
+                let entry = self.entries.entry(id).or_insert_with(|| Entry::new(element));
+                if !entry.adds.insert(stamp.clone()) { return false; }
+                let won: Vec<(Field, Option<String>)> = fields_of(&state).into_iter().filter(|(f, v)| entry.wins(f, &stamp, v)).collect();
+                if !entry.alive() { return true; }
+                if exists(db, element, id) {
+                    for (field, value) in won { self.write(db, element, id, &field, value); }
+                } else {
+                    self.place(db, id);
+                }
+                true
+            }
+            SyncOp::Set { element, id, field, value, stamp } => {
+                let entry = self.entries.entry(id).or_insert_with(|| Entry::new(element));
+                if !entry.wins(&field, &stamp, &value) { return false; }
+                if !entry.alive() { return true; }
+                if exists(db, element, id) { self.write(db, element, id, &field, value); } else { self.place(db, id); }
+                true
+            }
+            SyncOp::Remove { element, id, observed, .. } => {
+                let entry = self.entries.entry(id).or_insert_with(|| Entry::new(element));
+                let before = entry.removed.len();
+                entry.removed.extend(observed);
+                if entry.removed.len() == before { return false; }
+                if !entry.alive() {
+                    self.pending.remove(&id);
+                    match element {
+                        Element::Node => {
+                            // Its relationships go with it, but wait for it if a peer adds it again
+                            let cut: Vec<Uuid> = db.relationships.values().filter(|r| r.from_node == id || r.to_node == id).map(|r| r.id).collect();
+                            db.remove_node(id);
+                            self.pending.extend(cut.into_iter().filter(|r| self.entries.get(r).is_some_and(Entry::alive)));
+                        }
+                        Element::Relationship => { db.remove_relationship(id); }
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    // Put a live element that `db` lacks in from its registers; a relationship missing an end
+    // waits in `pending`, and a node arriving lets the relationships waiting for it in
+    fn place(&mut self, db: &mut GraphDatabase, id: Uuid) {
+        let Some(entry) = self.entries.get(&id) else { return };
+        let element = entry.element;
+        if !insert(db, element, id, entry.state()) {
+            self.pending.insert(id);
+            return;
+        }
+        self.pending.remove(&id);
+        if element == Element::Node {
+            for rid in self.pending.clone() {
+                let Some(rel) = self.entries.get(&rid) else { continue };
+                if insert(db, rel.element, rid, rel.state()) { self.pending.remove(&rid); }
+            }
+        }
+    }
+
+    // Write a register to the element in `db`; moving a relationship to a node that has not
+    // arrived yet takes it out until the node does
+    fn write(&mut self, db: &mut GraphDatabase, element: Element, id: Uuid, field: &Field, value: Option<String>) {
+        let missing_end = matches!((element, field), (Element::Relationship, Field::From | Field::To))
+            && value.as_deref().and_then(|v| Uuid::parse_str(v).ok()).is_none_or(|n| !db.nodes.contains_key(&n));
+        if missing_end {
+            db.remove_relationship(id);
+            self.pending.insert(id);
+        } else {
+            write_field(db, element, id, field, value);
+        }
+    }
+}
+
+fn exists(db: &GraphDatabase, element: Element, id: Uuid) -> bool {
+    match element {
+        Element::Node => db.nodes.contains_key(&id),
+        Element::Relationship => db.relationships.contains_key(&id),
+    }
+}
+
+// Create an element under its replicated ID. A relationship whose end is missing is left out;
+// returns whether the element was added.
+fn insert(db: &mut GraphDatabase, element: Element, id: Uuid, s: ElementState) -> bool {
+    let at = temporal::now();
+    let metadata = s.metadata.into_iter().collect();
+    match (element, s.from, s.to) {
+        (Element::Node, _, _) => {
+            db.nodes.insert(id, Node { id, label: s.label, metadata, description: s.description, created_at: at.clone(), updated_at: at });
+            db.invalidate_vocabulary();
+            db.record_node_change(id, None);
+            true
+        }
+        (Element::Relationship, Some(from_node), Some(to_node)) if db.nodes.contains_key(&from_node) && db.nodes.contains_key(&to_node) => {
+            let rel = Relationship { id, from_node, to_node, label: s.label, metadata, description: s.description, created_at: at.clone(), updated_at: at };
+            db.relationships.insert(id, rel);
+            db.invalidate_vocabulary();
+            db.record_relationship_change(id, None);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn write_field(db: &mut GraphDatabase, element: Element, id: Uuid, field: &Field, value: Option<String>) {
+    match (element, field, value) {
+        (Element::Node, Field::Label, Some(v)) => { db.update_node_label(id, v); }
+        (Element::Node, Field::Description, v) => { db.set_node_description(id, v.unwrap_or_default()); }
+        (Element::Node, Field::Metadata(k), Some(v)) => { db.upsert_node_metadata(id, k.clone(), v); }
+        (Element::Node, Field::Metadata(k), None) => { db.remove_node_metadata_key(id, k); }
+        (Element::Relationship, Field::Label, Some(v)) => { db.update_relationship_label(id, v); }
+        (Element::Relationship, Field::Description, v) => { db.set_relationship_description(id, v.unwrap_or_default()); }
+        (Element::Relationship, Field::Metadata(k), Some(v)) => { db.upsert_relationship_metadata(id, k.clone(), v); }
+        (Element::Relationship, Field::Metadata(k), None) => { db.remove_relationship_metadata_key(id, k); }
+        (Element::Relationship, end @ (Field::From | Field::To), Some(v)) => {
+            let Ok(node) = Uuid::parse_str(&v) else { return };
+            let before = db.relationships.get(&id).map(ElementState::from);
+            let Some(rel) = db.relationships.get_mut(&id) else { return };
+            if *end == Field::From { rel.from_node = node; } else { rel.to_node = node; }
+            db.touch_relationship(id);
+            db.record_relationship_change(id, before);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,189 @@
+//! Derived edges: relationships implied by metadata, such as a `SAME_PROJECT` edge between
+//! every two nodes with the same `project` value, to bring out the structure of imported
+//! tables. A rule either writes its edges into the graph, marked so a later [`refresh`] can
+//! replace them, or leaves them virtual for the canvas to draw. Nothing updates until asked.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::graph::{GraphDatabase, NodeId, Relationship};
+
+/// Metadata key marking a written edge with the key of its rule.
+pub const DERIVED_KEY: &str = "derived_from";
+/// Nodes sharing a value beyond this many are linked in a chain instead of every pair, so a
+/// common value like `status: open` does not bury the canvas in edges.
+pub const MAX_GROUP: usize = 50;
+
+/// Connect nodes whose `key` has the same value with `label` relationships.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedEdgeRule {
+    /// Label of the derived relationships; one rule per label
+    pub label: String,
+    pub key: String,
+    /// Only nodes with this label take part
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_label: Option<String>,
+    /// Write the edges into the graph instead of only drawing them
+    #[serde(default)]
+    pub materialize: bool,
+}
+
+impl DerivedEdgeRule {
+    pub fn new(label: &str, key: &str, node_label: Option<&str>, materialize: bool) -> Result<Self> {
+        let (label, key) = (label.trim(), key.trim());
+        if label.is_empty() { bail!("a derived edge rule needs a relationship label"); }
+        if key.is_empty() { bail!("a derived edge rule needs a metadata key"); }
+        let node_label = node_label.map(str::trim).filter(|l| !l.is_empty()).map(str::to_string);
+        Ok(DerivedEdgeRule { label: label.to_string(), key: key.to_string(), node_label, materialize })
+    }
+
+    /// Whether `rel` was written by this rule.
+    pub fn wrote(&self, rel: &Relationship) -> bool {
+        rel.label == self.label && rel.metadata.get(DERIVED_KEY) == Some(&self.key)
+    }
+}
+
+impl fmt::Display for DerivedEdgeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: same {}", self.label, self.key)?;
+        if let Some(l) = &self.node_label { write!(f, " on :{}", l)?; }
+        write!(f, " ({})", if self.materialize { "written" } else { "virtual" })
+    }
+}
+
+/// One edge a rule implies: `from` and `to` share `value`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivedEdge {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub value: String,
+}
+
+/// The edges `rule` implies in `db`, grouped by value in order. Nodes without the key or with
+/// a blank value are left out.
+pub fn derive(db: &GraphDatabase, rule: &DerivedEdgeRule) -> Vec<DerivedEdge> {
+    let mut groups: BTreeMap<&str, Vec<NodeId>> = BTreeMap::new();
+    for n in db.nodes.values() {
+        if rule.node_label.as_ref().is_some_and(|l| *l != n.label) { continue; }
+        let Some(value) = n.metadata.get(&rule.key).map(|v| v.trim()).filter(|v| !v.is_empty()) else { continue };
+        groups.entry(value).or_default().push(n.id);
+    }
+    let mut out = Vec::new();
+    for (value, mut ids) in groups {
+        ids.sort();
+        let edge = |from: NodeId, to: NodeId| DerivedEdge { from, to, value: value.to_string() };
+        if ids.len() > MAX_GROUP {
+            out.extend(ids.windows(2).map(|w| edge(w[0], w[1])));
+        } else {
+            for (i, a) in ids.iter().enumerate() {
+                out.extend(ids[i + 1..].iter().map(|b| edge(*a, *b)));
+            }
+        }
+    }
+    out
+}
+
+/// Relationships added and removed by a refresh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Refresh {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl Refresh {
+    pub fn changed(&self) -> bool {
+        self.added + self.removed > 0
+    }
+}
+
+impl std::ops::AddAssign for Refresh {
+    fn add_assign(&mut self, other: Refresh) {
+        self.added += other.added;
+        self.removed += other.removed;
+    }
+}
+
+impl fmt::Display for Refresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} added, {} removed", self.added, self.removed)
+    }
+}
+
+/// Bring the written edges of `rule` in line with the graph: edges whose nodes no longer
+/// share the value go and missing ones are added with the key and value on them.
+pub fn refresh_rule(db: &mut GraphDatabase, rule: &DerivedEdgeRule) -> Refresh {
+    let wanted = derive(db, rule);
+    let wanted_keys: HashSet<(NodeId, NodeId, &str)> = wanted.iter().map(|e| (e.from, e.to, e.value.as_str())).collect();
+    let mut existing: HashMap<(NodeId, NodeId, &str), Uuid> = HashMap::new();
+    let mut stale = Vec::new();
+    for r in db.relationships.values().filter(|r| rule.wrote(r)) {
+        let key = (r.from_node, r.to_node, r.metadata.get(&rule.key).map_or("", |v| v.as_str()));
+        // A second copy of the same edge is stale too
+        if !wanted_keys.contains(&key) || existing.contains_key(&key) {
+            stale.push(r.id);
+        } else {
+            existing.insert(key, r.id);
+        }
+    }
+    let missing: Vec<DerivedEdge> = wanted.iter().filter(|e| !existing.contains_key(&(e.from, e.to, e.value.as_str()))).cloned().collect();
+    let mut out = Refresh::default();
+    for id in stale {
+        if db.remove_relationship(id) { out.removed += 1; }
+    }
+    for e in missing {
+        let metadata = HashMap::from([(DERIVED_KEY.to_string(), rule.key.clone()), (rule.key.clone(), e.value)]);
+        if db.add_relationship(e.from, e.to, rule.label.clone(), metadata).is_some() { out.added += 1; }
+    }
+    out
+}
+
+/// Refresh the written edges of every rule that writes them.
+pub fn refresh(db: &mut GraphDatabase) -> Refresh {
+    let mut out = Refresh::default();
+    for rule in db.derived_edges.clone().iter().filter(|r| r.materialize) { out += refresh_rule(db, rule); }
+    out
+}
+
+/// Remove the edges `rule` wrote; returns how many.
+pub fn clear(db: &mut GraphDatabase, rule: &DerivedEdgeRule) -> usize {
+    let ids: Vec<Uuid> = db.relationships.values().filter(|r| rule.wrote(r)).map(|r| r.id).collect();
+    ids.into_iter().filter(|id| db.remove_relationship(*id)).count()
+}
+
+/// Add or replace the rule for its label and bring its edges up to date: edges written under
+/// another key or by a rule that now only draws them are removed.
+pub fn set_rule(db: &mut GraphDatabase, rule: DerivedEdgeRule) -> Refresh {
+    let mut out = Refresh::default();
+    if let Some(old) = db.derived_edges.iter().find(|r| r.label == rule.label).cloned()
+        && (old.key != rule.key || !rule.materialize)
+    {
+        out.removed = clear(db, &old);
+    }
+    db.set_derived_edge_rule(rule.clone());
+    if rule.materialize { out += refresh_rule(db, &rule); }
+    out
+}
+
+/// Drop the rule for `label` and the edges it wrote; `None` when there is no such rule.
+pub fn remove_rule(db: &mut GraphDatabase, label: &str) -> Option<usize> {
+    let rule = db.remove_derived_edge_rule(label)?;
+    Some(clear(db, &rule))
+}
+
+/// Edges of the rules that only draw them, with the label of their rule.
+pub fn virtual_edges(db: &GraphDatabase) -> Vec<(String, DerivedEdge)> {
+    db.derived_edges
+        .iter()
+        .filter(|r| !r.materialize)
+        .flat_map(|r| derive(db, r).into_iter().map(|e| (r.label.clone(), e)))
+        .collect()
+}
+
+/// One line per rule, e.g. `SAME_PROJECT: same project on :Task (virtual)`.
+pub fn describe(rules: &[DerivedEdgeRule]) -> Vec<String> {
+    rules.iter().map(|r| r.to_string()).collect()
+}
@@ -0,0 +1,208 @@
+//! Synthetic graphs for benchmarking layouts and demos: Erdős–Rényi, Barabási–Albert, grids
+//! and trees. Generation is deterministic for a seed. Every node gets a `name` (`n0`, `n1`,
+//! ...) and a label drawn from a weighted mix, independently of the topology, so changing
+//! the mix does not change the shape.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use super::graph::{GraphDatabase, NodeId};
+
+/// Topology to generate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Model {
+    /// Every pair of nodes is linked with probability `p`
+    ErdosRenyi { nodes: usize, p: f64 },
+    /// Preferential attachment: each new node links to `edges_per_node` existing nodes,
+    /// favoring well-connected ones
+    BarabasiAlbert { nodes: usize, edges_per_node: usize },
+    /// `rows` x `cols` lattice linked to the right and downwards
+    Grid { rows: usize, cols: usize },
+    /// Complete tree with `branching` children per node, `depth` levels below the root
+    Tree { depth: usize, branching: usize },
+}
+
+impl Model {
+    /// Each model with small default sizes, in the order offered by the GUI.
+    pub const ALL: [Model; 4] = [
+        Model::ErdosRenyi { nodes: 100, p: 0.03 },
+        Model::BarabasiAlbert { nodes: 100, edges_per_node: 2 },
+        Model::Grid { rows: 10, cols: 10 },
+        Model::Tree { depth: 4, branching: 3 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::ErdosRenyi { .. } => "Random (Erdős–Rényi)",
+            Model::BarabasiAlbert { .. } => "Scale-free (Barabási–Albert)",
+            Model::Grid { .. } => "Grid",
+            Model::Tree { .. } => "Tree",
+        }
+    }
+
+    /// Number of nodes the model produces.
+    pub fn node_count(&self) -> usize {
+        match *self {
+            Model::ErdosRenyi { nodes, .. } | Model::BarabasiAlbert { nodes, .. } => nodes,
+            Model::Grid { rows, cols } => rows.saturating_mul(cols),
+            Model::Tree { depth, branching } => {
+                let mut total = 1usize;
+                let mut level = 1usize;
+                for _ in 0..depth {
+                    level = level.saturating_mul(branching);
+                    total = total.saturating_add(level);
+                }
+                total
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generator {
+    pub model: Model,
+    /// Node labels with relative weights
+    pub labels: Vec<(String, u32)>,
+    /// Label of every generated relationship
+    pub relationship: String,
+    pub seed: u64,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator {
+            model: Model::ALL[0],
+            labels: vec![("Node".to_string(), 1)],
+            relationship: "LINKS".to_string(),
+            seed: 42,
+        }
+    }
+}
+
+/// Parse a label mix such as `Person:3, Company:1, City`; a label without a weight counts once.
+pub fn parse_label_mix(text: &str) -> Result<Vec<(String, u32)>> {
+    let mut out = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (label, weight) = match part.rsplit_once(':') {
+            Some((l, w)) => (l.trim(), w.trim().parse::<u32>().map_err(|_| anyhow!("invalid weight in '{}'", part))?),
+            None => (part, 1),
+        };
+        if label.is_empty() { return Err(anyhow!("missing label in '{}'", part)); }
+        if weight > 0 { out.push((label.to_string(), weight)); }
+    }
+    if out.is_empty() { return Err(anyhow!("at least one label with a positive weight is required")); }
+    Ok(out)
+}
+
+impl Generator {
+    pub fn generate(&self) -> GraphDatabase {
+        let mut db = GraphDatabase::new();
+        self.generate_into(&mut db);
+        db
+    }
+
+    /// Add the generated nodes and relationships to `db`, returning the new node ids.
+    pub fn generate_into(&self, db: &mut GraphDatabase) -> Vec<NodeId> {
+        let mut rng = Rng::new(self.seed);
+        let mut label_rng = Rng::new(self.seed ^ 0x5EED_1ABE_1000_0000);
+        let total: u64 = self.labels.iter().map(|(_, w)| *w as u64).sum();
+        let mut pick_label = || -> String {
+            if self.labels.len() < 2 || total == 0 {
+                return self.labels.first().map(|(l, _)| l.clone()).unwrap_or_else(|| "Node".to_string());
+            }
+            let mut roll = label_rng.next_u64() % total;
+            for (label, w) in &self.labels {
+                if roll < *w as u64 { return label.clone(); }
+                roll -= *w as u64;
+            }
+            self.labels[0].0.clone()
+        };
+        let count = self.model.node_count();
+        let ids: Vec<NodeId> = (0..count)
+            .map(|i| db.add_node(pick_label(), HashMap::from([("name".to_string(), format!("n{}", i))])))
+            .collect();
+        let link = |db: &mut GraphDatabase, a: usize, b: usize| {
+            db.add_relationship(ids[a], ids[b], self.relationship.clone(), HashMap::new());
+        };
+        match self.model {
+            Model::ErdosRenyi { nodes, p } => {
+                let p = p.clamp(0.0, 1.0);
+                for i in 0..nodes {
+                    for j in i + 1..nodes {
+                        if rng.unit() < p { link(db, i, j); }
+                    }
+                }
+            }
+            Model::BarabasiAlbert { nodes, edges_per_node } => {
+                let m = edges_per_node.max(1);
+                // Each endpoint appears once per incident edge, so uniform picks are degree-weighted
+                let mut endpoints: Vec<usize> = Vec::new();
+                let seed_size = (m + 1).min(nodes);
+                for i in 0..nodes {
+                    if i < seed_size {
+                        for j in 0..i {
+                            link(db, i, j);
+                            endpoints.push(i);
+                            endpoints.push(j);
+                        }
+                        continue;
+                    }
+                    let mut targets: Vec<usize> = Vec::with_capacity(m);
+                    while targets.len() < m.min(i) {
+                        let t = if endpoints.is_empty() { rng.below(i) } else { endpoints[rng.below(endpoints.len())] };
+                        if !targets.contains(&t) { targets.push(t); }
+                    }
+                    for t in targets {
+                        link(db, i, t);
+                        endpoints.push(i);
+                        endpoints.push(t);
+                    }
+                }
+            }
+            Model::Grid { rows, cols } => {
+                for r in 0..rows {
+                    for c in 0..cols {
+                        let i = r * cols + c;
+                        if c + 1 < cols { link(db, i, i + 1); }
+                        if r + 1 < rows { link(db, i, i + cols); }
+                    }
+                }
+            }
+            Model::Tree { branching, .. } => {
+                // Breadth-first numbering: the children of node i are i*b+1 ..= i*b+b
+                for child in 1..count {
+                    link(db, (child - 1) / branching.max(1), child);
+                }
+            }
+        }
+        ids
+    }
+}
+
+// Small deterministic PRNG (xorshift64*) so generated graphs are reproducible from a seed
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        if n == 0 { 0 } else { (self.next_u64() % n as u64) as usize }
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,796 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::OnceLock;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+use super::changes::{ChangeLog, Element, ElementState};
+use super::computed::ComputedProperty;
+use super::derived::DerivedEdgeRule;
+use super::validation::{self, PropertyRule, RuleViolation};
+use crate::gql::views::GraphView;
+use crate::persistence::feeds::Feed;
+
+// Basic type aliases for clarity
+pub type NodeId = Uuid;
+type Key = String;
+type Value = String;
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub id: NodeId,
+    pub label: String,
+    pub metadata: HashMap<Key, Value>,
+    // Free-form notes, kept apart from metadata so queries and imports leave them alone
+    #[serde(default)]
+    pub description: String,
+    // Maintained by GraphDatabase (UTC ISO-8601); empty for elements saved before they existed
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Relationship {
+    pub id: Uuid,
+    pub from_node: NodeId,
+    pub to_node: NodeId,
+    pub label: String,
+    pub metadata: HashMap<Key, Value>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+// Timestamp for created_at / updated_at
+fn now() -> String {
+    crate::gql::temporal::now()
+}
+
+/// `text` on one line, cut to at most `max_chars` characters with an ellipsis, for tooltips
+/// and lists that show the start of a description.
+pub fn text_preview(text: &str, max_chars: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= max_chars { return flat; }
+    let mut out: String = flat.chars().take(max_chars.saturating_sub(1)).collect();
+    out.truncate(out.trim_end().len());
+    out.push('…');
+    out
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraphDatabase {
+    pub nodes: HashMap<NodeId, Node>,
+    pub relationships: HashMap<Uuid, Relationship>,
+    // Relationship labels limited to one edge per (from, to) pair
+    #[serde(default)]
+    pub unique_relationship_labels: BTreeSet<String>,
+    // Properties of the graph itself (title, description, owner and custom keys)
+    #[serde(default)]
+    pub metadata: BTreeMap<Key, Value>,
+    // Rules on node metadata per label, enforced on writes (see `validation`)
+    #[serde(default)]
+    pub property_rules: BTreeMap<String, Vec<PropertyRule>>,
+    // Node metadata keys worked out from formulas per label (see `computed`)
+    #[serde(default)]
+    pub computed_properties: BTreeMap<String, Vec<ComputedProperty>>,
+    // Relationships implied by equal metadata values (see `derived`)
+    #[serde(default)]
+    pub derived_edges: Vec<DerivedEdgeRule>,
+    // Named read-only queries shown as canvas tabs and exported on their own (see `gql::views`)
+    #[serde(default)]
+    pub views: Vec<GraphView>,
+    // JSON APIs imported into this graph (see `persistence::feeds`)
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+    // Built on first use and kept current by the methods below; code that edits `nodes` or
+    // `relationships` directly calls `invalidate_vocabulary` afterwards
+    #[serde(skip)]
+    vocabulary: OnceLock<Vocabulary>,
+    // Writes made through the methods below (see `changes`); code that edits `nodes` or
+    // `relationships` directly records them with `record_node_change` and friends
+    #[serde(default)]
+    changes: ChangeLog,
+}
+
+/// Distinct node labels, relationship labels and metadata keys in a graph, for autocomplete.
+/// Each name is counted by the elements using it, so removals can drop names no longer used.
+/// Serializes as the catalog served at `/v1/catalog`: each name with its count.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Vocabulary {
+    #[serde(rename = "labels")]
+    node_labels: BTreeMap<String, usize>,
+    #[serde(rename = "relationship_types")]
+    relationship_labels: BTreeMap<String, usize>,
+    #[serde(rename = "node_property_keys")]
+    node_keys: BTreeMap<String, usize>,
+    #[serde(rename = "relationship_property_keys")]
+    relationship_keys: BTreeMap<String, usize>,
+}
+
+fn count_up(names: &mut BTreeMap<String, usize>, name: &str) {
+    if name.is_empty() { return; }
+    match names.get_mut(name) {
+        Some(n) => *n += 1,
+        None => { names.insert(name.to_string(), 1); }
+    }
+}
+
+fn count_down(names: &mut BTreeMap<String, usize>, name: &str) {
+    if let Some(n) = names.get_mut(name) {
+        *n -= 1;
+        if *n == 0 { names.remove(name); }
+    }
+}
+
+impl Vocabulary {
+    fn build(db: &GraphDatabase) -> Self {
+        let mut v = Vocabulary::default();
+        for n in db.nodes.values() { v.add_node(n); }
+        for r in db.relationships.values() { v.add_relationship(r); }
+        v
+    }
+
+    fn add_node(&mut self, n: &Node) {
+        count_up(&mut self.node_labels, &n.label);
+        for k in n.metadata.keys() { count_up(&mut self.node_keys, k); }
+    }
+
+    fn remove_node(&mut self, n: &Node) {
+        count_down(&mut self.node_labels, &n.label);
+        for k in n.metadata.keys() { count_down(&mut self.node_keys, k); }
+    }
+
+    fn add_relationship(&mut self, r: &Relationship) {
+        count_up(&mut self.relationship_labels, &r.label);
+        for k in r.metadata.keys() { count_up(&mut self.relationship_keys, k); }
+    }
+
+    fn remove_relationship(&mut self, r: &Relationship) {
+        count_down(&mut self.relationship_labels, &r.label);
+        for k in r.metadata.keys() { count_down(&mut self.relationship_keys, k); }
+    }
+
+    /// Node labels in use, sorted.
+    pub fn node_labels(&self) -> impl Iterator<Item = &str> {
+        self.node_labels.keys().map(String::as_str)
+    }
+
+    pub fn relationship_labels(&self) -> impl Iterator<Item = &str> {
+        self.relationship_labels.keys().map(String::as_str)
+    }
+
+    pub fn node_keys(&self) -> impl Iterator<Item = &str> {
+        self.node_keys.keys().map(String::as_str)
+    }
+
+    pub fn relationship_keys(&self) -> impl Iterator<Item = &str> {
+        self.relationship_keys.keys().map(String::as_str)
+    }
+}
+
+/// Graph metadata keys the Graph Properties dialog always offers.
+pub const GRAPH_METADATA_KEYS: [&str; 3] = ["title", "description", "owner"];
+
+/// Relationships that share a label and a (from, to) pair although the label is unique.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniquenessViolation {
+    pub label: String,
+    pub from: NodeId,
+    pub to: NodeId,
+    // Oldest first (ids are time-ordered)
+    pub relationships: Vec<Uuid>,
+}
+
+impl GraphDatabase {
+    // Instantiate a new, empty graph database
+    pub fn new() -> Self {
+        GraphDatabase {
+            nodes: HashMap::new(),
+            relationships: HashMap::new(),
+            unique_relationship_labels: BTreeSet::new(),
+            metadata: BTreeMap::new(),
+            property_rules: BTreeMap::new(),
+            computed_properties: BTreeMap::new(),
+            derived_edges: Vec::new(),
+            views: Vec::new(),
+            feeds: Vec::new(),
+            vocabulary: OnceLock::new(),
+            changes: ChangeLog::default(),
+        }
+    }
+
+    /// Labels and metadata keys in use. The first call scans the graph; after that the
+    /// mutation methods keep it current, so lookups cost nothing at any graph size.
+    pub fn vocabulary(&self) -> &Vocabulary {
+        self.vocabulary.get_or_init(|| Vocabulary::build(self))
+    }
+
+    // The vocabulary if something has built it, for measuring without building it
+    pub(crate) fn built_vocabulary(&self) -> Option<&Vocabulary> {
+        self.vocabulary.get()
+    }
+
+    /// Node labels in use, sorted.
+    pub fn labels(&self) -> Vec<String> {
+        self.vocabulary().node_labels().map(str::to_string).collect()
+    }
+
+    /// Relationship labels in use, sorted.
+    pub fn relationship_types(&self) -> Vec<String> {
+        self.vocabulary().relationship_labels().map(str::to_string).collect()
+    }
+
+    /// Metadata keys used on nodes or relationships, sorted.
+    pub fn property_keys(&self) -> Vec<String> {
+        let v = self.vocabulary();
+        let keys: BTreeSet<&str> = v.node_keys().chain(v.relationship_keys()).collect();
+        keys.into_iter().map(str::to_string).collect()
+    }
+
+    /// Forget the vocabulary after `nodes` or `relationships` were edited directly; it is
+    /// rebuilt on next use.
+    pub fn invalidate_vocabulary(&mut self) {
+        self.vocabulary = OnceLock::new();
+    }
+
+    /// Writes to this graph, for `CALL db.changes` and the change log file.
+    pub fn changes(&self) -> &ChangeLog {
+        &self.changes
+    }
+
+    pub fn changes_mut(&mut self) -> &mut ChangeLog {
+        &mut self.changes
+    }
+
+    fn node_state(&self, id: NodeId) -> Option<ElementState> {
+        self.nodes.get(&id).map(ElementState::from)
+    }
+
+    fn rel_state(&self, id: Uuid) -> Option<ElementState> {
+        self.relationships.get(&id).map(ElementState::from)
+    }
+
+    /// Log a change to node `id` made by editing `nodes` directly, given its state before.
+    pub fn record_node_change(&mut self, id: NodeId, before: Option<ElementState>) {
+        let after = self.node_state(id);
+        self.changes.record(Element::Node, id, before, after);
+    }
+
+    pub fn record_relationship_change(&mut self, id: Uuid, before: Option<ElementState>) {
+        let after = self.rel_state(id);
+        self.changes.record(Element::Relationship, id, before, after);
+    }
+
+    /// Put `other` in place of this graph, logging the difference as changes. The change log
+    /// itself stays, so undo, reloads and new graphs read as edits in one history.
+    pub fn replace_with(&mut self, other: GraphDatabase) {
+        let mut changes = std::mem::take(&mut self.changes);
+        let old = std::mem::replace(self, other);
+        changes.record_diff(&old, self);
+        self.changes = changes;
+    }
+
+    /// Run the write `f`, then `check` the graph against a copy taken before it. When either
+    /// fails the graph is put back as it was and the error returned, so a query failing partway
+    /// leaves nothing behind.
+    pub fn with_rollback<T>(
+        &mut self,
+        f: impl FnOnce(&mut GraphDatabase) -> anyhow::Result<T>,
+        check: impl FnOnce(&GraphDatabase, &GraphDatabase) -> anyhow::Result<()>,
+    ) -> anyhow::Result<T> {
+        let before = self.clone();
+        let out = f(self).and_then(|out| check(&before, self).map(|_| out));
+        if out.is_err() { *self = before; }
+        out
+    }
+
+    // Add a node and return its new ID
+    pub fn add_node(&mut self, label: String, metadata: HashMap<Key, Value>) -> NodeId {
+        let id = Uuid::now_v7();
+        let at = now();
+        let node = Node { id, label, metadata, description: String::new(), created_at: at.clone(), updated_at: at };
+        if let Some(v) = self.vocabulary.get_mut() { v.add_node(&node); }
+        self.nodes.insert(id, node);
+        self.record_node_change(id, None);
+        id
+    }
+
+    // Add a relationship if both ends exist; returns the relationship ID. For a unique label
+    // an existing edge between the same pair in the same direction is returned instead.
+    pub fn add_relationship(
+        &mut self,
+        from_node: NodeId,
+        to_node: NodeId,
+        label: String,
+        metadata: HashMap<Key, Value>,
+    ) -> Option<Uuid> {
+        if self.nodes.contains_key(&from_node) && self.nodes.contains_key(&to_node) {
+            if self.unique_relationship_labels.contains(&label)
+                && let Some(existing) = self.find_relationship(from_node, to_node, &label)
+            {
+                return Some(existing);
+            }
+            let id = Uuid::now_v7();
+            let at = now();
+            let relationship = Relationship { id, from_node, to_node, label, metadata, description: String::new(), created_at: at.clone(), updated_at: at };
+            if let Some(v) = self.vocabulary.get_mut() { v.add_relationship(&relationship); }
+            self.relationships.insert(id, relationship);
+            self.record_relationship_change(id, None);
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// The oldest relationship with `label` from `from_node` to `to_node`, if any.
+    pub fn find_relationship(&self, from_node: NodeId, to_node: NodeId, label: &str) -> Option<Uuid> {
+        self.relationships
+            .values()
+            .filter(|r| r.from_node == from_node && r.to_node == to_node && r.label == label)
+            .map(|r| r.id)
+            .min()
+    }
+
+    /// Allow at most one `label` edge per node pair and direction (or lift that limit).
+    /// Existing duplicates are kept; `uniqueness_violations` reports them.
+    pub fn set_relationship_unique(&mut self, label: &str, unique: bool) {
+        if unique {
+            self.unique_relationship_labels.insert(label.to_string());
+        } else {
+            self.unique_relationship_labels.remove(label);
+        }
+    }
+
+    pub fn is_relationship_unique(&self, label: &str) -> bool {
+        self.unique_relationship_labels.contains(label)
+    }
+
+    /// Duplicate edges for every unique label, sorted by label and endpoints.
+    pub fn uniqueness_violations(&self) -> Vec<UniquenessViolation> {
+        let mut groups: HashMap<(&str, NodeId, NodeId), Vec<Uuid>> = HashMap::new();
+        for r in self.relationships.values() {
+            if self.unique_relationship_labels.contains(&r.label) {
+                groups.entry((r.label.as_str(), r.from_node, r.to_node)).or_default().push(r.id);
+            }
+        }
+        let mut out: Vec<UniquenessViolation> = groups
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|((label, from, to), mut ids)| {
+                ids.sort();
+                UniquenessViolation { label: label.to_string(), from, to, relationships: ids }
+            })
+            .collect();
+        out.sort_by(|a, b| (&a.label, a.from, a.to).cmp(&(&b.label, b.from, b.to)));
+        out
+    }
+
+    /// Add or replace the rule for `rule.key` on nodes labeled `label`. Nodes that already
+    /// break it are kept; `rule_violations` reports them.
+    pub fn set_property_rule(&mut self, label: &str, rule: PropertyRule) {
+        let rules = self.property_rules.entry(label.to_string()).or_default();
+        match rules.iter_mut().find(|r| r.key == rule.key) {
+            Some(r) => *r = rule,
+            None => rules.push(rule),
+        }
+    }
+
+    /// Drop the rule for `key` on `label`; returns whether there was one.
+    pub fn remove_property_rule(&mut self, label: &str, key: &str) -> bool {
+        let Some(rules) = self.property_rules.get_mut(label) else { return false };
+        let before = rules.len();
+        rules.retain(|r| r.key != key);
+        let removed = rules.len() < before;
+        if rules.is_empty() { self.property_rules.remove(label); }
+        removed
+    }
+
+    /// Compute `prop.key` on nodes labeled `label` (replacing an earlier formula for it). The
+    /// values appear at the next `computed::refresh`.
+    pub fn set_computed_property(&mut self, label: &str, prop: ComputedProperty) {
+        let props = self.computed_properties.entry(label.to_string()).or_default();
+        match props.iter_mut().find(|p| p.key == prop.key) {
+            Some(p) => *p = prop,
+            None => props.push(prop),
+        }
+    }
+
+    /// Stop computing `key` on `label`; the last values stay as plain metadata. Returns
+    /// whether it was computed.
+    pub fn remove_computed_property(&mut self, label: &str, key: &str) -> bool {
+        let Some(props) = self.computed_properties.get_mut(label) else { return false };
+        let before = props.len();
+        props.retain(|p| p.key != key);
+        let removed = props.len() < before;
+        if props.is_empty() { self.computed_properties.remove(label); }
+        removed
+    }
+
+    /// Add a derived edge rule, replacing the one for the same relationship label. Its edges
+    /// appear at the next `derived::refresh`.
+    pub fn set_derived_edge_rule(&mut self, rule: DerivedEdgeRule) {
+        match self.derived_edges.iter_mut().find(|r| r.label == rule.label) {
+            Some(r) => *r = rule,
+            None => self.derived_edges.push(rule),
+        }
+    }
+
+    /// Drop the derived edge rule for `label`, returning it; edges it wrote stay until removed
+    /// with `derived::clear`.
+    pub fn remove_derived_edge_rule(&mut self, label: &str) -> Option<DerivedEdgeRule> {
+        let i = self.derived_edges.iter().position(|r| r.label == label)?;
+        Some(self.derived_edges.remove(i))
+    }
+
+    /// Add a view, replacing the one with the same name.
+    pub fn set_view(&mut self, view: GraphView) {
+        match self.views.iter_mut().find(|v| v.name == view.name) {
+            Some(v) => *v = view,
+            None => self.views.push(view),
+        }
+    }
+
+    /// Drop the view named `name`; returns whether there was one.
+    pub fn remove_view(&mut self, name: &str) -> bool {
+        let before = self.views.len();
+        self.views.retain(|v| v.name != name);
+        self.views.len() < before
+    }
+
+    /// Refuse a node with `label` and `metadata` that would break a property rule, listing
+    /// the broken rules in the error.
+    pub fn check_node(&self, label: &str, metadata: &HashMap<Key, Value>) -> anyhow::Result<()> {
+        let rules = self.property_rules.get(label).map(Vec::as_slice).unwrap_or_default();
+        let broken: Vec<String> = validation::problems(rules, metadata).into_iter().map(|(k, m)| format!("{} {}", k, m)).collect();
+        if broken.is_empty() { return Ok(()); }
+        Err(anyhow::anyhow!("(:{}) {}", label, broken.join("; ")))
+    }
+
+    /// Nodes breaking a property rule, sorted by label, node and key.
+    pub fn rule_violations(&self) -> Vec<RuleViolation> {
+        validation::violations(self, self.nodes.values())
+    }
+
+    pub fn update_node_label(&mut self, id: NodeId, new_label: String) -> bool {
+        let before = self.node_state(id);
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() {
+                count_down(&mut v.node_labels, &node.label);
+                count_up(&mut v.node_labels, &new_label);
+            }
+            node.label = new_label;
+            node.updated_at = now();
+            self.record_node_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn set_node_metadata(&mut self, id: NodeId, new_metadata: HashMap<Key, Value>) -> bool {
+        let before = self.node_state(id);
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() {
+                for k in node.metadata.keys() { count_down(&mut v.node_keys, k); }
+                for k in new_metadata.keys() { count_up(&mut v.node_keys, k); }
+            }
+            node.metadata = new_metadata;
+            node.updated_at = now();
+            self.record_node_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn set_node_description(&mut self, id: NodeId, description: String) -> bool {
+        let before = self.node_state(id);
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if node.description != description {
+                node.description = description;
+                node.updated_at = now();
+            }
+            self.record_node_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn upsert_node_metadata(&mut self, id: NodeId, key: String, value: String) -> bool {
+        let before = self.node_state(id);
+        if let Some(node) = self.nodes.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() && !node.metadata.contains_key(&key) { count_up(&mut v.node_keys, &key); }
+            node.metadata.insert(key, value);
+            node.updated_at = now();
+            self.record_node_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove_node_metadata_key(&mut self, id: NodeId, key: &str) -> bool {
+        let before = self.node_state(id);
+        if let Some(node) = self.nodes.get_mut(&id) {
+            let removed = node.metadata.remove(key).is_some();
+            if removed {
+                node.updated_at = now();
+                if let Some(v) = self.vocabulary.get_mut() { count_down(&mut v.node_keys, key); }
+            }
+            self.record_node_change(id, before);
+            removed
+        } else {
+            false
+        }
+    }
+
+    pub fn update_relationship_label(&mut self, id: Uuid, new_label: String) -> bool {
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() {
+                count_down(&mut v.relationship_labels, &rel.label);
+                count_up(&mut v.relationship_labels, &new_label);
+            }
+            rel.label = new_label;
+            rel.updated_at = now();
+            self.record_relationship_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move relationship `id` onto new endpoints (swap them to reverse it), keeping its id,
+    /// label and metadata. Both nodes must exist, and for a unique label no other edge may
+    /// already join them in that direction.
+    pub fn set_relationship_endpoints(&mut self, id: Uuid, from_node: NodeId, to_node: NodeId) -> anyhow::Result<()> {
+        let Some(rel) = self.relationships.get(&id) else { anyhow::bail!("no relationship {}", id) };
+        for end in [from_node, to_node] {
+            if !self.nodes.contains_key(&end) { anyhow::bail!("no node {}", end); }
+        }
+        if self.unique_relationship_labels.contains(&rel.label)
+            && let Some(other) = self.find_relationship(from_node, to_node, &rel.label).filter(|other| *other != id)
+        {
+            anyhow::bail!("[:{}] is unique and relationship {} already joins these nodes", rel.label, other);
+        }
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) && (rel.from_node, rel.to_node) != (from_node, to_node) {
+            rel.from_node = from_node;
+            rel.to_node = to_node;
+            rel.updated_at = now();
+        }
+        self.record_relationship_change(id, before);
+        Ok(())
+    }
+
+    pub fn set_relationship_metadata(&mut self, id: Uuid, new_metadata: HashMap<Key, Value>) -> bool {
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() {
+                for k in rel.metadata.keys() { count_down(&mut v.relationship_keys, k); }
+                for k in new_metadata.keys() { count_up(&mut v.relationship_keys, k); }
+            }
+            rel.metadata = new_metadata;
+            rel.updated_at = now();
+            self.record_relationship_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn set_relationship_description(&mut self, id: Uuid, description: String) -> bool {
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) {
+            if rel.description != description {
+                rel.description = description;
+                rel.updated_at = now();
+            }
+            self.record_relationship_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn upsert_relationship_metadata(&mut self, id: Uuid, key: String, value: String) -> bool {
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) {
+            if let Some(v) = self.vocabulary.get_mut() && !rel.metadata.contains_key(&key) { count_up(&mut v.relationship_keys, &key); }
+            rel.metadata.insert(key, value);
+            rel.updated_at = now();
+            self.record_relationship_change(id, before);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove_relationship_metadata_key(&mut self, id: Uuid, key: &str) -> bool {
+        let before = self.rel_state(id);
+        if let Some(rel) = self.relationships.get_mut(&id) {
+            let removed = rel.metadata.remove(key).is_some();
+            if removed {
+                rel.updated_at = now();
+                if let Some(v) = self.vocabulary.get_mut() { count_down(&mut v.relationship_keys, key); }
+            }
+            self.record_relationship_change(id, before);
+            removed
+        } else {
+            false
+        }
+    }
+
+    /// Stamp `updated_at` on a node changed outside the methods above (e.g. by an import merge).
+    pub fn touch_node(&mut self, id: NodeId) {
+        if let Some(node) = self.nodes.get_mut(&id) { node.updated_at = now(); }
+    }
+
+    pub fn touch_relationship(&mut self, id: Uuid) {
+        if let Some(rel) = self.relationships.get_mut(&id) { rel.updated_at = now(); }
+    }
+
+    // Delete operations
+    pub fn remove_relationship(&mut self, id: Uuid) -> bool {
+        let Some(rel) = self.relationships.remove(&id) else { return false };
+        if let Some(v) = self.vocabulary.get_mut() { v.remove_relationship(&rel); }
+        self.changes.record(Element::Relationship, id, Some((&rel).into()), None);
+        true
+    }
+
+    pub fn remove_node(&mut self, id: NodeId) -> bool {
+        if let Some(node) = self.nodes.remove(&id) {
+            if let Some(v) = self.vocabulary.get_mut() { v.remove_node(&node); }
+            // Cascade delete relationships involving this node
+            let to_remove: Vec<Uuid> = self
+                .relationships
+                .iter()
+                .filter_map(|(rid, rel)| {
+                    if rel.from_node == id || rel.to_node == id { Some(*rid) } else { None }
+                })
+                .collect();
+            for rid in to_remove {
+                self.remove_relationship(rid);
+            }
+            self.changes.record(Element::Node, id, Some((&node).into()), None);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Delete every node and relationship; graph properties and constraints stay.
+    pub fn clear_elements(&mut self) {
+        let rels: Vec<Uuid> = self.relationships.keys().copied().collect();
+        for id in rels { self.remove_relationship(id); }
+        let nodes: Vec<NodeId> = self.nodes.keys().copied().collect();
+        for id in nodes { self.remove_node(id); }
+    }
+
+    /// Relationships with both endpoints in `nodes`.
+    pub fn relationships_within(&self, nodes: &HashSet<NodeId>) -> Vec<Uuid> {
+        self.relationships
+            .values()
+            .filter(|r| nodes.contains(&r.from_node) && nodes.contains(&r.to_node))
+            .map(|r| r.id)
+            .collect()
+    }
+
+    /// Relationships from or to `id`, self-loops once, by label.
+    pub fn incident_relationships(&self, id: NodeId) -> Vec<&Relationship> {
+        let mut out: Vec<&Relationship> = self.relationships.values().filter(|r| r.from_node == id || r.to_node == id).collect();
+        out.sort_by(|a, b| (a.label.as_str(), a.id).cmp(&(b.label.as_str(), b.id)));
+        out
+    }
+
+    /// A copy holding `nodes`, `relationships` and the endpoints of those relationships, with
+    /// the graph properties and constraints. Feeds and the change log are left behind.
+    pub fn subgraph(&self, nodes: &HashSet<NodeId>, relationships: &HashSet<Uuid>) -> GraphDatabase {
+        let mut out = GraphDatabase::new();
+        out.relationships = relationships.iter().filter_map(|id| self.relationships.get(id)).map(|r| (r.id, r.clone())).collect();
+        let endpoints = out.relationships.values().flat_map(|r| [r.from_node, r.to_node]);
+        out.nodes = nodes.iter().copied().chain(endpoints).filter_map(|id| self.nodes.get(&id)).map(|n| (n.id, n.clone())).collect();
+        out.unique_relationship_labels = self.unique_relationship_labels.clone();
+        out.metadata = self.metadata.clone();
+        out.property_rules = self.property_rules.clone();
+        out.computed_properties = self.computed_properties.clone();
+        out.derived_edges = self.derived_edges.clone();
+        out.views = self.views.clone();
+        out
+    }
+
+    pub fn get_node(&self, id: NodeId) -> Option<&Node> { self.nodes.get(&id) }
+    pub fn get_relationship(&self, id: Uuid) -> Option<&Relationship> { self.relationships.get(&id) }
+    #[allow(dead_code)]
+    pub fn node_count(&self) -> usize { self.nodes.len() }
+    #[allow(dead_code)]
+    pub fn relationship_count(&self) -> usize { self.relationships.len() }
+
+    // Fetch helpers:
+    // Nodes
+    pub fn find_node_ids_by_label(&self, label: &str) -> Vec<NodeId> {
+        self
+            .nodes
+            .iter()
+            .filter_map(|(&id, node)| if node.label == label { Some(id) } else { None })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn find_node_ids_by_metadata_key(&self, key: &str) -> Vec<NodeId> {
+        self
+            .nodes
+            .iter()
+            .filter_map(|(&id, node)| if node.metadata.contains_key(key) { Some(id) } else { None })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn find_node_ids_by_metadata_kv(&self, key: &str, value: &str) -> Vec<NodeId> {
+        self
+            .nodes
+            .iter()
+            .filter_map(|(&id, node)| match node.metadata.get(key) {
+                Some(v) if v == value => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Nodes matching every whitespace-separated term of `text`, case-insensitively, in
+    /// their label, id, description, or a metadata key or value. Sorted by label, then id; an empty
+    /// `text` returns all nodes.
+    pub fn search_node_ids(&self, text: &str) -> Vec<NodeId> {
+        let terms: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+        let mut found: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|n| {
+                terms.iter().all(|t| {
+                    n.label.to_lowercase().contains(t)
+                        || n.id.to_string().starts_with(t.as_str())
+                        || n.description.to_lowercase().contains(t)
+                        || n.metadata.iter().any(|(k, v)| k.to_lowercase().contains(t) || v.to_lowercase().contains(t))
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.label.cmp(&b.label).then(a.id.cmp(&b.id)));
+        found.into_iter().map(|n| n.id).collect()
+    }
+
+    // Relationships
+    pub fn find_relationship_ids_by_label(&self, label: &str) -> Vec<Uuid> {
+        self
+            .relationships
+            .iter()
+            .filter_map(|(&id, rel)| if rel.label == label { Some(id) } else { None })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn find_relationship_ids_by_metadata_key(&self, key: &str) -> Vec<Uuid> {
+        self
+            .relationships
+            .iter()
+            .filter_map(|(&id, rel)| if rel.metadata.contains_key(key) { Some(id) } else { None })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn find_relationship_ids_by_metadata_kv(&self, key: &str, value: &str) -> Vec<Uuid> {
+        self
+            .relationships
+            .iter()
+            .filter_map(|(&id, rel)| match rel.metadata.get(key) {
+                Some(v) if v == value => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+}
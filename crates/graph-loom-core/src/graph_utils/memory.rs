@@ -0,0 +1,154 @@
+//! Approximate memory use of a graph, for judging whether it fits a small server. Sizes are
+//! worked out from element counts and the capacity of maps and strings, so they leave out
+//! allocator overhead; spare is memory held beyond what the contents need, which [`compact`]
+//! gives back.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use serde::Serialize;
+
+use super::graph::{GraphDatabase, Node, Relationship};
+
+/// Memory held by one part of the graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub name: String,
+    /// Entries: elements, names or positions
+    pub count: usize,
+    /// Bytes allocated
+    pub bytes: usize,
+    /// Bytes allocated but not in use
+    pub spare: usize,
+}
+
+impl Usage {
+    pub fn new(name: &str) -> Self {
+        Usage { name: name.to_string(), ..Default::default() }
+    }
+
+    pub fn used(&self) -> usize {
+        self.bytes - self.spare
+    }
+
+    fn add_string(&mut self, s: &str, capacity: usize) {
+        self.bytes += capacity;
+        self.spare += capacity - s.len();
+    }
+
+    /// Count the table of `map` (not what its keys and values point to).
+    pub fn add_map<K, V>(&mut self, map: &HashMap<K, V>) {
+        let (bytes, used) = table_bytes(map.capacity(), map.len(), size_of::<(K, V)>());
+        self.bytes += bytes;
+        self.spare += bytes - used;
+    }
+
+    fn add_metadata(&mut self, metadata: &HashMap<String, String>) {
+        self.add_map(metadata);
+        for (k, v) in metadata {
+            self.add_string(k, k.capacity());
+            self.add_string(v, v.capacity());
+        }
+    }
+}
+
+// Buckets of a hash table holding `capacity` entries (tables keep 1/8 of buckets free) and
+// its size in bytes: an entry and a control byte per bucket plus a group of control bytes
+fn table_bytes(capacity: usize, len: usize, entry: usize) -> (usize, usize) {
+    let buckets = match capacity {
+        0 => return (0, 0),
+        1..=3 => 4,
+        4..=7 => 8,
+        _ => (capacity * 8 / 7).next_power_of_two(),
+    };
+    let bytes = buckets * (entry + 1) + 16;
+    let used = (len * (entry + 1)).min(bytes);
+    (bytes, used)
+}
+
+/// The parts of a graph and what they hold.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryReport {
+    pub parts: Vec<Usage>,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.parts.iter().map(|p| p.bytes).sum()
+    }
+
+    pub fn spare(&self) -> usize {
+        self.parts.iter().map(|p| p.spare).sum()
+    }
+}
+
+fn node_usage(usage: &mut Usage, n: &Node) {
+    for s in [&n.label, &n.description, &n.created_at, &n.updated_at] { usage.add_string(s, s.capacity()); }
+    usage.add_metadata(&n.metadata);
+}
+
+fn relationship_usage(usage: &mut Usage, r: &Relationship) {
+    for s in [&r.label, &r.description, &r.created_at, &r.updated_at] { usage.add_string(s, s.capacity()); }
+    usage.add_metadata(&r.metadata);
+}
+
+/// Nodes, relationships and the label and key index of `db`. The index counts only once it
+/// has been built.
+pub fn measure(db: &GraphDatabase) -> MemoryReport {
+    let mut nodes = Usage::new("Nodes");
+    nodes.count = db.nodes.len();
+    nodes.add_map(&db.nodes);
+    for n in db.nodes.values() { node_usage(&mut nodes, n); }
+
+    let mut relationships = Usage::new("Relationships");
+    relationships.count = db.relationships.len();
+    relationships.add_map(&db.relationships);
+    for r in db.relationships.values() { relationship_usage(&mut relationships, r); }
+
+    // Tree maps allocate per entry, so they have no spare to speak of
+    let mut indexes = Usage::new("Indexes");
+    if let Some(v) = db.built_vocabulary() {
+        let entry = size_of::<(String, usize)>();
+        for name in v.node_labels().chain(v.relationship_labels()).chain(v.node_keys()).chain(v.relationship_keys()) {
+            indexes.count += 1;
+            indexes.bytes += entry + name.len();
+        }
+    }
+    MemoryReport { parts: vec![nodes, relationships, indexes] }
+}
+
+/// Shrink the maps and strings of `db` to their contents and return the bytes given back.
+/// The elements themselves are left as they were.
+pub fn compact(db: &mut GraphDatabase) -> usize {
+    let before = measure(db).total();
+    db.nodes.shrink_to_fit();
+    for n in db.nodes.values_mut() {
+        for s in [&mut n.label, &mut n.description, &mut n.created_at, &mut n.updated_at] { s.shrink_to_fit(); }
+        shrink_metadata(&mut n.metadata);
+    }
+    db.relationships.shrink_to_fit();
+    for r in db.relationships.values_mut() {
+        for s in [&mut r.label, &mut r.description, &mut r.created_at, &mut r.updated_at] { s.shrink_to_fit(); }
+        shrink_metadata(&mut r.metadata);
+    }
+    before.saturating_sub(measure(db).total())
+}
+
+fn shrink_metadata(metadata: &mut HashMap<String, String>) {
+    metadata.shrink_to_fit();
+    // Keys cannot be changed in place
+    for v in metadata.values_mut() { v.shrink_to_fit(); }
+}
+
+/// `bytes` with a binary unit, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 { return format!("{} B", bytes); }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,11 @@
+pub mod generators;
+pub mod changes;
+pub mod computed;
+pub mod crdt;
+pub mod derived;
+pub mod graph;
+pub mod memory;
+pub mod permissions;
+pub mod schema;
+pub mod templates;
+pub mod validation;
@@ -0,0 +1,130 @@
+//! Roles limit what an API key may write: which labels it may create, edit and delete. A
+//! relationship's type counts as its label. The API runs each request under the role of the
+//! key it presented (see [`as_role`]); writes made under a role go through [`enforced`], which
+//! undoes a write that touched a label the role does not cover. Without a role nothing is
+//! checked, so the GUI and the main API key keep full access.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::changes::{ChangeEvent, Element, Operation};
+use super::graph::GraphDatabase;
+
+// Refusals listed in the error of a refused write
+const MAX_LISTED: usize = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Edit,
+    Delete,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Create => "create",
+            Action::Edit => "edit",
+            Action::Delete => "delete",
+        })
+    }
+}
+
+/// A named API key and the labels it may write; `*` stands for every label.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Role {
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub create: Vec<String>,
+    #[serde(default)]
+    pub edit: Vec<String>,
+    #[serde(default)]
+    pub delete: Vec<String>,
+}
+
+impl Role {
+    pub fn may(&self, action: Action, label: &str) -> bool {
+        let labels = match action {
+            Action::Create => &self.create,
+            Action::Edit => &self.edit,
+            Action::Delete => &self.delete,
+        };
+        labels.iter().any(|l| l == "*" || l == label)
+    }
+
+    /// Why the role may not make `event`, or `None` when it may. Changing a label needs edit
+    /// rights on both the old and the new label.
+    pub fn refusal(&self, event: &ChangeEvent) -> Option<String> {
+        let needed: Vec<(Action, &str)> = match (event.op, &event.before, &event.after) {
+            (Operation::Create, _, Some(a)) => vec![(Action::Create, &a.label)],
+            (Operation::Delete, Some(b), _) => vec![(Action::Delete, &b.label)],
+            (Operation::Update, Some(b), Some(a)) => vec![(Action::Edit, &b.label), (Action::Edit, &a.label)],
+            _ => Vec::new(),
+        };
+        let (action, label) = needed.into_iter().find(|(action, label)| !self.may(*action, label))?;
+        let what = match event.element {
+            Element::Node => format!("(:{})", label),
+            Element::Relationship => format!("[:{}]", label),
+        };
+        Some(format!("may not {} {}", action, what))
+    }
+}
+
+/// Refuse roles without a name or key, and keys used twice (by two roles, or by a role and
+/// the main key `api_key`).
+pub fn check_roles(roles: &[Role], api_key: Option<&str>) -> Result<()> {
+    for (i, r) in roles.iter().enumerate() {
+        if r.name.trim().is_empty() { return Err(anyhow!("role {} has no name", i + 1)); }
+        if r.key.is_empty() { return Err(anyhow!("role '{}' has no API key", r.name)); }
+        if api_key == Some(r.key.as_str()) || roles[..i].iter().any(|o| o.key == r.key) {
+            return Err(anyhow!("role '{}' reuses an API key", r.name));
+        }
+    }
+    Ok(())
+}
+
+/// The role whose key is `key`, if any.
+pub fn role_for_key<'a>(roles: &'a [Role], key: &str) -> Option<&'a Role> {
+    roles.iter().find(|r| !r.key.is_empty() && r.key == key)
+}
+
+thread_local! {
+    static ROLE: RefCell<Option<Role>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with the writes it makes limited to `role` (`None` for full access).
+pub fn as_role<T>(role: Option<Role>, f: impl FnOnce() -> T) -> T {
+    let previous = ROLE.with(|r| r.replace(role));
+    let out = f();
+    ROLE.with(|r| *r.borrow_mut() = previous);
+    out
+}
+
+/// The role writes on this thread are limited to.
+pub fn current_role() -> Option<Role> {
+    ROLE.with(|r| r.borrow().clone())
+}
+
+/// Run the write `f`, then check what it changed against the current role. If `f` fails or
+/// the role does not cover a change, the graph is put back as it was and the write fails.
+/// Without a role this is just `f(db)`.
+pub fn enforced<T>(db: &mut GraphDatabase, f: impl FnOnce(&mut GraphDatabase) -> Result<T>) -> Result<T> {
+    let Some(role) = current_role() else { return f(db) };
+    db.with_rollback(f, |before, after| check(&role, before, after))
+}
+
+/// Refuse the changes logged between `before` and `after` that `role` does not cover.
+pub fn check(role: &Role, before: &GraphDatabase, after: &GraphDatabase) -> Result<()> {
+    let from = before.changes().last_seq();
+    let mut refused: Vec<String> = after.changes().pending().iter().filter(|e| e.seq > from).filter_map(|e| role.refusal(e)).collect();
+    refused.sort();
+    refused.dedup();
+    if refused.is_empty() { return Ok(()); }
+    let mut listed: Vec<String> = refused.iter().take(MAX_LISTED).cloned().collect();
+    if refused.len() > MAX_LISTED { listed.push(format!("and {} more", refused.len() - MAX_LISTED)); }
+    Err(anyhow!("role '{}' {}", role.name, listed.join("; ")))
+}
@@ -0,0 +1,72 @@
+//! Meta-model of a graph inferred from its contents: the node labels in use with their
+//! metadata keys, and which labels each relationship type connects. Shown as a small graph
+//! under View → Schema View, served at `/v1/schema` and listed by `CALL db.schema()`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::graph::GraphDatabase;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Schema {
+    /// Sorted by label
+    pub labels: Vec<LabelSchema>,
+    /// Sorted by (from, type, to)
+    pub relationships: Vec<RelationshipSchema>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LabelSchema {
+    pub label: String,
+    /// Nodes with this label
+    pub count: usize,
+    /// Metadata keys used by at least one of them, sorted
+    pub keys: Vec<String>,
+}
+
+/// Relationships of one type between nodes of two labels.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RelationshipSchema {
+    pub from: String,
+    #[serde(rename = "type")]
+    pub label: String,
+    pub to: String,
+    pub count: usize,
+    pub keys: Vec<String>,
+}
+
+impl Schema {
+    pub fn infer(db: &GraphDatabase) -> Schema {
+        let mut labels: BTreeMap<&str, (usize, BTreeSet<&str>)> = BTreeMap::new();
+        for n in db.nodes.values() {
+            let e = labels.entry(n.label.as_str()).or_default();
+            e.0 += 1;
+            e.1.extend(n.metadata.keys().map(String::as_str));
+        }
+        let mut patterns: BTreeMap<(&str, &str, &str), (usize, BTreeSet<&str>)> = BTreeMap::new();
+        for r in db.relationships.values() {
+            let (Some(a), Some(b)) = (db.nodes.get(&r.from_node), db.nodes.get(&r.to_node)) else { continue };
+            let e = patterns.entry((a.label.as_str(), r.label.as_str(), b.label.as_str())).or_default();
+            e.0 += 1;
+            e.1.extend(r.metadata.keys().map(String::as_str));
+        }
+        let keys = |set: BTreeSet<&str>| set.into_iter().map(str::to_string).collect();
+        Schema {
+            labels: labels
+                .into_iter()
+                .map(|(label, (count, k))| LabelSchema { label: label.to_string(), count, keys: keys(k) })
+                .collect(),
+            relationships: patterns
+                .into_iter()
+                .map(|((from, label, to), (count, k))| RelationshipSchema {
+                    from: from.to_string(),
+                    label: label.to_string(),
+                    to: to.to_string(),
+                    count,
+                    keys: keys(k),
+                })
+                .collect(),
+        }
+    }
+}
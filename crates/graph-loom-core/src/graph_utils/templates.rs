@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use super::generators::{Generator, Model};
+use super::graph::{GraphDatabase, NodeId};
+
+/// Built-in sample graphs offered by File → New From Template.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Template {
+    SocialNetwork,
+    DependencyGraph,
+    OrgChart,
+    BarabasiAlbert { nodes: usize, edges_per_node: usize, seed: u64 },
+}
+
+impl Template {
+    /// Fixed templates in gallery order; the generator uses default size settings.
+    pub const ALL: [Template; 4] = [
+        Template::SocialNetwork,
+        Template::DependencyGraph,
+        Template::OrgChart,
+        Template::BarabasiAlbert { nodes: 100, edges_per_node: 2, seed: 42 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Template::SocialNetwork => "Social network",
+            Template::DependencyGraph => "Dependency graph",
+            Template::OrgChart => "Org chart",
+            Template::BarabasiAlbert { .. } => "Random scale-free (Barabási–Albert)",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Template::SocialNetwork => "People with FOLLOWS/FRIENDS_WITH ties and the cities they live in.",
+            Template::DependencyGraph => "Crates of a small Rust workspace and their DEPENDS_ON edges.",
+            Template::OrgChart => "A company hierarchy: REPORTS_TO edges from employees to managers.",
+            Template::BarabasiAlbert { .. } => "Preferential-attachment network; hubs emerge as the graph grows.",
+        }
+    }
+
+    pub fn build(&self) -> GraphDatabase {
+        match *self {
+            Template::SocialNetwork => social_network(),
+            Template::DependencyGraph => dependency_graph(),
+            Template::OrgChart => org_chart(),
+            Template::BarabasiAlbert { nodes, edges_per_node, seed } => barabasi_albert(nodes, edges_per_node, seed),
+        }
+    }
+}
+
+fn meta(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+pub fn social_network() -> GraphDatabase {
+    let mut db = GraphDatabase::new();
+    let cities: Vec<NodeId> = ["London", "Berlin", "Austin"]
+        .iter()
+        .map(|c| db.add_node("City".into(), meta(&[("name", c)])))
+        .collect();
+    let people = [
+        ("Ada", "36", 0), ("Grace", "45", 2), ("Alan", "41", 0), ("Linus", "29", 1),
+        ("Margaret", "33", 2), ("Ken", "52", 2), ("Barbara", "38", 1), ("Dennis", "47", 0),
+    ];
+    let ids: Vec<NodeId> = people
+        .iter()
+        .map(|(name, age, city)| {
+            let id = db.add_node("Person".into(), meta(&[("name", name), ("age", age)]));
+            db.add_relationship(id, cities[*city], "LIVES_IN".into(), HashMap::new());
+            id
+        })
+        .collect();
+    let follows = [(0, 1), (0, 2), (1, 4), (2, 7), (3, 0), (3, 6), (4, 5), (5, 7), (6, 1), (7, 3)];
+    for (a, b) in follows {
+        db.add_relationship(ids[a], ids[b], "FOLLOWS".into(), HashMap::new());
+    }
+    let friends = [(0, 7, "2015"), (1, 5, "2009"), (3, 6, "2020"), (2, 4, "2018")];
+    for (a, b, since) in friends {
+        db.add_relationship(ids[a], ids[b], "FRIENDS_WITH".into(), meta(&[("since", since)]));
+    }
+    db
+}
+
+pub fn dependency_graph() -> GraphDatabase {
+    let mut db = GraphDatabase::new();
+    let crates = [
+        ("app", "bin"), ("cli", "bin"), ("core", "lib"), ("storage", "lib"),
+        ("net", "lib"), ("serde", "external"), ("tokio", "external"), ("log", "external"),
+    ];
+    let ids: HashMap<&str, NodeId> = crates
+        .iter()
+        .map(|(name, kind)| (*name, db.add_node("Crate".into(), meta(&[("name", name), ("kind", kind)]))))
+        .collect();
+    let deps = [
+        ("app", "core"), ("app", "net"), ("app", "log"), ("cli", "core"), ("cli", "log"),
+        ("core", "storage"), ("core", "serde"), ("storage", "serde"), ("net", "tokio"),
+        ("net", "core"), ("storage", "log"),
+    ];
+    for (from, to) in deps {
+        db.add_relationship(ids[from], ids[to], "DEPENDS_ON".into(), HashMap::new());
+    }
+    db
+}
+
+pub fn org_chart() -> GraphDatabase {
+    let mut db = GraphDatabase::new();
+    // (name, title, department, manager index)
+    let staff: [(&str, &str, &str, Option<usize>); 10] = [
+        ("Morgan", "CEO", "Executive", None),
+        ("Riley", "CTO", "Engineering", Some(0)),
+        ("Casey", "CFO", "Finance", Some(0)),
+        ("Jordan", "VP Sales", "Sales", Some(0)),
+        ("Avery", "Engineering Manager", "Engineering", Some(1)),
+        ("Quinn", "Staff Engineer", "Engineering", Some(4)),
+        ("Harper", "Engineer", "Engineering", Some(4)),
+        ("Rowan", "Accountant", "Finance", Some(2)),
+        ("Skyler", "Account Executive", "Sales", Some(3)),
+        ("Emery", "Account Executive", "Sales", Some(3)),
+    ];
+    let mut ids: Vec<NodeId> = Vec::with_capacity(staff.len());
+    for (name, title, dept, manager) in staff {
+        let id = db.add_node("Employee".into(), meta(&[("name", name), ("title", title), ("department", dept)]));
+        if let Some(m) = manager {
+            db.add_relationship(id, ids[m], "REPORTS_TO".into(), HashMap::new());
+        }
+        ids.push(id);
+    }
+    db
+}
+
+/// Barabási–Albert preferential attachment: start from a small clique of `m + 1` nodes, then each
+/// new node links to `m` distinct existing nodes chosen with probability proportional to degree.
+pub fn barabasi_albert(nodes: usize, m: usize, seed: u64) -> GraphDatabase {
+    Generator { model: Model::BarabasiAlbert { nodes, edges_per_node: m }, seed, ..Generator::default() }.generate()
+}
@@ -0,0 +1,177 @@
+//! Per-label property rules: keys a node must have and the values a key may take (a fixed
+//! set, a numeric range or a regular expression). Rules are stored with the graph. Writes from
+//! queries and the API run through [`guarded`], which refuses a write that leaves a created or
+//! changed node breaking a rule; nodes that broke a rule before it was added are only reported.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::graph::{GraphDatabase, Node, NodeId};
+
+// Violations listed in the error of a refused write
+const MAX_LISTED: usize = 5;
+
+/// Constraints on one metadata key of the nodes with a label. Checks other than `required`
+/// only apply when the key is present.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PropertyRule {
+    pub key: String,
+    #[serde(default)]
+    pub required: bool,
+    /// Allowed values; empty allows any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Regular expression the whole value must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+// Compiled patterns, shared by every check of the same rule
+static PATTERNS: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn compiled(pattern: &str) -> Result<Regex> {
+    let mut cache = PATTERNS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(re) = cache.get(pattern) { return Ok(re.clone()); }
+    let re = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| anyhow!("invalid regex '{}': {}", pattern, e))?;
+    cache.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+impl PropertyRule {
+    /// Parse a rule written as comma-separated checks, e.g.
+    /// `required, in=draft|published, min=0, max=10, regex=[A-Z].*`. `regex=` takes the rest
+    /// of the text, commas included, so it comes last.
+    pub fn parse(key: &str, spec: &str) -> Result<PropertyRule> {
+        let key = key.trim();
+        if key.is_empty() { return Err(anyhow!("a property rule needs a key")); }
+        let mut rule = PropertyRule { key: key.to_string(), ..PropertyRule::default() };
+        let mut rest = spec.trim();
+        while !rest.is_empty() {
+            if let Some(p) = rest.strip_prefix("regex=") {
+                rule.pattern = Some(p.trim().to_string());
+                break;
+            }
+            let (part, tail) = rest.split_once(',').unwrap_or((rest, ""));
+            rest = tail.trim_start();
+            let part = part.trim();
+            let number = |v: &str| v.trim().parse::<f64>().map_err(|_| anyhow!("'{}' expects a number", part));
+            match part.split_once('=') {
+                None if part.eq_ignore_ascii_case("required") => rule.required = true,
+                None if part.is_empty() => {}
+                Some(("in", values)) => rule.allowed = values.split('|').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect(),
+                Some(("min", v)) => rule.min = Some(number(v)?),
+                Some(("max", v)) => rule.max = Some(number(v)?),
+                _ => return Err(anyhow!("unknown rule '{}' (expected required, in=a|b, min=n, max=n or regex=...)", part)),
+            }
+        }
+        rule.validated()
+    }
+
+    /// The rule itself, refused when its pattern does not compile or its range is empty.
+    pub fn validated(self) -> Result<PropertyRule> {
+        if let Some(p) = &self.pattern { compiled(p)?; }
+        if let (Some(min), Some(max)) = (self.min, self.max)
+            && min > max
+        {
+            return Err(anyhow!("min {} is above max {} for '{}'", min, max, self.key));
+        }
+        Ok(self)
+    }
+
+    /// Why `value` breaks this rule, or `None` when it passes.
+    pub fn check(&self, value: Option<&str>) -> Option<String> {
+        let Some(value) = value.filter(|v| !v.is_empty()) else {
+            return self.required.then(|| "is required".to_string());
+        };
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|a| a == value) {
+            return Some(format!("must be one of {}", self.allowed.join(", ")));
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(n) = value.trim().parse::<f64>() else { return Some(format!("must be a number, got '{}'", value)) };
+            if let Some(min) = self.min.filter(|m| n < *m) { return Some(format!("must be at least {}", min)); }
+            if let Some(max) = self.max.filter(|m| n > *m) { return Some(format!("must be at most {}", max)); }
+        }
+        if let Some(p) = &self.pattern
+            && compiled(p).is_ok_and(|re| !re.is_match(value))
+        {
+            return Some(format!("must match /{}/", p));
+        }
+        None
+    }
+}
+
+impl fmt::Display for PropertyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.required { parts.push("required".to_string()); }
+        if !self.allowed.is_empty() { parts.push(format!("in={}", self.allowed.join("|"))); }
+        if let Some(min) = self.min { parts.push(format!("min={}", min)); }
+        if let Some(max) = self.max { parts.push(format!("max={}", max)); }
+        if let Some(p) = &self.pattern { parts.push(format!("regex={}", p)); }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// A node breaking one of its label's rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleViolation {
+    pub node: NodeId,
+    pub label: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(:{} {}) {} {}", self.label, self.node, self.key, self.message)
+    }
+}
+
+/// Each broken rule of `rules` as (key, reason).
+pub fn problems(rules: &[PropertyRule], metadata: &HashMap<String, String>) -> Vec<(String, String)> {
+    rules
+        .iter()
+        .filter_map(|r| r.check(metadata.get(&r.key).map(String::as_str)).map(|m| (r.key.clone(), m)))
+        .collect()
+}
+
+/// Rules broken by `nodes` of `db`, sorted by label, node and key.
+pub fn violations<'a>(db: &GraphDatabase, nodes: impl Iterator<Item = &'a Node>) -> Vec<RuleViolation> {
+    let mut out: Vec<RuleViolation> = nodes
+        .flat_map(|n| {
+            let rules = db.property_rules.get(&n.label).map(Vec::as_slice).unwrap_or_default();
+            problems(rules, &n.metadata).into_iter().map(|(key, message)| RuleViolation { node: n.id, label: n.label.clone(), key, message })
+        })
+        .collect();
+    out.sort_by(|a, b| (&a.label, a.node, &a.key).cmp(&(&b.label, b.node, &b.key)));
+    out
+}
+
+/// Run the write `f`, then check the nodes it created or changed. If `f` fails or a node
+/// breaks a rule the graph is put back as it was and the write fails. Without rules this is
+/// just `f(db)`.
+pub fn guarded<T>(db: &mut GraphDatabase, f: impl FnOnce(&mut GraphDatabase) -> Result<T>) -> Result<T> {
+    if db.property_rules.is_empty() { return f(db); }
+    db.with_rollback(f, check)
+}
+
+/// Refuse nodes created or changed between `before` and `after` that break a rule.
+pub fn check(before: &GraphDatabase, after: &GraphDatabase) -> Result<()> {
+    if after.property_rules.is_empty() { return Ok(()); }
+    let changed = after.nodes.values().filter(|n| before.nodes.get(&n.id).is_none_or(|b| b.label != n.label || b.metadata != n.metadata));
+    let broken = violations(after, changed);
+    if broken.is_empty() { return Ok(()); }
+    let mut listed: Vec<String> = broken.iter().take(MAX_LISTED).map(|v| v.to_string()).collect();
+    if broken.len() > MAX_LISTED { listed.push(format!("and {} more", broken.len() - MAX_LISTED)); }
+    Err(anyhow!("property rules rejected the change: {}", listed.join("; ")))
+}
@@ -0,0 +1,29 @@
+//! The graph engine behind Graph-Loom, usable without the GUI or the API servers.
+//!
+//! - [`graph_utils::graph::GraphDatabase`] is the in-memory property graph: nodes and
+//!   relationships with a label and string metadata, keyed by UUID.
+//! - [`gql::query_interface`] runs the Cypher-like query language against it
+//!   ([`execute_query`](gql::query_interface::execute_query),
+//!   [`execute_query_with_params`](gql::query_interface::execute_query_with_params)).
+//! - [`persistence`] reads and writes state files ([`persist`](persistence::persist)),
+//!   imports and exports JSON/CSV/GraphML and merges graphs.
+//!
+//! No type here depends on a UI toolkit; node positions in a state file are plain `(x, y)`
+//! pairs that any point type convertible to and from `(f32, f32)` can use.
+//!
+//! ```
+//! use graph_loom_core::gql::query_interface::{execute_query, QueryResultRow};
+//! use graph_loom_core::graph_utils::graph::GraphDatabase;
+//!
+//! let mut db = GraphDatabase::new();
+//! execute_query(&mut db, "CREATE (:Person {name: 'Ann'})").unwrap();
+//! // The same graph is editable directly
+//! let bob = db.add_node("Person".into(), [("name".to_string(), "Bob".to_string())].into());
+//! let out = execute_query(&mut db, "MATCH (p:Person {name: 'Bob'}) RETURN p").unwrap();
+//! assert!(matches!(&out.rows[..], [QueryResultRow::Node { id, .. }] if *id == bob));
+//! assert_eq!(db.node_count(), 2);
+//! ```
+
+pub mod graph_utils;
+pub mod gql;
+pub mod persistence;
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::persist::{self, AppStateFile};
+use super::settings::AppSettings;
+
+/// File name prefix of scheduled snapshots, e.g. `state_auto_20260101_120000.ron`.
+pub const AUTO_BACKUP_PREFIX: &str = "state_auto_";
+
+/// Scheduled snapshots in `dir`, newest first.
+pub fn list_auto_backups_in(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    if dir.exists() {
+        for e in fs::read_dir(dir)? {
+            let p = e?.path();
+            if let Some(name) = p.file_name().and_then(|s| s.to_str())
+                && name.starts_with(AUTO_BACKUP_PREFIX) && name.ends_with(".ron")
+            {
+                found.push(p);
+            }
+        }
+    }
+    // The timestamp in the name sorts chronologically
+    found.sort();
+    found.reverse();
+    Ok(found)
+}
+
+/// Delete all but the newest `keep` scheduled snapshots in `dir` (`keep == 0` keeps all).
+/// Returns the deleted paths.
+pub fn prune_in(dir: &Path, keep: usize) -> std::io::Result<Vec<PathBuf>> {
+    if keep == 0 { return Ok(Vec::new()); }
+    let old: Vec<PathBuf> = list_auto_backups_in(dir)?.into_iter().skip(keep).collect();
+    for p in &old { fs::remove_file(p)?; }
+    Ok(old)
+}
+
+/// Decides when the next scheduled snapshot is due. Snapshots are skipped while the graph is
+/// unchanged, and the clock starts from the newest snapshot on disk so restarts do not reset it.
+#[derive(Debug, Clone)]
+pub struct BackupSchedule {
+    last: Option<SystemTime>,
+    changed: bool,
+}
+
+impl BackupSchedule {
+    pub fn new() -> Self {
+        let dir = persist::active_state_path().parent().map(Path::to_path_buf).unwrap_or_default();
+        let last = list_auto_backups_in(&dir)
+            .ok()
+            .and_then(|l| l.first().and_then(|p| fs::metadata(p).ok()?.modified().ok()));
+        Self::starting_at(last)
+    }
+
+    pub fn starting_at(last: Option<SystemTime>) -> Self {
+        BackupSchedule { last, changed: false }
+    }
+
+    /// The graph changed since the last snapshot.
+    pub fn note_change(&mut self) {
+        self.changed = true;
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.changed
+    }
+
+    /// Whether the timer in `settings` calls for a snapshot at `now`.
+    pub fn is_due(&self, settings: &AppSettings, now: SystemTime) -> bool {
+        if settings.backup_interval_hours == 0 || !self.changed { return false; }
+        let interval = Duration::from_secs(u64::from(settings.backup_interval_hours) * 3600);
+        self.last.is_none_or(|t| now.duration_since(t).is_ok_and(|d| d >= interval))
+    }
+
+    /// Write a snapshot of `state` and apply the retention policy.
+    pub fn run(&mut self, state: &AppStateFile, settings: &AppSettings) -> anyhow::Result<PathBuf> {
+        let path = persist::save_auto_backup(state)?;
+        self.last = Some(SystemTime::now());
+        self.changed = false;
+        if let Some(dir) = path.parent() {
+            for p in prune_in(dir, settings.backup_keep)? {
+                log::info!("Removed old backup {}", p.display());
+            }
+        }
+        Ok(path)
+    }
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self { Self::new() }
+}
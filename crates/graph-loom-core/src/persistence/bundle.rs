@@ -0,0 +1,136 @@
+//! Portable project bundles: one zip holding the graph state with its layout, saved queries
+//! and the settings that change how the graph is drawn and queried, for handing a complete
+//! project to someone else. Machine-specific settings (paths, servers, keys) stay out.
+//!
+//! Entries: `manifest.json`, `state.ron` (same format as the autosave), `queries.json`
+//! and `settings.json`.
+
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+
+use super::persist::{self, AppStateFile};
+use super::settings::AppSettings;
+
+/// Extension of bundle files. They are plain zip archives.
+pub const BUNDLE_EXTENSION: &str = "glbundle";
+const FORMAT_VERSION: u32 = 1;
+// Entries are small text files; refuse anything that inflates past this
+const MAX_ENTRY_BYTES: u64 = 1 << 30;
+
+const MANIFEST: &str = "manifest.json";
+const STATE: &str = "state.ron";
+const QUERIES: &str = "queries.json";
+const SETTINGS: &str = "settings.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    created_by: String,
+}
+
+/// The shareable subset of [`AppSettings`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleSettings {
+    pub lod_enabled: bool,
+    pub lod_label_min_zoom: f32,
+    pub lod_hide_labels_node_threshold: usize,
+    pub query_case_insensitive: bool,
+    pub query_locale_collation: bool,
+}
+
+impl BundleSettings {
+    pub fn from_settings(s: &AppSettings) -> Self {
+        BundleSettings {
+            lod_enabled: s.lod_enabled,
+            lod_label_min_zoom: s.lod_label_min_zoom,
+            lod_hide_labels_node_threshold: s.lod_hide_labels_node_threshold,
+            query_case_insensitive: s.query_case_insensitive,
+            query_locale_collation: s.query_locale_collation,
+        }
+    }
+
+    /// Overwrite the matching fields of `s`, leaving everything else alone.
+    pub fn apply_to(&self, s: &mut AppSettings) {
+        s.lod_enabled = self.lod_enabled;
+        s.lod_label_min_zoom = self.lod_label_min_zoom;
+        s.lod_hide_labels_node_threshold = self.lod_hide_labels_node_threshold;
+        s.query_case_insensitive = self.query_case_insensitive;
+        s.query_locale_collation = self.query_locale_collation;
+    }
+}
+
+impl Default for BundleSettings {
+    fn default() -> Self { Self::from_settings(&AppSettings::default()) }
+}
+
+/// Everything a bundle carries.
+#[derive(Debug)]
+pub struct Bundle {
+    pub state: AppStateFile,
+    /// Queries in the order they were run, oldest first
+    pub queries: Vec<String>,
+    pub settings: BundleSettings,
+}
+
+/// Write `bundle` to `path`, replacing any existing file.
+pub fn write(path: &Path, bundle: &Bundle) -> anyhow::Result<()> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let manifest = Manifest { format_version: FORMAT_VERSION, created_by: format!("Graph-Loom {}", env!("CARGO_PKG_VERSION")) };
+    let entries = [
+        (MANIFEST, serde_json::to_string_pretty(&manifest)?),
+        (STATE, ron::ser::to_string(&bundle.state)?),
+        (QUERIES, serde_json::to_string_pretty(&bundle.queries)?),
+        (SETTINGS, serde_json::to_string_pretty(&bundle.settings)?),
+    ];
+    for (name, body) in entries {
+        zip.start_file(name, options)?;
+        zip.write_all(body.as_bytes())?;
+    }
+    let bytes = zip.finish()?.into_inner();
+    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+    persist::atomic_write(path, &bytes)?;
+    Ok(())
+}
+
+/// Read a bundle written by [`write`]. Only the state is required; missing queries or
+/// settings come back empty or at their defaults.
+pub fn read(path: &Path) -> anyhow::Result<Bundle> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)
+        .map_err(|e| anyhow::anyhow!("{} is not a bundle: {}", path.display(), e))?;
+    let manifest: Manifest = match read_entry(&mut zip, MANIFEST)? {
+        Some(s) => serde_json::from_str(&s)?,
+        None => anyhow::bail!("{} is not a bundle: no {}", path.display(), MANIFEST),
+    };
+    if manifest.format_version > FORMAT_VERSION {
+        anyhow::bail!("{} was written by a newer version ({})", path.display(), manifest.created_by);
+    }
+    let state = match read_entry(&mut zip, STATE)? {
+        Some(s) => super::migrate::read_state(&s)?.state,
+        None => anyhow::bail!("{} has no {}", path.display(), STATE),
+    };
+    let queries = match read_entry(&mut zip, QUERIES)? {
+        Some(s) => serde_json::from_str(&s)?,
+        None => Vec::new(),
+    };
+    let settings = match read_entry(&mut zip, SETTINGS)? {
+        Some(s) => serde_json::from_str(&s)?,
+        None => BundleSettings::default(),
+    };
+    Ok(Bundle { state, queries, settings })
+}
+
+fn read_entry<R: Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>, name: &str) -> anyhow::Result<Option<String>> {
+    let entry = match zip.by_name(name) {
+        Ok(e) => e,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut s = String::new();
+    entry.take(MAX_ENTRY_BYTES).read_to_string(&mut s)?;
+    Ok(Some(s))
+}
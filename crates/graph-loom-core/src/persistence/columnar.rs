@@ -0,0 +1,377 @@
+//! Node and relationship tables for analytics tools: one row per element, one column per
+//! field, written as Parquet or Arrow IPC so pandas, polars or DuckDB read them with types, or
+//! as CSV.
+//!
+//! Metadata values are strings in the graph; each metadata key becomes a column typed by what
+//! all of its values parse as (integer, float, boolean, date, datetime, else text), with
+//! nulls where an element lacks the key. `created_at` and `updated_at` are datetimes. An
+//! [`ExportTemplate`] picks the metadata columns and what happens to the other keys. Writing
+//! Parquet and Arrow needs the `parquet` feature; [`table`] and CSV are always available.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gql::temporal;
+use crate::graph_utils::graph::GraphDatabase;
+
+/// Which elements a table holds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Table {
+    Nodes,
+    Relationships,
+}
+
+/// File format the tables are written in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    Parquet,
+    /// Arrow IPC file (Feather v2)
+    ArrowIpc,
+    /// Plain CSV with a header row; nulls are empty fields
+    Csv,
+}
+
+impl ColumnarFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => "parquet",
+            ColumnarFormat::ArrowIpc => "arrow",
+            ColumnarFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => "application/vnd.apache.parquet",
+            ColumnarFormat::ArrowIpc => "application/vnd.apache.arrow.file",
+            ColumnarFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// What happens to the metadata keys an [`ExportTemplate`] does not pick as columns.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestMetadata {
+    /// A column per key, after the picked ones
+    #[default]
+    Flatten,
+    /// One `metadata_json` column holding them as a JSON object
+    Json,
+    /// Left out
+    Omit,
+}
+
+/// Named table shape, saved in the settings so GUI and command-line exports share it. The
+/// default template flattens every metadata key.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    pub name: String,
+    /// Metadata keys that become columns, in this order, whether or not any element has them
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub rest: RestMetadata,
+}
+
+/// Column holding the unpicked metadata of [`RestMetadata::Json`].
+pub const REST_JSON_COLUMN: &str = "metadata_json";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    DateTime,
+}
+
+/// A table column: its values as stored in the graph and the type they all parse as.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnType,
+    pub values: Vec<Option<String>>,
+}
+
+impl Column {
+    fn fixed(name: &str, kind: ColumnType, values: Vec<Option<String>>) -> Self {
+        Column { name: name.into(), kind, values }
+    }
+
+    fn inferred(name: String, values: Vec<Option<String>>) -> Self {
+        let kind = infer(values.iter().flatten().map(String::as_str));
+        Column { name, kind, values }
+    }
+}
+
+/// Fixed columns, then the template's metadata columns, then the other metadata keys as the
+/// template says (in key order when flattened). A metadata key named like a fixed column gets
+/// a `metadata.` prefix. Rows are in id order.
+pub fn table(db: &GraphDatabase, which: Table, template: &ExportTemplate) -> Vec<Column> {
+    match which {
+        Table::Nodes => {
+            let mut nodes: Vec<_> = db.nodes.values().collect();
+            nodes.sort_by_key(|n| n.id);
+            let fixed = ["id", "label", "description", "created_at", "updated_at"];
+            let mut columns = vec![
+                Column::fixed("id", ColumnType::Text, nodes.iter().map(|n| Some(n.id.to_string())).collect()),
+                Column::fixed("label", ColumnType::Text, nodes.iter().map(|n| Some(n.label.clone())).collect()),
+                Column::fixed("description", ColumnType::Text, nodes.iter().map(|n| non_empty(&n.description)).collect()),
+                Column::inferred("created_at".into(), nodes.iter().map(|n| non_empty(&n.created_at)).collect()),
+                Column::inferred("updated_at".into(), nodes.iter().map(|n| non_empty(&n.updated_at)).collect()),
+            ];
+            columns.extend(metadata_columns(&fixed, nodes.iter().map(|n| &n.metadata).collect(), template));
+            columns
+        }
+        Table::Relationships => {
+            let mut rels: Vec<_> = db.relationships.values().collect();
+            rels.sort_by_key(|r| r.id);
+            let fixed = ["id", "from", "to", "label", "description", "created_at", "updated_at"];
+            let mut columns = vec![
+                Column::fixed("id", ColumnType::Text, rels.iter().map(|r| Some(r.id.to_string())).collect()),
+                Column::fixed("from", ColumnType::Text, rels.iter().map(|r| Some(r.from_node.to_string())).collect()),
+                Column::fixed("to", ColumnType::Text, rels.iter().map(|r| Some(r.to_node.to_string())).collect()),
+                Column::fixed("label", ColumnType::Text, rels.iter().map(|r| Some(r.label.clone())).collect()),
+                Column::fixed("description", ColumnType::Text, rels.iter().map(|r| non_empty(&r.description)).collect()),
+                Column::inferred("created_at".into(), rels.iter().map(|r| non_empty(&r.created_at)).collect()),
+                Column::inferred("updated_at".into(), rels.iter().map(|r| non_empty(&r.updated_at)).collect()),
+            ];
+            columns.extend(metadata_columns(&fixed, rels.iter().map(|r| &r.metadata).collect(), template));
+            columns
+        }
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn metadata_columns(fixed: &[&str], rows: Vec<&HashMap<String, String>>, template: &ExportTemplate) -> Vec<Column> {
+    let column = |k: &String| {
+        let name = if fixed.contains(&k.as_str()) { format!("metadata.{}", k) } else { k.clone() };
+        Column::inferred(name, rows.iter().map(|m| m.get(k).cloned()).collect())
+    };
+    let mut columns: Vec<Column> = template.columns.iter().map(column).collect();
+    let rest: BTreeSet<&String> = rows.iter().flat_map(|m| m.keys()).filter(|k| !template.columns.contains(k)).collect();
+    match template.rest {
+        RestMetadata::Flatten => columns.extend(rest.into_iter().map(column)),
+        RestMetadata::Json => {
+            let values = rows
+                .iter()
+                .map(|m| {
+                    let left: BTreeMap<&String, &String> = m.iter().filter(|(k, _)| rest.contains(k)).collect();
+                    (!left.is_empty()).then(|| serde_json::to_string(&left).unwrap_or_default())
+                })
+                .collect();
+            columns.push(Column::fixed(REST_JSON_COLUMN, ColumnType::Text, values));
+        }
+        RestMetadata::Omit => {}
+    }
+    columns
+}
+
+// The narrowest type every value parses as; text when there are no values
+fn infer<'a>(values: impl Iterator<Item = &'a str> + Clone) -> ColumnType {
+    let mut values = values.peekable();
+    if values.peek().is_none() { return ColumnType::Text; }
+    let all = |f: fn(&str) -> bool| values.clone().all(f);
+    if all(|v| parse_integer(v).is_some()) {
+        ColumnType::Integer
+    } else if all(|v| parse_float(v).is_some()) {
+        ColumnType::Float
+    } else if all(|v| parse_bool(v).is_some()) {
+        ColumnType::Boolean
+    } else if all(|v| temporal::parse_date(v).is_some() && v.trim().len() == 10) {
+        ColumnType::Date
+    } else if all(|v| temporal::parse_datetime(v).is_some()) {
+        ColumnType::DateTime
+    } else {
+        ColumnType::Text
+    }
+}
+
+// Leading zeros and signs other than `-` mark codes (`007`, `+44`), which stay text
+pub fn parse_integer(v: &str) -> Option<i64> {
+    let digits = v.strip_prefix('-').unwrap_or(v);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) || (digits.len() > 1 && digits.starts_with('0')) { return None; }
+    v.parse().ok()
+}
+
+pub fn parse_float(v: &str) -> Option<f64> {
+    let digits = v.strip_prefix('-').unwrap_or(v);
+    let first = digits.bytes().next()?;
+    // Rust also parses `inf`, `NaN` and `1.` and the like, which are not numbers in a table
+    if !first.is_ascii_digit() || !digits.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+')) || digits.ends_with('.') { return None; }
+    if digits.len() > 1 && digits.starts_with('0') && !digits[1..].starts_with(['.', 'e', 'E']) { return None; }
+    v.parse().ok()
+}
+
+pub fn parse_bool(v: &str) -> Option<bool> {
+    match v {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod arrow {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use anyhow::{Context, Result};
+    use arrow_array::{ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    use super::{Column, ColumnType, ColumnarFormat};
+    use crate::gql::temporal;
+
+    // Rows per record batch (and Parquet row group)
+    const BATCH_ROWS: usize = 65_536;
+
+    fn data_type(kind: ColumnType) -> DataType {
+        match kind {
+            ColumnType::Text => DataType::Utf8,
+            ColumnType::Integer => DataType::Int64,
+            ColumnType::Float => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::DateTime => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        }
+    }
+
+    fn array(c: &Column, rows: std::ops::Range<usize>) -> ArrayRef {
+        let values = &c.values[rows];
+        let parsed = |f: &dyn Fn(&str) -> Option<i64>| values.iter().map(|v| v.as_deref().and_then(f)).collect::<Vec<_>>();
+        match c.kind {
+            ColumnType::Text => Arc::new(StringArray::from(values.iter().map(|v| v.as_deref()).collect::<Vec<_>>())),
+            ColumnType::Integer => Arc::new(Int64Array::from(parsed(&super::parse_integer))),
+            ColumnType::Float => Arc::new(Float64Array::from(values.iter().map(|v| v.as_deref().and_then(super::parse_float)).collect::<Vec<_>>())),
+            ColumnType::Boolean => Arc::new(BooleanArray::from(values.iter().map(|v| v.as_deref().and_then(super::parse_bool)).collect::<Vec<_>>())),
+            ColumnType::Date => {
+                let epoch = time::Date::from_calendar_date(1970, time::Month::January, 1).unwrap_or(time::Date::MIN);
+                let days = |v: &str| temporal::parse_date(v).and_then(|d| i32::try_from((d - epoch).whole_days()).ok());
+                Arc::new(Date32Array::from(values.iter().map(|v| v.as_deref().and_then(days)).collect::<Vec<_>>()))
+            }
+            ColumnType::DateTime => {
+                let micros = |v: &str| temporal::parse_datetime(v).and_then(|t| i64::try_from(t.unix_timestamp_nanos() / 1_000).ok());
+                Arc::new(TimestampMicrosecondArray::from(parsed(&micros)).with_timezone("UTC"))
+            }
+        }
+    }
+
+    pub fn write<W: Write + Send>(columns: &[Column], format: ColumnarFormat, w: W, on_rows: &mut dyn FnMut(usize) -> Result<()>) -> Result<()> {
+        let schema = Arc::new(Schema::new(columns.iter().map(|c| Field::new(c.name.clone(), data_type(c.kind), c.values.iter().any(Option::is_none))).collect::<Vec<_>>()));
+        let rows = columns.first().map_or(0, |c| c.values.len());
+        let batches = (0..rows).step_by(BATCH_ROWS).map(|start| start..(start + BATCH_ROWS).min(rows));
+        let batch = |range: std::ops::Range<usize>| -> Result<RecordBatch> {
+            Ok(RecordBatch::try_new(schema.clone(), columns.iter().map(|c| array(c, range.clone())).collect())?)
+        };
+        match format {
+            ColumnarFormat::Parquet => {
+                let props = WriterProperties::builder().set_compression(Compression::SNAPPY).set_max_row_group_size(BATCH_ROWS).build();
+                let mut writer = ArrowWriter::try_new(w, schema.clone(), Some(props)).context("starting the Parquet file")?;
+                for range in batches {
+                    let end = range.end;
+                    writer.write(&batch(range)?)?;
+                    on_rows(end)?;
+                }
+                writer.close().context("finishing the Parquet file")?;
+            }
+            ColumnarFormat::ArrowIpc => {
+                let mut writer = arrow_ipc::writer::FileWriter::try_new(w, &schema).context("starting the Arrow file")?;
+                for range in batches {
+                    let end = range.end;
+                    writer.write(&batch(range)?)?;
+                    on_rows(end)?;
+                }
+                writer.finish().context("finishing the Arrow file")?;
+            }
+            ColumnarFormat::Csv => super::write_csv(columns, w, on_rows)?,
+        }
+        Ok(())
+    }
+}
+
+// Rows written between progress reports for CSV
+const CSV_REPORT_ROWS: usize = 10_000;
+
+fn write_csv<W: std::io::Write>(columns: &[Column], w: W, on_rows: &mut dyn FnMut(usize) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let mut wtr = csv::Writer::from_writer(w);
+    wtr.write_record(columns.iter().map(|c| c.name.as_str()))?;
+    let rows = columns.first().map_or(0, |c| c.values.len());
+    for row in 0..rows {
+        wtr.write_record(columns.iter().map(|c| c.values[row].as_deref().unwrap_or("")))?;
+        if (row + 1).is_multiple_of(CSV_REPORT_ROWS) { on_rows(row + 1)?; }
+    }
+    wtr.flush()?;
+    on_rows(rows)
+}
+
+/// Write the table to `w`. `on_rows` hears the number of rows written every so often and can
+/// stop the export by returning an error.
+pub fn write<W: std::io::Write + Send>(
+    db: &GraphDatabase,
+    which: Table,
+    template: &ExportTemplate,
+    format: ColumnarFormat,
+    w: W,
+    on_rows: &mut dyn FnMut(usize) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    if format == ColumnarFormat::Csv { return write_csv(&table(db, which, template), w, on_rows); }
+    #[cfg(feature = "parquet")]
+    return arrow::write(&table(db, which, template), format, w, on_rows);
+    #[cfg(not(feature = "parquet"))]
+    {
+        let _ = (db, which, template, w, on_rows);
+        Err(anyhow::anyhow!("this build has no Parquet or Arrow support (feature `parquet`)"))
+    }
+}
+
+/// Where [`export_files`] writes the tables for `path`: `<stem>_nodes.<ext>` and
+/// `<stem>_relationships.<ext>` next to it.
+pub fn table_paths(path: &Path, format: ColumnarFormat) -> [(Table, PathBuf); 2] {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("graph");
+    [
+        (Table::Nodes, parent.join(format!("{}_nodes.{}", stem, format.extension()))),
+        (Table::Relationships, parent.join(format!("{}_relationships.{}", stem, format.extension()))),
+    ]
+}
+
+/// Write both tables next to `path` (see [`table_paths`]). `on_progress` hears the number of
+/// elements written so far and can stop the export by returning an error; a failed export
+/// leaves no files behind.
+pub fn export_files(
+    db: &GraphDatabase,
+    path: &Path,
+    template: &ExportTemplate,
+    format: ColumnarFormat,
+    on_progress: &mut dyn FnMut(usize) -> anyhow::Result<()>,
+) -> anyhow::Result<[PathBuf; 2]> {
+    let targets = table_paths(path, format);
+    if let Some(dir) = targets[0].1.parent() { std::fs::create_dir_all(dir)?; }
+    let mut offset = 0;
+    for (i, (which, target)) in targets.iter().enumerate() {
+        let written = std::fs::File::create(target).map_err(anyhow::Error::from).and_then(|file| {
+            let mut w = std::io::BufWriter::new(file);
+            write(db, *which, template, format, &mut w, &mut |rows| on_progress(offset + rows))?;
+            std::io::Write::flush(&mut w)?;
+            Ok(())
+        });
+        if let Err(e) = written {
+            for (_, t) in &targets[..=i] { let _ = std::fs::remove_file(t); }
+            return Err(e);
+        }
+        offset += if *which == Table::Nodes { db.nodes.len() } else { db.relationships.len() };
+    }
+    let [(_, nodes), (_, rels)] = targets;
+    Ok([nodes, rels])
+}
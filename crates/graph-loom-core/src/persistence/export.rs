@@ -0,0 +1,407 @@
+//! Whole-graph exports rendered a chunk at a time, so the HTTP API can stream large graphs
+//! instead of building the document in memory first, and the export files written from them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::columnar::{self, ColumnarFormat, ExportTemplate};
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+// Elements rendered per chunk
+const CHUNK_ELEMENTS: usize = 256;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `{"nodes": [...], "relationships": [...]}`, the shape File → Export writes.
+    Json,
+    /// The nodes file of a CSV export.
+    CsvNodes,
+    /// The relationships file of a CSV export.
+    CsvRelationships,
+    GraphMl,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::CsvNodes | ExportFormat::CsvRelationships => "text/csv; charset=utf-8",
+            ExportFormat::GraphMl => "application/xml",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "graph.json",
+            ExportFormat::CsvNodes => "graph_nodes.csv",
+            ExportFormat::CsvRelationships => "graph_relationships.csv",
+            ExportFormat::GraphMl => "graph.graphml",
+        }
+    }
+}
+
+/// Formats a whole graph can be saved as (`Graph-Loom export`, scheduled exports).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    #[default]
+    Json,
+    Csv,
+    Parquet,
+    Arrow,
+}
+
+impl FileFormat {
+    pub const ALL: [FileFormat; 4] = [FileFormat::Json, FileFormat::Csv, FileFormat::Parquet, FileFormat::Arrow];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "json" => FileFormat::Json,
+            "csv" => FileFormat::Csv,
+            "parquet" => FileFormat::Parquet,
+            "arrow" | "feather" | "ipc" => FileFormat::Arrow,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "JSON",
+            FileFormat::Csv => "CSV",
+            FileFormat::Parquet => "Parquet",
+            FileFormat::Arrow => "Arrow IPC",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Csv => "csv",
+            FileFormat::Parquet => "parquet",
+            FileFormat::Arrow => "arrow",
+        }
+    }
+}
+
+/// Write `db` to `path` and return the files written. Formats other than JSON write a nodes
+/// and a relationships file next to it (see [`columnar::table_paths`]); CSV without a
+/// template is the round-trip format File → Import reads back.
+pub fn write_files(db: &GraphDatabase, path: &Path, format: FileFormat, template: Option<&ExportTemplate>) -> anyhow::Result<Vec<PathBuf>> {
+    let columnar = match format {
+        FileFormat::Parquet => Some(ColumnarFormat::Parquet),
+        FileFormat::Arrow => Some(ColumnarFormat::ArrowIpc),
+        FileFormat::Csv if template.is_some() => Some(ColumnarFormat::Csv),
+        FileFormat::Json | FileFormat::Csv => None,
+    };
+    if let Some(columnar) = columnar {
+        let template = template.cloned().unwrap_or_default();
+        return Ok(columnar::export_files(db, path, &template, columnar, &mut |_| Ok(()))?.to_vec());
+    }
+    let targets = if format == FileFormat::Json {
+        vec![(ExportFormat::Json, path.to_path_buf())]
+    } else {
+        columnar::table_paths(path, ColumnarFormat::Csv)
+            .into_iter()
+            .zip([ExportFormat::CsvNodes, ExportFormat::CsvRelationships])
+            .map(|((_, p), f)| (f, p))
+            .collect()
+    };
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+    let db = Arc::new(db.clone());
+    for (format, target) in &targets {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(target).with_context(|| format!("creating {}", target.display()))?);
+        for chunk in ExportChunks::new(db.clone(), *format) { w.write_all(&chunk)?; }
+        w.flush()?;
+    }
+    Ok(targets.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Copy of `db` restricted to nodes with one of `labels` (all nodes when `None`) and the
+/// relationships between them.
+pub fn snapshot(db: &GraphDatabase, labels: Option<&[String]>) -> GraphDatabase {
+    let Some(labels) = labels else { return db.clone() };
+    let mut out = GraphDatabase::new();
+    out.metadata = db.metadata.clone();
+    out.unique_relationship_labels = db.unique_relationship_labels.clone();
+    out.property_rules = db.property_rules.clone();
+    out.computed_properties = db.computed_properties.clone();
+    out.derived_edges = db.derived_edges.clone();
+    out.views = db.views.clone();
+    out.nodes = db.nodes.iter().filter(|(_, n)| labels.contains(&n.label)).map(|(id, n)| (*id, n.clone())).collect();
+    out.relationships = db
+        .relationships
+        .iter()
+        .filter(|(_, r)| out.nodes.contains_key(&r.from_node) && out.nodes.contains_key(&r.to_node))
+        .map(|(id, r)| (*id, r.clone()))
+        .collect();
+    out
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+    Header,
+    Nodes,
+    Relationships,
+    Footer,
+    Done,
+}
+
+/// Iterator over the bytes of an export. Elements are written in id order. The graph is
+/// shared, so the files of one export can be rendered from one copy.
+pub struct ExportChunks {
+    db: Arc<GraphDatabase>,
+    format: ExportFormat,
+    nodes: Vec<NodeId>,
+    rels: Vec<Uuid>,
+    // Relationship ids touching each node: (outgoing, incoming)
+    adjacency: HashMap<NodeId, (Vec<Uuid>, Vec<Uuid>)>,
+    // GraphML <key> ids for metadata keys
+    keys: Vec<String>,
+    stage: Stage,
+    pos: usize,
+}
+
+impl ExportChunks {
+    pub fn new(db: impl Into<Arc<GraphDatabase>>, format: ExportFormat) -> Self {
+        let db = db.into();
+        let mut nodes: Vec<NodeId> = match format {
+            ExportFormat::CsvRelationships => Vec::new(),
+            _ => db.nodes.keys().copied().collect(),
+        };
+        nodes.sort();
+        let mut rels: Vec<Uuid> = match format {
+            ExportFormat::CsvNodes => Vec::new(),
+            _ => db.relationships.keys().copied().collect(),
+        };
+        rels.sort();
+        let mut adjacency: HashMap<NodeId, (Vec<Uuid>, Vec<Uuid>)> = HashMap::new();
+        if matches!(format, ExportFormat::Json | ExportFormat::CsvNodes) {
+            let mut all: Vec<&Uuid> = db.relationships.keys().collect();
+            all.sort();
+            for id in all {
+                let r = &db.relationships[id];
+                // A self-loop is listed both as outgoing and incoming
+                adjacency.entry(r.from_node).or_default().0.push(*id);
+                adjacency.entry(r.to_node).or_default().1.push(*id);
+            }
+        }
+        let keys = if format == ExportFormat::GraphMl {
+            let set: BTreeSet<&String> = db
+                .nodes
+                .values()
+                .flat_map(|n| n.metadata.keys())
+                .chain(db.relationships.values().flat_map(|r| r.metadata.keys()))
+                .collect();
+            set.into_iter().cloned().collect()
+        } else {
+            Vec::new()
+        };
+        ExportChunks { db, format, nodes, rels, adjacency, keys, stage: Stage::Header, pos: 0 }
+    }
+
+    /// Elements written so far and in total, for progress reporting.
+    pub fn progress(&self) -> (usize, usize) {
+        let total = self.nodes.len() + self.rels.len();
+        let done = match self.stage {
+            Stage::Header => 0,
+            Stage::Nodes => self.pos,
+            Stage::Relationships => self.nodes.len() + self.pos,
+            Stage::Footer | Stage::Done => total,
+        };
+        (done, total)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Json => b"{\"nodes\":[".to_vec(),
+            ExportFormat::CsvNodes => csv_line(&["id", "label", "metadata_json", "out_rels_json", "in_rels_json", "created_at", "updated_at", "description"]),
+            ExportFormat::CsvRelationships => csv_line(&["id", "from", "to", "label", "metadata_json", "created_at", "updated_at", "description"]),
+            ExportFormat::GraphMl => {
+                let mut s = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+                for k in ["label", "description", "created_at", "updated_at"] {
+                    s.push_str(&format!("  <key id=\"{0}\" for=\"all\" attr.name=\"{0}\" attr.type=\"string\"/>\n", k));
+                }
+                for (i, k) in self.keys.iter().enumerate() {
+                    s.push_str(&format!("  <key id=\"d{}\" for=\"all\" attr.name=\"{}\" attr.type=\"string\"/>\n", i, xml_escape(k)));
+                }
+                s.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+                s.into_bytes()
+            }
+        }
+    }
+
+    fn footer(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Json => b"]}\n".to_vec(),
+            ExportFormat::GraphMl => b"  </graph>\n</graphml>\n".to_vec(),
+            ExportFormat::CsvNodes | ExportFormat::CsvRelationships => Vec::new(),
+        }
+    }
+
+    fn write_node(&self, out: &mut Vec<u8>, id: &NodeId, first: bool) {
+        let n = &self.db.nodes[id];
+        let empty = (Vec::new(), Vec::new());
+        let (outs, ins) = self.adjacency.get(id).unwrap_or(&empty);
+        let refs = |ids: &[Uuid], dir: &str| -> Vec<serde_json::Value> {
+            ids.iter()
+                .map(|rid| {
+                    let r = &self.db.relationships[rid];
+                    let peer = if dir == "out" { r.to_node } else { r.from_node };
+                    serde_json::json!({"rel_id": r.id, "label": r.label, "peer": peer, "direction": dir})
+                })
+                .collect()
+        };
+        match self.format {
+            ExportFormat::Json => {
+                if !first { out.push(b','); }
+                let v = serde_json::json!({
+                    "id": n.id, "label": n.label, "metadata": n.metadata, "description": n.description,
+                    "created_at": n.created_at, "updated_at": n.updated_at,
+                    "out_rels": refs(outs, "out"), "in_rels": refs(ins, "in"),
+                });
+                out.extend_from_slice(v.to_string().as_bytes());
+            }
+            ExportFormat::CsvNodes => {
+                // Same columns as the GUI's CSV export
+                let out_refs: Vec<serde_json::Value> = outs
+                    .iter()
+                    .map(|rid| { let r = &self.db.relationships[rid]; serde_json::json!({"rel_id": r.id, "label": r.label, "to": r.to_node}) })
+                    .collect();
+                let in_refs: Vec<serde_json::Value> = ins
+                    .iter()
+                    .map(|rid| { let r = &self.db.relationships[rid]; serde_json::json!({"rel_id": r.id, "label": r.label, "from": r.from_node}) })
+                    .collect();
+                out.extend(csv_line(&[
+                    &n.id.to_string(),
+                    &n.label,
+                    &serde_json::to_string(&n.metadata).unwrap_or_else(|_| "{}".into()),
+                    &serde_json::Value::from(out_refs).to_string(),
+                    &serde_json::Value::from(in_refs).to_string(),
+                    &n.created_at,
+                    &n.updated_at,
+                    &n.description,
+                ]));
+            }
+            ExportFormat::GraphMl => {
+                out.extend(format!("    <node id=\"{}\">\n", n.id).into_bytes());
+                self.graphml_data(out, [&n.label, &n.description, &n.created_at, &n.updated_at], &n.metadata);
+                out.extend_from_slice(b"    </node>\n");
+            }
+            ExportFormat::CsvRelationships => {}
+        }
+    }
+
+    fn write_rel(&self, out: &mut Vec<u8>, id: &Uuid, first: bool) {
+        let r = &self.db.relationships[id];
+        match self.format {
+            ExportFormat::Json => {
+                if !first { out.push(b','); }
+                let v = serde_json::json!({
+                    "id": r.id, "from": r.from_node, "to": r.to_node, "label": r.label,
+                    "metadata": r.metadata, "description": r.description,
+                    "created_at": r.created_at, "updated_at": r.updated_at,
+                });
+                out.extend_from_slice(v.to_string().as_bytes());
+            }
+            ExportFormat::CsvRelationships => out.extend(csv_line(&[
+                &r.id.to_string(),
+                &r.from_node.to_string(),
+                &r.to_node.to_string(),
+                &r.label,
+                &serde_json::to_string(&r.metadata).unwrap_or_else(|_| "{}".into()),
+                &r.created_at,
+                &r.updated_at,
+                &r.description,
+            ])),
+            ExportFormat::GraphMl => {
+                out.extend(format!("    <edge id=\"{}\" source=\"{}\" target=\"{}\">\n", r.id, r.from_node, r.to_node).into_bytes());
+                self.graphml_data(out, [&r.label, &r.description, &r.created_at, &r.updated_at], &r.metadata);
+                out.extend_from_slice(b"    </edge>\n");
+            }
+            ExportFormat::CsvNodes => {}
+        }
+    }
+
+    // `fixed` holds label, description, created_at and updated_at; an empty description is left out
+    fn graphml_data(&self, out: &mut Vec<u8>, fixed: [&str; 4], metadata: &HashMap<String, String>) {
+        let mut s = String::new();
+        for (k, v) in ["label", "description", "created_at", "updated_at"].into_iter().zip(fixed) {
+            if k == "description" && v.is_empty() { continue; }
+            s.push_str(&format!("      <data key=\"{}\">{}</data>\n", k, xml_escape(v)));
+        }
+        for (i, k) in self.keys.iter().enumerate() {
+            if let Some(v) = metadata.get(k) {
+                s.push_str(&format!("      <data key=\"d{}\">{}</data>\n", i, xml_escape(v)));
+            }
+        }
+        out.extend(s.into_bytes());
+    }
+}
+
+impl Iterator for ExportChunks {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        loop {
+            match self.stage {
+                Stage::Header => {
+                    self.stage = Stage::Nodes;
+                    return Some(self.header());
+                }
+                Stage::Nodes if self.pos < self.nodes.len() => {
+                    let end = (self.pos + CHUNK_ELEMENTS).min(self.nodes.len());
+                    let mut out = Vec::new();
+                    for i in self.pos..end { self.write_node(&mut out, &self.nodes[i], i == 0); }
+                    self.pos = end;
+                    return Some(out);
+                }
+                Stage::Nodes => {
+                    self.stage = Stage::Relationships;
+                    self.pos = 0;
+                    if self.format == ExportFormat::Json { return Some(b"],\"relationships\":[".to_vec()); }
+                }
+                Stage::Relationships if self.pos < self.rels.len() => {
+                    let end = (self.pos + CHUNK_ELEMENTS).min(self.rels.len());
+                    let mut out = Vec::new();
+                    for i in self.pos..end { self.write_rel(&mut out, &self.rels[i], i == 0); }
+                    self.pos = end;
+                    return Some(out);
+                }
+                Stage::Relationships => self.stage = Stage::Footer,
+                Stage::Footer => {
+                    self.stage = Stage::Done;
+                    let f = self.footer();
+                    if !f.is_empty() { return Some(f); }
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+fn csv_line(fields: &[&str]) -> Vec<u8> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    let _ = wtr.write_record(fields);
+    wtr.into_inner().unwrap_or_default()
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,92 @@
+//! Scheduled exports: the graph written to a folder on a timer or when the app closes, for
+//! pipelines that pick the files up downstream. Each run replaces the previous files whole, so
+//! a reader sees the old export or the new one, never part of one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail};
+use uuid::Uuid;
+
+use super::export::{self, FileFormat};
+use super::settings::{AppSettings, ScheduledExport};
+use crate::graph_utils::graph::GraphDatabase;
+
+/// Base name of the exported files, e.g. `graph.json` or `graph_nodes.parquet`.
+pub const FILE_STEM: &str = "graph";
+
+/// The file a run writes first (the nodes table for formats with two files).
+pub fn first_file(config: &ScheduledExport) -> PathBuf {
+    let dir = Path::new(config.dir.trim());
+    match config.format {
+        FileFormat::Json => dir.join(format!("{}.json", FILE_STEM)),
+        f => dir.join(format!("{}_nodes.{}", FILE_STEM, f.extension())),
+    }
+}
+
+/// Write `db` to the configured folder and return the files written. They are written to a
+/// hidden folder next to their targets first and renamed over the previous export at the end.
+pub fn run(db: &GraphDatabase, config: &ScheduledExport, settings: &AppSettings) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = Path::new(config.dir.trim());
+    if dir.as_os_str().is_empty() { bail!("no folder set for scheduled exports"); }
+    let template = match &config.template {
+        Some(name) if config.format != FileFormat::Json => Some(settings.export_template(name)?),
+        _ => None,
+    };
+    // One staging folder per run, so a run at close cannot collide with a timed one still going
+    let staging = dir.join(format!(".graph-loom-export-{}", Uuid::now_v7()));
+    let path = staging.join(format!("{}.{}", FILE_STEM, config.format.extension()));
+    let moved = export::write_files(db, &path, config.format, template.as_ref()).and_then(|files| {
+        files
+            .iter()
+            .map(|f| {
+                let to = dir.join(f.file_name().ok_or_else(|| anyhow!("export wrote {}", f.display()))?);
+                fs::rename(f, &to)?;
+                Ok(to)
+            })
+            .collect()
+    });
+    let _ = fs::remove_dir_all(&staging);
+    moved
+}
+
+/// Decides when the next scheduled export is due. Exports are skipped while the graph is
+/// unchanged, and the clock starts from the files already in the folder so restarts do not
+/// reset it.
+#[derive(Debug, Clone)]
+pub struct ExportSchedule {
+    last: Option<SystemTime>,
+    changed: bool,
+}
+
+impl ExportSchedule {
+    /// Without files from an earlier export the first one is due right away.
+    pub fn new(config: &ScheduledExport) -> Self {
+        let last = fs::metadata(first_file(config)).ok().and_then(|m| m.modified().ok());
+        ExportSchedule { last, changed: last.is_none() }
+    }
+
+    /// The graph changed since the last export.
+    pub fn note_change(&mut self) {
+        self.changed = true;
+    }
+
+    /// Whether the timer in `config` calls for an export at `now`.
+    pub fn is_due(&self, config: &ScheduledExport, now: SystemTime) -> bool {
+        if config.interval_minutes == 0 || config.dir.trim().is_empty() || !self.changed { return false; }
+        let interval = Duration::from_secs(u64::from(config.interval_minutes) * 60);
+        self.last.is_none_or(|t| now.duration_since(t).is_ok_and(|d| d >= interval))
+    }
+
+    /// Whether closing the app calls for an export.
+    pub fn is_due_on_close(&self, config: &ScheduledExport) -> bool {
+        config.on_close && !config.dir.trim().is_empty() && self.changed
+    }
+
+    /// An export of the graph as of `now` has started.
+    pub fn started(&mut self, now: SystemTime) {
+        self.last = Some(now);
+        self.changed = false;
+    }
+}
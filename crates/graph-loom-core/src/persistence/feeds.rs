@@ -0,0 +1,364 @@
+//! JSON feeds: an HTTP API whose JSON response is mapped to nodes and relationships with
+//! jq-like path expressions and imported again on a timer, so the graph follows a live
+//! inventory. Feeds are saved with the graph; fetching is left to the app. Their headers are
+//! not: they tend to carry tokens, so the app keeps them with the user's settings (see
+//! [`AuthoredFeed`](super::settings::AuthoredFeed)).
+//!
+//! A mapping has one rule per line (`#` starts a comment):
+//!
+//! ```text
+//! nodes .services[] id=.id label=Service team=.owner.team
+//! relationships .services[] from=.id to=.depends_on[] label=DEPENDS_ON
+//! ```
+//!
+//! The path after the kind selects the items the rule applies to; values starting with `.`
+//! are paths into each item, others literals (quote a literal with spaces or a leading dot).
+//! Node rules need `id` and take `label` (default `Item`); relationship rules need `from` and
+//! `to`, holding node ids, and take `label` (default `RELATED_TO`) and `id`. Other keys become
+//! metadata; a node rule without any takes every field of the item.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::import::{external_id, ImportPayload};
+
+const DEFAULT_NODE_LABEL: &str = "Item";
+const DEFAULT_REL_LABEL: &str = "RELATED_TO";
+
+/// An HTTP API imported with a mapping, by hand or every `interval_mins` minutes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feed {
+    // Imported IDs derive from this, so renaming the feed or moving its URL keeps them
+    pub id: Uuid,
+    pub name: String,
+    pub url: String,
+    /// `Name: value` lines sent with the request; never written to the graph file
+    #[serde(skip)]
+    pub headers: String,
+    /// Minutes between imports; 0 imports only on request
+    #[serde(default)]
+    pub interval_mins: u32,
+    /// Rules in the syntax described in the module docs
+    pub mapping: String,
+    /// Written by this user rather than read from a graph file; only then is `${VAR}` expanded,
+    /// so a file from elsewhere cannot send environment variables to a URL of its choosing
+    #[serde(skip)]
+    pub authored: bool,
+}
+
+impl Feed {
+    pub fn new(name: impl Into<String>, url: impl Into<String>, mapping: impl Into<String>) -> Self {
+        Feed { id: Uuid::now_v7(), name: name.into(), url: url.into(), headers: String::new(), interval_mins: 0, mapping: mapping.into(), authored: true }
+    }
+
+    /// The URL with `${VAR}` replaced from the environment, so tokens need not be saved.
+    pub fn resolved_url(&self) -> Result<String> {
+        self.expand(self.url.trim())
+    }
+
+    /// The header lines as (name, value), with `${VAR}` replaced from the environment.
+    pub fn resolved_headers(&self) -> Result<Vec<(String, String)>> {
+        self.headers
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                let (name, value) = l.split_once(':').ok_or_else(|| anyhow!("header {:?} is not `Name: value`", l))?;
+                Ok((name.trim().to_string(), self.expand(value.trim())?))
+            })
+            .collect()
+    }
+
+    // `${VAR}` is only read for authored feeds; others that use it fail rather than send it as is
+    fn expand(&self, s: &str) -> Result<String> {
+        if self.authored { return expand_env(s); }
+        if s.contains("${") { bail!("feed {:?} comes from a graph file; ${{VAR}} is only read for feeds you wrote", self.name); }
+        Ok(s.to_string())
+    }
+
+    /// Map a response body to graph elements.
+    pub fn materialize(&self, body: &str) -> Result<ImportPayload> {
+        let mapping = FeedMapping::parse(&self.mapping)?;
+        let json: Value = serde_json::from_str(body).map_err(|e| anyhow!("the response is not JSON: {}", e))?;
+        Ok(mapping.apply(&format!("feed:{}", self.id), &json))
+    }
+}
+
+// `${VAR}` from the environment; a missing variable is an error rather than an empty string
+fn expand_env(s: &str) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| anyhow!("unclosed ${{ in {:?}", s))? + start;
+        let var = &rest[start + 2..end];
+        out.push_str(&std::env::var(var).map_err(|_| anyhow!("environment variable {} is not set", var))?);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A path into JSON: `.` for the value itself, `.name` for a field, `[n]` for an array
+/// element and `[]` for every element (or every field value of an object).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<Step>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Field(String),
+    Index(usize),
+    Each,
+}
+
+impl JsonPath {
+    pub fn parse(s: &str) -> Result<JsonPath> {
+        let s = s.trim();
+        if !s.starts_with('.') { bail!("path {:?} must start with `.`", s); }
+        let mut steps = Vec::new();
+        let mut rest = s;
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(|| anyhow!("unclosed [ in path {:?}", s))?;
+                let inner = r[..end].trim();
+                steps.push(if inner.is_empty() {
+                    Step::Each
+                } else {
+                    Step::Index(inner.parse().map_err(|_| anyhow!("{:?} in path {:?} is not an index", inner, s))?)
+                });
+                rest = &r[end + 1..];
+            } else if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end > 0 { steps.push(Step::Field(r[..end].to_string())); }
+                rest = &r[end..];
+            } else {
+                bail!("unexpected {:?} in path {:?}", rest, s);
+            }
+        }
+        Ok(JsonPath(steps))
+    }
+
+    /// The values the path leads to; steps that do not fit the value lead nowhere.
+    pub fn eval<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut at = vec![root];
+        for step in &self.0 {
+            at = at
+                .into_iter()
+                .flat_map(|v| -> Vec<&Value> {
+                    match (step, v) {
+                        (Step::Field(f), Value::Object(m)) => m.get(f).into_iter().collect(),
+                        (Step::Index(i), Value::Array(a)) => a.get(*i).into_iter().collect(),
+                        (Step::Each, Value::Array(a)) => a.iter().collect(),
+                        (Step::Each, Value::Object(m)) => m.values().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        at
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() { return f.write_str("."); }
+        for step in &self.0 {
+            match step {
+                Step::Field(name) => write!(f, ".{}", name)?,
+                Step::Index(i) => write!(f, "[{}]", i)?,
+                Step::Each => f.write_str("[]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A rule's value for a key: a path into each item or a literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    Path(JsonPath),
+    Literal(String),
+}
+
+impl FieldValue {
+    // Text of each value; NULLs and missing fields give none
+    fn texts(&self, item: &Value) -> Vec<String> {
+        match self {
+            FieldValue::Literal(s) => vec![s.clone()],
+            FieldValue::Path(p) => p.eval(item).into_iter().filter_map(text).collect(),
+        }
+    }
+
+    fn first(&self, item: &Value) -> Option<String> {
+        self.texts(item).into_iter().next()
+    }
+}
+
+fn text(v: &Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Nodes,
+    Relationships,
+}
+
+/// One mapping line: the items `select` finds and the fields taken from each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedRule {
+    pub kind: RuleKind,
+    pub select: JsonPath,
+    pub fields: Vec<(String, FieldValue)>,
+}
+
+impl FeedRule {
+    fn field(&self, key: &str) -> Option<&FieldValue> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn metadata(&self, item: &Value, reserved: &[&str]) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .filter(|(k, _)| !reserved.contains(&k.as_str()))
+            .filter_map(|(k, v)| {
+                let texts = v.texts(item);
+                let value = if texts.len() > 1 { serde_json::to_string(&texts).ok()? } else { texts.into_iter().next()? };
+                Some((k.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// A parsed mapping.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedMapping {
+    pub rules: Vec<FeedRule>,
+}
+
+impl FeedMapping {
+    pub fn parse(text: &str) -> Result<FeedMapping> {
+        let mut rules = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            rules.push(parse_rule(line).map_err(|e| anyhow!("mapping line {}: {}", n + 1, e))?);
+        }
+        if rules.is_empty() { bail!("the mapping has no rules"); }
+        Ok(FeedMapping { rules })
+    }
+
+    /// Graph elements for `json`, with IDs derived from `source` and the mapped ids.
+    pub fn apply(&self, source: &str, json: &Value) -> ImportPayload {
+        let node_id = |id: &str| external_id(source, &format!("node/{}", id));
+        let mut payload = ImportPayload::default();
+        for rule in &self.rules {
+            for item in rule.select.eval(json) {
+                match rule.kind {
+                    RuleKind::Nodes => {
+                        let Some(id) = rule.field("id").and_then(|v| v.first(item)) else { continue };
+                        let label = rule.field("label").and_then(|v| v.first(item)).unwrap_or_else(|| DEFAULT_NODE_LABEL.to_string());
+                        let metadata = if rule.fields.iter().all(|(k, _)| k == "id" || k == "label") {
+                            match item {
+                                Value::Object(m) => m.iter().filter_map(|(k, v)| Some((k.clone(), text(v)?))).collect(),
+                                _ => HashMap::new(),
+                            }
+                        } else {
+                            rule.metadata(item, &["id", "label"])
+                        };
+                        payload.add_node(node_id(&id), label, metadata);
+                    }
+                    RuleKind::Relationships => {
+                        let ends = |key: &str| rule.field(key).map(|v| v.texts(item)).unwrap_or_default();
+                        let label = rule.field("label").and_then(|v| v.first(item)).unwrap_or_else(|| DEFAULT_REL_LABEL.to_string());
+                        let id = rule.field("id").and_then(|v| v.first(item));
+                        let metadata = rule.metadata(item, &["id", "from", "to", "label"]);
+                        // A path to a list (`.depends_on[]`) makes one relationship per entry
+                        for from in ends("from") {
+                            for to in ends("to") {
+                                let key = match &id {
+                                    Some(id) => format!("rel/{}/{}/{}", id, from, to),
+                                    None => format!("rel/{}", serde_json::json!([from, label, to])),
+                                };
+                                payload.add_relationship(external_id(source, &key), node_id(&from), node_id(&to), label.clone(), metadata.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        payload
+    }
+}
+
+fn parse_rule(line: &str) -> Result<FeedRule> {
+    let mut words = split_words(line)?.into_iter();
+    let kind = match words.next().as_deref() {
+        Some("nodes") => RuleKind::Nodes,
+        Some("relationships" | "rels") => RuleKind::Relationships,
+        other => bail!("expected `nodes` or `relationships`, found {:?}", other.unwrap_or_default()),
+    };
+    let select = JsonPath::parse(&words.next().ok_or_else(|| anyhow!("missing the path selecting the items"))?)?;
+    let mut fields = Vec::new();
+    for w in words {
+        let (key, value) = w.split_once('=').ok_or_else(|| anyhow!("{:?} is not key=value", w))?;
+        if key.is_empty() { bail!("{:?} has no key", w); }
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            FieldValue::Literal(unquote(quoted).ok_or_else(|| anyhow!("unclosed quote in {:?}", w))?)
+        } else if value.starts_with('.') {
+            FieldValue::Path(JsonPath::parse(value)?)
+        } else {
+            FieldValue::Literal(value.to_string())
+        };
+        fields.push((key.to_string(), value));
+    }
+    let has = |k: &str| fields.iter().any(|(f, _)| f == k);
+    match kind {
+        RuleKind::Nodes if !has("id") => bail!("a nodes rule needs id="),
+        RuleKind::Relationships if !has("from") || !has("to") => bail!("a relationships rule needs from= and to="),
+        _ => {}
+    }
+    Ok(FeedRule { kind, select, fields })
+}
+
+// Words split on whitespace outside quotes, so `key="a b"` stays one word
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut quoted, mut escaped) = (false, false);
+    for c in line.chars() {
+        if c.is_whitespace() && !quoted {
+            if !word.is_empty() { words.push(std::mem::take(&mut word)); }
+            continue;
+        }
+        if quoted && !escaped && c == '"' { quoted = false; } else if !quoted && c == '"' { quoted = true; }
+        escaped = quoted && !escaped && c == '\\';
+        word.push(c);
+    }
+    if quoted { bail!("unclosed quote"); }
+    if !word.is_empty() { words.push(word); }
+    Ok(words)
+}
+
+// The text of a quoted literal after its opening quote, with `\"` and `\\` escapes
+fn unquote(s: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return chars.as_str().is_empty().then_some(out),
+            '\\' => out.push(chars.next()?),
+            c => out.push(c),
+        }
+    }
+    None
+}
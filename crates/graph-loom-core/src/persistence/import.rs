@@ -0,0 +1,792 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::graph_utils::changes::ElementState;
+use crate::graph_utils::graph::{GraphDatabase, Node, NodeId, Relationship};
+
+/// Counts of what an import changed in the target graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub nodes_added: usize,
+    pub nodes_updated: usize,
+    pub rels_added: usize,
+    pub rels_updated: usize,
+    // Relationships whose endpoints were not present after the nodes were merged
+    pub rels_skipped: usize,
+    // Elements that matched an existing one by key and were left out (`OnMatch::Skip`)
+    pub kept: usize,
+}
+
+impl ImportSummary {
+    pub fn is_empty(&self) -> bool {
+        self.nodes_added + self.nodes_updated + self.rels_added + self.rels_updated == 0
+    }
+
+    /// Accumulate another summary into this one.
+    pub fn add(&mut self, other: &ImportSummary) {
+        self.nodes_added += other.nodes_added;
+        self.nodes_updated += other.nodes_updated;
+        self.rels_added += other.rels_added;
+        self.rels_updated += other.rels_updated;
+        self.rels_skipped += other.rels_skipped;
+        self.kept += other.kept;
+    }
+
+    pub fn describe(&self) -> String {
+        let mut s = format!(
+            "nodes +{} ~{}, relationships +{} ~{}",
+            self.nodes_added, self.nodes_updated, self.rels_added, self.rels_updated
+        );
+        if self.rels_skipped > 0 {
+            s.push_str(&format!(" ({} skipped)", self.rels_skipped));
+        }
+        if self.kept > 0 {
+            s.push_str(&format!(", {} matched and kept as they were", self.kept));
+        }
+        s
+    }
+}
+
+/// Counts of what an import changes and the problems that keep elements out of it: rows that
+/// do not parse (bad UUIDs, malformed metadata) and relationships whose endpoints are missing.
+/// A dry run reports it before anything is committed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub summary: ImportSummary,
+    pub problems: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn describe(&self) -> String {
+        match self.problems.len() {
+            0 => self.summary.describe(),
+            n => format!("{}; {} problem(s)", self.summary.describe(), n),
+        }
+    }
+}
+
+// Shapes accepted on input. These mirror what the exporters write (see gui::frontend
+// export helpers); unknown fields such as out_rels/in_rels are ignored.
+#[derive(Debug, Clone, Deserialize)]
+struct NodeIn {
+    #[serde(default)]
+    id: Option<Uuid>,
+    label: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    // Replaces the existing description when present
+    #[serde(default)]
+    description: Option<String>,
+    // Timestamps are kept for new elements so a round trip preserves them
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RelIn {
+    #[serde(default)]
+    id: Option<Uuid>,
+    from: Uuid,
+    to: Uuid,
+    label: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    updated_at: Option<String>,
+}
+
+// (created_at, updated_at) for a new element: the imported values, else the import time
+fn import_timestamps(created_at: Option<String>, updated_at: Option<String>) -> (String, String) {
+    let created = created_at.filter(|s| !s.trim().is_empty()).unwrap_or_else(crate::gql::temporal::now);
+    let updated = updated_at.filter(|s| !s.trim().is_empty()).unwrap_or_else(|| created.clone());
+    (created, updated)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonIn {
+    Graph {
+        nodes: Vec<NodeIn>,
+        #[serde(default)]
+        relationships: Vec<RelIn>,
+    },
+    Nodes(Vec<NodeIn>),
+}
+
+// Insert or update a node keeping its id. Metadata is upserted key by key.
+fn merge_node(db: &mut GraphDatabase, n: NodeIn, summary: &mut ImportSummary) -> NodeId {
+    let id = n.id.unwrap_or_else(Uuid::now_v7);
+    db.invalidate_vocabulary();
+    let before = db.nodes.get(&id).map(ElementState::from);
+    match db.nodes.get_mut(&id) {
+        Some(existing) => {
+            existing.label = n.label;
+            existing.metadata.extend(n.metadata);
+            if let Some(d) = n.description { existing.description = d; }
+            db.touch_node(id);
+            summary.nodes_updated += 1;
+        }
+        None => {
+            let (created_at, updated_at) = import_timestamps(n.created_at, n.updated_at);
+            let description = n.description.unwrap_or_default();
+            db.nodes.insert(id, Node { id, label: n.label, metadata: n.metadata, description, created_at, updated_at });
+            summary.nodes_added += 1;
+        }
+    }
+    db.record_node_change(id, before);
+    id
+}
+
+// Returns the relationship merged into, or None when an endpoint is missing
+fn merge_rel(db: &mut GraphDatabase, r: RelIn, summary: &mut ImportSummary) -> Option<Uuid> {
+    if !db.nodes.contains_key(&r.from) || !db.nodes.contains_key(&r.to) {
+        summary.rels_skipped += 1;
+        return None;
+    }
+    let id = r.id.unwrap_or_else(Uuid::now_v7);
+    db.invalidate_vocabulary();
+    let before = db.relationships.get(&id).map(ElementState::from);
+    match db.relationships.get_mut(&id) {
+        Some(existing) => {
+            existing.from_node = r.from;
+            existing.to_node = r.to;
+            existing.label = r.label;
+            existing.metadata.extend(r.metadata);
+            if let Some(d) = r.description { existing.description = d; }
+            db.touch_relationship(id);
+            db.record_relationship_change(id, before);
+            summary.rels_updated += 1;
+        }
+        None => {
+            // Unique label: fold into the edge that already connects this pair
+            if db.is_relationship_unique(&r.label)
+                && let Some(existing) = db.find_relationship(r.from, r.to, &r.label)
+            {
+                let before = db.relationships.get(&existing).map(ElementState::from);
+                if let Some(rel) = db.relationships.get_mut(&existing) { rel.metadata.extend(r.metadata); }
+                db.touch_relationship(existing);
+                db.record_relationship_change(existing, before);
+                summary.rels_updated += 1;
+                return Some(existing);
+            }
+            let (created_at, updated_at) = import_timestamps(r.created_at, r.updated_at);
+            let description = r.description.unwrap_or_default();
+            db.relationships.insert(
+                id,
+                Relationship { id, from_node: r.from, to_node: r.to, label: r.label, metadata: r.metadata, description, created_at, updated_at },
+            );
+            db.record_relationship_change(id, None);
+            summary.rels_added += 1;
+        }
+    }
+    Some(id)
+}
+
+// One NDJSON line; relationships are tried first since they also carry a label
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ElementIn {
+    Rel(RelIn),
+    Node(NodeIn),
+}
+
+/// Nodes and relationships parsed up front so a bulk import can be merged in batches.
+#[derive(Debug, Clone, Default)]
+pub struct ImportPayload {
+    nodes: Vec<NodeIn>,
+    relationships: Vec<RelIn>,
+    // Elements left out because they did not parse, e.g. `line 3: invalid UUID`
+    problems: Vec<String>,
+}
+
+impl ImportPayload {
+    /// Parse a JSON export (whole graph or node list), or NDJSON with one node or
+    /// relationship object per line. Fails on the first element that does not parse.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        Self::parse_checked(s).strict()
+    }
+
+    /// Fail with the first element that did not parse, if any.
+    pub fn strict(self) -> anyhow::Result<Self> {
+        match self.problems.first() {
+            Some(p) => Err(anyhow::anyhow!("{}", p)),
+            None => Ok(self),
+        }
+    }
+
+    /// [`parse`](Self::parse) that leaves out the elements that do not parse and lists them
+    /// in [`problems`](Self::problems).
+    pub fn parse_checked(s: &str) -> Self {
+        let mut out = ImportPayload::default();
+        let element = |v: &serde_json::Value, what: &str, i: usize, problems: &mut Vec<String>| -> Option<ElementIn> {
+            let parsed = if what == "relationships" {
+                serde_json::from_value::<RelIn>(v.clone()).map(ElementIn::Rel)
+            } else {
+                serde_json::from_value::<NodeIn>(v.clone()).map(ElementIn::Node)
+            };
+            parsed.map_err(|e| problems.push(format!("{}[{}]: {}", what, i, e))).ok()
+        };
+        let document = serde_json::from_str::<serde_json::Value>(s).ok();
+        let lists: Option<Vec<(&str, &Vec<serde_json::Value>)>> = match &document {
+            Some(serde_json::Value::Array(nodes)) => Some(vec![("nodes", nodes)]),
+            Some(serde_json::Value::Object(o)) => o.get("nodes").and_then(|n| n.as_array()).map(|nodes| {
+                let mut lists = vec![("nodes", nodes)];
+                if let Some(rels) = o.get("relationships").and_then(|r| r.as_array()) { lists.push(("relationships", rels)); }
+                lists
+            }),
+            _ => None,
+        };
+        if let Some(lists) = lists {
+            for (what, items) in lists {
+                for (i, v) in items.iter().enumerate() {
+                    match element(v, what, i, &mut out.problems) {
+                        Some(ElementIn::Rel(r)) => out.relationships.push(r),
+                        Some(ElementIn::Node(n)) => out.nodes.push(n),
+                        None => {}
+                    }
+                }
+            }
+            return out;
+        }
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            match serde_json::from_str::<ElementIn>(line) {
+                Ok(ElementIn::Rel(r)) => out.relationships.push(r),
+                Ok(ElementIn::Node(n)) => out.nodes.push(n),
+                Err(e) => out.problems.push(format!("line {}: not a node or relationship: {}", i + 1, e)),
+            }
+        }
+        out
+    }
+
+    /// Read an exported file, picking the format from the extension like [`merge_file`].
+    /// Rows that do not parse are listed in [`problems`](Self::problems) under the file's name.
+    pub fn read_file(path: &Path) -> anyhow::Result<Self> {
+        let ext = path.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase());
+        let mut out = match ext.as_deref() {
+            Some("json") => Ok(Self::parse_checked(&fs::read_to_string(path)?)),
+            Some("csv") if is_relationships_csv(path) => {
+                let (relationships, problems) = read_relationships_csv(fs::File::open(path)?)?;
+                Ok(ImportPayload { nodes: Vec::new(), relationships, problems })
+            }
+            Some("csv") => {
+                let (nodes, problems) = read_nodes_csv(fs::File::open(path)?)?;
+                Ok(ImportPayload { nodes, relationships: Vec::new(), problems })
+            }
+            _ => Err(anyhow::anyhow!("unsupported import file type: {}", path.display())),
+        }?;
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        for p in &mut out.problems { *p = format!("{} {}", name, p); }
+        Ok(out)
+    }
+
+    /// Add the elements and problems of `other`, e.g. a relationships file read after its nodes.
+    pub fn append(&mut self, other: ImportPayload) {
+        self.nodes.extend(other.nodes);
+        self.relationships.extend(other.relationships);
+        self.problems.extend(other.problems);
+    }
+
+    /// Elements left out because they did not parse.
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// Add a node read from another system; see [`external_id`] for a stable `id`.
+    pub fn add_node(&mut self, id: Uuid, label: impl Into<String>, metadata: HashMap<String, String>) {
+        self.nodes.push(NodeIn { id: Some(id), label: label.into(), metadata, description: None, created_at: None, updated_at: None });
+    }
+
+    /// [`add_node`](Self::add_node) with a description, which replaces the node's own on merge.
+    pub fn add_described_node(&mut self, id: Uuid, label: impl Into<String>, metadata: HashMap<String, String>, description: impl Into<String>) {
+        self.nodes.push(NodeIn { id: Some(id), label: label.into(), metadata, description: Some(description.into()), created_at: None, updated_at: None });
+    }
+
+    /// Add a relationship read from another system. It is skipped on merge unless both
+    /// endpoints are in the payload or already in the graph.
+    pub fn add_relationship(&mut self, id: Uuid, from: Uuid, to: Uuid, label: impl Into<String>, metadata: HashMap<String, String>) {
+        self.relationships.push(RelIn { id: Some(id), from, to, label: label.into(), metadata, description: None, created_at: None, updated_at: None });
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() + self.relationships.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Split into batches of at most `size` elements. All nodes come before any
+    /// relationship so that relationships find their endpoints.
+    pub fn into_batches(self, size: usize) -> Vec<ImportPayload> {
+        let size = size.max(1);
+        let mut batches = Vec::with_capacity(self.len().div_ceil(size));
+        let mut nodes = self.nodes.into_iter().peekable();
+        while nodes.peek().is_some() {
+            batches.push(ImportPayload { nodes: nodes.by_ref().take(size).collect(), ..Default::default() });
+        }
+        let mut rels = self.relationships.into_iter().peekable();
+        while rels.peek().is_some() {
+            batches.push(ImportPayload { relationships: rels.by_ref().take(size).collect(), ..Default::default() });
+        }
+        batches
+    }
+}
+
+/// ID for the element that `source` (e.g. a server address) knows as `key`. It is the same
+/// on every import, so importing an element again updates it instead of adding a copy.
+pub fn external_id(source: &str, key: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("{}#{}", source, key).as_bytes())
+}
+
+/// Merge a parsed payload into `db`, matching nodes by UUID.
+pub fn merge_payload(db: &mut GraphDatabase, payload: ImportPayload) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    for n in payload.nodes { merge_node(db, n, &mut summary); }
+    for r in payload.relationships { merge_rel(db, r, &mut summary); }
+    summary
+}
+
+/// What an import does with an incoming node that matches an existing one by key.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnMatch {
+    /// Leave the existing node as it is; relationships attach to it
+    Skip,
+    /// Add the incoming metadata and description to the existing node, incoming values winning
+    #[default]
+    Update,
+    /// Add the incoming node next to the existing one
+    Create,
+}
+
+impl OnMatch {
+    pub const ALL: [OnMatch; 3] = [OnMatch::Skip, OnMatch::Update, OnMatch::Create];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "skip" => Some(OnMatch::Skip),
+            "update" => Some(OnMatch::Update),
+            "create" => Some(OnMatch::Create),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OnMatch::Skip => "Skip",
+            OnMatch::Update => "Update metadata",
+            OnMatch::Create => "Create anyway",
+        }
+    }
+}
+
+/// How an import finds the node an incoming one stands for. Nodes whose UUID is in the graph
+/// always merge into it; with a `key`, other nodes match the oldest node with the same value
+/// for that metadata key, so sources that mint new ids on every export can be imported again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportMatch {
+    /// Metadata key such as `email`; empty matches by UUID only
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub on_match: OnMatch,
+}
+
+impl ImportMatch {
+    pub fn by_key(key: impl Into<String>, on_match: OnMatch) -> Self {
+        ImportMatch { key: key.into(), on_match }
+    }
+
+    pub fn is_keyed(&self) -> bool {
+        !self.key.trim().is_empty()
+    }
+}
+
+/// Merges payloads one after another under an [`ImportMatch`]. It remembers which existing node
+/// each incoming node matched, so relationships in later batches or files attach to it.
+pub struct Merger {
+    rule: ImportMatch,
+    // Incoming node id -> node it matched
+    targets: HashMap<Uuid, NodeId>,
+    // Key value -> oldest node with it, and (from, to, label) -> relationship; built on first use
+    nodes_by_key: Option<HashMap<String, NodeId>>,
+    rels_by_ends: Option<HashMap<(NodeId, NodeId, String), Uuid>>,
+}
+
+impl Merger {
+    pub fn new(rule: ImportMatch) -> Self {
+        Merger { rule, targets: HashMap::new(), nodes_by_key: None, rels_by_ends: None }
+    }
+
+    pub fn merge(&mut self, db: &mut GraphDatabase, payload: ImportPayload) -> ImportSummary {
+        if !self.rule.is_keyed() { return merge_payload(db, payload); }
+        let mut summary = ImportSummary::default();
+        for n in payload.nodes { self.merge_node(db, n, &mut summary); }
+        for mut r in payload.relationships {
+            r.from = self.targets.get(&r.from).copied().unwrap_or(r.from);
+            r.to = self.targets.get(&r.to).copied().unwrap_or(r.to);
+            self.merge_rel(db, r, &mut summary);
+        }
+        summary
+    }
+
+    /// Merge an exported file like [`merge_file`]; a relationships file merged after its nodes
+    /// file follows the nodes they matched.
+    pub fn merge_file(&mut self, db: &mut GraphDatabase, path: &Path) -> anyhow::Result<ImportSummary> {
+        if !self.rule.is_keyed() { return merge_file(db, path); }
+        let payload = ImportPayload::read_file(path)?.strict()?;
+        Ok(self.merge(db, payload))
+    }
+
+    fn merge_node(&mut self, db: &mut GraphDatabase, n: NodeIn, summary: &mut ImportSummary) {
+        let key = self.rule.key.trim();
+        let value = n.metadata.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let (Some(value), false) = (value, n.id.is_some_and(|id| db.nodes.contains_key(&id))) else {
+            merge_node(db, n, summary);
+            return;
+        };
+        let index = self.nodes_by_key.get_or_insert_with(|| {
+            let mut idx: HashMap<String, NodeId> = HashMap::new();
+            for node in db.nodes.values() {
+                let Some(v) = node.metadata.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) else { continue };
+                let e = idx.entry(v.to_string()).or_insert(node.id);
+                if node.id < *e { *e = node.id; }
+            }
+            idx
+        });
+        let Some(&existing) = index.get(&value).filter(|_| self.rule.on_match != OnMatch::Create) else {
+            let id = merge_node(db, n, summary);
+            index.entry(value).or_insert(id);
+            return;
+        };
+        if let Some(incoming) = n.id { self.targets.insert(incoming, existing); }
+        if self.rule.on_match == OnMatch::Skip {
+            summary.kept += 1;
+            return;
+        }
+        db.invalidate_vocabulary();
+        let before = db.nodes.get(&existing).map(ElementState::from);
+        if let Some(node) = db.nodes.get_mut(&existing) {
+            node.metadata.extend(n.metadata);
+            if let Some(d) = n.description { node.description = d; }
+        }
+        db.touch_node(existing);
+        db.record_node_change(existing, before);
+        summary.nodes_updated += 1;
+    }
+
+    // Relationships without a known id match the one joining the same nodes with the same label
+    fn merge_rel(&mut self, db: &mut GraphDatabase, r: RelIn, summary: &mut ImportSummary) {
+        if r.id.is_some_and(|id| db.relationships.contains_key(&id)) || self.rule.on_match == OnMatch::Create {
+            merge_rel(db, r, summary);
+            return;
+        }
+        let index = self.rels_by_ends.get_or_insert_with(|| {
+            db.relationships.values().map(|rel| ((rel.from_node, rel.to_node, rel.label.clone()), rel.id)).collect()
+        });
+        let ends = (r.from, r.to, r.label.clone());
+        let Some(&existing) = index.get(&ends) else {
+            if let Some(id) = merge_rel(db, r, summary) { index.insert(ends, id); }
+            return;
+        };
+        if self.rule.on_match == OnMatch::Skip {
+            summary.kept += 1;
+            return;
+        }
+        let before = db.relationships.get(&existing).map(ElementState::from);
+        if let Some(rel) = db.relationships.get_mut(&existing) {
+            rel.metadata.extend(r.metadata);
+            if let Some(d) = r.description { rel.description = d; }
+        }
+        db.invalidate_vocabulary();
+        db.touch_relationship(existing);
+        db.record_relationship_change(existing, before);
+        summary.rels_updated += 1;
+    }
+}
+
+/// What merging `payload` into `db` would leave out: the elements that did not parse and the
+/// relationships whose endpoints are neither in the payload nor in `db`.
+pub fn problems(db: &GraphDatabase, payload: &ImportPayload) -> Vec<String> {
+    let mut out = payload.problems.clone();
+    let incoming: HashSet<Uuid> = payload.nodes.iter().filter_map(|n| n.id).collect();
+    for r in &payload.relationships {
+        let missing: Vec<String> = [("from", r.from), ("to", r.to)]
+            .into_iter()
+            .filter(|(_, id)| !db.nodes.contains_key(id) && !incoming.contains(id))
+            .map(|(end, id)| format!("{} node {}", end, id))
+            .collect();
+        if missing.is_empty() { continue; }
+        let name = r.id.map_or_else(|| format!("{} {} -> {}", r.label, r.from, r.to), |id| format!("{} {}", r.label, id));
+        out.push(format!("relationship {}: missing {}", name, missing.join(" and ")));
+    }
+    out
+}
+
+/// Merge `payload` under `rule` and list the [`problems`] that kept elements out.
+pub fn merge_with_report(db: &mut GraphDatabase, payload: ImportPayload, rule: &ImportMatch) -> ImportReport {
+    let problems = problems(db, &payload);
+    ImportReport { summary: Merger::new(rule.clone()).merge(db, payload), problems }
+}
+
+/// Merge a JSON export (whole graph or node list) into `db`.
+pub fn merge_json_str(db: &mut GraphDatabase, s: &str) -> anyhow::Result<ImportSummary> {
+    let parsed: JsonIn = serde_json::from_str(s)
+        .map_err(|e| anyhow::anyhow!("not a Graph-Loom JSON export: {}", e))?;
+    let mut summary = ImportSummary::default();
+    match parsed {
+        JsonIn::Graph { nodes, relationships } => {
+            for n in nodes { merge_node(db, n, &mut summary); }
+            for r in relationships { merge_rel(db, r, &mut summary); }
+        }
+        JsonIn::Nodes(nodes) => {
+            for n in nodes { merge_node(db, n, &mut summary); }
+        }
+    }
+    Ok(summary)
+}
+
+fn parse_meta(s: &str) -> anyhow::Result<HashMap<String, String>> {
+    if s.trim().is_empty() { return Ok(HashMap::new()); }
+    Ok(serde_json::from_str(s)?)
+}
+
+fn header_index(headers: &csv::StringRecord, name: &str) -> Option<usize> {
+    headers.iter().position(|h| h.trim() == name)
+}
+
+// The remaining rows of a CSV file, each parsed by `row`; rows that fail are listed as problems
+// with their line number
+fn read_csv_rows<R: Read, T>(mut rdr: csv::Reader<R>, row: impl Fn(&csv::StringRecord) -> anyhow::Result<T>) -> anyhow::Result<(Vec<T>, Vec<String>)> {
+    let mut rows = Vec::new();
+    let mut problems = Vec::new();
+    for rec in rdr.records() {
+        let rec = rec?;
+        let line = rec.position().map_or(0, |p| p.line());
+        match row(&rec) {
+            Ok(r) => rows.push(r),
+            Err(e) => problems.push(format!("line {}: {}", line, e)),
+        }
+    }
+    Ok((rows, problems))
+}
+
+fn optional_uuid(rec: &csv::StringRecord, i: Option<usize>) -> anyhow::Result<Option<Uuid>> {
+    match i.and_then(|i| rec.get(i)).map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => Ok(Some(Uuid::parse_str(s).map_err(|e| anyhow::anyhow!("invalid UUID '{}': {}", s, e))?)),
+        None => Ok(None),
+    }
+}
+
+fn read_nodes_csv<R: Read>(rdr: R) -> anyhow::Result<(Vec<NodeIn>, Vec<String>)> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let headers = rdr.headers()?.clone();
+    let i_label = header_index(&headers, "label").ok_or_else(|| anyhow::anyhow!("nodes CSV missing 'label' column"))?;
+    let i_id = header_index(&headers, "id");
+    let i_meta = header_index(&headers, "metadata_json");
+    let (i_created, i_updated) = (header_index(&headers, "created_at"), header_index(&headers, "updated_at"));
+    let i_desc = header_index(&headers, "description");
+    read_csv_rows(rdr, |rec| {
+        let id = optional_uuid(rec, i_id)?;
+        let metadata = parse_meta(i_meta.and_then(|i| rec.get(i)).unwrap_or(""))?;
+        let label = rec.get(i_label).unwrap_or("").to_string();
+        let created_at = i_created.and_then(|i| rec.get(i)).map(str::to_string);
+        let updated_at = i_updated.and_then(|i| rec.get(i)).map(str::to_string);
+        let description = i_desc.and_then(|i| rec.get(i)).map(str::to_string);
+        Ok(NodeIn { id, label, metadata, description, created_at, updated_at })
+    })
+}
+
+fn read_relationships_csv<R: Read>(rdr: R) -> anyhow::Result<(Vec<RelIn>, Vec<String>)> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let headers = rdr.headers()?.clone();
+    let col = |name: &str| header_index(&headers, name).ok_or_else(|| anyhow::anyhow!("relationships CSV missing '{}' column", name));
+    let (i_from, i_to, i_label) = (col("from")?, col("to")?, col("label")?);
+    let i_id = header_index(&headers, "id");
+    let i_meta = header_index(&headers, "metadata_json");
+    let (i_created, i_updated) = (header_index(&headers, "created_at"), header_index(&headers, "updated_at"));
+    let i_desc = header_index(&headers, "description");
+    read_csv_rows(rdr, |rec| {
+        let id = optional_uuid(rec, i_id)?;
+        let from = optional_uuid(rec, Some(i_from))?.ok_or_else(|| anyhow::anyhow!("empty 'from'"))?;
+        let to = optional_uuid(rec, Some(i_to))?.ok_or_else(|| anyhow::anyhow!("empty 'to'"))?;
+        let metadata = parse_meta(i_meta.and_then(|i| rec.get(i)).unwrap_or(""))?;
+        let label = rec.get(i_label).unwrap_or("").to_string();
+        let created_at = i_created.and_then(|i| rec.get(i)).map(str::to_string);
+        let updated_at = i_updated.and_then(|i| rec.get(i)).map(str::to_string);
+        let description = i_desc.and_then(|i| rec.get(i)).map(str::to_string);
+        Ok(RelIn { id, from, to, label, metadata, description, created_at, updated_at })
+    })
+}
+
+/// Merge a nodes CSV (`id,label,metadata_json[,...]`) into `db`. Nothing is merged when a
+/// row does not parse.
+pub fn merge_nodes_csv<R: Read>(db: &mut GraphDatabase, rdr: R) -> anyhow::Result<ImportSummary> {
+    let (nodes, problems) = read_nodes_csv(rdr)?;
+    if let Some(p) = problems.first() { return Err(anyhow::anyhow!("{}", p)); }
+    Ok(merge_payload(db, ImportPayload { nodes, ..Default::default() }))
+}
+
+/// Merge a relationships CSV (`id,from,to,label,metadata_json`) into `db`. Nothing is merged
+/// when a row does not parse.
+pub fn merge_relationships_csv<R: Read>(db: &mut GraphDatabase, rdr: R) -> anyhow::Result<ImportSummary> {
+    let (relationships, problems) = read_relationships_csv(rdr)?;
+    if let Some(p) = problems.first() { return Err(anyhow::anyhow!("{}", p)); }
+    Ok(merge_payload(db, ImportPayload { relationships, ..Default::default() }))
+}
+
+fn json_scalar_to_string(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// One `(label, metadata)` row per non-blank line of `text`, with the line as `name`, for
+/// seeding a brainstorm. List bullets (`- `, `* `, `• `) and surrounding whitespace are dropped.
+pub fn parse_lines(text: &str, label: &str) -> Vec<(String, HashMap<String, String>)> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            ["- ", "* ", "• "].iter().find_map(|b| line.strip_prefix(b)).unwrap_or(line).trim()
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| (label.to_string(), HashMap::from([("name".to_string(), line.to_string())])))
+        .collect()
+}
+
+/// Parse free-form pasted text into `(label, metadata)` pairs for new nodes.
+///
+/// Accepts a JSON array of objects (a `label` key becomes the label, a `metadata` object is
+/// flattened, every other field becomes metadata) or CSV/TSV text with a header row, as
+/// copied from a spreadsheet. Rows without a label get `default_label`.
+pub fn parse_node_rows(text: &str, default_label: &str) -> anyhow::Result<Vec<(String, HashMap<String, String>)>> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() { return Err(anyhow::anyhow!("nothing to import")); }
+    let mut out = Vec::new();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        let v: serde_json::Value = serde_json::from_str(trimmed)?;
+        let items = match v {
+            serde_json::Value::Array(a) => a,
+            obj @ serde_json::Value::Object(_) => vec![obj],
+            _ => return Err(anyhow::anyhow!("expected a JSON array of objects")),
+        };
+        for item in items {
+            let serde_json::Value::Object(map) = item else {
+                return Err(anyhow::anyhow!("expected a JSON array of objects"));
+            };
+            let mut label = default_label.to_string();
+            let mut metadata = HashMap::new();
+            for (k, v) in map {
+                match (k.as_str(), v) {
+                    ("label", serde_json::Value::String(s)) if !s.trim().is_empty() => label = s.trim().to_string(),
+                    // Ids and timestamps are not reused: pasted rows always become new nodes
+                    ("id" | "created_at" | "updated_at", _) => {}
+                    ("metadata", serde_json::Value::Object(m)) => {
+                        for (mk, mv) in m { metadata.insert(mk, json_scalar_to_string(&mv)); }
+                    }
+                    (_, v) => { metadata.insert(k, json_scalar_to_string(&v)); }
+                }
+            }
+            out.push((label, metadata));
+        }
+        return Ok(out);
+    }
+    // Spreadsheets copy as tab-separated text; fall back to commas
+    let first_line = trimmed.lines().next().unwrap_or("");
+    let delimiter = if first_line.contains('\t') { b'\t' } else { b',' };
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(trimmed.as_bytes());
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_string()).collect();
+    for rec in rdr.records() {
+        let rec = rec?;
+        let mut label = default_label.to_string();
+        let mut metadata = HashMap::new();
+        for (h, v) in headers.iter().zip(rec.iter()) {
+            let v = v.trim();
+            match h.as_str() {
+                "label" if !v.is_empty() => label = v.to_string(),
+                "id" | "label" | "out_rels_json" | "in_rels_json" | "created_at" | "updated_at" => {}
+                "metadata_json" => metadata.extend(parse_meta(v)?),
+                _ if h.is_empty() || v.is_empty() => {}
+                _ => { metadata.insert(h.clone(), v.to_string()); }
+            }
+        }
+        out.push((label, metadata));
+    }
+    if out.is_empty() { return Err(anyhow::anyhow!("no data rows found")); }
+    Ok(out)
+}
+
+/// Parse a metadata map edited as one JSON object. Strings are kept as they are, other
+/// scalars are stored in their JSON form and nested arrays or objects as compact JSON text.
+pub fn parse_metadata_json(text: &str) -> anyhow::Result<HashMap<String, String>> {
+    if text.trim().is_empty() { return Ok(HashMap::new()); }
+    let v: serde_json::Value = serde_json::from_str(text)?;
+    let serde_json::Value::Object(map) = v else {
+        return Err(anyhow::anyhow!("expected a JSON object of key/value pairs"));
+    };
+    let mut out = HashMap::new();
+    for (k, v) in map {
+        if k.trim().is_empty() { return Err(anyhow::anyhow!("metadata keys cannot be empty")); }
+        out.insert(k.trim().to_string(), json_scalar_to_string(&v));
+    }
+    Ok(out)
+}
+
+/// `metadata` as a pretty-printed JSON object with sorted keys, the text
+/// [`parse_metadata_json`] reads back.
+pub fn metadata_to_json(metadata: &HashMap<String, String>) -> String {
+    let sorted: std::collections::BTreeMap<&String, &String> = metadata.iter().collect();
+    serde_json::to_string_pretty(&sorted).unwrap_or_else(|_| "{}".into())
+}
+
+/// True for CSV files written as the relationships half of a graph export.
+pub fn is_relationships_csv(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.ends_with("_relationships"))
+        .unwrap_or(false)
+}
+
+/// [`merge_file`] under `rule`. Nothing is merged when an element does not parse.
+pub fn merge_file_matching(db: &mut GraphDatabase, path: &Path, rule: &ImportMatch) -> anyhow::Result<ImportSummary> {
+    Merger::new(rule.clone()).merge_file(db, path)
+}
+
+/// Merge an exported file into `db`, picking the format from the file extension.
+pub fn merge_file(db: &mut GraphDatabase, path: &Path) -> anyhow::Result<ImportSummary> {
+    let ext = path.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("json") => {
+            let s = fs::read_to_string(path)?;
+            merge_json_str(db, &s)
+        }
+        Some("csv") => {
+            let f = fs::File::open(path)?;
+            if is_relationships_csv(path) {
+                merge_relationships_csv(db, f)
+            } else {
+                merge_nodes_csv(db, f)
+            }
+        }
+        _ => Err(anyhow::anyhow!("unsupported import file type: {}", path.display())),
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// How often the owner rewrites its lock file.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// A lock whose heartbeat is older than this is treated as abandoned. Timestamps come from
+/// the writer's clock, so this leaves room for clock skew between machines on a share.
+pub const STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// Who holds a state lock. Stored as JSON in the lock file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub host: String,
+    /// Unix seconds when the lock was taken
+    pub since: u64,
+    /// Unix seconds of the last heartbeat
+    pub heartbeat: u64,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        let now = unix_now();
+        LockOwner { pid: std::process::id(), host: hostname(), since: now, heartbeat: now }
+    }
+
+    fn is_same_process(&self, other: &LockOwner) -> bool {
+        self.pid == other.pid && self.host == other.host
+    }
+
+    /// True when the owner stopped refreshing the lock, or is a process on this host that
+    /// no longer exists.
+    pub fn is_stale(&self) -> bool {
+        if unix_now().saturating_sub(self.heartbeat) > STALE_AFTER.as_secs() { return true; }
+        self.host == hostname() && process_alive(self.pid) == Some(false)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("PID {} on {}", self.pid, self.host)
+    }
+}
+
+/// Advisory lock on a state file, held by writing `<state>.lock`. Other Graph-Loom processes,
+/// possibly on other machines sharing the folder, see the owner and leave the file alone.
+/// Dropping the lock removes the file if it is still ours.
+#[derive(Debug)]
+pub struct StateLock {
+    path: PathBuf,
+    owner: LockOwner,
+}
+
+/// Lock file guarding `state`.
+pub fn lock_path(state: &Path) -> PathBuf {
+    let mut name = state.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    state.with_file_name(name)
+}
+
+impl StateLock {
+    /// Take the lock on `state`. `Ok(Err(owner))` means another live process holds it.
+    pub fn acquire(state: &Path) -> anyhow::Result<Result<StateLock, LockOwner>> {
+        let path = lock_path(state);
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        // One retry: a stale lock is removed and creation attempted again. Processes racing
+        // for it after that are settled by `create_new`.
+        for _ in 0..2 {
+            let me = LockOwner::current();
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    f.write_all(serde_json::to_string(&me)?.as_bytes())?;
+                    return Ok(Ok(StateLock { path, owner: me }));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let text = fs::read_to_string(&path).unwrap_or_default();
+                    match serde_json::from_str::<LockOwner>(&text).ok() {
+                        Some(owner) if !owner.is_stale() && !owner.is_same_process(&me) => return Ok(Err(owner)),
+                        Some(owner) => {
+                            log::warn!("Removing abandoned state lock held by {}", owner.describe());
+                            remove_if_unchanged(&path, &text)?;
+                        }
+                        // Unreadable: possibly being written right now; treat as abandoned only once old
+                        None if file_age(&path).is_some_and(|a| a > STALE_AFTER) => remove_if_unchanged(&path, &text)?,
+                        None => anyhow::bail!("{} exists but could not be read", path.display()),
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        anyhow::bail!("could not create {}", path.display())
+    }
+
+    /// Take the lock regardless of its current owner, who loses it at their next heartbeat.
+    pub fn take_over(state: &Path) -> anyhow::Result<StateLock> {
+        let path = lock_path(state);
+        let me = LockOwner::current();
+        fs::write(&path, serde_json::to_string(&me)?)?;
+        Ok(StateLock { path, owner: me })
+    }
+
+    /// Refresh the heartbeat. `Ok(Err(owner))` means another process took the lock over and
+    /// this one must stop writing the state file.
+    pub fn heartbeat(&mut self) -> anyhow::Result<Result<(), LockOwner>> {
+        if let Some(current) = read_owner(&self.path)
+            && !current.is_same_process(&self.owner)
+        {
+            return Ok(Err(current));
+        }
+        self.owner.heartbeat = unix_now();
+        fs::write(&self.path, serde_json::to_string(&self.owner)?)?;
+        Ok(Ok(()))
+    }
+
+    pub fn owner(&self) -> &LockOwner {
+        &self.owner
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        if read_owner(&self.path).is_some_and(|o| o.is_same_process(&self.owner)) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Remove the lock file if it still holds `seen`. Another process may have replaced a stale
+// lock with its own since it was read, so the file is first moved aside, which only one
+// process can do, and put back when it turns out to be someone's fresh lock.
+fn remove_if_unchanged(path: &Path, seen: &str) -> std::io::Result<()> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}-{}", std::process::id(), nanos));
+    let aside = path.with_file_name(name);
+    match fs::rename(path, &aside) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        other => other?,
+    }
+    // Linking does not replace a lock created in the meantime
+    if fs::read_to_string(&aside).ok().as_deref() != Some(seen) { let _ = fs::hard_link(&aside, path); }
+    fs::remove_file(&aside)
+}
+
+fn read_owner(path: &Path) -> Option<LockOwner> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn file_age(path: &Path) -> Option<Duration> {
+    fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Name of this machine, as far as the environment tells.
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown host".to_string())
+}
+
+// `None` where liveness cannot be checked without platform APIs
+fn process_alive(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    } else {
+        None
+    }
+}
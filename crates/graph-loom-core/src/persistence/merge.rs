@@ -0,0 +1,289 @@
+//! Merge another graph (a saved state file or an export) into the current one, matching
+//! nodes by UUID or by a metadata key and letting the user settle conflicting elements.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::graph_utils::changes::ElementState;
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+use super::import::{self, ImportSummary};
+use super::persist;
+
+/// How nodes of the incoming graph find their counterpart in the current one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatchBy {
+    Id,
+    /// Nodes with equal values for this metadata key are the same node. Nodes without the
+    /// key fall back to matching by UUID.
+    Key(String),
+}
+
+/// What to do with a matched element whose label or metadata values differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resolution {
+    #[default]
+    KeepMine,
+    KeepTheirs,
+    /// Keep my label and take the union of the metadata; their value wins on a shared key.
+    MergeMetadata,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    Node,
+    Relationship,
+}
+
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub kind: ElementKind,
+    pub mine: Uuid,
+    pub theirs: Uuid,
+    pub label_mine: String,
+    pub label_theirs: String,
+    // Keys present on both sides with different values: (key, mine, theirs)
+    pub differences: Vec<(String, String, String)>,
+    pub resolution: Resolution,
+}
+
+/// Result of comparing the incoming graph with the current one, ready to review and apply.
+pub struct MergePlan {
+    theirs: GraphDatabase,
+    // Incoming node id -> id it gets in the current graph (matched or newly inserted)
+    node_targets: HashMap<NodeId, NodeId>,
+    matched_nodes: HashMap<NodeId, NodeId>,
+    // Incoming relationship id -> matched relationship in the current graph
+    matched_rels: HashMap<Uuid, Uuid>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergePlan {
+    pub fn new_nodes(&self) -> usize {
+        self.theirs.nodes.len() - self.matched_nodes.len()
+    }
+
+    pub fn matched_nodes(&self) -> usize {
+        self.matched_nodes.len()
+    }
+
+    pub fn new_relationships(&self) -> usize {
+        self.theirs.relationships.len() - self.matched_rels.len()
+    }
+
+    pub fn matched_relationships(&self) -> usize {
+        self.matched_rels.len()
+    }
+
+    /// Set the same resolution on every conflict.
+    pub fn resolve_all(&mut self, resolution: Resolution) {
+        for c in &mut self.conflicts { c.resolution = resolution; }
+    }
+}
+
+/// Read a graph to merge: a saved state (`.ron`) or a JSON/CSV export.
+pub fn load_graph(path: &Path) -> anyhow::Result<GraphDatabase> {
+    let ext = path.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase());
+    if ext.as_deref() == Some("ron") {
+        return Ok(persist::load_from_path(path)?.db);
+    }
+    let mut db = GraphDatabase::new();
+    import::merge_file(&mut db, path)?;
+    Ok(db)
+}
+
+// Keys both sides have with different values
+fn differences(mine: &HashMap<String, String>, theirs: &HashMap<String, String>) -> Vec<(String, String, String)> {
+    let mut out: Vec<(String, String, String)> = theirs
+        .iter()
+        .filter_map(|(k, tv)| mine.get(k).filter(|mv| *mv != tv).map(|mv| (k.clone(), mv.clone(), tv.clone())))
+        .collect();
+    out.sort();
+    out
+}
+
+/// Compare `theirs` with `mine` without changing either.
+pub fn plan(mine: &GraphDatabase, theirs: GraphDatabase, by: &MatchBy) -> MergePlan {
+    let key_index: HashMap<&str, NodeId> = match by {
+        MatchBy::Id => HashMap::new(),
+        MatchBy::Key(key) => {
+            let mut idx: HashMap<&str, NodeId> = HashMap::new();
+            for n in mine.nodes.values() {
+                if let Some(v) = n.metadata.get(key) {
+                    // Several nodes share the value: the oldest one is the match
+                    let e = idx.entry(v.as_str()).or_insert(n.id);
+                    if n.id < *e { *e = n.id; }
+                }
+            }
+            idx
+        }
+    };
+    let mut conflicts = Vec::new();
+    let mut node_targets = HashMap::new();
+    let mut matched_nodes = HashMap::new();
+    // Each node here absorbs at most one incoming node
+    let mut taken: HashSet<NodeId> = HashSet::new();
+    let mut ids: Vec<&NodeId> = theirs.nodes.keys().collect();
+    ids.sort();
+    for id in ids {
+        let t = &theirs.nodes[id];
+        let matched = match by {
+            MatchBy::Key(key) => match t.metadata.get(key) {
+                Some(v) => key_index.get(v.as_str()).copied(),
+                None => mine.nodes.contains_key(id).then_some(*id),
+            },
+            MatchBy::Id => mine.nodes.contains_key(id).then_some(*id),
+        };
+        let Some(m) = matched.filter(|m| taken.insert(*m)) else {
+            // New node: keep its id unless that id already names an unrelated node here
+            let target = if mine.nodes.contains_key(id) { Uuid::now_v7() } else { *id };
+            node_targets.insert(*id, target);
+            continue;
+        };
+        node_targets.insert(*id, m);
+        matched_nodes.insert(*id, m);
+        let mn = &mine.nodes[&m];
+        let diff = differences(&mn.metadata, &t.metadata);
+        if mn.label != t.label || !diff.is_empty() {
+            conflicts.push(Conflict {
+                kind: ElementKind::Node,
+                mine: m,
+                theirs: *id,
+                label_mine: mn.label.clone(),
+                label_theirs: t.label.clone(),
+                differences: diff,
+                resolution: Resolution::default(),
+            });
+        }
+    }
+    let mut matched_rels = HashMap::new();
+    let mut taken: HashSet<Uuid> = HashSet::new();
+    let mut rel_ids: Vec<&Uuid> = theirs.relationships.keys().collect();
+    rel_ids.sort();
+    for id in rel_ids {
+        let r = &theirs.relationships[id];
+        let (Some(from), Some(to)) = (node_targets.get(&r.from_node), node_targets.get(&r.to_node)) else { continue };
+        let by_id = mine.relationships.get(id).filter(|m| m.from_node == *from && m.to_node == *to).map(|m| m.id);
+        let Some(m) = by_id.or_else(|| mine.find_relationship(*from, *to, &r.label)) else { continue };
+        if !taken.insert(m) { continue; }
+        matched_rels.insert(*id, m);
+        let mr = &mine.relationships[&m];
+        let diff = differences(&mr.metadata, &r.metadata);
+        if mr.label != r.label || !diff.is_empty() {
+            conflicts.push(Conflict {
+                kind: ElementKind::Relationship,
+                mine: m,
+                theirs: *id,
+                label_mine: mr.label.clone(),
+                label_theirs: r.label.clone(),
+                differences: diff,
+                resolution: Resolution::default(),
+            });
+        }
+    }
+    MergePlan { theirs, node_targets, matched_nodes, matched_rels, conflicts }
+}
+
+/// Apply a reviewed plan. Matched elements without conflicts gain the keys they were missing;
+/// conflicting ones follow their resolution.
+pub fn apply(mine: &mut GraphDatabase, plan: MergePlan) -> ImportSummary {
+    let MergePlan { theirs, node_targets, matched_nodes, matched_rels, conflicts } = plan;
+    let resolutions: HashMap<Uuid, Resolution> = conflicts.iter().map(|c| (c.theirs, c.resolution)).collect();
+    let mut summary = ImportSummary::default();
+    let GraphDatabase { nodes, relationships, unique_relationship_labels, metadata, property_rules, computed_properties, derived_edges, views, .. } = theirs;
+
+    for (id, mut n) in nodes {
+        let target = node_targets[&id];
+        if let Some(m) = matched_nodes.get(&id) {
+            let resolution = resolutions.get(&id).copied();
+            let before = mine.nodes.get(m).map(ElementState::from);
+            let changed = merge_element(mine.nodes.get_mut(m).map(|e| (&mut e.label, &mut e.metadata)), n.label, n.metadata, resolution)
+                | mine.nodes.get_mut(m).is_some_and(|e| merge_description(&mut e.description, n.description, resolution));
+            if changed {
+                mine.touch_node(*m);
+                mine.record_node_change(*m, before);
+                summary.nodes_updated += 1;
+            }
+        } else {
+            n.id = target;
+            mine.nodes.insert(target, n);
+            mine.record_node_change(target, None);
+            summary.nodes_added += 1;
+        }
+    }
+    for (id, mut r) in relationships {
+        let (Some(from), Some(to)) = (node_targets.get(&r.from_node), node_targets.get(&r.to_node)) else {
+            summary.rels_skipped += 1;
+            continue;
+        };
+        if let Some(m) = matched_rels.get(&id) {
+            let resolution = resolutions.get(&id).copied();
+            let before = mine.relationships.get(m).map(ElementState::from);
+            let changed = merge_element(mine.relationships.get_mut(m).map(|e| (&mut e.label, &mut e.metadata)), r.label, r.metadata, resolution)
+                | mine.relationships.get_mut(m).is_some_and(|e| merge_description(&mut e.description, r.description, resolution));
+            if changed {
+                mine.touch_relationship(*m);
+                mine.record_relationship_change(*m, before);
+                summary.rels_updated += 1;
+            }
+        } else {
+            r.id = if mine.relationships.contains_key(&id) { Uuid::now_v7() } else { id };
+            r.from_node = *from;
+            r.to_node = *to;
+            let id = r.id;
+            mine.relationships.insert(id, r);
+            mine.record_relationship_change(id, None);
+            summary.rels_added += 1;
+        }
+    }
+    mine.unique_relationship_labels.extend(unique_relationship_labels);
+    for (label, rules) in property_rules { mine.property_rules.entry(label).or_insert(rules); }
+    for (label, props) in computed_properties { mine.computed_properties.entry(label).or_insert(props); }
+    for rule in derived_edges {
+        if !mine.derived_edges.iter().any(|r| r.label == rule.label) { mine.derived_edges.push(rule); }
+    }
+    for view in views {
+        if !mine.views.iter().any(|v| v.name == view.name) { mine.views.push(view); }
+    }
+    for (k, v) in metadata { mine.metadata.entry(k).or_insert(v); }
+    mine.invalidate_vocabulary();
+    summary
+}
+
+// Update one matched element; returns whether it changed
+fn merge_element(
+    target: Option<(&mut String, &mut HashMap<String, String>)>,
+    label: String,
+    metadata: HashMap<String, String>,
+    resolution: Option<Resolution>,
+) -> bool {
+    let Some((my_label, my_meta)) = target else { return false };
+    let before = (my_label.clone(), my_meta.clone());
+    match resolution {
+        Some(Resolution::KeepMine) => {}
+        Some(Resolution::KeepTheirs) => {
+            *my_label = label;
+            *my_meta = metadata;
+        }
+        Some(Resolution::MergeMetadata) => my_meta.extend(metadata),
+        // No conflict: only add keys this side does not have yet
+        None => {
+            for (k, v) in metadata { my_meta.entry(k).or_insert(v); }
+        }
+    }
+    before != (my_label.clone(), my_meta.clone())
+}
+
+// Descriptions follow the label: keeping theirs replaces mine, otherwise theirs only fills an
+// empty one. Returns whether it changed.
+fn merge_description(mine: &mut String, theirs: String, resolution: Option<Resolution>) -> bool {
+    let replace = match resolution {
+        Some(Resolution::KeepMine) => false,
+        Some(Resolution::KeepTheirs) => true,
+        Some(Resolution::MergeMetadata) | None => mine.is_empty(),
+    };
+    if !replace || *mine == theirs { return false; }
+    *mine = theirs;
+    true
+}
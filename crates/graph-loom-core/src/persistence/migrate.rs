@@ -0,0 +1,99 @@
+//! Upgrades for saved state files. Each change to the saved shape of [`AppStateFile`] bumps
+//! [`SCHEMA`] and registers a [`Migration`] that rewrites a file of the previous schema, so
+//! files from any older version still open. Foreign formats (JSON, NDJSON and CSV exports)
+//! are read into a state as well.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+
+use super::import::{self, ImportPayload, ImportSummary};
+use super::persist::AppStateFile;
+use crate::graph_utils::graph::GraphDatabase;
+
+/// Schema of the state files this build writes. Files saved before the number existed are 0.
+pub const SCHEMA: u32 = 1;
+
+/// Rewrites a state file of schema `from` into schema `from + 1`. Steps work on the RON text
+/// so they can read shapes the current model no longer deserializes: a step parses its input
+/// with a frozen copy of the structs of its schema and writes the next one.
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(&str) -> anyhow::Result<String>,
+}
+
+/// Registered steps, one per schema below [`SCHEMA`].
+pub const MIGRATIONS: &[Migration] = &[Migration { from: 0, description: "number the schema", apply: number_schema }];
+
+// Schema 0 has the shape of schema 1 without the number
+fn number_schema(text: &str) -> anyhow::Result<String> {
+    let mut state: AppStateFile = ron::from_str(text)?;
+    state.schema = 1;
+    Ok(ron::to_string(&state)?)
+}
+
+// Reads only the schema number; the other fields are skipped
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    schema: u32,
+}
+
+/// A state read from an older or foreign file.
+#[derive(Debug)]
+pub struct Upgraded {
+    pub state: AppStateFile,
+    /// Schema the file was saved with; `None` for a foreign format
+    pub from: Option<u32>,
+    /// Descriptions of the steps that ran
+    pub steps: Vec<&'static str>,
+    /// What a foreign file held
+    pub imported: Option<ImportSummary>,
+}
+
+/// Read a state file of any schema up to [`SCHEMA`], running the steps it needs.
+pub fn read_state(text: &str) -> anyhow::Result<Upgraded> {
+    // Current files parse on the first try
+    let error = match ron::from_str::<AppStateFile>(text) {
+        Ok(state) if state.schema == SCHEMA => return Ok(Upgraded { state, from: Some(SCHEMA), steps: Vec::new(), imported: None }),
+        Ok(_) => None,
+        Err(e) => Some(e),
+    };
+    let from = ron::from_str::<Header>(text)
+        .map_err(|_| anyhow!("not a Graph-Loom state file: {}", error.map_or_else(String::new, |e| e.to_string())))?
+        .schema;
+    if from > SCHEMA { bail!("saved by a newer Graph-Loom (schema {}; this version reads up to {})", from, SCHEMA); }
+    let mut text = text.to_string();
+    let mut steps = Vec::new();
+    for schema in from..SCHEMA {
+        let step = MIGRATIONS.iter().find(|m| m.from == schema).ok_or_else(|| anyhow!("no upgrade from schema {}", schema))?;
+        text = (step.apply)(&text).with_context(|| format!("upgrading from schema {} ({})", schema, step.description))?;
+        steps.push(step.description);
+    }
+    let state: AppStateFile = ron::from_str(&text)?;
+    Ok(Upgraded { state, from: Some(from), steps, imported: None })
+}
+
+/// Read a saved state (`.ron`) of any schema, or a JSON, NDJSON or CSV export as a state
+/// without a layout.
+pub fn read_file(path: &Path) -> anyhow::Result<Upgraded> {
+    let ext = path.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("ron") => read_state(&fs::read_to_string(path)?).with_context(|| path.display().to_string()),
+        Some("json" | "ndjson" | "jsonl") => {
+            let payload = ImportPayload::parse(&fs::read_to_string(path)?).with_context(|| path.display().to_string())?;
+            Ok(foreign(payload))
+        }
+        Some("csv") => Ok(foreign(ImportPayload::read_file(path)?.strict()?)),
+        _ => bail!("cannot read {}: expected a .ron state or a JSON, NDJSON or CSV export", path.display()),
+    }
+}
+
+fn foreign(payload: ImportPayload) -> Upgraded {
+    let mut db = GraphDatabase::new();
+    let summary = import::merge_payload(&mut db, payload);
+    Upgraded { state: AppStateFile::from_db(db), from: None, steps: Vec::new(), imported: Some(summary) }
+}
@@ -0,0 +1,15 @@
+pub mod persist;
+pub mod snapshot;
+pub mod migrate;
+pub mod settings;
+pub mod import;
+pub mod feeds;
+pub mod watch;
+pub mod lock;
+pub mod backup;
+pub mod merge;
+pub mod export;
+pub mod columnar;
+pub mod export_schedule;
+pub mod result_export;
+pub mod bundle;
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+use super::migrate;
+use super::settings::AppSettings;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppStateFile {
+    // Shape of the file, see `migrate`; missing in files saved before it was numbered
+    #[serde(default)]
+    pub schema: u32,
+    pub db: GraphDatabase,
+    // store positions as map entries of node id -> (x, y)
+    pub node_positions: Vec<(NodeId, f32, f32)>,
+    pub pan: (f32, f32),
+    pub zoom: f32,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// A state file's contents, borrowed: serializes exactly like [`AppStateFile`], so a graph
+/// shared with the GUI can be written from another thread without another copy.
+#[derive(Debug, Serialize)]
+pub struct StateView<'a> {
+    pub schema: u32,
+    pub db: &'a GraphDatabase,
+    pub node_positions: &'a [(NodeId, f32, f32)],
+    pub pan: (f32, f32),
+    pub zoom: f32,
+    pub annotations: &'a [Annotation],
+}
+
+/// A note drawn on the canvas for presentations. Annotations are saved with the layout in
+/// world coordinates; they are not part of the graph and queries never see them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: Uuid,
+    pub shape: AnnotationShape,
+    // The note itself for text, a caption for rectangles and arrows
+    #[serde(default)]
+    pub text: String,
+    pub color: (u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AnnotationShape {
+    /// Text with its top-left corner at `at`
+    Text { at: (f32, f32) },
+    Rect { min: (f32, f32), max: (f32, f32) },
+    Arrow { from: (f32, f32), to: (f32, f32) },
+}
+
+impl Annotation {
+    pub fn new(shape: AnnotationShape, text: impl Into<String>, color: (u8, u8, u8)) -> Self {
+        Annotation { id: Uuid::now_v7(), shape, text: text.into(), color }
+    }
+}
+
+/// The canvas part of a state file: node positions, pan, zoom and annotations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub node_positions: Vec<(NodeId, f32, f32)>,
+    pub pan: (f32, f32),
+    pub zoom: f32,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self { node_positions: Vec::new(), pan: (0.0, 0.0), zoom: 1.0, annotations: Vec::new() }
+    }
+}
+
+impl AppStateFile {
+    /// Positions and pan may be any point type convertible to `(x, y)`, such as `egui::Pos2`.
+    pub fn from_runtime<P, V>(db: &GraphDatabase, node_positions: &HashMap<NodeId, P>, pan: V, zoom: f32) -> Self
+    where
+        P: Copy + Into<(f32, f32)>,
+        V: Into<(f32, f32)>,
+    {
+        Self::from_runtime_owned(db.clone(), node_positions, pan, zoom)
+    }
+
+    /// Create from runtime components without cloning the database if possible.
+    pub fn from_runtime_owned<P, V>(db: GraphDatabase, node_positions: &HashMap<NodeId, P>, pan: V, zoom: f32) -> Self
+    where
+        P: Copy + Into<(f32, f32)>,
+        V: Into<(f32, f32)>,
+    {
+        let node_positions = node_positions
+            .iter()
+            .map(|(id, pos)| {
+                let (x, y) = (*pos).into();
+                (*id, x, y)
+            })
+            .collect();
+        Self {
+            schema: migrate::SCHEMA,
+            db,
+            node_positions,
+            pan: pan.into(),
+            zoom,
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// A state with no layout, for callers without a canvas (background mode, scripts).
+    pub fn from_db(db: GraphDatabase) -> Self {
+        Self { schema: migrate::SCHEMA, db, node_positions: Vec::new(), pan: (0.0, 0.0), zoom: 1.0, annotations: Vec::new() }
+    }
+
+    /// Pair `db` with a layout saved earlier, dropping positions of nodes that no longer exist.
+    /// Callers without a canvas use this so saving does not discard the GUI's layout.
+    pub fn from_db_with_layout(db: GraphDatabase, layout: &Layout) -> Self {
+        let node_positions = layout.node_positions.iter().filter(|(id, _, _)| db.nodes.contains_key(id)).copied().collect();
+        Self { schema: migrate::SCHEMA, db, node_positions, pan: layout.pan, zoom: layout.zoom, annotations: layout.annotations.clone() }
+    }
+
+    pub fn view(&self) -> StateView<'_> {
+        StateView {
+            schema: self.schema,
+            db: &self.db,
+            node_positions: &self.node_positions,
+            pan: self.pan,
+            zoom: self.zoom,
+            annotations: &self.annotations,
+        }
+    }
+
+    pub fn into_parts(self) -> (GraphDatabase, Layout) {
+        (self.db, Layout { node_positions: self.node_positions, pan: self.pan, zoom: self.zoom, annotations: self.annotations })
+    }
+
+    /// Convert a persisted AppStateFile into runtime structures.
+    ///
+    /// This intentionally consumes `self` to avoid cloning large buffers.
+    /// Keeping the existing API preserves behavior; allow clippy's naming lint.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_runtime<P, V>(self) -> (GraphDatabase, HashMap<NodeId, P>, V, f32)
+    where
+        P: From<(f32, f32)>,
+        V: From<(f32, f32)>,
+    {
+        let positions: HashMap<NodeId, P> = self
+            .node_positions
+            .into_iter()
+            .map(|(id, x, y)| (id, P::from((x, y))))
+            .collect();
+        (self.db, positions, V::from(self.pan), self.zoom)
+    }
+}
+
+use std::sync::OnceLock;
+
+static SETTINGS_OVERRIDE: OnceLock<AppSettings> = OnceLock::new();
+
+pub fn set_settings_override(settings: AppSettings) {
+    let _ = SETTINGS_OVERRIDE.set(settings);
+}
+
+fn autosave_dir() -> PathBuf {
+    // If an override is set (e.g. from main.rs), use it.
+    if let Some(settings) = SETTINGS_OVERRIDE.get() {
+        return settings.autosave_dir();
+    }
+    // Load settings if present; else use defaults
+    let settings = AppSettings::load().unwrap_or_default();
+    settings.autosave_dir()
+}
+
+pub fn active_state_path() -> PathBuf {
+    autosave_dir().join("state.ron")
+}
+
+/// Append-only log of the changes made to the active graph, one JSON event per line.
+pub fn changes_log_path() -> PathBuf {
+    autosave_dir().join("changes.jsonl")
+}
+
+/// File written by the panic handler with the in-memory graph at the time of a crash.
+pub fn recovery_state_path() -> PathBuf {
+    autosave_dir().join("recovery.ron")
+}
+
+pub fn versioned_state_path_now() -> PathBuf {
+    let now = OffsetDateTime::now_utc();
+    let fmt = format_description!("[year][month][day]_[hour][minute][second]");
+    let stamp = now.format(fmt).unwrap_or_else(|_| "unknown".to_string());
+    autosave_dir().join(format!("state_{}.ron", stamp))
+}
+
+/// Path for a snapshot written by the backup schedule; these share the versions listing but
+/// carry their own prefix so retention only ever deletes them.
+pub fn auto_backup_path_now() -> PathBuf {
+    let now = OffsetDateTime::now_utc();
+    let fmt = format_description!("[year][month][day]_[hour][minute][second]");
+    let stamp = now.format(fmt).unwrap_or_else(|_| "unknown".to_string());
+    autosave_dir().join(format!("{}{}.ron", super::backup::AUTO_BACKUP_PREFIX, stamp))
+}
+
+fn ensure_autosave_dir() -> std::io::Result<()> {
+    fs::create_dir_all(autosave_dir())
+}
+
+pub(crate) fn atomic_write(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("ron.tmp");
+    {
+        let mut f = File::create(&tmp_path)?;
+        f.write_all(data)?;
+        f.flush()?;
+    }
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+pub fn save_active(state: &AppStateFile) -> anyhow::Result<PathBuf> {
+    ensure_autosave_dir()?;
+    let path = active_state_path();
+    atomic_write(&path, to_ron(&state.view())?.as_bytes())?;
+    Ok(path)
+}
+
+// State file text, formatted like every other save
+pub(crate) fn to_ron(state: &StateView) -> anyhow::Result<String> {
+    let pretty = PrettyConfig::new()
+        .separate_tuple_members(true)
+        .enumerate_arrays(true);
+    Ok(ron::ser::to_string_pretty(state, pretty)?)
+}
+
+/// Write `state` to `path`, e.g. a state file upgraded by `Graph-Loom import-state`.
+pub fn save_to_path(state: &AppStateFile, path: &Path) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { fs::create_dir_all(dir)?; }
+    let pretty = PrettyConfig::new()
+        .separate_tuple_members(true)
+        .enumerate_arrays(true);
+    let s = ron::ser::to_string_pretty(state, pretty)?;
+    atomic_write(path, s.as_bytes())?;
+    Ok(())
+}
+
+pub fn save_versioned(state: &AppStateFile) -> anyhow::Result<PathBuf> {
+    ensure_autosave_dir()?;
+    let pretty = PrettyConfig::new()
+        .separate_tuple_members(true)
+        .enumerate_arrays(true);
+    let s = ron::ser::to_string_pretty(state, pretty)?;
+    let path = versioned_state_path_now();
+    atomic_write(&path, s.as_bytes())?;
+    Ok(path)
+}
+
+pub fn save_auto_backup(state: &AppStateFile) -> anyhow::Result<PathBuf> {
+    ensure_autosave_dir()?;
+    let pretty = PrettyConfig::new()
+        .separate_tuple_members(true)
+        .enumerate_arrays(true);
+    let s = ron::ser::to_string_pretty(state, pretty)?;
+    let path = auto_backup_path_now();
+    atomic_write(&path, s.as_bytes())?;
+    Ok(path)
+}
+
+pub fn save_recovery(state: &AppStateFile) -> anyhow::Result<PathBuf> {
+    ensure_autosave_dir()?;
+    // Compact output: this runs while the app is going down
+    let s = ron::ser::to_string(state)?;
+    let path = recovery_state_path();
+    atomic_write(&path, s.as_bytes())?;
+    Ok(path)
+}
+
+pub fn load_recovery() -> anyhow::Result<Option<AppStateFile>> {
+    let path = recovery_state_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    load_from_path(&path).map(Some)
+}
+
+pub fn discard_recovery() {
+    let _ = fs::remove_file(recovery_state_path());
+}
+
+pub fn load_active() -> anyhow::Result<Option<AppStateFile>> {
+    let path = active_state_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    load_from_path(&path).map(Some)
+}
+
+/// Load a state file, upgrading it if an older version saved it (see `migrate`).
+pub fn load_from_path(path: &Path) -> anyhow::Result<AppStateFile> {
+    let mut f = File::open(path)?;
+    let mut buf = String::new();
+    f.read_to_string(&mut buf)?;
+    let upgraded = migrate::read_state(&buf)?;
+    if !upgraded.steps.is_empty() {
+        log::info!("Upgraded {} from schema {}: {}", path.display(), upgraded.from.unwrap_or(0), upgraded.steps.join(", "));
+    }
+    Ok(upgraded.state)
+}
+
+pub fn list_versions() -> anyhow::Result<Vec<PathBuf>> {
+    let dir = autosave_dir();
+    let mut entries: Vec<PathBuf> = Vec::new();
+    if dir.exists() {
+        for e in fs::read_dir(dir)? {
+            let p = e?.path();
+            if let Some(name) = p.file_name().and_then(|s| s.to_str())
+                && name.starts_with("state_") && name.ends_with(".ron")
+            {
+                entries.push(p);
+            }
+        }
+    }
+    // sort descending by filename (timestamp)
+    entries.sort();
+    entries.reverse();
+    Ok(entries)
+}
\ No newline at end of file
@@ -0,0 +1,160 @@
+//! Query results written as files: exactly the rows a query returned, in order. Besides nodes
+//! that includes relationships, paths and returned values such as aggregates (`info` rows).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::gql::query_interface::QueryResultRow;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// An array of rows shaped like the `rows` of a `/v1/query` response.
+    #[default]
+    Json,
+    /// A line per row: kind, ids, label, value and path columns, then a column per metadata key.
+    Csv,
+}
+
+impl ResultFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(ResultFormat::Json),
+            "csv" => Some(ResultFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ResultFormat::Json => "json",
+            ResultFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResultFormat::Json => "application/json",
+            ResultFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+// Columns every CSV export starts with; metadata keys named like them get a `metadata.` prefix
+const FIXED_COLUMNS: [&str; 8] = ["kind", "id", "label", "from", "to", "info", "nodes", "relationships"];
+
+fn kind(row: &QueryResultRow) -> &'static str {
+    match row {
+        QueryResultRow::Node { .. } => "node",
+        QueryResultRow::Relationship { .. } => "relationship",
+        QueryResultRow::Path { .. } => "path",
+        QueryResultRow::Info(_) => "info",
+    }
+}
+
+fn ids<T: ToString>(ids: &[T]) -> Vec<String> {
+    ids.iter().map(ToString::to_string).collect()
+}
+
+/// The row as JSON, with the fields `/v1/query` gives it.
+pub fn row_json(row: &QueryResultRow) -> Value {
+    let mut out = Map::new();
+    out.insert("kind".into(), kind(row).into());
+    match row {
+        QueryResultRow::Node { id, label, metadata } => {
+            out.insert("id".into(), id.to_string().into());
+            out.insert("label".into(), label.clone().into());
+            out.insert("metadata".into(), json!(metadata.iter().collect::<BTreeMap<_, _>>()));
+        }
+        QueryResultRow::Relationship { id, from, to, label, metadata } => {
+            out.insert("id".into(), id.to_string().into());
+            out.insert("label".into(), label.clone().into());
+            out.insert("from".into(), from.to_string().into());
+            out.insert("to".into(), to.to_string().into());
+            out.insert("metadata".into(), json!(metadata.iter().collect::<BTreeMap<_, _>>()));
+        }
+        QueryResultRow::Path { nodes, relationships } => {
+            out.insert("nodes".into(), json!(ids(nodes)));
+            out.insert("relationships".into(), json!(ids(relationships)));
+        }
+        QueryResultRow::Info(s) => {
+            out.insert("info".into(), s.clone().into());
+        }
+    }
+    Value::Object(out)
+}
+
+pub fn write_json<W: Write>(rows: &[QueryResultRow], mut w: W) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut w, &rows.iter().map(row_json).collect::<Vec<_>>())?;
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+pub fn write_csv<W: Write>(rows: &[QueryResultRow], w: W) -> anyhow::Result<()> {
+    let keys: BTreeSet<&String> = rows
+        .iter()
+        .flat_map(|r| match r {
+            QueryResultRow::Node { metadata, .. } | QueryResultRow::Relationship { metadata, .. } => Some(metadata.keys()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    let mut wtr = csv::Writer::from_writer(w);
+    let header: Vec<String> = FIXED_COLUMNS
+        .iter()
+        .map(|c| c.to_string())
+        .chain(keys.iter().map(|k| if FIXED_COLUMNS.contains(&k.as_str()) { format!("metadata.{}", k) } else { k.to_string() }))
+        .collect();
+    wtr.write_record(&header)?;
+    let list = |ids: Vec<String>| serde_json::to_string(&ids).unwrap_or_default();
+    for row in rows {
+        // Fields follow FIXED_COLUMNS
+        let mut record = vec![String::new(); FIXED_COLUMNS.len()];
+        record[0] = kind(row).to_string();
+        let metadata = match row {
+            QueryResultRow::Node { id, label, metadata } => {
+                record[1] = id.to_string();
+                record[2] = label.clone();
+                Some(metadata)
+            }
+            QueryResultRow::Relationship { id, from, to, label, metadata } => {
+                record[1] = id.to_string();
+                record[2] = label.clone();
+                record[3] = from.to_string();
+                record[4] = to.to_string();
+                Some(metadata)
+            }
+            QueryResultRow::Path { nodes, relationships } => {
+                record[6] = list(ids(nodes));
+                record[7] = list(ids(relationships));
+                None
+            }
+            QueryResultRow::Info(s) => {
+                record[5] = s.clone();
+                None
+            }
+        };
+        record.extend(keys.iter().map(|k| metadata.and_then(|m| m.get(*k)).cloned().unwrap_or_default()));
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+pub fn write<W: Write>(rows: &[QueryResultRow], format: ResultFormat, w: W) -> anyhow::Result<()> {
+    match format {
+        ResultFormat::Json => write_json(rows, w),
+        ResultFormat::Csv => write_csv(rows, w),
+    }
+}
+
+/// Write `rows` to `path`, creating its folder.
+pub fn write_file(rows: &[QueryResultRow], format: ResultFormat, path: &Path) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+    let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write(rows, format, &mut w)?;
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,584 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::gql::collation::{Collation, QueryOptions};
+use crate::persistence::columnar::ExportTemplate;
+use crate::persistence::export::FileFormat;
+use crate::persistence::feeds::Feed;
+use crate::persistence::import::ImportMatch;
+use crate::graph_utils::permissions::Role;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettings {
+    // If None, use OS default autosave directory
+    pub autosave_override: Option<PathBuf>,
+    // If None, use OS temporary directory for exports
+    #[serde(default)]
+    pub export_override: Option<PathBuf>,
+    // If None, server traffic logs go to OS temp dir
+    #[serde(default)]
+    pub api_log_override: Option<PathBuf>,
+    // Persist UI/LOD settings between runs
+    pub lod_enabled: bool,
+    pub lod_label_min_zoom: f32,
+    pub lod_hide_labels_node_threshold: usize,
+    // API service configuration (actix)
+    #[serde(default)]
+    pub api_enabled: bool,
+    #[serde(default = "AppSettings::default_bind_addr")]
+    pub api_bind_addr: String,
+    #[serde(default = "AppSettings::default_port")]
+    pub api_port: u16,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    // Further API keys, each limited to writing some labels
+    #[serde(default)]
+    pub api_roles: Vec<Role>,
+    // OpenTelemetry collector (OTLP/gRPC, e.g. http://localhost:4317) that request traces are
+    // sent to; read at startup
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    // gzip/brotli for HTTP responses when the client sends Accept-Encoding
+    #[serde(default = "AppSettings::default_api_compression")]
+    pub api_compression: bool,
+    // Request limits for the HTTP/WS and gRPC APIs
+    #[serde(default = "AppSettings::default_api_max_body_bytes")]
+    pub api_max_body_bytes: usize,
+    #[serde(default = "AppSettings::default_api_max_import_bytes")]
+    pub api_max_import_bytes: usize,
+    #[serde(default = "AppSettings::default_api_max_query_len")]
+    pub api_max_query_len: usize,
+    #[serde(default = "AppSettings::default_api_max_params")]
+    pub api_max_params: usize,
+    // Client IP restrictions for the HTTP/WS and gRPC servers
+    #[serde(default)]
+    pub api_ip_filter: IpFilter,
+    #[serde(default)]
+    pub grpc_ip_filter: IpFilter,
+    // TLS and client certificate authentication for the gRPC listener
+    #[serde(default)]
+    pub grpc_tls: GrpcTls,
+    // gRPC service configuration
+    #[serde(default)]
+    pub grpc_enabled: bool,
+    #[serde(default = "AppSettings::default_grpc_port")]
+    pub grpc_port: u16,
+    // Whether to continue running in background when GUI window is closed
+    #[serde(default)]
+    pub background_on_close: bool,
+    // Watch folder: JSON/CSV exports dropped here are merged into the active graph
+    #[serde(default)]
+    pub watch_folder_enabled: bool,
+    // If None, watch {temp_dir}/Graph-Loom/inbox
+    #[serde(default)]
+    pub watch_folder_override: Option<PathBuf>,
+    // Named presets of the options below, switchable at runtime or via --profile
+    #[serde(default)]
+    pub profiles: Vec<SettingsProfile>,
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    // Session defaults for query matching; a `CYPHER ...` prefix overrides them per query
+    #[serde(default)]
+    pub query_case_insensitive: bool,
+    #[serde(default)]
+    pub query_locale_collation: bool,
+    // Scheduled snapshots (see `persistence::backup`); 0 hours turns the timer off
+    #[serde(default)]
+    pub backup_interval_hours: u32,
+    #[serde(default)]
+    pub backup_on_close: bool,
+    // Automatic snapshots kept; older ones are deleted, manual versions never are. 0 keeps all
+    #[serde(default = "AppSettings::default_backup_keep")]
+    pub backup_keep: usize,
+    // File → Open Recent: loaded versions and bundles, newest first
+    #[serde(default)]
+    pub recent_files: Vec<RecentFile>,
+    // Label and highlight colors on the canvas
+    #[serde(default)]
+    pub color_palette: ColorPalette,
+    // WCAG contrast ratio node labels keep against the canvas background; 0 leaves them as is
+    #[serde(default)]
+    pub label_min_contrast: f32,
+    // Scale of the whole interface, for high-DPI screens and screen sharing
+    #[serde(default = "AppSettings::default_scale")]
+    pub ui_scale: f32,
+    // Multiplies the zoom-dependent size of node, relationship and annotation text
+    #[serde(default = "AppSettings::default_scale")]
+    pub canvas_font_scale: f32,
+    // Smooth two-finger scrolling pans the canvas; wheel notches and pinches still zoom
+    #[serde(default = "AppSettings::default_trackpad_pans")]
+    pub trackpad_pans: bool,
+    // The canvas keeps gliding briefly after a quick background drag
+    #[serde(default = "AppSettings::default_kinetic_panning")]
+    pub kinetic_panning: bool,
+    // The first-run tour was finished or dismissed; Help → Take the Tour still opens it
+    #[serde(default)]
+    pub tour_seen: bool,
+    // Frame time breakdown drawn over the canvas (View → Performance Overlay)
+    #[serde(default)]
+    pub perf_hud: bool,
+    // Named table shapes for CSV, Parquet and Arrow exports (File → Export Graph, `export`)
+    #[serde(default)]
+    pub export_templates: Vec<ExportTemplate>,
+    // Exports written on a timer or at close for downstream pipelines (see `persistence::export_schedule`)
+    #[serde(default)]
+    pub scheduled_export: ScheduledExport,
+    // How file drops, connectors, feeds and the watch folder match incoming nodes to existing ones
+    #[serde(default)]
+    pub import_match: ImportMatch,
+    // JSON API feeds this user wrote, by feed id (see `AuthoredFeed`)
+    #[serde(default)]
+    pub authored_feeds: BTreeMap<Uuid, AuthoredFeed>,
+}
+
+/// A JSON API feed as its author last saved it. Only a feed whose URL still matches is
+/// treated as theirs: it runs on its timer, reads `${VAR}` from the environment and sends
+/// these headers, which stay here rather than in the graph file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthoredFeed {
+    pub url: String,
+    #[serde(default)]
+    pub headers: String,
+}
+
+/// Color scheme for label coding and highlights on the canvas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorPalette {
+    #[default]
+    Standard,
+    /// Okabe–Ito, distinguishable with protanopia and deuteranopia
+    OkabeIto,
+    /// Paul Tol's bright scheme, also safe for tritanopia
+    TolBright,
+}
+
+/// Unpinned entries kept in [`AppSettings::recent_files`]; pinned ones do not count.
+pub const RECENT_FILES_MAX: usize = 10;
+
+/// A state file or bundle listed under File → Open Recent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    // Pinned entries are listed first and never age out
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// CIDR blocks or single addresses allowed to / refused from connecting to a server. An empty
+/// allow list admits everyone not denied (see `api::ipfilter`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpFilter {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// TLS for the gRPC listener; file paths point at PEM files. With a client CA, clients
+/// presenting a certificate it signed are authenticated without an API key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrpcTls {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub cert_file: String,
+    #[serde(default)]
+    pub key_file: String,
+    // Empty: no client certificates are requested
+    #[serde(default)]
+    pub client_ca_file: String,
+    // Refuse the TLS handshake for clients without a certificate from `client_ca_file`
+    #[serde(default)]
+    pub require_client_cert: bool,
+}
+
+/// Export written to a folder on a timer and/or when the app closes. The files keep the same
+/// names and are replaced whole each time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledExport {
+    // Empty turns scheduled exports off
+    #[serde(default)]
+    pub dir: String,
+    #[serde(default)]
+    pub format: FileFormat,
+    // Name of one of `export_templates`; ignored for JSON
+    #[serde(default)]
+    pub template: Option<String>,
+    // 0 turns the timer off
+    #[serde(default)]
+    pub interval_minutes: u32,
+    #[serde(default)]
+    pub on_close: bool,
+}
+
+/// A named snapshot of the API, bind, LOD and export options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsProfile {
+    pub name: String,
+    #[serde(default)]
+    pub export_override: Option<PathBuf>,
+    pub lod_enabled: bool,
+    pub lod_label_min_zoom: f32,
+    pub lod_hide_labels_node_threshold: usize,
+    #[serde(default)]
+    pub api_enabled: bool,
+    #[serde(default = "AppSettings::default_bind_addr")]
+    pub api_bind_addr: String,
+    #[serde(default = "AppSettings::default_port")]
+    pub api_port: u16,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub grpc_enabled: bool,
+    #[serde(default = "AppSettings::default_grpc_port")]
+    pub grpc_port: u16,
+    #[serde(default)]
+    pub grpc_tls: GrpcTls,
+    #[serde(default)]
+    pub background_on_close: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            autosave_override: None,
+            export_override: None,
+            api_log_override: None,
+            lod_enabled: true,
+            lod_label_min_zoom: 0.7,
+            lod_hide_labels_node_threshold: 200,
+            api_enabled: false,
+            api_bind_addr: Self::default_bind_addr(),
+            api_port: Self::default_port(),
+            api_key: None,
+            api_roles: Vec::new(),
+            otlp_endpoint: None,
+            api_compression: Self::default_api_compression(),
+            api_max_body_bytes: Self::default_api_max_body_bytes(),
+            api_max_import_bytes: Self::default_api_max_import_bytes(),
+            api_max_query_len: Self::default_api_max_query_len(),
+            api_max_params: Self::default_api_max_params(),
+            api_ip_filter: IpFilter::default(),
+            grpc_ip_filter: IpFilter::default(),
+            grpc_tls: GrpcTls::default(),
+            grpc_enabled: false,
+            grpc_port: Self::default_grpc_port(),
+            background_on_close: false,
+            watch_folder_enabled: false,
+            watch_folder_override: None,
+            profiles: Vec::new(),
+            active_profile: None,
+            query_case_insensitive: false,
+            query_locale_collation: false,
+            backup_interval_hours: 0,
+            backup_on_close: false,
+            backup_keep: Self::default_backup_keep(),
+            recent_files: Vec::new(),
+            color_palette: ColorPalette::default(),
+            label_min_contrast: 0.0,
+            ui_scale: Self::default_scale(),
+            canvas_font_scale: Self::default_scale(),
+            trackpad_pans: Self::default_trackpad_pans(),
+            kinetic_panning: Self::default_kinetic_panning(),
+            tour_seen: false,
+            perf_hud: false,
+            export_templates: Vec::new(),
+            scheduled_export: ScheduledExport::default(),
+            import_match: ImportMatch::default(),
+            authored_feeds: BTreeMap::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    fn config_dir() -> PathBuf {
+        // Cross-platform user config dir
+        #[cfg(target_os = "macos")]
+        {
+            // ~/Library/Application Support/Graph-Loom
+            let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("~"));
+            return home.join("Library").join("Application Support").join("Graph-Loom");
+        }
+        #[cfg(target_os = "windows")]
+        {
+            // %APPDATA%\Graph-Loom
+            if let Ok(appdata) = std::env::var("APPDATA") {
+                return PathBuf::from(appdata).join("Graph-Loom");
+            }
+            return PathBuf::from("Graph-Loom");
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            // $XDG_CONFIG_HOME/Graph-Loom or ~/.config/Graph-Loom
+            if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
+                return PathBuf::from(xdg).join("Graph-Loom");
+            }
+            let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("~"));
+            return home.join(".config").join("Graph-Loom");
+        }
+    }
+
+    fn autosave_default_dir() -> PathBuf {
+        // Cross-platform user-writable autosave dir
+        #[cfg(target_os = "macos")]
+        {
+            // Prefer system temp autosave like Sublime, else App Support
+            let tmp = std::env::var_os("TMPDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/tmp"));
+            return tmp.join("Graph-Loom");
+        }
+        #[cfg(target_os = "windows")]
+        {
+            // %LOCALAPPDATA%\Graph-Loom\Autosave else TEMP
+            if let Ok(local) = std::env::var("LOCALAPPDATA") {
+                return PathBuf::from(local).join("Graph-Loom").join("Autosave");
+            }
+            if let Ok(temp) = std::env::var("TEMP") {
+                return PathBuf::from(temp).join("Graph-Loom");
+            }
+            return PathBuf::from("Graph-Loom");
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            // $XDG_STATE_HOME/graph-loom or ~/.local/state/graph-loom, else /tmp/Graph-Loom
+            if let Ok(xdg) = std::env::var("XDG_STATE_HOME") {
+                return PathBuf::from(xdg).join("graph-loom");
+            }
+            if let Ok(home) = std::env::var("HOME") {
+                return PathBuf::from(home).join(".local").join("state").join("graph-loom");
+            }
+            return PathBuf::from("/tmp").join("Graph-Loom");
+        }
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        // New JSON settings path
+        let json_path = Self::config_dir().join("settings.json");
+        if json_path.exists() {
+            let mut f = std::fs::File::open(json_path)?;
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            let v: Self = serde_json::from_str(&s)?;
+            return Ok(v);
+        }
+        // Migrate from legacy RON if present
+        let ron_path = Self::config_dir().join("settings.ron");
+        if ron_path.exists() {
+            let mut f = std::fs::File::open(&ron_path)?;
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            let v: Self = ron::from_str(&s)?;
+            // Save immediately to JSON for future reads, ignore errors silently
+            let _ = v.save();
+            return Ok(v);
+        }
+        Ok(Self::default())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let dir = Self::config_dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("settings.json");
+        let s = serde_json::to_string_pretty(self)?;
+        let mut f = std::fs::File::create(path)?;
+        f.write_all(s.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn autosave_dir(&self) -> PathBuf {
+        if let Some(p) = &self.autosave_override { return p.clone(); }
+        Self::autosave_default_dir()
+    }
+
+    /// Return the directory where the settings file (settings.json) is stored.
+    /// This is OS-specific and resolves to a per-user configuration directory.
+    pub fn settings_dir() -> PathBuf {
+        Self::config_dir()
+    }
+
+    /// Default export directory when no override is set: OS temporary directory.
+    /// Example: {temp_dir}/Graph-Loom/exports
+    pub fn export_default_dir() -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push("Graph-Loom");
+        p.push("exports");
+        p
+    }
+
+    /// Effective export directory honoring user override or falling back to OS temp.
+    pub fn export_dir(&self) -> PathBuf {
+        if let Some(p) = &self.export_override { return p.clone(); }
+        Self::export_default_dir()
+    }
+
+    /// Move `path` to the top of the recent list, keeping its pin, and drop the oldest
+    /// unpinned entries past [`RECENT_FILES_MAX`].
+    pub fn note_recent(&mut self, path: &Path) {
+        let pinned = self.recent_files.iter().any(|r| r.path == path && r.pinned);
+        self.recent_files.retain(|r| r.path != path);
+        self.recent_files.insert(0, RecentFile { path: path.to_path_buf(), pinned });
+        let mut unpinned = 0;
+        self.recent_files.retain(|r| {
+            if !r.pinned { unpinned += 1; }
+            r.pinned || unpinned <= RECENT_FILES_MAX
+        });
+    }
+
+    pub fn set_recent_pinned(&mut self, path: &Path, pinned: bool) {
+        if let Some(r) = self.recent_files.iter_mut().find(|r| r.path == path) { r.pinned = pinned; }
+    }
+
+    pub fn forget_recent(&mut self, path: &Path) {
+        self.recent_files.retain(|r| r.path != path);
+    }
+
+    pub fn clear_unpinned_recent(&mut self) {
+        self.recent_files.retain(|r| r.pinned);
+    }
+
+    /// Recent files as listed in the menu: pinned first, each group newest first.
+    pub fn recent_files_ordered(&self) -> Vec<&RecentFile> {
+        let (mut pinned, unpinned): (Vec<&RecentFile>, Vec<&RecentFile>) = self.recent_files.iter().partition(|r| r.pinned);
+        pinned.extend(unpinned);
+        pinned
+    }
+
+    /// `feed` ready to fetch: with its headers and `${VAR}` expansion if this user wrote it,
+    /// as read from the graph file otherwise.
+    pub fn authored_feed(&self, feed: &Feed) -> Feed {
+        let mut feed = feed.clone();
+        match self.authored_feeds.get(&feed.id).filter(|a| a.url == feed.url) {
+            Some(a) => (feed.headers, feed.authored) = (a.headers.clone(), true),
+            None => (feed.headers, feed.authored) = (String::new(), false),
+        }
+        feed
+    }
+
+    /// Record `feed` as written by this user, headers included.
+    pub fn note_authored_feed(&mut self, feed: &Feed) {
+        self.authored_feeds.insert(feed.id, AuthoredFeed { url: feed.url.clone(), headers: feed.headers.clone() });
+    }
+
+    /// The saved export template called `name`.
+    pub fn export_template(&self, name: &str) -> anyhow::Result<ExportTemplate> {
+        if let Some(t) = self.export_templates.iter().find(|t| t.name == name) { return Ok(t.clone()); }
+        let saved: Vec<&str> = self.export_templates.iter().map(|t| t.name.as_str()).collect();
+        anyhow::bail!("no export template named '{}' (saved: {})", name, if saved.is_empty() { "none".to_string() } else { saved.join(", ") })
+    }
+
+    pub(crate) fn default_bind_addr() -> String { "127.0.0.1".to_string() }
+    pub(crate) fn default_port() -> u16 { 8787 }
+    pub(crate) fn default_grpc_port() -> u16 { 50051 }
+    pub(crate) fn default_api_compression() -> bool { true }
+    pub(crate) fn default_api_max_body_bytes() -> usize { 1024 * 1024 }
+    pub(crate) fn default_api_max_import_bytes() -> usize { 64 * 1024 * 1024 }
+    pub(crate) fn default_api_max_query_len() -> usize { 64 * 1024 }
+    pub(crate) fn default_api_max_params() -> usize { 256 }
+    pub(crate) fn default_backup_keep() -> usize { 10 }
+    pub(crate) fn default_scale() -> f32 { 1.0 }
+    pub(crate) fn default_trackpad_pans() -> bool { true }
+    pub(crate) fn default_kinetic_panning() -> bool { true }
+
+    pub fn api_endpoint(&self) -> String {
+        format!("{}:{}", self.api_bind_addr, self.api_port)
+    }
+
+    /// Default API log directory when no override is set: OS temporary directory.
+    /// Example: {temp_dir}/Graph-Loom/api-logs
+    pub fn api_log_default_dir() -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push("Graph-Loom");
+        p.push("api-logs");
+        p
+    }
+
+    /// Effective API log directory honoring user override or falling back to OS temp.
+    pub fn api_log_dir(&self) -> PathBuf {
+        if let Some(p) = &self.api_log_override { return p.clone(); }
+        Self::api_log_default_dir()
+    }
+
+    /// Default watch folder when no override is set: OS temporary directory.
+    /// Example: {temp_dir}/Graph-Loom/inbox
+    pub fn watch_folder_default_dir() -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push("Graph-Loom");
+        p.push("inbox");
+        p
+    }
+
+    /// Effective watch folder honoring user override or falling back to OS temp.
+    pub fn watch_folder_dir(&self) -> PathBuf {
+        if let Some(p) = &self.watch_folder_override { return p.clone(); }
+        Self::watch_folder_default_dir()
+    }
+
+    /// Query matching options to install as the session default.
+    pub fn query_options(&self) -> QueryOptions {
+        QueryOptions {
+            case_insensitive: self.query_case_insensitive,
+            collation: if self.query_locale_collation { Collation::Locale } else { Collation::Binary },
+        }
+    }
+
+    /// Capture the current profile-scoped options under `name`.
+    pub fn profile_from_current(&self, name: &str) -> SettingsProfile {
+        SettingsProfile {
+            name: name.to_string(),
+            export_override: self.export_override.clone(),
+            lod_enabled: self.lod_enabled,
+            lod_label_min_zoom: self.lod_label_min_zoom,
+            lod_hide_labels_node_threshold: self.lod_hide_labels_node_threshold,
+            api_enabled: self.api_enabled,
+            api_bind_addr: self.api_bind_addr.clone(),
+            api_port: self.api_port,
+            api_key: self.api_key.clone(),
+            grpc_enabled: self.grpc_enabled,
+            grpc_port: self.grpc_port,
+            grpc_tls: self.grpc_tls.clone(),
+            background_on_close: self.background_on_close,
+        }
+    }
+
+    /// Store the current options as profile `name` (replacing one with the same name) and mark it active.
+    pub fn save_profile(&mut self, name: &str) {
+        let profile = self.profile_from_current(name);
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.active_profile = Some(name.to_string());
+    }
+
+    /// Copy profile `name` onto the active options. Returns false if no such profile exists.
+    pub fn apply_profile(&mut self, name: &str) -> bool {
+        let Some(p) = self.profiles.iter().find(|p| p.name == name).cloned() else { return false };
+        self.export_override = p.export_override;
+        self.lod_enabled = p.lod_enabled;
+        self.lod_label_min_zoom = p.lod_label_min_zoom;
+        self.lod_hide_labels_node_threshold = p.lod_hide_labels_node_threshold;
+        self.api_enabled = p.api_enabled;
+        self.api_bind_addr = p.api_bind_addr;
+        self.api_port = p.api_port;
+        self.api_key = p.api_key;
+        self.grpc_enabled = p.grpc_enabled;
+        self.grpc_port = p.grpc_port;
+        self.grpc_tls = p.grpc_tls;
+        self.background_on_close = p.background_on_close;
+        self.active_profile = Some(p.name);
+        true
+    }
+
+    /// Delete profile `name`; clears the active marker if it pointed at it.
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        let before = self.profiles.len();
+        self.profiles.retain(|p| p.name != name);
+        if self.active_profile.as_deref() == Some(name) { self.active_profile = None; }
+        self.profiles.len() != before
+    }
+}
@@ -0,0 +1,63 @@
+//! Saving without holding up the GUI. A [`Snapshot`] is the state as of one graph generation
+//! with the graph behind an `Arc`, so it can be serialized and written on another thread
+//! while edits go on, and [`SaveSlot`] keeps writes to the state file in generation order.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use super::migrate;
+use super::persist::{self, Annotation, StateView};
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+/// The graph and layout to save, as of `generation`.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub generation: u64,
+    pub db: Arc<GraphDatabase>,
+    pub node_positions: Vec<(NodeId, f32, f32)>,
+    pub pan: (f32, f32),
+    pub zoom: f32,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Snapshot {
+    pub fn view(&self) -> StateView<'_> {
+        StateView {
+            schema: migrate::SCHEMA,
+            db: &self.db,
+            node_positions: &self.node_positions,
+            pan: self.pan,
+            zoom: self.zoom,
+            annotations: &self.annotations,
+        }
+    }
+}
+
+/// Orders saves of the active state file. Saves running on other threads can finish out of
+/// order, so each carries the generation it was taken at and one older than the generation
+/// already on disk is dropped. Clones share the slot.
+#[derive(Debug, Clone, Default)]
+pub struct SaveSlot {
+    written: Arc<Mutex<Option<u64>>>,
+}
+
+impl SaveSlot {
+    /// Write `state`, taken at `generation`, to `path` (the active state file); `false` when
+    /// a newer generation is already written. Serializing happens before the slot is taken,
+    /// so only the file write waits on another save.
+    pub fn save(&self, generation: u64, state: &StateView, path: &Path) -> anyhow::Result<bool> {
+        let text = persist::to_ron(state)?;
+        let mut written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        if written.is_some_and(|w| w > generation) { return Ok(false); }
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) { fs::create_dir_all(dir)?; }
+        persist::atomic_write(path, text.as_bytes())?;
+        *written = Some(generation);
+        Ok(true)
+    }
+
+    /// The generation of the last write.
+    pub fn written(&self) -> Option<u64> {
+        *self.written.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Subfolder that successfully imported files are moved into.
+pub const IMPORTED_DIR: &str = "imported";
+/// Subfolder that files which failed to import are moved into.
+pub const FAILED_DIR: &str = "failed";
+
+/// Watches an import folder through the platform's file notifications. A file is reported
+/// once its size and modification time are unchanged `settle` after it was last touched, so
+/// partially written files are not picked up. The owner imports each reported path and then
+/// calls [`finish`]. Dropping the watcher stops it.
+pub struct FolderWatcher {
+    dir: PathBuf,
+    rx: Receiver<PathBuf>,
+    // Owns the sender the thread reads from; dropping it ends the thread
+    _watcher: RecommendedWatcher,
+}
+
+impl FolderWatcher {
+    pub fn start(dir: PathBuf, settle: Duration) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let (tx, rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(event_tx)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        let scan_dir = dir.clone();
+        thread::Builder::new()
+            .name("graph-loom-watch".into())
+            .spawn(move || {
+                // path -> (len, mtime) when last looked at; files already there count as touched
+                let mut pending: HashMap<PathBuf, Option<Signature>> = HashMap::new();
+                let mut sent: HashSet<PathBuf> = HashSet::new();
+                if let Ok(entries) = fs::read_dir(&scan_dir) {
+                    pending.extend(entries.flatten().map(|e| e.path()).filter(|p| is_importable(p)).map(|p| (p, None)));
+                }
+                let mut last_check = Instant::now();
+                loop {
+                    let event = if pending.is_empty() { events.recv().map_err(|_| RecvTimeoutError::Disconnected) } else { events.recv_timeout(settle) };
+                    match event {
+                        Ok(Ok(event)) => {
+                            for p in event.paths.into_iter().filter(|p| is_importable(p)) {
+                                // Moved away (imported or failed): a new file with the same name is picked up
+                                if !p.exists() { sent.remove(&p); pending.remove(&p); continue; }
+                                if !sent.contains(&p) { pending.insert(p, None); }
+                            }
+                        }
+                        Ok(Err(e)) => log::warn!("Watching {} failed: {}", scan_dir.display(), e),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                    if last_check.elapsed() < settle { continue; }
+                    last_check = Instant::now();
+                    let mut ready: Vec<PathBuf> = Vec::new();
+                    pending.retain(|p, seen| {
+                        let Some(now) = signature(p) else { return false };
+                        if *seen == Some(now) {
+                            ready.push(p.clone());
+                            return false;
+                        }
+                        *seen = Some(now);
+                        true
+                    });
+                    // Nodes before relationships so CSV pairs resolve their endpoints
+                    ready.sort_by_key(|p| (super::import::is_relationships_csv(p), p.clone()));
+                    for p in ready {
+                        sent.insert(p.clone());
+                        if tx.send(p).is_err() { return; }
+                    }
+                }
+            })?;
+        Ok(Self { dir, rx, _watcher: watcher })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Next file that is ready to import, if any.
+    pub fn try_recv(&self) -> Option<PathBuf> {
+        self.rx.try_recv().ok()
+    }
+}
+
+type Signature = (u64, Option<SystemTime>);
+
+// Size and modification time of a regular file
+fn signature(p: &Path) -> Option<Signature> {
+    let meta = fs::metadata(p).ok()?;
+    meta.is_file().then(|| (meta.len(), meta.modified().ok()))
+}
+
+fn is_importable(p: &Path) -> bool {
+    matches!(
+        p.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase()).as_deref(),
+        Some("json") | Some("csv")
+    )
+}
+
+/// Move a processed file out of the watch folder into `imported/` or `failed/`.
+pub fn finish(path: &Path, ok: bool) -> std::io::Result<PathBuf> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let dest_dir = parent.join(if ok { IMPORTED_DIR } else { FAILED_DIR });
+    fs::create_dir_all(&dest_dir)?;
+    let name = path.file_name().map(|s| s.to_os_string()).unwrap_or_default();
+    let mut dest = dest_dir.join(&name);
+    if dest.exists() {
+        let stamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        dest = dest_dir.join(format!("{}_{}", stamp, name.to_string_lossy()));
+    }
+    fs::rename(path, &dest)?;
+    Ok(dest)
+}
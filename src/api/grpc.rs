@@ -1,37 +1,124 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tonic::{transport::Server, Request, Response, Status};
+use anyhow::Context;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::{Request, Response, Status};
 
-use crate::api::{get_request_sender, ApiRequest};
-use crate::gql::query_interface::QueryResultRow;
-use crate::persistence::settings::AppSettings;
+use crate::api::ipfilter::IpRules;
+use crate::api::telemetry::{client_request_id, REQUEST_ID_HEADER};
+use crate::api::{get_request_sender, ApiLimits, ApiRequest, ApiTask, Caller, Direction, GraphOp, LimitError};
+use crate::gql::query_interface::QueryOutcome;
+use crate::graph_utils::permissions::Role;
+use crate::gql::query_interface::{self, QueryResultRow};
+use crate::persistence::settings::{AppSettings, GrpcTls};
 
 pub mod proto {
     tonic::include_proto!("graph_loom");
 }
 
 use proto::graph_query_server::{GraphQuery, GraphQueryServer};
-use proto::{QueryRequest, QueryResponse, QueryRow, Node, Relationship};
+use proto::{QueryRequest, QueryResponse, QueryRow, Node, Relationship, Path};
+use proto::{PrepareRequest, PrepareResponse, ExecutePreparedRequest, DeallocateRequest, DeallocateResponse};
+use proto::{CreateNodeRequest, UpdateNodeRequest, DeleteNodeRequest, DeleteNodeResponse, CreateRelationshipRequest};
+use proto::{GetNeighborsRequest, GetNeighborsResponse, Neighbor};
+
+/// Upper bound on prepared statements held for gRPC callers.
+const MAX_PREPARED: usize = 1024;
+
+// Prepared statement handle -> query text; the parsed plan itself lives in the Cypher plan cache
+static PREPARED: once_cell::sync::Lazy<Mutex<HashMap<String, String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
-#[derive(Default)]
 pub struct MyGraphQuery {
     api_key: Option<String>,
+    roles: Vec<Role>,
+    limits: ApiLimits,
+    ip_rules: IpRules,
+    // A client CA is configured, so a verified client certificate replaces the API key
+    cert_auth: bool,
 }
 
-#[tonic::async_trait]
-impl GraphQuery for MyGraphQuery {
-    async fn execute(
-        &self,
-        request: Request<QueryRequest>,
-    ) -> Result<Response<QueryResponse>, Status> {
-        if let Some(required_key) = &self.api_key {
-            let metadata = request.metadata();
-            match metadata.get("x-api-key") {
-                Some(key) if key == required_key => {}
-                _ => return Err(Status::unauthenticated("invalid or missing api key")),
-            }
+/// Listener TLS settings from `t`, or `None` when TLS is off. Fails if a PEM file cannot be read.
+pub fn tls_config(t: &GrpcTls) -> anyhow::Result<Option<ServerTlsConfig>> {
+    if !t.enabled { return Ok(None); }
+    let read = |what: &str, path: &str| -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(!path.trim().is_empty(), "gRPC TLS is enabled but no {} file is set", what);
+        std::fs::read(path.trim()).with_context(|| format!("reading gRPC TLS {} file {}", what, path.trim()))
+    };
+    let identity = Identity::from_pem(read("certificate", &t.cert_file)?, read("private key", &t.key_file)?);
+    let mut tls = ServerTlsConfig::new().identity(identity);
+    if !t.client_ca_file.trim().is_empty() {
+        tls = tls
+            .client_ca_root(Certificate::from_pem(read("client CA", &t.client_ca_file)?))
+            .client_auth_optional(!t.require_client_cert);
+    } else {
+        anyhow::ensure!(!t.require_client_cert, "client certificates are required but no client CA file is set");
+    }
+    Ok(Some(tls))
+}
+
+fn limit_status(e: LimitError) -> Status {
+    match e {
+        LimitError::QueryTooLong { .. } => Status::resource_exhausted(e.to_string()),
+        LimitError::TooManyParams { .. } => Status::invalid_argument(e.to_string()),
+    }
+}
+
+/// Status for a failed typed operation: `PERMISSION_DENIED` for a write the caller's role
+/// (`limited`) does not cover, `NOT_FOUND` for a missing node and `FAILED_PRECONDITION` for a
+/// change the graph's property rules or computed properties refuse.
+pub fn graph_op_status(e: String, limited: bool) -> Status {
+    if limited && e.starts_with("role '") {
+        Status::permission_denied(e)
+    } else if e.starts_with("node ") && e.ends_with(" not found") {
+        Status::not_found(e)
+    } else {
+        Status::failed_precondition(e)
+    }
+}
+
+// tonic handlers return `Status` by value; boxing it here would only be unboxed again
+// Who is calling, and the ID the call is logged and traced under
+struct Call {
+    // Limits on the call's writes, for a role key
+    role: Option<Role>,
+    request_id: String,
+}
+
+// Answer `msg`, echoing the call's request ID in the response metadata
+fn reply<T>(call: &Call, msg: T) -> Response<T> {
+    let mut res = Response::new(msg);
+    if let Ok(v) = call.request_id.parse() { res.metadata_mut().insert(REQUEST_ID_HEADER, v); }
+    res
+}
+
+#[allow(clippy::result_large_err)]
+impl MyGraphQuery {
+    // Every handler starts here: client address filter, then API key. The request ID is the
+    // client's `x-request-id`, if it sent a usable one.
+    fn check_key<T>(&self, request: &Request<T>) -> Result<Call, Status> {
+        if let Some(addr) = request.remote_addr()
+            && !self.ip_rules.allows(addr.ip())
+        {
+            return Err(Status::permission_denied("client address not allowed"));
+        }
+        let request_id = client_request_id(request.metadata().get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()))
+            .unwrap_or_else(|| format!("grpc-{}", uuid::Uuid::now_v7()));
+        // The handshake already verified any certificate against the client CA
+        if self.cert_auth && request.peer_certs().is_some_and(|certs| !certs.is_empty()) {
+            return Ok(Call { role: None, request_id });
+        }
+        let key = request.metadata().get("x-api-key").and_then(|k| k.to_str().ok());
+        match Caller::of(key, self.api_key.as_deref(), &self.roles) {
+            Caller::Full => Ok(Call { role: None, request_id }),
+            Caller::Role(role) => Ok(Call { role: Some(role), request_id }),
+            Caller::Refused => Err(Status::unauthenticated("invalid or missing api key")),
         }
+    }
 
-        let req = request.into_inner();
+    // Hand a request to the broker and wait for its outcome
+    fn submit(&self, call: &Call, query: String, params: Option<HashMap<String, String>>, log: bool, task: Option<ApiTask>) -> Result<Result<QueryOutcome, String>, Status> {
+        let _span = tracing::info_span!("grpc.request", request_id = %call.request_id).entered();
         let sender = match get_request_sender() {
             Some(s) => s.clone(),
             None => return Err(Status::unavailable("broker not ready")),
@@ -39,64 +126,218 @@ impl GraphQuery for MyGraphQuery {
 
         let (tx, rx) = std::sync::mpsc::channel();
         let api_req = ApiRequest {
-            request_id: format!("grpc-{}", uuid::Uuid::now_v7()),
-            query: req.query.clone(),
-            params: Some(req.params),
-            log: req.log,
+            request_id: call.request_id.clone(),
+            query,
+            params,
+            log,
             respond_to: tx,
+            task,
+            role: call.role.clone(),
         };
 
         if sender.send(api_req).is_err() {
             return Err(Status::internal("failed to enqueue request"));
         }
 
-        match rx.recv_timeout(std::time::Duration::from_secs(30)) {
-            Ok(Ok(out)) => {
-                let mut rows = Vec::with_capacity(out.rows.len());
-                for r in out.rows {
-                    let row = match r {
-                        QueryResultRow::Node { id, label, metadata } => QueryRow {
-                            item: Some(proto::query_row::Item::Node(Node {
-                                id: id.to_string(),
-                                label,
-                                metadata,
-                            })),
-                        },
-                        QueryResultRow::Relationship { id, from, to, label, metadata } => QueryRow {
-                            item: Some(proto::query_row::Item::Relationship(Relationship {
-                                id: id.to_string(),
-                                from_id: from.to_string(),
-                                to_id: to.to_string(),
-                                label,
-                                metadata,
-                            })),
-                        },
-                        QueryResultRow::Info(s) => QueryRow {
-                            item: Some(proto::query_row::Item::Info(s)),
-                        },
-                    };
-                    rows.push(row);
-                }
-                Ok(Response::new(QueryResponse {
+        rx.recv_timeout(std::time::Duration::from_secs(30)).map_err(|_| Status::deadline_exceeded("query timeout"))
+    }
+
+    fn graph_op(&self, call: &Call, op: GraphOp) -> Result<QueryOutcome, Status> {
+        let limited = call.role.is_some();
+        self.submit(call, String::new(), None, false, Some(ApiTask::Graph(op)))?.map_err(|e| graph_op_status(e, limited))
+    }
+
+    fn run(&self, call: &Call, query: String, params: HashMap<String, String>, log: bool) -> Result<Response<QueryResponse>, Status> {
+        self.limits.check_query(&query, Some(&params)).map_err(limit_status)?;
+        match self.submit(call, query, Some(params), log, None)? {
+            Ok(out) => {
+                let rows = out.rows.into_iter().map(|r| QueryRow { item: Some(row_item(r)) }).collect();
+                Ok(reply(call, QueryResponse {
                     rows,
                     affected_nodes: out.affected_nodes as u64,
                     affected_relationships: out.affected_relationships as u64,
                     mutated: out.mutated,
                     error: String::new(),
+                    warnings: out.warnings,
                 }))
             }
-            Ok(Err(e)) => Ok(Response::new(QueryResponse {
+            Err(e) => Ok(reply(call, QueryResponse {
                 rows: vec![],
                 affected_nodes: 0,
                 affected_relationships: 0,
                 mutated: false,
                 error: e,
+                warnings: vec![],
             })),
-            Err(_) => Err(Status::deadline_exceeded("query timeout")),
         }
     }
 }
 
+fn row_item(r: QueryResultRow) -> proto::query_row::Item {
+    match r {
+        QueryResultRow::Node { id, label, metadata } => proto::query_row::Item::Node(Node {
+            id: id.to_string(),
+            label,
+            metadata,
+        }),
+        QueryResultRow::Relationship { id, from, to, label, metadata } => proto::query_row::Item::Relationship(Relationship {
+            id: id.to_string(),
+            from_id: from.to_string(),
+            to_id: to.to_string(),
+            label,
+            metadata,
+        }),
+        QueryResultRow::Path { nodes, relationships } => proto::query_row::Item::Path(Path {
+            node_ids: nodes.iter().map(|id| id.to_string()).collect(),
+            relationship_ids: relationships.iter().map(|id| id.to_string()).collect(),
+        }),
+        QueryResultRow::Info(s) => proto::query_row::Item::Info(s),
+        // Row kinds the protocol predates are passed on as text
+        other => proto::query_row::Item::Info(format!("{:?}", other)),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_id(field: &str, s: &str) -> Result<uuid::Uuid, Status> {
+    uuid::Uuid::parse_str(s.trim()).map_err(|_| Status::invalid_argument(format!("{} is not a valid id", field)))
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.trim().is_empty() { None } else { Some(s) }
+}
+
+// The single node or relationship an edit returns (`Status` by value, as in the handlers)
+#[allow(clippy::result_large_err)]
+fn first_node(out: QueryOutcome) -> Result<Node, Status> {
+    out.rows.into_iter().find_map(|r| match row_item(r) { proto::query_row::Item::Node(n) => Some(n), _ => None })
+        .ok_or_else(|| Status::internal("no node in result"))
+}
+
+#[allow(clippy::result_large_err)]
+fn first_relationship(out: QueryOutcome) -> Result<Relationship, Status> {
+    out.rows.into_iter().find_map(|r| match row_item(r) { proto::query_row::Item::Relationship(r) => Some(r), _ => None })
+        .ok_or_else(|| Status::internal("no relationship in result"))
+}
+
+#[tonic::async_trait]
+impl GraphQuery for MyGraphQuery {
+    async fn execute(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<QueryResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        self.run(&call, req.query, req.params, req.log)
+    }
+
+    async fn prepare(
+        &self,
+        request: Request<PrepareRequest>,
+    ) -> Result<Response<PrepareResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        self.limits.check_query(&req.query, None).map_err(limit_status)?;
+        if let Err(e) = query_interface::prepare(&req.query) {
+            return Ok(reply(&call, PrepareResponse { statement_id: String::new(), error: e.to_string() }));
+        }
+        let mut prepared = PREPARED.lock().map_err(|_| Status::internal("prepared statements unavailable"))?;
+        if prepared.len() >= MAX_PREPARED {
+            return Err(Status::resource_exhausted("too many prepared statements; deallocate unused handles"));
+        }
+        let statement_id = uuid::Uuid::now_v7().to_string();
+        prepared.insert(statement_id.clone(), req.query);
+        Ok(reply(&call, PrepareResponse { statement_id, error: String::new() }))
+    }
+
+    async fn execute_prepared(
+        &self,
+        request: Request<ExecutePreparedRequest>,
+    ) -> Result<Response<QueryResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        let query = PREPARED
+            .lock()
+            .map_err(|_| Status::internal("prepared statements unavailable"))?
+            .get(&req.statement_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("unknown statement_id"))?;
+        self.run(&call, query, req.params, req.log)
+    }
+
+    async fn deallocate(
+        &self,
+        request: Request<DeallocateRequest>,
+    ) -> Result<Response<DeallocateResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        let found = PREPARED
+            .lock()
+            .map_err(|_| Status::internal("prepared statements unavailable"))?
+            .remove(&req.statement_id)
+            .is_some();
+        Ok(reply(&call, DeallocateResponse { found }))
+    }
+
+    async fn create_node(&self, request: Request<CreateNodeRequest>) -> Result<Response<Node>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        if req.label.trim().is_empty() { return Err(Status::invalid_argument("label is required")); }
+        let out = self.graph_op(&call, GraphOp::CreateNode { label: req.label, metadata: req.metadata })?;
+        Ok(reply(&call, first_node(out)?))
+    }
+
+    async fn update_node(&self, request: Request<UpdateNodeRequest>) -> Result<Response<Node>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        let op = GraphOp::UpdateNode {
+            id: parse_id("id", &req.id)?,
+            label: non_empty(req.label),
+            set: req.set_metadata,
+            remove: req.remove_keys,
+        };
+        Ok(reply(&call, first_node(self.graph_op(&call, op)?)?))
+    }
+
+    async fn delete_node(&self, request: Request<DeleteNodeRequest>) -> Result<Response<DeleteNodeResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        let out = self.graph_op(&call, GraphOp::DeleteNode { id: parse_id("id", &req.id)? })?;
+        Ok(reply(&call, DeleteNodeResponse { relationships_removed: out.affected_relationships as u64 }))
+    }
+
+    async fn create_relationship(&self, request: Request<CreateRelationshipRequest>) -> Result<Response<Relationship>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        if req.label.trim().is_empty() { return Err(Status::invalid_argument("label is required")); }
+        let op = GraphOp::CreateRelationship {
+            from: parse_id("from_id", &req.from_id)?,
+            to: parse_id("to_id", &req.to_id)?,
+            label: req.label,
+            metadata: req.metadata,
+        };
+        Ok(reply(&call, first_relationship(self.graph_op(&call, op)?)?))
+    }
+
+    async fn get_neighbors(&self, request: Request<GetNeighborsRequest>) -> Result<Response<GetNeighborsResponse>, Status> {
+        let call = self.check_key(&request)?;
+        let req = request.into_inner();
+        let direction = match proto::Direction::try_from(req.direction) {
+            Ok(proto::Direction::Outgoing) => Direction::Outgoing,
+            Ok(proto::Direction::Incoming) => Direction::Incoming,
+            Ok(proto::Direction::Both) => Direction::Both,
+            Err(_) => return Err(Status::invalid_argument("unknown direction")),
+        };
+        let out = self.graph_op(&call, GraphOp::Neighbors { id: parse_id("id", &req.id)?, direction, label: non_empty(req.label) })?;
+        // Rows come as relationship, node pairs
+        let mut neighbors = Vec::new();
+        let mut items = out.rows.into_iter().map(row_item);
+        while let (Some(proto::query_row::Item::Relationship(rel)), Some(proto::query_row::Item::Node(node))) = (items.next(), items.next()) {
+            neighbors.push(Neighbor { relationship: Some(rel), node: Some(node) });
+        }
+        Ok(reply(&call, GetNeighborsResponse { neighbors }))
+    }
+}
+
 struct GrpcServerState {
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
     runtime: Option<tokio::runtime::Runtime>,
@@ -117,6 +358,11 @@ pub fn start_grpc_server(cfg: &AppSettings) -> anyhow::Result<()> {
     let addr = format!("{}:{}", cfg.api_bind_addr, cfg.grpc_port).parse()?;
     let (tx, rx) = tokio::sync::oneshot::channel::<()>();
     let api_key = cfg.api_key.clone();
+    let roles = cfg.api_roles.clone();
+    let limits = ApiLimits::from_settings(cfg);
+    let ip_rules = IpRules::parse_lenient(&cfg.grpc_ip_filter);
+    let tls = tls_config(&cfg.grpc_tls)?;
+    let cert_auth = tls.is_some() && !cfg.grpc_tls.client_ca_file.trim().is_empty();
 
     {
         let mut state = GRPC_SERVER_STATE.lock().unwrap();
@@ -130,20 +376,30 @@ pub fn start_grpc_server(cfg: &AppSettings) -> anyhow::Result<()> {
             .build() {
                 Ok(r) => r,
                 Err(e) => {
-                    eprintln!("[Graph-Loom] Failed to create tokio runtime for gRPC: {}", e);
+                    log::error!("Failed to create tokio runtime for gRPC: {}", e);
                     return;
                 }
             };
 
         rt.block_on(async {
-            let service = MyGraphQuery { api_key };
-            if let Err(e) = Server::builder()
-                .add_service(GraphQueryServer::new(service))
+            let service = MyGraphQuery { api_key, roles, limits, ip_rules, cert_auth };
+            let mut builder = Server::builder();
+            if let Some(tls) = tls {
+                builder = match builder.tls_config(tls) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        log::error!("gRPC TLS setup failed: {}", e);
+                        return;
+                    }
+                };
+            }
+            if let Err(e) = builder
+                .add_service(GraphQueryServer::new(service).max_decoding_message_size(limits.max_body_bytes))
                 .serve_with_shutdown(addr, async {
                     let _ = rx.await;
                 })
                 .await {
-                    eprintln!("[Graph-Loom] gRPC server failed: {}", e);
+                    log::error!("gRPC server failed: {}", e);
                 }
         });
         {
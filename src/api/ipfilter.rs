@@ -0,0 +1,96 @@
+//! Client IP allow/deny lists for the API servers.
+//!
+//! Entries are CIDR blocks (`10.0.0.0/8`, `fd00::/8`) or single addresses. A client matching
+//! the deny list is always refused; when the allow list is non-empty, only clients matching
+//! it get through. An allow list with no valid entry lets no one through.
+
+use std::net::IpAddr;
+
+use crate::persistence::settings::IpFilter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+// IPv4 clients often show up as IPv4-mapped IPv6 addresses on dual-stack sockets
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+impl Cidr {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| format!("'{}' is not an IP address or CIDR block", s))?;
+        let addr = normalize(addr);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("'{}' has an invalid prefix length", s))?,
+            None => max,
+        };
+        Ok(Cidr { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, normalize(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parsed form of an [`IpFilter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpRules {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+    // An allow list was set but none of it parsed: refuse everyone rather than admit them
+    closed: bool,
+}
+
+impl IpRules {
+    /// Parse every entry; blank entries are ignored.
+    pub fn parse(filter: &IpFilter) -> Result<Self, String> {
+        let parse = |list: &[String]| -> Result<Vec<Cidr>, String> {
+            list.iter().filter(|e| !e.trim().is_empty()).map(|e| Cidr::parse(e)).collect()
+        };
+        Ok(IpRules { allow: parse(&filter.allow)?, deny: parse(&filter.deny)?, closed: false })
+    }
+
+    /// Like [`IpRules::parse`] but skips invalid entries with a logged error, for settings
+    /// files edited by hand. When that leaves a configured allow list empty, every client is
+    /// refused.
+    pub fn parse_lenient(filter: &IpFilter) -> Self {
+        let parse = |list: &[String]| -> Vec<Cidr> {
+            list.iter()
+                .filter(|e| !e.trim().is_empty())
+                .filter_map(|e| Cidr::parse(e).map_err(|err| log::error!("Ignoring IP filter entry: {}", err)).ok())
+                .collect()
+        };
+        let allow = parse(&filter.allow);
+        let closed = allow.is_empty() && filter.allow.iter().any(|e| !e.trim().is_empty());
+        if closed { log::error!("No entry of the IP allow list is valid; refusing every client until it is fixed"); }
+        IpRules { allow, deny: parse(&filter.deny), closed }
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        if self.closed { return false; }
+        if self.deny.iter().any(|c| c.contains(ip)) { return false; }
+        self.allow.is_empty() || self.allow.iter().any(|c| c.contains(ip))
+    }
+}
@@ -0,0 +1,163 @@
+//! Registry of asynchronous query jobs submitted through `/v1/jobs`.
+//!
+//! A job is an ordinary broker request whose `request_id` is the job id. The thread that
+//! executes requests calls [`begin`] first, which moves the job to `running` (or tells it to
+//! skip a job cancelled while queued); the submitting thread records the outcome with
+//! [`finish`]. Queries cannot be interrupted once running, so cancelling a running job only
+//! discards its result.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::gql::query_interface::QueryOutcome;
+
+// Finished jobs kept for polling; the oldest are dropped first
+const MAX_FINISHED: usize = 100;
+/// Start of every job id. Client request IDs may not use it, or [`begin`] would take their
+/// request for a job.
+pub const ID_PREFIX: &str = "job-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: String,
+    pub state: JobState,
+    pub submitted_at: String,
+    // Milliseconds since submission, or total run time once finished
+    pub elapsed_ms: u128,
+    // Jobs ahead of this one while it is queued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Job {
+    state: JobState,
+    submitted_at: String,
+    submitted: Instant,
+    finished: Option<Instant>,
+    result: Option<Result<QueryOutcome, String>>,
+}
+
+static JOBS: Lazy<Mutex<HashMap<String, Job>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Register a new queued job and return its id.
+pub fn create() -> String {
+    let id = format!("{}{}", ID_PREFIX, uuid::Uuid::now_v7());
+    let mut jobs = JOBS.lock().unwrap();
+    prune(&mut jobs);
+    jobs.insert(
+        id.clone(),
+        Job { state: JobState::Queued, submitted_at: crate::gql::temporal::now(), submitted: Instant::now(), finished: None, result: None },
+    );
+    id
+}
+
+fn prune(jobs: &mut HashMap<String, Job>) {
+    let mut finished: Vec<(Instant, String)> =
+        jobs.iter().filter_map(|(id, j)| j.finished.map(|t| (t, id.clone()))).collect();
+    if finished.len() < MAX_FINISHED { return; }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED) {
+        jobs.remove(id);
+    }
+}
+
+/// Called right before a request runs. Returns false when it is a job that was cancelled
+/// while queued and must be skipped; any other request id is allowed through.
+pub fn begin(request_id: &str) -> bool {
+    if !request_id.starts_with(ID_PREFIX) { return true; }
+    let mut jobs = JOBS.lock().unwrap();
+    match jobs.get_mut(request_id) {
+        Some(job) if job.state == JobState::Cancelled => false,
+        Some(job) => {
+            job.state = JobState::Running;
+            true
+        }
+        None => true,
+    }
+}
+
+/// Record a job's outcome. A job cancelled in the meantime stays cancelled.
+pub fn finish(id: &str, result: Result<QueryOutcome, String>) {
+    let mut jobs = JOBS.lock().unwrap();
+    let Some(job) = jobs.get_mut(id) else { return };
+    job.finished.get_or_insert_with(Instant::now);
+    if job.state == JobState::Cancelled { return; }
+    job.state = if result.is_ok() { JobState::Succeeded } else { JobState::Failed };
+    job.result = Some(result);
+}
+
+/// Cancel a queued or running job. Returns its state afterwards, or `None` for an unknown id.
+pub fn cancel(id: &str) -> Option<JobState> {
+    let mut jobs = JOBS.lock().unwrap();
+    let job = jobs.get_mut(id)?;
+    if !job.state.is_finished() {
+        job.state = JobState::Cancelled;
+        job.finished = Some(Instant::now());
+    }
+    Some(job.state)
+}
+
+/// Forget a finished job. Returns false when the id is unknown or the job is still active.
+pub fn remove(id: &str) -> bool {
+    let mut jobs = JOBS.lock().unwrap();
+    if !jobs.get(id).is_some_and(|j| j.state.is_finished()) { return false; }
+    jobs.remove(id);
+    true
+}
+
+fn status_of(jobs: &HashMap<String, Job>, id: &str, job: &Job) -> JobStatus {
+    let queue_position = (job.state == JobState::Queued)
+        .then(|| jobs.values().filter(|o| o.state == JobState::Queued && o.submitted < job.submitted).count());
+    let end = job.finished.unwrap_or_else(Instant::now);
+    JobStatus {
+        id: id.to_string(),
+        state: job.state,
+        submitted_at: job.submitted_at.clone(),
+        elapsed_ms: end.duration_since(job.submitted).as_millis(),
+        queue_position,
+        rows: job.result.as_ref().and_then(|r| r.as_ref().ok()).map(|o| o.rows.len()),
+        error: job.result.as_ref().and_then(|r| r.as_ref().err()).cloned(),
+    }
+}
+
+pub fn status(id: &str) -> Option<JobStatus> {
+    let jobs = JOBS.lock().unwrap();
+    jobs.get(id).map(|j| status_of(&jobs, id, j))
+}
+
+/// All known jobs, oldest first.
+pub fn list() -> Vec<JobStatus> {
+    let jobs = JOBS.lock().unwrap();
+    let mut all: Vec<(&String, &Job)> = jobs.iter().collect();
+    all.sort_by_key(|(_, j)| j.submitted);
+    all.into_iter().map(|(id, j)| status_of(&jobs, id, j)).collect()
+}
+
+/// Outcome of a finished job (cloned so it can be fetched more than once).
+pub fn result(id: &str) -> Option<Result<QueryOutcome, String>> {
+    JOBS.lock().unwrap().get(id).and_then(|j| j.result.clone())
+}
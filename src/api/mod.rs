@@ -2,7 +2,18 @@ use once_cell::sync::OnceCell;
 use std::collections::HashMap;
 use std::sync::mpsc::{Receiver, Sender};
 
-use crate::gql::query_interface::QueryOutcome;
+use std::path::{Path, PathBuf};
+
+use crate::gql::query_interface::{self, QueryOutcome, QueryResultRow};
+use crate::graph_utils::changes::{self, ChangeEvent, Since};
+use crate::graph_utils::graph::{GraphDatabase, NodeId, Vocabulary};
+use crate::graph_utils::memory::{self, MemoryReport};
+use crate::graph_utils::permissions::{self, Role};
+use crate::graph_utils::schema::Schema;
+use crate::graph_utils::validation;
+use crate::persistence::export;
+use crate::persistence::import::{self, ImportPayload, ImportSummary};
+use crate::persistence::settings::AppSettings;
 
 // Global sender that Actix handlers use to send requests into the GUI thread
 static API_REQ_TX: OnceCell<Sender<ApiRequest>> = OnceCell::new();
@@ -14,6 +25,335 @@ pub struct ApiRequest {
     pub params: Option<HashMap<String, String>>, // optional
     pub log: bool,
     pub respond_to: Sender<Result<QueryOutcome, String>>, // Ok = outcome, Err = error string
+    // When set, the GUI thread runs this instead of `query`
+    pub task: Option<ApiTask>,
+    // Role of the API key the request came with; `None` writes without limits
+    pub role: Option<Role>,
+}
+
+/// Work other than a query that API handlers hand to the GUI thread.
+#[derive(Debug, Clone)]
+pub enum ApiTask {
+    /// One batch of a bulk import; `replace` clears the graph first (set on the first batch only).
+    /// A keyed `matching` comes with the whole body as one batch.
+    Import { batch: ImportPayload, replace: bool, matching: import::ImportMatch },
+    /// Send a copy of the graph (only nodes with these labels, when given) for an export.
+    Snapshot { labels: Option<Vec<String>>, reply: Sender<GraphDatabase> },
+    /// Send the labels, relationship types and metadata keys in use, with their counts.
+    Catalog { reply: Sender<Vocabulary> },
+    /// Send the schema inferred from the graph.
+    Schema { reply: Sender<Schema> },
+    /// Send the logged changes selected by `since`, or why they could not be read.
+    Changes { since: Since, reply: Sender<Result<Vec<ChangeEvent>, String>> },
+    /// Send the memory the graph holds, after shrinking it to its contents when `compact`; the
+    /// second value is the bytes compacting gave back.
+    Memory { compact: bool, reply: Sender<(MemoryReport, usize)> },
+    /// Save or load the state file; handled by whoever owns it (GUI or background loop).
+    Admin(AdminTask),
+    /// A typed edit or lookup from the gRPC mutation RPCs.
+    Graph(GraphOp),
+}
+
+#[derive(Debug, Clone)]
+pub enum GraphOp {
+    CreateNode { label: String, metadata: HashMap<String, String> },
+    /// `label: None` keeps the label; `set` is applied before `remove`.
+    UpdateNode { id: NodeId, label: Option<String>, set: HashMap<String, String>, remove: Vec<String> },
+    /// Delete a node and the relationships touching it.
+    DeleteNode { id: NodeId },
+    CreateRelationship { from: NodeId, to: NodeId, label: String, metadata: HashMap<String, String> },
+    /// Relationships touching `id` (only those with `label`, when given), each with the node at
+    /// the other end.
+    Neighbors { id: NodeId, direction: Direction, label: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    Both,
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Debug, Clone)]
+pub enum AdminTask {
+    Save,
+    SaveVersion,
+    /// Replace the running graph with a saved version.
+    LoadVersion(PathBuf),
+}
+
+/// Run a query or a graph-only task (import batch, snapshot) against `db`. Writes are logged
+/// as made by `api:<request id>` and limited to the request's role; the work is traced in an
+/// `api.execute` span carrying the request ID.
+pub fn execute(db: &mut GraphDatabase, req: &mut ApiRequest) -> anyhow::Result<QueryOutcome> {
+    let _span = tracing::info_span!("api.execute", request_id = %req.request_id).entered();
+    if !jobs::begin(&req.request_id) { return Err(anyhow::anyhow!("job {} was cancelled", req.request_id)); }
+    permissions::as_role(req.role.clone(), || changes::as_actor(format!("api:{}", req.request_id), || run(db, req)))
+}
+
+fn run(db: &mut GraphDatabase, req: &mut ApiRequest) -> anyhow::Result<QueryOutcome> {
+    match req.task.take() {
+        Some(ApiTask::Import { batch, replace, matching }) => {
+            let role = permissions::current_role();
+            let merge = |db: &mut GraphDatabase| {
+                if replace { db.clear_elements(); }
+                let mut out = import_outcome(&import::Merger::new(matching).merge(db, batch));
+                out.mutated |= replace;
+                Ok(out)
+            };
+            if role.is_none() && db.property_rules.is_empty() { return merge(db); }
+            // Imported rows bring their computed keys along, so only the role and rules apply
+            db.with_rollback(merge, |before, after| {
+                if let Some(role) = &role { permissions::check(role, before, after)?; }
+                validation::check(before, after)
+            })
+        }
+        Some(ApiTask::Snapshot { labels, reply }) => {
+            let _ = reply.send(export::snapshot(db, labels.as_deref()));
+            Ok(QueryOutcome::default())
+        }
+        Some(ApiTask::Catalog { reply }) => {
+            let _ = reply.send(db.vocabulary().clone());
+            Ok(QueryOutcome::default())
+        }
+        Some(ApiTask::Schema { reply }) => {
+            let _ = reply.send(Schema::infer(db));
+            Ok(QueryOutcome::default())
+        }
+        Some(ApiTask::Changes { since, reply }) => {
+            let _ = reply.send(db.changes().since(&since).map_err(|e| e.to_string()));
+            Ok(QueryOutcome::default())
+        }
+        Some(ApiTask::Memory { compact, reply }) => {
+            let reclaimed = if compact { memory::compact(db) } else { 0 };
+            let _ = reply.send((memory::measure(db), reclaimed));
+            Ok(QueryOutcome::default())
+        }
+        Some(ApiTask::Admin(task)) => Err(anyhow::anyhow!("{:?} needs the state file owner", task)),
+        Some(ApiTask::Graph(op)) => query_interface::checked(db, |db| apply_op(db, op)),
+        None => match &req.params {
+            Some(p) => query_interface::execute_query_with_params(db, &req.query, p),
+            None if req.log => query_interface::execute_and_log(db, &req.query),
+            None => query_interface::execute_query(db, &req.query),
+        },
+    }
+}
+
+fn node_row(db: &GraphDatabase, id: NodeId) -> Option<QueryResultRow> {
+    db.get_node(id).map(|n| QueryResultRow::Node { id, label: n.label.clone(), metadata: n.metadata.clone() })
+}
+
+fn rel_row(db: &GraphDatabase, id: uuid::Uuid) -> Option<QueryResultRow> {
+    db.get_relationship(id).map(|r| QueryResultRow::Relationship {
+        id,
+        from: r.from_node,
+        to: r.to_node,
+        label: r.label.clone(),
+        metadata: r.metadata.clone(),
+    })
+}
+
+/// Run a typed graph operation. Its rows are the element created or updated; for `Neighbors`,
+/// relationship/node pairs in relationship id order; for `DeleteNode`, a
+/// `relationships_removed=N` info row. Fails only when a referenced node does not exist.
+pub fn apply_op(db: &mut GraphDatabase, op: GraphOp) -> anyhow::Result<QueryOutcome> {
+    let missing = |id: &NodeId| anyhow::anyhow!("node {} not found", id);
+    match op {
+        GraphOp::CreateNode { label, metadata } => {
+            db.check_node(&label, &metadata)?;
+            let id = db.add_node(label, metadata);
+            Ok(QueryOutcome { rows: node_row(db, id).into_iter().collect(), affected_nodes: 1, mutated: true, ..Default::default() })
+        }
+        GraphOp::UpdateNode { id, label, set, remove } => {
+            let before = db.get_node(id).ok_or_else(|| missing(&id))?.clone();
+            // Refuse the whole update when the node it leaves would break a property rule
+            let mut after = before.metadata.clone();
+            after.extend(set.clone());
+            for k in &remove { after.remove(k); }
+            db.check_node(label.as_deref().unwrap_or(&before.label), &after)?;
+            if let Some(label) = label.filter(|l| *l != before.label) { db.update_node_label(id, label); }
+            for (k, v) in set {
+                if before.metadata.get(&k) != Some(&v) { db.upsert_node_metadata(id, k, v); }
+            }
+            for k in remove { db.remove_node_metadata_key(id, &k); }
+            let mutated = db.get_node(id).is_some_and(|n| n.label != before.label || n.metadata != before.metadata);
+            Ok(QueryOutcome { rows: node_row(db, id).into_iter().collect(), affected_nodes: mutated as usize, mutated, ..Default::default() })
+        }
+        GraphOp::DeleteNode { id } => {
+            if !db.nodes.contains_key(&id) { return Err(missing(&id)); }
+            let rels = db.relationships.values().filter(|r| r.from_node == id || r.to_node == id).count();
+            db.remove_node(id);
+            Ok(QueryOutcome {
+                rows: vec![QueryResultRow::Info(format!("relationships_removed={}", rels))],
+                affected_nodes: 1,
+                affected_relationships: rels,
+                mutated: true,
+                warnings: Vec::new(),
+            })
+        }
+        GraphOp::CreateRelationship { from, to, label, metadata } => {
+            for id in [&from, &to] {
+                if !db.nodes.contains_key(id) { return Err(missing(id)); }
+            }
+            let before = db.relationship_count();
+            let id = db.add_relationship(from, to, label, metadata).ok_or_else(|| missing(&from))?;
+            // A unique label hands back the existing edge instead of adding one
+            let mutated = db.relationship_count() != before;
+            Ok(QueryOutcome { rows: rel_row(db, id).into_iter().collect(), affected_relationships: mutated as usize, mutated, ..Default::default() })
+        }
+        GraphOp::Neighbors { id, direction, label } => {
+            if !db.nodes.contains_key(&id) { return Err(missing(&id)); }
+            let mut rels: Vec<(uuid::Uuid, NodeId)> = db
+                .relationships
+                .values()
+                .filter(|r| label.as_ref().is_none_or(|l| *l == r.label))
+                .filter_map(|r| {
+                    let out = r.from_node == id && direction != Direction::Incoming;
+                    let inc = r.to_node == id && direction != Direction::Outgoing;
+                    if out { Some((r.id, r.to_node)) } else if inc { Some((r.id, r.from_node)) } else { None }
+                })
+                .collect();
+            rels.sort();
+            let rows = rels.into_iter().flat_map(|(rid, peer)| rel_row(db, rid).into_iter().chain(node_row(db, peer))).collect();
+            Ok(QueryOutcome { rows, ..Default::default() })
+        }
+    }
+}
+
+/// Outcome of an admin task that wrote or read `path`.
+pub fn path_outcome(path: &Path) -> QueryOutcome {
+    QueryOutcome { rows: vec![QueryResultRow::Info(format!("path={}", path.display()))], ..Default::default() }
+}
+
+/// Report an import summary as `key=value` info rows (read back with [`summary_from_outcome`]).
+pub fn import_outcome(s: &ImportSummary) -> QueryOutcome {
+    let rows = [
+        ("nodes_added", s.nodes_added),
+        ("nodes_updated", s.nodes_updated),
+        ("rels_added", s.rels_added),
+        ("rels_updated", s.rels_updated),
+        ("rels_skipped", s.rels_skipped),
+        ("kept", s.kept),
+    ]
+    .iter()
+    .map(|(k, v)| QueryResultRow::Info(format!("{}={}", k, v)))
+    .collect();
+    QueryOutcome {
+        rows,
+        affected_nodes: s.nodes_added + s.nodes_updated,
+        affected_relationships: s.rels_added + s.rels_updated,
+        mutated: !s.is_empty(),
+        warnings: Vec::new(),
+    }
+}
+
+pub fn summary_from_outcome(o: &QueryOutcome) -> ImportSummary {
+    let mut s = ImportSummary::default();
+    for r in &o.rows {
+        let QueryResultRow::Info(line) = r else { continue };
+        let Some((k, v)) = line.split_once('=') else { continue };
+        let n: usize = v.parse().unwrap_or(0);
+        match k {
+            "nodes_added" => s.nodes_added = n,
+            "nodes_updated" => s.nodes_updated = n,
+            "rels_added" => s.rels_added = n,
+            "rels_updated" => s.rels_updated = n,
+            "rels_skipped" => s.rels_skipped = n,
+            "kept" => s.kept = n,
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Version of the HTTP response shapes served under `/v1/`. Bump it, and add a new route
+/// prefix, for breaking changes; older versions stay listed in [`SUPPORTED_API_VERSIONS`].
+pub const API_VERSION: u32 = 1;
+pub const SUPPORTED_API_VERSIONS: &[u32] = &[1];
+/// Request header a client uses to pin a version; every response carries the served version.
+pub const API_VERSION_HEADER: &str = "X-API-Version";
+
+/// Version to serve for a request's `X-API-Version` header (`1` or `v1`). No header means the
+/// current version; an unknown one is an error naming the supported versions.
+pub fn negotiate_version(requested: Option<&str>) -> Result<u32, String> {
+    let Some(raw) = requested.map(str::trim).filter(|s| !s.is_empty()) else { return Ok(API_VERSION) };
+    let digits = raw.strip_prefix(['v', 'V']).unwrap_or(raw);
+    match digits.parse::<u32>() {
+        Ok(v) if SUPPORTED_API_VERSIONS.contains(&v) => Ok(v),
+        _ => {
+            let supported: Vec<String> = SUPPORTED_API_VERSIONS.iter().map(|v| v.to_string()).collect();
+            Err(format!("unsupported API version '{}'; supported: {}", raw, supported.join(", ")))
+        }
+    }
+}
+
+/// Whom an API key identifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// The main key, or anyone while no key is set
+    Full,
+    /// A role's key
+    Role(Role),
+    Refused,
+}
+
+impl Caller {
+    /// Resolve the `key` a request presented against the main key and the role keys.
+    pub fn of(key: Option<&str>, api_key: Option<&str>, roles: &[Role]) -> Caller {
+        if let Some(role) = key.and_then(|k| permissions::role_for_key(roles, k)) { return Caller::Role(role.clone()); }
+        match api_key {
+            Some(required) if key != Some(required) => Caller::Refused,
+            _ => Caller::Full,
+        }
+    }
+}
+
+/// Size limits checked before a request reaches the broker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiLimits {
+    pub max_body_bytes: usize,
+    // Bulk import bodies (`/v1/import`) have their own, larger limit
+    pub max_import_bytes: usize,
+    pub max_query_len: usize,
+    pub max_params: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    QueryTooLong { len: usize, max: usize },
+    TooManyParams { count: usize, max: usize },
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitError::QueryTooLong { len, max } => write!(f, "query is {} bytes; the limit is {} bytes", len, max),
+            LimitError::TooManyParams { count, max } => write!(f, "{} parameters given; at most {} are allowed", count, max),
+        }
+    }
+}
+
+impl ApiLimits {
+    pub fn from_settings(s: &AppSettings) -> Self {
+        ApiLimits {
+            max_body_bytes: s.api_max_body_bytes,
+            max_import_bytes: s.api_max_import_bytes,
+            max_query_len: s.api_max_query_len,
+            max_params: s.api_max_params,
+        }
+    }
+
+    pub fn check_query(&self, query: &str, params: Option<&HashMap<String, String>>) -> Result<(), LimitError> {
+        if query.len() > self.max_query_len {
+            return Err(LimitError::QueryTooLong { len: query.len(), max: self.max_query_len });
+        }
+        let count = params.map(|p| p.len()).unwrap_or(0);
+        if count > self.max_params {
+            return Err(LimitError::TooManyParams { count, max: self.max_params });
+        }
+        Ok(())
+    }
 }
 
 pub fn set_request_sender(tx: Sender<ApiRequest>) {
@@ -31,6 +371,36 @@ pub fn init_broker() -> Receiver<ApiRequest> {
     rx
 }
 
+/// Hand requests that are queued, or that in-flight handlers submit while the servers wind
+/// down, to `handle` until nothing has arrived for `quiet` or `limit` has passed. Returns the
+/// number of requests handled.
+pub fn drain_requests(
+    rx: &Receiver<ApiRequest>,
+    quiet: std::time::Duration,
+    limit: std::time::Duration,
+    mut handle: impl FnMut(ApiRequest),
+) -> usize {
+    let deadline = std::time::Instant::now() + limit;
+    let mut handled = 0;
+    loop {
+        let wait = quiet.min(deadline.saturating_duration_since(std::time::Instant::now()));
+        if wait.is_zero() { break; }
+        match rx.recv_timeout(wait) {
+            Ok(req) => {
+                handle(req);
+                handled += 1;
+            }
+            Err(_) => break,
+        }
+    }
+    handled
+}
+
+pub mod jobs;
+pub mod ipfilter;
+pub mod sync;
+pub mod telemetry;
+
 // Server lifecycle API (feature-gated). Non-API builds get no-op stubs.
 #[cfg(feature = "api")]
 pub mod server;
@@ -42,6 +412,7 @@ pub mod server {
     use crate::persistence::settings::AppSettings;
 
     pub fn start_server(_cfg: &AppSettings) -> anyhow::Result<()> { Ok(()) }
+    pub fn stop_accepting() {}
     pub fn stop_server() {}
     pub fn is_running() -> bool { false }
 }
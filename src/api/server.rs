@@ -4,12 +4,26 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}};
 use std::time::Duration;
 
-use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{middleware, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder};
 use serde::{Deserialize, Serialize};
 use tokio::runtime::Runtime;
 
-use super::{get_request_sender, ApiRequest};
+use super::ipfilter::IpRules;
+use super::jobs::{self, JobState};
+use super::sync;
+use super::telemetry::{client_request_id, REQUEST_ID_HEADER};
+use super::{get_request_sender, negotiate_version, summary_from_outcome, AdminTask, ApiLimits, ApiRequest, ApiTask, Caller, LimitError};
+use super::{API_VERSION, API_VERSION_HEADER, SUPPORTED_API_VERSIONS};
+use crate::graph_utils::changes::Since;
+use crate::graph_utils::graph::GraphDatabase;
+use crate::graph_utils::memory::Usage;
+use crate::graph_utils::permissions::Role;
 use crate::gql::query_interface::{QueryOutcome, QueryResultRow};
+use crate::persistence::columnar::{self, ColumnarFormat, ExportTemplate, Table};
+use crate::persistence::export::{ExportChunks, ExportFormat};
+use crate::persistence::import::{self, ImportPayload, ImportSummary};
+use crate::persistence::persist;
+use crate::persistence::result_export::{self, ResultFormat};
 use crate::persistence::settings::AppSettings;
 
 // Store server state for stop/restart
@@ -27,7 +41,10 @@ static REQ_COUNTER: AtomicU64 = AtomicU64::new(1);
 #[derive(Clone)]
 struct Cfg {
     api_key: Option<String>,
+    roles: Vec<Role>,
     log_dir: std::path::PathBuf,
+    limits: ApiLimits,
+    ip_rules: IpRules,
 }
 
 fn ensure_dir(p: &std::path::Path) {
@@ -45,6 +62,7 @@ fn log_line(dir: &std::path::Path, line: &str) {
     let path = dir.join(fname);
     ensure_dir(&path);
     let ts_s = now.format(&ts).unwrap_or_else(|_| String::new());
+    log::info!(target: crate::logging::API_TARGET, "{}", line);
     let msg = format!("{} | {}\n", ts_s, line);
     if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(&path) {
         let _ = f.write_all(msg.as_bytes());
@@ -57,6 +75,33 @@ fn next_request_id() -> String {
     format!("{}-{}", now, n)
 }
 
+// Request ID chosen by `request_id_mw`, kept in the request's extensions
+#[derive(Clone)]
+struct RequestId(String);
+
+fn request_id(req: &HttpRequest) -> String {
+    req.extensions().get::<RequestId>().map(|r| r.0.clone()).unwrap_or_else(next_request_id)
+}
+
+// Give every request an ID (the client's `x-request-id`, or a fresh one), run it in a span
+// carrying that ID and echo the ID in the response
+async fn request_id_mw(
+    req: actix_web::dev::ServiceRequest,
+    next: middleware::Next<impl actix_web::body::MessageBody + 'static>,
+) -> Result<actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>, actix_web::Error> {
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    use tracing::Instrument;
+    let rid = client_request_id(req.headers().get(REQUEST_ID_HEADER).and_then(|h| h.to_str().ok())).unwrap_or_else(next_request_id);
+    req.extensions_mut().insert(RequestId(rid.clone()));
+    let span = tracing::info_span!("http.request", request_id = %rid, method = %req.method(), path = %req.path(), status = tracing::field::Empty);
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    span.record("status", res.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&rid) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
+
 #[derive(Deserialize)]
 struct QueryBody {
     query: String,
@@ -75,17 +120,21 @@ struct OutcomeRowDto {
     #[serde(skip_serializing_if = "Option::is_none")] to: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] metadata: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")] info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] nodes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")] relationships: Option<Vec<String>>,
 }
 
 #[derive(Serialize)]
 struct OutcomeDto {
+    request_id: String,
     rows: Vec<OutcomeRowDto>,
     affected_nodes: usize,
     affected_relationships: usize,
     mutated: bool,
+    warnings: Vec<String>,
 }
 
-fn map_outcome(o: QueryOutcome) -> OutcomeDto {
+fn map_outcome(o: QueryOutcome, request_id: &str) -> OutcomeDto {
     let mut rows = Vec::with_capacity(o.rows.len());
     for r in o.rows {
         match r {
@@ -97,6 +146,8 @@ fn map_outcome(o: QueryOutcome) -> OutcomeDto {
                 to: None,
                 metadata: Some(metadata),
                 info: None,
+                nodes: None,
+                relationships: None,
             }),
             QueryResultRow::Relationship { id, from, to, label, metadata } => rows.push(OutcomeRowDto {
                 kind: "relationship",
@@ -106,6 +157,8 @@ fn map_outcome(o: QueryOutcome) -> OutcomeDto {
                 to: Some(to.to_string()),
                 metadata: Some(metadata),
                 info: None,
+                nodes: None,
+                relationships: None,
             }),
             QueryResultRow::Info(s) => rows.push(OutcomeRowDto {
                 kind: "info",
@@ -115,73 +168,739 @@ fn map_outcome(o: QueryOutcome) -> OutcomeDto {
                 to: None,
                 metadata: None,
                 info: Some(s),
+                nodes: None,
+                relationships: None,
+            }),
+            QueryResultRow::Path { nodes, relationships } => rows.push(OutcomeRowDto {
+                kind: "path",
+                id: String::new(),
+                label: None,
+                from: None,
+                to: None,
+                metadata: None,
+                info: None,
+                nodes: Some(nodes.iter().map(|id| id.to_string()).collect()),
+                relationships: Some(relationships.iter().map(|id| id.to_string()).collect()),
+            }),
+            // Row kinds this server predates are passed on as text
+            other => rows.push(OutcomeRowDto {
+                kind: "info",
+                id: String::new(),
+                label: None,
+                from: None,
+                to: None,
+                metadata: None,
+                info: Some(format!("{:?}", other)),
+                nodes: None,
+                relationships: None,
             }),
         }
     }
     OutcomeDto {
+        request_id: request_id.to_string(),
         rows,
         affected_nodes: o.affected_nodes,
         affected_relationships: o.affected_relationships,
         mutated: o.mutated,
+        warnings: o.warnings,
     }
 }
 
 fn unauthorized() -> HttpResponse { HttpResponse::Unauthorized().body("unauthorized") }
 
+fn stamp_version(headers: &mut actix_web::http::header::HeaderMap, version: u32) {
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    if let Ok(name) = HeaderName::try_from(API_VERSION_HEADER) {
+        headers.insert(name, HeaderValue::from(version));
+    }
+}
+
+// Refuse requests pinned to a version this server does not speak, and stamp every response
+// with the version served
+async fn version_mw(
+    req: actix_web::dev::ServiceRequest,
+    next: middleware::Next<impl actix_web::body::MessageBody + 'static>,
+) -> Result<actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>, actix_web::Error> {
+    let requested = req.headers().get(API_VERSION_HEADER).map(|h| h.to_str().unwrap_or("?"));
+    let version = match negotiate_version(requested) {
+        Ok(v) => v,
+        Err(e) => {
+            let mut res = HttpResponse::NotAcceptable().body(e);
+            stamp_version(res.headers_mut(), API_VERSION);
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    };
+    let mut res = next.call(req).await?;
+    stamp_version(res.headers_mut(), version);
+    Ok(res.map_into_left_body())
+}
+
+#[derive(Serialize)]
+struct MetaDto {
+    api_version: u32,
+    supported_versions: &'static [u32],
+    app_version: &'static str,
+    version_header: &'static str,
+    // Unversioned aliases kept for older clients; they serve the v1 shapes
+    legacy_routes: [&'static str; 3],
+}
+
+// Version discovery; open without an API key so clients can check compatibility first
+async fn handle_meta() -> impl Responder {
+    HttpResponse::Ok().json(MetaDto {
+        api_version: API_VERSION,
+        supported_versions: SUPPORTED_API_VERSIONS,
+        app_version: env!("CARGO_PKG_VERSION"),
+        version_header: API_VERSION_HEADER,
+        legacy_routes: ["/api/query", "/api/info", "/api/repl"],
+    })
+}
+
+// Refuse clients outside the configured allow list (or on the deny list) before any handler runs
+async fn ip_filter_mw(
+    req: actix_web::dev::ServiceRequest,
+    next: middleware::Next<impl actix_web::body::MessageBody + 'static>,
+) -> Result<actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>, actix_web::Error> {
+    let cfg = req.app_data::<web::Data<Cfg>>().cloned();
+    if let (Some(cfg), Some(peer)) = (cfg, req.peer_addr())
+        && !cfg.ip_rules.allows(peer.ip())
+    {
+        log_line(&cfg.log_dir, &format!("HTTP refused {} by IP filter", peer));
+        return Ok(req.into_response(HttpResponse::Forbidden().body("client address not allowed")).map_into_right_body());
+    }
+    next.call(req).await.map(|res| res.map_into_left_body())
+}
+
+fn limit_exceeded(e: LimitError) -> HttpResponse {
+    match e {
+        LimitError::QueryTooLong { .. } => HttpResponse::PayloadTooLarge().body(e.to_string()),
+        LimitError::TooManyParams { .. } => HttpResponse::BadRequest().body(e.to_string()),
+    }
+}
+
+// JSON bodies over the size limit get a 413 that names the limit; malformed ones a 400
+fn json_error(limit: usize) -> impl Fn(actix_web::error::JsonPayloadError, &HttpRequest) -> actix_web::Error + Clone {
+    move |err, _req| {
+        use actix_web::error::JsonPayloadError;
+        let resp = match &err {
+            JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                HttpResponse::PayloadTooLarge().body(format!("request body exceeds the {}-byte limit", limit))
+            }
+            other => HttpResponse::BadRequest().body(format!("invalid JSON body: {}", other)),
+        };
+        actix_web::error::InternalError::from_response(err, resp).into()
+    }
+}
+
+fn caller(req: &HttpRequest, cfg: &Cfg) -> Caller {
+    let key = req.headers().get("X-API-Key").and_then(|h| h.to_str().ok());
+    Caller::of(key, cfg.api_key.as_deref(), &cfg.roles)
+}
+
 fn check_api_key(req: &HttpRequest, cfg: &Cfg) -> bool {
-    match &cfg.api_key {
-        None => true,
-        Some(required) => match req.headers().get("X-API-Key") {
-            Some(h) => h.to_str().map(|v| v == required).unwrap_or(false),
-            None => false,
-        },
+    caller(req, cfg) != Caller::Refused
+}
+
+// Role whose limits apply to the request's writes
+fn role_of(req: &HttpRequest, cfg: &Cfg) -> Option<Role> {
+    match caller(req, cfg) {
+        Caller::Role(role) => Some(role),
+        _ => None,
     }
 }
 
 async fn handle_query(cfg: web::Data<Cfg>, req: HttpRequest, body: web::Json<QueryBody>) -> impl Responder {
     if !check_api_key(&req, &cfg) { return unauthorized(); }
-    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    match run_query(&cfg, &req, &body) {
+        Ok((out, rid)) => HttpResponse::Ok().json(map_outcome(out, &rid)),
+        Err(resp) => resp,
+    }
+}
+
+// Run the body's query through the broker and wait for its outcome; errors are the response
+// to send. Returns the request ID with the outcome.
+fn run_query(cfg: &Cfg, req: &HttpRequest, body: &QueryBody) -> Result<(QueryOutcome, String), HttpResponse> {
+    if let Err(e) = cfg.limits.check_query(&body.query, body.params.as_ref()) { return Err(limit_exceeded(e)); }
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return Err(HttpResponse::ServiceUnavailable().body("broker not ready")) };
     let (tx, rx) = std::sync::mpsc::channel();
-    let rid = next_request_id();
+    let rid = request_id(req);
     let api_req = ApiRequest {
         request_id: rid.clone(),
         query: body.query.clone(),
         params: body.params.clone(),
         log: body.log.unwrap_or(true),
         respond_to: tx,
+        task: None,
+        role: role_of(req, cfg),
     };
     let peer = req.peer_addr().map(|a| a.to_string()).unwrap_or_else(|| "unknown".into());
-    log_line(&cfg.log_dir, &format!("RID={} HTTP /api/query from {} qlen={} params={} log={}", rid, peer, api_req.query.len(), api_req.params.as_ref().map(|m| m.len()).unwrap_or(0), api_req.log));
+    log_line(&cfg.log_dir, &format!("RID={} HTTP {} from {} qlen={} params={} log={}", rid, req.path(), peer, api_req.query.len(), api_req.params.as_ref().map(|m| m.len()).unwrap_or(0), api_req.log));
     let t0 = std::time::Instant::now();
     if sender.send(api_req).is_err() {
         log_line(&cfg.log_dir, &format!("RID={} enqueue failed", rid));
-        return HttpResponse::ServiceUnavailable().body("failed to enqueue");
+        return Err(HttpResponse::ServiceUnavailable().body("failed to enqueue"));
     }
     match rx.recv_timeout(Duration::from_secs(30)) {
         Ok(Ok(out)) => {
             let dt = t0.elapsed();
             log_line(&cfg.log_dir, &format!("RID={} HTTP OK nodes={} rels={} mutated={} dt_ms={}", rid, out.affected_nodes, out.affected_relationships, out.mutated, dt.as_millis()));
-            HttpResponse::Ok().json(map_outcome(out))
+            Ok((out, rid))
         }
         Ok(Err(e)) => {
             let dt = t0.elapsed();
             log_line(&cfg.log_dir, &format!("RID={} HTTP ERR {} dt_ms={}", rid, e, dt.as_millis()));
-            HttpResponse::BadRequest().body(e)
+            Err(HttpResponse::BadRequest().body(e))
         }
         Err(_) => {
             let dt = t0.elapsed();
             log_line(&cfg.log_dir, &format!("RID={} HTTP TIMEOUT dt_ms={}", rid, dt.as_millis()));
-            HttpResponse::GatewayTimeout().body("query timeout")
+            Err(HttpResponse::GatewayTimeout().body("query timeout"))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryExportParams {
+    // "json" (default) or "csv"
+    #[serde(default)]
+    format: Option<String>,
+}
+
+// Run a query and send exactly the rows it returned as a JSON or CSV file
+async fn handle_query_export(cfg: web::Data<Cfg>, req: HttpRequest, params: web::Query<QueryExportParams>, body: web::Json<QueryBody>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let format = match params.format.as_deref().map_or(Some(ResultFormat::Json), ResultFormat::parse) {
+        Some(f) => f,
+        None => return HttpResponse::BadRequest().body(format!("unknown format '{}' (expected json or csv)", params.format.as_deref().unwrap_or_default())),
+    };
+    let (out, rid) = match run_query(&cfg, &req, &body) {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+    let mut buf = Vec::new();
+    if let Err(e) = result_export::write(&out.rows, format, &mut buf) { return HttpResponse::InternalServerError().body(e.to_string()); }
+    log_line(&cfg.log_dir, &format!("RID={} HTTP query export rows={} bytes={}", rid, out.rows.len(), buf.len()));
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"query_results.{}\"", format.extension())))
+        .body(buf)
+}
+
+// Graph metadata and counts as a flat JSON object (runs CALL db.info() through the broker)
+async fn handle_info(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    let (tx, rx) = std::sync::mpsc::channel();
+    let rid = request_id(&req);
+    let api_req = ApiRequest { request_id: rid.clone(), query: "CALL db.info()".into(), params: None, log: false, respond_to: tx, task: None, role: None };
+    log_line(&cfg.log_dir, &format!("RID={} HTTP {}", rid, req.path()));
+    if sender.send(api_req).is_err() { return HttpResponse::ServiceUnavailable().body("failed to enqueue"); }
+    match rx.recv_timeout(Duration::from_secs(30)) {
+        Ok(Ok(out)) => {
+            let info: serde_json::Map<String, serde_json::Value> = out
+                .rows
+                .into_iter()
+                .filter_map(|r| match r { QueryResultRow::Info(s) => s.split_once('=').map(|(k, v)| (k.to_string(), v.into())), _ => None })
+                .collect();
+            HttpResponse::Ok().json(info)
+        }
+        Ok(Err(e)) => HttpResponse::InternalServerError().body(e),
+        Err(_) => HttpResponse::GatewayTimeout().body("query timeout"),
+    }
+}
+
+// Labels, relationship types and metadata keys in use, each with the number of elements
+// using it. Read from the catalog the graph keeps current, so this does not scan the graph.
+async fn handle_catalog(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    match fetch(&cfg, &req, |reply| ApiTask::Catalog { reply }) {
+        Ok(catalog) => HttpResponse::Ok().json(catalog),
+        Err(resp) => resp,
+    }
+}
+
+// One part of the catalog as a sorted list of names: labels, relationship-types or property-keys
+async fn handle_catalog_part(cfg: web::Data<Cfg>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let part = path.into_inner();
+    if !matches!(part.as_str(), "labels" | "relationship-types" | "property-keys") {
+        return HttpResponse::NotFound().body(format!("unknown catalog '{}' (expected labels, relationship-types or property-keys)", part));
+    }
+    let catalog = match fetch(&cfg, &req, |reply| ApiTask::Catalog { reply }) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let names: Vec<&str> = match part.as_str() {
+        "labels" => catalog.node_labels().collect(),
+        "relationship-types" => catalog.relationship_labels().collect(),
+        _ => {
+            let keys: std::collections::BTreeSet<&str> = catalog.node_keys().chain(catalog.relationship_keys()).collect();
+            keys.into_iter().collect()
+        }
+    };
+    HttpResponse::Ok().json(names)
+}
+
+// Labels, types and the label pairs each relationship type connects, with counts and keys
+async fn handle_schema(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    match fetch(&cfg, &req, |reply| ApiTask::Schema { reply }) {
+        Ok(schema) => HttpResponse::Ok().json(schema),
+        Err(resp) => resp,
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangesParams {
+    // Sequence number or ISO-8601 time; everything when absent
+    #[serde(default)]
+    since: Option<String>,
+}
+
+// Logged writes after a sequence number or since a time, oldest first
+async fn handle_changes(cfg: web::Data<Cfg>, req: HttpRequest, query: web::Query<ChangesParams>) -> impl Responder {
+    let since = match Since::parse(query.since.as_deref().unwrap_or("")) {
+        Ok(s) => s,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    match fetch(&cfg, &req, |reply| ApiTask::Changes { since, reply }) {
+        Ok(Ok(events)) => HttpResponse::Ok().json(events),
+        Ok(Err(e)) => HttpResponse::InternalServerError().body(e),
+        Err(resp) => resp,
+    }
+}
+
+// Hand a task that replies with a value to the graph owner and wait for the value
+fn fetch<T>(cfg: &Cfg, req: &HttpRequest, task: impl FnOnce(std::sync::mpsc::Sender<T>) -> ApiTask) -> Result<T, HttpResponse> {
+    if !check_api_key(req, cfg) { return Err(unauthorized()); }
+    let sender = get_request_sender().cloned().ok_or_else(|| HttpResponse::ServiceUnavailable().body("broker not ready"))?;
+    let rid = request_id(req);
+    log_line(&cfg.log_dir, &format!("RID={} HTTP {}", rid, req.path()));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+    let api_req = ApiRequest { request_id: rid, query: String::new(), params: None, log: false, respond_to: tx, task: Some(task(reply_tx)), role: role_of(req, cfg) };
+    if sender.send(api_req).is_err() { return Err(HttpResponse::ServiceUnavailable().body("failed to enqueue")); }
+    let value = reply_rx.recv_timeout(Duration::from_secs(30)).map_err(|_| HttpResponse::GatewayTimeout().body("request timeout"))?;
+    let _ = rx.recv_timeout(Duration::from_secs(1));
+    Ok(value)
+}
+
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+struct ImportParams {
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    batch_size: Option<usize>,
+    // Report what the import would change without changing the graph
+    #[serde(default)]
+    dry_run: bool,
+    // Metadata key matching incoming nodes to existing ones, and what to do on a match
+    #[serde(default)]
+    match_key: Option<String>,
+    #[serde(default)]
+    on_match: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ImportDto {
+    mode: &'static str,
+    batches: usize,
+    nodes_added: usize,
+    nodes_updated: usize,
+    rels_added: usize,
+    rels_updated: usize,
+    rels_skipped: usize,
+    kept: usize,
+}
+
+// A dry run's counts, plus the elements the import would leave out
+#[derive(Serialize)]
+struct DryRunDto {
+    #[serde(flatten)]
+    import: ImportDto,
+    dry_run: bool,
+    problems: Vec<String>,
+}
+
+impl ImportDto {
+    fn new(mode: &'static str, batches: usize, s: ImportSummary) -> Self {
+        ImportDto {
+            mode,
+            batches,
+            nodes_added: s.nodes_added,
+            nodes_updated: s.nodes_updated,
+            rels_added: s.rels_added,
+            rels_updated: s.rels_updated,
+            rels_skipped: s.rels_skipped,
+            kept: s.kept,
+        }
+    }
+}
+
+// Bulk import of a graph JSON export or NDJSON body. The body is parsed here and handed to
+// the GUI thread in batches, so the UI keeps drawing between them.
+async fn handle_import(cfg: web::Data<Cfg>, req: HttpRequest, query: web::Query<ImportParams>, body: web::Bytes) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let replace = match query.mode.as_deref().unwrap_or("merge") {
+        "merge" => false,
+        "replace" => true,
+        other => return HttpResponse::BadRequest().body(format!("unknown mode '{}' (expected merge or replace)", other)),
+    };
+    let mode = if replace { "replace" } else { "merge" };
+    let on_match = match query.on_match.as_deref().map(|s| import::OnMatch::parse(s).ok_or(s)).transpose() {
+        Ok(m) => m.unwrap_or_default(),
+        Err(other) => return HttpResponse::BadRequest().body(format!("unknown on_match '{}' (expected skip, update or create)", other)),
+    };
+    let matching = import::ImportMatch::by_key(query.match_key.clone().unwrap_or_default(), on_match);
+    let batch_size = query.batch_size.unwrap_or(IMPORT_BATCH_SIZE);
+    let text = match std::str::from_utf8(&body) { Ok(t) => t, Err(_) => return HttpResponse::BadRequest().body("body is not UTF-8") };
+    if query.dry_run { return import_dry_run(&cfg, &req, ImportPayload::parse_checked(text), replace, &matching, batch_size).await; }
+    let payload = match ImportPayload::parse(text) { Ok(p) => p, Err(e) => return HttpResponse::BadRequest().body(e.to_string()) };
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    let role = role_of(&req, &cfg);
+    let rid = request_id(&req);
+    let peer = req.peer_addr().map(|a| a.to_string()).unwrap_or_else(|| "unknown".into());
+    log_line(&cfg.log_dir, &format!("RID={} HTTP /v1/import from {} mode={} elements={}", rid, peer, mode, payload.len()));
+    let t0 = std::time::Instant::now();
+    // Keyed matching remembers which node each incoming one matched, so the body stays whole
+    let mut batches = if matching.is_keyed() { vec![payload] } else { payload.into_batches(batch_size) };
+    // A replace with an empty body still has to clear the graph
+    if replace && batches.is_empty() { batches.push(ImportPayload::default()); }
+    let mut total = ImportSummary::default();
+    let mut done = 0usize;
+    for batch in batches {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let task = ApiTask::Import { batch, replace: replace && done == 0, matching: matching.clone() };
+        let api_req = ApiRequest { request_id: format!("{}-b{}", rid, done + 1), query: String::new(), params: None, log: false, respond_to: tx, task: Some(task), role: role.clone() };
+        if sender.send(api_req).is_err() { return HttpResponse::ServiceUnavailable().body("failed to enqueue"); }
+        match rx.recv_timeout(Duration::from_secs(30)) {
+            Ok(Ok(out)) => total.add(&summary_from_outcome(&out)),
+            Ok(Err(e)) => return HttpResponse::InternalServerError().body(e),
+            Err(_) => {
+                log_line(&cfg.log_dir, &format!("RID={} HTTP import TIMEOUT after {} batches", rid, done));
+                return HttpResponse::GatewayTimeout().json(ImportDto::new(mode, done, total));
+            }
         }
+        done += 1;
+    }
+    log_line(&cfg.log_dir, &format!("RID={} HTTP import OK batches={} {} dt_ms={}", rid, done, total.describe(), t0.elapsed().as_millis()));
+    HttpResponse::Ok().json(ImportDto::new(mode, done, total))
+}
+
+// `/v1/import?dry_run=true`: merge into a snapshot of the graph (an empty one for replace) and
+// report the counts and problems; elements that do not parse are listed rather than rejected
+async fn import_dry_run(cfg: &Cfg, req: &HttpRequest, payload: ImportPayload, replace: bool, matching: &import::ImportMatch, batch_size: usize) -> HttpResponse {
+    let mode = if replace { "replace" } else { "merge" };
+    let rid = request_id(req);
+    log_line(&cfg.log_dir, &format!("RID={} HTTP /v1/import dry run mode={} elements={}", rid, mode, payload.len()));
+    let mut db = if replace {
+        GraphDatabase::new()
+    } else {
+        let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (snap_tx, snap_rx) = std::sync::mpsc::channel();
+        let task = ApiTask::Snapshot { labels: None, reply: snap_tx };
+        let api_req = ApiRequest { request_id: rid.clone(), query: String::new(), params: None, log: false, respond_to: tx, task: Some(task), role: None };
+        if sender.send(api_req).is_err() { return HttpResponse::ServiceUnavailable().body("failed to enqueue"); }
+        let Ok(db) = snap_rx.recv_timeout(Duration::from_secs(30)) else { return HttpResponse::GatewayTimeout().body("import timeout") };
+        let _ = rx.recv_timeout(Duration::from_secs(1));
+        db
+    };
+    let batches = if matching.is_keyed() { 1 } else { payload.len().div_ceil(batch_size.max(1)) };
+    let matching = matching.clone();
+    let report = match web::block(move || import::merge_with_report(&mut db, payload, &matching)).await {
+        Ok(report) => report,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    log_line(&cfg.log_dir, &format!("RID={} HTTP import dry run {}", rid, report.describe()));
+    HttpResponse::Ok().json(DryRunDto { import: ImportDto::new(mode, batches, report.summary), dry_run: true, problems: report.problems })
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    #[serde(default)]
+    format: Option<String>,
+    // Comma-separated node labels; relationships are kept when both ends are exported
+    #[serde(default)]
+    labels: Option<String>,
+    // CSV, Parquet and Arrow: "nodes" (default) or "relationships"
+    #[serde(default)]
+    part: Option<String>,
+}
+
+// Text formats are streamed; Parquet and Arrow tables are written whole, then sent
+enum ExportKind {
+    Stream(ExportFormat),
+    Table(ColumnarFormat, Table),
+}
+
+// Response body that renders the export a chunk at a time as the client reads it
+struct ExportBody(ExportChunks);
+
+impl actix_web::body::MessageBody for ExportBody {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> actix_web::body::BodySize { actix_web::body::BodySize::Stream }
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Result<web::Bytes, Self::Error>>> {
+        std::task::Poll::Ready(self.get_mut().0.next().map(|c| Ok(web::Bytes::from(c))))
+    }
+}
+
+// Stream the graph (or a label-filtered part of it). The GUI thread only copies the graph;
+// rendering happens here while the response is written.
+async fn handle_export(cfg: web::Data<Cfg>, req: HttpRequest, query: web::Query<ExportParams>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let kind = match (query.format.as_deref().unwrap_or("json"), query.part.as_deref().unwrap_or("nodes")) {
+        ("json", _) => ExportKind::Stream(ExportFormat::Json),
+        ("graphml", _) => ExportKind::Stream(ExportFormat::GraphMl),
+        ("csv", "nodes") => ExportKind::Stream(ExportFormat::CsvNodes),
+        ("csv", "relationships") => ExportKind::Stream(ExportFormat::CsvRelationships),
+        ("parquet" | "arrow", _) if !cfg!(feature = "parquet") => return HttpResponse::NotImplemented().body("this build has no Parquet or Arrow support (feature `parquet`)"),
+        ("parquet", "nodes") => ExportKind::Table(ColumnarFormat::Parquet, Table::Nodes),
+        ("parquet", "relationships") => ExportKind::Table(ColumnarFormat::Parquet, Table::Relationships),
+        ("arrow", "nodes") => ExportKind::Table(ColumnarFormat::ArrowIpc, Table::Nodes),
+        ("arrow", "relationships") => ExportKind::Table(ColumnarFormat::ArrowIpc, Table::Relationships),
+        ("csv" | "parquet" | "arrow", other) => return HttpResponse::BadRequest().body(format!("unknown part '{}' (expected nodes or relationships)", other)),
+        (other, _) => return HttpResponse::BadRequest().body(format!("unknown format '{}' (expected json, csv, graphml, parquet or arrow)", other)),
+    };
+    let labels: Option<Vec<String>> = query
+        .labels
+        .as_deref()
+        .map(|s| s.split(',').map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect());
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    let rid = request_id(&req);
+    let described = match &kind {
+        ExportKind::Stream(format) => format!("{:?}", format),
+        ExportKind::Table(format, table) => format!("{:?}/{:?}", format, table),
+    };
+    log_line(&cfg.log_dir, &format!("RID={} HTTP /v1/export format={} labels={:?}", rid, described, labels));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (snap_tx, snap_rx) = std::sync::mpsc::channel();
+    let task = ApiTask::Snapshot { labels, reply: snap_tx };
+    let api_req = ApiRequest { request_id: rid.clone(), query: String::new(), params: None, log: false, respond_to: tx, task: Some(task), role: None };
+    if sender.send(api_req).is_err() { return HttpResponse::ServiceUnavailable().body("failed to enqueue"); }
+    let db = match snap_rx.recv_timeout(Duration::from_secs(30)) {
+        Ok(db) => db,
+        Err(_) => return HttpResponse::GatewayTimeout().body("export timeout"),
+    };
+    let _ = rx.recv_timeout(Duration::from_secs(1));
+    log_line(&cfg.log_dir, &format!("RID={} HTTP export nodes={} rels={}", rid, db.nodes.len(), db.relationships.len()));
+    let format = match kind {
+        ExportKind::Stream(format) => format,
+        ExportKind::Table(format, table) => {
+            let written = web::block(move || {
+                let mut buf = Vec::new();
+                columnar::write(&db, table, &ExportTemplate::default(), format, &mut buf, &mut |_| Ok(()))?;
+                Ok::<_, anyhow::Error>(buf)
+            })
+            .await;
+            let name = format!("graph_{}.{}", if table == Table::Nodes { "nodes" } else { "relationships" }, format.extension());
+            return match written {
+                Ok(Ok(bytes)) => HttpResponse::Ok()
+                    .content_type(format.content_type())
+                    .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", name)))
+                    .body(bytes),
+                Ok(Err(e)) => HttpResponse::InternalServerError().body(e.to_string()),
+                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+            };
+        }
+    };
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", format.file_name())))
+        .body(ExportBody(ExportChunks::new(db, format)))
+}
+
+// Admin endpoints write state files or replace the whole graph, so they stay disabled
+// until an API key is configured
+fn check_admin(req: &HttpRequest, cfg: &Cfg) -> Option<HttpResponse> {
+    if cfg.api_key.is_none() { return Some(HttpResponse::Forbidden().body("admin endpoints require an API key")); }
+    match caller(req, cfg) {
+        Caller::Full => None,
+        Caller::Role(_) => Some(HttpResponse::Forbidden().body("admin endpoints require the main API key")),
+        Caller::Refused => Some(unauthorized()),
+    }
+}
+
+// Hand a save/load to whoever owns the state file (GUI or background loop) and report the path
+fn run_admin(cfg: &Cfg, req: &HttpRequest, task: AdminTask) -> HttpResponse {
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    let (tx, rx) = std::sync::mpsc::channel();
+    let rid = request_id(req);
+    log_line(&cfg.log_dir, &format!("RID={} HTTP admin {:?}", rid, task));
+    let api_req = ApiRequest { request_id: rid.clone(), query: String::new(), params: None, log: false, respond_to: tx, task: Some(ApiTask::Admin(task)), role: None };
+    if sender.send(api_req).is_err() { return HttpResponse::ServiceUnavailable().body("failed to enqueue"); }
+    match rx.recv_timeout(Duration::from_secs(60)) {
+        Ok(Ok(out)) => {
+            let path = out.rows.iter().find_map(|r| match r { QueryResultRow::Info(s) => s.strip_prefix("path=").map(str::to_string), _ => None });
+            log_line(&cfg.log_dir, &format!("RID={} HTTP admin OK {}", rid, path.as_deref().unwrap_or("")));
+            HttpResponse::Ok().json(serde_json::json!({ "path": path }))
+        }
+        Ok(Err(e)) => {
+            log_line(&cfg.log_dir, &format!("RID={} HTTP admin ERR {}", rid, e));
+            HttpResponse::InternalServerError().body(e)
+        }
+        Err(_) => HttpResponse::GatewayTimeout().body("admin request timeout"),
+    }
+}
+
+async fn handle_admin_save(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    if let Some(denied) = check_admin(&req, &cfg) { return denied; }
+    run_admin(&cfg, &req, AdminTask::Save)
+}
+
+async fn handle_admin_save_version(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    if let Some(denied) = check_admin(&req, &cfg) { return denied; }
+    run_admin(&cfg, &req, AdminTask::SaveVersion)
+}
+
+#[derive(Serialize)]
+struct MemoryDto {
+    total: usize,
+    spare: usize,
+    parts: Vec<Usage>,
+    // Only after a compaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reclaimed: Option<usize>,
+}
+
+fn memory_response(cfg: &Cfg, req: &HttpRequest, compact: bool) -> HttpResponse {
+    if let Some(denied) = check_admin(req, cfg) { return denied; }
+    match fetch(cfg, req, |reply| ApiTask::Memory { compact, reply }) {
+        Ok((report, reclaimed)) => HttpResponse::Ok().json(MemoryDto {
+            total: report.total(),
+            spare: report.spare(),
+            parts: report.parts,
+            reclaimed: compact.then_some(reclaimed),
+        }),
+        Err(resp) => resp,
+    }
+}
+
+// Approximate bytes held by nodes, relationships and indexes
+async fn handle_admin_memory(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    memory_response(&cfg, &req, false)
+}
+
+// Shrink over-allocated maps and strings; answers with the usage afterwards
+async fn handle_admin_compact(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    memory_response(&cfg, &req, true)
+}
+
+#[derive(Serialize)]
+struct VersionDto {
+    name: String,
+    bytes: u64,
+}
+
+// Saved versions, newest first
+async fn handle_admin_versions(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    if let Some(denied) = check_admin(&req, &cfg) { return denied; }
+    match persist::list_versions() {
+        Ok(list) => {
+            let versions: Vec<VersionDto> = list
+                .iter()
+                .filter_map(|p| {
+                    let name = p.file_name()?.to_str()?.to_string();
+                    Some(VersionDto { name, bytes: std::fs::metadata(p).map(|m| m.len()).unwrap_or(0) })
+                })
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({ "versions": versions }))
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+async fn handle_admin_load_version(cfg: web::Data<Cfg>, req: HttpRequest, name: web::Path<String>) -> impl Responder {
+    if let Some(denied) = check_admin(&req, &cfg) { return denied; }
+    // Only names from the version listing are accepted, never arbitrary paths
+    let found = persist::list_versions()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| p.file_name().and_then(|s| s.to_str()) == Some(name.as_str()));
+    match found {
+        Some(path) => run_admin(&cfg, &req, AdminTask::LoadVersion(path)),
+        None => HttpResponse::NotFound().body(format!("no saved version named '{}'", name)),
+    }
+}
+
+// Submit a query as a job: answers 202 with the job id at once, and a worker thread waits on
+// the broker for as long as the query takes
+async fn handle_job_submit(cfg: web::Data<Cfg>, req: HttpRequest, body: web::Json<QueryBody>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    if let Err(e) = cfg.limits.check_query(&body.query, body.params.as_ref()) { return limit_exceeded(e); }
+    let sender = match get_request_sender() { Some(s) => s.clone(), None => return HttpResponse::ServiceUnavailable().body("broker not ready") };
+    let id = jobs::create();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let body = body.into_inner();
+    let api_req = ApiRequest { request_id: id.clone(), query: body.query, params: body.params, log: body.log.unwrap_or(true), respond_to: tx, task: None, role: role_of(&req, &cfg) };
+    let peer = req.peer_addr().map(|a| a.to_string()).unwrap_or_else(|| "unknown".into());
+    log_line(&cfg.log_dir, &format!("RID={} HTTP /v1/jobs from {} qlen={}", id, peer, api_req.query.len()));
+    if sender.send(api_req).is_err() {
+        jobs::finish(&id, Err("failed to enqueue".into()));
+        return HttpResponse::ServiceUnavailable().body("failed to enqueue");
+    }
+    let log_dir = cfg.log_dir.clone();
+    let job_id = id.clone();
+    std::thread::spawn(move || {
+        let res = rx.recv().unwrap_or_else(|_| Err("request dropped by the broker".into()));
+        log_line(&log_dir, &format!("RID={} job finished ok={}", job_id, res.is_ok()));
+        jobs::finish(&job_id, res);
+    });
+    match jobs::status(&id) {
+        Some(status) => HttpResponse::Accepted().json(status),
+        None => HttpResponse::InternalServerError().body("job vanished"),
+    }
+}
+
+async fn handle_job_list(cfg: web::Data<Cfg>, req: HttpRequest) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    HttpResponse::Ok().json(serde_json::json!({ "jobs": jobs::list() }))
+}
+
+async fn handle_job_status(cfg: web::Data<Cfg>, req: HttpRequest, id: web::Path<String>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    match jobs::status(&id) {
+        Some(status) => HttpResponse::Ok().json(status),
+        None => HttpResponse::NotFound().body(format!("no job '{}'", id)),
+    }
+}
+
+// Results in the same shape as /api/query once the job has succeeded
+async fn handle_job_result(cfg: web::Data<Cfg>, req: HttpRequest, id: web::Path<String>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let Some(status) = jobs::status(&id) else { return HttpResponse::NotFound().body(format!("no job '{}'", id)) };
+    match (status.state, jobs::result(&id)) {
+        (JobState::Succeeded, Some(Ok(out))) => HttpResponse::Ok().json(map_outcome(out, &id)),
+        (JobState::Failed, Some(Err(e))) => HttpResponse::BadRequest().body(e),
+        (JobState::Cancelled, _) => HttpResponse::Gone().body("job was cancelled"),
+        // Still queued or running
+        _ => HttpResponse::Conflict().json(status),
+    }
+}
+
+// Cancel an active job; a finished job is forgotten instead
+async fn handle_job_delete(cfg: web::Data<Cfg>, req: HttpRequest, id: web::Path<String>) -> impl Responder {
+    if !check_api_key(&req, &cfg) { return unauthorized(); }
+    let Some(status) = jobs::status(&id) else { return HttpResponse::NotFound().body(format!("no job '{}'", id)) };
+    if status.state.is_finished() {
+        jobs::remove(&id);
+        return HttpResponse::NoContent().finish();
+    }
+    jobs::cancel(&id);
+    log_line(&cfg.log_dir, &format!("RID={} job cancelled", id));
+    match jobs::status(&id) {
+        Some(status) => HttpResponse::Ok().json(status),
+        None => HttpResponse::NoContent().finish(),
     }
 }
 
 // Simple WebSocket REPL: line-per-query
 use actix_web_actors::ws;
 
-struct ReplWs { cfg: Cfg }
+struct ReplWs { cfg: Cfg, role: Option<Role> }
 
-impl ReplWs { fn new(cfg: Cfg) -> Self { Self { cfg } } }
+impl ReplWs { fn new(cfg: Cfg, role: Option<Role>) -> Self { Self { cfg, role } } }
 
 impl actix::Actor for ReplWs {
     type Context = ws::WebsocketContext<Self>;
@@ -199,15 +918,16 @@ impl actix::StreamHandler<Result<ws::Message, ws::ProtocolError>> for ReplWs {
                 let sender = match get_request_sender() { Some(s) => s.clone(), None => { ctx.text("broker not ready"); return; } };
                 let q = text.trim().to_string();
                 if q.is_empty() { return; }
+                if let Err(e) = self.cfg.limits.check_query(&q, None) { ctx.text(format!("error: {}", e)); return; }
                 let rid = next_request_id();
                 log_line(&self.cfg.log_dir, &format!("RID={} WS query qlen={}", rid, q.len()));
                 let (tx, rx) = std::sync::mpsc::channel();
-                let req = ApiRequest { request_id: rid.clone(), query: q, params: None, log: true, respond_to: tx };
+                let req = ApiRequest { request_id: rid.clone(), query: q, params: None, log: true, respond_to: tx, task: None, role: self.role.clone() };
                 let t0 = std::time::Instant::now();
                 if sender.send(req).is_err() { ctx.text("enqueue failed"); return; }
                 match rx.recv_timeout(Duration::from_secs(60)) {
                     Ok(Ok(out)) => {
-                        let dto = map_outcome(out);
+                        let dto = map_outcome(out, &rid);
                         let s = serde_json::to_string_pretty(&dto).unwrap_or_else(|_| "{}".into());
                         ctx.text(s);
                         let dt = t0.elapsed();
@@ -226,12 +946,75 @@ impl actix::StreamHandler<Result<ws::Message, ws::ProtocolError>> for ReplWs {
 
 async fn ws_handler(cfg: web::Data<Cfg>, req: HttpRequest, stream: web::Payload) -> actix_web::Result<HttpResponse> {
     if !check_api_key(&req, &cfg) { return Ok(unauthorized()); }
-    ws::start(ReplWs::new(cfg.get_ref().clone()), &req, stream)
+    ws::start(ReplWs::new(cfg.get_ref().clone(), role_of(&req, &cfg)), &req, stream)
+}
+
+// Peer sync connection: text messages are handed to the graph owner through `sync`, and
+// whatever it queues for this link is written back
+struct SyncWs { link: usize, outbox: std::sync::mpsc::Receiver<String>, cfg: Cfg }
+
+impl actix::Actor for SyncWs {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        use actix::AsyncContext;
+        log_line(&self.cfg.log_dir, &format!("WS sync peer {} connected", self.link));
+        ctx.run_interval(Duration::from_millis(50), |act, ctx| loop {
+            match act.outbox.try_recv() {
+                Ok(text) => ctx.text(text),
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    ctx.close(None);
+                    actix::ActorContext::stop(ctx);
+                    break;
+                }
+            }
+        });
+    }
+
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        sync::leave(self.link);
+        log_line(&self.cfg.log_dir, &format!("WS sync peer {} closed", self.link));
+    }
+}
+
+impl actix::StreamHandler<Result<ws::Message, ws::ProtocolError>> for SyncWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Text(text)) => sync::deliver(self.link, text.to_string()),
+            Ok(ws::Message::Ping(b)) => ctx.pong(&b),
+            Ok(ws::Message::Close(_)) | Err(_) => { ctx.close(None); actix::ActorContext::stop(ctx); }
+            _ => {}
+        }
+    }
 }
 
+async fn sync_handler(cfg: web::Data<Cfg>, req: HttpRequest, stream: web::Payload) -> actix_web::Result<HttpResponse> {
+    // Peers write past the query executor, so a role key cannot sync
+    match caller(&req, &cfg) {
+        Caller::Full => {}
+        Caller::Role(_) => return Ok(HttpResponse::Forbidden().body("peer sync requires the main API key")),
+        Caller::Refused => return Ok(unauthorized()),
+    }
+    let peer = req.peer_addr().map(|a| a.to_string()).unwrap_or_else(|| "unknown".into());
+    let Some((link, outbox)) = sync::join(peer) else { return Ok(HttpResponse::Forbidden().body("peer sync is off")) };
+    let started = ws::WsResponseBuilder::new(SyncWs { link, outbox, cfg: cfg.get_ref().clone() }, &req, stream)
+        .frame_size(SYNC_FRAME_BYTES)
+        .start();
+    if started.is_err() { sync::leave(link); }
+    started
+}
+
+// A peer's snapshot arrives in chunks of a few hundred operations; this leaves ample room
+const SYNC_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
 pub fn start_server(cfg: &AppSettings) -> anyhow::Result<()> {
     let bind = cfg.api_endpoint();
     let api_key = cfg.api_key.clone();
+    let roles = cfg.api_roles.clone();
+    let compress = cfg.api_compression;
+    let limits = ApiLimits::from_settings(cfg);
+    let ip_rules = IpRules::parse_lenient(&cfg.api_ip_filter);
     let log_dir = cfg.api_log_dir();
     stop_server();
 
@@ -242,24 +1025,59 @@ pub fn start_server(cfg: &AppSettings) -> anyhow::Result<()> {
             .build() {
                 Ok(r) => r,
                 Err(e) => {
-                    eprintln!("[Graph-Loom] Failed to create tokio runtime for API: {}", e);
+                    log::error!("Failed to create tokio runtime for API: {}", e);
                     return;
                 }
             };
         
         rt.block_on(async move {
-            let cfg_data = Cfg { api_key, log_dir: log_dir.clone() };
+            let cfg_data = Cfg { api_key, roles, log_dir: log_dir.clone(), limits, ip_rules };
             log_line(&cfg_data.log_dir, &format!("Server starting on {}", bind));
             let server = match HttpServer::new(move || {
                 App::new()
+                    // Negotiated from Accept-Encoding; WebSocket upgrades are left alone
+                    .wrap(middleware::Condition::new(compress, middleware::Compress::default()))
+                    .wrap(middleware::from_fn(version_mw))
+                    .wrap(middleware::from_fn(ip_filter_mw))
+                    .wrap(middleware::from_fn(request_id_mw))
                     .app_data(web::Data::new(cfg_data.clone()))
+                    .app_data(web::JsonConfig::default().limit(limits.max_body_bytes).error_handler(json_error(limits.max_body_bytes)))
+                    .route("/v1/meta", web::get().to(handle_meta))
+                    .route("/v1/query", web::post().to(handle_query))
+                    .route("/v1/query/export", web::post().to(handle_query_export))
+                    .route("/v1/info", web::get().to(handle_info))
+                    .route("/v1/repl", web::get().to(ws_handler))
+                    .route("/v1/sync", web::get().to(sync_handler))
+                    .route("/v1/catalog", web::get().to(handle_catalog))
+                    .route("/v1/catalog/{part}", web::get().to(handle_catalog_part))
+                    .route("/v1/schema", web::get().to(handle_schema))
+                    .route("/v1/changes", web::get().to(handle_changes))
+                    .route("/v1/export", web::get().to(handle_export))
+                    .route("/v1/jobs", web::post().to(handle_job_submit))
+                    .route("/v1/jobs", web::get().to(handle_job_list))
+                    .route("/v1/jobs/{id}", web::get().to(handle_job_status))
+                    .route("/v1/jobs/{id}", web::delete().to(handle_job_delete))
+                    .route("/v1/jobs/{id}/result", web::get().to(handle_job_result))
+                    .route("/v1/admin/save", web::post().to(handle_admin_save))
+                    .route("/v1/admin/versions", web::get().to(handle_admin_versions))
+                    .route("/v1/admin/versions", web::post().to(handle_admin_save_version))
+                    .route("/v1/admin/versions/{name}/load", web::post().to(handle_admin_load_version))
+                    .route("/v1/admin/memory", web::get().to(handle_admin_memory))
+                    .route("/v1/admin/compact", web::post().to(handle_admin_compact))
+                    .service(
+                        web::resource("/v1/import")
+                            .app_data(web::PayloadConfig::new(limits.max_import_bytes))
+                            .route(web::post().to(handle_import)),
+                    )
+                    // Legacy unversioned routes
                     .route("/api/query", web::post().to(handle_query))
+                    .route("/api/info", web::get().to(handle_info))
                     .route("/api/repl", web::get().to(ws_handler))
             })
             .bind(&bind) {
                 Ok(s) => s.run(),
                 Err(e) => {
-                    eprintln!("[Graph-Loom] API server bind failed on {}: {}", bind, e);
+                    log::error!("API server bind failed on {}: {}", bind, e);
                     return;
                 }
             };
@@ -277,6 +1095,16 @@ pub fn start_server(cfg: &AppSettings) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Stop accepting connections but let requests already being served finish; [`stop_server`]
+/// afterwards tears the server down.
+pub fn stop_accepting() {
+    let handle = SERVER_STATE.lock().unwrap().handle.clone();
+    if let Some(h) = handle {
+        // The stop command is sent right away; the returned future only reports completion
+        drop(h.stop(true));
+    }
+}
+
 pub fn stop_server() {
     let (handle, rt) = {
         let mut st = SERVER_STATE.lock().unwrap();
@@ -0,0 +1,333 @@
+//! Tools → Peer Sync: instances edit one graph together by exchanging its writes as CRDT
+//! operations (see `graph_utils::crdt`) over WebSocket. A peer either connects to another
+//! instance's `/v1/sync` or, with the API on, accepts connections there. Every instance passes
+//! on what it learns from one peer to the others, so a small team can link up through one host.
+//!
+//! Alongside the graph, peers share presence: each user's name, pointer and selection, which
+//! the canvas shows as colored markers.
+//!
+//! The sockets run on their own threads and only move text; the graph owner (the GUI thread)
+//! calls [`PeerSync::pump`] to send its writes and apply the peers'.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::graph_utils::crdt::{Replica, SyncOp};
+use crate::graph_utils::graph::GraphDatabase;
+
+// Operations per message, so a large graph's snapshot stays within WebSocket frame limits
+const OPS_PER_MESSAGE: usize = 500;
+
+/// What peers send each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SyncMessage {
+    /// First message on a connection, followed by the sender's whole graph as operations
+    Hello { replica: String },
+    Ops { ops: Vec<SyncOp> },
+    Presence(Presence),
+    /// Replicas no longer reachable through the sender
+    Gone { replicas: Vec<String> },
+}
+
+/// Where a user's pointer is, as an offset in canvas units from the nearest node. Layouts
+/// differ between instances, so a position only makes sense relative to the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pointer {
+    pub node: Uuid,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+/// What one user is looking at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    pub replica: String,
+    pub name: String,
+    /// `None` while the pointer is off the canvas
+    #[serde(default)]
+    pub pointer: Option<Pointer>,
+    /// Selected nodes and relationships
+    #[serde(default)]
+    pub selected: Vec<Uuid>,
+}
+
+// What the connection threads report to the graph owner
+enum HubEvent {
+    Joined(usize),
+    Received(usize, String),
+    Left(usize),
+    Failed(String, String),
+}
+
+// Open connections; present only while peer sync is on
+struct Hub {
+    next: usize,
+    links: HashMap<usize, (String, Sender<String>)>,
+    events: Sender<HubEvent>,
+    waker: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+static HUB: Lazy<Mutex<Option<Hub>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_hub<T>(f: impl FnOnce(&mut Hub) -> T) -> Option<T> {
+    let mut hub = HUB.lock().unwrap_or_else(|e| e.into_inner());
+    hub.as_mut().map(f)
+}
+
+fn notify(hub: &Hub, event: HubEvent) {
+    let _ = hub.events.send(event);
+    if let Some(wake) = &hub.waker { wake(); }
+}
+
+/// Register a new connection named `name`. Returns its link ID and the messages to write to
+/// it, or `None` when peer sync is off.
+pub fn join(name: impl Into<String>) -> Option<(usize, Receiver<String>)> {
+    with_hub(|hub| {
+        let (tx, rx) = mpsc::channel();
+        let link = hub.next;
+        hub.next += 1;
+        hub.links.insert(link, (name.into(), tx));
+        notify(hub, HubEvent::Joined(link));
+        (link, rx)
+    })
+}
+
+/// A message read from connection `link`.
+pub fn deliver(link: usize, text: String) {
+    with_hub(|hub| notify(hub, HubEvent::Received(link, text)));
+}
+
+/// Connection `link` closed.
+pub fn leave(link: usize) {
+    with_hub(|hub| {
+        hub.links.remove(&link);
+        notify(hub, HubEvent::Left(link));
+    });
+}
+
+fn send_to(link: usize, msg: &SyncMessage) {
+    let Ok(text) = serde_json::to_string(msg) else { return };
+    with_hub(|hub| {
+        if let Some((_, tx)) = hub.links.get(&link) { let _ = tx.send(text); }
+    });
+}
+
+fn send_ops(link: usize, ops: &[SyncOp]) {
+    for chunk in ops.chunks(OPS_PER_MESSAGE) { send_to(link, &SyncMessage::Ops { ops: chunk.to_vec() }); }
+}
+
+fn links() -> Vec<usize> {
+    with_hub(|hub| hub.links.keys().copied().collect()).unwrap_or_default()
+}
+
+/// A connected peer as listed in the Peer Sync window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// Address it connected from or was reached at
+    pub name: String,
+    /// Its replica ID, once it said hello
+    pub replica: Option<String>,
+}
+
+/// The graph owner's end of peer sync. Dropping it turns peer sync off and closes every
+/// connection.
+pub struct PeerSync {
+    replica: Replica,
+    events: Receiver<HubEvent>,
+    peers: HashMap<usize, PeerInfo>,
+    // Other users by replica, with the link they were heard on
+    participants: HashMap<String, (usize, Presence)>,
+    presence: Option<Presence>,
+    /// Latest connection or protocol error, for display
+    pub last_error: Option<String>,
+}
+
+impl PeerSync {
+    /// Turn peer sync on for `db` under a fresh replica ID.
+    pub fn start(db: &GraphDatabase) -> PeerSync {
+        let (tx, rx) = mpsc::channel();
+        let mut hub = HUB.lock().unwrap_or_else(|e| e.into_inner());
+        *hub = Some(Hub { next: 0, links: HashMap::new(), events: tx, waker: None });
+        PeerSync { replica: Replica::new(uuid::Uuid::now_v7().to_string(), db), events: rx, peers: HashMap::new(), participants: HashMap::new(), presence: None, last_error: None }
+    }
+
+    /// Call `wake` whenever a peer connects or sends something, e.g. to repaint the window.
+    pub fn set_waker(&self, wake: impl Fn() + Send + Sync + 'static) {
+        with_hub(|hub| hub.waker = Some(Arc::new(wake)));
+    }
+
+    pub fn replica_id(&self) -> &str {
+        self.replica.id()
+    }
+
+    /// Connected peers, sorted by name.
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let mut out: Vec<PeerInfo> = self.peers.values().cloned().collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+
+    /// Other users' presence, sorted by name.
+    pub fn participants(&self) -> Vec<Presence> {
+        let mut out: Vec<Presence> = self.participants.values().map(|(_, p)| p.clone()).collect();
+        out.sort_by(|a, b| (&a.name, &a.replica).cmp(&(&b.name, &b.replica)));
+        out
+    }
+
+    /// Tell the peers where this user's pointer is and what they have selected. Only
+    /// changes are sent.
+    pub fn share_presence(&mut self, name: &str, pointer: Option<Pointer>, selected: Vec<Uuid>) {
+        let presence = Presence { replica: self.replica.id().to_string(), name: name.to_string(), pointer, selected };
+        if self.presence.as_ref() == Some(&presence) { return; }
+        let msg = SyncMessage::Presence(presence.clone());
+        for link in links() { send_to(link, &msg); }
+        self.presence = Some(presence);
+    }
+
+    /// Send the writes made to `db` since the last call to every peer, then apply what the
+    /// peers sent. Returns how many operations from peers changed something.
+    pub fn pump(&mut self, db: &mut GraphDatabase) -> usize {
+        match self.replica.local_ops(db) {
+            Ok(ops) if !ops.is_empty() => {
+                for link in links() { send_ops(link, &ops); }
+            }
+            Ok(_) => {}
+            Err(e) => self.last_error = Some(format!("Reading the change log failed: {}", e)),
+        }
+        let mut applied = 0;
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                HubEvent::Joined(link) => {
+                    let name = with_hub(|hub| hub.links.get(&link).map(|(n, _)| n.clone())).flatten().unwrap_or_default();
+                    self.peers.insert(link, PeerInfo { name, replica: None });
+                    send_to(link, &SyncMessage::Hello { replica: self.replica.id().to_string() });
+                    send_ops(link, &self.replica.snapshot(db));
+                    let present = self.presence.iter().chain(self.participants.values().map(|(_, p)| p));
+                    for p in present.cloned().collect::<Vec<_>>() { send_to(link, &SyncMessage::Presence(p)); }
+                }
+                HubEvent::Left(link) => {
+                    self.peers.remove(&link);
+                    self.forget_via(link, None);
+                }
+                HubEvent::Failed(name, e) => self.last_error = Some(format!("{}: {}", name, e)),
+                HubEvent::Received(link, text) => match serde_json::from_str::<SyncMessage>(&text) {
+                    Ok(SyncMessage::Hello { replica }) => {
+                        if let Some(p) = self.peers.get_mut(&link) { p.replica = Some(replica); }
+                    }
+                    Ok(SyncMessage::Presence(p)) => {
+                        // Relaying only changes stops a presence from circling a ring of peers
+                        if p.replica == self.replica.id() || self.participants.get(&p.replica).is_some_and(|(_, known)| *known == p) { continue; }
+                        let msg = SyncMessage::Presence(p.clone());
+                        for other in links().into_iter().filter(|l| *l != link) { send_to(other, &msg); }
+                        self.participants.insert(p.replica.clone(), (link, p));
+                    }
+                    Ok(SyncMessage::Gone { replicas }) => self.forget_via(link, Some(&replicas)),
+                    Ok(SyncMessage::Ops { ops }) => {
+                        // Only news is passed on, so operations die out in a ring of peers
+                        let news: Vec<SyncOp> = ops.into_iter().filter(|op| self.replica.apply(db, op.clone())).collect();
+                        applied += news.len();
+                        if !news.is_empty() {
+                            for other in links().into_iter().filter(|l| *l != link) { send_ops(other, &news); }
+                        }
+                    }
+                    Err(e) => self.last_error = Some(format!("Bad message from a peer: {}", e)),
+                },
+            }
+        }
+        applied
+    }
+
+    // Drop the participants heard on `link` (all of them, or those in `only`) and tell the
+    // other peers they are gone
+    fn forget_via(&mut self, link: usize, only: Option<&[String]>) {
+        let gone: Vec<String> = self
+            .participants
+            .iter()
+            .filter(|(r, (via, _))| *via == link && only.is_none_or(|o| o.contains(r)))
+            .map(|(r, _)| r.clone())
+            .collect();
+        if gone.is_empty() { return; }
+        for r in &gone { self.participants.remove(r); }
+        let msg = SyncMessage::Gone { replicas: gone };
+        for other in links().into_iter().filter(|l| *l != link) { send_to(other, &msg); }
+    }
+}
+
+impl Drop for PeerSync {
+    fn drop(&mut self) {
+        // Dropping the outboxes ends the connection threads
+        *HUB.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Connect to the peer at `url` (`ws://host:port/v1/sync`) on a background thread. The
+/// outcome shows up in [`PeerSync::peers`] or [`PeerSync::last_error`].
+#[cfg(feature = "api")]
+pub fn connect(url: &str, api_key: Option<&str>) {
+    use std::time::Duration;
+    use tungstenite::client::IntoClientRequest;
+    use tungstenite::stream::MaybeTlsStream;
+    use tungstenite::Message;
+
+    let url = url.trim().to_string();
+    let api_key = api_key.map(str::to_string);
+    let fail = |name: &str, e: String| { with_hub(|hub| notify(hub, HubEvent::Failed(name.to_string(), e))); };
+    std::thread::spawn(move || {
+        let mut req = match url.as_str().into_client_request() {
+            Ok(r) => r,
+            Err(e) => return fail(&url, e.to_string()),
+        };
+        if let Some(key) = &api_key {
+            match key.parse() {
+                Ok(v) => { req.headers_mut().insert("X-API-Key", v); }
+                Err(_) => return fail(&url, "invalid API key".into()),
+            }
+        }
+        let mut socket = match tungstenite::connect(req) {
+            Ok((s, _)) => s,
+            Err(e) => return fail(&url, e.to_string()),
+        };
+        // Short reads so queued messages go out promptly
+        if let MaybeTlsStream::Plain(s) = socket.get_mut() { let _ = s.set_read_timeout(Some(Duration::from_millis(50))); }
+        let Some((link, outbox)) = join(url.clone()) else { return };
+        'conn: loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => deliver(link, text),
+                Ok(Message::Close(_)) => break,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+                Err(e) => {
+                    fail(&url, e.to_string());
+                    break;
+                }
+            }
+            loop {
+                match outbox.try_recv() {
+                    Ok(text) => {
+                        if let Err(e) = socket.send(Message::Text(text)) {
+                            fail(&url, e.to_string());
+                            break 'conn;
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        break 'conn;
+                    }
+                }
+            }
+        }
+        leave(link);
+    });
+}
+
+#[cfg(not(feature = "api"))]
+pub fn connect(url: &str, _api_key: Option<&str>) {
+    with_hub(|hub| notify(hub, HubEvent::Failed(url.to_string(), "this build has no API support".into())));
+}
@@ -0,0 +1,85 @@
+//! Request tracing. Every API request runs in a `tracing` span carrying its request ID, with
+//! the query's parse and execution as child spans. Builds with the `otel` feature can send
+//! these spans to an OpenTelemetry collector over OTLP/gRPC (Jaeger, Grafana Tempo, ...) when
+//! **API Settings → OTLP endpoint** is set; the exporter starts with the app.
+
+use super::jobs;
+use crate::persistence::settings::AppSettings;
+
+/// Header (HTTP) and metadata key (gRPC) carrying a request's ID both ways. A client may send
+/// its own ID to follow a request through the logs; otherwise the server makes one up.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longest client-chosen request ID that is kept
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The request ID a client sent, if it is short and printable enough to put in logs and not
+/// one that could be mistaken for a job id.
+pub fn client_request_id(value: Option<&str>) -> Option<String> {
+    let v = value?.trim();
+    let ok = !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN && v.chars().all(|c| c.is_ascii_graphic()) && !v.starts_with(jobs::ID_PREFIX);
+    ok.then(|| v.to_string())
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use std::sync::Mutex;
+
+    use anyhow::Context;
+    use once_cell::sync::Lazy;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    // The batch exporter runs on its own runtime, since the app has no async main
+    static EXPORTER: Lazy<Mutex<Option<(tokio::runtime::Runtime, TracerProvider)>>> = Lazy::new(|| Mutex::new(None));
+
+    pub fn start(endpoint: &str) -> anyhow::Result<()> {
+        let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build()?;
+        let _guard = rt.enter();
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .with_context(|| format!("OTLP exporter for {}", endpoint))?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", "graph-loom")]))
+            .build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("graph-loom"));
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer)).context("a tracing subscriber is already installed")?;
+        drop(_guard);
+        *EXPORTER.lock().unwrap_or_else(|e| e.into_inner()) = Some((rt, provider));
+        Ok(())
+    }
+
+    pub fn shutdown() {
+        let Some((rt, provider)) = EXPORTER.lock().unwrap_or_else(|e| e.into_inner()).take() else { return };
+        let _guard = rt.enter();
+        if let Err(e) = provider.shutdown() { log::warn!("Flushing traces failed: {}", e); }
+        drop(_guard);
+        rt.shutdown_background();
+    }
+}
+
+/// Start exporting spans to the OTLP endpoint in `settings`, if one is set. Failing to start
+/// is logged; the app runs on without tracing.
+pub fn init(settings: &AppSettings) {
+    let Some(endpoint) = settings.otlp_endpoint.as_deref().map(str::trim).filter(|e| !e.is_empty()) else { return };
+    #[cfg(feature = "otel")]
+    match otlp::start(endpoint) {
+        Ok(()) => log::info!("Exporting traces to {}", endpoint),
+        Err(e) => log::error!("Trace export not started: {:#}", e),
+    }
+    #[cfg(not(feature = "otel"))]
+    log::warn!("An OTLP endpoint ({}) is set, but this build has no OpenTelemetry support (feature `otel`)", endpoint);
+}
+
+/// Send the spans still buffered; call before exiting.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otlp::shutdown();
+}
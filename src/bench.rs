@@ -0,0 +1,163 @@
+//! `Graph-Loom bench`: times the canvas work that does not need a window on a generated
+//! graph, so render/physics regressions can be compared between builds. The graph is a
+//! seeded Barabási–Albert network, so the same arguments always measure the same input.
+//! Painting needs a window; View → Performance Overlay shows it live.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{Pos2, Rect, Vec2};
+
+use crate::gql::query_interface;
+use crate::graph_utils::generators::{Generator, Model};
+use crate::gui::frontend::GraphApp;
+use crate::gui::physics;
+use crate::gui::tasks::Progress;
+
+pub const USAGE: &str = "usage: Graph-Loom bench [--nodes N] [--edges-per-node M] [--steps S] [--seed X] [--gravity]";
+
+// Canvas the layout targets, roughly a maximized window
+const CANVAS: Rect = Rect { min: Pos2::ZERO, max: Pos2::new(1600.0, 1000.0) };
+// Frame time the physics steps assume (60 Hz)
+const DT: f32 = 1.0 / 60.0;
+// Each query is run this many times
+const QUERY_RUNS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub nodes: usize,
+    pub edges_per_node: usize,
+    pub steps: usize,
+    pub seed: u64,
+    /// Include center-of-mass gravity in the physics steps (off by default, as in the app)
+    pub gravity: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { nodes: 5_000, edges_per_node: 2, steps: 10, seed: 42, gravity: false }
+    }
+}
+
+/// Parse the arguments after `bench`.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| -> Result<u64, String> {
+            it.next().and_then(|v| v.parse().ok()).ok_or_else(|| format!("{} expects a number\n{}", name, USAGE))
+        };
+        match arg.as_str() {
+            "--nodes" => opts.nodes = value("--nodes")? as usize,
+            "--edges-per-node" => opts.edges_per_node = value("--edges-per-node")? as usize,
+            "--steps" => opts.steps = value("--steps")? as usize,
+            "--seed" => opts.seed = value("--seed")?,
+            "--gravity" => opts.gravity = true,
+            other => return Err(format!("unknown bench option '{}'\n{}", other, USAGE)),
+        }
+    }
+    Ok(opts)
+}
+
+/// Timings of one measured operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub name: String,
+    pub runs: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Timing {
+    fn from_samples(name: impl Into<String>, samples: &[Duration]) -> Self {
+        let runs = samples.len();
+        let total: Duration = samples.iter().sum();
+        Timing {
+            name: name.into(),
+            runs,
+            mean: if runs == 0 { Duration::ZERO } else { total / runs as u32 },
+            min: samples.iter().min().copied().unwrap_or_default(),
+            max: samples.iter().max().copied().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub options: Options,
+    pub nodes: usize,
+    pub relationships: usize,
+    pub timings: Vec<Timing>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "graph: {} nodes, {} relationships (seed {}, {} edges per node{})",
+            self.nodes,
+            self.relationships,
+            self.options.seed,
+            self.options.edges_per_node,
+            if self.options.gravity { ", gravity" } else { "" }
+        )?;
+        writeln!(f, "{:<28} {:>5} {:>12} {:>12} {:>12}", "operation", "runs", "mean ms", "min ms", "max ms")?;
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        for t in &self.timings {
+            writeln!(f, "{:<28} {:>5} {:>12.3} {:>12.3} {:>12.3}", t.name, t.runs, ms(t.mean), ms(t.min), ms(t.max))?;
+        }
+        Ok(())
+    }
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let out = f();
+    (out, started.elapsed())
+}
+
+/// Generate the graph, lay it out, run the physics steps and a fixed set of queries.
+pub fn run(opts: &Options) -> anyhow::Result<Report> {
+    let generator = Generator {
+        model: Model::BarabasiAlbert { nodes: opts.nodes, edges_per_node: opts.edges_per_node },
+        seed: opts.seed,
+        ..Generator::default()
+    };
+    let mut timings = Vec::new();
+    let (mut db, spent) = timed(|| generator.generate());
+    timings.push(Timing::from_samples("generate", &[spent]));
+
+    let (layout, spent) = timed(|| GraphApp::community_layout(&db, CANVAS, &Progress::default()));
+    let mut positions = layout?;
+    timings.push(Timing::from_samples("community layout", &[spent]));
+
+    let forces = physics::Forces {
+        gravity: opts.gravity.then_some(physics::Gravity { center: CANVAS.center(), strength: 6.0, com_radius: 150.0, com_min_neighbors: 2 }),
+        hub_repulsion_scale: 1.0,
+    };
+    let mut velocities: HashMap<_, Vec2> = HashMap::new();
+    let samples: Vec<Duration> = (0..opts.steps)
+        .map(|_| timed(|| physics::step(&db, &mut positions, &mut velocities, &forces, None, DT)).1)
+        .collect();
+    timings.push(Timing::from_samples("physics step", &samples));
+
+    let last = opts.nodes.saturating_sub(1);
+    let queries = [
+        ("query: lookup by name", format!("MATCH (n:Node {{name: 'n{}'}}) RETURN n", last)),
+        ("query: neighbors", "MATCH (a:Node)-[:LINKS]->(b:Node {name: 'n0'}) RETURN a".to_string()),
+        ("query: filtered scan", "MATCH (n:Node) WHERE n.name ENDS WITH '7' RETURN n.name LIMIT 100".to_string()),
+        ("query: degree", "CALL algo.degree()".to_string()),
+    ];
+    for (name, q) in queries {
+        let mut samples = Vec::with_capacity(QUERY_RUNS);
+        for _ in 0..QUERY_RUNS {
+            let (res, spent) = timed(|| query_interface::execute_query(&mut db, &q));
+            res?;
+            samples.push(spent);
+        }
+        timings.push(Timing::from_samples(name, &samples));
+    }
+    Ok(Report { options: opts.clone(), nodes: db.nodes.len(), relationships: db.relationships.len(), timings })
+}
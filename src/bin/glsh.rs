@@ -70,7 +70,7 @@ fn main() {
     let eval = matches.get_one::<String>("eval").cloned();
     let quiet = matches.get_flag("quiet");
 
-    let endpoint = format!("ws://{}:{}/api/repl", host, port);
+    let endpoint = format!("ws://{}:{}/v1/repl", host, port);
     let url = match Url::parse(&endpoint) {
         Ok(u) => u,
         Err(e) => {
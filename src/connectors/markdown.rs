@@ -0,0 +1,331 @@
+//! Import a folder of Markdown notes, such as an Obsidian vault: a node per note and a
+//! relationship per link between notes, `[[wikilinks]]` and `[text](note.md)` links alike.
+//!
+//! A note becomes a `Note` node with its text as the description and `title`, `path`,
+//! `folder`, `tags` (front matter `tags` plus inline `#tags`) and its other front matter keys
+//! as metadata. Links resolve the way Obsidian resolves them: by path, then by file name or
+//! alias, ignoring case. A link to a note that does not exist yet makes an `Unresolved` node,
+//! which becomes the note once it is written where the link points. Hidden folders (`.obsidian`,
+//! `.git`, `.trash`) are skipped, as are links inside code.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::persistence::import::{external_id, ImportPayload};
+
+pub const NOTE_LABEL: &str = "Note";
+pub const UNRESOLVED_LABEL: &str = "Unresolved";
+pub const LINK_LABEL: &str = "LINKS_TO";
+
+// Notes read between progress reports
+const REPORT_EVERY: usize = 100;
+
+// Extensions of embedded files; links to these are not links to notes
+const ATTACHMENTS: [&str; 18] =
+    ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "pdf", "mp3", "mp4", "wav", "ogg", "m4a", "webm", "mov", "canvas", "csv", "zip"];
+
+struct Note {
+    // Vault-relative path with `/` separators, e.g. `projects/Graph Loom.md`
+    path: String,
+    front: Vec<(String, String)>,
+    aliases: Vec<String>,
+    tags: BTreeSet<String>,
+    body: String,
+    // Link targets as written (without alias), and whether each is a Markdown link
+    links: Vec<(String, bool)>,
+}
+
+impl Note {
+    // Lower-case path without the extension: what links by path match and IDs derive from
+    fn key(&self) -> String {
+        strip_md(&self.path).to_lowercase()
+    }
+
+    fn stem(&self) -> &str {
+        let name = self.path.rsplit('/').next().unwrap_or(&self.path);
+        strip_md(name)
+    }
+
+    fn folder(&self) -> &str {
+        self.path.rsplit_once('/').map_or("", |(f, _)| f)
+    }
+}
+
+/// Read every note under `dir`. `on_notes` hears the number of notes read every so often and
+/// can stop the import by returning an error.
+pub fn read_vault(dir: &Path, mut on_notes: impl FnMut(usize) -> Result<()>) -> Result<ImportPayload> {
+    if !dir.is_dir() { bail!("{} is not a folder", dir.display()); }
+    let mut files = Vec::new();
+    collect_notes(dir, "", &mut files)?;
+    if files.is_empty() { bail!("no Markdown notes in {}", dir.display()); }
+    files.sort();
+    let mut notes = Vec::with_capacity(files.len());
+    for (i, path) in files.into_iter().enumerate() {
+        let bytes = std::fs::read(dir.join(&path)).with_context(|| format!("reading {}", path))?;
+        notes.push(parse_note(path, &String::from_utf8_lossy(&bytes)));
+        if (i + 1).is_multiple_of(REPORT_EVERY) { on_notes(i + 1)?; }
+    }
+    on_notes(notes.len())?;
+    let source = format!("vault:{}", dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()).display());
+    Ok(materialize(&source, &notes))
+}
+
+// Vault-relative paths of the Markdown files under `dir/rel`, outside hidden folders
+fn collect_notes(dir: &Path, rel: &str, out: &mut Vec<String>) -> Result<()> {
+    let here = if rel.is_empty() { dir.to_path_buf() } else { dir.join(rel) };
+    for entry in std::fs::read_dir(&here).with_context(|| format!("reading {}", here.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') { continue; }
+        let path = if rel.is_empty() { name.clone() } else { format!("{}/{}", rel, name) };
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            collect_notes(dir, &path, out)?;
+        } else if kind.is_file() && is_md(&name) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_md(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower.ends_with(".md") || lower.ends_with(".markdown")
+}
+
+fn is_attachment(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, ext)| ATTACHMENTS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn strip_md(name: &str) -> &str {
+    let lower = name.to_ascii_lowercase();
+    [".md", ".markdown"].iter().find(|e| lower.ends_with(*e)).map_or(name, |e| &name[..name.len() - e.len()])
+}
+
+fn parse_note(path: String, text: &str) -> Note {
+    let (front, body) = split_front_matter(text);
+    let mut tags: BTreeSet<String> = BTreeSet::new();
+    let mut aliases = Vec::new();
+    let mut fields = Vec::new();
+    for (key, values) in front {
+        match key.as_str() {
+            "tags" | "tag" => tags.extend(values.iter().map(|t| t.trim_start_matches('#').to_string())),
+            "aliases" | "alias" => aliases.extend(values.iter().cloned()),
+            _ => {}
+        }
+        if key != "tags" && key != "tag" { fields.push((key, values.join(", "))); }
+    }
+    let prose = prose(body);
+    tags.extend(inline_tags(&prose));
+    let links = links(&prose);
+    Note { path, front: fields, aliases, tags, body: body.trim().to_string(), links }
+}
+
+// Front matter as (key, values), and the text after it. Only flat `key: value` and
+// `key: [a, b]` entries and `- item` lists are read; anything nested is skipped.
+fn split_front_matter(text: &str) -> (Vec<(String, Vec<String>)>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else { return (Vec::new(), text) };
+    let mut end = None;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((yaml_end, body_start)) = end else { return (Vec::new(), text) };
+    let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+    for line in rest[..yaml_end].lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            if line.starts_with([' ', '\t', '-']) && let Some((_, values)) = entries.last_mut() { values.push(unquote(item)); }
+            continue;
+        }
+        if line.starts_with([' ', '\t']) { continue; }
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        let values = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(list) => list.split(',').map(unquote).filter(|v| !v.is_empty()).collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        entries.push((key.trim().to_string(), values));
+    }
+    (entries, &rest[body_start..])
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    let quoted = s.len() >= 2 && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')));
+    if quoted { s[1..s.len() - 1].to_string() } else { s.to_string() }
+}
+
+// The text with fenced code blocks and inline code removed, so links and tags in code are
+// not picked up
+fn prose(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut fence: Option<&str> = None;
+    for line in body.lines() {
+        let t = line.trim_start();
+        match fence {
+            Some(f) => {
+                if t.starts_with(f) { fence = None; }
+                continue;
+            }
+            None if t.starts_with("```") || t.starts_with("~~~") => {
+                fence = Some(&t[..3]);
+                continue;
+            }
+            None => {}
+        }
+        let mut in_code = false;
+        for c in line.chars() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if !in_code {
+                out.push(c);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// `#tag` and `#nested/tag` preceded by a space or a line start; headings (`# Title`) and
+// numbers (`#1`) are not tags
+fn inline_tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        if c == '#' && prev.is_whitespace() {
+            let tag: String = text[i + 1..].chars().take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/')).collect();
+            if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) { tags.push(tag); }
+        }
+        prev = c;
+    }
+    tags
+}
+
+// Link targets: `[[target|alias]]`, `![[target]]` and `[text](target.md)`
+fn links(text: &str) -> Vec<(String, bool)> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("]]") else { break };
+        let inner = &after[..end];
+        if !inner.contains('\n') {
+            let target = inner.split('|').next().unwrap_or_default();
+            out.push((target.to_string(), false));
+        }
+        rest = &after[end + 2..];
+    }
+    let mut rest = text;
+    while let Some(start) = rest.find("](") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find(')') else { break };
+        let target = after[..end].trim().trim_start_matches('<').trim_end_matches('>');
+        let target = target.split_whitespace().next().unwrap_or_default();
+        if !target.contains("://") && !target.starts_with("mailto:") {
+            let target = target.split('#').next().unwrap_or_default();
+            let decoded = percent_decode(target);
+            if is_md(&decoded) { out.push((decoded, true)); }
+        }
+        rest = &after[end + 1..];
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| s.get(i + 1..i + 3)).flatten().and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// `path` joined to `folder`, with `.` and `..` resolved; a leading `/` means the vault root
+fn join(folder: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = if path.starts_with('/') { Vec::new() } else { folder.split('/').filter(|p| !p.is_empty()).collect() };
+    for p in path.split('/') {
+        match p {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            p => parts.push(p),
+        }
+    }
+    parts.join("/")
+}
+
+fn materialize(source: &str, notes: &[Note]) -> ImportPayload {
+    let by_key: HashMap<String, usize> = notes.iter().enumerate().map(|(i, n)| (n.key(), i)).collect();
+    // Notes sharing a name: the first in path order wins, like links without a folder
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    for (i, n) in notes.iter().enumerate() {
+        by_name.entry(n.stem().to_lowercase()).or_insert(i);
+        for a in &n.aliases { by_name.entry(a.to_lowercase()).or_insert(i); }
+    }
+    let note_id = |key: &str| external_id(source, &format!("note/{}", key));
+
+    let mut payload = ImportPayload::default();
+    for n in notes {
+        let mut metadata: HashMap<String, String> = n.front.iter().cloned().collect();
+        metadata.entry("title".into()).or_insert_with(|| n.stem().to_string());
+        metadata.insert("path".into(), n.path.clone());
+        if !n.folder().is_empty() { metadata.insert("folder".into(), n.folder().to_string()); }
+        if !n.tags.is_empty() { metadata.insert("tags".into(), n.tags.iter().cloned().collect::<Vec<_>>().join(", ")); }
+        payload.add_described_node(note_id(&n.key()), NOTE_LABEL, metadata, n.body.clone());
+    }
+
+    let mut unresolved: HashSet<String> = HashSet::new();
+    let mut linked: HashSet<(String, String)> = HashSet::new();
+    for n in notes {
+        for (target, markdown) in &n.links {
+            // `Note#Heading` and `Note#^block` link into a note
+            let target = target.split('#').next().unwrap_or_default().trim();
+            if target.is_empty() { continue; }
+            // Markdown links are relative to the note; wikilinks name a note anywhere
+            let wanted = strip_md(&if *markdown { join(n.folder(), target) } else { target.to_string() }).to_lowercase();
+            let found = match markdown {
+                true => by_key.get(&wanted),
+                false => by_key.get(&wanted).or_else(|| by_name.get(&wanted)).or_else(|| by_name.get(wanted.rsplit('/').next().unwrap_or(&wanted))),
+            };
+            let key = match found {
+                Some(&i) => notes[i].key(),
+                None if is_attachment(target) => continue,
+                None => {
+                    let key = wanted;
+                    if unresolved.insert(key.clone()) {
+                        let title = strip_md(target.rsplit('/').next().unwrap_or(target)).to_string();
+                        payload.add_node(note_id(&key), UNRESOLVED_LABEL, HashMap::from([("title".to_string(), title)]));
+                    }
+                    key
+                }
+            };
+            let from = n.key();
+            if key == from || !linked.insert((from.clone(), key.clone())) { continue; }
+            let id = external_id(source, &format!("link/{}/{}", from, key));
+            payload.add_relationship(id, note_id(&from), note_id(&key), LINK_LABEL, HashMap::new());
+        }
+    }
+    payload
+}
@@ -0,0 +1,10 @@
+//! Importers that read from other systems and tools rather than from export files. Each one
+//! produces an [`ImportPayload`](crate::persistence::import::ImportPayload) that is merged
+//! like a file import, with IDs derived from the source so that importing again updates the
+//! same elements.
+
+pub mod markdown;
+pub mod neo4j;
+pub mod packages;
+pub mod rest;
+pub mod sql;
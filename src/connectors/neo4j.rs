@@ -0,0 +1,249 @@
+//! Import from a running Neo4j server: log in over Bolt, run a Cypher query and keep the
+//! nodes, relationships and paths in its rows (also inside lists and maps). Other values in
+//! the rows are ignored. Needs the `neo4j` feature; without it [`fetch`] fails.
+//!
+//! A node keeps its first Neo4j label; further labels go into its `labels` metadata. Property
+//! values become metadata strings, with lists and maps written as JSON.
+
+use anyhow::Result;
+
+use crate::persistence::import::ImportPayload;
+
+/// Query run when none is given: every node with its outgoing relationships.
+pub const DEFAULT_QUERY: &str = "MATCH (n) OPTIONAL MATCH (n)-[r]->(m) RETURN n, r, m";
+
+/// Which server to read from and what to run there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Neo4jSource {
+    /// e.g. `bolt://localhost:7687` or `neo4j+s://<id>.databases.neo4j.io`
+    pub uri: String,
+    pub user: String,
+    pub password: String,
+    /// Empty for the server's default database
+    pub database: String,
+    pub query: String,
+}
+
+impl Default for Neo4jSource {
+    fn default() -> Self {
+        Neo4jSource {
+            uri: "bolt://localhost:7687".into(),
+            user: "neo4j".into(),
+            password: String::new(),
+            database: String::new(),
+            query: DEFAULT_QUERY.into(),
+        }
+    }
+}
+
+/// The Bolt side, with the `neo4j` feature.
+#[cfg(feature = "neo4j")]
+pub mod bolt {
+    use std::collections::{HashMap, HashSet};
+
+    use anyhow::{anyhow, Context, Result};
+    use neo4rs::{BoltMap, BoltNode, BoltPath, BoltRelation, BoltType, BoltUnboundedRelation, Row};
+    use uuid::Uuid;
+
+    use super::{Neo4jSource, DEFAULT_QUERY};
+    use crate::persistence::import::{external_id, ImportPayload};
+
+    // Label for nodes without one
+    const UNLABELED: &str = "Node";
+
+    // Rows read between progress reports
+    const REPORT_EVERY: usize = 500;
+
+    impl Neo4jSource {
+        /// What imported IDs are derived from: the server and database the elements came from.
+        pub fn key(&self) -> String {
+            format!("neo4j:{}/{}", self.uri.trim().trim_end_matches('/'), self.database.trim())
+        }
+    }
+
+    /// Collects the graph elements in query rows, each once.
+    pub struct Materializer {
+        source: String,
+        payload: ImportPayload,
+        nodes: HashSet<i64>,
+        rels: HashSet<i64>,
+        rows: usize,
+    }
+
+    impl Materializer {
+        /// `source` is what element IDs are derived from (see [`Neo4jSource::key`]).
+        pub fn new(source: impl Into<String>) -> Self {
+            Materializer { source: source.into(), payload: ImportPayload::default(), nodes: HashSet::new(), rels: HashSet::new(), rows: 0 }
+        }
+
+        pub fn add_row(&mut self, row: &Row) -> Result<()> {
+            let values: HashMap<String, BoltType> = row.to().map_err(|e| anyhow!("row {}: {}", self.rows + 1, e))?;
+            for v in values.values() { self.add_value(v); }
+            self.rows += 1;
+            Ok(())
+        }
+
+        pub fn rows(&self) -> usize {
+            self.rows
+        }
+
+        /// The collected elements. Fails when the rows held no node or relationship at all.
+        pub fn finish(self) -> Result<ImportPayload> {
+            if self.payload.is_empty() && self.rows > 0 {
+                return Err(anyhow!("the query returned {} row(s) but no nodes, relationships or paths (e.g. RETURN n, r, m)", self.rows));
+            }
+            Ok(self.payload)
+        }
+
+        fn add_value(&mut self, v: &BoltType) {
+            match v {
+                BoltType::Node(n) => self.add_node(n),
+                BoltType::Relation(r) => self.add_relation(r),
+                BoltType::Path(p) => self.add_path(p),
+                BoltType::List(l) => l.value.iter().for_each(|x| self.add_value(x)),
+                BoltType::Map(m) => m.value.values().for_each(|x| self.add_value(x)),
+                _ => {}
+            }
+        }
+
+        fn id(&self, kind: &str, id: i64) -> Uuid {
+            external_id(&self.source, &format!("{}/{}", kind, id))
+        }
+
+        fn add_node(&mut self, n: &BoltNode) {
+            if !self.nodes.insert(n.id.value) { return; }
+            let mut labels = n.labels.value.iter().filter_map(|l| match l {
+                BoltType::String(s) => Some(s.value.clone()),
+                _ => None,
+            });
+            let label = labels.next().unwrap_or_else(|| UNLABELED.to_string());
+            let mut metadata = properties(&n.properties);
+            let more: Vec<String> = labels.collect();
+            if !more.is_empty() { metadata.entry("labels".into()).or_insert_with(|| more.join(",")); }
+            self.payload.add_node(self.id("node", n.id.value), label, metadata);
+        }
+
+        fn add_relation(&mut self, r: &BoltRelation) {
+            self.add_rel(r.id.value, r.start_node_id.value, r.end_node_id.value, &r.typ.value, &r.properties);
+        }
+
+        // A path lists its nodes and relationships once each; `indices` walks it as
+        // (relationship, node) pairs from the first node. A relationship index counts from 1
+        // and is negative when the path runs against the relationship's direction.
+        fn add_path(&mut self, p: &BoltPath) {
+            let nodes: Vec<&BoltNode> = p.nodes.value.iter().filter_map(|n| if let BoltType::Node(n) = n { Some(n) } else { None }).collect();
+            let rels: Vec<&BoltUnboundedRelation> =
+                p.rels.value.iter().filter_map(|r| if let BoltType::UnboundedRelation(r) = r { Some(r) } else { None }).collect();
+            for n in &nodes { self.add_node(n); }
+            let Some(mut at) = nodes.first().map(|n| n.id.value) else { return };
+            for step in p.indices().chunks(2) {
+                let [rel, node] = step else { break };
+                let r = usize::try_from(rel.value.unsigned_abs()).ok().and_then(|i| i.checked_sub(1)).and_then(|i| rels.get(i));
+                let next = usize::try_from(node.value).ok().and_then(|i| nodes.get(i));
+                let (Some(r), Some(next)) = (r, next) else { break };
+                let (from, to) = if rel.value > 0 { (at, next.id.value) } else { (next.id.value, at) };
+                self.add_rel(r.id.value, from, to, &r.typ.value, &r.properties);
+                at = next.id.value;
+            }
+        }
+
+        fn add_rel(&mut self, id: i64, from: i64, to: i64, typ: &str, props: &BoltMap) {
+            if !self.rels.insert(id) { return; }
+            self.payload.add_relationship(self.id("rel", id), self.id("node", from), self.id("node", to), typ, properties(props));
+        }
+    }
+
+    // Nulls are left out
+    fn properties(m: &BoltMap) -> HashMap<String, String> {
+        m.value
+            .iter()
+            .filter_map(|(k, v)| {
+                let text = match json(v)? {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                Some((k.value.clone(), text))
+            })
+            .collect()
+    }
+
+    fn json(v: &BoltType) -> Option<serde_json::Value> {
+        use serde_json::Value;
+        Some(match v {
+            BoltType::Null(_) => return None,
+            BoltType::String(s) => Value::String(s.value.clone()),
+            BoltType::Boolean(b) => Value::Bool(b.value),
+            BoltType::Integer(i) => Value::from(i.value),
+            BoltType::Float(f) => serde_json::Number::from_f64(f.value).map(Value::Number).unwrap_or_else(|| Value::String(f.value.to_string())),
+            BoltType::List(l) => Value::Array(l.value.iter().filter_map(json).collect()),
+            BoltType::Map(m) => Value::Object(m.value.iter().filter_map(|(k, v)| Some((k.value.clone(), json(v)?))).collect()),
+            BoltType::Point2D(p) => serde_json::json!({ "srid": p.sr_id.value, "x": p.x.value, "y": p.y.value }),
+            BoltType::Point3D(p) => serde_json::json!({ "srid": p.sr_id.value, "x": p.x.value, "y": p.y.value, "z": p.z.value }),
+            BoltType::Bytes(b) => Value::String(b.value.iter().map(|b| format!("{:02x}", b)).collect()),
+            BoltType::Date(_)
+            | BoltType::Time(_)
+            | BoltType::LocalTime(_)
+            | BoltType::DateTime(_)
+            | BoltType::LocalDateTime(_)
+            | BoltType::DateTimeZoneId(_)
+            | BoltType::Duration(_) => Value::String(temporal(v)?),
+            BoltType::Node(_) | BoltType::Relation(_) | BoltType::UnboundedRelation(_) | BoltType::Path(_) => return None,
+        })
+    }
+
+    // Temporal values in ISO-8601
+    fn temporal(v: &BoltType) -> Option<String> {
+        const LOCAL: &str = "%Y-%m-%dT%H:%M:%S%.f";
+        let v = v.clone();
+        Some(match &v {
+            BoltType::Date(_) => chrono::NaiveDate::try_from(v).ok()?.to_string(),
+            BoltType::DateTime(_) => chrono::DateTime::<chrono::FixedOffset>::try_from(v).ok()?.format("%Y-%m-%dT%H:%M:%S%.f%:z").to_string(),
+            BoltType::LocalDateTime(_) => chrono::NaiveDateTime::try_from(v).ok()?.format(LOCAL).to_string(),
+            BoltType::DateTimeZoneId(_) => {
+                let (at, zone) = <(chrono::NaiveDateTime, String)>::try_from(v).ok()?;
+                format!("{}[{}]", at.format(LOCAL), zone)
+            }
+            BoltType::Time(_) | BoltType::LocalTime(_) => {
+                let (time, offset) = <(chrono::NaiveTime, Option<chrono::FixedOffset>)>::try_from(v).ok()?;
+                format!("{}{}", time.format("%H:%M:%S%.f"), offset.map(|o| o.to_string()).unwrap_or_default())
+            }
+            BoltType::Duration(_) => format!("PT{}S", std::time::Duration::try_from(v).ok()?.as_secs_f64()),
+            _ => return None,
+        })
+    }
+
+    pub fn fetch(source: &Neo4jSource, mut on_rows: impl FnMut(usize) -> Result<()>) -> Result<ImportPayload> {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        rt.block_on(async {
+            let mut config = neo4rs::ConfigBuilder::default()
+                .uri(source.uri.trim())
+                .user(source.user.trim())
+                .password(source.password.as_str())
+                .max_connections(1);
+            if !source.database.trim().is_empty() { config = config.db(source.database.trim()); }
+            let graph = neo4rs::Graph::connect(config.build()?).await.with_context(|| format!("connecting to {}", source.uri.trim()))?;
+            let query = if source.query.trim().is_empty() { DEFAULT_QUERY } else { source.query.trim() };
+            let mut rows = graph.execute(neo4rs::query(query)).await.context("running the query")?;
+            let mut m = Materializer::new(source.key());
+            while let Some(row) = rows.next().await.context("reading the results")? {
+                m.add_row(&row)?;
+                if m.rows().is_multiple_of(REPORT_EVERY) { on_rows(m.rows())?; }
+            }
+            on_rows(m.rows())?;
+            m.finish()
+        })
+    }
+}
+
+/// Connect to the source's server, run its query and collect the elements in the rows.
+/// `on_rows` hears the number of rows read every few hundred rows and can stop the import by
+/// returning an error.
+pub fn fetch(source: &Neo4jSource, on_rows: impl FnMut(usize) -> Result<()>) -> Result<ImportPayload> {
+    #[cfg(feature = "neo4j")]
+    return bolt::fetch(source, on_rows);
+    #[cfg(not(feature = "neo4j"))]
+    {
+        let _ = (source, on_rows);
+        Err(anyhow::anyhow!("this build has no Neo4j support (feature `neo4j`)"))
+    }
+}
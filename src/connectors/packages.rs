@@ -0,0 +1,330 @@
+//! Import package-dependency graphs: a `Package` node per package version and a `DEPENDS_ON`
+//! relationship per dependency, from `cargo metadata` output or an npm `package-lock.json`.
+//!
+//! Nodes carry `name`, `version` and `ecosystem` (`cargo` or `npm`) plus what the ecosystem
+//! records about the package (license, source, ...); relationships carry the dependency `kind`
+//! (`normal`, `dev`, `build`, `optional`, `peer`) and the version requirement `req`. IDs depend
+//! only on the ecosystem, names and versions, so projects imported into the same graph share
+//! the packages they have in common.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::persistence::import::{external_id, ImportPayload};
+
+pub const PACKAGE_LABEL: &str = "Package";
+pub const DEPENDS_LABEL: &str = "DEPENDS_ON";
+
+const CRATES_IO: [&str; 2] = ["registry+https://github.com/rust-lang/crates.io-index", "sparse+https://index.crates.io/"];
+
+/// Read the dependency graph at `path`: a `package-lock.json` (or `npm-shrinkwrap.json`), a
+/// `Cargo.toml` (runs `cargo metadata`), saved `cargo metadata` JSON, or a folder holding one
+/// of the first two.
+pub fn read(path: &Path) -> Result<ImportPayload> {
+    if path.is_dir() {
+        for name in ["package-lock.json", "npm-shrinkwrap.json", "Cargo.toml"] {
+            if path.join(name).is_file() { return read(&path.join(name)); }
+        }
+        bail!("no package-lock.json or Cargo.toml in {}", path.display());
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if name == "cargo.toml" { return cargo_metadata(&run_cargo_metadata(path)?); }
+    let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    if name == "package-lock.json" || name == "npm-shrinkwrap.json" { return package_lock(&text); }
+    // Any other JSON: tell the formats apart by their top-level keys
+    let value: serde_json::Value = serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    if value.get("resolve").is_some() && value.get("workspace_members").is_some() { return cargo_metadata(&text); }
+    if value.get("lockfileVersion").is_some() { return package_lock(&text); }
+    bail!("{} is neither `cargo metadata` output nor an npm lockfile", path.display())
+}
+
+fn run_cargo_metadata(manifest: &Path) -> Result<String> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let out = std::process::Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(manifest)
+        .output()
+        .context("running `cargo metadata` (is cargo installed?)")?;
+    if !out.status.success() {
+        bail!("`cargo metadata` failed: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    String::from_utf8(out.stdout).context("`cargo metadata` printed invalid UTF-8")
+}
+
+fn package_id(ecosystem: &str, name: &str, version: &str) -> uuid::Uuid {
+    external_id(ecosystem, &format!("package/{}@{}", name, version))
+}
+
+fn dependency_id(ecosystem: &str, from: (&str, &str), to: (&str, &str)) -> uuid::Uuid {
+    external_id(ecosystem, &format!("depends/{}@{}/{}@{}", from.0, from.1, to.0, to.1))
+}
+
+#[derive(Deserialize)]
+struct CargoMetadata {
+    packages: Vec<CargoPackage>,
+    #[serde(default)]
+    workspace_members: Vec<String>,
+    resolve: Option<CargoResolve>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    id: String,
+    name: String,
+    version: String,
+    source: Option<String>,
+    license: Option<String>,
+    description: Option<String>,
+    repository: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<CargoDependency>,
+}
+
+#[derive(Deserialize)]
+struct CargoDependency {
+    name: String,
+    req: String,
+    kind: Option<String>,
+    #[serde(default)]
+    optional: bool,
+}
+
+#[derive(Deserialize)]
+struct CargoResolve {
+    nodes: Vec<CargoNode>,
+}
+
+#[derive(Deserialize)]
+struct CargoNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<CargoNodeDep>,
+}
+
+#[derive(Deserialize)]
+struct CargoNodeDep {
+    pkg: String,
+    #[serde(default)]
+    dep_kinds: Vec<CargoDepKind>,
+}
+
+#[derive(Deserialize)]
+struct CargoDepKind {
+    kind: Option<String>,
+    target: Option<String>,
+}
+
+/// The packages in `cargo metadata --format-version 1` output, linked as resolved. Output made
+/// with `--no-deps` has no resolved graph and gives the workspace members alone.
+pub fn cargo_metadata(json: &str) -> Result<ImportPayload> {
+    let meta: CargoMetadata = serde_json::from_str(json).context("parsing `cargo metadata` output")?;
+    let packages: HashMap<&str, &CargoPackage> = meta.packages.iter().map(|p| (p.id.as_str(), p)).collect();
+    let mut payload = ImportPayload::default();
+    for p in &meta.packages {
+        let mut metadata = HashMap::from([
+            ("name".to_string(), p.name.clone()),
+            ("version".to_string(), p.version.clone()),
+            ("ecosystem".to_string(), "cargo".to_string()),
+        ]);
+        let source = match p.source.as_deref() {
+            None => "local",
+            Some(s) if CRATES_IO.contains(&s) => "crates.io",
+            Some(s) => s,
+        };
+        metadata.insert("source".into(), source.to_string());
+        if meta.workspace_members.contains(&p.id) { metadata.insert("workspace_member".into(), "true".into()); }
+        for (key, value) in [("license", &p.license), ("description", &p.description), ("repository", &p.repository)] {
+            if let Some(v) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) { metadata.insert(key.into(), v.to_string()); }
+        }
+        payload.add_node(package_id("cargo", &p.name, &p.version), PACKAGE_LABEL, metadata);
+    }
+    for node in meta.resolve.iter().flat_map(|r| &r.nodes) {
+        let from = packages.get(node.id.as_str()).ok_or_else(|| anyhow!("resolved package {} is not among the packages", node.id))?;
+        for dep in &node.deps {
+            let to = packages.get(dep.pkg.as_str()).ok_or_else(|| anyhow!("resolved package {} is not among the packages", dep.pkg))?;
+            // A crate can be, say, both a normal and a build dependency
+            let kinds: Vec<&str> = dedup(dep.dep_kinds.iter().map(|k| k.kind.as_deref().unwrap_or("normal")));
+            let declared = from.dependencies.iter().filter(|d| d.name == to.name);
+            let declared = declared.clone().find(|d| kinds.contains(&d.kind.as_deref().unwrap_or("normal"))).or_else(|| declared.clone().next());
+            let kind = if kinds.is_empty() { declared.and_then(|d| d.kind.as_deref()).unwrap_or("normal").to_string() } else { kinds.join(", ") };
+            let mut metadata = HashMap::from([("kind".to_string(), kind)]);
+            let targets = dedup(dep.dep_kinds.iter().filter_map(|k| k.target.as_deref()));
+            if !targets.is_empty() { metadata.insert("target".into(), targets.join(", ")); }
+            if let Some(d) = declared {
+                metadata.insert("req".into(), d.req.clone());
+                if d.optional { metadata.insert("optional".into(), "true".into()); }
+            }
+            let id = dependency_id("cargo", (&from.name, &from.version), (&to.name, &to.version));
+            payload.add_relationship(id, package_id("cargo", &from.name, &from.version), package_id("cargo", &to.name, &to.version), DEPENDS_LABEL, metadata);
+        }
+    }
+    Ok(payload)
+}
+
+fn dedup<'a>(items: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut out: Vec<&str> = Vec::new();
+    for i in items {
+        if !out.contains(&i) { out.push(i); }
+    }
+    out
+}
+
+#[derive(Deserialize, Default)]
+struct LockEntry {
+    name: Option<String>,
+    version: Option<String>,
+    resolved: Option<String>,
+    license: Option<String>,
+    #[serde(default)]
+    dev: bool,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    link: bool,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+    #[serde(default, rename = "devDependencies")]
+    dev_dependencies: BTreeMap<String, String>,
+    #[serde(default, rename = "optionalDependencies")]
+    optional_dependencies: BTreeMap<String, String>,
+    #[serde(default, rename = "peerDependencies")]
+    peer_dependencies: BTreeMap<String, String>,
+}
+
+// Lockfile version 1 nests the tree instead of listing install locations
+#[derive(Deserialize)]
+struct LegacyEntry {
+    version: Option<String>,
+    resolved: Option<String>,
+    #[serde(default)]
+    dev: bool,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    requires: BTreeMap<String, String>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, LegacyEntry>,
+}
+
+#[derive(Deserialize)]
+struct PackageLock {
+    name: Option<String>,
+    version: Option<String>,
+    packages: Option<BTreeMap<String, LockEntry>>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, LegacyEntry>,
+}
+
+/// The packages in an npm `package-lock.json` (any lockfile version), linked the way Node
+/// resolves them: from the nearest `node_modules` folder up.
+pub fn package_lock(json: &str) -> Result<ImportPayload> {
+    let lock: PackageLock = serde_json::from_str(json).context("parsing package-lock.json")?;
+    let entries = match lock.packages {
+        Some(packages) => packages,
+        None => {
+            let mut entries = BTreeMap::new();
+            // Version 1 lists the root's dependencies but not the root's requirements on them
+            let root = LockEntry {
+                name: lock.name.clone(),
+                version: lock.version.clone(),
+                dependencies: lock.dependencies.iter().filter(|(_, e)| !e.dev).map(|(n, e)| (n.clone(), e.version.clone().unwrap_or_default())).collect(),
+                dev_dependencies: lock.dependencies.iter().filter(|(_, e)| e.dev).map(|(n, e)| (n.clone(), e.version.clone().unwrap_or_default())).collect(),
+                ..Default::default()
+            };
+            entries.insert(String::new(), root);
+            flatten_legacy("", &lock.dependencies, &mut entries);
+            entries
+        }
+    };
+    let root_name = lock.name.as_deref().unwrap_or("root");
+    let name_of = |location: &str, e: &LockEntry| -> String {
+        if let Some(n) = &e.name { return n.clone(); }
+        match location.rsplit_once("node_modules/") {
+            Some((_, name)) => name.to_string(),
+            None if location.is_empty() => root_name.to_string(),
+            None => location.rsplit('/').next().unwrap_or(location).to_string(),
+        }
+    };
+    // Installed packages by location; links (workspace packages) point at their folder
+    let mut resolved: HashMap<&str, (String, String)> = HashMap::new();
+    for (location, e) in &entries {
+        if e.link { continue; }
+        resolved.insert(location, (name_of(location, e), e.version.clone().unwrap_or_else(|| "0.0.0".into())));
+    }
+    for (location, e) in &entries {
+        if !e.link { continue; }
+        if let Some(target) = e.resolved.as_deref().and_then(|t| resolved.get(t).cloned()) { resolved.insert(location, target); }
+    }
+
+    let mut payload = ImportPayload::default();
+    for (location, e) in &entries {
+        if e.link { continue; }
+        let (name, version) = &resolved[location.as_str()];
+        let mut metadata = HashMap::from([
+            ("name".to_string(), name.clone()),
+            ("version".to_string(), version.clone()),
+            ("ecosystem".to_string(), "npm".to_string()),
+        ]);
+        if !location.contains("node_modules/") { metadata.insert("workspace_member".into(), "true".into()); }
+        if let Some(r) = &e.resolved { metadata.insert("resolved".into(), r.clone()); }
+        if let Some(l) = &e.license { metadata.insert("license".into(), l.clone()); }
+        if e.dev { metadata.insert("dev".into(), "true".into()); }
+        if e.optional { metadata.insert("optional".into(), "true".into()); }
+        payload.add_node(package_id("npm", name, version), PACKAGE_LABEL, metadata);
+    }
+    let mut linked = std::collections::HashSet::new();
+    for (location, e) in &entries {
+        if e.link { continue; }
+        let from = &resolved[location.as_str()];
+        let kinds = [("normal", &e.dependencies), ("dev", &e.dev_dependencies), ("optional", &e.optional_dependencies), ("peer", &e.peer_dependencies)];
+        for (kind, deps) in kinds {
+            for (dep, req) in deps {
+                // Missing optional and peer dependencies are normal; so is a dev dependency of
+                // an installed package, which npm does not install
+                let Some(to) = lookup(&resolved, location, dep) else { continue };
+                if !linked.insert((from.clone(), to.clone())) { continue; }
+                let metadata = HashMap::from([("kind".to_string(), kind.to_string()), ("req".to_string(), req.clone())]);
+                let id = dependency_id("npm", (&from.0, &from.1), (&to.0, &to.1));
+                payload.add_relationship(id, package_id("npm", &from.0, &from.1), package_id("npm", &to.0, &to.1), DEPENDS_LABEL, metadata);
+            }
+        }
+    }
+    if payload.is_empty() { bail!("the lockfile lists no packages"); }
+    Ok(payload)
+}
+
+fn flatten_legacy(parent: &str, deps: &BTreeMap<String, LegacyEntry>, out: &mut BTreeMap<String, LockEntry>) {
+    for (name, e) in deps {
+        let location = if parent.is_empty() { format!("node_modules/{}", name) } else { format!("{}/node_modules/{}", parent, name) };
+        out.insert(
+            location.clone(),
+            LockEntry {
+                version: e.version.clone(),
+                resolved: e.resolved.clone(),
+                dev: e.dev,
+                optional: e.optional,
+                dependencies: e.requires.clone(),
+                ..Default::default()
+            },
+        );
+        flatten_legacy(&location, &e.dependencies, out);
+    }
+}
+
+// Where Node finds `dep` when required from `location`: its own `node_modules`, then each
+// enclosing one up to the root's
+fn lookup(resolved: &HashMap<&str, (String, String)>, location: &str, dep: &str) -> Option<(String, String)> {
+    let mut at = location;
+    loop {
+        let candidate = if at.is_empty() { format!("node_modules/{}", dep) } else { format!("{}/node_modules/{}", at, dep) };
+        if let Some(found) = resolved.get(candidate.as_str()) { return Some(found.clone()); }
+        if at.is_empty() { return None; }
+        at = match at.rsplit_once("/node_modules/") {
+            Some((outer, _)) => outer,
+            None => "",
+        };
+    }
+}
@@ -0,0 +1,43 @@
+//! Import from JSON HTTP APIs: GET a feed's URL and map the response with the feed's mapping
+//! (see [`feeds`](crate::persistence::feeds)). Needs the `rest` feature; without it [`fetch`]
+//! fails.
+
+use anyhow::Result;
+
+use crate::persistence::feeds::Feed;
+use crate::persistence::import::ImportPayload;
+
+#[cfg(feature = "rest")]
+mod http {
+    use std::time::Duration;
+
+    use anyhow::{Context, Result};
+
+    use crate::persistence::feeds::Feed;
+
+    // Longest a request may take, connecting and reading included
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    // Largest response read
+    const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+    pub fn get(feed: &Feed) -> Result<String> {
+        let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(TIMEOUT)).build().into();
+        let url = feed.resolved_url()?;
+        let mut req = agent.get(&url).header("Accept", "application/json");
+        for (name, value) in feed.resolved_headers()? { req = req.header(name, value); }
+        let mut resp = req.call().with_context(|| format!("GET {}", feed.url.trim()))?;
+        resp.body_mut().with_config().limit(MAX_BODY).read_to_string().context("reading the response")
+    }
+}
+
+/// Fetch the feed's URL and map the JSON it returns to graph elements.
+pub fn fetch(feed: &Feed) -> Result<ImportPayload> {
+    #[cfg(feature = "rest")]
+    return feed.materialize(&http::get(feed)?);
+    #[cfg(not(feature = "rest"))]
+    {
+        let _ = feed;
+        Err(anyhow::anyhow!("this build has no HTTP import support (feature `rest`)"))
+    }
+}
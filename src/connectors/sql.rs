@@ -0,0 +1,464 @@
+//! Import from a SQL database: Postgres, MySQL/MariaDB or SQLite. Rows become nodes and
+//! relationships in one of two ways:
+//!
+//! - **Queries:** a node query returning one row per node and an edge query returning one row
+//!   per relationship (see [`SqlSource`] for the columns they need).
+//! - **Foreign keys:** every row of the chosen tables becomes a node labelled with its table,
+//!   and every foreign key a relationship from the referencing row to the referenced one.
+//!
+//! Values are read as text; other columns become metadata, and NULLs are left out. Needs the
+//! `sql` feature; without it [`fetch`] fails.
+
+use anyhow::Result;
+
+use crate::persistence::import::ImportPayload;
+
+/// How rows are turned into graph elements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SqlMapping {
+    #[default]
+    Queries,
+    ForeignKeys,
+}
+
+/// Which database to read from and how.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SqlSource {
+    /// e.g. `postgres://user@localhost/shop`, `mysql://user@localhost/shop` or `sqlite://shop.db`
+    pub url: String,
+    /// Used instead of a password in the URL when not empty
+    pub password: String,
+    pub mapping: SqlMapping,
+    /// Queries: one row per node, with an `id` column unique among the nodes and an optional
+    /// `label` column
+    pub node_query: String,
+    /// Queries: one row per relationship, with `source` and `target` columns holding node
+    /// `id`s (`from`/`to` also work) and optional `label` and `id` columns
+    pub edge_query: String,
+    /// Foreign keys: the tables to read, comma-separated; empty for all of them
+    pub tables: String,
+}
+
+impl SqlSource {
+    /// The URL without a password, for showing to the user.
+    pub fn display_url(&self) -> String {
+        let url = self.url.trim();
+        let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+        let end = rest.find('/').unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+        match authority.rsplit_once('@') {
+            Some((user, host)) => {
+                let user = user.split_once(':').map_or(user, |(u, _)| u);
+                format!("{}://{}@{}{}", scheme, user, host, path)
+            }
+            None => url.to_string(),
+        }
+    }
+}
+
+/// The database side, with the `sql` feature.
+#[cfg(feature = "sql")]
+pub mod driver {
+    use std::collections::{BTreeMap, HashMap};
+    use std::str::FromStr;
+
+    use anyhow::{anyhow, bail, Context, Result};
+    use futures_util::TryStreamExt;
+    use sqlx::mysql::{MySqlConnectOptions, MySqlConnection};
+    use sqlx::postgres::{PgConnectOptions, PgConnection};
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+    use sqlx::{Column, ColumnIndex, ConnectOptions, Connection, Database, Decode, Executor, Row, TypeInfo};
+
+    use super::{SqlMapping, SqlSource};
+    use crate::persistence::import::{external_id, ImportPayload};
+
+    // Label for query rows without a `label` column, and relationships without one
+    const UNLABELED_NODE: &str = "Row";
+    const UNLABELED_REL: &str = "RELATED_TO";
+
+    // Rows read between progress reports
+    const REPORT_EVERY: usize = 500;
+
+    impl SqlSource {
+        /// What imported IDs are derived from: the database the rows came from.
+        pub fn key(&self) -> String {
+            format!("sql:{}", self.display_url())
+        }
+    }
+
+    /// A query result as text: `values[row][column]`, `None` for NULL.
+    #[derive(Default)]
+    struct Rows {
+        columns: Vec<String>,
+        values: Vec<Vec<Option<String>>>,
+    }
+
+    impl Rows {
+        fn column(&self, names: &[&str]) -> Option<usize> {
+            names.iter().find_map(|n| self.columns.iter().position(|c| c.eq_ignore_ascii_case(n)))
+        }
+
+        // Non-NULL values outside `skip`
+        fn metadata(&self, row: &[Option<String>], skip: &[usize]) -> HashMap<String, String> {
+            self.columns
+                .iter()
+                .zip(row)
+                .enumerate()
+                .filter(|(i, _)| !skip.contains(i))
+                .filter_map(|(_, (c, v))| Some((c.clone(), v.clone()?)))
+                .collect()
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Dialect {
+        Postgres,
+        MySql,
+        Sqlite,
+    }
+
+    impl Dialect {
+        fn quote(self, ident: &str) -> String {
+            match self {
+                Dialect::MySql => format!("`{}`", ident.replace('`', "``")),
+                _ => format!("\"{}\"", ident.replace('"', "\"\"")),
+            }
+        }
+
+        // (table, column) of every primary key column, in key order
+        fn primary_keys_sql(self) -> &'static str {
+            match self {
+                Dialect::Postgres => "SELECT kcu.table_name, kcu.column_name \
+                    FROM information_schema.table_constraints tc \
+                    JOIN information_schema.key_column_usage kcu \
+                      ON kcu.constraint_schema = tc.constraint_schema AND kcu.constraint_name = tc.constraint_name \
+                    WHERE tc.constraint_type = 'PRIMARY KEY' AND tc.table_schema = current_schema() \
+                    ORDER BY kcu.table_name, kcu.ordinal_position",
+                Dialect::MySql => "SELECT table_name, column_name FROM information_schema.key_column_usage \
+                    WHERE table_schema = DATABASE() AND constraint_name = 'PRIMARY' \
+                    ORDER BY table_name, ordinal_position",
+                Dialect::Sqlite => "SELECT m.name, p.name FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+                    WHERE m.type = 'table' AND p.pk > 0 ORDER BY m.name, p.pk",
+            }
+        }
+
+        // (constraint, table, column, referenced table, referenced column) of every foreign
+        // key column, in key order. SQLite leaves the referenced column NULL for the primary key.
+        fn foreign_keys_sql(self) -> &'static str {
+            match self {
+                Dialect::Postgres => "SELECT kcu.constraint_name, kcu.table_name, kcu.column_name, ref.table_name, ref.column_name \
+                    FROM information_schema.referential_constraints rc \
+                    JOIN information_schema.key_column_usage kcu \
+                      ON kcu.constraint_schema = rc.constraint_schema AND kcu.constraint_name = rc.constraint_name \
+                    JOIN information_schema.key_column_usage ref \
+                      ON ref.constraint_schema = rc.unique_constraint_schema AND ref.constraint_name = rc.unique_constraint_name \
+                     AND ref.ordinal_position = kcu.position_in_unique_constraint \
+                    WHERE kcu.table_schema = current_schema() \
+                    ORDER BY kcu.table_name, kcu.constraint_name, kcu.ordinal_position",
+                Dialect::MySql => "SELECT constraint_name, table_name, column_name, referenced_table_name, referenced_column_name \
+                    FROM information_schema.key_column_usage \
+                    WHERE table_schema = DATABASE() AND referenced_table_name IS NOT NULL \
+                    ORDER BY table_name, constraint_name, ordinal_position",
+                Dialect::Sqlite => "SELECT f.id, m.name, f.\"from\", f.\"table\", f.\"to\" \
+                    FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) f \
+                    WHERE m.type = 'table' ORDER BY m.name, f.id, f.seq",
+            }
+        }
+
+        fn tables_sql(self) -> &'static str {
+            match self {
+                Dialect::Postgres => "SELECT table_name FROM information_schema.tables \
+                    WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY table_name",
+                Dialect::MySql => "SELECT table_name FROM information_schema.tables \
+                    WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE' ORDER BY table_name",
+                Dialect::Sqlite => "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            }
+        }
+    }
+
+    enum Conn {
+        Postgres(PgConnection),
+        MySql(MySqlConnection),
+        Sqlite(SqliteConnection),
+    }
+
+    // Counts rows across queries for progress reports
+    struct Counter<'a> {
+        read: usize,
+        on_rows: &'a mut dyn FnMut(usize) -> Result<()>,
+    }
+
+    impl Counter<'_> {
+        fn row(&mut self) -> Result<()> {
+            self.read += 1;
+            if self.read.is_multiple_of(REPORT_EVERY) { (self.on_rows)(self.read)?; }
+            Ok(())
+        }
+    }
+
+    impl Conn {
+        async fn open(source: &SqlSource) -> Result<Conn> {
+            let url = source.url.trim();
+            let scheme = url.split_once(':').map(|(s, _)| s.to_ascii_lowercase()).unwrap_or_default();
+            let password = Some(source.password.as_str()).filter(|p| !p.is_empty());
+            Ok(match scheme.as_str() {
+                "postgres" | "postgresql" => {
+                    let mut options = PgConnectOptions::from_str(url)?;
+                    if let Some(p) = password { options = options.password(p); }
+                    Conn::Postgres(options.connect().await?)
+                }
+                "mysql" | "mariadb" => {
+                    let mut options = MySqlConnectOptions::from_str(&url.replacen("mariadb:", "mysql:", 1))?;
+                    if let Some(p) = password { options = options.password(p); }
+                    Conn::MySql(options.connect().await?)
+                }
+                // Only read: a mistyped path fails instead of creating an empty database
+                "sqlite" => Conn::Sqlite(SqliteConnectOptions::from_str(url)?.read_only(true).connect().await?),
+                _ => bail!("unsupported database URL {:?}; use postgres://, mysql:// or sqlite://", source.display_url()),
+            })
+        }
+
+        fn dialect(&self) -> Dialect {
+            match self {
+                Conn::Postgres(_) => Dialect::Postgres,
+                Conn::MySql(_) => Dialect::MySql,
+                Conn::Sqlite(_) => Dialect::Sqlite,
+            }
+        }
+
+        async fn rows(&mut self, sql: &str, counter: &mut Counter<'_>) -> Result<Rows> {
+            match self {
+                Conn::Postgres(c) => read_rows::<sqlx::Postgres>(c, sql, counter).await,
+                Conn::MySql(c) => read_rows::<sqlx::MySql>(c, sql, counter).await,
+                Conn::Sqlite(c) => read_rows::<sqlx::Sqlite>(c, sql, counter).await,
+            }
+        }
+
+        async fn close(self) {
+            let closed = match self {
+                Conn::Postgres(c) => c.close().await,
+                Conn::MySql(c) => c.close().await,
+                Conn::Sqlite(c) => c.close().await,
+            };
+            if let Err(e) = closed { log::debug!("Closing the SQL connection: {}", e); }
+        }
+    }
+
+    // A query string without arguments runs unprepared, so Postgres and MySQL send every value
+    // as text (SQLite converts on request); one decoder covers all column types that way.
+    // Binary values that are not UTF-8 read as NULL.
+    async fn read_rows<'c, DB: Database>(conn: &'c mut DB::Connection, sql: &str, counter: &mut Counter<'_>) -> Result<Rows>
+    where
+        &'c mut DB::Connection: Executor<'c, Database = DB>,
+        for<'r> String: Decode<'r, DB>,
+        usize: ColumnIndex<DB::Row>,
+    {
+        let mut out = Rows::default();
+        let mut stream = conn.fetch(sql);
+        while let Some(row) = stream.try_next().await? {
+            if out.columns.is_empty() { out.columns = row.columns().iter().map(|c| c.name().to_string()).collect(); }
+            let values = row
+                .columns()
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let v = row.try_get_unchecked::<Option<String>, _>(i).ok().flatten()?;
+                    // Postgres writes booleans as t/f
+                    Some(match (c.type_info().name(), v.as_str()) {
+                        ("BOOL", "t") => "true".to_string(),
+                        ("BOOL", "f") => "false".to_string(),
+                        _ => v,
+                    })
+                })
+                .collect();
+            out.values.push(values);
+            counter.row()?;
+        }
+        Ok(out)
+    }
+
+    /// Connect, read the rows the source's mapping asks for and turn them into graph elements.
+    pub fn fetch(source: &SqlSource, mut on_rows: impl FnMut(usize) -> Result<()>) -> Result<ImportPayload> {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        rt.block_on(async {
+            let mut conn = Conn::open(source).await.with_context(|| format!("connecting to {}", source.display_url()))?;
+            let mut counter = Counter { read: 0, on_rows: &mut on_rows };
+            let payload = match source.mapping {
+                SqlMapping::Queries => read_queries(&mut conn, source, &mut counter).await,
+                SqlMapping::ForeignKeys => read_foreign_keys(&mut conn, source, &mut counter).await,
+            };
+            conn.close().await;
+            let payload = payload?;
+            (counter.on_rows)(counter.read)?;
+            Ok(payload)
+        })
+    }
+
+    async fn read_queries(conn: &mut Conn, source: &SqlSource, counter: &mut Counter<'_>) -> Result<ImportPayload> {
+        let (node_query, edge_query) = (source.node_query.trim(), source.edge_query.trim());
+        if node_query.is_empty() && edge_query.is_empty() { bail!("enter a node query, an edge query or both"); }
+        let key = source.key();
+        let node_id = |v: &str| external_id(&key, &format!("node/{}", v));
+        let mut payload = ImportPayload::default();
+        if !node_query.is_empty() {
+            let rows = conn.rows(node_query, counter).await.context("running the node query")?;
+            if !rows.values.is_empty() {
+                let i_id = rows.column(&["id"]).ok_or_else(|| anyhow!("the node query returns no `id` column"))?;
+                let i_label = rows.column(&["label"]);
+                for row in &rows.values {
+                    let Some(id) = &row[i_id] else { continue };
+                    let label = i_label.and_then(|i| row[i].clone()).unwrap_or_else(|| UNLABELED_NODE.to_string());
+                    let skip: Vec<usize> = [Some(i_id), i_label].into_iter().flatten().collect();
+                    payload.add_node(node_id(id), label, rows.metadata(row, &skip));
+                }
+            }
+        }
+        if !edge_query.is_empty() {
+            let rows = conn.rows(edge_query, counter).await.context("running the edge query")?;
+            if !rows.values.is_empty() {
+                let i_from = rows.column(&["source", "from"]).ok_or_else(|| anyhow!("the edge query returns no `source` column"))?;
+                let i_to = rows.column(&["target", "to"]).ok_or_else(|| anyhow!("the edge query returns no `target` column"))?;
+                let (i_label, i_id) = (rows.column(&["label", "type"]), rows.column(&["id"]));
+                for row in &rows.values {
+                    let (Some(from), Some(to)) = (&row[i_from], &row[i_to]) else { continue };
+                    let label = i_label.and_then(|i| row[i].clone()).unwrap_or_else(|| UNLABELED_REL.to_string());
+                    // Without an `id`, the same endpoints and label make the same relationship
+                    let rel_key = match i_id.and_then(|i| row[i].as_deref()) {
+                        Some(id) => format!("rel/{}", id),
+                        None => format!("rel/{}", serde_json::json!([from, label, to])),
+                    };
+                    let skip: Vec<usize> = [Some(i_from), Some(i_to), i_label, i_id].into_iter().flatten().collect();
+                    payload.add_relationship(external_id(&key, &rel_key), node_id(from), node_id(to), label, rows.metadata(row, &skip));
+                }
+            }
+        }
+        Ok(payload)
+    }
+
+    // A foreign key: columns of `table` referencing columns of `ref_table`
+    struct ForeignKey {
+        name: String,
+        table: String,
+        columns: Vec<String>,
+        ref_table: String,
+        // Empty when the key references the primary key without naming its columns (SQLite)
+        ref_columns: Vec<String>,
+    }
+
+    async fn read_foreign_keys(conn: &mut Conn, source: &SqlSource, counter: &mut Counter<'_>) -> Result<ImportPayload> {
+        let dialect = conn.dialect();
+        let mut schema = Counter { read: 0, on_rows: &mut |_| Ok(()) };
+        let all = conn.rows(dialect.tables_sql(), &mut schema).await.context("listing the tables")?;
+        let all: Vec<String> = all.values.into_iter().filter_map(|r| r.into_iter().next().flatten()).collect();
+        let wanted: Vec<&str> = source.tables.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+        let tables: Vec<String> = if wanted.is_empty() {
+            all.clone()
+        } else {
+            wanted
+                .iter()
+                .map(|w| all.iter().find(|t| t.eq_ignore_ascii_case(w)).cloned().ok_or_else(|| anyhow!("no table named {:?}", w)))
+                .collect::<Result<_>>()?
+        };
+        if tables.is_empty() { bail!("the database has no tables"); }
+
+        let mut primary_keys: HashMap<String, Vec<String>> = HashMap::new();
+        for r in conn.rows(dialect.primary_keys_sql(), &mut schema).await.context("reading primary keys")?.values {
+            if let [Some(t), Some(c), ..] = r.as_slice() { primary_keys.entry(t.clone()).or_default().push(c.clone()); }
+        }
+        let mut foreign_keys: Vec<ForeignKey> = Vec::new();
+        for r in conn.rows(dialect.foreign_keys_sql(), &mut schema).await.context("reading foreign keys")?.values {
+            let [Some(name), Some(table), Some(column), Some(ref_table), ref_column, ..] = r.as_slice() else { continue };
+            match foreign_keys.last_mut() {
+                Some(fk) if fk.name == *name && fk.table == *table => {
+                    fk.columns.push(column.clone());
+                    fk.ref_columns.extend(ref_column.clone());
+                }
+                _ => foreign_keys.push(ForeignKey {
+                    name: name.clone(),
+                    table: table.clone(),
+                    columns: vec![column.clone()],
+                    ref_table: ref_table.clone(),
+                    ref_columns: ref_column.iter().cloned().collect(),
+                }),
+            }
+        }
+        for fk in &mut foreign_keys {
+            if fk.ref_columns.is_empty() { fk.ref_columns = primary_keys.get(&fk.ref_table).cloned().unwrap_or_default(); }
+        }
+
+        let mut data: BTreeMap<String, Rows> = BTreeMap::new();
+        for t in &tables {
+            let sql = format!("SELECT * FROM {}", dialect.quote(t));
+            data.insert(t.clone(), conn.rows(&sql, counter).await.with_context(|| format!("reading table {}", t))?);
+        }
+        Ok(materialize_tables(&source.key(), &data, &primary_keys, &foreign_keys))
+    }
+
+    // Values of `columns` in a row, if it has them all and none is NULL
+    fn values_of(rows: &Rows, row: &[Option<String>], columns: &[String]) -> Option<Vec<String>> {
+        columns.iter().map(|c| row.get(rows.column(&[c.as_str()])?)?.clone()).collect()
+    }
+
+    fn materialize_tables(
+        key: &str,
+        data: &BTreeMap<String, Rows>,
+        primary_keys: &HashMap<String, Vec<String>>,
+        foreign_keys: &[ForeignKey],
+    ) -> ImportPayload {
+        // A row is known by its table and primary key values; rows of tables without one by
+        // all of their values
+        let row_key = |table: &str, rows: &Rows, row: &[Option<String>]| -> Option<String> {
+            let values = match primary_keys.get(table) {
+                Some(pk) => serde_json::json!(values_of(rows, row, pk)?),
+                None => serde_json::json!(row),
+            };
+            Some(format!("row/{}/{}", table, values))
+        };
+        let mut payload = ImportPayload::default();
+        for (table, rows) in data {
+            for row in &rows.values {
+                let Some(k) = row_key(table, rows, row) else { continue };
+                payload.add_node(external_id(key, &k), table.clone(), rows.metadata(row, &[]));
+            }
+        }
+        for fk in foreign_keys {
+            let Some(rows) = data.get(&fk.table) else { continue };
+            let label = fk.columns.iter().map(|c| c.to_uppercase()).collect::<Vec<_>>().join("_");
+            // Referenced rows by the referenced columns' values, unless those are the primary
+            // key (then the ID follows from the values, even for tables not imported now)
+            let by_primary_key = primary_keys.get(&fk.ref_table) == Some(&fk.ref_columns);
+            let mut targets: HashMap<Vec<String>, String> = HashMap::new();
+            if !by_primary_key && let Some(refs) = data.get(&fk.ref_table) {
+                for row in &refs.values {
+                    if let (Some(v), Some(k)) = (values_of(refs, row, &fk.ref_columns), row_key(&fk.ref_table, refs, row)) { targets.insert(v, k); }
+                }
+            }
+            for row in &rows.values {
+                let (Some(from), Some(values)) = (row_key(&fk.table, rows, row), values_of(rows, row, &fk.columns)) else { continue };
+                let to = if by_primary_key {
+                    format!("row/{}/{}", fk.ref_table, serde_json::json!(values))
+                } else {
+                    match targets.get(&values) {
+                        Some(k) => k.clone(),
+                        None => continue,
+                    }
+                };
+                let id = external_id(key, &format!("fk/{}/{}/{}", fk.table, fk.name, from));
+                payload.add_relationship(id, external_id(key, &from), external_id(key, &to), label.clone(), HashMap::new());
+            }
+        }
+        payload
+    }
+}
+
+/// Connect to the source's database and read its rows as graph elements. `on_rows` hears the
+/// number of rows read every few hundred rows and can stop the import by returning an error.
+pub fn fetch(source: &SqlSource, on_rows: impl FnMut(usize) -> Result<()>) -> Result<ImportPayload> {
+    #[cfg(feature = "sql")]
+    return driver::fetch(source, on_rows);
+    #[cfg(not(feature = "sql"))]
+    {
+        let _ = (source, on_rows);
+        Err(anyhow::anyhow!("this build has no SQL database support (feature `sql`)"))
+    }
+}
@@ -0,0 +1,70 @@
+//! `Graph-Loom export`: write the saved graph to files without opening a window, in the same
+//! formats as File → Export Graph and with the export templates saved in the settings.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::persistence::export::{self, FileFormat};
+use crate::persistence::persist;
+use crate::persistence::settings::AppSettings;
+
+pub const USAGE: &str = "usage: Graph-Loom export <path> [--format json|csv|parquet|arrow] [--template NAME] [--state FILE]";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub path: PathBuf,
+    /// From `--format`, else from the path's extension
+    pub format: FileFormat,
+    /// Export template saved in the settings
+    pub template: Option<String>,
+    /// Saved state to read instead of the autosave
+    pub state: Option<PathBuf>,
+}
+
+/// Parse the arguments after `export`.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut path = None;
+    let mut format = None;
+    let mut template = None;
+    let mut state = None;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| it.next().cloned().ok_or_else(|| format!("{} expects a value\n{}", name, USAGE));
+        match arg.as_str() {
+            "--format" => {
+                let v = value("--format")?;
+                format = Some(FileFormat::parse(&v).ok_or_else(|| format!("unknown format '{}' (expected json, csv, parquet or arrow)\n{}", v, USAGE))?);
+            }
+            "--template" => template = Some(value("--template")?),
+            "--state" => state = Some(PathBuf::from(value("--state")?)),
+            other if other.starts_with("--") => return Err(format!("unknown export option '{}'\n{}", other, USAGE)),
+            other if path.is_none() => path = Some(PathBuf::from(other)),
+            other => return Err(format!("unexpected argument '{}'\n{}", other, USAGE)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("missing the path to export to\n{}", USAGE))?;
+    let format = match format {
+        Some(f) => f,
+        None => path
+            .extension()
+            .and_then(|e| FileFormat::parse(&e.to_string_lossy().to_lowercase()))
+            .ok_or_else(|| format!("cannot tell the format from '{}'; pass --format\n{}", path.display(), USAGE))?,
+    };
+    if format == FileFormat::Json && template.is_some() { return Err(format!("templates shape CSV, Parquet and Arrow tables, not JSON\n{}", USAGE)); }
+    Ok(Options { path, format, template, state })
+}
+
+/// Load the graph and write it; returns the files written.
+pub fn run(opts: &Options) -> Result<Vec<PathBuf>> {
+    let template = match &opts.template {
+        Some(name) => Some(AppSettings::load().unwrap_or_default().export_template(name)?),
+        None => None,
+    };
+    let state = match &opts.state {
+        Some(path) => persist::load_from_path(path)?,
+        None => persist::load_active()?.ok_or_else(|| anyhow!("no saved graph at {}", persist::active_state_path().display()))?,
+    };
+    let (db, _) = state.into_parts();
+    export::write_files(&db, &opts.path, opts.format, template.as_ref())
+}
@@ -1,2 +0,0 @@
-pub mod query_interface;
-pub mod cypher_spec;
\ No newline at end of file
@@ -0,0 +1,42 @@
+//! What the canvas writes on relationships: the label, the value of one metadata key
+//! (e.g. `weight` or `since`), or both; and which metadata the hover tooltip lists.
+
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EdgeText {
+    #[default]
+    Label,
+    Metadata,
+    Both,
+}
+
+/// Every mode with the name shown in the sidebar.
+pub const ALL: [(EdgeText, &str); 3] = [
+    (EdgeText::Label, "Label"),
+    (EdgeText::Metadata, "Metadata value"),
+    (EdgeText::Both, "Label and value"),
+];
+
+/// Text for an edge with `label` and `metadata`. Without a `key` every mode shows the label;
+/// an edge lacking the key shows nothing in metadata mode and only its label otherwise.
+pub fn caption(mode: EdgeText, key: &str, label: &str, metadata: &HashMap<String, String>) -> Option<String> {
+    let value = metadata.get(key).filter(|_| !key.is_empty()).map(|v| format!("{}: {}", key, v));
+    match (mode, value) {
+        (EdgeText::Label, _) => Some(label.to_string()),
+        (_, None) if key.is_empty() => Some(label.to_string()),
+        (EdgeText::Metadata, value) => value,
+        (EdgeText::Both, Some(v)) => Some(format!("{} · {}", label, v)),
+        (EdgeText::Both, None) => Some(label.to_string()),
+    }
+}
+
+/// Up to `max` metadata entries for a relationship tooltip, with the number left out: the
+/// chosen edge `key` first, then the rest by key.
+pub fn key_metadata<'a>(key: &str, metadata: &'a HashMap<String, String>, max: usize) -> (Vec<(&'a str, &'a str)>, usize) {
+    let mut entries: Vec<(&str, &str)> = metadata.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    entries.sort_by_key(|(k, _)| (*k != key, *k));
+    let more = entries.len().saturating_sub(max);
+    entries.truncate(max);
+    (entries, more)
+}
@@ -1,21 +1,64 @@
 #![allow(clippy::collapsible_if)]
 #![allow(clippy::needless_return)]
 #![allow(clippy::excessive_precision)]
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use eframe::egui::{self, Color32, Pos2, Rect, Sense, Stroke, Vec2};
 use uuid::Uuid;
 
-use crate::graph_utils::graph::{GraphDatabase, NodeId};
-use crate::persistence::persist::{self, AppStateFile};
-use crate::persistence::settings::AppSettings;
+use crate::graph_utils::computed::{self, ComputedProperty};
+use crate::graph_utils::derived::{self, DerivedEdge, DerivedEdgeRule};
+use crate::graph_utils::graph::{self, GraphDatabase, NodeId, GRAPH_METADATA_KEYS};
+use crate::graph_utils::generators::{self, Generator, Model};
+use crate::graph_utils::memory::{self, MemoryReport, Usage};
+use crate::graph_utils::permissions::{self, Role};
+use crate::graph_utils::schema::Schema;
+use crate::graph_utils::templates::Template;
+use crate::graph_utils::validation::{self, PropertyRule, RuleViolation};
+use crate::persistence::persist::{self, Annotation, AnnotationShape, AppStateFile};
+use crate::persistence::snapshot::{SaveSlot, Snapshot};
+use crate::persistence::feeds::{Feed, FeedMapping};
+use crate::persistence::import;
+use crate::persistence::merge::{self, MatchBy, MergePlan, Resolution};
+use crate::persistence::watch::{self, FolderWatcher};
+use crate::gui::edge_text::{self, EdgeText};
+use crate::gui::history::{History, RestorePoints, MASS_MUTATION_THRESHOLD};
+use crate::gui::lanes::{LaneAxis, Swimlanes};
+use crate::gui::live_graph::LiveGraph;
+use crate::gui::navigation::{self, CameraMove, Inertia};
+use crate::gui::palette;
+use crate::gui::physics;
+use crate::gui::result_view::ResultView;
+use crate::gql::views::{self, GraphView};
+use crate::gui::schema_view;
+use crate::gui::status::{FrameStats, Phase};
+use crate::gui::session::{PrefsTab, SessionState, SidebarMode, WindowGeometry};
+use crate::gui::tasks::{self, Progress, Task};
+use crate::gui::tour::{Tour, TourTarget};
+use crate::persistence::settings::{AppSettings, IpFilter, RecentFile};
+use crate::gql::procedures;
+use crate::gql::reference;
 use crate::gql::query_interface::{self, QueryResultRow};
-use crate::api::{self, ApiRequest};
+use crate::api::{self, sync, AdminTask, ApiRequest, ApiTask};
+use crate::connectors::markdown;
+use crate::connectors::neo4j::{self, Neo4jSource};
+use crate::connectors::packages;
+use crate::connectors::rest;
+use crate::connectors::sql::{self, SqlMapping, SqlSource};
+use crate::gui::tray::{TrayAction, TrayMenu, TrayStatus};
+use crate::persistence::backup::BackupSchedule;
+use crate::persistence::export_schedule::{self, ExportSchedule};
+use crate::persistence::result_export::{self, ResultFormat};
+use crate::persistence::export::{ExportChunks, ExportFormat, FileFormat};
+use crate::persistence::columnar::{self, ColumnarFormat, ExportTemplate, RestMetadata};
+use crate::persistence::bundle::{self, Bundle, BundleSettings};
+use crate::persistence::lock::{self, LockOwner, StateLock};
 
 // Export matched nodes
-fn export_nodes_json(db: &GraphDatabase, ids: &[NodeId], path: &std::path::Path) -> std::io::Result<()> {
+fn export_nodes_json(nodes: &[graph::Node], path: &std::path::Path) -> std::io::Result<()> {
     use std::fs::File;
     use std::io::Write;
     #[derive(serde::Serialize)]
@@ -23,13 +66,14 @@ fn export_nodes_json(db: &GraphDatabase, ids: &[NodeId], path: &std::path::Path)
         id: &'a Uuid,
         label: &'a str,
         metadata: &'a HashMap<String, String>,
+        description: &'a str,
+        created_at: &'a str,
+        updated_at: &'a str,
     }
-    let mut out: Vec<NodeOut> = Vec::with_capacity(ids.len());
-    for id in ids {
-        if let Some(n) = db.get_node(*id) {
-            out.push(NodeOut { id: &n.id, label: &n.label, metadata: &n.metadata });
-        }
-    }
+    let out: Vec<NodeOut> = nodes
+        .iter()
+        .map(|n| NodeOut { id: &n.id, label: &n.label, metadata: &n.metadata, description: &n.description, created_at: &n.created_at, updated_at: &n.updated_at })
+        .collect();
     if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
     let f = File::create(path)?;
     serde_json::to_writer_pretty(f, &out).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
@@ -39,122 +83,19 @@ fn export_nodes_json(db: &GraphDatabase, ids: &[NodeId], path: &std::path::Path)
     Ok(())
 }
 
-fn export_nodes_csv(db: &GraphDatabase, ids: &[NodeId], path: &std::path::Path) -> std::io::Result<()> {
+fn export_nodes_csv(nodes: &[graph::Node], path: &std::path::Path) -> std::io::Result<()> {
     if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
     let mut wtr = csv::Writer::from_path(path)?;
-    // headers: id,label,metadata_json
-    wtr.write_record(["id", "label", "metadata_json"]) ?;
-    for id in ids {
-        if let Some(n) = db.get_node(*id) {
-            let meta_json = serde_json::to_string(&n.metadata).unwrap_or_else(|_| "{}".into());
-            wtr.write_record(&[n.id.to_string(), n.label.clone(), meta_json])?;
-        }
+    // headers: id,label,metadata_json,created_at,updated_at,description
+    wtr.write_record(["id", "label", "metadata_json", "created_at", "updated_at", "description"]) ?;
+    for n in nodes {
+        let meta_json = serde_json::to_string(&n.metadata).unwrap_or_else(|_| "{}".into());
+        wtr.write_record(&[n.id.to_string(), n.label.clone(), meta_json, n.created_at.clone(), n.updated_at.clone(), n.description.clone()])?;
     }
     wtr.flush()?;
     Ok(())
 }
 
-// Helpers for exporting the entire graph
-fn export_graph_json(db: &GraphDatabase, path: &std::path::Path) -> std::io::Result<()> {
-    use std::fs::File;
-    use std::io::Write;
-    #[derive(serde::Serialize)]
-    struct RelRef<'a> {
-        rel_id: &'a uuid::Uuid,
-        label: &'a str,
-        peer: &'a uuid::Uuid,
-        direction: &'a str, // "out" or "in"
-    }
-    #[derive(serde::Serialize)]
-    struct NodeOut<'a> {
-        id: &'a uuid::Uuid,
-        label: &'a str,
-        metadata: &'a HashMap<String, String>,
-        out_rels: Vec<RelRef<'a>>,
-        in_rels: Vec<RelRef<'a>>,
-    }
-    #[derive(serde::Serialize)]
-    struct RelOut<'a> {
-        id: &'a uuid::Uuid,
-        from: &'a uuid::Uuid,
-        to: &'a uuid::Uuid,
-        label: &'a str,
-        metadata: &'a HashMap<String, String>,
-    }
-    #[derive(serde::Serialize)]
-    struct GraphOut<'a> {
-        nodes: Vec<NodeOut<'a>>,
-        relationships: Vec<RelOut<'a>>,
-    }
-
-    let mut node_outs: Vec<NodeOut> = Vec::with_capacity(db.nodes.len());
-    for (_id, node) in db.nodes.iter() {
-        let mut out_rels: Vec<RelRef> = Vec::new();
-        let mut in_rels: Vec<RelRef> = Vec::new();
-        for rel in db.relationships.values() {
-            if rel.from_node == node.id {
-                out_rels.push(RelRef { rel_id: &rel.id, label: &rel.label, peer: &rel.to_node, direction: "out" });
-            } else if rel.to_node == node.id {
-                in_rels.push(RelRef { rel_id: &rel.id, label: &rel.label, peer: &rel.from_node, direction: "in" });
-            }
-        }
-        node_outs.push(NodeOut { id: &node.id, label: &node.label, metadata: &node.metadata, out_rels, in_rels });
-    }
-    let mut rel_outs: Vec<RelOut> = Vec::with_capacity(db.relationships.len());
-    for (_rid, rel) in db.relationships.iter() {
-        rel_outs.push(RelOut { id: &rel.id, from: &rel.from_node, to: &rel.to_node, label: &rel.label, metadata: &rel.metadata });
-    }
-    if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
-    let f = File::create(path)?;
-    let g = GraphOut { nodes: node_outs, relationships: rel_outs };
-    serde_json::to_writer_pretty(f, &g).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    // newline at end
-    let mut f2 = std::fs::OpenOptions::new().append(true).open(path)?;
-    let _ = f2.write_all(b"\n");
-    Ok(())
-}
-
-fn export_graph_csv(db: &GraphDatabase, base_path: &std::path::Path) -> std::io::Result<(std::path::PathBuf, std::path::PathBuf)> {
-    // Derive nodes/relationships file paths from base
-    let parent = base_path.parent().unwrap_or_else(|| std::path::Path::new("."));
-    std::fs::create_dir_all(parent)?;
-    let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("graph");
-    let nodes_path = parent.join(format!("{}_nodes.csv", stem));
-    let rels_path = parent.join(format!("{}_relationships.csv", stem));
-    // Write nodes CSV: id,label,metadata_json,out_rels_json,in_rels_json
-    {
-        let mut wtr = csv::Writer::from_path(&nodes_path)?;
-        wtr.write_record(["id", "label", "metadata_json", "out_rels_json", "in_rels_json"])?;
-        for (_id, n) in db.nodes.iter() {
-            let meta_json = serde_json::to_string(&n.metadata).unwrap_or_else(|_| "{}".into());
-            let mut out_refs: Vec<serde_json::Value> = Vec::new();
-            let mut in_refs: Vec<serde_json::Value> = Vec::new();
-            for rel in db.relationships.values() {
-                if rel.from_node == n.id {
-                    out_refs.push(serde_json::json!({"rel_id": rel.id, "label": rel.label, "to": rel.to_node}));
-                } else if rel.to_node == n.id {
-                    in_refs.push(serde_json::json!({"rel_id": rel.id, "label": rel.label, "from": rel.from_node}));
-                }
-            }
-            let out_json = serde_json::to_string(&out_refs).unwrap_or_else(|_| "[]".into());
-            let in_json = serde_json::to_string(&in_refs).unwrap_or_else(|_| "[]".into());
-            wtr.write_record(&[n.id.to_string(), n.label.clone(), meta_json, out_json, in_json])?;
-        }
-        wtr.flush()?;
-    }
-    // Write relationships CSV: id,from,to,label,metadata_json
-    {
-        let mut wtr = csv::Writer::from_path(&rels_path)?;
-        wtr.write_record(["id", "from", "to", "label", "metadata_json"])?;
-        for (_rid, r) in db.relationships.iter() {
-            let meta_json = serde_json::to_string(&r.metadata).unwrap_or_else(|_| "{}".into());
-            wtr.write_record(&[r.id.to_string(), r.from_node.to_string(), r.to_node.to_string(), r.label.clone(), meta_json])?;
-        }
-        wtr.flush()?;
-    }
-    Ok((nodes_path, rels_path))
-}
-
 // Style for toast notifications
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -163,18 +104,134 @@ enum NoticeStyle {
     Prominent,
 }
 
+// Label of the layout task; at most one runs at a time
+const LAYOUT_TASK: &str = "Laying out graph";
+// Label of the autosave task; the next autosave waits for it
+const AUTOSAVE_TASK: &str = "Autosave";
+// Start of the labels of Export Matches tasks, whose outcome shows in the console
+const QUERY_EXPORT_TASK: &str = "Exporting query results";
+
+// Tools → Generate Graph refuses sizes above this; the canvas is unusable well before
+const MAX_GENERATED_NODES: usize = 20_000;
+
+// What a background task hands back to the UI thread
+enum TaskOutput {
+    // Files merged into a copy of the graph taken at `generation` (an empty one for `replace`);
+    // `payload` holds the files that merged, to merge again if the graph changed meanwhile
+    Import { paths: Vec<std::path::PathBuf>, replace: bool, payload: import::ImportPayload, db: GraphDatabase, summary: import::ImportSummary, errors: Vec<String>, generation: u64 },
+    // Notice describing the written files
+    Export(String),
+    // The same for an Export Matches file in the console
+    QueryExport(String),
+    // Files a scheduled export replaced
+    ScheduledExport(Vec<std::path::PathBuf>),
+    // An autosave of the graph as of `generation`; no path when a newer save got there first
+    Saved { generation: u64, path: Option<std::path::PathBuf>, style: NoticeStyle },
+    // Target positions; `full` re-places every node, otherwise only nodes without a position
+    Layout { positions: HashMap<NodeId, Pos2>, full: bool, rect: Rect },
+    // A console query run against a copy of the graph taken at `generation`
+    Query { query: String, outcome: query_interface::QueryOutcome, db: GraphDatabase, generation: u64 },
+    // Elements read from another system (`source`) and merged into a copy of the graph taken
+    // at `generation`; `payload` is kept to merge again if the graph changed meanwhile
+    Connector { source: String, payload: import::ImportPayload, db: GraphDatabase, summary: import::ImportSummary, generation: u64 },
+    // An import to show in the Import Preview window before it is committed
+    Preview(Box<ImportPreview>),
+}
+
+// An import merged into a copy of the graph taken at `generation` but not applied until the
+// Import Preview window commits it; `payload` is kept to merge again if the graph changed
+struct ImportPreview {
+    // Files read, or empty for a connector
+    paths: Vec<std::path::PathBuf>,
+    source: String,
+    payload: import::ImportPayload,
+    db: GraphDatabase,
+    report: import::ImportReport,
+    generation: u64,
+}
+
+// Hover text of the Preview… buttons next to the importers' Import buttons
+const PREVIEW_HINT: &str = "Show what the import would create or merge and what it would leave out, then commit or discard it";
+
+// The "Match existing nodes" row shared by the import windows; true when `rule` changed
+fn import_match_ui(ui: &mut egui::Ui, rule: &mut import::ImportMatch) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Match existing nodes by");
+        changed |= ui
+            .add(egui::TextEdit::singleline(&mut rule.key).hint_text("UUID only").desired_width(110.0))
+            .on_hover_text("Metadata key such as email or name. Incoming nodes whose UUID is new match the oldest node with the same value.")
+            .changed();
+        ui.add_enabled_ui(rule.is_keyed(), |ui| {
+            egui::ComboBox::from_id_salt(ui.id().with("import_on_match"))
+                .selected_text(rule.on_match.name())
+                .show_ui(ui, |ui| {
+                    for m in import::OnMatch::ALL { changed |= ui.selectable_value(&mut rule.on_match, m, m.name()).changed(); }
+                })
+                .response
+                .on_hover_text("What to do with a matched node: leave it, add the incoming metadata to it, or add the incoming node next to it");
+        });
+    });
+    changed
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum SelectedItem {
     Node(NodeId),
     Rel(Uuid),
 }
 
+// Shape the canvas draws on the next click or drag while annotating
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum AnnotationTool {
+    Text,
+    Rect,
+    Arrow,
+}
+
+// File formats of File → Export Graph; all but JSON write a nodes and a relationships file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum GraphExportKind {
+    Json,
+    Csv,
+    Parquet,
+    Arrow,
+    GraphMl,
+}
+
+impl GraphExportKind {
+    const ALL: [GraphExportKind; 5] = [GraphExportKind::Json, GraphExportKind::Csv, GraphExportKind::Parquet, GraphExportKind::Arrow, GraphExportKind::GraphMl];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GraphExportKind::Json => "JSON",
+            GraphExportKind::Csv => "CSV",
+            GraphExportKind::Parquet => "Parquet",
+            GraphExportKind::Arrow => "Arrow",
+            GraphExportKind::GraphMl => "GraphML",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            GraphExportKind::Json => "json",
+            GraphExportKind::Csv => "csv",
+            GraphExportKind::Parquet => ColumnarFormat::Parquet.extension(),
+            GraphExportKind::Arrow => ColumnarFormat::ArrowIpc.extension(),
+            GraphExportKind::GraphMl => "graphml",
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum PickTarget {
     From,
     To,
     // Used when creating a brand-new node and pre-linking it to an existing node
     NewNodeTarget,
+    // A new start or end for an existing relationship
+    RelFrom(Uuid),
+    RelTo(Uuid),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -183,20 +240,8 @@ enum NewNodeRelDir {
     ExistingToNew,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum SidebarMode {
-    Tooling,
-    Query,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum PrefsTab {
-    App,
-    Api,
-}
-
 pub struct GraphApp {
-    db: GraphDatabase,
+    db: LiveGraph,
     node_positions: HashMap<NodeId, Pos2>,
     // Per-node velocities (for smooth, damped motion)
     node_velocities: HashMap<NodeId, Vec2>,
@@ -230,12 +275,91 @@ pub struct GraphApp {
     // Creation forms state
     create_node_label: String,
     create_node_meta: Vec<(String, String)>,
+    // Quick Create: one node per line, optionally chained by a relationship
+    quick_create_text: String,
+    quick_create_label: String,
+    quick_create_link: bool,
+    quick_create_link_label: String,
     create_rel_label: String,
     create_rel_from: Option<NodeId>,
     create_rel_to: Option<NodeId>,
     create_rel_meta: Vec<(String, String)>,
     create_rel_display_key: String,
     pick_target: Option<PickTarget>,
+    // Searchable alternative to picking on the canvas: the slot being filled and the filter
+    node_picker: Option<PickTarget>,
+    node_picker_filter: String,
+    // Matches for (filter, graph generation), so big graphs are not searched every frame
+    node_picker_matches: Option<(String, u64, Vec<NodeId>)>,
+    // Canvas annotations (saved with the layout) and the drawing tool state
+    annotations: Vec<Annotation>,
+    show_annotations: bool,
+    annotation_tool: Option<AnnotationTool>,
+    annotation_text: String,
+    annotation_color: Color32,
+    // World position where the current rectangle/arrow drag began
+    annotation_drag_start: Option<Pos2>,
+    selected_annotation: Option<Uuid>,
+    // UI scale last handed to egui; Ctrl +/- zoom stays in effect until the preference changes
+    applied_ui_scale: Option<f32>,
+    // Tools → Generate Graph; each model keeps its own sizes while switching between them
+    show_generator_window: bool,
+    generator_choice: usize,
+    generator_models: [Model; 4],
+    generator_labels: String,
+    generator_relationship: String,
+    generator_seed: u64,
+    // Help → Query Reference, built when opened; None while the window is closed
+    query_reference: Option<Vec<reference::Section>>,
+    query_reference_filter: String,
+    // Schema inferred when View → Schema View was opened (or last refreshed)
+    schema_view: Option<Schema>,
+    // Memory use measured when View → Memory Usage was opened (or last refreshed or compacted)
+    memory_report: Option<MemoryReport>,
+    // Tools → Peer Sync: on while `peer_sync` is set; the form connects to another instance
+    peer_sync: Option<sync::PeerSync>,
+    show_sync_window: bool,
+    sync_url: String,
+    sync_key: String,
+    // Name shown to the other participants
+    sync_name: String,
+    // File → Import From → Neo4j
+    show_neo4j_window: bool,
+    neo4j_source: Neo4jSource,
+    // File → Import From → SQL Database
+    show_sql_window: bool,
+    sql_source: SqlSource,
+    // File → Import From → Markdown Vault: the folder typed in the form
+    show_vault_window: bool,
+    vault_dir: String,
+    // File → Import From → Package Dependencies: the lockfile, manifest or folder typed in the form
+    show_packages_window: bool,
+    packages_path: String,
+    // File → Import From → JSON API Feeds: the feed being edited, when each feed last ran this
+    // session (scheduled feeds are checked every few seconds) and the feeds from an opened file
+    // the user allowed to run on their timer, with the URL they were shown
+    show_feeds_window: bool,
+    feed_draft: Option<Feed>,
+    feed_last_run: HashMap<Uuid, Instant>,
+    feeds_allowed: HashMap<Uuid, String>,
+    // The import shown in the Import Preview window
+    import_preview: Option<Box<ImportPreview>>,
+    last_feed_check: Instant,
+    // Guided tour in progress and where its target widgets were drawn this frame
+    tour: Option<Tour>,
+    tour_anchors: HashMap<TourTarget, Rect>,
+    // Frame rate and build time shown in the status bar
+    frame_stats: FrameStats,
+    // Glide after a quick background drag (kinetic panning)
+    pan_inertia: Inertia,
+    // Eased view change from a navigation command (fit, reset, reveal)
+    camera: Option<CameraMove>,
+    // Last normal (not maximized) window geometry and whether the window is maximized
+    window_geometry: Option<WindowGeometry>,
+    window_maximized: bool,
+    // Sidebar scroll offsets seen last frame, and restored ones still to apply
+    scroll_offsets: BTreeMap<String, f32>,
+    pending_scroll: BTreeMap<String, f32>,
     // Preemptive relationship when creating a new node
     create_node_rel_enabled: bool,
     create_node_rel_direction: NewNodeRelDir,
@@ -247,9 +371,17 @@ pub struct GraphApp {
     node_meta_new_kv: HashMap<NodeId, (String, String)>,
     rel_label_edits: HashMap<Uuid, String>,
     rel_meta_new_kv: HashMap<Uuid, (String, String)>,
+    // Metadata being edited as JSON text; present while the window's JSON tab is open
+    node_meta_json: HashMap<NodeId, String>,
+    rel_meta_json: HashMap<Uuid, String>,
+    // Unsaved description edits per details window
+    node_desc_edits: HashMap<NodeId, String>,
+    rel_desc_edits: HashMap<Uuid, String>,
     // Bulk edit / multi-select state
     multi_select_active: bool,
     multi_selected_nodes: HashSet<NodeId>,
+    // Relationships in the bulk selection: those the rectangle fully encloses, and clicked edges
+    multi_selected_rels: HashSet<Uuid>,
     // Rectangle (rubber-band) selection while in multi-select mode
     rect_select_start: Option<Pos2>,
     rect_select_current: Option<Pos2>,
@@ -263,17 +395,33 @@ pub struct GraphApp {
     query_text: String,
     query_history: Vec<String>,
     query_output: Vec<String>,
+    // Rows the last query returned, for Export Matches → Export Result Rows
+    query_rows: Vec<QueryResultRow>,
+    // Lint warnings for the query text, and the text they were computed for
+    query_lint: Vec<String>,
+    query_lint_for: String,
     last_query_error: Option<String>,
     // Query matches highlighting
     query_selected_nodes: HashSet<NodeId>,
     query_selected_rels: HashSet<Uuid>,
+    // Canvas limited to the matches, and the graph generation it last followed
+    result_view: Option<ResultView>,
+    result_view_generation: u64,
+    // Output line index -> path (nodes, relationships) for path rows; clicking a line highlights that path
+    query_output_paths: HashMap<usize, (Vec<NodeId>, Vec<Uuid>)>,
+    query_active_path: Option<usize>,
     // Export options for query matches
     query_export_is_json: bool,
     query_export_path: String,
     query_export_status: Option<String>,
     // Export entire graph modal
     show_export_all_window: bool,
-    export_all_is_json: bool,
+    export_all_kind: GraphExportKind,
+    // Export only the bulk selection: its nodes and relationships, with their endpoints
+    export_selection_only: bool,
+    // Settings export template shaping the tables, and the one being edited
+    export_all_template: Option<String>,
+    export_template_draft: Option<ExportTemplate>,
     export_all_path: String,
     export_all_status: Option<String>,
     // Query suggestions
@@ -289,6 +437,11 @@ pub struct GraphApp {
     _cluster_converge_strength: f32,
     gravity_enabled: bool,
     gravity_strength: f32,
+    // Swimlanes by a metadata key, kept in step with the graph as of `lanes_generation`
+    swimlanes: Option<Swimlanes>,
+    lanes_generation: u64,
+    lane_key: String,
+    lane_axis: LaneAxis,
     // Center-of-mass (COM) local gravity settings
     com_gravity_radius: f32,         // within this radius, prefer attraction to local COM
     com_gravity_min_neighbors: usize, // minimum nearby nodes to switch from global to local COM
@@ -303,8 +456,14 @@ pub struct GraphApp {
     edge_label_min_zoom: f32,
     edge_label_count_threshold: usize,
     edge_label_bg_alpha: u8,
+    // What edges show, and the metadata key whose value they show
+    edge_text: EdgeText,
+    edge_meta_key: String,
     // Focus/hover state for dimming/highlighting
     hover_node: Option<NodeId>,
+    hover_rel: Option<Uuid>,
+    // Peer and relationship of the Connections row under the pointer in a node window
+    connection_preview: Option<(NodeId, Uuid)>,
     // Transient zoom HUD (show current zoom briefly when scrolling)
     zoom_hud_until: Option<Instant>,
     // App settings and Preferences UI state
@@ -319,19 +478,110 @@ pub struct GraphApp {
     prefs_tab: PrefsTab,
     // Preferences: API log directory override editor buffer
     prefs_api_log_override_str: String,
+    // Preferences: watch folder override editor buffer
+    prefs_watch_folder_str: String,
+    // Preferences: IP filter editor buffers, one entry per line
+    // (HTTP allow, HTTP deny, gRPC allow, gRPC deny)
+    prefs_ip_filter_strs: [String; 4],
+    // API roles being edited: name, key, then the labels it may create, edit and delete
+    prefs_role_strs: Vec<[String; 5]>,
+    // Settings menu: name buffer for saving the current settings as a profile
+    new_profile_name: String,
     // API server runtime
     api_rx: Option<Receiver<ApiRequest>>,
     api_running: bool,
+    // Servers stopped from the tray; settings still say enabled
+    api_paused: bool,
+    // Tray menu (set by the binary) and the actions its event thread forwards
+    tray: Option<TrayMenu>,
+    tray_rx: Option<Receiver<TrayAction>>,
+    // When the graph was last written to disk in this session, for the tray status
+    last_saved_at: Option<Instant>,
+    // Orders writes of the state file between autosaves on worker threads and saves here
+    save_slot: SaveSlot,
     // Prevention for immediate re-open loop
     last_background_time: Option<Instant>,
     first_focused_observed: Option<Instant>,
+    // Watch-folder auto-import (None when disabled or the folder could not be created)
+    folder_watcher: Option<FolderWatcher>,
+    // Files dropped onto the window awaiting the merge-or-replace prompt
+    pending_drop_files: Vec<std::path::PathBuf>,
+    // Logs window
+    show_logs_window: bool,
+    logs_filter: String,
+    logs_min_level: log::Level,
+    logs_api_only: bool,
+    // State recovered from a crash on the previous run, awaiting restore/discard
+    pending_recovery: Option<AppStateFile>,
+    // Advisory lock on the active state file; while another process holds it the graph is
+    // read-only (`read_only_reason`) and `lock_conflict` drives the prompt
+    state_lock: Option<StateLock>,
+    lock_conflict: Option<LockOwner>,
+    read_only_reason: Option<String>,
+    last_lock_heartbeat: Instant,
+    // Scheduled versioned snapshots (Preferences > Backups), checked once a minute
+    backup_schedule: BackupSchedule,
+    last_backup_check: Instant,
+    // Scheduled exports for downstream pipelines (Preferences > Scheduled Export)
+    export_schedule: ExportSchedule,
+    last_export_check: Instant,
+    // Undo/redo history and its panel
+    history: History,
+    // Copies of the graph taken before destructive operations (Edit → Restore Last Checkpoint)
+    restore_points: RestorePoints,
+    // Background tasks shown in the status bar, and the context used to wake the UI when one ends
+    tasks: Vec<Task<TaskOutput>>,
+    repaint_ctx: Option<egui::Context>,
+    // Bumped on every change; results computed on an older copy of the graph are not applied
+    graph_generation: u64,
+    show_history_window: bool,
+    // Merge Graph dialog: source file, node matching and the plan under review
+    show_merge_window: bool,
+    merge_path: String,
+    merge_by_key: bool,
+    merge_key: String,
+    merge_plan: Option<MergePlan>,
+    merge_error: Option<String>,
+    // Export Bundle / Open Bundle dialog; `bundle_opening` picks which of the two it is
+    show_bundle_window: bool,
+    bundle_opening: bool,
+    bundle_path: String,
+    bundle_apply_settings: bool,
+    bundle_error: Option<String>,
+    // Graph Properties dialog: editable copy of the graph metadata
+    show_graph_props_window: bool,
+    graph_props_edit: Vec<(String, String)>,
+    // Relationship uniqueness constraints and the last audit result
+    show_constraints_window: bool,
+    constraints_audit: Option<Vec<String>>,
+    show_rules_window: bool,
+    // Property Rules form: label, key and the rule written as in db.constraints.setProperty
+    rule_form: (String, String, String),
+    rules_report: Option<Vec<RuleViolation>>,
+    // Computed property form: label, key, formula and seconds between refreshes (0: on change)
+    computed_form: (String, String, String, u64),
+    // Derived edge rules: form (relationship label, key, node label, write into the graph) and
+    // the edges of virtual rules as of the last refresh, drawn dashed
+    show_derived_window: bool,
+    derived_form: (String, String, String, bool),
+    derived_virtual: Vec<(String, DerivedEdge)>,
+    show_derived_virtual: bool,
+    // Named views window and its form: name, read-only query and the key to roll up by
+    show_views_window: bool,
+    view_form: (String, String, String),
+    // New From Template gallery
+    show_template_window: bool,
+    template_choice: usize,
+    template_ba_nodes: usize,
+    template_ba_edges: usize,
+    template_ba_seed: u64,
 }
 
 impl GraphApp {
     pub fn new(db: GraphDatabase) -> Self {
         let settings = AppSettings::load().unwrap_or_default();
         let mut s = Self {
-            db,
+            db: db.into(),
             node_positions: HashMap::new(),
             node_velocities: HashMap::new(),
             converge_start: Some(Instant::now()),
@@ -355,12 +605,65 @@ impl GraphApp {
             open_rel_windows: BTreeSet::new(),
             create_node_label: String::new(),
             create_node_meta: vec![],
+            quick_create_text: String::new(),
+            quick_create_label: "Idea".into(),
+            quick_create_link: false,
+            quick_create_link_label: "NEXT".into(),
             create_rel_label: String::new(),
             create_rel_from: None,
             create_rel_to: None,
             create_rel_meta: vec![],
             create_rel_display_key: String::new(),
             pick_target: None,
+            node_picker: None,
+            node_picker_filter: String::new(),
+            node_picker_matches: None,
+            annotations: Vec::new(),
+            show_annotations: true,
+            annotation_tool: None,
+            annotation_text: String::new(),
+            annotation_color: Color32::from_rgb(255, 220, 120),
+            annotation_drag_start: None,
+            selected_annotation: None,
+            applied_ui_scale: None,
+            show_generator_window: false,
+            generator_choice: 0,
+            generator_models: Model::ALL,
+            generator_labels: "Node".to_string(),
+            generator_relationship: "LINKS".to_string(),
+            generator_seed: 42,
+            query_reference: None,
+            query_reference_filter: String::new(),
+            schema_view: None,
+            memory_report: None,
+            peer_sync: None,
+            show_sync_window: false,
+            sync_url: String::new(),
+            sync_key: String::new(),
+            show_neo4j_window: false,
+            neo4j_source: Neo4jSource::default(),
+            show_sql_window: false,
+            sql_source: SqlSource::default(),
+            show_vault_window: false,
+            vault_dir: String::new(),
+            show_packages_window: false,
+            packages_path: String::new(),
+            show_feeds_window: false,
+            feed_draft: None,
+            feed_last_run: HashMap::new(),
+            feeds_allowed: HashMap::new(),
+            import_preview: None,
+            last_feed_check: Instant::now(),
+            sync_name: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "Guest".to_string()),
+            tour: None,
+            tour_anchors: HashMap::new(),
+            frame_stats: FrameStats::default(),
+            pan_inertia: Inertia::default(),
+            camera: None,
+            window_geometry: None,
+            window_maximized: false,
+            scroll_offsets: BTreeMap::new(),
+            pending_scroll: BTreeMap::new(),
             create_node_rel_enabled: false,
             create_node_rel_direction: NewNodeRelDir::NewToExisting,
             create_node_rel_label: String::from("REL"),
@@ -370,8 +673,13 @@ impl GraphApp {
             node_meta_new_kv: HashMap::new(),
             rel_label_edits: HashMap::new(),
             rel_meta_new_kv: HashMap::new(),
+            node_meta_json: HashMap::new(),
+            rel_meta_json: HashMap::new(),
+            node_desc_edits: HashMap::new(),
+            rel_desc_edits: HashMap::new(),
             multi_select_active: false,
             multi_selected_nodes: HashSet::new(),
+            multi_selected_rels: HashSet::new(),
             rect_select_start: None,
             rect_select_current: None,
             bulk_add_key: String::new(),
@@ -382,14 +690,24 @@ impl GraphApp {
             query_text: String::new(),
             query_history: Vec::new(),
             query_output: Vec::new(),
+            query_rows: Vec::new(),
+            query_lint: Vec::new(),
+            query_lint_for: String::new(),
             last_query_error: None,
             query_selected_nodes: HashSet::new(),
             query_selected_rels: HashSet::new(),
+            result_view: None,
+            result_view_generation: 0,
+            query_output_paths: HashMap::new(),
+            query_active_path: None,
             query_export_is_json: true,
             query_export_path: String::new(),
             query_export_status: None,
             show_export_all_window: false,
-            export_all_is_json: true,
+            export_all_kind: GraphExportKind::Json,
+            export_selection_only: false,
+            export_all_template: None,
+            export_template_draft: None,
             export_all_path: String::new(),
             export_all_status: None,
             query_suggest_visible: false,
@@ -401,6 +719,10 @@ impl GraphApp {
             _cluster_converge_threshold: 30,
             _cluster_converge_strength: 3.0,
             gravity_enabled: false,
+            swimlanes: None,
+            lanes_generation: 0,
+            lane_key: String::new(),
+            lane_axis: LaneAxis::default(),
             gravity_strength: 6.0,
             com_gravity_radius: 150.0,
             com_gravity_min_neighbors: 2,
@@ -413,7 +735,11 @@ impl GraphApp {
             edge_label_min_zoom: 0.8,
             edge_label_count_threshold: 500,
             edge_label_bg_alpha: 170,
+            edge_text: EdgeText::Label,
+            edge_meta_key: String::new(),
             hover_node: None,
+            hover_rel: None,
+            connection_preview: None,
             zoom_hud_until: None,
             app_settings: settings.clone(),
             show_prefs_window: false,
@@ -423,15 +749,77 @@ impl GraphApp {
             prefs_export_override_str: String::new(),
             prefs_tab: PrefsTab::App,
             prefs_api_log_override_str: String::new(),
+            prefs_watch_folder_str: String::new(),
+            prefs_ip_filter_strs: Default::default(),
+            prefs_role_strs: Vec::new(),
+            new_profile_name: String::new(),
             api_rx: None,
             api_running: false,
+            api_paused: false,
+            tray: None,
+            tray_rx: None,
+            last_saved_at: None,
+            save_slot: SaveSlot::default(),
             last_background_time: None,
             first_focused_observed: None,
+            folder_watcher: None,
+            pending_drop_files: Vec::new(),
+            show_logs_window: false,
+            logs_filter: String::new(),
+            logs_min_level: log::Level::Trace,
+            logs_api_only: false,
+            pending_recovery: persist::load_recovery().ok().flatten(),
+            state_lock: None,
+            lock_conflict: None,
+            read_only_reason: None,
+            last_lock_heartbeat: Instant::now(),
+            backup_schedule: BackupSchedule::new(),
+            last_backup_check: Instant::now(),
+            export_schedule: ExportSchedule::new(&settings.scheduled_export),
+            last_export_check: Instant::now(),
+            history: History::new(&GraphDatabase::new(), "Opened graph"),
+            restore_points: RestorePoints::default(),
+            tasks: Vec::new(),
+            repaint_ctx: None,
+            graph_generation: 0,
+            show_history_window: false,
+            show_merge_window: false,
+            merge_path: String::new(),
+            merge_by_key: false,
+            merge_key: "name".into(),
+            merge_plan: None,
+            merge_error: None,
+            show_bundle_window: false,
+            bundle_opening: false,
+            bundle_path: String::new(),
+            bundle_apply_settings: true,
+            bundle_error: None,
+            show_graph_props_window: false,
+            graph_props_edit: Vec::new(),
+            show_constraints_window: false,
+            constraints_audit: None,
+            show_rules_window: false,
+            rule_form: (String::new(), String::new(), String::new()),
+            computed_form: (String::new(), String::new(), String::new(), 0),
+            show_derived_window: false,
+            derived_form: (String::new(), String::new(), String::new(), false),
+            derived_virtual: Vec::new(),
+            show_derived_virtual: true,
+            show_views_window: false,
+            view_form: (String::new(), String::new(), String::new()),
+            rules_report: None,
+            show_template_window: false,
+            template_choice: 0,
+            template_ba_nodes: 100,
+            template_ba_edges: 2,
+            template_ba_seed: 42,
         };
         // Apply settings to runtime toggles
         s.lod_enabled = s.app_settings.lod_enabled;
         s.lod_label_min_zoom = s.app_settings.lod_label_min_zoom;
         s.lod_hide_labels_node_threshold = s.app_settings.lod_hide_labels_node_threshold;
+        // First run: nothing built yet and the tour never finished or dismissed
+        if !s.app_settings.tour_seen && s.db.nodes.is_empty() { s.start_tour(); }
         // Initialize API broker and server based on settings
         let rx = api::init_broker();
         s.api_rx = Some(rx);
@@ -439,16 +827,26 @@ impl GraphApp {
             let _ = api::server::start_server(&s.app_settings);
         }
         if s.app_settings.grpc_enabled {
-            let _ = api::grpc::start_grpc_server(&s.app_settings);
+            if let Err(e) = api::grpc::start_grpc_server(&s.app_settings) {
+                log::error!("Failed to start gRPC server: {}", e);
+            }
         }
         if s.app_settings.api_enabled || s.app_settings.grpc_enabled {
             s.api_running = true;
         }
+        s.restart_folder_watcher();
+        s.reset_history("Opened graph");
+        s.acquire_state_lock();
         s
     }
 
     fn ensure_layout(&mut self, rect: Rect) {
-        if self.node_positions.len() == self.db.nodes.len() {
+        // Nodes outside the result view get their place once it closes
+        if self.node_positions.len() == self.db.nodes.len() || self.result_view.is_some() {
+            return;
+        }
+        if self.is_large_graph() {
+            if self.db.nodes.keys().any(|id| !self.node_positions.contains_key(id)) { self.start_layout_task(rect, false); }
             return;
         }
 
@@ -489,6 +887,14 @@ impl GraphApp {
     }
 
     fn apply_cluster_layout_all(&mut self, rect: Rect) {
+        self.close_result_view();
+        if self.is_large_graph() {
+            self.swimlanes = None;
+            self.re_cluster_pending = false;
+            self.start_layout_task(rect, true);
+            return;
+        }
+        self.swimlanes = None;
         let cluster_positions = self.compute_community_layout(rect);
         let center = rect.center();
         for id in self.db.nodes.keys().copied() {
@@ -503,18 +909,92 @@ impl GraphApp {
         self.mark_dirty();
     }
 
+    // Arrange every node in swimlanes by `lane_key`, starting at the canvas corner, and bring
+    // the lanes into view
+    fn apply_swimlanes(&mut self) {
+        self.close_result_view();
+        let origin = self.last_canvas_rect.map_or(Pos2::ZERO, |r| r.min);
+        let (lanes, positions) = Swimlanes::arrange(&self.db, self.lane_key.trim(), self.lane_axis, origin);
+        self.last_save_info = Some(format!("Arranged {} node(s) in {} lane(s) by '{}'", positions.len(), lanes.lanes.len(), lanes.key));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Subtle;
+        self.node_positions.extend(positions);
+        self.node_velocities.clear();
+        self.swimlanes = Some(lanes);
+        self.converge_start = Some(Instant::now());
+        self.mark_dirty();
+        self.lanes_generation = self.graph_generation;
+        self.fit_nodes(self.node_positions.keys().copied().collect::<Vec<_>>());
+    }
+
+    // Add nodes in a compact grid starting at `anchor` (world space) and make them the bulk
+    // selection; returns their ids in order
+    fn add_node_grid(&mut self, rows: Vec<(String, HashMap<String, String>)>, anchor: Pos2) -> Vec<NodeId> {
+        let cols = (rows.len() as f32).sqrt().ceil().max(1.0) as usize;
+        let spacing = 60.0;
+        self.multi_selected_nodes.clear();
+        self.multi_selected_rels.clear();
+        let mut ids = Vec::with_capacity(rows.len());
+        for (i, (label, meta)) in rows.into_iter().enumerate() {
+            let id = self.db.add_node(label, meta);
+            let (cx, cy) = ((i % cols) as f32, (i / cols) as f32);
+            self.node_positions.insert(id, Pos2::new(anchor.x + cx * spacing, anchor.y + cy * spacing));
+            self.multi_selected_nodes.insert(id);
+            ids.push(id);
+        }
+        self.converge_start = Some(Instant::now());
+        ids
+    }
+
+    // Quick Create: a node per line of the box near the middle of the view, chained in order
+    // when linking is on
+    fn quick_create_nodes(&mut self) {
+        let label = self.quick_create_label.trim();
+        let rows = import::parse_lines(&self.quick_create_text, if label.is_empty() { "Idea" } else { label });
+        if let Some(e) = rows.iter().find_map(|(label, meta)| self.db.check_node(label, meta).err()) {
+            self.save_error = Some(format!("Quick Create refused by property rules: {}", e));
+            return;
+        }
+        // World point at the canvas center: (p - c) * zoom + c + pan = c
+        let anchor = self.last_canvas_rect.map_or(Pos2::ZERO, |r| r.center() - self.pan / self.zoom);
+        let ids = self.add_node_grid(rows, anchor);
+        let mut links = 0;
+        if self.quick_create_link {
+            let rel_label = self.quick_create_link_label.trim();
+            let rel_label = if rel_label.is_empty() { "NEXT" } else { rel_label };
+            for pair in ids.windows(2) {
+                if self.db.add_relationship(pair[0], pair[1], rel_label.to_string(), HashMap::new()).is_some() { links += 1; }
+            }
+        }
+        self.mark_dirty();
+        let summary = if links > 0 { format!("Created {} node(s) and {} link(s)", ids.len(), links) } else { format!("Created {} node(s)", ids.len()) };
+        self.record_history(summary.clone());
+        self.last_save_info = Some(summary);
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.quick_create_text.clear();
+    }
+
+    // Community layout computed on the UI thread, for graphs below the background threshold
+    fn compute_community_layout(&self, rect: Rect) -> HashMap<NodeId, Pos2> {
+        Self::community_layout(&self.db, rect, &Progress::default()).unwrap_or_default()
+    }
+
     // Compute a community-based layout for all nodes without overriding existing positions.
+    // `progress` counts label propagation rounds and cancels between them.
     // - Communities are detected via simple label propagation, with extra similarity from labels and metadata overlaps.
     // - Dense communities are placed closer to the border; sparse nodes are biased toward the center.
-    fn compute_community_layout(&self, rect: Rect) -> HashMap<NodeId, Pos2> {
+    pub(crate) fn community_layout(db: &GraphDatabase, rect: Rect, progress: &Progress) -> anyhow::Result<HashMap<NodeId, Pos2>> {
         use std::collections::{HashMap as Map, HashSet as Set};
 
         // Build adjacency and degree
         let mut neighbors: Map<NodeId, Vec<NodeId>> = Map::new();
-        for id in self.db.nodes.keys() {
+        for id in db.nodes.keys() {
             neighbors.entry(*id).or_default();
         }
-        for rel in self.db.relationships.values() {
+        for rel in db.relationships.values() {
+            // Self-loops say nothing about which community a node belongs to
+            if rel.from_node == rel.to_node { continue; }
             neighbors.entry(rel.from_node).or_default().push(rel.to_node);
             neighbors.entry(rel.to_node).or_default().push(rel.from_node);
         }
@@ -522,14 +1002,14 @@ impl GraphApp {
         // Precompute label/meta for similarity
         let mut node_label: Map<NodeId, String> = Map::new();
         let mut node_meta: Map<NodeId, Map<String, String>> = Map::new();
-        for (id, n) in &self.db.nodes {
+        for (id, n) in &db.nodes {
             node_label.insert(*id, n.label.clone());
             node_meta.insert(*id, n.metadata.clone());
         }
 
         // Initialize labels (each node in its own community)
         let mut community: Map<NodeId, NodeId> = Map::new();
-        for id in self.db.nodes.keys() {
+        for id in db.nodes.keys() {
             community.insert(*id, *id);
         }
 
@@ -562,9 +1042,12 @@ impl GraphApp {
         };
 
         // Label propagation iterations
-        let mut order: Vec<NodeId> = self.db.nodes.keys().copied().collect();
+        let mut order: Vec<NodeId> = db.nodes.keys().copied().collect();
         order.sort();
+        progress.set_total(8);
         for _iter in 0..8 { // few iterations for stability
+            progress.check()?;
+            progress.advance(1);
             let mut changed = false;
             for &u in &order {
                 let mut scores: Map<NodeId, f32> = Map::new();
@@ -663,7 +1146,7 @@ impl GraphApp {
             }
         }
 
-        out
+        Ok(out)
     }
 
     // Label-centric target layout: place one centroid per distinct node label around a ring,
@@ -707,27 +1190,34 @@ impl GraphApp {
         targets
     }
 
-    // Stable color per label, chosen from a small distinct palette via hashing.
-    fn color_for_label(label: &str) -> Color32 {
-        const PALETTE: [Color32; 12] = [
-            Color32::from_rgb(0x7b, 0xa3, 0xff), // blue
-            Color32::from_rgb(0xff, 0xa3, 0x7b), // orange
-            Color32::from_rgb(0x7b, 0xff, 0xa3), // green
-            Color32::from_rgb(0xff, 0x7b, 0xa3), // pink
-            Color32::from_rgb(0xa3, 0x7b, 0xff), // violet
-            Color32::from_rgb(0xff, 0xe0, 0x7b), // yellow
-            Color32::from_rgb(0x7b, 0xff, 0xe0), // teal
-            Color32::from_rgb(0xe0, 0x7b, 0xff), // purple
-            Color32::from_rgb(0x7b, 0xe0, 0xff), // cyan
-            Color32::from_rgb(0xff, 0x7b, 0xe0), // magenta
-            Color32::from_rgb(0x9a, 0xcd, 0x32), // yellowgreen
-            Color32::from_rgb(0xcd, 0x32, 0x9a), // fuchsia
-        ];
-        use std::hash::{Hash, Hasher};
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        label.hash(&mut hasher);
-        let h = hasher.finish() as usize;
-        PALETTE[h % PALETTE.len()]
+    // Size of canvas text: grows with zoom within [min, max], times the font size preference
+    fn canvas_text_size(&self, base: f32, min: f32, max: f32) -> f32 {
+        (base * self.zoom).clamp(min, max) * self.app_settings.canvas_font_scale
+    }
+
+    // Whether a node with `label` and `metadata` would break a property rule; if so the edit is
+    // refused and the menu bar says why
+    fn refused_by_rules(&mut self, label: &str, metadata: &HashMap<String, String>) -> bool {
+        match self.db.check_node(label, metadata) {
+            Ok(()) => false,
+            Err(e) => {
+                self.save_error = Some(format!("Refused by property rules: {}", e));
+                true
+            }
+        }
+    }
+
+    // Refuse an edit that sets or removes a computed key; `before` is `None` for a new node
+    fn refused_as_computed(&mut self, label: &str, before: Option<&HashMap<String, String>>, after: &HashMap<String, String>) -> bool {
+        let keys = computed::hand_set(&self.db, label, before, after);
+        if keys.is_empty() { return false; }
+        self.save_error = Some(format!("Computed for (:{}) and not editable: {}", label, keys.join(", ")));
+        true
+    }
+
+    // Stable color per label from the palette chosen in Preferences
+    fn color_for_label(&self, label: &str) -> Color32 {
+        palette::label_color(self.app_settings.color_palette, label)
     }
 
     // Post-process to ensure nodes are not overlapping. Operates in world space.
@@ -819,10 +1309,14 @@ impl GraphApp {
     }
 
     pub fn from_state(state: AppStateFile) -> Self {
+        let annotations = state.annotations.clone();
         let (db, positions, pan, zoom) = state.to_runtime();
+        // Keep a saved layout; only a state without positions (e.g. written by older versions
+        // of background mode) gets a fresh one
+        let relayout = positions.is_empty();
         let settings = AppSettings::load().unwrap_or_default();
         let mut s = Self {
-            db,
+            db: db.into(),
             node_positions: positions,
             node_velocities: HashMap::new(),
             converge_start: Some(Instant::now()),
@@ -846,12 +1340,65 @@ impl GraphApp {
             open_rel_windows: BTreeSet::new(),
             create_node_label: String::new(),
             create_node_meta: vec![],
+            quick_create_text: String::new(),
+            quick_create_label: "Idea".into(),
+            quick_create_link: false,
+            quick_create_link_label: "NEXT".into(),
             create_rel_label: String::new(),
             create_rel_from: None,
             create_rel_to: None,
             create_rel_meta: vec![],
             create_rel_display_key: String::new(),
             pick_target: None,
+            node_picker: None,
+            node_picker_filter: String::new(),
+            node_picker_matches: None,
+            annotations,
+            show_annotations: true,
+            annotation_tool: None,
+            annotation_text: String::new(),
+            annotation_color: Color32::from_rgb(255, 220, 120),
+            annotation_drag_start: None,
+            selected_annotation: None,
+            applied_ui_scale: None,
+            show_generator_window: false,
+            generator_choice: 0,
+            generator_models: Model::ALL,
+            generator_labels: "Node".to_string(),
+            generator_relationship: "LINKS".to_string(),
+            generator_seed: 42,
+            query_reference: None,
+            query_reference_filter: String::new(),
+            schema_view: None,
+            memory_report: None,
+            peer_sync: None,
+            show_sync_window: false,
+            sync_url: String::new(),
+            sync_key: String::new(),
+            show_neo4j_window: false,
+            neo4j_source: Neo4jSource::default(),
+            show_sql_window: false,
+            sql_source: SqlSource::default(),
+            show_vault_window: false,
+            vault_dir: String::new(),
+            show_packages_window: false,
+            packages_path: String::new(),
+            show_feeds_window: false,
+            feed_draft: None,
+            feed_last_run: HashMap::new(),
+            feeds_allowed: HashMap::new(),
+            import_preview: None,
+            last_feed_check: Instant::now(),
+            sync_name: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "Guest".to_string()),
+            tour: None,
+            tour_anchors: HashMap::new(),
+            frame_stats: FrameStats::default(),
+            pan_inertia: Inertia::default(),
+            camera: None,
+            window_geometry: None,
+            window_maximized: false,
+            scroll_offsets: BTreeMap::new(),
+            pending_scroll: BTreeMap::new(),
             create_node_rel_enabled: false,
             create_node_rel_direction: NewNodeRelDir::NewToExisting,
             create_node_rel_label: String::from("REL"),
@@ -861,8 +1408,13 @@ impl GraphApp {
             node_meta_new_kv: HashMap::new(),
             rel_label_edits: HashMap::new(),
             rel_meta_new_kv: HashMap::new(),
+            node_meta_json: HashMap::new(),
+            rel_meta_json: HashMap::new(),
+            node_desc_edits: HashMap::new(),
+            rel_desc_edits: HashMap::new(),
             multi_select_active: false,
             multi_selected_nodes: HashSet::new(),
+            multi_selected_rels: HashSet::new(),
             rect_select_start: None,
             rect_select_current: None,
             bulk_add_key: String::new(),
@@ -873,25 +1425,39 @@ impl GraphApp {
             query_text: String::new(),
             query_history: Vec::new(),
             query_output: Vec::new(),
+            query_rows: Vec::new(),
+            query_lint: Vec::new(),
+            query_lint_for: String::new(),
             last_query_error: None,
             query_selected_nodes: HashSet::new(),
             query_selected_rels: HashSet::new(),
+            result_view: None,
+            result_view_generation: 0,
+            query_output_paths: HashMap::new(),
+            query_active_path: None,
             query_export_is_json: true,
             query_export_path: String::new(),
             query_export_status: None,
             show_export_all_window: false,
-            export_all_is_json: true,
+            export_all_kind: GraphExportKind::Json,
+            export_selection_only: false,
+            export_all_template: None,
+            export_template_draft: None,
             export_all_path: String::new(),
             export_all_status: None,
             query_suggest_visible: false,
             query_suggest_items: Vec::new(),
             query_suggest_index: 0,
             query_suggest_hover_index: None,
-            re_cluster_pending: true,
+            re_cluster_pending: relayout,
             _cluster_converge_enabled: false,
             _cluster_converge_threshold: 30,
             _cluster_converge_strength: 3.0,
             gravity_enabled: false,
+            swimlanes: None,
+            lanes_generation: 0,
+            lane_key: String::new(),
+            lane_axis: LaneAxis::default(),
             gravity_strength: 6.0,
             com_gravity_radius: 150.0,
             com_gravity_min_neighbors: 2,
@@ -904,7 +1470,11 @@ impl GraphApp {
             edge_label_min_zoom: 0.8,
             edge_label_count_threshold: 500,
             edge_label_bg_alpha: 170,
+            edge_text: EdgeText::Label,
+            edge_meta_key: String::new(),
             hover_node: None,
+            hover_rel: None,
+            connection_preview: None,
             zoom_hud_until: None,
             app_settings: settings.clone(),
             show_prefs_window: false,
@@ -914,15 +1484,77 @@ impl GraphApp {
             prefs_export_override_str: String::new(),
             prefs_tab: PrefsTab::App,
             prefs_api_log_override_str: String::new(),
+            prefs_watch_folder_str: String::new(),
+            prefs_ip_filter_strs: Default::default(),
+            prefs_role_strs: Vec::new(),
+            new_profile_name: String::new(),
             api_rx: None,
             api_running: false,
+            api_paused: false,
+            tray: None,
+            tray_rx: None,
+            last_saved_at: None,
+            save_slot: SaveSlot::default(),
             last_background_time: None,
             first_focused_observed: None,
+            folder_watcher: None,
+            pending_drop_files: Vec::new(),
+            show_logs_window: false,
+            logs_filter: String::new(),
+            logs_min_level: log::Level::Trace,
+            logs_api_only: false,
+            pending_recovery: persist::load_recovery().ok().flatten(),
+            state_lock: None,
+            lock_conflict: None,
+            read_only_reason: None,
+            last_lock_heartbeat: Instant::now(),
+            backup_schedule: BackupSchedule::new(),
+            last_backup_check: Instant::now(),
+            export_schedule: ExportSchedule::new(&settings.scheduled_export),
+            last_export_check: Instant::now(),
+            history: History::new(&GraphDatabase::new(), "Opened graph"),
+            restore_points: RestorePoints::default(),
+            tasks: Vec::new(),
+            repaint_ctx: None,
+            graph_generation: 0,
+            show_history_window: false,
+            show_merge_window: false,
+            merge_path: String::new(),
+            merge_by_key: false,
+            merge_key: "name".into(),
+            merge_plan: None,
+            merge_error: None,
+            show_bundle_window: false,
+            bundle_opening: false,
+            bundle_path: String::new(),
+            bundle_apply_settings: true,
+            bundle_error: None,
+            show_graph_props_window: false,
+            graph_props_edit: Vec::new(),
+            show_constraints_window: false,
+            constraints_audit: None,
+            show_rules_window: false,
+            rule_form: (String::new(), String::new(), String::new()),
+            computed_form: (String::new(), String::new(), String::new(), 0),
+            show_derived_window: false,
+            derived_form: (String::new(), String::new(), String::new(), false),
+            derived_virtual: Vec::new(),
+            show_derived_virtual: true,
+            show_views_window: false,
+            view_form: (String::new(), String::new(), String::new()),
+            rules_report: None,
+            show_template_window: false,
+            template_choice: 0,
+            template_ba_nodes: 100,
+            template_ba_edges: 2,
+            template_ba_seed: 42,
         };
         // Apply settings to runtime toggles
         s.lod_enabled = s.app_settings.lod_enabled;
         s.lod_label_min_zoom = s.app_settings.lod_label_min_zoom;
         s.lod_hide_labels_node_threshold = s.app_settings.lod_hide_labels_node_threshold;
+        // First run: nothing built yet and the tour never finished or dismissed
+        if !s.app_settings.tour_seen && s.db.nodes.is_empty() { s.start_tour(); }
         // Initialize API broker and server based on settings
         let rx = api::init_broker();
         s.api_rx = Some(rx);
@@ -930,336 +1562,2309 @@ impl GraphApp {
             let _ = api::server::start_server(&s.app_settings);
         }
         if s.app_settings.grpc_enabled {
-            let _ = api::grpc::start_grpc_server(&s.app_settings);
+            if let Err(e) = api::grpc::start_grpc_server(&s.app_settings) {
+                log::error!("Failed to start gRPC server: {}", e);
+            }
         }
         if s.app_settings.api_enabled || s.app_settings.grpc_enabled {
             s.api_running = true;
         }
+        s.restart_folder_watcher();
+        s.reset_history("Opened graph");
+        s.acquire_state_lock();
         s
     }
 
-    fn mark_dirty(&mut self) {
-        self.dirty = true;
-        self.last_change = Instant::now();
+    // (Re)start or stop the watch-folder poller to match current settings
+    fn restart_folder_watcher(&mut self) {
+        self.folder_watcher = None;
+        if !self.app_settings.watch_folder_enabled { return; }
+        match FolderWatcher::start(self.app_settings.watch_folder_dir(), Duration::from_secs(2)) {
+            Ok(w) => self.folder_watcher = Some(w),
+            Err(e) => self.save_error = Some(format!("Watch folder unavailable: {}", e)),
+        }
     }
 
-    fn save_now_with(&mut self, style: NoticeStyle) {
-        let state = AppStateFile::from_runtime(&self.db, &self.node_positions, self.pan, self.zoom);
-        match persist::save_active(&state) {
-            Ok(path) => {
-                self.dirty = false;
-                self.last_save = Instant::now();
-                self.save_error = None;
-                self.last_save_info = Some(format!("Saved to {}", path.display()));
-                self.last_info_time = Some(Instant::now());
-                self.last_info_style = style;
-            }
-            Err(e) => {
-                self.save_error = Some(format!("Save failed: {}", e));
+    // Merge any files the watch folder has reported since the last frame
+    fn process_watch_folder(&mut self) {
+        let Some(watcher) = &self.folder_watcher else { return };
+        let mut ready: Vec<std::path::PathBuf> = Vec::new();
+        while let Some(p) = watcher.try_recv() { ready.push(p); }
+        for p in ready {
+            let name = p.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>").to_string();
+            self.create_restore_point(format!("Watch folder import {}", name));
+            let rule = &self.app_settings.import_match;
+            let res = validation::guarded(&mut self.db, |db| import::merge_file_matching(db, &p, rule));
+            let _ = watch::finish(&p, res.is_ok());
+            match res {
+                Ok(summary) => {
+                    if !summary.is_empty() {
+                        self.re_cluster_pending = true;
+                        self.mark_dirty();
+                        self.record_history(format!("Watch folder import {}", name));
+                    }
+                    log::info!("Watch folder imported {}: {}", p.display(), summary.describe());
+                    self.last_save_info = Some(format!("Imported {}: {}", name, summary.describe()));
+                    self.last_info_time = Some(Instant::now());
+                    self.last_info_style = NoticeStyle::Prominent;
+                }
+                Err(e) => {
+                    log::error!("Watch import of {} failed: {}", p.display(), e);
+                    self.save_error = Some(format!("Watch import of {} failed: {}", name, e));
+                }
             }
         }
     }
 
-    fn save_now(&mut self) { self.save_now_with(NoticeStyle::Prominent); }
-
-    fn save_versioned_now(&mut self) {
-        let state = AppStateFile::from_runtime(&self.db, &self.node_positions, self.pan, self.zoom);
-        match persist::save_versioned(&state) {
-            Ok(path) => {
-                self.last_save = Instant::now();
-                self.save_error = None;
-                self.last_save_info = Some(format!("Saved version {}", path.display()));
-                self.last_info_time = Some(Instant::now());
-                self.last_info_style = NoticeStyle::Prominent;
+    // Import exported files on a worker thread. They are merged into a copy of the graph (an
+    // empty one with `replace`) that takes the graph's place when the task finishes.
+    fn import_files(&mut self, mut paths: Vec<std::path::PathBuf>, replace: bool) {
+        if paths.is_empty() { return; }
+        // Nodes before relationships so CSV pairs resolve their endpoints
+        paths.sort_by_key(|p| (import::is_relationships_csv(p), p.clone()));
+        let mut db = if replace { GraphDatabase::new().into() } else { self.db.clone() };
+        let generation = self.graph_generation;
+        let mut merger = import::Merger::new(self.app_settings.import_match.clone());
+        self.spawn_task(format!("Importing {} file(s)", paths.len()), move |progress| {
+            progress.set_total(paths.len());
+            let mut summary = import::ImportSummary::default();
+            let mut errors: Vec<String> = Vec::new();
+            let mut payload = import::ImportPayload::default();
+            for p in &paths {
+                progress.check()?;
+                progress.set_stage(p.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>"));
+                let merged = import::ImportPayload::read_file(p).and_then(|read| read.strict()).and_then(|read| {
+                    let sm = validation::guarded(&mut db, |db| Ok(merger.merge(db, read.clone())))?;
+                    payload.append(read);
+                    Ok(sm)
+                });
+                match merged {
+                    Ok(sm) => summary.add(&sm),
+                    Err(e) => errors.push(format!("{}: {}", p.display(), e)),
+                }
+                progress.advance(1);
             }
-            Err(e) => self.save_error = Some(format!("Save version failed: {}", e)),
-        }
+            Ok(TaskOutput::Import { paths, replace, payload, db: db.into_inner(), summary, errors, generation })
+        });
     }
 
-    /// Clear all selections and related transient UI state
-    fn deselect_all(&mut self) {
-        self.selected = None;
-        self.dragging = None;
-        self.hover_node = None;
-        self.multi_selected_nodes.clear();
-        self.query_selected_nodes.clear();
-        self.query_selected_rels.clear();
-        self.pick_target = None;
-        self.create_rel_from = None;
-        self.create_rel_to = None;
-        self.pending_new_node_for_link = None;
-        self.mark_dirty();
+    // Read files on a worker thread and merge them into a copy of the graph for the Import
+    // Preview window. Rows that do not parse are listed there instead of failing their file.
+    fn preview_files(&mut self, mut paths: Vec<std::path::PathBuf>) {
+        if paths.is_empty() { return; }
+        paths.sort_by_key(|p| (import::is_relationships_csv(p), p.clone()));
+        let mut db = self.db.clone();
+        let generation = self.graph_generation;
+        let rule = self.app_settings.import_match.clone();
+        self.spawn_task(format!("Previewing {} file(s)", paths.len()), move |progress| {
+            progress.set_total(paths.len());
+            let mut payload = import::ImportPayload::default();
+            let mut failed = Vec::new();
+            for p in &paths {
+                progress.check()?;
+                progress.set_stage(p.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>"));
+                match import::ImportPayload::read_file(p) {
+                    Ok(read) => payload.append(read),
+                    Err(e) => failed.push(format!("{}: {}", p.display(), e)),
+                }
+                progress.advance(1);
+            }
+            let mut report = validation::guarded(&mut db, |db| Ok(import::merge_with_report(db, payload.clone(), &rule)))?;
+            report.problems.splice(0..0, failed);
+            let source = format!("{} file(s)", paths.len());
+            Ok(TaskOutput::Preview(Box::new(ImportPreview { paths, source, payload, db: db.into_inner(), report, generation })))
+        });
     }
 
-    // Get a node position if present; otherwise, initialize a reasonable default
-    // position (golden spiral around canvas center) and return it. This prevents
-    // panics when newly created nodes have not yet been laid out by ensure_layout.
-    fn get_or_init_position(&mut self, id: NodeId, rect: Rect) -> Pos2 {
-        if let Some(p) = self.node_positions.get(&id) {
-            return *p;
+    // Commit the import shown in the Import Preview window
+    fn commit_import_preview(&mut self, preview: ImportPreview) {
+        let ImportPreview { paths, source, payload, db, report, generation } = preview;
+        let (db, summary) = if generation == self.graph_generation {
+            (db, report.summary)
+        } else {
+            // Edited meanwhile: merge into the graph as it is now
+            let mut db = self.db.clone();
+            let mut merger = import::Merger::new(self.app_settings.import_match.clone());
+            match validation::guarded(&mut db, |db| Ok(merger.merge(db, payload))) {
+                Ok(summary) => (db.into_inner(), summary),
+                Err(e) => {
+                    self.save_error = Some(format!("Import from {} failed: {}", source, e));
+                    return;
+                }
+            }
+        };
+        if paths.is_empty() { self.apply_connector_import(source, db, summary) } else { self.apply_import(paths, false, db, summary, Vec::new()) }
+        if !report.problems.is_empty() {
+            if let Some(info) = &mut self.last_save_info { info.push_str(&format!(" ({} problem(s) left out)", report.problems.len())); }
         }
-        let center = rect.center();
-        let k = self.node_positions.len() as u32;
-        let pos = golden_spiral_position(center, k, rect);
-        self.node_positions.insert(id, pos);
-        pos
     }
 
-    // Public helpers callable from native (OS) menu integrations
-    pub fn menu_save(&mut self) { self.save_now(); }
+    // Swap in the graph an import task produced. With `replace`, the current graph is backed
+    // up as a version first.
+    fn apply_import(&mut self, paths: Vec<std::path::PathBuf>, replace: bool, db: GraphDatabase, total: import::ImportSummary, errors: Vec<String>) {
+        self.create_restore_point(format!("{} {} file(s)", if replace { "Replace graph from" } else { "Import" }, paths.len()));
+        if replace {
+            if !self.db.nodes.is_empty() || !self.db.relationships.is_empty() { self.save_versioned_now(); }
+            self.node_positions.clear();
+            self.node_velocities.clear();
+            self.selected = None;
+            self.open_node_windows.clear();
+            self.open_rel_windows.clear();
+            self.multi_selected_nodes.clear();
+            self.multi_selected_rels.clear();
+            self.query_selected_nodes.clear();
+            self.query_selected_rels.clear();
+        }
+        self.db.replace_with(db);
+        if replace || !total.is_empty() {
+            self.re_cluster_pending = true;
+            self.converge_start = Some(Instant::now());
+            self.mark_dirty();
+            self.record_history(format!("{} {} file(s)", if replace { "Replaced graph from" } else { "Imported" }, paths.len() - errors.len()));
+        }
+        self.last_save_info = Some(format!("Imported {} file(s): {}", paths.len() - errors.len(), total.describe()));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.save_error = if errors.is_empty() { None } else { Some(format!("Import failed for {}", errors.join("; "))) };
+    }
 
-    pub fn menu_save_version(&mut self) { self.save_versioned_now(); }
+    // Read from the Neo4j server in the File → Import From → Neo4j form
+    fn import_from_neo4j(&mut self, preview: bool) {
+        let source = self.neo4j_source.clone();
+        self.import_from_connector(source.uri.trim().to_string(), "row(s)", preview, move |on_rows| neo4j::fetch(&source, on_rows));
+    }
 
-    pub fn menu_load_latest(&mut self) {
-        match persist::load_active() {
-            Ok(Some(state)) => {
-                let (db, pos, pan, zoom) = state.to_runtime();
-                self.db = db; self.node_positions = pos; self.pan = pan; self.zoom = zoom;
-                self.selected = None; self.open_node_windows.clear(); self.open_rel_windows.clear();
-                self.dirty = false; self.last_change = Instant::now();
-                self.last_save_info = Some("Loaded latest state".into());
-                self.last_info_time = Some(Instant::now());
-                self.last_info_style = NoticeStyle::Prominent;
-                self.save_error = None;
-            }
-            Ok(None) => { self.save_error = Some("No active state file found".into()); }
-            Err(e) => { self.save_error = Some(format!("Load failed: {}", e)); }
-        }
+    // Read from the database in the File → Import From → SQL Database form
+    fn import_from_sql(&mut self, preview: bool) {
+        let source = self.sql_source.clone();
+        self.import_from_connector(source.display_url(), "row(s)", preview, move |on_rows| sql::fetch(&source, on_rows));
     }
 
-    pub fn menu_new_graph(&mut self) {
-        // Back up existing graph if it's non-empty
-        let had_content = !self.db.nodes.is_empty() || !self.db.relationships.is_empty();
-        if had_content { self.save_versioned_now(); }
+    // Read the notes in the folder in the File → Import From → Markdown Vault form
+    fn import_from_vault(&mut self, preview: bool) {
+        let dir = std::path::PathBuf::from(self.vault_dir.trim());
+        let name = dir.file_name().map_or_else(|| dir.display().to_string(), |n| n.to_string_lossy().into_owned());
+        self.import_from_connector(name, "note(s)", preview, move |on_notes| markdown::read_vault(&dir, on_notes));
+    }
 
-        // Reset runtime to a fresh, empty graph
-        self.db = GraphDatabase::new();
-        self.node_positions.clear();
-        self.node_velocities.clear();
-        self.selected = None;
-        self.dragging = None;
-        self.open_node_windows.clear();
-        self.open_rel_windows.clear();
-        self.multi_selected_nodes.clear();
-        self.pick_target = None;
-        self.create_rel_from = None;
-        self.create_rel_to = None;
-        self.pending_new_node_for_link = None;
-        self.pan = Vec2::ZERO;
-        self.zoom = 1.0;
-        self.re_cluster_pending = true;
-        self.converge_start = Some(Instant::now());
-        self.dirty = true;
-        self.last_change = Instant::now();
-        self.save_error = None;
-        self.last_info_time = Some(Instant::now());
-        self.last_info_style = NoticeStyle::Prominent;
-        self.last_save_info = Some(
-            if had_content { "Created new empty graph (backup saved)" } else { "Created new empty graph" }
-                .to_string(),
-        );
+    // Read the dependency graph at the path in the File → Import From → Package Dependencies form
+    fn import_packages(&mut self, preview: bool) {
+        let path = std::path::PathBuf::from(self.packages_path.trim());
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        self.import_from_connector(name, "package(s)", preview, move |_| packages::read(&path));
     }
 
-    pub fn menu_reset_view(&mut self) {
-        self.pan = Vec2::ZERO;
-        self.zoom = 1.0;
-        self.mark_dirty();
+    // Fetch a feed saved with the graph and merge what its mapping makes of the response; only
+    // feeds this user wrote get their headers and `${VAR}` expansion
+    fn import_feed(&mut self, feed: Feed, preview: bool) {
+        let feed = self.app_settings.authored_feed(&feed);
+        self.feed_last_run.insert(feed.id, Instant::now());
+        self.import_from_connector(feed.name.clone(), "response(s)", preview, move |_| rest::fetch(&feed));
     }
 
-    pub fn menu_open_prefs(&mut self) {
-        // Prepare editable copy and open the window
-        self.prefs_edit = self.app_settings.clone();
-        self.prefs_autosave_override_str = match &self.prefs_edit.autosave_override {
-            Some(p) => p.display().to_string(),
-            None => String::new(),
-        };
-        self.prefs_export_override_str = match &self.prefs_edit.export_override {
-            Some(p) => p.display().to_string(),
-            None => String::new(),
-        };
-        self.prefs_api_log_override_str = match &self.prefs_edit.api_log_override {
-            Some(p) => p.display().to_string(),
-            None => String::new(),
-        };
-        self.prefs_tab = PrefsTab::App;
-        self.prefs_status = None;
-        self.show_prefs_window = true;
+    // Start the scheduled feeds whose interval has passed since they last ran; feeds that have
+    // not run this session start right away. Feeds someone else wrote into the file wait until
+    // the user allows them in the feeds window.
+    fn run_due_feeds(&mut self) {
+        if !cfg!(feature = "rest") || self.read_only_reason.is_some() { return; }
+        if self.last_feed_check.elapsed() < Duration::from_secs(10) { return; }
+        self.last_feed_check = Instant::now();
+        let due: Vec<Feed> = self
+            .db
+            .feeds
+            .iter()
+            .filter(|f| f.interval_mins > 0)
+            .filter(|f| self.feeds_allowed.get(&f.id) == Some(&f.url) || self.app_settings.authored_feed(f).authored)
+            .filter(|f| self.feed_last_run.get(&f.id).is_none_or(|t| t.elapsed() >= Duration::from_secs(u64::from(f.interval_mins) * 60)))
+            .filter(|f| !self.tasks.iter().any(|t| t.label == format!("Importing from {}", f.name)))
+            .cloned()
+            .collect();
+        for feed in due { self.import_feed(feed, false); }
     }
 
-}
+    // Run a connector's `fetch` on a worker thread and merge what it read into a copy of the
+    // graph; `source` names what it reads from in messages and `unit` what it counts as it reads.
+    // With `preview` the result goes to the Import Preview window instead of the graph.
+    fn import_from_connector<F>(&mut self, source: String, unit: &'static str, preview: bool, fetch: F)
+    where
+        F: FnOnce(&mut dyn FnMut(usize) -> anyhow::Result<()>) -> anyhow::Result<import::ImportPayload> + Send + 'static,
+    {
+        let mut db = self.db.clone();
+        let generation = self.graph_generation;
+        let mut merger = import::Merger::new(self.app_settings.import_match.clone());
+        self.spawn_task(format!("Importing from {}", source), move |progress| {
+            progress.set_stage(format!("Connecting to {}", source));
+            let payload = fetch(&mut |rows| {
+                progress.check()?;
+                progress.set_stage(format!("{} {} read", rows, unit));
+                Ok(())
+            })?;
+            progress.set_stage("Merging");
+            progress.set_total(payload.len());
+            let problems = import::problems(&db, &payload);
+            let summary = validation::guarded(&mut db, |db| {
+                let mut summary = import::ImportSummary::default();
+                for batch in payload.clone().into_batches(1000) {
+                    progress.check()?;
+                    progress.advance(batch.len());
+                    summary.add(&merger.merge(db, batch));
+                }
+                Ok(summary)
+            })?;
+            if preview {
+                let report = import::ImportReport { summary, problems };
+                return Ok(TaskOutput::Preview(Box::new(ImportPreview { paths: Vec::new(), source, payload, db: db.into_inner(), report, generation })));
+            }
+            if !problems.is_empty() { log::warn!("Import from {} left out: {}", source, problems.join("; ")); }
+            Ok(TaskOutput::Connector { source, payload, db: db.into_inner(), summary, generation })
+        });
+    }
 
-impl eframe::App for GraphApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Detect if the window was shown externally (e.g. by another instance using Win32 API)
-        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
-            let cooldown_passed = self.last_background_time
-                .map(|t| t.elapsed() > Duration::from_secs(2))
-                .unwrap_or(true);
+    // Swap in the graph a connector import produced
+    fn apply_connector_import(&mut self, source: String, db: GraphDatabase, summary: import::ImportSummary) {
+        self.create_restore_point(format!("Import from {}", source));
+        self.db.replace_with(db);
+        if !summary.is_empty() {
+            self.re_cluster_pending = true;
+            self.converge_start = Some(Instant::now());
+            self.mark_dirty();
+            self.record_history(format!("Imported from {}", source));
+        }
+        self.last_save_info = Some(format!("Imported from {}: {}", source, summary.describe()));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
 
-            if cooldown_passed && ctx.input(|i| i.viewport().focused == Some(true)) {
-                // Double check focus over 100ms to avoid transient reports during backgrounding
-                match self.first_focused_observed {
-                    Some(t) if t.elapsed() >= Duration::from_millis(100) => {
-                        crate::gui::app_state::SHOW_WINDOW.store(true, std::sync::atomic::Ordering::SeqCst);
-                        self.first_focused_observed = None;
-                    }
-                    Some(_) => {
-                        // Still waiting for 100ms to pass
-                        ctx.request_repaint(); // Keep checking
+    // Write the whole graph, the named view on the canvas, or with "Selection only" the
+    // subgraph of the bulk selection, on a worker thread; formats other than JSON and GraphML produce a nodes and a relationships
+    // file. A template shapes the CSV, Parquet and Arrow tables; CSV without one is the
+    // round-trip format File → Import reads back.
+    fn export_graph(&mut self, path: std::path::PathBuf, kind: GraphExportKind, template: Option<ExportTemplate>) {
+        let db = if self.export_selection_only && !(self.multi_selected_nodes.is_empty() && self.multi_selected_rels.is_empty()) {
+            Arc::new(self.db.subgraph(&self.multi_selected_nodes, &self.multi_selected_rels))
+        } else if let Some(view) = self.result_view.as_ref().filter(|v| v.name.is_some()) {
+            Arc::new(view.graph.clone())
+        } else {
+            self.shared_graph()
+        };
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("graph").to_string();
+        let columnar = match kind {
+            GraphExportKind::Parquet => Some(ColumnarFormat::Parquet),
+            GraphExportKind::Arrow => Some(ColumnarFormat::ArrowIpc),
+            GraphExportKind::Csv if template.is_some() => Some(ColumnarFormat::Csv),
+            GraphExportKind::Json | GraphExportKind::Csv | GraphExportKind::GraphMl => None,
+        };
+        if let Some(format) = columnar {
+            let template = template.unwrap_or_default();
+            self.spawn_task(format!("Exporting {}", name), move |progress| export_tables(&db, &path, &template, format, progress));
+            return;
+        }
+        self.spawn_task(format!("Exporting {}", name), move |progress| {
+            let targets = if kind == GraphExportKind::Json {
+                vec![(ExportFormat::Json, path)]
+            } else if kind == GraphExportKind::GraphMl {
+                vec![(ExportFormat::GraphMl, path)]
+            } else {
+                let parent = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+                let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("graph");
+                vec![
+                    (ExportFormat::CsvNodes, parent.join(format!("{}_nodes.csv", stem))),
+                    (ExportFormat::CsvRelationships, parent.join(format!("{}_relationships.csv", stem))),
+                ]
+            };
+            if let Some(dir) = targets[0].1.parent() { std::fs::create_dir_all(dir)?; }
+            progress.set_total(db.nodes.len() + db.relationships.len());
+            let mut offset = 0;
+            for (i, (format, target)) in targets.iter().enumerate() {
+                let chunks = ExportChunks::new(db.clone(), *format);
+                let total = chunks.progress().1;
+                if let Err(e) = write_export_file(chunks, target, progress, offset) {
+                    // Leave nothing half-written behind
+                    for (_, t) in &targets[..=i] { let _ = std::fs::remove_file(t); }
+                    return Err(e);
+                }
+                offset += total;
+            }
+            Ok(TaskOutput::Export(match targets.as_slice() {
+                [(_, p)] => format!("Exported {} to {}", kind.name(), p.display()),
+                [(_, np), (_, rp)] => format!("Exported CSV files: {} and {}", np.display(), rp.display()),
+                _ => "Exported".into(),
+            }))
+        });
+    }
+
+    // Write the console's matched nodes, or every result row when `rows`, on a worker thread.
+    // The task gets copies of just those, not of the graph.
+    fn export_query_results(&mut self, rows: bool) {
+        let path = std::path::PathBuf::from(self.query_export_path.clone());
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("export").to_string();
+        let json = self.query_export_is_json;
+        self.query_export_status = Some("Exporting in the background…".into());
+        let label = format!("{} to {}", QUERY_EXPORT_TASK, name);
+        if rows {
+            let rows = self.query_rows.clone();
+            let format = if json { ResultFormat::Json } else { ResultFormat::Csv };
+            self.spawn_task(label, move |progress| {
+                progress.set_stage("Writing rows");
+                result_export::write_file(&rows, format, &path)?;
+                Ok(TaskOutput::QueryExport(format!("Exported {} row(s) to {}", rows.len(), path.display())))
+            });
+            return;
+        }
+        let nodes: Vec<graph::Node> = self.query_selected_nodes.iter().filter_map(|id| self.db.get_node(*id)).cloned().collect();
+        self.spawn_task(label, move |progress| {
+            progress.set_stage("Writing nodes");
+            if json { export_nodes_json(&nodes, &path)? } else { export_nodes_csv(&nodes, &path)? }
+            Ok(TaskOutput::QueryExport(format!("Exported {} node(s) to {}", nodes.len(), path.display())))
+        });
+    }
+
+    // The settings template picked in the Export Graph window, if it still exists
+    fn selected_export_template(&self) -> Option<ExportTemplate> {
+        let name = self.export_all_template.as_deref()?;
+        self.app_settings.export_templates.iter().find(|t| t.name == name).cloned()
+    }
+
+    // Template choice and editor in the Export Graph window: which metadata keys become
+    // columns and what happens to the rest
+    fn export_template_picker(&mut self, ui: &mut egui::Ui) {
+        let none = if self.export_all_kind == GraphExportKind::Csv { "None (importable CSV)" } else { "All fields" };
+        ui.horizontal(|ui| {
+            ui.label("Template:");
+            egui::ComboBox::from_id_salt("export_template")
+                .selected_text(self.export_all_template.clone().unwrap_or_else(|| none.to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.export_all_template, None, none);
+                    for t in &self.app_settings.export_templates {
+                        ui.selectable_value(&mut self.export_all_template, Some(t.name.clone()), &t.name);
                     }
-                    None => {
-                        self.first_focused_observed = Some(Instant::now());
-                        ctx.request_repaint();
+                });
+            if ui.button("Edit Fields…").clicked() {
+                self.export_template_draft = Some(self.selected_export_template().unwrap_or_default());
+            }
+        });
+        let Some(draft) = self.export_template_draft.as_mut() else { return };
+        let mut keys: BTreeSet<String> = self.db.nodes.values().flat_map(|n| n.metadata.keys().cloned()).collect();
+        keys.extend(self.db.relationships.values().flat_map(|r| r.metadata.keys().cloned()));
+        keys.extend(draft.columns.iter().cloned());
+        let mut save = false;
+        let mut delete = false;
+        let mut close = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.label("Metadata keys to export as columns (in the order picked):");
+            egui::ScrollArea::vertical().max_height(160.0).id_salt("export_template_keys").show(ui, |ui| {
+                for key in &keys {
+                    let mut picked = draft.columns.contains(key);
+                    if ui.checkbox(&mut picked, key).changed() {
+                        if picked { draft.columns.push(key.clone()); } else { draft.columns.retain(|c| c != key); }
                     }
                 }
-            } else {
-                self.first_focused_observed = None;
+            });
+            ui.horizontal(|ui| {
+                ui.label("Other keys:");
+                ui.radio_value(&mut draft.rest, RestMetadata::Flatten, "A column each");
+                ui.radio_value(&mut draft.rest, RestMetadata::Json, "One JSON column");
+                ui.radio_value(&mut draft.rest, RestMetadata::Omit, "Leave out");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut draft.name);
+                save = ui.add_enabled(!draft.name.trim().is_empty(), egui::Button::new("Save Template")).clicked();
+                delete = self.app_settings.export_templates.iter().any(|t| t.name == draft.name.trim()) && ui.button("Delete").clicked();
+                close = ui.button("Close").clicked();
+            });
+        });
+        if save {
+            let mut template = draft.clone();
+            template.name = template.name.trim().to_string();
+            match self.app_settings.export_templates.iter_mut().find(|t| t.name == template.name) {
+                Some(existing) => *existing = template.clone(),
+                None => self.app_settings.export_templates.push(template.clone()),
             }
-        } else {
-            self.first_focused_observed = None;
+            self.export_all_template = Some(template.name);
+            self.export_template_draft = None;
+            if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+        } else if delete {
+            let name = draft.name.trim().to_string();
+            self.app_settings.export_templates.retain(|t| t.name != name);
+            if self.export_all_template.as_deref() == Some(name.as_str()) { self.export_all_template = None; }
+            self.export_template_draft = None;
+            if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+        } else if close {
+            self.export_template_draft = None;
         }
+    }
 
-        // Handle window close event for backgrounding
-        if ctx.input(|i| i.viewport().close_requested()) {
-            if self.app_settings.background_on_close && (self.app_settings.api_enabled || self.app_settings.grpc_enabled) {
-                // Use the static from gui::app_state
-                crate::gui::app_state::SHOW_WINDOW.store(false, std::sync::atomic::Ordering::SeqCst);
-                self.last_background_time = Some(Instant::now());
-                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+    fn is_large_graph(&self) -> bool {
+        self.db.nodes.len() + self.db.relationships.len() >= tasks::BACKGROUND_THRESHOLD
+    }
+
+    // Compute a community layout on a worker thread. Cancelling keeps the current layout and
+    // places nodes without a position on a spiral.
+    fn start_layout_task(&mut self, rect: Rect, full: bool) {
+        if self.tasks.iter().any(|t| t.label == LAYOUT_TASK) { return; }
+        let db = self.db.clone();
+        self.spawn_task(LAYOUT_TASK, move |progress| {
+            let positions = match Self::community_layout(&db, rect, progress) {
+                Ok(p) => p,
+                Err(e) if tasks::is_cancelled(&e) => HashMap::new(),
+                Err(e) => return Err(e),
+            };
+            Ok(TaskOutput::Layout { positions, full, rect })
+        });
+    }
+
+    fn apply_layout(&mut self, positions: HashMap<NodeId, Pos2>, full: bool, rect: Rect) {
+        self.close_result_view();
+        let center = rect.center();
+        let mut ids: Vec<NodeId> = self.db.nodes.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            let target = positions.get(&id).copied();
+            if full && let Some(p) = target {
+                self.node_positions.insert(id, p);
+            } else if !self.node_positions.contains_key(&id) {
+                let k = self.node_positions.len() as u32;
+                self.node_positions.insert(id, target.unwrap_or_else(|| golden_spiral_position(center, k, rect)));
             }
         }
+        self.resolve_overlaps(rect);
+        self.converge_start = Some(Instant::now());
+        if full { self.mark_dirty(); }
+    }
 
-        // Handle window visibility and background mode
-        let show_window = crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst);
-        static LAST_SHOW_WINDOW: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
-        if show_window != LAST_SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
-            if show_window {
-                // RESTORING from background
-                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
-                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
-                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
-                // Also request attention when showing from internal state change
-                ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Critical));
-                // Briefly set AlwaysOnTop here too to be safe
-                ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+    // Run the console query. On large graphs it runs against a copy on a worker thread and
+    // its result is applied when it finishes.
+    fn run_console_query(&mut self, q: String) {
+        if self.is_large_graph() {
+            let mut db = self.db.clone();
+            let generation = self.graph_generation;
+            let short: String = q.chars().take(40).collect();
+            self.spawn_task(format!("Query: {}", short), move |_| {
+                let outcome = query_interface::execute_and_log(&mut db, &q)?;
+                Ok(TaskOutput::Query { query: q, outcome, db: db.into_inner(), generation })
+            });
+            return;
+        }
+        // Keep the graph as it was so a large mutation can be rolled back from Edit → Restore Last Checkpoint
+        let started = Instant::now();
+        let before = query_interface::may_write(&q).then(|| (self.db.clone(), self.full_layout().clone()));
+        match query_interface::execute_and_log(&mut self.db, &q) {
+            Ok(outcome) => self.show_query_outcome(q, outcome, before),
+            Err(err) => {
+                self.last_query_error = Some(err.to_string());
+            }
+        }
+        self.frame_stats.add(Phase::Queries, started.elapsed());
+    }
 
-                // Use Win32 API to force foreground on Windows
-                crate::gui::win_utils::force_foreground_window();
+    // Start a background task; the UI is woken when it finishes
+    fn spawn_task(&mut self, label: impl Into<String>, work: impl FnOnce(&Progress) -> anyhow::Result<TaskOutput> + Send + 'static) {
+        let ctx = self.repaint_ctx.clone();
+        self.tasks.push(Task::spawn(label, work, move || if let Some(ctx) = ctx { ctx.request_repaint(); }));
+    }
 
-                let ctx_clone = ctx.clone();
-                std::thread::spawn(move || {
-                    for i in 1..=5 {
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-                        
-                        // If the user has hidden the window again during this loop, stop immediately
-                        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
-                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
-                            break;
-                        }
+    // Apply the results of finished tasks; keeps repainting while any are running so their
+    // progress moves
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        let mut finished = Vec::new();
+        self.tasks.retain(|t| match t.poll() {
+            Some(res) => {
+                finished.push((t.label.clone(), t.progress().is_cancelled(), res));
+                false
+            }
+            None => true,
+        });
+        for (label, cancelled, res) in finished { self.finish_task(label, cancelled, res); }
+        if !self.tasks.is_empty() { ctx.request_repaint_after(Duration::from_millis(100)); }
+    }
 
-                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Visible(true));
-                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
-                        
-                        // Use Win32 API to force foreground on Windows
-                        #[cfg(target_os = "windows")]
-                        unsafe {
-                            let _ = windows::Win32::UI::WindowsAndMessaging::AllowSetForegroundWindow(windows::Win32::UI::WindowsAndMessaging::ASFW_ANY);
+    fn finish_task(&mut self, label: String, cancelled: bool, res: anyhow::Result<TaskOutput>) {
+        let out = match res {
+            // Imports and queries that finished despite a cancel are discarded as well
+            Ok(TaskOutput::Import { .. } | TaskOutput::Query { .. } | TaskOutput::Connector { .. } | TaskOutput::Preview(_)) if cancelled => None,
+            Ok(out) => Some(out),
+            Err(e) if tasks::is_cancelled(&e) => None,
+            Err(e) => {
+                log::error!("{} failed: {}", label, e);
+                match label.strip_prefix("Query: ") {
+                    Some(_) => self.last_query_error = Some(e.to_string()),
+                    None if label.starts_with(QUERY_EXPORT_TASK) => self.query_export_status = Some(format!("Export failed: {}", e)),
+                    None => self.save_error = Some(format!("{} failed: {}", label, e)),
+                }
+                return;
+            }
+        };
+        let Some(out) = out else {
+            self.last_save_info = Some(format!("{} cancelled", label));
+            self.last_info_time = Some(Instant::now());
+            self.last_info_style = NoticeStyle::Prominent;
+            return;
+        };
+        match out {
+            TaskOutput::Import { paths, replace, payload, db, summary, errors, generation } => {
+                // A replacement does not build on the graph, so edits made meanwhile do not
+                // matter; the graph they left is kept as a version
+                if replace || generation == self.graph_generation {
+                    self.apply_import(paths, replace, db, summary, errors);
+                } else {
+                    // Edited meanwhile: merge what was read into the graph as it is now
+                    let mut db = self.db.clone();
+                    let mut merger = import::Merger::new(self.app_settings.import_match.clone());
+                    match validation::guarded(&mut db, |db| Ok(merger.merge(db, payload))) {
+                        Ok(summary) => self.apply_import(paths, false, db.into_inner(), summary, errors),
+                        Err(e) => self.save_error = Some(format!("Import of {} file(s) failed: {}", paths.len(), e)),
+                    }
+                }
+            }
+            TaskOutput::Connector { source, payload, db, summary, generation } => {
+                if generation == self.graph_generation {
+                    self.apply_connector_import(source, db, summary);
+                } else {
+                    // Edited meanwhile: merge into the graph as it is now
+                    let mut db = self.db.clone();
+                    let mut merger = import::Merger::new(self.app_settings.import_match.clone());
+                    match validation::guarded(&mut db, |db| Ok(merger.merge(db, payload))) {
+                        Ok(summary) => self.apply_connector_import(source, db.into_inner(), summary),
+                        Err(e) => self.save_error = Some(format!("Import from {} failed: {}", source, e)),
+                    }
+                }
+            }
+            TaskOutput::Preview(preview) => self.import_preview = Some(preview),
+            TaskOutput::Export(msg) => {
+                self.export_all_status = Some(msg.clone());
+                self.last_save_info = Some(msg);
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Prominent;
+            }
+            TaskOutput::QueryExport(msg) => {
+                self.query_export_status = Some(msg.clone());
+                self.last_save_info = Some(msg);
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Subtle;
+            }
+            TaskOutput::ScheduledExport(paths) => {
+                let dir = paths.first().and_then(|p| p.parent()).map(|d| d.display().to_string()).unwrap_or_default();
+                log::info!("Scheduled export wrote {} file(s) to {}", paths.len(), dir);
+                self.last_save_info = Some(format!("Scheduled export written to {}", dir));
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Subtle;
+            }
+            // Skipped when a newer save got there first
+            TaskOutput::Saved { path: None, .. } => {}
+            TaskOutput::Saved { generation, path: Some(path), style } => {
+                if generation == self.graph_generation { self.dirty = false; }
+                self.last_save = Instant::now();
+                self.last_saved_at = Some(self.last_save);
+                self.save_error = None;
+                self.last_save_info = Some(format!("Saved to {}", path.display()));
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = style;
+            }
+            TaskOutput::Layout { positions, full, rect } => self.apply_layout(positions, full, rect),
+            TaskOutput::Query { query, outcome, db, generation } => {
+                if !outcome.mutated {
+                    self.show_query_outcome(query, outcome, None);
+                } else if generation == self.graph_generation {
+                    let before = std::mem::replace(&mut self.db, db.into());
+                    let positions = self.full_layout().clone();
+                    self.show_query_outcome(query, outcome, Some((before, positions)));
+                } else {
+                    self.last_query_error = Some("The graph changed while the query ran; its changes were not applied. Run it again.".into());
+                }
+            }
+        }
+    }
+
+    // Show a query's rows in the console, select what it matched and record its changes.
+    // `before` is the graph prior to a mutating query, kept as a restore point when the query
+    // changed many elements.
+    fn show_query_outcome(&mut self, q: String, outcome: query_interface::QueryOutcome, before: Option<(LiveGraph, HashMap<NodeId, Pos2>)>) {
+        self.last_query_error = None;
+        // record history
+        if self.query_history.last().map(|h| h != &q).unwrap_or(true) {
+            self.query_history.push(q.clone());
+        }
+        // display rows succinctly and capture matches
+        self.query_selected_nodes.clear();
+        self.query_selected_rels.clear();
+        self.query_output.clear();
+        self.query_output_paths.clear();
+        self.query_active_path = None;
+        self.query_rows = outcome.rows.clone();
+        for row in outcome.rows {
+            match row {
+                QueryResultRow::Node { id, label, metadata } => {
+                    self.query_output.push(format!("NODE {} {} {:?}", id, label, metadata));
+                    self.query_selected_nodes.insert(id);
+                }
+                QueryResultRow::Relationship { id, from, to, label, metadata } => {
+                    self.query_output.push(format!("REL {} {} {} {} {:?}", id, from, to, label, metadata));
+                    self.query_selected_rels.insert(id);
+                    // ensure endpoints are positioned if new
+                    if let Some(pa) = self.node_positions.get(&from) { let _ = pa; } else { if let Some(rect) = self.last_canvas_rect { let pos = golden_spiral_position(rect.center(), self.node_positions.len() as u32, rect); self.node_positions.insert(from, pos); } }
+                    if let Some(pb) = self.node_positions.get(&to) { let _ = pb; } else { if let Some(rect) = self.last_canvas_rect { let pos = golden_spiral_position(rect.center(), self.node_positions.len() as u32 + 1, rect); self.node_positions.insert(to, pos); } }
+                }
+                QueryResultRow::Path { nodes, relationships } => {
+                    let mut line = format!("PATH length={}", relationships.len());
+                    for (i, nid) in nodes.iter().enumerate() {
+                        if i > 0 {
+                            let rl = relationships.get(i - 1).and_then(|rid| self.db.relationships.get(rid)).map(|r| r.label.as_str()).unwrap_or("?");
+                            line.push_str(&format!(" -[{}]-", rl));
                         }
-                        crate::gui::win_utils::force_foreground_window();
+                        let nl = self.db.nodes.get(nid).map(|n| n.label.as_str()).unwrap_or("?");
+                        line.push_str(&format!(" ({} {})", nl, nid));
+                    }
+                    self.query_selected_nodes.extend(nodes.iter().copied());
+                    self.query_selected_rels.extend(relationships.iter().copied());
+                    self.query_output_paths.insert(self.query_output.len(), (nodes, relationships));
+                    self.query_output.push(line);
+                }
+                QueryResultRow::Info(s) => self.query_output.push(s),
+                other => self.query_output.push(format!("{:?}", other)),
+            }
+        }
+        self.query_output.push(format!("Affected: nodes={} rels={}", outcome.affected_nodes, outcome.affected_relationships));
+        // An open result view follows the new matches
+        if self.result_view.is_some() && self.named_view().is_none() { self.open_result_view(); }
+        // The graph may have changed, so lint again on the next frame
+        self.query_lint_for.clear();
+        if outcome.mutated {
+            self.mark_dirty();
+            let short: String = q.chars().take(40).collect();
+            let short = format!("{}{}", short, if q.chars().count() > 40 { "…" } else { "" });
+            if let Some((db, positions)) = before
+                && outcome.affected_nodes + outcome.affected_relationships >= MASS_MUTATION_THRESHOLD
+            {
+                self.restore_points.push(db.into_inner(), positions, format!("Query: {}", short));
+            }
+            self.record_history(format!("Query: {}", short));
+        }
+    }
 
-                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Focus);
+    // Status bar listing running tasks with their progress and a Cancel button
+    fn start_tour(&mut self) {
+        self.tour = Some(Tour::default());
+        self.prepare_tour_step();
+    }
 
-                        // Double check after commands
-                        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
-                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
-                            break;
-                        }
+    // Finished or dismissed: remember it so the tour does not start again
+    // Frame time breakdown in the canvas corner, averaged over the last second
+    fn show_perf_hud(&mut self, ctx: &egui::Context) {
+        let Some(canvas) = self.last_canvas_rect else { return };
+        let now = Instant::now();
+        let avg = self.frame_stats.average(now);
+        let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+        let physics_on = self.converge_start.is_some_and(|t| t.elapsed() < Duration::from_secs(5)) || self.dragging.is_some();
+        egui::Area::new(egui::Id::new("perf_hud"))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(canvas.right_top() + Vec2::new(-8.0, 8.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::Grid::new("perf_hud_grid").num_columns(2).show(ui, |ui| {
+                        let mut row = |name: &str, value: String| {
+                            ui.monospace(name);
+                            ui.monospace(value);
+                            ui.end_row();
+                        };
+                        row("fps", self.frame_stats.fps(now).to_string());
+                        row("frame", format!("{:6.2} ms", ms(avg.total)));
+                        row("physics", format!("{:6.2} ms{}", ms(avg.physics), if physics_on { "" } else { " (idle)" }));
+                        row("painting", format!("{:6.2} ms", ms(avg.painting)));
+                        row("queries", format!("{:6.2} ms", ms(avg.queries)));
+                        row("other", format!("{:6.2} ms", ms(avg.other())));
+                        row("graph", format!("{} n / {} r", self.db.nodes.len(), self.db.relationships.len()));
+                    });
+                });
+            });
+    }
 
-                        if i % 2 == 0 {
-                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Critical));
-                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
-                        }
-                        if i == 4 {
-                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
-                        }
-                        ctx_clone.request_repaint();
+    fn end_tour(&mut self) {
+        self.tour = None;
+        if !self.app_settings.tour_seen {
+            self.app_settings.tour_seen = true;
+            self.prefs_edit.tour_seen = true;
+            if let Err(e) = self.app_settings.save() { log::warn!("Failed to save settings: {}", e); }
+        }
+    }
+
+    // Bring the current step's widget on screen
+    fn prepare_tour_step(&mut self) {
+        let Some(tour) = self.tour else { return };
+        match tour.step().target {
+            Some(TourTarget::CreateNode) => {
+                self.sidebar_open = true;
+                self.sidebar_mode = SidebarMode::Tooling;
+            }
+            Some(TourTarget::QueryTab) => self.sidebar_open = true,
+            Some(TourTarget::QueryEditor) => {
+                self.sidebar_open = true;
+                self.sidebar_mode = SidebarMode::Query;
+            }
+            _ => {}
+        }
+    }
+
+    // Callout for the current tour step, next to its widget with the widget outlined
+    fn show_tour(&mut self, ctx: &egui::Context) {
+        let Some(mut tour) = self.tour else { return };
+        let step = tour.step();
+        let target = step.target.and_then(|t| self.tour_anchors.get(&t).copied());
+        let screen = ctx.content_rect();
+        let width = 320.0;
+        let mut area = egui::Area::new(egui::Id::new("tour_callout")).order(egui::Order::Foreground);
+        area = match target {
+            Some(r) => {
+                ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("tour_highlight")))
+                    .rect_stroke(r.expand(4.0), 6.0, Stroke::new(2.5, Color32::from_rgb(255, 200, 80)), egui::StrokeKind::Outside);
+                // Below the widget, or beside it when it fills most of the height (the canvas)
+                let pos = if r.height() > screen.height() * 0.5 {
+                    Pos2::new(r.center().x - width * 0.5, r.center().y - 80.0)
+                } else {
+                    Pos2::new(r.left(), r.bottom() + 12.0)
+                };
+                let pos = Pos2::new(pos.x.clamp(screen.left() + 8.0, (screen.right() - width - 8.0).max(screen.left())), pos.y.clamp(screen.top(), screen.bottom() - 160.0));
+                area.fixed_pos(pos)
+            }
+            None => area.anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO),
+        };
+        let mut action: Option<bool> = None; // Some(true) = next, Some(false) = back
+        let mut close = false;
+        area.show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).inner_margin(egui::Margin::same(12)).show(ui, |ui| {
+                ui.set_width(width);
+                ui.horizontal(|ui| {
+                    ui.strong(step.title);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.weak(format!("{} / {}", tour.index() + 1, crate::gui::tour::STEPS.len()));
+                    });
+                });
+                ui.add_space(4.0);
+                ui.label(step.body);
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button(if tour.is_last() { "Finish" } else { "Next" }).clicked() { action = Some(true); }
+                    if !tour.is_first() && ui.button("Back").clicked() { action = Some(false); }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if !tour.is_last() && ui.small_button("Skip tour").clicked() { close = true; }
+                    });
+                });
+            });
+        });
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) { close = true; }
+        match action {
+            Some(true) if !tour.advance() => close = true,
+            Some(false) => tour.back(),
+            _ => {}
+        }
+        if close {
+            self.end_tour();
+        } else if self.tour != Some(tour) {
+            self.tour = Some(tour);
+            self.prepare_tour_step();
+        }
+    }
+
+    // Bottom bar with graph and session stats, followed by the running background tasks
+    fn show_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} nodes", self.db.node_count()));
+                ui.separator();
+                ui.label(format!("{} relationships", self.db.relationship_count()));
+                ui.separator();
+                if let Some(view) = &self.result_view {
+                    let text = format!("Result view: {} nodes, {} relationships", view.graph.node_count(), view.graph.relationship_count());
+                    if ui.link(text).on_hover_text("Click to show the whole graph").clicked() { self.close_result_view(); }
+                    ui.separator();
+                }
+                ui.label(format!("Zoom {:.0}%", self.zoom * 100.0));
+                ui.separator();
+                match (self.multi_selected_nodes.len(), self.multi_selected_rels.len()) {
+                    (0, 0) => { ui.weak("No selection"); }
+                    (n, 0) => { ui.label(format!("{} selected", n)); }
+                    (n, r) => { ui.label(format!("{} selected, {} relationship(s)", n, r)); }
+                }
+                ui.separator();
+                if self.dirty {
+                    ui.colored_label(Color32::YELLOW, "● Unsaved changes");
+                } else {
+                    match self.last_saved_at {
+                        Some(t) => ui.weak(format!("Saved {}", crate::gui::tray::ago(t.elapsed()))),
+                        None => ui.weak("No changes"),
+                    };
+                }
+                ui.separator();
+                match (self.api_endpoint_url(), self.api_paused) {
+                    (_, true) => { ui.weak("API paused"); }
+                    (Some(url), false) => {
+                        if ui.link(format!("API {}", url)).on_hover_text("Click to copy").clicked() { ctx.copy_text(url); }
+                    }
+                    (None, false) => { ui.weak("API off"); }
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let build_ms = self.frame_stats.last_build().as_secs_f32() * 1000.0;
+                    ui.weak(format!("{} fps · {:.1} ms", self.frame_stats.fps(Instant::now()), build_ms))
+                        .on_hover_text("Frames drawn in the last second and the time the last one took. The window only redraws on input or animation, so an idle window shows few frames.");
+                });
+            });
+            for t in &self.tasks {
+                ui.horizontal(|ui| {
+                    let p = t.progress();
+                    let bar = match p.fraction() {
+                        Some(f) => egui::ProgressBar::new(f).show_percentage(),
+                        None => egui::ProgressBar::new(0.0).animate(true),
+                    };
+                    ui.add(bar.desired_width(160.0));
+                    let stage = p.stage();
+                    ui.label(if stage.is_empty() { t.label.clone() } else { format!("{}: {}", t.label, stage) });
+                    ui.weak(format!("{}s", t.started.elapsed().as_secs()));
+                    if p.is_cancelled() {
+                        ui.weak("Cancelling…");
+                    } else if ui.small_button("Cancel").clicked() {
+                        t.cancel();
                     }
                 });
-            } else {
-                // GOING to background
-                // On Windows, if we want the app icon to STAY in the taskbar but the window to be hidden,
-                // Minimized(true) is often better than Visible(false).
-                // However, the user said "The app icon on the taskbar also does not return as it should",
-                // implying it DOES leave the taskbar (which is what we want for "background mode").
-                // If we use Visible(false), it leaves the taskbar. 
-                // To make it come back, we MUST use Visible(true).
-                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
             }
-            LAST_SHOW_WINDOW.store(show_window, std::sync::atomic::Ordering::SeqCst);
+        });
+    }
+
+    // Apply new settings to the running app: LOD toggles, API/gRPC servers, watch folder and
+    // default export paths. Callers are responsible for persisting `new`.
+    fn apply_app_settings(&mut self, new: AppSettings) {
+        // Determine if API server config changed
+        let old_limits = api::ApiLimits::from_settings(&self.app_settings);
+        let old_api = (self.app_settings.api_enabled.clone(), self.app_settings.api_bind_addr.clone(), self.app_settings.api_port, self.app_settings.api_key.clone(), self.app_settings.api_compression, old_limits, self.app_settings.api_ip_filter.clone(), self.app_settings.api_roles.clone());
+        let old_grpc = (self.app_settings.grpc_enabled.clone(), self.app_settings.grpc_port, self.app_settings.api_bind_addr.clone(), self.app_settings.api_key.clone(), old_limits, self.app_settings.grpc_ip_filter.clone(), self.app_settings.grpc_tls.clone(), self.app_settings.api_roles.clone());
+        // Detect export dir change to refresh default export paths in views
+        let old_export_dir = self.app_settings.export_dir();
+        let old_watch = (self.app_settings.watch_folder_enabled, self.app_settings.watch_folder_dir());
+        if new.scheduled_export != self.app_settings.scheduled_export {
+            self.export_schedule = ExportSchedule::new(&new.scheduled_export);
+        }
+        self.app_settings = new;
+        crate::gql::collation::set_session_options(self.app_settings.query_options());
+        if old_watch != (self.app_settings.watch_folder_enabled, self.app_settings.watch_folder_dir()) {
+            self.restart_folder_watcher();
+        }
+        // Apply to runtime
+        self.lod_enabled = self.app_settings.lod_enabled;
+        self.lod_label_min_zoom = self.app_settings.lod_label_min_zoom;
+        self.lod_hide_labels_node_threshold = self.app_settings.lod_hide_labels_node_threshold;
+        let new_limits = api::ApiLimits::from_settings(&self.app_settings);
+        let new_api = (self.app_settings.api_enabled.clone(), self.app_settings.api_bind_addr.clone(), self.app_settings.api_port, self.app_settings.api_key.clone(), self.app_settings.api_compression, new_limits, self.app_settings.api_ip_filter.clone(), self.app_settings.api_roles.clone());
+        let new_grpc = (self.app_settings.grpc_enabled.clone(), self.app_settings.grpc_port, self.app_settings.api_bind_addr.clone(), self.app_settings.api_key.clone(), new_limits, self.app_settings.grpc_ip_filter.clone(), self.app_settings.grpc_tls.clone(), self.app_settings.api_roles.clone());
+
+        // While paused from the tray, changes take effect on resume
+        if old_api != new_api && !self.api_paused {
+            // Restart server
+            api::server::stop_server();
+            if self.app_settings.api_enabled {
+                let _ = api::server::start_server(&self.app_settings);
+            }
         }
 
-        if !show_window {
-            // When hidden, we don't need to update the UI at all.
-            // But we might still need to process API requests.
-            if let Some(rx) = &self.api_rx {
-                if let Ok(req) = rx.recv_timeout(Duration::from_millis(500)) {
-                    // Execute query on GUI thread
-                    let res = match &req.params {
-                        Some(p) => query_interface::execute_query_with_params(&mut self.db, &req.query, p),
-                        None => query_interface::execute_and_log(&mut self.db, &req.query),
-                    };
-                    let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
-                    
-                    // If we mutated the DB, we might want to save eventually.
-                    // But we don't need to repaint the UI.
+        if old_grpc != new_grpc && !self.api_paused {
+            api::grpc::stop_grpc_server();
+            if self.app_settings.grpc_enabled {
+                if let Err(e) = api::grpc::start_grpc_server(&self.app_settings) {
+                    self.save_error = Some(format!("gRPC server not started: {:#}", e));
                 }
-            } else {
-                // No API, just sleep
-                std::thread::sleep(Duration::from_millis(500));
             }
-            // Ask egui to wake us up later, or when there is input (though there shouldn't be when hidden)
-            ctx.request_repaint_after(Duration::from_millis(500));
-            return;
         }
 
-    // Process pending API requests (execute queries on the GUI thread safely)
-    if let Some(rx) = &self.api_rx {
-        // Limit processing per frame to avoid freezing the GUI
-        let mut count = 0;
-        while let Ok(req) = rx.try_recv() {
-            let t0 = std::time::Instant::now();
-            // Execute query on GUI thread
-            let res = match &req.params {
-                Some(p) => query_interface::execute_query_with_params(&mut self.db, &req.query, p),
-                None => query_interface::execute_and_log(&mut self.db, &req.query),
+        self.api_running = !self.api_paused && (self.app_settings.api_enabled || self.app_settings.grpc_enabled);
+
+        let new_export_dir = self.app_settings.export_dir();
+        if old_export_dir != new_export_dir {
+            // If export_all_path is empty or under old dir, regenerate under new dir
+            let refresh_export_all = self.export_all_path.is_empty() || {
+                let p = std::path::Path::new(&self.export_all_path);
+                p.starts_with(&old_export_dir)
+            };
+            if refresh_export_all {
+                let now = time::OffsetDateTime::now_utc();
+                let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
+                let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
+                let ext = self.export_all_kind.extension();
+                let mut base = new_export_dir.clone();
+                base.push(format!("graph_export_{}.{}", stamp, ext));
+                self.export_all_path = base.display().to_string();
+            }
+            // If query_export_path is empty or under old dir, regenerate under new dir
+            let refresh_query = self.query_export_path.is_empty() || {
+                let p = std::path::Path::new(&self.query_export_path);
+                p.starts_with(&old_export_dir)
             };
-            let dt = t0.elapsed();
-            // Debug print for visibility in console during development
-            eprintln!(
-                "[API GUI] RID={} done mutated={} dt_ms={}",
-                req.request_id,
-                res.as_ref().map(|o| o.mutated).unwrap_or(false),
-                dt.as_millis()
-            );
-            // Best effort respond; ignore send errors if client disconnected
-            let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
-            
-            count += 1;
-            if count >= 5 { break; } // Process at most 5 requests per frame
+            if refresh_query {
+                let now = time::OffsetDateTime::now_utc();
+                let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
+                let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
+                let ext = if self.query_export_is_json { "json" } else { "csv" };
+                let mut base = new_export_dir;
+                base.push(format!("query_export_{}.{}", stamp, ext));
+                self.query_export_path = base.display().to_string();
+            }
         }
     }
-        // Native menu command handling removed; in-window menus cover these actions
 
-        // Preferences window
-        if self.show_prefs_window {
-            let mut open = true;
-            egui::Window::new("Preferences")
-                .open(&mut open)
-                .resizable(true)
-                .collapsible(false)
-                .show(ctx, |ui| {
-                    // Tabs: App vs API
-                    ui.horizontal(|ui| {
+    // Exchange writes with sync peers; their edits become one undo step per batch
+    fn pump_peer_sync(&mut self) {
+        let Some(peers) = &mut self.peer_sync else { return };
+        let applied = peers.pump(&mut self.db);
+        if applied > 0 {
+            self.mark_dirty();
+            self.record_history(format!("{} edit(s) from peers", applied));
+        }
+    }
+
+    // Run one broker request: admin tasks go through the app's save/load paths, the rest
+    // only need the graph
+    fn run_api_request(&mut self, req: &mut ApiRequest) -> anyhow::Result<query_interface::QueryOutcome> {
+        let task = match req.task.take() {
+            Some(ApiTask::Admin(task)) => return self.run_admin_task(task),
+            other => other,
+        };
+        let is_import = matches!(task, Some(ApiTask::Import { .. }));
+        if is_import { self.create_restore_point("API import"); }
+        req.task = task;
+        let started = Instant::now();
+        let out = api::execute(&mut self.db, req);
+        self.frame_stats.add(Phase::Queries, started.elapsed());
+        // While hidden, keep the last layout; `ensure_layout` places the new nodes once shown
+        let shown = crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst);
+        if is_import && shown && out.as_ref().is_ok_and(|o| o.mutated) { self.re_cluster_pending = true; }
+        out
+    }
+
+    fn run_admin_task(&mut self, task: AdminTask) -> anyhow::Result<query_interface::QueryOutcome> {
+        let saved = match task {
+            AdminTask::Save => self.save_now_with(NoticeStyle::Subtle),
+            AdminTask::SaveVersion => self.save_versioned_now(),
+            AdminTask::LoadVersion(path) => {
+                self.load_version(&path)?;
+                return Ok(api::path_outcome(&path));
+            }
+        };
+        match saved {
+            Some(path) => Ok(api::path_outcome(&path)),
+            None => Err(anyhow::anyhow!(self.save_error.clone().unwrap_or_else(|| "save failed".into()))),
+        }
+    }
+
+    // Replace the graph with a saved version
+    fn load_version(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let state = persist::load_from_path(path)?;
+        self.annotations = state.annotations.clone();
+        let (db, pos, pan, zoom) = state.to_runtime();
+        self.db.replace_with(db); self.node_positions = pos; self.pan = pan; self.zoom = zoom; self.swimlanes = None; self.result_view = None;
+        self.selected = None; self.open_node_windows.clear(); self.open_rel_windows.clear();
+        self.dirty = false; self.last_change = Instant::now();
+        self.reset_history("Loaded version");
+        let label = path.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+        self.last_save_info = Some(format!("Loaded {}", label));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.save_error = None;
+        self.remember_recent(path);
+        Ok(())
+    }
+
+    // Put `path` at the top of File → Open Recent and persist the list
+    fn remember_recent(&mut self, path: &std::path::Path) {
+        self.app_settings.note_recent(path);
+        self.save_recent_files();
+    }
+
+    // Keep the rule set in an import window when Preferences applies its copy of the settings
+    fn import_match_changed(&mut self) {
+        self.prefs_edit.import_match = self.app_settings.import_match.clone();
+        if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+    }
+
+    fn save_recent_files(&self) {
+        if let Err(e) = self.app_settings.save() { log::warn!("Could not save recent files: {}", e); }
+    }
+
+    // Open a file from File → Open Recent: bundles replace the project, anything else is a
+    // saved state
+    fn open_recent(&mut self, path: &std::path::Path) {
+        let is_bundle = path.extension().and_then(|e| e.to_str()) == Some(bundle::BUNDLE_EXTENSION);
+        let res = if is_bundle { self.open_bundle(path) } else { self.load_version(path) };
+        if let Err(e) = res { self.save_error = Some(format!("Failed to load {}: {}", path.display(), e)); }
+    }
+
+    // Open the bundle dialog, suggesting a file in the export folder when exporting
+    fn open_bundle_window(&mut self, opening: bool) {
+        if !opening && self.bundle_path.trim().is_empty() {
+            let now = time::OffsetDateTime::now_utc();
+            let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
+            let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
+            let mut base = self.app_settings.export_dir();
+            base.push(format!("graph_bundle_{}.{}", stamp, bundle::BUNDLE_EXTENSION));
+            self.bundle_path = base.display().to_string();
+        }
+        self.bundle_opening = opening;
+        self.bundle_error = None;
+        self.show_bundle_window = true;
+    }
+
+    // Write the graph, layout, query history and shareable settings to one file
+    fn export_bundle(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let b = Bundle {
+            state: self.state_file(),
+            queries: self.query_history.clone(),
+            settings: BundleSettings::from_settings(&self.app_settings),
+        };
+        bundle::write(path, &b)?;
+        self.last_save_info = Some(format!("Exported bundle to {}", path.display()));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        Ok(())
+    }
+
+    // Replace the graph with a bundle's contents. The current graph is backed up as a version
+    // first, and the bundle's settings are applied on request.
+    fn open_bundle(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let b = bundle::read(path)?;
+        if !self.db.nodes.is_empty() || !self.db.relationships.is_empty() { self.save_versioned_now(); }
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>").to_string();
+        self.create_restore_point(format!("Open bundle {}", name));
+        self.annotations = b.state.annotations.clone();
+        let (db, pos, pan, zoom) = b.state.to_runtime();
+        self.restore_from_history(db);
+        self.node_positions = pos; self.node_velocities.clear(); self.pan = pan; self.zoom = zoom; self.swimlanes = None; self.result_view = None;
+        self.re_cluster_pending = self.node_positions.is_empty();
+        self.query_history = b.queries;
+        if self.bundle_apply_settings {
+            let mut next = self.app_settings.clone();
+            b.settings.apply_to(&mut next);
+            next.save()?;
+            self.apply_app_settings(next);
+        }
+        self.reset_history(&format!("Opened bundle {}", name));
+        self.last_save_info = Some(format!("Opened bundle {}", name));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.save_error = None;
+        self.remember_recent(path);
+        Ok(())
+    }
+
+    // Load the merge source and compare it with the current graph
+    fn analyze_merge(&mut self) {
+        self.merge_plan = None;
+        let by = if self.merge_by_key && !self.merge_key.trim().is_empty() { MatchBy::Key(self.merge_key.trim().to_string()) } else { MatchBy::Id };
+        match merge::load_graph(std::path::Path::new(self.merge_path.trim())) {
+            Ok(theirs) => {
+                self.merge_plan = Some(merge::plan(&self.db, theirs, &by));
+                self.merge_error = None;
+            }
+            Err(e) => self.merge_error = Some(format!("Cannot read {}: {}", self.merge_path.trim(), e)),
+        }
+    }
+
+    fn apply_merge(&mut self) {
+        let Some(plan) = self.merge_plan.take() else { return };
+        let name = std::path::Path::new(self.merge_path.trim()).file_name().and_then(|s| s.to_str()).unwrap_or("file").to_string();
+        self.create_restore_point(format!("Merge {}", name));
+        let summary = merge::apply(&mut self.db, plan);
+        if !summary.is_empty() {
+            self.re_cluster_pending = true;
+            self.converge_start = Some(Instant::now());
+            self.mark_dirty();
+            self.record_history(format!("Merged {}", name));
+        }
+        self.last_save_info = Some(format!("Merged {}: {}", name, summary.describe()));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
+
+    // Fill the Graph Properties editor: the standard keys first, then custom keys
+    fn open_graph_properties(&mut self) {
+        let mut rows: Vec<(String, String)> = GRAPH_METADATA_KEYS
+            .iter()
+            .map(|k| (k.to_string(), self.db.metadata.get(*k).cloned().unwrap_or_default()))
+            .collect();
+        rows.extend(
+            self.db.metadata.iter().filter(|(k, _)| !GRAPH_METADATA_KEYS.contains(&k.as_str())).map(|(k, v)| (k.clone(), v.clone())),
+        );
+        self.graph_props_edit = rows;
+        self.show_graph_props_window = true;
+    }
+
+    // Record the current graph as a new undoable step
+    fn record_history(&mut self, label: impl Into<String>) {
+        self.history.record(&self.db, label);
+    }
+
+    // Start history over from the current graph (after load/new/replace)
+    fn reset_history(&mut self, label: &str) {
+        self.history = History::new(&self.db, label);
+    }
+
+    // Swap in a graph state from history and drop UI state that refers to vanished elements
+    fn restore_from_history(&mut self, db: GraphDatabase) {
+        self.db.replace_with(db);
+        self.node_positions.retain(|id, _| self.db.nodes.contains_key(id));
+        self.node_velocities.retain(|id, _| self.db.nodes.contains_key(id));
+        self.open_node_windows.retain(|id| self.db.nodes.contains_key(id));
+        self.open_rel_windows.retain(|id| self.db.relationships.contains_key(id));
+        self.multi_selected_nodes.retain(|id| self.db.nodes.contains_key(id));
+        self.multi_selected_rels.retain(|id| self.db.relationships.contains_key(id));
+        self.query_selected_nodes.retain(|id| self.db.nodes.contains_key(id));
+        self.query_selected_rels.retain(|id| self.db.relationships.contains_key(id));
+        self.node_label_edits.clear();
+        self.rel_label_edits.clear();
+        self.selected = None;
+        self.dragging = None;
+        self.converge_start = Some(Instant::now());
+        self.mark_dirty();
+    }
+
+    // Keep the current graph as a restore point before a destructive operation
+    fn create_restore_point(&mut self, label: impl Into<String>) {
+        let positions = self.result_view.as_ref().map_or(&self.node_positions, |v| &v.saved_positions);
+        self.restore_points.create(&self.db, positions, label);
+    }
+
+    // Go back to the newest restore point; the restore itself is an undoable step
+    pub fn menu_restore_checkpoint(&mut self) {
+        let Some((label, db, positions)) = self.restore_points.take_latest() else { return };
+        self.restore_from_history(db);
+        self.result_view = None;
+        self.node_positions = positions;
+        self.record_history(format!("Restored checkpoint: {}", label));
+        self.last_save_info = Some(format!("Restored the graph from before: {}", label));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
+
+    pub fn menu_undo(&mut self) {
+        if let Some(db) = self.history.undo() { self.restore_from_history(db); }
+    }
+
+    pub fn menu_redo(&mut self) {
+        if let Some(db) = self.history.redo() { self.restore_from_history(db); }
+    }
+
+    fn mark_dirty(&mut self) {
+        self.graph_generation += 1;
+        self.dirty = true;
+        self.last_change = Instant::now();
+        self.backup_schedule.note_change();
+        self.export_schedule.note_change();
+    }
+
+    // The graph with its layout and annotations, as written to state files
+    fn state_file(&self) -> AppStateFile {
+        let (pan, zoom) = self.full_view();
+        AppStateFile::from_runtime(&self.db, self.full_layout(), pan, zoom).with_annotations(self.annotations.clone())
+    }
+
+    fn save_now_with(&mut self, style: NoticeStyle) -> Option<std::path::PathBuf> {
+        if let Some(reason) = &self.read_only_reason {
+            self.save_error = Some(format!("Read-only: {}", reason));
+            return None;
+        }
+        // The change log is written ahead of the state file that records how far it got
+        if let Err(e) = self.db.changes_mut().flush() { log::error!("Change log write failed: {}", e); }
+        let snapshot = self.snapshot();
+        let path = persist::active_state_path();
+        // The current generation is never older than one already written
+        match self.save_slot.save(snapshot.generation, &snapshot.view(), &path).map(|_| path) {
+            Ok(path) => {
+                self.dirty = false;
+                self.last_save = Instant::now();
+                self.last_saved_at = Some(self.last_save);
+                self.save_error = None;
+                self.last_save_info = Some(format!("Saved to {}", path.display()));
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = style;
+                Some(path)
+            }
+            Err(e) => {
+                self.save_error = Some(format!("Save failed: {}", e));
+                None
+            }
+        }
+    }
+
+    fn save_now(&mut self) { self.save_now_with(NoticeStyle::Prominent); }
+
+    // The graph for worker threads; the next edit copies it if a worker still holds it
+    fn shared_graph(&self) -> Arc<GraphDatabase> { self.db.snapshot() }
+
+    // The state to save as of the current generation
+    fn snapshot(&mut self) -> Snapshot {
+        let (pan, zoom) = self.full_view();
+        Snapshot {
+            generation: self.graph_generation,
+            db: self.shared_graph(),
+            node_positions: self.full_layout().iter().map(|(id, p)| (*id, p.x, p.y)).collect(),
+            pan: (pan.x, pan.y),
+            zoom,
+            annotations: self.annotations.clone(),
+        }
+    }
+
+    // Autosave: the frame only takes the snapshot; a worker serializes and writes it. The
+    // graph stays dirty until a save of its current generation lands.
+    fn autosave(&mut self, style: NoticeStyle) {
+        if self.tasks.iter().any(|t| t.label == AUTOSAVE_TASK) { return; }
+        // The change log is written ahead of the state file that records how far it got
+        if let Err(e) = self.db.changes_mut().flush() { log::error!("Change log write failed: {}", e); }
+        let snapshot = self.snapshot();
+        let slot = self.save_slot.clone();
+        let path = persist::active_state_path();
+        self.spawn_task(AUTOSAVE_TASK, move |_| {
+            let written = slot.save(snapshot.generation, &snapshot.view(), &path)?;
+            Ok(TaskOutput::Saved { generation: snapshot.generation, path: written.then_some(path), style })
+        });
+    }
+
+    // Write a scheduled snapshot when due; `closing` applies the on-close setting instead of the timer
+    fn maybe_backup(&mut self, closing: bool) {
+        let due = if closing {
+            self.app_settings.backup_on_close && self.backup_schedule.has_changes()
+        } else {
+            if self.last_backup_check.elapsed() < Duration::from_secs(60) { return; }
+            self.last_backup_check = Instant::now();
+            self.backup_schedule.is_due(&self.app_settings, std::time::SystemTime::now())
+        };
+        if !due { return; }
+        let state = self.state_file();
+        match self.backup_schedule.run(&state, &self.app_settings) {
+            Ok(path) => log::info!("Backup written to {}", path.display()),
+            Err(e) => self.save_error = Some(format!("Backup failed: {}", e)),
+        }
+    }
+
+    // Write the scheduled export when due: on a worker thread for the timer, right away when
+    // `closing` (the on-close setting)
+    fn maybe_scheduled_export(&mut self, closing: bool) {
+        let config = &self.app_settings.scheduled_export;
+        if closing {
+            if !self.export_schedule.is_due_on_close(config) { return; }
+            self.export_schedule.started(std::time::SystemTime::now());
+            match export_schedule::run(&self.db, config, &self.app_settings) {
+                Ok(paths) => log::info!("Scheduled export wrote {} file(s) to {}", paths.len(), config.dir.trim()),
+                Err(e) => log::error!("Scheduled export failed: {:#}", e),
+            }
+            return;
+        }
+        if self.last_export_check.elapsed() < Duration::from_secs(60) { return; }
+        self.last_export_check = Instant::now();
+        if !self.export_schedule.is_due(config, std::time::SystemTime::now()) { return; }
+        self.export_schedule.started(std::time::SystemTime::now());
+        let (config, settings) = (config.clone(), self.app_settings.clone());
+        let db = self.shared_graph();
+        self.spawn_task("Scheduled export", move |_| Ok(TaskOutput::ScheduledExport(export_schedule::run(&db, &config, &settings)?)));
+    }
+
+    fn acquire_state_lock(&mut self) {
+        match StateLock::acquire(&persist::active_state_path()) {
+            Ok(Ok(l)) => self.state_lock = Some(l),
+            Ok(Err(owner)) => self.enter_read_only(owner),
+            // No lock (e.g. read-only folder): saving reports its own errors
+            Err(e) => log::warn!("Could not lock the state file: {}", e),
+        }
+    }
+
+    fn enter_read_only(&mut self, owner: LockOwner) {
+        log::warn!("State file is locked by {}; opening read-only", owner.describe());
+        self.state_lock = None;
+        self.read_only_reason = Some(format!("graph is locked by {}", owner.describe()));
+        self.lock_conflict = Some(owner);
+    }
+
+    // Keep our lock fresh, and notice when another process has taken it over
+    fn heartbeat_state_lock(&mut self) {
+        if self.last_lock_heartbeat.elapsed() < lock::HEARTBEAT_INTERVAL { return; }
+        self.last_lock_heartbeat = Instant::now();
+        let Some(l) = &mut self.state_lock else { return };
+        match l.heartbeat() {
+            Ok(Ok(())) => {}
+            Ok(Err(owner)) => self.enter_read_only(owner),
+            Err(e) => log::warn!("State lock heartbeat failed: {}", e),
+        }
+    }
+
+    // Prompt shown while another process holds the state lock
+    fn show_lock_dialog(&mut self, ctx: &egui::Context) {
+        let Some(owner) = self.lock_conflict.clone() else { return };
+        let mut read_only = false;
+        let mut take_over = false;
+        let mut quit = false;
+        egui::Window::new("Graph Is Locked")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!("The graph is locked by {}.", owner.describe()));
+                let since = time::OffsetDateTime::from_unix_timestamp(owner.since as i64)
+                    .ok()
+                    .and_then(|t| t.format(time::macros::format_description!("[year]-[month]-[day] [hour]:[minute] UTC")).ok());
+                if let Some(since) = since { ui.small(format!("Locked since {}", since)); }
+                ui.label("Another Graph-Loom is saving to this state file. Saving here as well would overwrite its autosaves.");
+                ui.small(lock::lock_path(&persist::active_state_path()).display().to_string());
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Open Read-Only").clicked() { read_only = true; }
+                    if ui.button("Take Over").on_hover_text("Save here from now on; the other instance stops saving").clicked() { take_over = true; }
+                    if ui.button("Quit").clicked() { quit = true; }
+                });
+            });
+        if read_only {
+            self.lock_conflict = None;
+        } else if take_over {
+            match StateLock::take_over(&persist::active_state_path()) {
+                Ok(l) => {
+                    log::warn!("Took over the state lock from {}", owner.describe());
+                    self.state_lock = Some(l);
+                    self.read_only_reason = None;
+                    self.lock_conflict = None;
+                    self.save_error = None;
+                }
+                Err(e) => self.save_error = Some(format!("Could not take over the lock: {}", e)),
+            }
+        } else if quit {
+            crate::gui::app_state::QUIT_REQUESTED.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// Move API requests onto a channel whose every delivery repaints `ctx`, so a hidden window
+    /// sleeps until a request arrives instead of polling for one.
+    pub fn wake_on_api_requests(&mut self, ctx: egui::Context) {
+        let Some(inner) = self.api_rx.take() else { return };
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for req in inner {
+                if tx.send(req).is_err() { break; }
+                ctx.request_repaint();
+            }
+        });
+        self.api_rx = Some(rx);
+    }
+
+    /// Hand the tray menu to the app; `rx` receives the actions its event thread does not
+    /// handle itself.
+    pub fn attach_tray(&mut self, menu: TrayMenu, rx: Receiver<TrayAction>) {
+        self.tray = Some(menu);
+        self.tray_rx = Some(rx);
+    }
+
+    // Address clients should use, while a server is running
+    fn api_endpoint_url(&self) -> Option<String> {
+        if !self.api_running { return None; }
+        let s = &self.app_settings;
+        if s.api_enabled {
+            Some(format!("http://{}", s.api_endpoint()))
+        } else if s.grpc_enabled {
+            let scheme = if s.grpc_tls.enabled { "https" } else { "http" };
+            Some(format!("{}://{}:{}", scheme, s.api_bind_addr, s.grpc_port))
+        } else {
+            None
+        }
+    }
+
+    fn set_api_paused(&mut self, paused: bool) {
+        if paused {
+            api::server::stop_server();
+            api::grpc::stop_grpc_server();
+            log::info!("API paused from the tray");
+        } else {
+            if self.app_settings.api_enabled && let Err(e) = api::server::start_server(&self.app_settings) {
+                self.save_error = Some(format!("API server not started: {:#}", e));
+            }
+            if self.app_settings.grpc_enabled && let Err(e) = api::grpc::start_grpc_server(&self.app_settings) {
+                self.save_error = Some(format!("gRPC server not started: {:#}", e));
+            }
+            log::info!("API resumed from the tray");
+        }
+        self.api_paused = paused;
+        self.api_running = !paused && (self.app_settings.api_enabled || self.app_settings.grpc_enabled);
+    }
+
+    // Run tray actions and refresh the tray status lines
+    fn process_tray(&mut self, ctx: &egui::Context) {
+        let actions: Vec<TrayAction> = self.tray_rx.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default();
+        for action in actions {
+            match action {
+                TrayAction::ToggleApi => self.set_api_paused(!self.api_paused),
+                TrayAction::SaveNow => { self.save_now(); }
+                TrayAction::OpenExportFolder => {
+                    let dir = self.app_settings.export_dir();
+                    if let Err(e) = crate::gui::tray::open_folder(&dir) {
+                        self.save_error = Some(format!("Could not open {}: {}", dir.display(), e));
+                    }
+                }
+                TrayAction::CopyEndpoint => match self.api_endpoint_url() {
+                    Some(url) => ctx.copy_text(url),
+                    None => self.save_error = Some("No API server is running".into()),
+                },
+                TrayAction::Hide => {
+                    crate::gui::app_state::SHOW_WINDOW.store(false, std::sync::atomic::Ordering::SeqCst);
+                    self.last_background_time = Some(Instant::now());
+                }
+                // Handled by the tray event handler
+                TrayAction::Show | TrayAction::Quit => {}
+            }
+        }
+        let status = TrayStatus {
+            endpoint: self.api_endpoint_url(),
+            paused: self.api_paused,
+            nodes: self.db.node_count(),
+            relationships: self.db.relationship_count(),
+            since_save: self.last_saved_at.map(|t| t.elapsed()),
+        };
+        if let Some(tray) = &mut self.tray {
+            tray.update(&status);
+            // Keep "Last save" current while the window is idle
+            ctx.request_repaint_after(Duration::from_secs(5));
+        }
+    }
+
+    // Last work before the process ends: refuse new API connections, answer the requests that
+    // were already accepted, then save
+    fn flush_on_exit(&mut self) {
+        for t in &self.tasks { t.cancel(); }
+        api::server::stop_accepting();
+        api::grpc::stop_grpc_server();
+        if let Some(rx) = self.api_rx.take() {
+            let drained = api::drain_requests(&rx, Duration::from_millis(300), Duration::from_secs(5), |mut req| {
+                let res = self.run_api_request(&mut req);
+                if res.as_ref().map(|o| o.mutated).unwrap_or(false) { self.mark_dirty(); }
+                let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+            });
+            if drained > 0 { log::info!("Answered {} in-flight API request(s) before exit", drained); }
+        }
+        api::server::stop_server();
+        if self.dirty && let Some(path) = self.save_now_with(NoticeStyle::Subtle) {
+            log::info!("Final state saved to {}", path.display());
+        }
+        self.maybe_backup(true);
+        self.maybe_scheduled_export(true);
+        if let Err(e) = self.session_state().save() { log::warn!("Failed to save session: {}", e); }
+        // Release the state lock for whoever opens the graph next
+        self.state_lock = None;
+    }
+
+    /// Append the graph's changes to `path` whenever it is saved.
+    pub fn log_changes_to(&mut self, path: std::path::PathBuf) {
+        self.db.changes_mut().attach(path);
+    }
+
+    /// Reopen the windows and panels of a previous run. Windows of nodes or relationships
+    /// that no longer exist are dropped.
+    pub fn restore_session(&mut self, session: SessionState) {
+        self.open_node_windows = session.open_nodes.into_iter().filter(|id| self.db.nodes.contains_key(id)).collect();
+        self.open_rel_windows = session.open_relationships.into_iter().filter(|id| self.db.relationships.contains_key(id)).collect();
+        self.sidebar_open = session.sidebar_open;
+        self.sidebar_mode = session.sidebar_mode;
+        self.prefs_tab = session.prefs_tab;
+        self.window_geometry = session.window;
+        self.window_maximized = session.window.is_some_and(|w| w.maximized);
+        self.pending_scroll = session.scroll;
+    }
+
+    fn session_state(&self) -> SessionState {
+        let mut scroll = self.pending_scroll.clone();
+        scroll.extend(self.scroll_offsets.iter().map(|(k, v)| (k.clone(), *v)));
+        SessionState {
+            open_nodes: self.open_node_windows.iter().copied().collect(),
+            open_relationships: self.open_rel_windows.iter().copied().collect(),
+            sidebar_open: self.sidebar_open,
+            sidebar_mode: self.sidebar_mode,
+            prefs_tab: self.prefs_tab,
+            window: self.window_geometry.map(|w| WindowGeometry { maximized: self.window_maximized, ..w }),
+            scroll,
+        }
+    }
+
+    // Remember where the window is, in native points so it can be handed back to the
+    // viewport builder. The normal geometry is kept while maximized or minimized.
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let zoom = ctx.zoom_factor();
+        let (outer, inner, maximized, minimized) = ctx.input(|i| {
+            let v = i.viewport();
+            (v.outer_rect, v.inner_rect, v.maximized, v.minimized)
+        });
+        if minimized == Some(true) { return; }
+        self.window_maximized = maximized.unwrap_or(false);
+        if self.window_maximized { return; }
+        if let Some(inner) = inner {
+            let pos = outer.unwrap_or(inner).min;
+            self.window_geometry = Some(WindowGeometry {
+                x: pos.x * zoom,
+                y: pos.y * zoom,
+                width: inner.width() * zoom,
+                height: inner.height() * zoom,
+                maximized: false,
+            });
+        }
+    }
+
+    // Vertical scroll area for a sidebar section, scrolled to the restored offset on first use
+    fn sidebar_scroll_area(&mut self, key: &str) -> egui::ScrollArea {
+        let area = egui::ScrollArea::vertical().id_salt(key);
+        match self.pending_scroll.remove(key) {
+            Some(y) => area.vertical_scroll_offset(y),
+            None => area,
+        }
+    }
+
+    fn save_versioned_now(&mut self) -> Option<std::path::PathBuf> {
+        let state = self.state_file();
+        match persist::save_versioned(&state) {
+            Ok(path) => {
+                self.last_save = Instant::now();
+                self.save_error = None;
+                self.last_save_info = Some(format!("Saved version {}", path.display()));
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Prominent;
+                Some(path)
+            }
+            Err(e) => {
+                self.save_error = Some(format!("Save version failed: {}", e));
+                None
+            }
+        }
+    }
+
+    // Put `id` into the slot a canvas pick or the node picker was filling. A new node waiting
+    // for its link target gets the relationship right away.
+    fn assign_pick(&mut self, target: PickTarget, id: NodeId) {
+        match target {
+            PickTarget::From => { self.create_rel_from = Some(id); self.pick_target = None; }
+            PickTarget::To => { self.create_rel_to = Some(id); self.pick_target = None; }
+            PickTarget::NewNodeTarget => {
+                // Set the target for pre-linking a new node
+                self.create_node_rel_target = Some(id);
+                if let Some(new_id) = self.pending_new_node_for_link {
+                    if new_id != id {
+                        let rel_label = if self.create_node_rel_label.trim().is_empty() { "REL".to_string() } else { self.create_node_rel_label.trim().to_string() };
+                        let rid_opt = match self.create_node_rel_direction {
+                            NewNodeRelDir::NewToExisting => self.db.add_relationship(new_id, id, rel_label, HashMap::new()),
+                            NewNodeRelDir::ExistingToNew => self.db.add_relationship(id, new_id, rel_label, HashMap::new()),
+                        };
+                        if let Some(rid) = rid_opt {
+                            self.selected = Some(SelectedItem::Rel(rid));
+                            self.record_history("Linked new node");
+                        }
+                        self.mark_dirty();
+                    }
+                    // Clear pending regardless to end the flow
+                    self.pending_new_node_for_link = None;
+                }
+                self.pick_target = None;
+            }
+            PickTarget::RelFrom(rid) | PickTarget::RelTo(rid) => {
+                self.pick_target = None;
+                let Some(rel) = self.db.relationships.get(&rid) else { return };
+                let (from, to) = if matches!(target, PickTarget::RelFrom(_)) { (id, rel.to_node) } else { (rel.from_node, id) };
+                let what = format!("Moved relationship {} to {} -> {}", rel.label, format_short_node(&self.db, from), format_short_node(&self.db, to));
+                self.move_relationship(rid, from, to, what);
+            }
+        }
+    }
+
+    // Put relationship `rid` on new endpoints, or say why it cannot go there
+    fn move_relationship(&mut self, rid: Uuid, from: NodeId, to: NodeId, what: String) {
+        match self.db.set_relationship_endpoints(rid, from, to) {
+            Ok(()) => {
+                self.re_cluster_pending = true;
+                self.mark_dirty();
+                self.record_history(what);
+            }
+            Err(e) => self.save_error = Some(format!("Could not move relationship: {}", e)),
+        }
+    }
+
+    fn open_node_picker(&mut self, target: PickTarget) {
+        self.node_picker = Some(target);
+        self.node_picker_filter.clear();
+    }
+
+    // Searchable node list for the slot in `node_picker`, for targets that are off screen
+    fn show_node_picker(&mut self, ctx: &egui::Context) {
+        let Some(target) = self.node_picker else { return };
+        let title = match target {
+            PickTarget::From => "Choose From Node",
+            PickTarget::To => "Choose To Node",
+            PickTarget::NewNodeTarget => "Choose Link Target",
+            PickTarget::RelFrom(_) => "Choose New From Node",
+            PickTarget::RelTo(_) => "Choose New To Node",
+        };
+        let mut open = true;
+        let mut chosen: Option<NodeId> = None;
+        egui::Window::new(title)
+            .id(egui::Id::new("node_picker"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let edit = ui.add(egui::TextEdit::singleline(&mut self.node_picker_filter).hint_text("Filter by label, id or metadata").desired_width(f32::INFINITY));
+                if !edit.has_focus() && self.node_picker_filter.is_empty() { edit.request_focus(); }
+                let generation = self.graph_generation;
+                let stale = self.node_picker_matches.as_ref().is_none_or(|(f, g, _)| *f != self.node_picker_filter || *g != generation);
+                if stale {
+                    self.node_picker_matches = Some((self.node_picker_filter.clone(), generation, self.db.search_node_ids(&self.node_picker_filter)));
+                }
+                let found = self.node_picker_matches.as_ref().map(|(_, _, ids)| ids.as_slice()).unwrap_or_default();
+                let key = self.create_rel_display_key.trim();
+                ui.weak(format!("{} match(es)", found.len()));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show_rows(ui, ui.text_style_height(&egui::TextStyle::Body) + 4.0, found.len(), |ui, range| {
+                    for id in &found[range] {
+                        let mut text = format_short_node(&self.db, *id);
+                        if let Some(v) = self.db.nodes.get(id).and_then(|n| n.metadata.get(key)) {
+                            text = format!("{} — {}={}", text, key, v);
+                        }
+                        if ui.selectable_label(false, text).on_hover_text(id.to_string()).clicked() { chosen = Some(*id); }
+                    }
+                });
+                // Enter takes the first match
+                if edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) { chosen = found.first().copied(); }
+            });
+        if let Some(id) = chosen {
+            self.assign_pick(target, id);
+        }
+        if chosen.is_some() || !open {
+            self.node_picker = None;
+            self.node_picker_matches = None;
+        }
+    }
+
+    // Side panel section: drawing tools and the list of annotations
+    fn annotations_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show_annotations, "Show annotations");
+        ui.horizontal(|ui| {
+            for (tool, name) in [(None, "Off"), (Some(AnnotationTool::Text), "Text"), (Some(AnnotationTool::Rect), "Rectangle"), (Some(AnnotationTool::Arrow), "Arrow")] {
+                if ui.selectable_label(self.annotation_tool == tool, name).clicked() {
+                    self.annotation_tool = tool;
+                    self.annotation_drag_start = None;
+                    if tool.is_some() { self.show_annotations = true; }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgba(&mut self.annotation_color);
+            ui.add(egui::TextEdit::singleline(&mut self.annotation_text).hint_text("Text or caption"));
+        });
+        if let Some(tool) = self.annotation_tool {
+            let hint = match tool {
+                AnnotationTool::Text => "Click the canvas to place the text (Esc to stop)",
+                _ => "Drag on the canvas to draw (Esc to stop)",
+            };
+            ui.colored_label(Color32::YELLOW, hint);
+        }
+        if self.annotations.is_empty() { return; }
+        ui.separator();
+        let mut remove: Option<Uuid> = None;
+        for a in &self.annotations {
+            let kind = match a.shape {
+                AnnotationShape::Text { .. } => "Text",
+                AnnotationShape::Rect { .. } => "Rectangle",
+                AnnotationShape::Arrow { .. } => "Arrow",
+            };
+            let caption = if a.text.is_empty() { kind.to_string() } else { format!("{}: {}", kind, a.text) };
+            ui.horizontal(|ui| {
+                let selected = self.selected_annotation == Some(a.id);
+                if ui.selectable_label(selected, caption).clicked() {
+                    self.selected_annotation = if selected { None } else { Some(a.id) };
+                }
+                if ui.small_button("Delete").clicked() { remove = Some(a.id); }
+            });
+        }
+        if let Some(a) = self.selected_annotation.and_then(|id| self.annotations.iter_mut().find(|a| a.id == id)) {
+            let mut color = Color32::from_rgb(a.color.0, a.color.1, a.color.2);
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                changed |= ui.color_edit_button_srgba(&mut color).changed();
+                changed |= ui.text_edit_singleline(&mut a.text).changed();
+            });
+            a.color = (color.r(), color.g(), color.b());
+            if changed { self.mark_dirty(); }
+        }
+        if let Some(id) = remove {
+            self.annotations.retain(|a| a.id != id);
+            if self.selected_annotation == Some(id) { self.selected_annotation = None; }
+            self.mark_dirty();
+        }
+    }
+
+    /// Clear all selections and related transient UI state
+    // The graph's parts plus the layout maps this window keeps per node
+    fn measure_memory(&self) -> MemoryReport {
+        let mut report = memory::measure(&self.db);
+        let mut positions = Usage::new("Positions");
+        positions.count = self.node_positions.len();
+        positions.add_map(&self.node_positions);
+        positions.add_map(&self.node_velocities);
+        report.parts.push(positions);
+        report
+    }
+
+    // Give back what the graph and the layout maps hold beyond their contents
+    fn compact_memory(&mut self) {
+        let before = self.measure_memory().total();
+        memory::compact(&mut self.db);
+        self.node_positions.shrink_to_fit();
+        self.node_velocities.shrink_to_fit();
+        let report = self.measure_memory();
+        self.last_save_info = Some(format!("Compacted: {} given back", memory::format_bytes(before.saturating_sub(report.total()))));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.memory_report = Some(report);
+    }
+
+    // Stop the layout simulation until something sets `converge_start` again
+    fn sleep_physics(&mut self) {
+        self.converge_start = None;
+        for v in self.node_velocities.values_mut() { *v = Vec2::ZERO; }
+    }
+
+    fn deselect_all(&mut self) {
+        self.selected = None;
+        self.dragging = None;
+        self.hover_node = None;
+        self.hover_rel = None;
+        self.multi_selected_nodes.clear();
+        self.multi_selected_rels.clear();
+        self.query_selected_nodes.clear();
+        self.query_selected_rels.clear();
+        self.pick_target = None;
+        self.create_rel_from = None;
+        self.create_rel_to = None;
+        self.pending_new_node_for_link = None;
+        self.mark_dirty();
+    }
+
+    // Get a node position if present; otherwise, initialize a reasonable default
+    // position (golden spiral around canvas center) and return it. This prevents
+    // panics when newly created nodes have not yet been laid out by ensure_layout.
+    fn get_or_init_position(&mut self, id: NodeId, rect: Rect) -> Pos2 {
+        if let Some(p) = self.node_positions.get(&id) {
+            return *p;
+        }
+        let center = rect.center();
+        let k = self.node_positions.len() as u32;
+        let pos = golden_spiral_position(center, k, rect);
+        self.node_positions.insert(id, pos);
+        pos
+    }
+
+    // Public helpers callable from native (OS) menu integrations
+    pub fn menu_save(&mut self) { self.save_now(); }
+
+    pub fn menu_save_version(&mut self) { self.save_versioned_now(); }
+
+    pub fn menu_load_latest(&mut self) {
+        match persist::load_active() {
+            Ok(Some(state)) => {
+                self.annotations = state.annotations.clone();
+                let (db, pos, pan, zoom) = state.to_runtime();
+                self.db.replace_with(db); self.node_positions = pos; self.pan = pan; self.zoom = zoom; self.swimlanes = None; self.result_view = None;
+                self.selected = None; self.open_node_windows.clear(); self.open_rel_windows.clear();
+                self.dirty = false; self.last_change = Instant::now();
+                self.reset_history("Loaded latest state");
+                self.last_save_info = Some("Loaded latest state".into());
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Prominent;
+                self.save_error = None;
+            }
+            Ok(None) => { self.save_error = Some("No active state file found".into()); }
+            Err(e) => { self.save_error = Some(format!("Load failed: {}", e)); }
+        }
+    }
+
+    pub fn menu_new_graph(&mut self) {
+        // Back up existing graph if it's non-empty
+        let had_content = !self.db.nodes.is_empty() || !self.db.relationships.is_empty();
+        if had_content { self.save_versioned_now(); }
+        self.create_restore_point("New graph");
+
+        // Reset runtime to a fresh, empty graph
+        self.db.replace_with(GraphDatabase::new());
+        self.node_positions.clear();
+        self.node_velocities.clear();
+        self.selected = None;
+        self.dragging = None;
+        self.open_node_windows.clear();
+        self.open_rel_windows.clear();
+        self.multi_selected_nodes.clear();
+        self.multi_selected_rels.clear();
+        self.pick_target = None;
+        self.create_rel_from = None;
+        self.create_rel_to = None;
+        self.pending_new_node_for_link = None;
+        self.annotations.clear();
+        self.selected_annotation = None;
+        self.swimlanes = None;
+        self.result_view = None;
+        self.pan = Vec2::ZERO;
+        self.zoom = 1.0;
+        self.re_cluster_pending = true;
+        self.converge_start = Some(Instant::now());
+        self.mark_dirty();
+        self.reset_history("New graph");
+        self.save_error = None;
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+        self.last_save_info = Some(
+            if had_content { "Created new empty graph (backup saved)" } else { "Created new empty graph" }
+                .to_string(),
+        );
+    }
+
+    // Replace the graph with a built-in template (current graph is backed up as a version first)
+    pub fn menu_new_from_template(&mut self, template: Template) {
+        self.menu_new_graph();
+        self.db.replace_with(template.build());
+        self.reset_history(&format!("New from template: {}", template.name()));
+        self.last_save_info = Some(format!(
+            "Created '{}' ({} nodes, {} relationships)",
+            template.name(),
+            self.db.nodes.len(),
+            self.db.relationships.len()
+        ));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
+
+    // Build a synthetic graph, either in place of the current one (backed up first) or added to it
+    pub fn menu_generate_graph(&mut self, generator: &Generator, replace: bool) {
+        if replace {
+            self.menu_new_graph();
+            self.db.replace_with(generator.generate());
+            self.reset_history(&format!("Generated {}", generator.model.name()));
+        } else {
+            generator.generate_into(&mut self.db);
+            self.re_cluster_pending = true;
+            self.converge_start = Some(Instant::now());
+            self.mark_dirty();
+            self.record_history(format!("Generated {}", generator.model.name()));
+        }
+        self.last_save_info = Some(format!(
+            "Generated {} ({} nodes, {} relationships in the graph)",
+            generator.model.name(),
+            self.db.nodes.len(),
+            self.db.relationships.len()
+        ));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
+
+    pub fn menu_reset_view(&mut self) {
+        self.move_camera((1.0, Vec2::ZERO));
+    }
+
+    // Ease the view to `target` (zoom, pan); input on the canvas cuts the move short. Before the
+    // canvas has been laid out there is nothing to animate.
+    fn move_camera(&mut self, target: (f32, Vec2)) {
+        match self.last_canvas_rect {
+            Some(canvas) => self.camera = Some(CameraMove::new(canvas.center(), self.zoom, self.pan, target, Instant::now())),
+            None => {
+                (self.zoom, self.pan) = target;
+                self.mark_dirty();
+            }
+        }
+    }
+
+    // Ease the view to show every node of `ids` that has a position
+    fn fit_nodes(&mut self, ids: impl IntoIterator<Item = NodeId>) {
+        let points: Vec<Pos2> = ids.into_iter().filter_map(|id| self.node_positions.get(&id).copied()).collect();
+        if let (Some(canvas), false) = (self.last_canvas_rect, points.is_empty()) {
+            self.move_camera(navigation::fit_view(Rect::from_points(&points), canvas));
+        }
+    }
+
+    // Ease the view to put `id` in the middle without changing the zoom
+    fn center_on(&mut self, id: NodeId) {
+        if let (Some(canvas), Some(p)) = (self.last_canvas_rect, self.node_positions.get(&id).copied()) {
+            self.move_camera((self.zoom, (canvas.center() - p) * self.zoom));
+        }
+    }
+
+    // Leave an eased move where it is, keeping the view it reached
+    fn stop_camera(&mut self) {
+        if self.camera.take().is_some() { self.mark_dirty(); }
+    }
+
+    // The layout of the whole graph, also while the result view holds the canvas
+    fn full_layout(&self) -> &HashMap<NodeId, Pos2> {
+        self.result_view.as_ref().map_or(&self.node_positions, |v| &v.saved_positions)
+    }
+
+    // Pan and zoom of the whole graph
+    fn full_view(&self) -> (Vec2, f32) {
+        self.result_view.as_ref().map_or((self.pan, self.zoom), |v| (v.saved_pan, v.saved_zoom))
+    }
+
+    // What the canvas draws and lays out: the matches in the result view, else everything
+    fn shown_graph(&self) -> &GraphDatabase {
+        self.result_view.as_ref().map_or(&*self.db, |v| &v.graph)
+    }
+
+    // Limit the canvas to the query matches, ringed around the middle of the view, and set
+    // the whole layout aside. Without matches the whole graph comes back.
+    fn open_result_view(&mut self) {
+        self.close_result_view();
+        if self.query_selected_nodes.is_empty() && self.query_selected_rels.is_empty() { return; }
+        let center = self.last_canvas_rect.map_or(Pos2::ZERO, |r| r.center() - self.pan / self.zoom);
+        let saved = std::mem::take(&mut self.node_positions);
+        let (view, positions) = ResultView::open(&self.db, &self.query_selected_nodes, &self.query_selected_rels, saved, self.pan, self.zoom, center);
+        self.show_view(view, positions);
+    }
+
+    // Show the named view `name` as its own canvas tab, in place of the whole graph
+    fn open_named_view(&mut self, name: &str) {
+        let graph = match views::evaluate_named(&mut self.db, name) {
+            Ok(g) => g,
+            Err(e) => {
+                self.save_error = Some(e.to_string());
+                return;
+            }
+        };
+        self.close_result_view();
+        let center = self.last_canvas_rect.map_or(Pos2::ZERO, |r| r.center() - self.pan / self.zoom);
+        let saved = std::mem::take(&mut self.node_positions);
+        let (view, positions) = ResultView::named(name, self.db.changes().last_seq(), graph, saved, self.pan, self.zoom, center);
+        self.show_view(view, positions);
+    }
+
+    // Bring the shown view up to date with the graph. A named view is evaluated again only
+    // after data changes, and closes once it no longer evaluates.
+    fn refresh_result_view(&mut self) {
+        let seq = self.db.changes().last_seq();
+        let Some(view) = &mut self.result_view else { return };
+        let Some(name) = view.name.clone() else {
+            view.refresh(&self.db);
+            return;
+        };
+        if view.evaluated_at == seq { return; }
+        match views::evaluate_named(&mut self.db, &name) {
+            Ok(graph) => {
+                if let Some(view) = &mut self.result_view {
+                    view.graph = graph;
+                    view.evaluated_at = seq;
+                }
+            }
+            Err(e) => {
+                self.close_result_view();
+                self.save_error = Some(e.to_string());
+            }
+        }
+    }
+
+    // The name of the view whose tab is shown, if any
+    fn named_view(&self) -> Option<&str> {
+        self.result_view.as_ref().and_then(|v| v.name.as_deref())
+    }
+
+    // Tabs above the canvas: the whole graph, the query result view while open, and one per
+    // named view
+    fn show_view_tabs(&mut self, ui: &mut egui::Ui) {
+        let shown = self.named_view().map(str::to_string);
+        let mut open: Option<Option<String>> = None;
+        ui.horizontal(|ui| {
+            if ui.selectable_label(self.result_view.is_none(), "Graph").clicked() { open = Some(None); }
+            if self.result_view.is_some() && shown.is_none() { let _ = ui.selectable_label(true, "Query Result"); }
+            for v in &self.db.views {
+                let tab = ui.selectable_label(shown.as_deref() == Some(v.name.as_str()), &v.name).on_hover_text(v.to_string());
+                if tab.clicked() { open = Some(Some(v.name.clone())); }
+            }
+            if ui.small_button("+").on_hover_text("Define a view").clicked() { self.show_views_window = true; }
+        });
+        ui.separator();
+        match open {
+            Some(None) => self.close_result_view(),
+            Some(Some(name)) if shown.as_deref() != Some(name.as_str()) => self.open_named_view(&name),
+            _ => {}
+        }
+    }
+
+    // Hand the canvas to `view`, starting from `positions`
+    fn show_view(&mut self, view: ResultView, positions: HashMap<NodeId, Pos2>) {
+        self.node_positions = positions;
+        self.node_velocities.clear();
+        self.result_view = Some(view);
+        self.result_view_generation = self.graph_generation;
+        self.converge_start = Some(Instant::now());
+        self.fit_nodes(self.node_positions.keys().copied().collect::<Vec<_>>());
+    }
+
+    // Put the whole graph back as it was before the result view
+    fn close_result_view(&mut self) {
+        let Some(view) = self.result_view.take() else { return };
+        self.node_positions = view.saved_positions;
+        self.node_velocities.clear();
+        self.move_camera((view.saved_zoom, view.saved_pan));
+        self.converge_start = Some(Instant::now());
+    }
+
+    // The bulk selection plus the node whose details are open
+    fn selected_node_ids(&self) -> HashSet<NodeId> {
+        let mut ids = self.multi_selected_nodes.clone();
+        if let Some(SelectedItem::Node(id)) = self.selected { ids.insert(id); }
+        ids
+    }
+
+    pub fn menu_switch_profile(&mut self, name: &str) {
+        let mut next = self.app_settings.clone();
+        if !next.apply_profile(name) {
+            self.save_error = Some(format!("Unknown settings profile '{}'", name));
+            return;
+        }
+        if let Err(e) = next.save() {
+            self.save_error = Some(format!("Failed to save settings: {}", e));
+            return;
+        }
+        self.apply_app_settings(next);
+        self.last_save_info = Some(format!("Switched to profile '{}'", name));
+        self.last_info_time = Some(Instant::now());
+        self.last_info_style = NoticeStyle::Prominent;
+    }
+
+    pub fn menu_open_prefs(&mut self) {
+        // Prepare editable copy and open the window
+        self.prefs_edit = self.app_settings.clone();
+        self.prefs_autosave_override_str = match &self.prefs_edit.autosave_override {
+            Some(p) => p.display().to_string(),
+            None => String::new(),
+        };
+        self.prefs_export_override_str = match &self.prefs_edit.export_override {
+            Some(p) => p.display().to_string(),
+            None => String::new(),
+        };
+        self.prefs_api_log_override_str = match &self.prefs_edit.api_log_override {
+            Some(p) => p.display().to_string(),
+            None => String::new(),
+        };
+        self.prefs_watch_folder_str = match &self.prefs_edit.watch_folder_override {
+            Some(p) => p.display().to_string(),
+            None => String::new(),
+        };
+        self.prefs_ip_filter_strs = [
+            self.prefs_edit.api_ip_filter.allow.join("\n"),
+            self.prefs_edit.api_ip_filter.deny.join("\n"),
+            self.prefs_edit.grpc_ip_filter.allow.join("\n"),
+            self.prefs_edit.grpc_ip_filter.deny.join("\n"),
+        ];
+        self.prefs_role_strs = self
+            .prefs_edit
+            .api_roles
+            .iter()
+            .map(|r| [r.name.clone(), r.key.clone(), r.create.join(", "), r.edit.join(", "), r.delete.join(", ")])
+            .collect();
+        self.prefs_status = None;
+        self.show_prefs_window = true;
+    }
+
+    // Best-effort write of the in-memory graph to the recovery file after a panic
+    fn emergency_save(&self) {
+        let state = self.state_file();
+        match persist::save_recovery(&state) {
+            Ok(path) => log::error!("Emergency save written to {}", path.display()),
+            Err(e) => log::error!("Emergency save failed: {}", e),
+        }
+    }
+
+    // One frame of UI and background work; wrapped by `update` so a panic triggers an emergency save
+    fn update_frame(&mut self, ctx: &egui::Context) {
+        self.repaint_ctx = Some(ctx.clone());
+        if self.applied_ui_scale != Some(self.app_settings.ui_scale) {
+            ctx.set_zoom_factor(self.app_settings.ui_scale.clamp(0.5, 3.0));
+            self.applied_ui_scale = Some(self.app_settings.ui_scale);
+        }
+        self.poll_tasks(ctx);
+        self.track_window_geometry(ctx);
+        self.tour_anchors.clear();
+        // Detect if the window was shown externally (e.g. by another instance using Win32 API)
+        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
+            let cooldown_passed = self.last_background_time
+                .map(|t| t.elapsed() > Duration::from_secs(2))
+                .unwrap_or(true);
+
+            if cooldown_passed && ctx.input(|i| i.viewport().focused == Some(true)) {
+                // Double check focus over 100ms to avoid transient reports during backgrounding
+                match self.first_focused_observed {
+                    Some(t) if t.elapsed() >= Duration::from_millis(100) => {
+                        crate::gui::app_state::SHOW_WINDOW.store(true, std::sync::atomic::Ordering::SeqCst);
+                        self.first_focused_observed = None;
+                    }
+                    Some(_) => {
+                        // Still waiting for 100ms to pass
+                        ctx.request_repaint(); // Keep checking
+                    }
+                    None => {
+                        self.first_focused_observed = Some(Instant::now());
+                        ctx.request_repaint();
+                    }
+                }
+            } else {
+                self.first_focused_observed = None;
+            }
+        } else {
+            self.first_focused_observed = None;
+        }
+
+        // Quit from the tray or a termination signal; `on_exit` flushes before the process ends
+        let quitting = crate::gui::app_state::QUIT_REQUESTED.load(std::sync::atomic::Ordering::SeqCst);
+        if quitting && !ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        self.process_tray(ctx);
+
+        // Handle window close event for backgrounding
+        if ctx.input(|i| i.viewport().close_requested()) && !quitting {
+            if self.app_settings.background_on_close && (self.app_settings.api_enabled || self.app_settings.grpc_enabled) {
+                // Use the static from gui::app_state
+                crate::gui::app_state::SHOW_WINDOW.store(false, std::sync::atomic::Ordering::SeqCst);
+                self.last_background_time = Some(Instant::now());
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            }
+        }
+
+        // Handle window visibility and background mode
+        let show_window = crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst);
+        static LAST_SHOW_WINDOW: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+        if show_window != LAST_SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
+            if show_window {
+                // RESTORING from background
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                // Also request attention when showing from internal state change
+                ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Critical));
+                // Briefly set AlwaysOnTop here too to be safe
+                ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+
+                // Use Win32 API to force foreground on Windows
+                crate::gui::win_utils::force_foreground_window();
+
+                let ctx_clone = ctx.clone();
+                std::thread::spawn(move || {
+                    for i in 1..=5 {
+                        std::thread::sleep(std::time::Duration::from_millis(500));
+                        
+                        // If the user has hidden the window again during this loop, stop immediately
+                        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
+                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+                            break;
+                        }
+
+                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                        
+                        // Use Win32 API to force foreground on Windows
+                        #[cfg(target_os = "windows")]
+                        unsafe {
+                            let _ = windows::Win32::UI::WindowsAndMessaging::AllowSetForegroundWindow(windows::Win32::UI::WindowsAndMessaging::ASFW_ANY);
+                        }
+                        crate::gui::win_utils::force_foreground_window();
+
+                        ctx_clone.send_viewport_cmd(egui::ViewportCommand::Focus);
+
+                        // Double check after commands
+                        if !crate::gui::app_state::SHOW_WINDOW.load(std::sync::atomic::Ordering::SeqCst) {
+                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+                            break;
+                        }
+
+                        if i % 2 == 0 {
+                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Critical));
+                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+                        }
+                        if i == 4 {
+                            ctx_clone.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+                        }
+                        ctx_clone.request_repaint();
+                    }
+                });
+            } else {
+                // GOING to background
+                // On Windows, if we want the app icon to STAY in the taskbar but the window to be hidden,
+                // Minimized(true) is often better than Visible(false).
+                // However, the user said "The app icon on the taskbar also does not return as it should",
+                // implying it DOES leave the taskbar (which is what we want for "background mode").
+                // If we use Visible(false), it leaves the taskbar. 
+                // To make it come back, we MUST use Visible(true).
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            }
+            LAST_SHOW_WINDOW.store(show_window, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        if !show_window {
+            // When hidden, we don't need to update the UI at all.
+            // API requests wake us (see `wake_on_api_requests`); only the chores below need a timer.
+            let reqs: Vec<ApiRequest> = self.api_rx.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default();
+            for mut req in reqs {
+                // Execute query on GUI thread
+                let label = if req.task.is_some() { "API import" } else { "API query" };
+                let res = self.run_api_request(&mut req);
+                if res.as_ref().map(|o| o.mutated).unwrap_or(false) {
+                    self.record_history(label);
+                    self.mark_dirty();
+                }
+                let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+            }
+            self.pump_peer_sync();
+            self.process_watch_folder();
+            self.run_due_feeds();
+            self.heartbeat_state_lock();
+            self.maybe_backup(false);
+            self.maybe_scheduled_export(false);
+            if self.dirty && self.read_only_reason.is_none() && self.last_change.elapsed() >= Duration::from_secs(5) {
+                self.autosave(NoticeStyle::Subtle);
+            }
+            ctx.request_repaint_after(Duration::from_secs(2));
+            return;
+        }
+
+    // Process pending API requests (execute queries on the GUI thread safely)
+    let mut api_mutations = 0usize;
+    // Limit processing per frame to avoid freezing the GUI
+    let api_reqs: Vec<ApiRequest> = self.api_rx.as_ref().map(|rx| rx.try_iter().take(5).collect()).unwrap_or_default();
+    for mut req in api_reqs {
+        let t0 = std::time::Instant::now();
+        // Execute query on GUI thread
+        let res = self.run_api_request(&mut req);
+        let dt = t0.elapsed();
+        log::info!(
+            target: crate::logging::API_TARGET,
+            "RID={} done mutated={} dt_ms={}",
+            req.request_id,
+            res.as_ref().map(|o| o.mutated).unwrap_or(false),
+            dt.as_millis()
+        );
+        if res.as_ref().map(|o| o.mutated).unwrap_or(false) { api_mutations += 1; }
+        // Best effort respond; ignore send errors if client disconnected
+        let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+    }
+    if api_mutations > 0 {
+        self.mark_dirty();
+        self.record_history(if api_mutations == 1 { "API query".to_string() } else { format!("{} API queries", api_mutations) });
+    }
+    self.pump_peer_sync();
+        self.process_watch_folder();
+        self.run_due_feeds();
+        // Native menu command handling removed; in-window menus cover these actions
+
+        // Preferences window
+        if self.show_prefs_window {
+            let mut open = true;
+            egui::Window::new("Preferences")
+                .open(&mut open)
+                .resizable(true)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    // Tabs: App vs API
+                    ui.horizontal(|ui| {
                         let app_sel = self.prefs_tab == PrefsTab::App;
                         if ui.selectable_label(app_sel, "App Settings").clicked() { self.prefs_tab = PrefsTab::App; }
                         let api_sel = self.prefs_tab == PrefsTab::Api;
@@ -1267,294 +3872,1839 @@ impl eframe::App for GraphApp {
                     });
                     ui.separator();
 
-                    match self.prefs_tab {
-                        PrefsTab::App => {
-                            ui.heading("General");
-                            ui.separator();
+                    match self.prefs_tab {
+                        PrefsTab::App => {
+                            ui.heading("General");
+                            ui.separator();
+
+                            // Autosave directory override
+                            ui.label("Autosave directory (leave empty for OS default):");
+                            let resp = ui.text_edit_singleline(&mut self.prefs_autosave_override_str);
+                            if resp.lost_focus() {
+                                // no-op; parse on Save
+                            }
+                            if ui.button("Clear to default (OS temp)").clicked() {
+                                self.prefs_autosave_override_str.clear();
+                            }
+
+                            ui.add_space(8.0);
+                            // Export directory override
+                            ui.label("Export directory (leave empty for OS temp):");
+                            let resp2 = ui.text_edit_singleline(&mut self.prefs_export_override_str);
+                            if resp2.lost_focus() {
+                                // no-op; parse on Save
+                            }
+                            if ui.button("Clear to default (OS temp)").clicked() {
+                                self.prefs_export_override_str.clear();
+                            }
+
+                            ui.add_space(8.0);
+                            // Show where the settings file is stored on this system (read-only info)
+                            let settings_dir = AppSettings::settings_dir();
+                            ui.label("Settings save directory:");
+                            ui.monospace(settings_dir.display().to_string());
+
+                            ui.add_space(4.0);
+                            // Show effective export directory that will be used when path is not specified
+                            let eff_export = if self.prefs_export_override_str.trim().is_empty() {
+                                AppSettings::export_default_dir()
+                            } else {
+                                std::path::PathBuf::from(self.prefs_export_override_str.trim())
+                            };
+                            ui.label("Effective export default directory:");
+                            ui.monospace(eff_export.display().to_string());
+
+                            ui.separator();
+                            ui.heading("Watch Folder");
+                            ui.checkbox(&mut self.prefs_edit.watch_folder_enabled, "Auto-import JSON/CSV exports dropped into the watch folder")
+                                .on_hover_text("Imported files are merged into the active graph and moved to an 'imported' (or 'failed') subfolder.");
+                            ui.label("Watch folder (leave empty for OS temp):");
+                            let _ = ui.text_edit_singleline(&mut self.prefs_watch_folder_str);
+                            if ui.button("Clear to default (OS temp)").clicked() {
+                                self.prefs_watch_folder_str.clear();
+                            }
+                            let eff_watch = if self.prefs_watch_folder_str.trim().is_empty() {
+                                AppSettings::watch_folder_default_dir()
+                            } else {
+                                std::path::PathBuf::from(self.prefs_watch_folder_str.trim())
+                            };
+                            ui.small(format!("Effective watch folder: {}", eff_watch.display()));
+
+                            ui.separator();
+                            ui.heading("Backups");
+                            ui.horizontal(|ui| {
+                                ui.label("Versioned snapshot every");
+                                ui.add(egui::DragValue::new(&mut self.prefs_edit.backup_interval_hours).range(0..=168).suffix(" h"));
+                                ui.small("(0 = off)");
+                            });
+                            ui.checkbox(&mut self.prefs_edit.backup_on_close, "Also write a snapshot when the app closes");
+                            ui.horizontal(|ui| {
+                                ui.label("Keep the newest");
+                                ui.add(egui::DragValue::new(&mut self.prefs_edit.backup_keep).range(0..=1000));
+                                ui.label("automatic snapshots (0 = all)");
+                            });
+                            ui.small("Snapshots are skipped while the graph is unchanged. Copies saved with \"Save As\" are never deleted.");
+
+                            ui.separator();
+                            ui.heading("Scheduled Export");
+                            let scheduled = &mut self.prefs_edit.scheduled_export;
+                            ui.label("Export folder (leave empty to turn off):");
+                            let _ = ui.text_edit_singleline(&mut scheduled.dir);
+                            ui.horizontal(|ui| {
+                                ui.label("Format:");
+                                egui::ComboBox::from_id_salt("scheduled_export_format")
+                                    .selected_text(scheduled.format.name())
+                                    .show_ui(ui, |ui| {
+                                        for f in FileFormat::ALL { ui.selectable_value(&mut scheduled.format, f, f.name()); }
+                                    });
+                                if scheduled.format != FileFormat::Json {
+                                    ui.label("Template:");
+                                    egui::ComboBox::from_id_salt("scheduled_export_template")
+                                        .selected_text(scheduled.template.clone().unwrap_or_else(|| "None".into()))
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut scheduled.template, None, "None");
+                                            for t in &self.app_settings.export_templates {
+                                                ui.selectable_value(&mut scheduled.template, Some(t.name.clone()), &t.name);
+                                            }
+                                        });
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Export every");
+                                ui.add(egui::DragValue::new(&mut scheduled.interval_minutes).range(0..=10_080).suffix(" min"));
+                                ui.small("(0 = off)");
+                            });
+                            ui.checkbox(&mut scheduled.on_close, "Also export when the app closes");
+                            if !scheduled.dir.trim().is_empty() {
+                                ui.small(format!("Writes {} (replaced whole each time; skipped while the graph is unchanged)", export_schedule::first_file(scheduled).display()));
+                            }
+
+                            ui.separator();
+                            ui.heading("Query Matching");
+                            ui.checkbox(&mut self.prefs_edit.query_case_insensitive, "Case-insensitive labels and string comparisons")
+                                .on_hover_text("Applies to MATCH labels/types, pattern properties, WHERE comparisons and CONTAINS / STARTS WITH / ENDS WITH.");
+                            ui.checkbox(&mut self.prefs_edit.query_locale_collation, "Locale-aware ORDER BY (accents and case only break ties)");
+                            ui.small("Override per query with a prefix, e.g. CYPHER caseInsensitive=true collation=locale MATCH ...");
+
+                            ui.separator();
+                            ui.heading("Rendering / LOD");
+                            ui.checkbox(&mut self.prefs_edit.lod_enabled, "Enable level-of-detail (LOD)");
+                            ui.add(egui::Slider::new(&mut self.prefs_edit.lod_label_min_zoom, 0.1..=3.0).text("Label min zoom"));
+                            ui.add(egui::Slider::new(&mut self.prefs_edit.lod_hide_labels_node_threshold, 0..=5000).text("Hide labels above N nodes"));
+
+                            ui.separator();
+                            ui.heading("Scale");
+                            ui.add(egui::Slider::new(&mut self.prefs_edit.ui_scale, 0.5..=3.0).step_by(0.05).text("Interface scale"))
+                                .on_hover_text("Size of menus, panels and windows. Ctrl +/- adjusts it temporarily.");
+                            ui.add(egui::Slider::new(&mut self.prefs_edit.canvas_font_scale, 0.5..=3.0).step_by(0.05).text("Canvas text size"))
+                                .on_hover_text("Node, relationship and annotation text on the canvas, on top of the zoom level.");
+
+                            ui.separator();
+                            ui.heading("Navigation");
+                            ui.checkbox(&mut self.prefs_edit.trackpad_pans, "Two-finger trackpad scrolling pans the canvas")
+                                .on_hover_text("Pinch to zoom. Mouse wheels zoom either way; turn this off to make trackpad scrolling zoom as well.");
+                            ui.checkbox(&mut self.prefs_edit.kinetic_panning, "Kinetic panning")
+                                .on_hover_text("After a quick drag of the background the canvas glides on and slows down.");
+
+                            ui.separator();
+                            ui.heading("Colors");
+                            ui.horizontal(|ui| {
+                                ui.label("Palette");
+                                egui::ComboBox::from_id_salt("prefs_color_palette")
+                                    .selected_text(palette::name(self.prefs_edit.color_palette))
+                                    .show_ui(ui, |ui| {
+                                        for (kind, name) in palette::ALL {
+                                            ui.selectable_value(&mut self.prefs_edit.color_palette, kind, name);
+                                        }
+                                    });
+                            });
+                            ui.horizontal(|ui| {
+                                // Swatches of the label colors followed by the highlight colors
+                                let pal = palette::palette(self.prefs_edit.color_palette);
+                                let highlights = [pal.selected, pal.selected_rel, pal.query_match, pal.multi_select, pal.pick_from, pal.pick_to];
+                                for (i, c) in pal.labels.iter().chain(highlights.iter()).enumerate() {
+                                    if i == pal.labels.len() { ui.add_space(8.0); }
+                                    let (rect, _) = ui.allocate_exact_size(Vec2::splat(14.0), Sense::hover());
+                                    ui.painter().rect_filled(rect, 2.0, *c);
+                                }
+                            });
+                            ui.add(egui::Slider::new(&mut self.prefs_edit.label_min_contrast, 0.0..=7.0).step_by(0.5).text("Minimum label contrast"))
+                                .on_hover_text("Node labels are lightened or darkened to keep at least this contrast ratio against the canvas. 4.5 meets WCAG AA; 0 keeps the palette colors.");
+
+                            ui.separator();
+                            ui.heading("Background Mode");
+                            ui.checkbox(&mut self.prefs_edit.background_on_close, "Continue running in background when window is closed")
+                                .on_hover_text("If enabled, closing the window will not stop the API server. You can restore the window from the system tray icon.");
+                        }
+                        PrefsTab::Api => {
+                            ui.heading("API Service");
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.prefs_edit.api_enabled, "Enable HTTP/WS API Server");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.prefs_edit.grpc_enabled, "Enable gRPC Server");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Bind address");
+                                ui.text_edit_singleline(&mut self.prefs_edit.api_bind_addr);
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("HTTP Port");
+                                let mut port = self.prefs_edit.api_port as i32;
+                                if ui.add(egui::DragValue::new(&mut port).range(1..=65535)).changed() {
+                                    self.prefs_edit.api_port = port as u16;
+                                }
+                                ui.label(format!("Endpoint: {}", self.prefs_edit.api_endpoint()));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("gRPC Port");
+                                let mut gport = self.prefs_edit.grpc_port as i32;
+                                if ui.add(egui::DragValue::new(&mut gport).range(1..=65535)).changed() {
+                                    self.prefs_edit.grpc_port = gport as u16;
+                                }
+                                ui.label(format!("Endpoint: {}:{}", self.prefs_edit.api_bind_addr, self.prefs_edit.grpc_port));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("API Key (optional)");
+                                let mut key = self.prefs_edit.api_key.clone().unwrap_or_default();
+                                if ui.text_edit_singleline(&mut key).changed() {
+                                    if key.trim().is_empty() { self.prefs_edit.api_key = None; } else { self.prefs_edit.api_key = Some(key.clone()); }
+                                }
+                                if ui.button("Clear").clicked() { self.prefs_edit.api_key = None; }
+                            });
+                            ui.checkbox(&mut self.prefs_edit.grpc_tls.enabled, "Serve gRPC over TLS")
+                                .on_hover_text("Stored per settings profile.");
+                            if self.prefs_edit.grpc_tls.enabled {
+                                egui::Grid::new("grpc_tls_grid").num_columns(2).show(ui, |ui| {
+                                    ui.label("Server certificate (PEM)");
+                                    ui.text_edit_singleline(&mut self.prefs_edit.grpc_tls.cert_file);
+                                    ui.end_row();
+                                    ui.label("Private key (PEM)");
+                                    ui.text_edit_singleline(&mut self.prefs_edit.grpc_tls.key_file);
+                                    ui.end_row();
+                                    ui.label("Client CA (PEM, optional)");
+                                    ui.text_edit_singleline(&mut self.prefs_edit.grpc_tls.client_ca_file);
+                                    ui.end_row();
+                                });
+                                ui.add_enabled(
+                                    !self.prefs_edit.grpc_tls.client_ca_file.trim().is_empty(),
+                                    egui::Checkbox::new(&mut self.prefs_edit.grpc_tls.require_client_cert, "Require client certificates (mTLS)"),
+                                )
+                                .on_hover_text("Clients with a certificate signed by the client CA need no API key.");
+                            }
+                            ui.checkbox(&mut self.prefs_edit.api_compression, "Compress HTTP responses (gzip/brotli)")
+                                .on_hover_text("Query results and exports are compressed when the client sends Accept-Encoding.");
+                            ui.horizontal(|ui| {
+                                ui.label("OTLP endpoint (optional)");
+                                let mut endpoint = self.prefs_edit.otlp_endpoint.clone().unwrap_or_default();
+                                let edit = egui::TextEdit::singleline(&mut endpoint).hint_text("http://localhost:4317");
+                                if ui.add(edit).on_hover_text("Request traces go to this OpenTelemetry collector. Takes effect after a restart, in builds with the `otel` feature.").changed() {
+                                    self.prefs_edit.otlp_endpoint = if endpoint.trim().is_empty() { None } else { Some(endpoint.clone()) };
+                                }
+                            });
+
+                            ui.add_space(6.0);
+                            ui.label("Request limits (larger requests are rejected with a 4xx error):");
+                            egui::Grid::new("api_limits_grid").num_columns(2).show(ui, |ui| {
+                                ui.label("Max request body (KiB)");
+                                let mut kib = self.prefs_edit.api_max_body_bytes / 1024;
+                                if ui.add(egui::DragValue::new(&mut kib).range(1..=1024 * 1024)).changed() {
+                                    self.prefs_edit.api_max_body_bytes = kib * 1024;
+                                }
+                                ui.end_row();
+                                ui.label("Max import body (MiB)");
+                                let mut mib = self.prefs_edit.api_max_import_bytes / (1024 * 1024);
+                                if ui.add(egui::DragValue::new(&mut mib).range(1..=4096)).changed() {
+                                    self.prefs_edit.api_max_import_bytes = mib * 1024 * 1024;
+                                }
+                                ui.end_row();
+                                ui.label("Max query length (bytes)");
+                                ui.add(egui::DragValue::new(&mut self.prefs_edit.api_max_query_len).range(1..=16 * 1024 * 1024));
+                                ui.end_row();
+                                ui.label("Max parameters per query");
+                                ui.add(egui::DragValue::new(&mut self.prefs_edit.api_max_params).range(0..=100_000));
+                                ui.end_row();
+                            });
+
+                            ui.add_space(6.0);
+                            ui.label("Client IP filter (CIDR blocks or addresses, one per line; deny wins, empty allow admits all):");
+                            egui::Grid::new("api_ip_filter_grid").num_columns(3).show(ui, |ui| {
+                                ui.label("");
+                                ui.label("Allow");
+                                ui.label("Deny");
+                                ui.end_row();
+                                for (row, name) in ["HTTP/WS", "gRPC"].iter().enumerate() {
+                                    ui.label(*name);
+                                    for col in 0..2 {
+                                        ui.add(
+                                            egui::TextEdit::multiline(&mut self.prefs_ip_filter_strs[row * 2 + col])
+                                                .desired_rows(2)
+                                                .desired_width(180.0)
+                                                .hint_text("e.g. 10.0.0.0/8"),
+                                        );
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+
+                            ui.add_space(6.0);
+                            ui.label("Roles: further API keys that may only write some labels (comma-separated, * for all). Admin endpoints and peer sync need the main key.");
+                            let mut remove_role = None;
+                            egui::Grid::new("api_roles_grid").num_columns(6).show(ui, |ui| {
+                                for h in ["Role", "API key", "Create", "Edit", "Delete", ""] { ui.label(h); }
+                                ui.end_row();
+                                for (i, row) in self.prefs_role_strs.iter_mut().enumerate() {
+                                    ui.add(egui::TextEdit::singleline(&mut row[0]).desired_width(90.0));
+                                    ui.add(egui::TextEdit::singleline(&mut row[1]).password(true).desired_width(110.0));
+                                    for text in &mut row[2..] {
+                                        ui.add(egui::TextEdit::singleline(text).desired_width(110.0).hint_text("none"));
+                                    }
+                                    if ui.small_button("🗑").on_hover_text("Remove this role").clicked() { remove_role = Some(i); }
+                                    ui.end_row();
+                                }
+                            });
+                            if let Some(i) = remove_role { self.prefs_role_strs.remove(i); }
+                            if ui.button("Add Role").clicked() {
+                                self.prefs_role_strs.push([String::new(), String::new(), "*".into(), "*".into(), String::new()]);
+                            }
+
+                            ui.add_space(6.0);
+                            ui.label("API log directory (leave empty for OS temp):");
+                            let _ = ui.text_edit_singleline(&mut self.prefs_api_log_override_str);
+                            if ui.button("Clear to default (OS temp)").clicked() {
+                                self.prefs_api_log_override_str.clear();
+                            }
+                            let eff_api_log = if self.prefs_api_log_override_str.trim().is_empty() {
+                                AppSettings::api_log_default_dir()
+                            } else {
+                                std::path::PathBuf::from(self.prefs_api_log_override_str.trim())
+                            };
+                            ui.small(format!("Effective API log dir: {}", eff_api_log.display()));
+                        }
+                    }
+
+                    if let Some(msg) = &self.prefs_status {
+                        ui.separator();
+                        ui.label(msg);
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            // Apply autosave path
+                            self.prefs_edit.autosave_override = if self.prefs_autosave_override_str.trim().is_empty() {
+                                None
+                            } else {
+                                Some(std::path::PathBuf::from(self.prefs_autosave_override_str.trim()))
+                            };
+                            // Apply export path
+                            self.prefs_edit.export_override = if self.prefs_export_override_str.trim().is_empty() {
+                                None
+                            } else {
+                                Some(std::path::PathBuf::from(self.prefs_export_override_str.trim()))
+                            };
+                            // Apply API log path
+                            self.prefs_edit.api_log_override = if self.prefs_api_log_override_str.trim().is_empty() {
+                                None
+                            } else {
+                                Some(std::path::PathBuf::from(self.prefs_api_log_override_str.trim()))
+                            };
+                            // Apply watch folder path
+                            self.prefs_edit.watch_folder_override = if self.prefs_watch_folder_str.trim().is_empty() {
+                                None
+                            } else {
+                                Some(std::path::PathBuf::from(self.prefs_watch_folder_str.trim()))
+                            };
+                            // Apply IP filters; refuse to save an entry the servers could not use
+                            let lines = |s: &str| -> Vec<String> {
+                                s.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect()
+                            };
+                            let [api_allow, api_deny, grpc_allow, grpc_deny] = &self.prefs_ip_filter_strs;
+                            self.prefs_edit.api_ip_filter = IpFilter { allow: lines(api_allow), deny: lines(api_deny) };
+                            self.prefs_edit.grpc_ip_filter = IpFilter { allow: lines(grpc_allow), deny: lines(grpc_deny) };
+                            let invalid = api::ipfilter::IpRules::parse(&self.prefs_edit.api_ip_filter)
+                                .and_then(|_| api::ipfilter::IpRules::parse(&self.prefs_edit.grpc_ip_filter))
+                                .err()
+                                .map(|e| format!("invalid IP filter entry: {}", e));
+                            let labels = |s: &str| -> Vec<String> { s.split(',').map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect() };
+                            self.prefs_edit.api_roles = self
+                                .prefs_role_strs
+                                .iter()
+                                .map(|[name, key, create, edit, delete]| Role {
+                                    name: name.trim().to_string(),
+                                    key: key.trim().to_string(),
+                                    create: labels(create),
+                                    edit: labels(edit),
+                                    delete: labels(delete),
+                                })
+                                .collect();
+                            let invalid = invalid.or_else(|| permissions::check_roles(&self.prefs_edit.api_roles, self.prefs_edit.api_key.as_deref()).err().map(|e| e.to_string()));
+                            // The recent list and export templates change outside this dialog; keep the live ones
+                            self.prefs_edit.recent_files = self.app_settings.recent_files.clone();
+                            self.prefs_edit.export_templates = self.app_settings.export_templates.clone();
+                            // Persist
+                            let saved = match invalid {
+                                Some(e) => Err(anyhow::anyhow!(e)),
+                                None => self.prefs_edit.save(),
+                            };
+                            match saved {
+                                Ok(()) => {
+                                    self.apply_app_settings(self.prefs_edit.clone());
+                                    self.last_save_info = Some("Preferences saved".into());
+                                    self.last_info_time = Some(Instant::now());
+                                    self.last_info_style = NoticeStyle::Prominent;
+                                    self.show_prefs_window = false;
+                                }
+                                Err(e) => {
+                                    self.prefs_status = Some(format!("Failed to save preferences: {}", e));
+                                }
+                            }
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.show_prefs_window = false;
+                        }
+                    });
+                });
+            if !open { self.show_prefs_window = false; }
+        }
+
+        // Export Entire Graph modal
+        if self.show_export_all_window {
+            let mut open = true;
+            egui::Window::new("Export Graph")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.label("Choose export format and destination path.");
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Format:");
+                        let mut changed = false;
+                        for kind in GraphExportKind::ALL {
+                            if ui.selectable_label(self.export_all_kind == kind, kind.name()).clicked() && self.export_all_kind != kind {
+                                self.export_all_kind = kind;
+                                changed = true;
+                            }
+                        }
+                        if changed {
+                            // Update extension hint
+                            let desired_ext = format!(".{}", self.export_all_kind.extension());
+                            if self.export_all_path.is_empty() {
+                                let now = time::OffsetDateTime::now_utc();
+                                let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
+                                let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
+                                let mut base = self.app_settings.export_dir();
+                                base.push(format!("graph_export_{}{}", stamp, desired_ext));
+                                self.export_all_path = base.display().to_string();
+                            } else {
+                                // Swap extension if present
+                                if let Some(p) = std::path::Path::new(&self.export_all_path).file_stem() {
+                                    let parent = std::path::Path::new(&self.export_all_path).parent().map(|p| p.to_path_buf()).unwrap_or_default();
+                                    let stem = p.to_string_lossy();
+                                    self.export_all_path = parent.join(format!("{}{}", stem, desired_ext)).display().to_string();
+                                }
+                            }
+                        }
+                    });
+                    if self.export_all_path.is_empty() {
+                        let now = time::OffsetDateTime::now_utc();
+                        let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
+                        let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
+                        let ext = self.export_all_kind.extension();
+                        let mut base = self.app_settings.export_dir();
+                        base.push(format!("graph_export_{}.{}", stamp, ext));
+                        self.export_all_path = base.display().to_string();
+                    }
+                    if matches!(self.export_all_kind, GraphExportKind::Parquet | GraphExportKind::Arrow) {
+                        ui.weak("Writes a nodes and a relationships table; each metadata key becomes a column typed by its values.");
+                        if !cfg!(feature = "parquet") {
+                            ui.colored_label(ui.visuals().warn_fg_color, "This build has no Parquet or Arrow support; build with --features parquet.");
+                        }
+                    }
+                    if !matches!(self.export_all_kind, GraphExportKind::Json | GraphExportKind::GraphMl) { self.export_template_picker(ui); }
+                    if let Some(name) = self.named_view() { ui.label(format!("Exports the view \"{}\" shown on the canvas.", name)); }
+                    let (nodes, rels) = (self.multi_selected_nodes.len(), self.multi_selected_rels.len());
+                    ui.add_enabled(nodes + rels > 0, egui::Checkbox::new(&mut self.export_selection_only, format!("Selection only ({} nodes, {} relationships)", nodes, rels)))
+                        .on_hover_text("Export the bulk selection as a subgraph; endpoints of selected relationships are included")
+                        .on_disabled_hover_text("Select nodes or relationships with Bulk Edit Selection first");
+                    ui.label("Save to:");
+                    ui.text_edit_singleline(&mut self.export_all_path);
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Export").clicked() {
+                            let path = std::path::PathBuf::from(self.export_all_path.clone());
+                            let template = self.selected_export_template();
+                            self.export_graph(path, self.export_all_kind, template);
+                            self.export_all_status = Some("Exporting in the background…".into());
+                        }
+                        if ui.button("Cancel").clicked() { self.show_export_all_window = false; }
+                    });
+                    if let Some(msg) = &self.export_all_status { ui.separator(); ui.small(msg.clone()); }
+                });
+            if !open { self.show_export_all_window = false; }
+        }
+
+        self.show_lock_dialog(ctx);
+
+        // Crash recovery prompt: offer to restore the emergency save from the previous run
+        if self.pending_recovery.is_some() {
+            let mut restore = false;
+            let mut discard = false;
+            egui::Window::new("Recover Unsaved Work")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    if let Some(state) = &self.pending_recovery {
+                        ui.label("Graph-Loom did not shut down cleanly last time. An emergency save was found:");
+                        ui.monospace(format!("{} nodes, {} relationships", state.db.nodes.len(), state.db.relationships.len()));
+                        ui.small(persist::recovery_state_path().display().to_string());
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() { restore = true; }
+                        if ui.button("Discard").clicked() { discard = true; }
+                    });
+                });
+            if restore {
+                if let Some(state) = self.pending_recovery.take() {
+                    // Keep the pre-restore graph as a version before replacing it
+                    if !self.db.nodes.is_empty() || !self.db.relationships.is_empty() { self.save_versioned_now(); }
+                    self.annotations = state.annotations.clone();
+                    let (db, pos, pan, zoom) = state.to_runtime();
+                    self.db.replace_with(db); self.node_positions = pos; self.pan = pan; self.zoom = zoom; self.swimlanes = None; self.result_view = None;
+                    self.selected = None; self.open_node_windows.clear(); self.open_rel_windows.clear();
+                    self.mark_dirty();
+                    self.reset_history("Restored emergency save");
+                    self.last_save_info = Some("Restored emergency save".into());
+                    self.last_info_time = Some(Instant::now());
+                    self.last_info_style = NoticeStyle::Prominent;
+                }
+                persist::discard_recovery();
+            } else if discard {
+                self.pending_recovery = None;
+                persist::discard_recovery();
+            }
+        }
+
+        // Template gallery
+        if self.show_template_window {
+            let mut open = true;
+            let mut create: Option<Template> = None;
+            egui::Window::new("New From Template")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    for (i, t) in Template::ALL.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.template_choice, i, egui::RichText::new(t.name()).strong());
+                        });
+                        ui.indent(("template_desc", i), |ui| { ui.small(t.description()); });
+                    }
+                    let mut chosen = Template::ALL[self.template_choice.min(Template::ALL.len() - 1)];
+                    if let Template::BarabasiAlbert { .. } = chosen {
+                        ui.separator();
+                        ui.add(egui::Slider::new(&mut self.template_ba_nodes, 2..=2000).text("nodes").logarithmic(true));
+                        ui.add(egui::Slider::new(&mut self.template_ba_edges, 1..=6).text("edges per new node"));
+                        ui.horizontal(|ui| {
+                            ui.label("Seed");
+                            ui.add(egui::DragValue::new(&mut self.template_ba_seed));
+                        });
+                        chosen = Template::BarabasiAlbert {
+                            nodes: self.template_ba_nodes,
+                            edges_per_node: self.template_ba_edges,
+                            seed: self.template_ba_seed,
+                        };
+                    }
+                    ui.separator();
+                    ui.small("The current graph is saved as a version before it is replaced.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Create").clicked() { create = Some(chosen); }
+                        if ui.button("Cancel").clicked() { self.show_template_window = false; }
+                    });
+                });
+            if let Some(t) = create {
+                self.menu_new_from_template(t);
+                open = false;
+            }
+            if !open { self.show_template_window = false; }
+        }
 
-                            // Autosave directory override
-                            ui.label("Autosave directory (leave empty for OS default):");
-                            let resp = ui.text_edit_singleline(&mut self.prefs_autosave_override_str);
-                            if resp.lost_focus() {
-                                // no-op; parse on Save
-                            }
-                            if ui.button("Clear to default (OS temp)").clicked() {
-                                self.prefs_autosave_override_str.clear();
-                            }
+        // Graph generator: synthetic graphs for trying out layouts and features
+        if self.show_generator_window {
+            let mut open = true;
+            let mut run: Option<bool> = None;
+            egui::Window::new("Generate Graph")
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    for (i, m) in self.generator_models.iter().enumerate() {
+                        ui.radio_value(&mut self.generator_choice, i, m.name());
+                    }
+                    ui.separator();
+                    match &mut self.generator_models[self.generator_choice] {
+                        Model::ErdosRenyi { nodes, p } => {
+                            ui.add(egui::Slider::new(nodes, 2..=2000).text("nodes").logarithmic(true));
+                            ui.add(egui::Slider::new(p, 0.0..=0.2).text("link probability").logarithmic(true));
+                        }
+                        Model::BarabasiAlbert { nodes, edges_per_node } => {
+                            ui.add(egui::Slider::new(nodes, 2..=5000).text("nodes").logarithmic(true));
+                            ui.add(egui::Slider::new(edges_per_node, 1..=6).text("edges per new node"));
+                        }
+                        Model::Grid { rows, cols } => {
+                            ui.add(egui::Slider::new(rows, 1..=100).text("rows"));
+                            ui.add(egui::Slider::new(cols, 1..=100).text("columns"));
+                        }
+                        Model::Tree { depth, branching } => {
+                            ui.add(egui::Slider::new(depth, 1..=10).text("depth"));
+                            ui.add(egui::Slider::new(branching, 1..=8).text("children per node"));
+                        }
+                    }
+                    egui::Grid::new("generator_opts").num_columns(2).show(ui, |ui| {
+                        ui.label("Node labels");
+                        ui.add(egui::TextEdit::singleline(&mut self.generator_labels).hint_text("Person:3, Company:1"))
+                            .on_hover_text("Comma-separated labels with optional weights; each node gets one at random");
+                        ui.end_row();
+                        ui.label("Relationship label");
+                        ui.text_edit_singleline(&mut self.generator_relationship);
+                        ui.end_row();
+                        ui.label("Seed");
+                        ui.add(egui::DragValue::new(&mut self.generator_seed));
+                        ui.end_row();
+                    });
+                    let count = self.generator_models[self.generator_choice].node_count();
+                    let labels = generators::parse_label_mix(&self.generator_labels);
+                    let too_big = count > MAX_GENERATED_NODES;
+                    ui.separator();
+                    match &labels {
+                        Err(e) => { ui.colored_label(Color32::RED, e.to_string()); }
+                        Ok(_) if too_big => { ui.colored_label(Color32::RED, format!("{} nodes is more than the limit of {}", count, MAX_GENERATED_NODES)); }
+                        Ok(_) => { ui.small(format!("{} nodes", count)); }
+                    }
+                    let ready = labels.is_ok() && !too_big && !self.generator_relationship.trim().is_empty();
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(ready, egui::Button::new("Replace Graph")).on_hover_text("The current graph is saved as a version first").clicked() { run = Some(true); }
+                        if ui.add_enabled(ready, egui::Button::new("Add to Graph")).clicked() { run = Some(false); }
+                    });
+                });
+            if let (Some(replace), Ok(labels)) = (run, generators::parse_label_mix(&self.generator_labels)) {
+                let generator = Generator {
+                    model: self.generator_models[self.generator_choice],
+                    labels,
+                    relationship: self.generator_relationship.trim().to_string(),
+                    seed: self.generator_seed,
+                };
+                self.menu_generate_graph(&generator, replace);
+                open = false;
+            }
+            if !open { self.show_generator_window = false; }
+        }
 
-                            ui.add_space(8.0);
-                            // Export directory override
-                            ui.label("Export directory (leave empty for OS temp):");
-                            let resp2 = ui.text_edit_singleline(&mut self.prefs_export_override_str);
-                            if resp2.lost_focus() {
-                                // no-op; parse on Save
-                            }
-                            if ui.button("Clear to default (OS temp)").clicked() {
-                                self.prefs_export_override_str.clear();
-                            }
+        // History panel: recent operations, click one to jump the graph back (or forward) to it
+        if self.show_history_window {
+            let mut open = true;
+            let mut jump: Option<usize> = None;
+            egui::Window::new("History")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([320.0, 360.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo")).clicked() { jump = Some(self.history.cursor() - 1); }
+                        if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo")).clicked() { jump = Some(self.history.cursor() + 1); }
+                        ui.small(format!("keeps last {}", crate::gui::history::HISTORY_CAPACITY));
+                    });
+                    ui.separator();
+                    egui::ScrollArea::vertical().stick_to_bottom(true).auto_shrink([false, false]).show(ui, |ui| {
+                        let cursor = self.history.cursor();
+                        for (i, e) in self.history.entries().iter().enumerate() {
+                            let ago = e.at.elapsed().as_secs();
+                            let text = format!("{}  ({}s ago)", e.label, ago);
+                            // Entries after the cursor are redo states
+                            let rich = if i > cursor { egui::RichText::new(text).weak() } else { egui::RichText::new(text) };
+                            if ui.selectable_label(i == cursor, rich).clicked() { jump = Some(i); }
+                        }
+                    });
+                });
+            if let Some(i) = jump {
+                if let Some(db) = self.history.jump_to(i) { self.restore_from_history(db); }
+            }
+            if !open { self.show_history_window = false; }
+        }
 
-                            ui.add_space(8.0);
-                            // Show where the settings file is stored on this system (read-only info)
-                            let settings_dir = AppSettings::settings_dir();
-                            ui.label("Settings save directory:");
-                            ui.monospace(settings_dir.display().to_string());
+        // Query Reference: clauses, functions and procedures with examples that can be sent to the console
+        if let Some(sections) = &self.query_reference {
+            let mut open = true;
+            let mut insert: Option<String> = None;
+            egui::Window::new("Query Reference")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([460.0, 520.0])
+                .show(ctx, |ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.query_reference_filter).hint_text("filter").desired_width(f32::INFINITY));
+                    let filter = self.query_reference_filter.trim().to_lowercase();
+                    ui.separator();
+                    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                        for section in sections {
+                            let entries: Vec<_> = section.entries.iter()
+                                .filter(|e| filter.is_empty() || e.name.to_lowercase().contains(&filter) || e.summary.to_lowercase().contains(&filter))
+                                .collect();
+                            if entries.is_empty() { continue; }
+                            egui::CollapsingHeader::new(section.title).default_open(true).show(ui, |ui| {
+                                for e in entries {
+                                    ui.strong(&e.name);
+                                    ui.monospace(&e.syntax);
+                                    if !e.summary.is_empty() { ui.label(&e.summary); }
+                                    for ex in &e.examples {
+                                        ui.horizontal(|ui| {
+                                            if ui.small_button("Insert").on_hover_text("Put this example in the query console").clicked() {
+                                                insert = Some(ex.clone());
+                                            }
+                                            ui.add(egui::Label::new(egui::RichText::new(ex).monospace().weak()).wrap());
+                                        });
+                                    }
+                                    ui.add_space(6.0);
+                                }
+                            });
+                        }
+                    });
+                });
+            if let Some(q) = insert {
+                self.query_text = q;
+                self.sidebar_open = true;
+                self.sidebar_mode = SidebarMode::Query;
+            }
+            if !open { self.query_reference = None; }
+        }
 
-                            ui.add_space(4.0);
-                            // Show effective export directory that will be used when path is not specified
-                            let eff_export = if self.prefs_export_override_str.trim().is_empty() {
-                                AppSettings::export_default_dir()
-                            } else {
-                                std::path::PathBuf::from(self.prefs_export_override_str.trim())
-                            };
-                            ui.label("Effective export default directory:");
-                            ui.monospace(eff_export.display().to_string());
+        // Schema View: labels and the relationship types between them, from the graph's contents
+        if let Some(schema) = &self.schema_view {
+            let mut open = true;
+            let mut refresh = false;
+            let mut query: Option<String> = None;
+            egui::Window::new("Schema View")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([520.0, 460.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.weak(format!("{} label(s), {} relationship pattern(s)", schema.labels.len(), schema.relationships.len()));
+                        if ui.button("Refresh").clicked() { refresh = true; }
+                    });
+                    ui.separator();
+                    if schema.labels.is_empty() {
+                        ui.weak("The graph is empty.");
+                        return;
+                    }
+                    egui::CollapsingHeader::new("Patterns").default_open(false).show(ui, |ui| {
+                        egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                            for r in &schema.relationships {
+                                let keys = if r.keys.is_empty() { String::new() } else { format!("  {{{}}}", r.keys.join(", ")) };
+                                ui.monospace(format!("(:{})-[:{}]->(:{})  ×{}{}", r.from, r.label, r.to, r.count, keys));
+                            }
+                        });
+                    });
+                    if let Some(label) = schema_view::show(ui, schema, |l| self.color_for_label(l)) {
+                        query = Some(format!("MATCH (n:{}) RETURN n LIMIT 25", label));
+                    }
+                });
+            if let Some(q) = query {
+                self.query_text = q;
+                self.sidebar_open = true;
+                self.sidebar_mode = SidebarMode::Query;
+            }
+            if refresh { self.schema_view = Some(Schema::infer(&self.db)); }
+            if !open { self.schema_view = None; }
+        }
 
-                            ui.separator();
-                            ui.heading("Rendering / LOD");
-                            ui.checkbox(&mut self.prefs_edit.lod_enabled, "Enable level-of-detail (LOD)");
-                            ui.add(egui::Slider::new(&mut self.prefs_edit.lod_label_min_zoom, 0.1..=3.0).text("Label min zoom"));
-                            ui.add(egui::Slider::new(&mut self.prefs_edit.lod_hide_labels_node_threshold, 0..=5000).text("Hide labels above N nodes"));
+        // Memory Usage: what the graph, its indexes and the layout hold, and a way to trim it
+        if let Some(report) = &self.memory_report {
+            let mut open = true;
+            let mut refresh = false;
+            let mut compact = false;
+            egui::Window::new("Memory Usage")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.weak("Approximate: counted from entries and the capacity of maps and strings, without allocator overhead.");
+                    ui.separator();
+                    egui::Grid::new("memory_usage_grid").num_columns(5).striped(true).show(ui, |ui| {
+                        for h in ["Part", "Entries", "Used", "Allocated", "Spare"] { ui.strong(h); }
+                        ui.end_row();
+                        for p in &report.parts {
+                            ui.label(&p.name);
+                            ui.label(p.count.to_string());
+                            ui.label(memory::format_bytes(p.used()));
+                            ui.label(memory::format_bytes(p.bytes));
+                            ui.label(memory::format_bytes(p.spare));
+                            ui.end_row();
+                        }
+                        ui.strong("Total");
+                        ui.label("");
+                        ui.strong(memory::format_bytes(report.total() - report.spare()));
+                        ui.strong(memory::format_bytes(report.total()));
+                        ui.strong(memory::format_bytes(report.spare()));
+                        ui.end_row();
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Refresh").clicked() { refresh = true; }
+                        if ui.add_enabled(report.spare() > 0, egui::Button::new("Compact"))
+                            .on_hover_text("Shrink maps and strings to their contents")
+                            .clicked()
+                        {
+                            compact = true;
+                        }
+                    });
+                });
+            if refresh { self.memory_report = Some(self.measure_memory()); }
+            if compact { self.compact_memory(); }
+            if !open { self.memory_report = None; }
+        }
 
-                            ui.separator();
-                            ui.heading("Background Mode");
-                            ui.checkbox(&mut self.prefs_edit.background_on_close, "Continue running in background when window is closed")
-                                .on_hover_text("If enabled, closing the window will not stop the API server. You can restore the window from the system tray icon.");
+        // Import from Neo4j: run a query on a Neo4j server and merge what it returns
+        if self.show_neo4j_window {
+            let mut open = true;
+            let mut start = None;
+            let mut match_changed = false;
+            let busy = self.tasks.iter().any(|t| t.label.starts_with("Importing from "));
+            egui::Window::new("Import from Neo4j")
+                .open(&mut open)
+                .default_width(460.0)
+                .show(ctx, |ui| {
+                    ui.label("Runs the query on the server and merges the nodes, relationships and paths it returns. Importing again updates the elements an earlier import added.");
+                    if !cfg!(feature = "neo4j") {
+                        ui.colored_label(ui.visuals().warn_fg_color, "This build has no Neo4j support; build with --features neo4j.");
+                    }
+                    let src = &mut self.neo4j_source;
+                    egui::Grid::new("neo4j_source_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Server");
+                        ui.add(egui::TextEdit::singleline(&mut src.uri).hint_text("bolt://localhost:7687"));
+                        ui.end_row();
+                        ui.label("User");
+                        ui.text_edit_singleline(&mut src.user);
+                        ui.end_row();
+                        ui.label("Password");
+                        ui.add(egui::TextEdit::singleline(&mut src.password).password(true));
+                        ui.end_row();
+                        ui.label("Database");
+                        ui.add(egui::TextEdit::singleline(&mut src.database).hint_text("server default"));
+                        ui.end_row();
+                    });
+                    ui.label("Query");
+                    ui.add(egui::TextEdit::multiline(&mut src.query).code_editor().desired_rows(4).desired_width(f32::INFINITY));
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    ui.horizontal(|ui| {
+                        let ready = !busy && !src.uri.trim().is_empty();
+                        if ui.add_enabled(ready, egui::Button::new("Import")).on_disabled_hover_text("An import is running").clicked() { start = Some(false); }
+                        if ui.add_enabled(ready, egui::Button::new("Preview…")).on_hover_text(PREVIEW_HINT).on_disabled_hover_text("An import is running").clicked() { start = Some(true); }
+                        if ui.button("Default Query").clicked() { src.query = neo4j::DEFAULT_QUERY.to_string(); }
+                    });
+                    ui.weak("Progress shows in the status bar. Relationships whose endpoints the query did not return are skipped.");
+                });
+            if let Some(preview) = start { self.import_from_neo4j(preview); }
+            if match_changed { self.import_match_changed(); }
+            if !open { self.show_neo4j_window = false; }
+        }
+
+        // Import from a SQL database: map query results or foreign keys to graph elements
+        if self.show_sql_window {
+            let mut open = true;
+            let mut start = None;
+            let mut match_changed = false;
+            let busy = self.tasks.iter().any(|t| t.label.starts_with("Importing from "));
+            egui::Window::new("Import from SQL Database")
+                .open(&mut open)
+                .default_width(480.0)
+                .show(ctx, |ui| {
+                    ui.label("Reads rows from Postgres, MySQL or SQLite and merges them as nodes and relationships. Import again to refresh: elements an earlier import added are updated.");
+                    if !cfg!(feature = "sql") {
+                        ui.colored_label(ui.visuals().warn_fg_color, "This build has no SQL database support; build with --features sql.");
+                    }
+                    let src = &mut self.sql_source;
+                    egui::Grid::new("sql_source_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Database URL");
+                        ui.add(egui::TextEdit::singleline(&mut src.url).hint_text("postgres://user@localhost/shop").desired_width(320.0));
+                        ui.end_row();
+                        ui.label("Password");
+                        ui.add(egui::TextEdit::singleline(&mut src.password).password(true).hint_text("from the URL"));
+                        ui.end_row();
+                        ui.label("Map");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut src.mapping, SqlMapping::Queries, "Queries");
+                            ui.radio_value(&mut src.mapping, SqlMapping::ForeignKeys, "Foreign keys");
+                        });
+                        ui.end_row();
+                    });
+                    match src.mapping {
+                        SqlMapping::Queries => {
+                            ui.label("Node query: an id column, an optional label column; other columns become metadata");
+                            ui.add(
+                                egui::TextEdit::multiline(&mut src.node_query)
+                                    .code_editor()
+                                    .desired_rows(3)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("SELECT 'customer:' || id AS id, 'Customer' AS label, name FROM customers"),
+                            );
+                            ui.label("Edge query: source and target columns holding node ids, optional label and id columns");
+                            ui.add(
+                                egui::TextEdit::multiline(&mut src.edge_query)
+                                    .code_editor()
+                                    .desired_rows(3)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("SELECT 'customer:' || customer_id AS source, 'order:' || id AS target, 'PLACED' AS label FROM orders"),
+                            );
                         }
-                        PrefsTab::Api => {
-                            ui.heading("API Service");
-                            ui.horizontal(|ui| {
-                                ui.checkbox(&mut self.prefs_edit.api_enabled, "Enable HTTP/WS API Server");
-                            });
+                        SqlMapping::ForeignKeys => {
+                            ui.label("Every row becomes a node labelled with its table, every foreign key a relationship to the row it references.");
                             ui.horizontal(|ui| {
-                                ui.checkbox(&mut self.prefs_edit.grpc_enabled, "Enable gRPC Server");
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("Bind address");
-                                ui.text_edit_singleline(&mut self.prefs_edit.api_bind_addr);
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("HTTP Port");
-                                let mut port = self.prefs_edit.api_port as i32;
-                                if ui.add(egui::DragValue::new(&mut port).range(1..=65535)).changed() {
-                                    self.prefs_edit.api_port = port as u16;
-                                }
-                                ui.label(format!("Endpoint: {}", self.prefs_edit.api_endpoint()));
+                                ui.label("Tables");
+                                ui.add(egui::TextEdit::singleline(&mut src.tables).hint_text("all; or e.g. customers, orders").desired_width(f32::INFINITY));
                             });
-                            ui.horizontal(|ui| {
-                                ui.label("gRPC Port");
-                                let mut gport = self.prefs_edit.grpc_port as i32;
-                                if ui.add(egui::DragValue::new(&mut gport).range(1..=65535)).changed() {
-                                    self.prefs_edit.grpc_port = gport as u16;
+                        }
+                    }
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    ui.horizontal(|ui| {
+                        let ready = !busy && !src.url.trim().is_empty();
+                        if ui.add_enabled(ready, egui::Button::new("Import")).on_disabled_hover_text("An import is running").clicked() { start = Some(false); }
+                        if ui.add_enabled(ready, egui::Button::new("Preview…")).on_hover_text(PREVIEW_HINT).on_disabled_hover_text("An import is running").clicked() { start = Some(true); }
+                    });
+                    ui.weak("Progress shows in the status bar. Relationships whose endpoints were not imported are skipped.");
+                });
+            if let Some(preview) = start { self.import_from_sql(preview); }
+            if match_changed { self.import_match_changed(); }
+            if !open { self.show_sql_window = false; }
+        }
+
+        // Import a folder of Markdown notes, linked by wikilinks
+        if self.show_vault_window {
+            let mut open = true;
+            let mut start = None;
+            let mut match_changed = false;
+            let busy = self.tasks.iter().any(|t| t.label.starts_with("Importing from "));
+            egui::Window::new("Import Markdown Vault")
+                .open(&mut open)
+                .default_width(440.0)
+                .show(ctx, |ui| {
+                    ui.label("Reads every Markdown note in a folder, such as an Obsidian vault: a Note node per note and a LINKS_TO relationship per [[wikilink]] or link to another note. Import again to refresh: notes an earlier import added are updated.");
+                    ui.horizontal(|ui| {
+                        ui.label("Folder");
+                        ui.add(egui::TextEdit::singleline(&mut self.vault_dir).hint_text("/home/me/Notes").desired_width(f32::INFINITY));
+                    });
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    ui.horizontal(|ui| {
+                        let ready = !busy && !self.vault_dir.trim().is_empty();
+                        if ui.add_enabled(ready, egui::Button::new("Import")).on_disabled_hover_text("An import is running").clicked() { start = Some(false); }
+                        if ui.add_enabled(ready, egui::Button::new("Preview…")).on_hover_text(PREVIEW_HINT).on_disabled_hover_text("An import is running").clicked() { start = Some(true); }
+                    });
+                    ui.weak("Front matter and #tags become metadata, the note text its description. Links to notes that do not exist yet make Unresolved nodes.");
+                });
+            if let Some(preview) = start { self.import_from_vault(preview); }
+            if match_changed { self.import_match_changed(); }
+            if !open { self.show_vault_window = false; }
+        }
+
+        // Import a Cargo or npm dependency graph
+        if self.show_packages_window {
+            let mut open = true;
+            let mut start = None;
+            let mut match_changed = false;
+            let busy = self.tasks.iter().any(|t| t.label.starts_with("Importing from "));
+            egui::Window::new("Import Package Dependencies")
+                .open(&mut open)
+                .default_width(440.0)
+                .show(ctx, |ui| {
+                    ui.label("Reads a dependency graph: a Package node per package version and a DEPENDS_ON relationship per dependency, with versions, requirements and dependency kinds as metadata.");
+                    ui.horizontal(|ui| {
+                        ui.label("Path");
+                        ui.add(egui::TextEdit::singleline(&mut self.packages_path).hint_text("/home/me/project/Cargo.toml").desired_width(f32::INFINITY));
+                    });
+                    ui.weak("A Cargo.toml (runs cargo metadata), saved cargo metadata JSON, a package-lock.json, or a project folder holding one.");
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    ui.horizontal(|ui| {
+                        let ready = !busy && !self.packages_path.trim().is_empty();
+                        if ui.add_enabled(ready, egui::Button::new("Import")).on_disabled_hover_text("An import is running").clicked() { start = Some(false); }
+                        if ui.add_enabled(ready, egui::Button::new("Preview…")).on_hover_text(PREVIEW_HINT).on_disabled_hover_text("An import is running").clicked() { start = Some(true); }
+                    });
+                    ui.weak("Packages are identified by name and version, so projects imported into one graph share their common dependencies.");
+                });
+            if let Some(preview) = start { self.import_packages(preview); }
+            if match_changed { self.import_match_changed(); }
+            if !open { self.show_packages_window = false; }
+        }
+
+        // JSON API feeds: URLs imported with a path mapping, by hand or on a timer
+        if self.show_feeds_window {
+            let mut open = true;
+            let mut run: Option<(Feed, bool)> = None;
+            let mut match_changed = false;
+            let mut delete: Option<Uuid> = None;
+            let mut allow: Option<(Uuid, String)> = None;
+            let mut save = false;
+            egui::Window::new("JSON API Feeds")
+                .open(&mut open)
+                .default_width(520.0)
+                .show(ctx, |ui| {
+                    ui.label("Each feed fetches JSON from a URL and maps it to nodes and relationships. Feeds are saved with the graph; importing again updates what an earlier import added.");
+                    if !cfg!(feature = "rest") {
+                        ui.colored_label(ui.visuals().warn_fg_color, "This build has no HTTP import support; build with --features rest.");
+                    }
+                    if self.db.feeds.is_empty() { ui.weak("No feeds yet."); }
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    egui::Grid::new("feeds_grid").striped(true).num_columns(4).show(ui, |ui| {
+                        for feed in &self.db.feeds {
+                            let authored = self.app_settings.authored_feed(feed).authored;
+                            ui.horizontal(|ui| {
+                                ui.label(&feed.name).on_hover_text(&feed.url);
+                                if !authored {
+                                    ui.colored_label(ui.visuals().warn_fg_color, "⚠").on_hover_text(format!(
+                                        "From the opened file, not written by you: {}\nIt gets no headers or ${{VAR}} values, and runs on its timer only once allowed. Edit and save it to make it yours.",
+                                        feed.url
+                                    ));
                                 }
-                                ui.label(format!("Endpoint: {}:{}", self.prefs_edit.api_bind_addr, self.prefs_edit.grpc_port));
                             });
+                            ui.weak(if feed.interval_mins == 0 { "manual".to_string() } else { format!("every {} min", feed.interval_mins) });
+                            match self.feed_last_run.get(&feed.id) {
+                                Some(t) => ui.weak(format!("ran {}", crate::gui::tray::ago(t.elapsed()))),
+                                None => ui.weak("not run yet"),
+                            };
                             ui.horizontal(|ui| {
-                                ui.label("API Key (optional)");
-                                let mut key = self.prefs_edit.api_key.clone().unwrap_or_default();
-                                if ui.text_edit_singleline(&mut key).changed() {
-                                    if key.trim().is_empty() { self.prefs_edit.api_key = None; } else { self.prefs_edit.api_key = Some(key.clone()); }
+                                let running = self.tasks.iter().any(|t| t.label == format!("Importing from {}", feed.name));
+                                if ui.add_enabled(!running, egui::Button::new("Import Now")).clicked() { run = Some((feed.clone(), false)); }
+                                if ui.add_enabled(!running, egui::Button::new("Preview…")).on_hover_text(PREVIEW_HINT).clicked() { run = Some((feed.clone(), true)); }
+                                if !authored && feed.interval_mins > 0 && self.feeds_allowed.get(&feed.id) != Some(&feed.url) && ui.button("Allow Timer").on_hover_text(format!("Fetch {} every {} min", feed.url, feed.interval_mins)).clicked() {
+                                    allow = Some((feed.id, feed.url.clone()));
                                 }
-                                if ui.button("Clear").clicked() { self.prefs_edit.api_key = None; }
+                                if ui.button("Edit").clicked() { self.feed_draft = Some(self.app_settings.authored_feed(feed)); }
+                                if ui.small_button("🗑").on_hover_text("Delete feed").clicked() { delete = Some(feed.id); }
                             });
+                            ui.end_row();
+                        }
+                    });
+                    if self.feed_draft.is_none() && ui.button("Add Feed").clicked() {
+                        self.feed_draft = Some(Feed::new("", "", ""));
+                    }
+                    let Some(draft) = &mut self.feed_draft else { return };
+                    ui.separator();
+                    egui::Grid::new("feed_draft_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Name");
+                        ui.add(egui::TextEdit::singleline(&mut draft.name).hint_text("service inventory"));
+                        ui.end_row();
+                        ui.label("URL");
+                        ui.add(egui::TextEdit::singleline(&mut draft.url).hint_text("https://inventory.local/api/services").desired_width(360.0));
+                        ui.end_row();
+                        ui.label("Headers");
+                        ui.add(egui::TextEdit::multiline(&mut draft.headers).desired_rows(2).hint_text("Authorization: Bearer ${INVENTORY_TOKEN}"));
+                        ui.end_row();
+                        ui.label("Every");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut draft.interval_mins).range(0..=10080).suffix(" min"));
+                            ui.weak("0 = only on request");
+                        });
+                        ui.end_row();
+                    });
+                    ui.label("Mapping");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut draft.mapping)
+                            .code_editor()
+                            .desired_rows(4)
+                            .desired_width(f32::INFINITY)
+                            .hint_text("nodes .services[] id=.id label=Service team=.owner.team\nrelationships .services[] from=.id to=.depends_on[] label=DEPENDS_ON"),
+                    );
+                    ui.weak("One rule per line: `nodes` or `relationships`, a path selecting the items, then key=value pairs. Values starting with . are paths into each item. ${VAR} in the URL and headers is read from the environment. Headers are kept with your settings, not the graph file.");
+                    let problem = match FeedMapping::parse(&draft.mapping) {
+                        _ if draft.name.trim().is_empty() || draft.url.trim().is_empty() => Some("Enter a name and a URL".to_string()),
+                        Err(e) => Some(e.to_string()),
+                        Ok(_) => None,
+                    };
+                    if let Some(p) = &problem { ui.colored_label(ui.visuals().warn_fg_color, p); }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(problem.is_none(), egui::Button::new("Save")).clicked() { save = true; }
+                        if ui.button("Cancel").clicked() { self.feed_draft = None; }
+                    });
+                });
+            if save && let Some(mut feed) = self.feed_draft.take() {
+                feed.name = feed.name.trim().to_string();
+                self.app_settings.note_authored_feed(&feed);
+                if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+                feed.headers.clear();
+                match self.db.feeds.iter_mut().find(|f| f.id == feed.id) {
+                    Some(existing) => *existing = feed,
+                    None => self.db.feeds.push(feed),
+                }
+                self.mark_dirty();
+            }
+            if let Some(id) = delete {
+                self.db.feeds.retain(|f| f.id != id);
+                self.feed_last_run.remove(&id);
+                self.feeds_allowed.remove(&id);
+                if self.app_settings.authored_feeds.remove(&id).is_some() && let Err(e) = self.app_settings.save() {
+                    self.save_error = Some(format!("Failed to save settings: {}", e));
+                }
+                if self.feed_draft.as_ref().is_some_and(|d| d.id == id) { self.feed_draft = None; }
+                self.mark_dirty();
+            }
+            if let Some((id, url)) = allow { self.feeds_allowed.insert(id, url); }
+            if let Some((feed, preview)) = run { self.import_feed(feed, preview); }
+            if match_changed { self.import_match_changed(); }
+            if !open {
+                self.show_feeds_window = false;
+                self.feed_draft = None;
+            }
+        }
 
-                            ui.add_space(6.0);
-                            ui.label("API log directory (leave empty for OS temp):");
-                            let _ = ui.text_edit_singleline(&mut self.prefs_api_log_override_str);
-                            if ui.button("Clear to default (OS temp)").clicked() {
-                                self.prefs_api_log_override_str.clear();
+        // Peer Sync: share live edits with other instances
+        if self.show_sync_window {
+            let mut open = true;
+            let mut toggle = false;
+            let mut connect = false;
+            let accept_at = self.api_endpoint_url().map(|u| format!("{}/v1/sync", u.replacen("http://", "ws://", 1)));
+            egui::Window::new("Peer Sync")
+                .open(&mut open)
+                .resizable(false)
+                .default_width(420.0)
+                .show(ctx, |ui| {
+                    ui.weak("Edits made here and on connected instances are exchanged as they happen. Concurrent edits to the same property keep the latest; a delete only removes what the deleting side had seen, so anything added meanwhile stays.");
+                    ui.separator();
+                    let Some(peers) = &self.peer_sync else {
+                        if ui.button("Turn on peer sync").clicked() { toggle = true; }
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label("This replica:");
+                        ui.monospace(peers.replica_id());
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Your name");
+                        ui.add(egui::TextEdit::singleline(&mut self.sync_name).desired_width(200.0));
+                    });
+                    match &accept_at {
+                        Some(url) => { ui.label(format!("Peers can connect to {}", url)); }
+                        None => { ui.weak("Turn on the HTTP API in Preferences to accept peers."); }
+                    }
+                    ui.separator();
+                    let list = peers.peers();
+                    if list.is_empty() { ui.weak("No peers connected."); }
+                    for p in list {
+                        ui.label(format!("{}  {}", p.name, p.replica.as_deref().unwrap_or("(connecting)")));
+                    }
+                    let people = peers.participants();
+                    if !people.is_empty() {
+                        ui.separator();
+                        ui.strong("Participants");
+                        for p in people {
+                            ui.horizontal(|ui| {
+                                let color = palette::label_color(self.app_settings.color_palette, &p.replica);
+                                let (dot, _) = ui.allocate_exact_size(Vec2::splat(10.0), Sense::hover());
+                                ui.painter().circle_filled(dot.center(), 5.0, color);
+                                ui.label(&p.name);
+                                if !p.selected.is_empty() { ui.weak(format!("{} selected", p.selected.len())); }
+                            });
+                        }
+                    }
+                    if let Some(e) = &peers.last_error { ui.colored_label(egui::Color32::LIGHT_RED, e); }
+                    ui.separator();
+                    egui::Grid::new("sync_connect").num_columns(2).show(ui, |ui| {
+                        ui.label("Peer URL");
+                        ui.add(egui::TextEdit::singleline(&mut self.sync_url).hint_text("ws://host:8787/v1/sync").desired_width(280.0));
+                        ui.end_row();
+                        ui.label("API key");
+                        ui.add(egui::TextEdit::singleline(&mut self.sync_key).password(true).hint_text("if the peer requires one").desired_width(280.0));
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.sync_url.trim().is_empty(), egui::Button::new("Connect")).clicked() { connect = true; }
+                        if ui.button("Turn off").clicked() { toggle = true; }
+                    });
+                });
+            if toggle {
+                self.peer_sync = match self.peer_sync.take() {
+                    Some(_) => None,
+                    None => {
+                        let peers = sync::PeerSync::start(&self.db);
+                        let ctx = ctx.clone();
+                        peers.set_waker(move || ctx.request_repaint());
+                        Some(peers)
+                    }
+                };
+            }
+            if connect {
+                let key = Some(self.sync_key.trim()).filter(|k| !k.is_empty());
+                sync::connect(&self.sync_url, key);
+            }
+            if !open { self.show_sync_window = false; }
+        }
+
+        // Merge Graph: match an exported/saved graph against this one and settle conflicts
+        if self.show_bundle_window {
+            let mut open = true;
+            let mut run = false;
+            egui::Window::new(if self.bundle_opening { "Open Bundle" } else { "Export Bundle" })
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    if self.bundle_opening {
+                        ui.label("Replaces the current graph, layout and query history. The current graph is saved as a version first.");
+                    } else {
+                        ui.label("One file with the graph, layout, query history and display/query settings, for sharing a project.");
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.add(egui::TextEdit::singleline(&mut self.bundle_path).hint_text(format!("project.{}", bundle::BUNDLE_EXTENSION)).desired_width(360.0));
+                    });
+                    if self.bundle_opening {
+                        ui.checkbox(&mut self.bundle_apply_settings, "Apply the bundle's display and query settings");
+                    }
+                    if let Some(e) = &self.bundle_error { ui.colored_label(Color32::RED, e); }
+                    ui.separator();
+                    let action = if self.bundle_opening { "Open" } else { "Export" };
+                    if ui.add_enabled(!self.bundle_path.trim().is_empty(), egui::Button::new(action)).clicked() { run = true; }
+                });
+            if run {
+                let path = std::path::PathBuf::from(self.bundle_path.trim());
+                let res = if self.bundle_opening { self.open_bundle(&path) } else { self.export_bundle(&path) };
+                match res {
+                    Ok(()) => self.show_bundle_window = false,
+                    Err(e) => self.bundle_error = Some(format!("{}: {}", path.display(), e)),
+                }
+            }
+            if !open { self.show_bundle_window = false; }
+        }
+
+        if self.show_merge_window {
+            let mut open = true;
+            let mut analyze = false;
+            let mut apply = false;
+            egui::Window::new("Merge Graph")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([520.0, 420.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.add(egui::TextEdit::singleline(&mut self.merge_path).hint_text("state .ron or JSON/CSV export").desired_width(360.0));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Match nodes by");
+                        ui.radio_value(&mut self.merge_by_key, false, "UUID");
+                        ui.radio_value(&mut self.merge_by_key, true, "Key");
+                        ui.add_enabled(self.merge_by_key, egui::TextEdit::singleline(&mut self.merge_key).desired_width(100.0));
+                        if ui.button("Analyze").clicked() { analyze = true; }
+                    });
+                    if let Some(e) = &self.merge_error { ui.colored_label(Color32::RED, e); }
+                    let Some(plan) = self.merge_plan.as_mut() else { return };
+                    ui.separator();
+                    ui.label(format!(
+                        "Nodes: {} new, {} matched. Relationships: {} new, {} matched. {} conflict(s).",
+                        plan.new_nodes(), plan.matched_nodes(), plan.new_relationships(), plan.matched_relationships(), plan.conflicts.len()
+                    ));
+                    if !plan.conflicts.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("All:");
+                            if ui.button("Keep mine").clicked() { plan.resolve_all(Resolution::KeepMine); }
+                            if ui.button("Keep theirs").clicked() { plan.resolve_all(Resolution::KeepTheirs); }
+                            if ui.button("Merge metadata").clicked() { plan.resolve_all(Resolution::MergeMetadata); }
+                        });
+                        egui::ScrollArea::vertical().max_height(240.0).auto_shrink([false, true]).show(ui, |ui| {
+                            for (i, c) in plan.conflicts.iter_mut().enumerate() {
+                                ui.push_id(i, |ui| {
+                                    let kind = match c.kind { merge::ElementKind::Node => "Node", merge::ElementKind::Relationship => "Relationship" };
+                                    ui.strong(format!("{} {}", kind, &c.mine.to_string()[..8]));
+                                    if c.label_mine != c.label_theirs {
+                                        ui.colored_label(Color32::YELLOW, format!("label: {} → {}", c.label_mine, c.label_theirs));
+                                    }
+                                    for (k, mine, theirs) in &c.differences {
+                                        ui.colored_label(Color32::YELLOW, format!("{}: {} → {}", k, mine, theirs));
+                                    }
+                                    ui.horizontal(|ui| {
+                                        ui.radio_value(&mut c.resolution, Resolution::KeepMine, "Keep mine");
+                                        ui.radio_value(&mut c.resolution, Resolution::KeepTheirs, "Keep theirs");
+                                        ui.radio_value(&mut c.resolution, Resolution::MergeMetadata, "Merge metadata");
+                                    });
+                                    ui.separator();
+                                });
                             }
-                            let eff_api_log = if self.prefs_api_log_override_str.trim().is_empty() {
-                                AppSettings::api_log_default_dir()
+                        });
+                    }
+                    if ui.button("Apply Merge").clicked() { apply = true; }
+                });
+            if analyze { self.analyze_merge(); }
+            if apply {
+                self.apply_merge();
+                open = false;
+            }
+            if !open {
+                self.show_merge_window = false;
+                self.merge_plan = None;
+                self.merge_error = None;
+            }
+        }
+
+        // Graph Properties: title, description, owner and custom metadata of the graph itself
+        if self.show_graph_props_window {
+            let mut open = true;
+            let mut save = false;
+            egui::Window::new("Graph Properties")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([380.0, 300.0])
+                .show(ctx, |ui| {
+                    let mut remove: Option<usize> = None;
+                    egui::Grid::new("graph_props_grid").num_columns(3).show(ui, |ui| {
+                        for (i, (k, v)) in self.graph_props_edit.iter_mut().enumerate() {
+                            if i < GRAPH_METADATA_KEYS.len() {
+                                ui.label(k.as_str());
+                                if i == 1 {
+                                    ui.add(egui::TextEdit::multiline(v).desired_rows(3));
+                                } else {
+                                    ui.text_edit_singleline(v);
+                                }
                             } else {
-                                std::path::PathBuf::from(self.prefs_api_log_override_str.trim())
-                            };
-                            ui.small(format!("Effective API log dir: {}", eff_api_log.display()));
+                                ui.add(egui::TextEdit::singleline(k).hint_text("key").desired_width(100.0));
+                                ui.text_edit_singleline(v);
+                                if ui.button("-").on_hover_text("Remove key").clicked() { remove = Some(i); }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(i) = remove { self.graph_props_edit.remove(i); }
+                    if ui.button("+ Add key").clicked() { self.graph_props_edit.push((String::new(), String::new())); }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() { save = true; }
+                        ui.small(format!("{} nodes, {} relationships", self.db.nodes.len(), self.db.relationships.len()));
+                    });
+                });
+            if save {
+                let metadata: BTreeMap<String, String> = self
+                    .graph_props_edit
+                    .iter()
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+                    .collect();
+                if metadata != self.db.metadata {
+                    self.db.metadata = metadata;
+                    self.mark_dirty();
+                    self.record_history("Edited graph properties");
+                }
+                open = false;
+            }
+            if !open { self.show_graph_props_window = false; }
+        }
+
+        // Relationship constraints: toggle per-label uniqueness and audit existing duplicates
+        if self.show_constraints_window {
+            let mut open = true;
+            let mut toggle: Option<(String, bool)> = None;
+            let mut audit = false;
+            egui::Window::new("Relationship Constraints")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([380.0, 320.0])
+                .show(ctx, |ui| {
+                    ui.label("Unique labels allow at most one edge per node pair and direction.");
+                    ui.separator();
+                    let mut labels: BTreeSet<String> = self.db.relationships.values().map(|r| r.label.clone()).collect();
+                    labels.extend(self.db.unique_relationship_labels.iter().cloned());
+                    if labels.is_empty() { ui.weak("No relationship labels yet."); }
+                    for label in labels {
+                        let mut unique = self.db.is_relationship_unique(&label);
+                        if ui.checkbox(&mut unique, &label).changed() { toggle = Some((label, unique)); }
+                    }
+                    ui.separator();
+                    if ui.button("Audit").clicked() { audit = true; }
+                    if let Some(lines) = &self.constraints_audit {
+                        if lines.is_empty() {
+                            ui.label("No duplicate edges.");
+                        } else {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                for l in lines { ui.colored_label(Color32::YELLOW, l); }
+                            });
                         }
                     }
+                });
+            if let Some((label, unique)) = toggle {
+                self.db.set_relationship_unique(&label, unique);
+                self.mark_dirty();
+                self.record_history(format!("{} {} unique", if unique { "Make" } else { "Unmake" }, label));
+                self.constraints_audit = None;
+            }
+            if audit {
+                let name = |id: &NodeId| self.db.nodes.get(id).map(|n| format!("{} {}", n.label, &id.to_string()[..8])).unwrap_or_else(|| id.to_string());
+                self.constraints_audit = Some(
+                    self.db
+                        .uniqueness_violations()
+                        .iter()
+                        .map(|v| format!("{}: {} -> {} has {} edges", v.label, name(&v.from), name(&v.to), v.relationships.len()))
+                        .collect(),
+                );
+            }
+            if !open { self.show_constraints_window = false; }
+        }
 
-                    if let Some(msg) = &self.prefs_status {
-                        ui.separator();
-                        ui.label(msg);
+        // Property rules: per-label checks on node metadata, and a report of nodes breaking them
+        if self.show_rules_window {
+            let mut open = true;
+            let mut remove: Option<(String, String)> = None;
+            let mut set: Option<(String, PropertyRule)> = None;
+            let mut validate = false;
+            let mut reveal: Option<NodeId> = None;
+            let mut remove_computed: Option<(String, String)> = None;
+            let mut set_computed: Option<(String, ComputedProperty)> = None;
+            egui::Window::new("Property Rules")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([460.0, 380.0])
+                .show(ctx, |ui| {
+                    ui.label("Creates and edits that would break a rule are refused. Existing nodes are only reported.");
+                    ui.separator();
+                    if self.db.property_rules.is_empty() { ui.weak("No rules yet."); }
+                    egui::Grid::new("property_rules_grid").striped(true).show(ui, |ui| {
+                        for (label, rules) in &self.db.property_rules {
+                            for r in rules {
+                                ui.monospace(format!(":{}", label));
+                                ui.monospace(&r.key);
+                                ui.label(r.to_string());
+                                if ui.small_button("Remove").clicked() { remove = Some((label.clone(), r.key.clone())); }
+                                ui.end_row();
+                            }
+                        }
+                    });
+                    ui.separator();
+                    let (label, key, spec) = &mut self.rule_form;
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(label).hint_text("label").desired_width(100.0));
+                        ui.add(egui::TextEdit::singleline(key).hint_text("key").desired_width(100.0));
+                    });
+                    ui.add(egui::TextEdit::singleline(spec).hint_text("required, in=a|b, min=0, max=10, regex=...").desired_width(f32::INFINITY));
+                    let parsed = PropertyRule::parse(key, spec);
+                    ui.horizontal(|ui| {
+                        let ready = !label.trim().is_empty() && !spec.trim().is_empty() && parsed.is_ok();
+                        if ui.add_enabled(ready, egui::Button::new("Set Rule")).clicked()
+                            && let Ok(rule) = &parsed
+                        {
+                            set = Some((label.trim().to_string(), rule.clone()));
+                        }
+                        if !spec.trim().is_empty()
+                            && let Err(e) = &parsed
+                        {
+                            ui.colored_label(Color32::RED, e.to_string());
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Validate").on_hover_text("List existing nodes that break a rule").clicked() { validate = true; }
+                    if let Some(report) = &self.rules_report {
+                        if report.is_empty() {
+                            ui.label("Every node passes.");
+                        } else {
+                            ui.label(format!("{} problem(s); click one to open the node", report.len()));
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                for v in report {
+                                    let text = format!("(:{} {}) {} {}", v.label, &v.node.to_string()[..8], v.key, v.message);
+                                    if ui.selectable_label(false, egui::RichText::new(text).color(Color32::YELLOW)).clicked() { reveal = Some(v.node); }
+                                }
+                            });
+                        }
                     }
+                    ui.separator();
+                    ui.heading("Computed Properties");
+                    ui.label("Keys worked out from a formula over degree, in_degree, out_degree, pagerank and other keys. They refresh after changes and cannot be edited by hand.");
+                    egui::Grid::new("computed_properties_grid").striped(true).show(ui, |ui| {
+                        for (label, props) in &self.db.computed_properties {
+                            for p in props {
+                                ui.monospace(format!(":{}", label));
+                                ui.monospace(&p.key);
+                                ui.label(p.to_string());
+                                if ui.small_button("Remove").clicked() { remove_computed = Some((label.clone(), p.key.clone())); }
+                                ui.end_row();
+                            }
+                        }
+                    });
+                    let (label, key, formula, every) = &mut self.computed_form;
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(label).hint_text("label").desired_width(100.0));
+                        ui.add(egui::TextEdit::singleline(key).hint_text("key").desired_width(100.0));
+                        ui.label("refresh every");
+                        ui.add(egui::DragValue::new(every).range(0..=86_400).suffix(" s")).on_hover_text("0 refreshes after every change");
+                    });
+                    ui.add(egui::TextEdit::singleline(formula).hint_text("pagerank * 100, degree, price * quantity, ...").desired_width(f32::INFINITY));
+                    let parsed = ComputedProperty::new(key, formula, Some(*every));
+                    ui.horizontal(|ui| {
+                        let ready = !label.trim().is_empty() && !formula.trim().is_empty() && parsed.is_ok();
+                        if ui.add_enabled(ready, egui::Button::new("Set Computed Property")).clicked()
+                            && let Ok(prop) = &parsed
+                        {
+                            set_computed = Some((label.trim().to_string(), prop.clone()));
+                        }
+                        if !formula.trim().is_empty()
+                            && let Err(e) = &parsed
+                        {
+                            ui.colored_label(Color32::RED, e.to_string());
+                        }
+                    });
+                });
+            if let Some((label, key)) = remove_computed
+                && self.db.remove_computed_property(&label, &key)
+            {
+                self.mark_dirty();
+                self.record_history(format!("Stopped computing {} on {}", key, label));
+            }
+            if let Some((label, prop)) = set_computed {
+                let caption = format!("Computed {} on {}", prop.key, label);
+                self.db.set_computed_property(&label, prop);
+                self.mark_dirty();
+                self.record_history(caption);
+                self.computed_form.1.clear();
+                self.computed_form.2.clear();
+            }
+            if let Some((label, key)) = remove
+                && self.db.remove_property_rule(&label, &key)
+            {
+                self.mark_dirty();
+                self.record_history(format!("Removed rule for {} on {}", key, label));
+                self.rules_report = None;
+            }
+            if let Some((label, rule)) = set {
+                let caption = format!("Set rule for {} on {}", rule.key, label);
+                self.db.set_property_rule(&label, rule);
+                self.mark_dirty();
+                self.record_history(caption);
+                self.rule_form.1.clear();
+                self.rule_form.2.clear();
+                validate = true;
+            }
+            if validate { self.rules_report = Some(self.db.rule_violations()); }
+            if let Some(id) = reveal {
+                self.selected = Some(SelectedItem::Node(id));
+                self.open_node_windows.insert(id);
+                self.center_on(id);
+            }
+            if !open { self.show_rules_window = false; }
+        }
 
+        // Derived edges: relationships implied by equal metadata values, written or only drawn
+        if self.show_derived_window {
+            let mut open = true;
+            let mut remove: Option<String> = None;
+            let mut set: Option<DerivedEdgeRule> = None;
+            let mut refresh = false;
+            egui::Window::new("Derived Edges")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([440.0, 320.0])
+                .show(ctx, |ui| {
+                    ui.label("Connect nodes that share a metadata value. Written edges become part of the graph; virtual ones are only drawn. Both update when refreshed.");
+                    ui.separator();
+                    if self.db.derived_edges.is_empty() { ui.weak("No rules yet."); }
+                    egui::Grid::new("derived_edges_grid").striped(true).show(ui, |ui| {
+                        for r in &self.db.derived_edges {
+                            ui.label(r.to_string());
+                            if ui.small_button("Remove").on_hover_text("Remove the rule and the edges it wrote").clicked() { remove = Some(r.label.clone()); }
+                            ui.end_row();
+                        }
+                    });
                     ui.separator();
+                    let (label, key, node_label, write) = &mut self.derived_form;
                     ui.horizontal(|ui| {
-                        if ui.button("Save").clicked() {
-                            // Apply autosave path
-                            self.prefs_edit.autosave_override = if self.prefs_autosave_override_str.trim().is_empty() {
-                                None
-                            } else {
-                                Some(std::path::PathBuf::from(self.prefs_autosave_override_str.trim()))
-                            };
-                            // Apply export path
-                            self.prefs_edit.export_override = if self.prefs_export_override_str.trim().is_empty() {
-                                None
-                            } else {
-                                Some(std::path::PathBuf::from(self.prefs_export_override_str.trim()))
-                            };
-                            // Apply API log path
-                            self.prefs_edit.api_log_override = if self.prefs_api_log_override_str.trim().is_empty() {
-                                None
-                            } else {
-                                Some(std::path::PathBuf::from(self.prefs_api_log_override_str.trim()))
-                            };
-                            // Persist
-                            match self.prefs_edit.save() {
-                                Ok(()) => {
-                                    // Determine if API server config changed
-                                    let old_api = (self.app_settings.api_enabled.clone(), self.app_settings.api_bind_addr.clone(), self.app_settings.api_port, self.app_settings.api_key.clone());
-                                    let old_grpc = (self.app_settings.grpc_enabled.clone(), self.app_settings.grpc_port, self.app_settings.api_bind_addr.clone(), self.app_settings.api_key.clone());
-                                    // Detect export dir change to refresh default export paths in views
-                                    let old_export_dir = self.app_settings.export_dir();
-                                    self.app_settings = self.prefs_edit.clone();
-                                    // Apply to runtime
-                                    self.lod_enabled = self.app_settings.lod_enabled;
-                                    self.lod_label_min_zoom = self.app_settings.lod_label_min_zoom;
-                                    self.lod_hide_labels_node_threshold = self.app_settings.lod_hide_labels_node_threshold;
-                                    let new_api = (self.app_settings.api_enabled.clone(), self.app_settings.api_bind_addr.clone(), self.app_settings.api_port, self.app_settings.api_key.clone());
-                                    let new_grpc = (self.app_settings.grpc_enabled.clone(), self.app_settings.grpc_port, self.app_settings.api_bind_addr.clone(), self.app_settings.api_key.clone());
-                                    
-                                    if old_api != new_api {
-                                        // Restart server
-                                        api::server::stop_server();
-                                        if self.app_settings.api_enabled {
-                                            let _ = api::server::start_server(&self.app_settings);
-                                        }
-                                    }
+                        ui.add(egui::TextEdit::singleline(label).hint_text("relationship label").desired_width(130.0));
+                        ui.add(egui::TextEdit::singleline(key).hint_text("key").desired_width(90.0));
+                        ui.add(egui::TextEdit::singleline(node_label).hint_text("node label (any)").desired_width(110.0));
+                    });
+                    let parsed = DerivedEdgeRule::new(label, key, Some(node_label.as_str()), *write);
+                    ui.horizontal(|ui| {
+                        ui.checkbox(write, "Write into graph");
+                        if ui.add_enabled(parsed.is_ok(), egui::Button::new("Set Rule")).clicked()
+                            && let Ok(rule) = &parsed
+                        {
+                            set = Some(rule.clone());
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.db.derived_edges.is_empty(), egui::Button::new("Refresh")).clicked() { refresh = true; }
+                        ui.checkbox(&mut self.show_derived_virtual, "Draw virtual edges");
+                        ui.weak(format!("{} virtual", self.derived_virtual.len()));
+                    });
+                });
+            if let Some(label) = remove
+                && let Some(removed) = derived::remove_rule(&mut self.db, &label)
+            {
+                self.mark_dirty();
+                self.record_history(format!("Stopped deriving {} ({} edges removed)", label, removed));
+                self.derived_virtual = derived::virtual_edges(&self.db);
+            }
+            if let Some(rule) = set {
+                let caption = format!("Derived {}", rule);
+                let changes = derived::set_rule(&mut self.db, rule);
+                self.mark_dirty();
+                self.record_history(caption.clone());
+                self.derived_virtual = derived::virtual_edges(&self.db);
+                self.derived_form.0.clear();
+                self.last_save_info = Some(if changes.changed() { format!("{}: {}", caption, changes) } else { caption });
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Subtle;
+            }
+            if refresh {
+                let changes = derived::refresh(&mut self.db);
+                if changes.changed() {
+                    self.mark_dirty();
+                    self.record_history(format!("Refreshed derived edges ({})", changes));
+                }
+                self.derived_virtual = derived::virtual_edges(&self.db);
+                self.last_save_info = Some(format!("Derived edges: {}; {} virtual", changes, self.derived_virtual.len()));
+                self.last_info_time = Some(Instant::now());
+                self.last_info_style = NoticeStyle::Subtle;
+            }
+            if !open { self.show_derived_window = false; }
+        }
 
-                                    if old_grpc != new_grpc {
-                                        api::grpc::stop_grpc_server();
-                                        if self.app_settings.grpc_enabled {
-                                            let _ = api::grpc::start_grpc_server(&self.app_settings);
-                                        }
-                                    }
+        // Named views: read-only queries shown as canvas tabs and exported on their own
+        if self.show_views_window {
+            let mut open = true;
+            let mut remove: Option<String> = None;
+            let mut set: Option<GraphView> = None;
+            let mut show: Option<String> = None;
+            let mut export: Option<String> = None;
+            egui::Window::new("Views")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([480.0, 340.0])
+                .show(ctx, |ui| {
+                    ui.label("A view is a saved query that gets its own canvas tab. It holds no copy of the data and follows edits; a key rolls its matches up into one node per value.");
+                    ui.separator();
+                    if self.db.views.is_empty() { ui.weak("No views yet."); }
+                    egui::Grid::new("views_grid").striped(true).show(ui, |ui| {
+                        for v in &self.db.views {
+                            ui.strong(&v.name);
+                            ui.monospace(graph::text_preview(&v.query, 50)).on_hover_text(&v.query);
+                            ui.label(v.aggregate.as_deref().map(|k| format!("by {}", k)).unwrap_or_default());
+                            if ui.small_button("Show").clicked() { show = Some(v.name.clone()); }
+                            if ui.small_button("Export…").on_hover_text("Open the view and export it with File → Export Graph").clicked() { export = Some(v.name.clone()); }
+                            if ui.small_button("Edit").clicked() { self.view_form = (v.name.clone(), v.query.clone(), v.aggregate.clone().unwrap_or_default()); }
+                            if ui.small_button("Remove").clicked() { remove = Some(v.name.clone()); }
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+                    let (name, query, aggregate) = &mut self.view_form;
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(name).hint_text("name").desired_width(120.0));
+                        ui.add(egui::TextEdit::singleline(aggregate).hint_text("roll up by key (optional)").desired_width(160.0));
+                        if ui.button("Use Console Query").on_hover_text("Copy the query console's text").clicked() { *query = self.query_text.clone(); }
+                    });
+                    ui.add(egui::TextEdit::multiline(query).hint_text("MATCH (s:Service) WHERE s.tier = 'core' RETURN s").desired_rows(2).desired_width(f32::INFINITY).code_editor());
+                    let parsed = GraphView::new(name, query, Some(aggregate.as_str()));
+                    ui.horizontal(|ui| {
+                        let ready = !name.trim().is_empty() && !query.trim().is_empty() && parsed.is_ok();
+                        if ui.add_enabled(ready, egui::Button::new("Save View")).clicked()
+                            && let Ok(view) = &parsed
+                        {
+                            set = Some(view.clone());
+                        }
+                        if !query.trim().is_empty()
+                            && let Err(e) = &parsed
+                        {
+                            ui.colored_label(Color32::RED, e.to_string());
+                        }
+                    });
+                });
+            if let Some(name) = remove
+                && self.db.remove_view(&name)
+            {
+                if self.named_view() == Some(name.as_str()) { self.close_result_view(); }
+                self.mark_dirty();
+                self.record_history(format!("Removed view {}", name));
+            }
+            if let Some(view) = set {
+                let name = view.name.clone();
+                self.db.set_view(view);
+                self.mark_dirty();
+                self.record_history(format!("Saved view {}", name));
+                self.view_form = (String::new(), String::new(), String::new());
+                // Show what was saved, also when its tab was already open
+                self.close_result_view();
+                show = Some(name);
+            }
+            if let Some(name) = export {
+                if self.named_view() != Some(name.as_str()) { self.open_named_view(&name); }
+                self.show_export_all_window = self.named_view() == Some(name.as_str());
+            }
+            if let Some(name) = show
+                && self.named_view() != Some(name.as_str())
+            {
+                self.open_named_view(&name);
+            }
+            if !open { self.show_views_window = false; }
+        }
 
-                                    self.api_running = self.app_settings.api_enabled || self.app_settings.grpc_enabled;
-
-                                    let new_export_dir = self.app_settings.export_dir();
-                                    if old_export_dir != new_export_dir {
-                                        // If export_all_path is empty or under old dir, regenerate under new dir
-                                        let refresh_export_all = self.export_all_path.is_empty() || {
-                                            let p = std::path::Path::new(&self.export_all_path);
-                                            p.starts_with(&old_export_dir)
-                                        };
-                                        if refresh_export_all {
-                                            let now = time::OffsetDateTime::now_utc();
-                                            let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
-                                            let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
-                                            let ext = if self.export_all_is_json { "json" } else { "csv" };
-                                            let mut base = new_export_dir.clone();
-                                            base.push(format!("graph_export_{}.{}", stamp, ext));
-                                            self.export_all_path = base.display().to_string();
-                                        }
-                                        // If query_export_path is empty or under old dir, regenerate under new dir
-                                        let refresh_query = self.query_export_path.is_empty() || {
-                                            let p = std::path::Path::new(&self.query_export_path);
-                                            p.starts_with(&old_export_dir)
-                                        };
-                                        if refresh_query {
-                                            let now = time::OffsetDateTime::now_utc();
-                                            let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
-                                            let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
-                                            let ext = if self.query_export_is_json { "json" } else { "csv" };
-                                            let mut base = new_export_dir;
-                                            base.push(format!("query_export_{}.{}", stamp, ext));
-                                            self.query_export_path = base.display().to_string();
-                                        }
-                                    }
-                                    self.last_save_info = Some("Preferences saved".into());
-                                    self.last_info_time = Some(Instant::now());
-                                    self.last_info_style = NoticeStyle::Prominent;
-                                    self.show_prefs_window = false;
-                                }
-                                Err(e) => {
-                                    self.prefs_status = Some(format!("Failed to save preferences: {}", e));
+        // Logs window: recent in-memory log lines with level/text filtering
+        if self.show_logs_window {
+            let mut open = true;
+            egui::Window::new("Logs")
+                .open(&mut open)
+                .resizable(true)
+                .default_size([640.0, 360.0])
+                .show(ctx, |ui| {
+                    let entries = crate::logging::snapshot();
+                    let needle = self.logs_filter.trim().to_lowercase();
+                    let shown: Vec<&crate::logging::LogEntry> = entries
+                        .iter()
+                        .filter(|e| e.level <= self.logs_min_level)
+                        .filter(|e| !self.logs_api_only || e.target == crate::logging::API_TARGET)
+                        .filter(|e| needle.is_empty() || e.message.to_lowercase().contains(&needle) || e.target.to_lowercase().contains(&needle))
+                        .collect();
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("logs_level")
+                            .selected_text(self.logs_min_level.as_str())
+                            .show_ui(ui, |ui| {
+                                for lvl in [log::Level::Error, log::Level::Warn, log::Level::Info, log::Level::Debug, log::Level::Trace] {
+                                    ui.selectable_value(&mut self.logs_min_level, lvl, lvl.as_str());
                                 }
-                            }
+                            });
+                        ui.add(egui::TextEdit::singleline(&mut self.logs_filter).hint_text("filter").desired_width(180.0));
+                        ui.checkbox(&mut self.logs_api_only, "API only");
+                        if ui.button("Copy").clicked() {
+                            let text = shown.iter().map(|e| e.format_line()).collect::<Vec<_>>().join("\n");
+                            ctx.copy_text(text);
                         }
-                        if ui.button("Cancel").clicked() {
-                            self.show_prefs_window = false;
+                        if ui.button("Clear").clicked() { crate::logging::clear(); }
+                    });
+                    ui.small(format!("{} of {} lines (keeps last {})", shown.len(), entries.len(), crate::logging::CAPACITY));
+                    ui.separator();
+                    egui::ScrollArea::vertical().stick_to_bottom(true).auto_shrink([false, false]).show(ui, |ui| {
+                        for e in shown {
+                            let color = match e.level {
+                                log::Level::Error => Color32::from_rgb(255, 110, 110),
+                                log::Level::Warn => Color32::from_rgb(255, 200, 90),
+                                log::Level::Info => ui.visuals().text_color(),
+                                _ => Color32::GRAY,
+                            };
+                            ui.label(egui::RichText::new(e.format_line()).monospace().color(color));
                         }
                     });
                 });
-            if !open { self.show_prefs_window = false; }
+            if !open { self.show_logs_window = false; }
         }
 
-        // Export Entire Graph modal
-        if self.show_export_all_window {
+        // Drag-and-drop import: collect dropped files and ask whether to merge or replace
+        let dropped: Vec<std::path::PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if !dropped.is_empty() {
+            self.pending_drop_files.extend(dropped);
+        }
+        let hovering_files = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        if hovering_files {
+            egui::Area::new("drop_hint".into())
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.heading("Drop exported JSON/CSV files to import");
+                    });
+                });
+        }
+        if !self.pending_drop_files.is_empty() {
             let mut open = true;
-            egui::Window::new("Export Graph")
+            let mut choice: Option<bool> = None; // Some(replace)
+            let mut preview = false;
+            let mut match_changed = false;
+            let (supported, unsupported): (Vec<std::path::PathBuf>, Vec<std::path::PathBuf>) = self
+                .pending_drop_files
+                .iter()
+                .cloned()
+                .partition(|p| matches!(p.extension().and_then(|s| s.to_str()).map(|s| s.to_ascii_lowercase()).as_deref(), Some("json") | Some("csv")));
+            egui::Window::new("Import Dropped Files")
                 .open(&mut open)
                 .collapsible(false)
                 .resizable(true)
                 .show(ctx, |ui| {
-                    ui.label("Choose export format and destination path.");
+                    for p in &supported {
+                        ui.monospace(p.display().to_string());
+                    }
+                    for p in &unsupported {
+                        ui.colored_label(Color32::YELLOW, format!("{} (unsupported, will be ignored)", p.display()));
+                    }
                     ui.separator();
+                    if supported.is_empty() {
+                        ui.label("None of the dropped files can be imported. Supported: JSON and CSV exports.");
+                        if ui.button("Close").clicked() { choice = Some(false); }
+                        return;
+                    }
+                    match_changed |= import_match_ui(ui, &mut self.app_settings.import_match);
+                    ui.label("Merge into the current graph, or replace it (a backup version is saved first)?");
                     ui.horizontal(|ui| {
-                        ui.label("Format:");
-                        let mut changed = false;
-                        if ui.selectable_label(self.export_all_is_json, "JSON").clicked() {
-                            if !self.export_all_is_json { self.export_all_is_json = true; changed = true; }
-                        }
-                        if ui.selectable_label(!self.export_all_is_json, "CSV").clicked() {
-                            if self.export_all_is_json { self.export_all_is_json = false; changed = true; }
+                        if ui.button("Merge").clicked() { choice = Some(false); }
+                        if ui.button(egui::RichText::new("Replace").color(Color32::RED)).clicked() { choice = Some(true); }
+                        if ui.button("Preview Merge…").on_hover_text(PREVIEW_HINT).clicked() { preview = true; }
+                        if supported.len() == 1
+                            && ui.button("Review Merge…").on_hover_text("Match nodes by UUID or key and resolve conflicts first").clicked()
+                        {
+                            self.merge_path = supported[0].display().to_string();
+                            self.analyze_merge();
+                            self.show_merge_window = true;
+                            self.pending_drop_files.clear();
                         }
-                        if changed {
-                            // Update extension hint
-                            let desired_ext = if self.export_all_is_json { ".json" } else { ".csv" };
-                            if self.export_all_path.is_empty() {
-                                let now = time::OffsetDateTime::now_utc();
-                                let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
-                                let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
-                                let mut base = self.app_settings.export_dir();
-                                base.push(format!("graph_export_{}{}", stamp, desired_ext));
-                                self.export_all_path = base.display().to_string();
-                            } else {
-                                // Swap extension if present
-                                if let Some(p) = std::path::Path::new(&self.export_all_path).file_stem() {
-                                    let parent = std::path::Path::new(&self.export_all_path).parent().map(|p| p.to_path_buf()).unwrap_or_default();
-                                    let stem = p.to_string_lossy();
-                                    self.export_all_path = parent.join(format!("{}{}", stem, desired_ext)).display().to_string();
-                                }
-                            }
+                        if ui.button("Cancel").clicked() { self.pending_drop_files.clear(); }
+                    });
+                });
+            if let Some(replace) = choice {
+                self.pending_drop_files.clear();
+                self.import_files(supported, replace);
+            } else if preview {
+                self.pending_drop_files.clear();
+                self.preview_files(supported);
+            }
+            if !open { self.pending_drop_files.clear(); }
+            if match_changed { self.import_match_changed(); }
+        }
+        // What a previewed import would change, committed only on request
+        if let Some(preview) = &self.import_preview {
+            let mut open = true;
+            let mut commit = false;
+            let mut discard = false;
+            let summary = &preview.report.summary;
+            egui::Window::new("Import Preview")
+                .open(&mut open)
+                .collapsible(false)
+                .default_width(480.0)
+                .show(ctx, |ui| {
+                    ui.label(format!("Importing from {} would:", preview.source));
+                    egui::Grid::new("import_preview_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Create");
+                        ui.label(format!("{} node(s), {} relationship(s)", summary.nodes_added, summary.rels_added));
+                        ui.end_row();
+                        ui.label("Merge into existing");
+                        ui.label(format!("{} node(s), {} relationship(s)", summary.nodes_updated, summary.rels_updated));
+                        ui.end_row();
+                        if summary.kept > 0 {
+                            ui.label("Matched and left as they are");
+                            ui.label(format!("{} element(s)", summary.kept));
+                            ui.end_row();
                         }
                     });
-                    if self.export_all_path.is_empty() {
-                        let now = time::OffsetDateTime::now_utc();
-                        let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
-                        let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
-                        let ext = if self.export_all_is_json { "json" } else { "csv" };
-                        let mut base = self.app_settings.export_dir();
-                        base.push(format!("graph_export_{}.{}", stamp, ext));
-                        self.export_all_path = base.display().to_string();
+                    ui.separator();
+                    if preview.report.problems.is_empty() {
+                        ui.label("No problems found.");
+                    } else {
+                        ui.colored_label(Color32::YELLOW, format!("{} problem(s); these elements would be left out:", preview.report.problems.len()));
+                        egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+                            for p in &preview.report.problems { ui.monospace(p); }
+                        });
                     }
-                    ui.label("Save to:");
-                    ui.text_edit_singleline(&mut self.export_all_path);
-                    ui.add_space(6.0);
+                    ui.separator();
                     ui.horizontal(|ui| {
-                        if ui.button("Export").clicked() {
-                            let path = std::path::PathBuf::from(self.export_all_path.clone());
-                            let parent = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| std::path::PathBuf::from("."));
-                            let res_msg = if let Err(e) = std::fs::create_dir_all(&parent) {
-                                Err(format!("Failed to create directory: {}", e))
-                            } else if self.export_all_is_json {
-                                match export_graph_json(&self.db, &path) {
-                                    Ok(()) => Ok(format!("Exported JSON to {}", path.display())),
-                                    Err(e) => Err(format!("Export failed: {}", e)),
-                                }
-                            } else {
-                                match export_graph_csv(&self.db, &path) {
-                                    Ok((np, rp)) => Ok(format!("Exported CSV files: {} and {}", np.display(), rp.display())),
-                                    Err(e) => Err(format!("Export failed: {}", e)),
-                                }
-                            };
-                            self.export_all_status = Some(res_msg.unwrap_or_else(|e| e));
-                        }
-                        if ui.button("Cancel").clicked() { self.show_export_all_window = false; }
+                        if ui.button("Commit").clicked() { commit = true; }
+                        if ui.button("Discard").clicked() { discard = true; }
                     });
-                    if let Some(msg) = &self.export_all_status { ui.separator(); ui.small(msg.clone()); }
                 });
-            if !open { self.show_export_all_window = false; }
+            if commit {
+                if let Some(preview) = self.import_preview.take() { self.commit_import_preview(*preview); }
+            } else if discard || !open {
+                self.import_preview = None;
+            }
         }
         egui::TopBottomPanel::top("top_bar").show(ctx, |ui| {
             // Check for keyboard shortcuts
@@ -1570,13 +5720,22 @@ impl eframe::App for GraphApp {
             if ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O))) {
                 self.menu_load_latest();
             }
+            // Undo/redo only when no text field has focus, so text editing keeps its own undo
+            if !ctx.wants_keyboard_input() {
+                if ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z))) {
+                    self.menu_redo();
+                }
+                if ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z))) {
+                    self.menu_undo();
+                }
+            }
 
             // Use compact menus so options remain accessible regardless of width
             ui.horizontal(|ui| {
                 ui.label("Graph-Loom");
 
                 // File menu:
-                ui.menu_button("File", |ui| {
+                let file_menu = ui.menu_button("File", |ui| {
                     if ui.add(egui::Button::new("Save").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S)))).clicked() {
                         self.menu_save();
                         ui.close();
@@ -1593,13 +5752,17 @@ impl eframe::App for GraphApp {
                             let now = time::OffsetDateTime::now_utc();
                             let fmt = time::macros::format_description!("[year][month][day]_[hour][minute][second]");
                             let stamp = now.format(&fmt).unwrap_or_else(|_| "now".into());
-                            let ext = if self.export_all_is_json { "json" } else { "csv" };
+                            let ext = self.export_all_kind.extension();
                             let mut base = self.app_settings.export_dir();
                             base.push(format!("graph_export_{}.{}", stamp, ext));
                             self.export_all_path = base.display().to_string();
                         }
                         ui.close();
                     }
+                    if ui.button("Export Bundle…").clicked() {
+                        self.open_bundle_window(false);
+                        ui.close();
+                    }
                     if ui.add(egui::Button::new("Load Latest").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O)))).clicked() {
                         self.menu_load_latest();
                         ui.close();
@@ -1608,26 +5771,173 @@ impl eframe::App for GraphApp {
                         self.show_load_versions = true;
                         ui.close();
                     }
+                    ui.menu_button("Open Recent", |ui| {
+                        let recent: Vec<RecentFile> = self.app_settings.recent_files_ordered().into_iter().cloned().collect();
+                        if recent.is_empty() { ui.weak("No recent files"); }
+                        let mut to_open = None;
+                        let mut pin = None;
+                        let mut forget = None;
+                        for (i, r) in recent.iter().enumerate() {
+                            // Pinned entries come first; separate them from the rest
+                            if i > 0 && recent[i - 1].pinned && !r.pinned { ui.separator(); }
+                            let name = r.path.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+                            let exists = r.path.exists();
+                            ui.horizontal(|ui| {
+                                if ui.small_button(if r.pinned { "Unpin" } else { "Pin" }).clicked() { pin = Some((r.path.clone(), !r.pinned)); }
+                                let resp = ui.add_enabled(exists, egui::Button::new(name)).on_hover_text(r.path.display().to_string());
+                                let resp = resp.on_disabled_hover_text(format!("{} no longer exists", r.path.display()));
+                                if resp.clicked() {
+                                    to_open = Some(r.path.clone());
+                                    ui.close();
+                                }
+                                if !exists && ui.small_button("Remove").clicked() { forget = Some(r.path.clone()); }
+                            });
+                        }
+                        ui.separator();
+                        if ui.add_enabled(recent.iter().any(|r| !r.pinned), egui::Button::new("Clear Unpinned")).clicked() {
+                            self.app_settings.clear_unpinned_recent();
+                            self.save_recent_files();
+                        }
+                        if let Some((p, pinned)) = pin {
+                            self.app_settings.set_recent_pinned(&p, pinned);
+                            self.save_recent_files();
+                        }
+                        if let Some(p) = forget {
+                            self.app_settings.forget_recent(&p);
+                            self.save_recent_files();
+                        }
+                        if let Some(p) = to_open { self.open_recent(&p); }
+                    });
                     ui.separator();
                     if ui.add(egui::Button::new("New Graph").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::N)))).clicked() {
                         self.menu_new_graph();
                         ui.close();
                     }
+                    if ui.button("New From Template…").clicked() {
+                        self.show_template_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Merge Graph…").clicked() {
+                        self.show_merge_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Open Bundle…").clicked() {
+                        self.open_bundle_window(true);
+                        ui.close();
+                    }
+                    ui.menu_button("Import From", |ui| {
+                        if ui.button("Neo4j…").clicked() {
+                            self.show_neo4j_window = true;
+                            ui.close();
+                        }
+                        if ui.button("SQL Database…").clicked() {
+                            self.show_sql_window = true;
+                            ui.close();
+                        }
+                        if ui.button("Markdown Vault…").clicked() {
+                            self.show_vault_window = true;
+                            ui.close();
+                        }
+                        if ui.button("Package Dependencies…").clicked() {
+                            self.show_packages_window = true;
+                            ui.close();
+                        }
+                        if ui.button("JSON API Feeds…").clicked() {
+                            self.show_feeds_window = true;
+                            ui.close();
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Graph Properties…").clicked() {
+                        self.open_graph_properties();
+                        ui.close();
+                    }
                     ui.separator();
                     if ui.add(egui::Button::new("Quit").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Q)))).clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         ui.close();
                     }
                 });
+                self.tour_anchors.insert(TourTarget::FileMenu, file_menu.response.rect);
+
+                ui.menu_button("Edit", |ui| {
+                    if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z)))).clicked() {
+                        self.menu_undo();
+                        ui.close();
+                    }
+                    if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)))).clicked() {
+                        self.menu_redo();
+                        ui.close();
+                    }
+                    let restore = ui.add_enabled(!self.restore_points.is_empty(), egui::Button::new("Restore Last Checkpoint"));
+                    let restore = match self.restore_points.latest() {
+                        Some(p) => restore.on_hover_text(format!("Before: {} ({}s ago, {} kept)", p.label, p.at.elapsed().as_secs(), self.restore_points.len())),
+                        None => restore.on_disabled_hover_text("Checkpoints are taken before New Graph, imports, bulk deletes and large query changes"),
+                    };
+                    if restore.clicked() {
+                        self.menu_restore_checkpoint();
+                        ui.close();
+                    }
+                    ui.separator();
+                    if ui.button("History…").clicked() {
+                        self.show_history_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Relationship Constraints…").clicked() {
+                        self.show_constraints_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Property Rules…").clicked() {
+                        self.show_rules_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Derived Edges…").clicked() {
+                        self.show_derived_window = true;
+                        self.derived_virtual = derived::virtual_edges(&self.db);
+                        ui.close();
+                    }
+                });
 
                 ui.menu_button("View", |ui| {
                     if ui.add(egui::Button::new("Reset View").shortcut_text(ctx.format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Num0)))).clicked() {
                         self.menu_reset_view();
                         ui.close();
                     }
+                    if ui.add_enabled(!self.node_positions.is_empty(), egui::Button::new("Fit Graph")).clicked() {
+                        self.fit_nodes(self.node_positions.keys().copied().collect::<Vec<_>>());
+                        ui.close();
+                    }
+                    let selection = self.selected_node_ids();
+                    if ui.add_enabled(!selection.is_empty(), egui::Button::new("Fit to Selection")).clicked() {
+                        self.fit_nodes(selection);
+                        ui.close();
+                    }
+                    let mut result_view = self.result_view.is_some() && self.named_view().is_none();
+                    let has_matches = !self.query_selected_nodes.is_empty() || !self.query_selected_rels.is_empty();
+                    if ui.add_enabled(has_matches || result_view, egui::Checkbox::new(&mut result_view, "Query Result View")).changed() {
+                        if result_view { self.open_result_view(); } else { self.close_result_view(); }
+                        ui.close();
+                    }
+                    if ui.button("Views…").clicked() {
+                        self.show_views_window = true;
+                        ui.close();
+                    }
                     ui.separator();
                     ui.label("Zoom");
-                    ui.add(egui::Slider::new(&mut self.zoom, 0.25..=2.0).clamping(egui::SliderClamping::Always));
+                    ui.add(egui::Slider::new(&mut self.zoom, navigation::ZOOM_RANGE).clamping(egui::SliderClamping::Always));
+                    ui.separator();
+                    if ui.button("Schema View…").clicked() {
+                        self.schema_view = Some(Schema::infer(&self.db));
+                        ui.close();
+                    }
+                    if ui.button("Memory Usage…").clicked() {
+                        self.memory_report = Some(self.measure_memory());
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.app_settings.perf_hud, "Performance Overlay").changed() {
+                        self.prefs_edit.perf_hud = self.app_settings.perf_hud;
+                        if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+                    }
                 });
 
 
@@ -1656,6 +5966,22 @@ impl eframe::App for GraphApp {
                         self.open_node_windows.clear();
                         self.open_rel_windows.clear();
                     }
+                    ui.separator();
+                    if ui.button("Logs…").clicked() {
+                        self.show_logs_window = true;
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Tools", |ui| {
+                    if ui.button("Generate Graph…").clicked() {
+                        self.show_generator_window = true;
+                        ui.close();
+                    }
+                    if ui.button("Peer Sync…").clicked() {
+                        self.show_sync_window = true;
+                        ui.close();
+                    }
                 });
 
                 // Settings/Preferences
@@ -1664,10 +5990,60 @@ impl eframe::App for GraphApp {
                         self.menu_open_prefs();
                         ui.close();
                     }
+                    ui.menu_button("Profiles", |ui| {
+                        let mut switch_to: Option<String> = None;
+                        let mut delete: Option<String> = None;
+                        if self.app_settings.profiles.is_empty() {
+                            ui.small("No saved profiles");
+                        }
+                        for p in self.app_settings.profiles.iter() {
+                            ui.horizontal(|ui| {
+                                let active = self.app_settings.active_profile.as_deref() == Some(p.name.as_str());
+                                if ui.selectable_label(active, &p.name).clicked() { switch_to = Some(p.name.clone()); }
+                                if ui.small_button("🗑").on_hover_text("Delete profile").clicked() { delete = Some(p.name.clone()); }
+                            });
+                        }
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.new_profile_name).hint_text("profile name").desired_width(120.0));
+                            let name = self.new_profile_name.trim().to_string();
+                            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save current")).clicked() {
+                                self.app_settings.save_profile(&name);
+                                match self.app_settings.save() {
+                                    Ok(()) => {
+                                        self.last_save_info = Some(format!("Saved profile '{}'", name));
+                                        self.last_info_time = Some(Instant::now());
+                                        self.last_info_style = NoticeStyle::Prominent;
+                                    }
+                                    Err(e) => self.save_error = Some(format!("Failed to save profile: {}", e)),
+                                }
+                                self.new_profile_name.clear();
+                            }
+                        });
+                        if let Some(name) = switch_to {
+                            self.menu_switch_profile(&name);
+                            ui.close();
+                        }
+                        if let Some(name) = delete {
+                            self.app_settings.remove_profile(&name);
+                            if let Err(e) = self.app_settings.save() { self.save_error = Some(format!("Failed to save settings: {}", e)); }
+                        }
+                    });
+                });
+
+                let help_menu = ui.menu_button("Help", |ui| {
+                    if ui.button("Query Reference…").clicked() {
+                        self.query_reference = Some(reference::sections());
+                        ui.close();
+                    }
+                    if ui.button("Take the Tour").clicked() {
+                        self.start_tour();
+                        ui.close();
+                    }
                 });
+                self.tour_anchors.insert(TourTarget::HelpMenu, help_menu.response.rect);
 
-                // Keep a tiny status label; avoid long texts to prevent hiding on small widths
-                ui.small(format!("N:{} R:{}", self.db.nodes.len(), self.db.relationships.len()));
+                if self.read_only_reason.is_some() { ui.separator(); ui.colored_label(Color32::YELLOW, "Read-only"); }
                 if let Some(err) = &self.save_error { ui.separator(); ui.colored_label(Color32::RED, err); }
             });
         });
@@ -1692,7 +6068,9 @@ impl eframe::App for GraphApp {
                             self.sidebar_mode = SidebarMode::Tooling;
                         }
                         let query_sel = self.sidebar_mode == SidebarMode::Query;
-                        if ui.selectable_label(query_sel, "Query").clicked() {
+                        let query_tab = ui.selectable_label(query_sel, "Query");
+                        self.tour_anchors.insert(TourTarget::QueryTab, query_tab.rect);
+                        if query_tab.clicked() {
                             self.deselect_all();
                             self.multi_select_active = false;
                             self.sidebar_mode = SidebarMode::Query;
@@ -1705,7 +6083,7 @@ impl eframe::App for GraphApp {
                             ui.heading("Tooling");
                             ui.add_space(4.0);
                             // Make tooling usable on very small windows via scrolling
-                            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                            let offset = self.sidebar_scroll_area("tooling").auto_shrink([false, false]).show(ui, |ui| {
                                 egui::CollapsingHeader::new("Layout")
                                     .default_open(false)
                                     .show(ui, |ui| {
@@ -1718,6 +6096,24 @@ impl eframe::App for GraphApp {
                         }
                         ui.small("Clusters by relationships, labels, and metadata. Dense clusters toward border; sparse toward center.");
 
+                        ui.separator();
+                        ui.label("Swimlanes");
+                        ui.horizontal(|ui| {
+                            ui.label("Group by key");
+                            ui.add(egui::TextEdit::singleline(&mut self.lane_key).hint_text("team").desired_width(100.0));
+                            ui.selectable_value(&mut self.lane_axis, LaneAxis::Rows, "Rows");
+                            ui.selectable_value(&mut self.lane_axis, LaneAxis::Columns, "Columns");
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!self.lane_key.trim().is_empty(), egui::Button::new("Arrange in Lanes")).clicked() {
+                                self.apply_swimlanes();
+                            }
+                            if ui.add_enabled(self.swimlanes.is_some(), egui::Button::new("Remove Lanes")).clicked() {
+                                self.swimlanes = None;
+                            }
+                        });
+                        ui.small("One lane per value of the key, nodes without it last. Nodes stay in their lane; editing the value moves them.");
+
                         ui.separator();
                         ui.label("Layout aids for large graphs");
                         ui.horizontal(|ui| {
@@ -1777,9 +6173,33 @@ impl eframe::App for GraphApp {
                                 self.edge_label_bg_alpha = alpha_f as u8;
                             }
                         });
+                        ui.horizontal(|ui| {
+                            ui.label("Show on edges");
+                            egui::ComboBox::from_id_salt("edge_text_mode")
+                                .selected_text(edge_text::ALL.iter().find(|(m, _)| *m == self.edge_text).map(|(_, n)| *n).unwrap_or("Label"))
+                                .show_ui(ui, |ui| {
+                                    for (mode, name) in edge_text::ALL {
+                                        ui.selectable_value(&mut self.edge_text, mode, name);
+                                    }
+                                });
+                        });
+                        if self.edge_text != EdgeText::Label {
+                            let keys: BTreeSet<&String> = self.db.relationships.values().flat_map(|r| r.metadata.keys()).collect();
+                            ui.horizontal(|ui| {
+                                ui.label("Metadata key");
+                                egui::ComboBox::from_id_salt("edge_text_key")
+                                    .selected_text(if self.edge_meta_key.is_empty() { "(choose)" } else { self.edge_meta_key.as_str() })
+                                    .show_ui(ui, |ui| {
+                                        for k in keys {
+                                            ui.selectable_value(&mut self.edge_meta_key, k.clone(), k.as_str());
+                                        }
+                                    });
+                            });
+                            ui.small("Edges with this key show its value whenever edge labels pass the zoom and count limits above; other edges show on hover.");
+                        }
                         });
 
-                    egui::CollapsingHeader::new("Create Node")
+                    let create_node = egui::CollapsingHeader::new("Create Node")
                         .default_open(false)
                         .show(ui, |ui| {
                             ui.horizontal(|ui| {
@@ -1818,6 +6238,7 @@ impl eframe::App for GraphApp {
                                         if ui.button(txt).clicked() {
                                             self.pick_target = if picking { None } else { Some(PickTarget::NewNodeTarget) };
                                         }
+                                        if ui.button("Search…").clicked() { self.open_node_picker(PickTarget::NewNodeTarget); }
                                         if ui.button("Clear Target").clicked() { self.create_node_rel_target = None; }
                                     });
                                     if matches!(self.pick_target, Some(PickTarget::NewNodeTarget)) {
@@ -1847,6 +6268,7 @@ impl eframe::App for GraphApp {
                                         let kk = k.trim();
                                         if !kk.is_empty() { md.insert(kk.to_string(), v.trim().to_string()); }
                                     }
+                                    if self.refused_by_rules(&label, &md) || self.refused_as_computed(&label, None, &md) { return; }
                                     let id = self.db.add_node(label, md);
                                     self.re_cluster_pending = true;
                                     // Place the new node on the golden spiral around the current origin
@@ -1875,10 +6297,31 @@ impl eframe::App for GraphApp {
                                     self.create_node_label.clear();
                                     self.create_node_meta.clear();
                                     self.mark_dirty();
+                                    self.record_history(format!("Created node {}", format_short_node(&self.db, id)));
                                 }
                             }
                             if let Some(e) = error_node { ui.colored_label(Color32::RED, e); }
                         });
+                    self.tour_anchors.insert(TourTarget::CreateNode, create_node.header_response.rect);
+
+                    egui::CollapsingHeader::new("Quick Create")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.label("One node per line, with the line as its name");
+                            ui.add(egui::TextEdit::multiline(&mut self.quick_create_text).desired_rows(5).hint_text("Pricing page\nOnboarding email\nReferral program"));
+                            ui.horizontal(|ui| {
+                                ui.label("Label");
+                                ui.add(egui::TextEdit::singleline(&mut self.quick_create_label).desired_width(100.0));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.quick_create_link, "Link consecutive lines");
+                                ui.add_enabled(self.quick_create_link, egui::TextEdit::singleline(&mut self.quick_create_link_label).desired_width(80.0));
+                            });
+                            let count = import::parse_lines(&self.quick_create_text, "").len();
+                            if ui.add_enabled(count > 0, egui::Button::new(format!("Create {} Node(s)", count))).clicked() {
+                                self.quick_create_nodes();
+                            }
+                        });
 
                     egui::CollapsingHeader::new("Create Relationship")
                         .default_open(false)
@@ -1906,6 +6349,7 @@ impl eframe::App for GraphApp {
                                 if ui.button(pick_from_text).clicked() {
                                     self.pick_target = if pick_from_active { None } else { Some(PickTarget::From) };
                                 }
+                                if ui.button("Search…").clicked() { self.open_node_picker(PickTarget::From); }
                                 if ui.button("Clear From").clicked() { self.create_rel_from = None; }
                             });
                             ui.horizontal(|ui| {
@@ -1930,6 +6374,7 @@ impl eframe::App for GraphApp {
                                 if ui.button(pick_to_text).clicked() {
                                     self.pick_target = if pick_to_active { None } else { Some(PickTarget::To) };
                                 }
+                                if ui.button("Search…").clicked() { self.open_node_picker(PickTarget::To); }
                                 if ui.button("Clear To").clicked() { self.create_rel_to = None; }
                             });
                             if self.pick_target.is_some() {
@@ -1961,7 +6406,6 @@ impl eframe::App for GraphApp {
                                 let (from, to) = (self.create_rel_from, self.create_rel_to);
                                 if label.is_empty() { error_rel = Some("Label cannot be empty".into()); }
                                 else if from.is_none() || to.is_none() { error_rel = Some("Select both From and To nodes".into()); }
-                                else if from == to { error_rel = Some("From and To must be different".into()); }
                                 else {
                                     let mut md = HashMap::new();
                                     for (k, v) in &self.create_rel_meta {
@@ -1969,7 +6413,8 @@ impl eframe::App for GraphApp {
                                         if !kk.is_empty() { md.insert(kk.to_string(), v.trim().to_string()); }
                                     }
                                     if let (Some(from_id), Some(to_id)) = (from, to) {
-                                        if let Some(rid) = self.db.add_relationship(from_id, to_id, label, md) {
+                                        if let Some(rid) = self.db.add_relationship(from_id, to_id, label.clone(), md) {
+                                            self.record_history(format!("Created relationship {}", label));
                                             self.selected = Some(SelectedItem::Rel(rid));
                                             self.re_cluster_pending = true;
                                             self.create_rel_label.clear();
@@ -1988,7 +6433,11 @@ impl eframe::App for GraphApp {
                             if let Some(e) = error_rel { ui.colored_label(Color32::RED, e); }
                         });
 
-                    let bulk_resp = egui::CollapsingHeader::new("Bulk Edit Nodes")
+                    egui::CollapsingHeader::new("Annotations")
+                        .default_open(false)
+                        .show(ui, |ui| self.annotations_ui(ui));
+
+                    let bulk_resp = egui::CollapsingHeader::new("Bulk Edit Selection")
                         .default_open(false)
                         .show(ui, |ui| {
                             ui.horizontal(|ui| {
@@ -1996,34 +6445,54 @@ impl eframe::App for GraphApp {
                                 if ui.button(toggle_txt).clicked() {
                                     self.multi_select_active = !self.multi_select_active;
                                 }
-                                if ui.button("Clear Selection").clicked() { self.multi_selected_nodes.clear(); }
+                                if ui.button("Clear Selection").clicked() {
+                                    self.multi_selected_nodes.clear();
+                                    self.multi_selected_rels.clear();
+                                }
                             });
-                            ui.small(format!("Selected: {} nodes", self.multi_selected_nodes.len()));
+                            ui.small(format!("Selected: {} nodes, {} relationships", self.multi_selected_nodes.len(), self.multi_selected_rels.len()));
+                            if self.multi_select_active {
+                                ui.weak("Drag a rectangle to add the nodes in it and the relationships between them; click an edge to add or remove it.");
+                            }
+                            let nothing_selected = self.multi_selected_nodes.is_empty() && self.multi_selected_rels.is_empty();
 
                             ui.separator();
-                            ui.label("Add/Update Metadata on selected nodes");
+                            ui.label("Add/Update Metadata on selected nodes and relationships");
                             ui.label("Key");
                             ui.text_edit_singleline(&mut self.bulk_add_key);
                             ui.label("Value");
                             ui.text_edit_singleline(&mut self.bulk_add_value);
-                            let disabled = self.multi_selected_nodes.is_empty() || self.bulk_add_key.trim().is_empty();
+                            let disabled = nothing_selected || self.bulk_add_key.trim().is_empty();
                             let btn = ui.add_enabled(!disabled, egui::Button::new("Apply"));
                             if btn.clicked() {
                                 let key = self.bulk_add_key.trim().to_string();
                                 let val = self.bulk_add_value.clone();
                                 let mut count = 0usize;
+                                let mut refused = 0usize;
                                 for id in self.multi_selected_nodes.clone() {
+                                    let Some(n) = self.db.nodes.get(&id) else { continue };
+                                    let mut after = n.metadata.clone();
+                                    after.insert(key.clone(), val.clone());
+                                    if self.db.check_node(&n.label, &after).is_err() || !computed::hand_set(&self.db, &n.label, Some(&n.metadata), &after).is_empty() { refused += 1; continue; }
                                     if self.db.upsert_node_metadata(id, key.clone(), val.clone()) { count += 1; }
                                 }
-                                if count > 0 { self.re_cluster_pending = true; self.mark_dirty(); }
-                                self.bulk_status = Some(format!("Upserted '{}' for {} node(s)", key, count));
+                                let mut rel_count = 0usize;
+                                for id in self.multi_selected_rels.clone() {
+                                    if self.db.upsert_relationship_metadata(id, key.clone(), val.clone()) { rel_count += 1; }
+                                }
+                                if count + rel_count > 0 {
+                                    self.re_cluster_pending = true; self.mark_dirty();
+                                    self.record_history(format!("Bulk set '{}' on {} node(s), {} relationship(s)", key, count, rel_count));
+                                }
+                                let refused = if refused > 0 { format!("; {} refused by property rules or computed keys", refused) } else { String::new() };
+                                self.bulk_status = Some(format!("Upserted '{}' for {} node(s), {} relationship(s){}", key, count, rel_count, refused));
                             }
 
                             ui.separator();
-                            ui.label("Delete Metadata key(s) on selected nodes");
+                            ui.label("Delete Metadata key(s) on selected nodes and relationships");
                             ui.label("Keys (comma or space separated)");
                             ui.text_edit_singleline(&mut self.bulk_delete_keys);
-                            let disabled = self.multi_selected_nodes.is_empty() || self.bulk_delete_keys.trim().is_empty();
+                            let disabled = nothing_selected || self.bulk_delete_keys.trim().is_empty();
                             let btn = ui.add_enabled(!disabled, egui::Button::new("Delete Keys"));
                             if btn.clicked() {
                                 let keys: Vec<String> = self.bulk_delete_keys
@@ -2031,20 +6500,36 @@ impl eframe::App for GraphApp {
                                     .filter_map(|s| { let t = s.trim(); if t.is_empty() { None } else { Some(t.to_string()) } })
                                     .collect();
                                 let mut affected = 0usize;
+                                let mut refused = 0usize;
                                 for id in self.multi_selected_nodes.clone() {
+                                    let Some(n) = self.db.nodes.get(&id) else { continue };
+                                    let mut after = n.metadata.clone();
+                                    for k in &keys { after.remove(k); }
+                                    if self.db.check_node(&n.label, &after).is_err() || !computed::hand_set(&self.db, &n.label, Some(&n.metadata), &after).is_empty() { refused += 1; continue; }
                                     let mut any = false;
                                     for k in &keys {
                                         if self.db.remove_node_metadata_key(id, k) { any = true; }
                                     }
                                     if any { affected += 1; }
                                 }
-                                if affected > 0 { self.re_cluster_pending = true; self.mark_dirty(); }
-                                self.bulk_status = Some(format!("Deleted keys [{}] on {} node(s)", keys.join(", "), affected));
+                                let mut rels_affected = 0usize;
+                                for id in self.multi_selected_rels.clone() {
+                                    let mut any = false;
+                                    for k in &keys {
+                                        if self.db.remove_relationship_metadata_key(id, k) { any = true; }
+                                    }
+                                    if any { rels_affected += 1; }
+                                }
+                                if affected + rels_affected > 0 {
+                                    self.re_cluster_pending = true; self.mark_dirty();
+                                    self.record_history(format!("Bulk removed key(s) on {} node(s), {} relationship(s)", affected, rels_affected));
+                                }
+                                let refused = if refused > 0 { format!("; {} refused by property rules", refused) } else { String::new() };
+                                self.bulk_status = Some(format!("Deleted keys [{}] on {} node(s), {} relationship(s){}", keys.join(", "), affected, rels_affected, refused));
                             }
                             ui.separator();
-                            // Mass delete selected nodes
-                            let del_disabled = self.multi_selected_nodes.is_empty();
-                            if ui.add_enabled(!del_disabled, egui::Button::new("Delete Selected Nodes")).clicked() {
+                            // Mass delete selected nodes and relationships
+                            if ui.add_enabled(!nothing_selected, egui::Button::new("Delete Selected")).clicked() {
                                 self.confirm_mass_delete = true;
                             }
                             if let Some(msg) = &self.bulk_status { ui.small(msg.clone()); }
@@ -2053,7 +6538,8 @@ impl eframe::App for GraphApp {
                     if !bulk_resp.fully_open() && self.multi_select_active {
                         self.multi_select_active = false;
                     }
-                    });
+                    }).state.offset.y;
+                    self.scroll_offsets.insert("tooling".to_owned(), offset);
                 }
                 SidebarMode::Query => {
                             ui.heading("Query Console");
@@ -2072,7 +6558,7 @@ impl eframe::App for GraphApp {
                                     style.text_styles.insert(egui::TextStyle::Small, egui::FontId::proportional(11.0));
                                     ui.set_style(style);
                                 }
-                                egui::ScrollArea::vertical().show(ui, |ui| {
+                                let offset = self.sidebar_scroll_area("query").show(ui, |ui| {
                             ui.label("Enter query (Cmd/Ctrl+Enter to run):");
                             let edit = egui::TextEdit::multiline(&mut self.query_text)
                                 .desired_rows(8)
@@ -2081,6 +6567,7 @@ impl eframe::App for GraphApp {
                                 // Assign a persistent id so we can programmatically move the caret
                                 .id_source("query_text_edit");
                             let te_resp = ui.add(edit);
+                            self.tour_anchors.insert(TourTarget::QueryEditor, te_resp.rect);
 
                             // Suggestion logic: compute prefix token at end-of-text
                             // Global early cancel: ESC should always close the suggestions popup
@@ -2146,33 +6633,23 @@ impl eframe::App for GraphApp {
                                     (text[i..end].to_string(), i)
                                 } else { (String::new(), text.len()) };
 
-                                // Build suggestion universe (cached)
+                                // Build suggestion universe
                                 let mut pool: Vec<String> = Vec::new();
                                 const KEYWORDS: &[&str] = &[
                                     "MATCH","OPTIONAL","OPTIONAL MATCH","WHERE","RETURN","ORDER BY","SKIP","LIMIT",
                                     "CREATE","MERGE","SET","REMOVE","DELETE","DETACH DELETE",
-                                    "DISTINCT","ASC","DESC",
+                                    "DISTINCT","ASC","DESC","UNWIND","WITH","AS",
+                                    "CASE","WHEN","THEN","ELSE","END","IS NULL","IS NOT NULL","UNION","UNION ALL","EXISTS","nodes","relationships","length","CALL","CYPHER",
+                                    "toUpper","toLower","trim","size","coalesce","abs","round","datetime","date","duration","timestamp","createdAt","updatedAt",
                                 ];
                                 pool.extend(KEYWORDS.iter().map(|s| s.to_string()));
+                                pool.extend(procedures::list().into_iter().map(|p| p.name));
                                 
-                                // Only add dynamic items if DB is small enough or if we really need to
-                                // For performance, we could cache this, but let's at least limit it
-                                if self.db.nodes.len() < 1000 {
-                                    let mut labels: BTreeSet<String> = BTreeSet::new();
-                                    let mut rels: BTreeSet<String> = BTreeSet::new();
-                                    let mut props: BTreeSet<String> = BTreeSet::new();
-                                    for n in self.db.nodes.values() {
-                                        if !n.label.is_empty() { labels.insert(n.label.clone()); }
-                                        for k in n.metadata.keys() { props.insert(k.clone()); }
-                                    }
-                                    for r in self.db.relationships.values() {
-                                        if !r.label.is_empty() { rels.insert(r.label.clone()); }
-                                        for k in r.metadata.keys() { props.insert(k.clone()); }
-                                    }
-                                    pool.extend(labels.into_iter().map(|l| format!(":{}", l)));
-                                    pool.extend(rels.into_iter().map(|t| format!(":{}", t)));
-                                    pool.extend(props.into_iter().map(|p| format!("{}.{}", "n", p)));
-                                }
+                                // Labels and keys come from the graph's vocabulary, which is kept current on mutation
+                                let vocabulary = self.db.vocabulary();
+                                pool.extend(vocabulary.node_labels().map(|l| format!(":{}", l)));
+                                pool.extend(vocabulary.relationship_labels().map(|t| format!(":{}", t)));
+                                pool.extend(vocabulary.node_keys().chain(vocabulary.relationship_keys()).map(|p| format!("n.{}", p)));
 
                                 // Filter by prefix (case-insensitive)
                                 let pfx_up = prefix.to_uppercase();
@@ -2351,6 +6828,14 @@ impl eframe::App for GraphApp {
                                     });
                                 });
                             }
+                            // Lint as the text changes so mistakes show before the query runs
+                            if self.query_lint_for != self.query_text {
+                                self.query_lint = query_interface::lint(&self.db, &self.query_text);
+                                self.query_lint_for = self.query_text.clone();
+                            }
+                            for w in &self.query_lint {
+                                ui.colored_label(Color32::YELLOW, format!("Warning: {}", w));
+                            }
                             let mut run_now = false;
                             if ui.button("Run").clicked() {
                                 run_now = true;
@@ -2366,40 +6851,7 @@ impl eframe::App for GraphApp {
                             if run_now {
                                 let q = self.query_text.trim().to_string();
                                 if !q.is_empty() {
-                                    match query_interface::execute_and_log(&mut self.db, &q) {
-                                        Ok(outcome) => {
-                                            self.last_query_error = None;
-                                            // record history
-                                            if self.query_history.last().map(|h| h != &q).unwrap_or(true) {
-                                                self.query_history.push(q.clone());
-                                            }
-                                            // display rows succinctly and capture matches
-                                            self.query_selected_nodes.clear();
-                                            self.query_selected_rels.clear();
-                                            self.query_output.clear();
-                                            for row in outcome.rows {
-                                                match row {
-                                                    QueryResultRow::Node { id, label, metadata } => {
-                                                        self.query_output.push(format!("NODE {} {} {:?}", id, label, metadata));
-                                                        self.query_selected_nodes.insert(id);
-                                                    }
-                                                    QueryResultRow::Relationship { id, from, to, label, metadata } => {
-                                                        self.query_output.push(format!("REL {} {} {} {} {:?}", id, from, to, label, metadata));
-                                                        self.query_selected_rels.insert(id);
-                                                        // ensure endpoints are positioned if new
-                                                        if let Some(pa) = self.node_positions.get(&from) { let _ = pa; } else { if let Some(rect) = self.last_canvas_rect { let pos = golden_spiral_position(rect.center(), self.node_positions.len() as u32, rect); self.node_positions.insert(from, pos); } }
-                                                        if let Some(pb) = self.node_positions.get(&to) { let _ = pb; } else { if let Some(rect) = self.last_canvas_rect { let pos = golden_spiral_position(rect.center(), self.node_positions.len() as u32 + 1, rect); self.node_positions.insert(to, pos); } }
-                                                    }
-                                                    QueryResultRow::Info(s) => self.query_output.push(s),
-                                                }
-                                            }
-                                            self.query_output.push(format!("Affected: nodes={} rels={}", outcome.affected_nodes, outcome.affected_relationships));
-                                            if outcome.mutated { self.mark_dirty(); }
-                                        }
-                                        Err(err) => {
-                                            self.last_query_error = Some(err.to_string());
-                                        }
-                                    }
+                                    self.run_console_query(q);
                                 }
                             }
                             ui.separator();
@@ -2409,6 +6861,20 @@ impl eframe::App for GraphApp {
                                 if ui.add_enabled(!deselect_disabled, egui::Button::new("Deselect Matches")).clicked() {
                                     self.query_selected_nodes.clear();
                                     self.query_selected_rels.clear();
+                                    if self.named_view().is_none() { self.close_result_view(); }
+                                }
+                                let mut result_view = self.result_view.is_some() && self.named_view().is_none();
+                                if ui.add_enabled(!deselect_disabled || result_view, egui::Checkbox::new(&mut result_view, "Result View"))
+                                    .on_hover_text("Show only the matches, laid out on their own")
+                                    .changed()
+                                {
+                                    if result_view { self.open_result_view(); } else { self.close_result_view(); }
+                                }
+                                if ui.add_enabled(!self.query_selected_nodes.is_empty(), egui::Button::new("Show Matches"))
+                                    .on_hover_text("Move the view to the matched nodes")
+                                    .clicked()
+                                {
+                                    self.fit_nodes(self.query_selected_nodes.clone());
                                 }
                                 ui.small(format!("Matched: {} node(s), {} rel(s)", self.query_selected_nodes.len(), self.query_selected_rels.len()));
                             });
@@ -2429,19 +6895,16 @@ impl eframe::App for GraphApp {
                                 }
                                 ui.label("Save as:");
                                 ui.text_edit_singleline(&mut self.query_export_path);
-                                let can_export = !self.query_selected_nodes.is_empty();
+                                let idle = !self.tasks.iter().any(|t| t.label.starts_with(QUERY_EXPORT_TASK));
+                                let can_export = idle && !self.query_selected_nodes.is_empty();
                                 if ui.add_enabled(can_export, egui::Button::new("Export Selected Nodes")).clicked() {
-                                    let path = std::path::PathBuf::from(self.query_export_path.clone());
-                                    let parent = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| std::path::PathBuf::from("."));
-                                    if let Err(e) = std::fs::create_dir_all(&parent) { self.query_export_status = Some(format!("Failed to create dir: {}", e)); }
-                                    else {
-                                        let ids: Vec<NodeId> = self.query_selected_nodes.iter().copied().collect();
-                                        let res = if self.query_export_is_json { export_nodes_json(&self.db, &ids, &path) } else { export_nodes_csv(&self.db, &ids, &path) };
-                                        match res {
-                                            Ok(()) => self.query_export_status = Some(format!("Exported {} node(s) to {}", ids.len(), path.display())),
-                                            Err(e) => self.query_export_status = Some(format!("Export failed: {}", e)),
-                                        }
-                                    }
+                                    self.export_query_results(false);
+                                }
+                                if ui.add_enabled(idle && !self.query_rows.is_empty(), egui::Button::new("Export Result Rows"))
+                                    .on_hover_text("Every row the query returned, in order: nodes, relationships, paths and values such as counts")
+                                    .clicked()
+                                {
+                                    self.export_query_results(true);
                                 }
                                 if let Some(msg) = &self.query_export_status { ui.small(msg.clone()); }
                             });
@@ -2449,8 +6912,24 @@ impl eframe::App for GraphApp {
                                 ui.colored_label(Color32::RED, format!("Error: {}", err));
                             }
                             ui.label("Output:");
-                            for line in &self.query_output {
-                                ui.monospace(line);
+                            let mut clicked_path: Option<usize> = None;
+                            for (i, line) in self.query_output.iter().enumerate() {
+                                if self.query_output_paths.contains_key(&i) {
+                                    let active = self.query_active_path == Some(i);
+                                    if ui.selectable_label(active, egui::RichText::new(line).monospace()).on_hover_text("Highlight this path").clicked() {
+                                        clicked_path = Some(i);
+                                    }
+                                } else {
+                                    ui.monospace(line);
+                                }
+                            }
+                            if let Some(i) = clicked_path
+                                && let Some((nodes, rels)) = self.query_output_paths.get(&i)
+                            {
+                                self.query_selected_nodes = nodes.iter().copied().collect();
+                                self.query_selected_rels = rels.iter().copied().collect();
+                                self.query_active_path = Some(i);
+                                self.fit_nodes(nodes.clone());
                             }
                             ui.separator();
                             ui.horizontal(|ui| {
@@ -2465,7 +6944,8 @@ impl eframe::App for GraphApp {
                                     self.query_text = h.clone();
                                 }
                             }
-                        }); // close Query ScrollArea
+                        }).state.offset.y; // close Query ScrollArea
+                        self.scroll_offsets.insert("query".to_owned(), offset);
                     }); // close Query scope
                 } // close SidebarMode::Query
             } // close match self.sidebar_mode
@@ -2474,18 +6954,22 @@ impl eframe::App for GraphApp {
 
         // Confirmation modal for mass delete
         if self.confirm_mass_delete {
-            egui::Window::new("Confirm Delete Selected Nodes")
+            egui::Window::new("Confirm Delete Selection")
                 .collapsible(false)
                 .resizable(false)
                 .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
                 .show(ctx, |ui| {
                     let count = self.multi_selected_nodes.len();
-                    ui.label(format!("This will permanently delete {} selected node(s) and any relationships connected to them.", count));
-                    ui.label("This action cannot be undone.");
+                    let rel_count = self.multi_selected_rels.len();
+                    ui.label(format!("This will permanently delete {} selected node(s) and any relationships connected to them, and {} selected relationship(s).", count, rel_count));
+                    ui.label("You can undo this from Edit → Undo or the History panel.");
                     ui.separator();
                     ui.horizontal(|ui| {
                         if ui.button(egui::RichText::new("Delete").color(Color32::RED)).clicked() {
                             let ids: Vec<NodeId> = self.multi_selected_nodes.iter().copied().collect();
+                            self.create_restore_point(format!("Delete {} selected node(s), {} relationship(s)", ids.len(), rel_count));
+                            // Relationships first, so those a deleted node takes along are not counted
+                            let rels_deleted = self.multi_selected_rels.drain().filter(|rid| self.db.remove_relationship(*rid)).count();
                             let mut deleted = 0usize;
                             for id in ids {
                                 if self.db.remove_node(id) {
@@ -2499,8 +6983,11 @@ impl eframe::App for GraphApp {
                             // clear selection and multi-select
                             self.selected = None;
                             self.multi_selected_nodes.clear();
-                            if deleted > 0 { self.mark_dirty(); }
-                            self.bulk_status = Some(format!("Deleted {} node(s) and their relationships", deleted));
+                            if deleted + rels_deleted > 0 {
+                                self.mark_dirty();
+                                self.record_history(format!("Bulk deleted {} node(s), {} relationship(s)", deleted, rels_deleted));
+                            }
+                            self.bulk_status = Some(format!("Deleted {} node(s) and their relationships, and {} selected relationship(s)", deleted, rels_deleted));
                             self.confirm_mass_delete = false;
                         }
                         if ui.button("Cancel").clicked() {
@@ -2510,7 +6997,12 @@ impl eframe::App for GraphApp {
                 });
         }
 
+        self.show_node_picker(ctx);
+        self.show_status_bar(ctx);
+
+        let canvas_started = Instant::now();
         egui::CentralPanel::default().show(ctx, |ui| {
+            if !self.db.views.is_empty() || self.result_view.is_some() { self.show_view_tabs(ui); }
             // Detect canvas size/position changes and adjust pan to keep view stable
             let prev_rect = self.last_canvas_rect;
             let available = ui.available_rect_before_wrap();
@@ -2523,10 +7015,26 @@ impl eframe::App for GraphApp {
             }
             // remember canvas rect for new-node placement and future resize detection
             self.last_canvas_rect = Some(available);
+            if let Some(camera) = self.camera {
+                let (zoom, pan, done) = camera.at(Instant::now());
+                (self.zoom, self.pan) = (zoom, pan);
+                if done {
+                    self.camera = None;
+                    self.mark_dirty();
+                } else {
+                    ui.ctx().request_repaint();
+                }
+            }
+            self.tour_anchors.insert(TourTarget::Canvas, available);
             // If auto re-cluster requested, apply before drawing
-            if self.re_cluster_pending {
+            // A re-cluster waits for the result view to close; the view follows edits
+            if self.re_cluster_pending && self.result_view.is_none() {
                 self.apply_cluster_layout_all(available);
             }
+            if self.result_view.is_some() && self.result_view_generation != self.graph_generation {
+                self.result_view_generation = self.graph_generation;
+                self.refresh_result_view();
+            }
             self.ensure_layout(available);
 
             // Background allocation for panning/clicking, restricted when something is likely being dragged or interacted with.
@@ -2537,6 +7045,8 @@ impl eframe::App for GraphApp {
             // cancel pick with Esc
             if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
                 self.pick_target = None;
+                self.annotation_tool = None;
+                self.annotation_drag_start = None;
             }
 
             // Helpers to transform between world and screen space
@@ -2571,41 +7081,140 @@ impl eframe::App for GraphApp {
                         self.rect_select_current = Some(cur);
                     }
                 }
-                // On release (primary button up), compute world-rect and add all nodes inside to multi selection
+                // On release (primary button up), compute world-rect and add all nodes inside to multi
+                // selection, with the relationships between them
                 if self.rect_select_start.is_some() && !ui.input(|i| i.pointer.primary_down()) {
                     if let (Some(a), Some(b)) = (self.rect_select_start.take(), self.rect_select_current.take()) {
                         let aw = from_screen(a);
                         let bw = from_screen(b);
                         let sel_rect = Rect::from_two_pos(aw, bw);
-                        for (id, pos_w) in self.node_positions.iter() {
-                            if sel_rect.contains(*pos_w) {
-                                self.multi_selected_nodes.insert(*id);
+                        let inside: HashSet<NodeId> = self.node_positions.iter().filter(|(_, p)| sel_rect.contains(**p)).map(|(id, _)| *id).collect();
+                        self.multi_selected_rels.extend(self.shown_graph().relationships_within(&inside));
+                        self.multi_selected_nodes.extend(inside);
+                    }
+                }
+            } else {
+                // Ensure rectangle state is cleared when not in multi-select mode
+                self.rect_select_start = None;
+                self.rect_select_current = None;
+            }
+
+            // Annotation tools: a click places text, a drag draws a rectangle or arrow
+            if let Some(tool) = self.annotation_tool && !self.multi_select_active {
+                let color = (self.annotation_color.r(), self.annotation_color.g(), self.annotation_color.b());
+                let text = self.annotation_text.trim().to_string();
+                let mut created: Option<Annotation> = None;
+                match tool {
+                    AnnotationTool::Text => {
+                        if bg_resp.clicked() && let Some(p) = ui.input(|i| i.pointer.interact_pos()) {
+                            let at = from_screen(p);
+                            created = Some(Annotation::new(AnnotationShape::Text { at: (at.x, at.y) }, if text.is_empty() { "Note".to_string() } else { text }, color));
+                        }
+                    }
+                    AnnotationTool::Rect | AnnotationTool::Arrow => {
+                        if bg_resp.drag_started() && let Some(p) = ui.input(|i| i.pointer.press_origin()) {
+                            self.annotation_drag_start = Some(from_screen(p));
+                        }
+                        if let Some(start) = self.annotation_drag_start && !ui.input(|i| i.pointer.primary_down()) {
+                            self.annotation_drag_start = None;
+                            if let Some(p) = ui.input(|i| i.pointer.latest_pos()) {
+                                let end = from_screen(p);
+                                // Ignore slips of a few pixels
+                                if (end - start).length() * self.zoom >= 6.0 {
+                                    let shape = if tool == AnnotationTool::Rect {
+                                        let r = Rect::from_two_pos(start, end);
+                                        AnnotationShape::Rect { min: (r.min.x, r.min.y), max: (r.max.x, r.max.y) }
+                                    } else {
+                                        AnnotationShape::Arrow { from: (start.x, start.y), to: (end.x, end.y) }
+                                    };
+                                    created = Some(Annotation::new(shape, text, color));
+                                }
                             }
                         }
                     }
                 }
-            } else {
-                // Ensure rectangle state is cleared when not in multi-select mode
-                self.rect_select_start = None;
-                self.rect_select_current = None;
+                if let Some(a) = created {
+                    self.selected_annotation = Some(a.id);
+                    self.annotations.push(a);
+                    self.mark_dirty();
+                }
             }
 
-            // Zoom with scroll only when pointer is over the canvas area
+            // Zoom with scroll only when pointer is over the canvas area, keeping the point under
+            // the pointer in place. Trackpads pan with two fingers and zoom by pinching; two-finger
+            // touch gestures do both at once.
+            let line_speed = ui.ctx().options(|o| o.input_options.line_scroll_speed);
+            let (scroll, pinch, touch) = ui.input(|i| {
+                (navigation::split_scroll(&i.events, self.app_settings.trackpad_pans, line_speed), i.zoom_delta(), i.multi_touch())
+            });
             if bg_resp.hovered() {
-                let scroll = ui.input(|i| i.raw_scroll_delta.y);
-                if scroll != 0.0 {
-                    let factor = (1.0 + scroll * 0.001).clamp(0.9, 1.1);
-                    self.zoom = (self.zoom * factor).clamp(0.25, 2.0);
+                let gesture_pan = scroll.pan + touch.map_or(Vec2::ZERO, |t| t.translation_delta);
+                let mut factor = pinch;
+                if scroll.wheel != 0.0 {
+                    factor *= (1.0 + scroll.wheel * 0.001).clamp(0.9, 1.1);
+                }
+                if factor != 1.0 || gesture_pan != Vec2::ZERO { self.stop_camera(); }
+                if factor != 1.0 {
+                    let anchor = touch.map(|t| t.center_pos).or(ui.ctx().pointer_hover_pos()).unwrap_or(center);
+                    let (zoom, pan) = navigation::zoom_about(self.zoom, self.pan, center, anchor, factor);
+                    if pan != self.pan { self.mark_dirty(); }
+                    (self.zoom, self.pan) = (zoom, pan);
                     // Show transient zoom HUD
                     self.zoom_hud_until = Some(Instant::now() + Duration::from_millis(1000));
                     ui.ctx().request_repaint_after(Duration::from_millis(16));
                 }
+                if gesture_pan != Vec2::ZERO {
+                    self.pan_inertia.stop();
+                    self.pan += gesture_pan;
+                    self.mark_dirty();
+                }
+            }
+            // Any press ends a glide or camera move; otherwise keep it moving
+            if ui.input(|i| i.pointer.any_pressed()) {
+                self.pan_inertia.stop();
+                if ui.ui_contains_pointer() { self.stop_camera(); }
+            } else if let Some(d) = self.pan_inertia.step(ui.input(|i| i.stable_dt)) {
+                self.pan += d;
+                self.mark_dirty();
+                ui.ctx().request_repaint();
+            }
+
+            // Clipboard paste (Ctrl/Cmd+V) of JSON node objects or CSV/TSV rows creates nodes near the cursor.
+            // Text fields keep their own paste handling.
+            if !ui.ctx().wants_keyboard_input() {
+                let pasted: Option<String> = ui.input(|i| i.events.iter().find_map(|e| match e {
+                    egui::Event::Paste(t) => Some(t.clone()),
+                    _ => None,
+                }));
+                if let Some(text) = pasted {
+                    let anchor_screen = ui.ctx().pointer_hover_pos()
+                        .filter(|p| available.contains(*p))
+                        .unwrap_or(available.center());
+                    let anchor = from_screen(anchor_screen);
+                    match import::parse_node_rows(&text, "Node") {
+                        Ok(rows) if rows.iter().any(|(label, meta)| self.db.check_node(label, meta).is_err()) => {
+                            let first = rows.iter().find_map(|(label, meta)| self.db.check_node(label, meta).err());
+                            self.save_error = first.map(|e| format!("Paste refused by property rules: {}", e));
+                        }
+                        Ok(rows) => {
+                            let count = rows.len();
+                            self.add_node_grid(rows, anchor);
+                            self.mark_dirty();
+                            self.record_history(format!("Pasted {} node(s)", count));
+                            self.last_save_info = Some(format!("Pasted {} node(s)", count));
+                            self.last_info_time = Some(Instant::now());
+                            self.last_info_style = NoticeStyle::Prominent;
+                        }
+                        Err(e) => { self.save_error = Some(format!("Paste import failed: {}", e)); }
+                    }
+                }
             }
 
             // Panning: update pan based on background drag delta, if not in multi-select mode
             // and no node is being dragged.
 
             let painter = ui.painter_at(available);
+            let pal = palette::palette(self.app_settings.color_palette);
 
             // Draw transient zoom HUD if active
             if let Some(until) = self.zoom_hud_until {
@@ -2645,13 +7254,99 @@ impl eframe::App for GraphApp {
                 }
             }
             self.hover_node = hover_node;
+            // Start of the hovered node's description, unless something is being dragged
+            if let Some(n) = hover_node.and_then(|id| self.db.nodes.get(&id))
+                && !n.description.is_empty()
+                && self.dragging.is_none()
+            {
+                egui::Tooltip::always_open(ui.ctx().clone(), ui.layer_id(), egui::Id::new(("node_description", n.id)), egui::PopupAnchor::Pointer)
+                    .gap(12.0)
+                    .show(|ui| {
+                        ui.strong(&n.label);
+                        ui.label(graph::text_preview(&n.description, 200));
+                    });
+            }
+            // Relationships get the same affordance when no node is under the pointer
+            let hover_rel = if hover_node.is_none() && self.dragging.is_none() && ui.ui_contains_pointer() {
+                ui.ctx().pointer_hover_pos().and_then(|p| edge_under(self.shown_graph(), &self.node_positions, p, zoom, to_screen))
+            } else {
+                None
+            };
+            self.hover_rel = hover_rel;
+            if let Some(rel) = hover_rel.and_then(|id| self.db.relationships.get(&id)) {
+                egui::Tooltip::always_open(ui.ctx().clone(), ui.layer_id(), egui::Id::new(("relationship_tooltip", rel.id)), egui::PopupAnchor::Pointer)
+                    .gap(12.0)
+                    .show(|ui| {
+                        ui.strong(&rel.label);
+                        ui.label(format!("{} → {}", format_short_node(&self.db, rel.from_node), format_short_node(&self.db, rel.to_node)));
+                        let (entries, more) = edge_text::key_metadata(&self.edge_meta_key, &rel.metadata, 5);
+                        if !entries.is_empty() {
+                            egui::Grid::new(("relationship_tooltip_meta", rel.id)).num_columns(2).show(ui, |ui| {
+                                for (k, v) in entries {
+                                    ui.weak(k);
+                                    ui.label(graph::text_preview(v, 60));
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                        if more > 0 { ui.weak(format!("+{} more", more)); }
+                        if !rel.description.is_empty() { ui.label(graph::text_preview(&rel.description, 200)); }
+                    });
+            }
+
+            // Lanes and annotations sit beneath the graph
+            if let Some(lanes) = self.swimlanes.as_ref().filter(|_| self.result_view.is_none()) {
+                let header_size = self.canvas_text_size(13.0, 8.0, 24.0);
+                let header_color = ui.visuals().weak_text_color();
+                for (i, (lane, r)) in lanes.lanes.iter().zip(lanes.rects(&self.node_positions)).enumerate() {
+                    let r = Rect::from_min_max(to_screen(r.min), to_screen(r.max));
+                    let fill = if i % 2 == 0 { ui.visuals().faint_bg_color } else { ui.visuals().extreme_bg_color };
+                    painter.rect_filled(r, 0.0, fill.gamma_multiply(0.6));
+                    painter.rect_stroke(r, 0.0, Stroke::new(1.0, ui.visuals().widgets.noninteractive.bg_stroke.color), egui::StrokeKind::Inside);
+                    painter.text(r.min + Vec2::splat(6.0), egui::Align2::LEFT_TOP, lanes.header(lane), egui::FontId::proportional(header_size), header_color);
+                }
+            }
+            if self.show_annotations {
+                let annotation_text_size = self.canvas_text_size(14.0, 8.0, 28.0);
+                for a in &self.annotations {
+                    let selected = self.selected_annotation == Some(a.id);
+                    draw_annotation(&painter, a.shape, &a.text, Color32::from_rgb(a.color.0, a.color.1, a.color.2), selected, annotation_text_size, to_screen);
+                }
+                // Preview of the shape being dragged out
+                if let (Some(tool), Some(start), Some(cur)) = (self.annotation_tool, self.annotation_drag_start, ui.ctx().pointer_latest_pos()) {
+                    let end = from_screen(cur);
+                    let shape = match tool {
+                        AnnotationTool::Arrow => AnnotationShape::Arrow { from: (start.x, start.y), to: (end.x, end.y) },
+                        _ => {
+                            let r = Rect::from_two_pos(start, end);
+                            AnnotationShape::Rect { min: (r.min.x, r.min.y), max: (r.max.x, r.max.y) }
+                        }
+                    };
+                    draw_annotation(&painter, shape, self.annotation_text.trim(), self.annotation_color, true, annotation_text_size, to_screen);
+                }
+            }
 
             // Draw edges (with slight curvature and adaptive opacity)
             let edge_count = self.db.relationships.len();
             let base_alpha: u8 = if self.zoom < 0.7 || edge_count > 600 { 120 } else if self.zoom < 0.9 || edge_count > 300 { 160 } else { 200 };
             let base_color = Color32::from_rgba_premultiplied(200, 200, 200, base_alpha);
             let edge_stroke = Stroke { width: 1.5, color: base_color };
-            for rel in self.db.relationships.values() {
+            // Virtual derived edges: dashed, beneath the real ones
+            if self.show_derived_virtual {
+                let shown = self.shown_graph();
+                let stroke = Stroke::new(1.0, base_color.gamma_multiply(0.7));
+                for (_, e) in &self.derived_virtual {
+                    if !shown.nodes.contains_key(&e.from) || !shown.nodes.contains_key(&e.to) { continue; }
+                    if let (Some(a), Some(b)) = (self.node_positions.get(&e.from), self.node_positions.get(&e.to)) {
+                        painter.extend(egui::Shape::dashed_line(&[to_screen(*a), to_screen(*b)], stroke, 6.0, 4.0));
+                    }
+                }
+            }
+            let lanes = parallel_edge_lanes(self.shown_graph());
+            let edge_values_visible = self.edge_text != EdgeText::Label
+                && !self.edge_meta_key.is_empty()
+                && (!self.lod_enabled || (self.zoom >= self.edge_label_min_zoom && self.db.relationships.len() < self.edge_label_count_threshold));
+            for rel in self.shown_graph().relationships.values() {
                 if let (Some(pa), Some(pb)) = (
                     self.node_positions.get(&rel.from_node),
                     self.node_positions.get(&rel.to_node),
@@ -2659,14 +7354,17 @@ impl eframe::App for GraphApp {
                     let a = to_screen(*pa);
                     let b = to_screen(*pb);
                     let incident_hover = self.hover_node.map(|h| h == rel.from_node || h == rel.to_node).unwrap_or(false);
+                    let is_hovered = self.hover_rel == Some(rel.id) || self.connection_preview.is_some_and(|(_, r)| r == rel.id);
             // Highlight if selected AND the popout for this relationship is open
             let is_sel = matches!(self.selected, Some(SelectedItem::Rel(id)) if id == rel.id)
                 && self.open_rel_windows.contains(&rel.id);
             let is_qsel = self.query_selected_rels.contains(&rel.id);
             let mut stroke = if is_sel {
-                Stroke { width: 3.0, color: Color32::from_rgb(255, 200, 80) }
-            } else if is_qsel || incident_hover {
-                Stroke { width: 2.5, color: Color32::from_rgb(120, 220, 255) }
+                Stroke { width: 3.0, color: pal.selected_rel }
+            } else if self.multi_selected_rels.contains(&rel.id) {
+                Stroke { width: 2.5, color: pal.multi_select }
+            } else if is_qsel || incident_hover || is_hovered {
+                Stroke { width: 2.5, color: pal.query_match }
             } else {
                 edge_stroke
             };
@@ -2675,33 +7373,37 @@ impl eframe::App for GraphApp {
                 let c = stroke.color; stroke.color = Color32::from_rgba_premultiplied(c.r(), c.g(), c.b(), (c.a() as f32 * 0.4) as u8);
             }
 
-            // Curvature: offset midpoint along perpendicular; stable by hashing endpoints
-            let dir = Vec2::new(b.x - a.x, b.y - a.y);
-            let len = (dir.x * dir.x + dir.y * dir.y).sqrt();
-            if len > 1.0 {
-                let mid = Pos2::new((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
-                let n = Vec2::new(-dir.y / len, dir.x / len);
-                let mut seed = rel.from_node.as_u128() ^ rel.to_node.as_u128();
-                seed ^= seed >> 33;
-                let sign = if (seed & 1) == 0 { 1.0 } else { -1.0 };
-                let mag = (8.0 * self.zoom).clamp(2.0, 16.0);
-                let ctrl = mid + n * (mag * sign as f32);
+            // Curvature: bend at the lane's control point (parallel edges fan out)
+            let edge_lane = lanes.get(&rel.id).copied().unwrap_or((0, 1));
+            let is_loop = rel.from_node == rel.to_node;
+            let ctrl = if is_loop {
+                // Self-loop: a small circle sitting on top of the node; ctrl is its apex
+                let (c, r) = self_loop_circle(a, edge_lane, self.zoom);
+                painter.circle_stroke(c, r, stroke);
+                c - Vec2::new(0.0, r)
+            } else {
+                let ctrl = edge_control_point(a, b, rel.from_node, rel.to_node, edge_lane, self.zoom);
                 painter.line_segment([a, ctrl], stroke);
                 painter.line_segment([ctrl, b], stroke);
-            } else {
-                painter.line_segment([a, b], stroke);
-            }
+                ctrl
+            };
 
                     // Relationship label at midpoint with improved LOD visibility and pill background
                     let mid = Pos2::new((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
                     let dir = Vec2::new(b.x - a.x, b.y - a.y);
                     let len = (dir.x * dir.x + dir.y * dir.y).sqrt();
 
-                    // Visibility: only show relationship label text when hovering over a connected node
-                    let show_label = incident_hover;
+                    // Visibility: labels show when hovering over a connected node; a chosen metadata
+                    // key also shows on its own within the edge label LOD limits
+                    let caption = edge_text::caption(self.edge_text, &self.edge_meta_key, &rel.label, &rel.metadata);
+                    let show_value = edge_values_visible && rel.metadata.contains_key(&self.edge_meta_key);
+                    let show_label = incident_hover || is_hovered || show_value;
 
-                    if show_label && len > f32::EPSILON {
+                    if let Some(caption) = caption.filter(|_| show_label)
+                        && (len > f32::EPSILON || is_loop)
+                    {
                         // Perpendicular and tangential offsets, alternating per edge for separation
+                        let len = len.max(f32::EPSILON);
                         let n = Vec2::new(-dir.y / len, dir.x / len);
                         let t = Vec2::new(dir.x / len, dir.y / len);
                         let mut seed = rel.from_node.as_u128() ^ (rel.to_node.as_u128().rotate_left(17)) ^ rel.id.as_u128();
@@ -2711,13 +7413,15 @@ impl eframe::App for GraphApp {
                         let perp_mag = (8.0 * self.zoom).clamp(4.0, 16.0);
                         let tan_mag = (lane as f32) * 4.0 * self.zoom;
                         let offset = n * (perp_mag * side as f32) + t * tan_mag;
+                        // Parallel edges: pin each label to its own lane so the group's labels stack
+                        let offset = if edge_lane.1 > 1 || is_loop { ctrl - mid } else { offset };
 
                         // Text styling
-                        let font = egui::FontId::proportional((12.0 * self.zoom).clamp(8.0, 16.0));
+                        let font = egui::FontId::proportional(self.canvas_text_size(12.0, 8.0, 16.0));
                         let txt_color = if is_sel { Color32::from_rgb(30, 30, 30) } else { Color32::from_rgb(20, 20, 20) };
                         let pill_fill = if is_sel {
                             Color32::from_rgba_premultiplied(255, 220, 120, 220)
-                        } else if is_qsel || incident_hover {
+                        } else if is_qsel || incident_hover || is_hovered {
                             Color32::from_rgba_premultiplied(180, 235, 255, self.edge_label_bg_alpha)
                         } else {
                             Color32::from_rgba_premultiplied(245, 245, 245, self.edge_label_bg_alpha)
@@ -2725,7 +7429,7 @@ impl eframe::App for GraphApp {
                         let _outline = Color32::from_rgba_premultiplied(0, 0, 0, 120);
 
                         // Layout the text to size the pill
-                        let galley = ui.painter().layout_no_wrap(rel.label.clone(), font.clone(), txt_color);
+                        let galley = ui.painter().layout_no_wrap(caption, font.clone(), txt_color);
                         let pad = Vec2::new(6.0 * self.zoom, 3.0 * self.zoom);
                         let pill_size = galley.size() + pad * 2.0;
                         let center = mid + offset;
@@ -2750,12 +7454,13 @@ impl eframe::App for GraphApp {
 
             // Iterate over a snapshot of ids to avoid borrowing conflicts when we
             // lazily initialize positions.
-            let node_ids: Vec<NodeId> = self.db.nodes.keys().copied().collect();
+            let node_ids: Vec<NodeId> = self.shown_graph().nodes.keys().copied().collect();
             for id in node_ids {
                 // Be resilient if a node is missing a precomputed position
                 let pos_world = self.get_or_init_position(id, available);
-                // Safe to immutably read the node after the mutable borrow in get_or_init_position ends
-                let node = match self.db.nodes.get(&id) { Some(n) => n, None => continue };
+                // Safe to immutably read the node after the mutable borrow in get_or_init_position ends;
+                // the shown graph also holds the group nodes of an aggregated view
+                let node = match self.result_view.as_ref().map_or(&*self.db, |v| &v.graph).nodes.get(&id) { Some(n) => n, None => continue };
                 let pos_screen = to_screen(pos_world);
                 let rect = Rect::from_center_size(pos_screen, Vec2::splat(node_radius_draw * 2.0));
                 let resp = ui.allocate_rect(rect, Sense::click_and_drag());
@@ -2780,15 +7485,15 @@ impl eframe::App for GraphApp {
                         format_short_node(&self.db, id)
                     ).strong());
                     ui.monospace(format!("UUID: {}", id));
-                    // Show degree (incident edges) and up to 5 properties
-                    let degree = self
+                    // Show degree (in + out, so a self-loop counts twice) and up to 5 properties
+                    let degree: usize = self
                         .db
                         .relationships
                         .values()
-                        .filter(|r| r.from_node == id || r.to_node == id)
-                        .count();
+                        .map(|r| (r.from_node == id) as usize + (r.to_node == id) as usize)
+                        .sum();
                     ui.small(format!("degree: {}", degree));
-                    if let Some(n) = self.db.nodes.get(&id) {
+                    if let Some(n) = self.shown_graph().nodes.get(&id) {
                         let mut shown = 0usize;
                         for (k, v) in n.metadata.iter() {
                             if shown >= 5 { break; }
@@ -2803,21 +7508,26 @@ impl eframe::App for GraphApp {
                 // A node is visually selected only if its details window is open
                 let is_selected = matches!(self.selected, Some(SelectedItem::Node(nid)) if nid == id)
                     && self.open_node_windows.contains(&id);
-                let fill = if is_selected { Color32::from_rgb(80, 120, 255) } else { Color32::from_rgb(60, 60, 60) };
+                let fill = if is_selected { pal.selected } else { Color32::from_rgb(60, 60, 60) };
                 // Highlight From/To selections
                 let mut stroke = if is_selected { Stroke::new(2.0, Color32::WHITE) } else { Stroke::new(1.5, Color32::DARK_GRAY) };
-                if self.create_rel_from == Some(id) { stroke = Stroke::new(2.5, Color32::from_rgb(80, 220, 120)); }
-                if self.create_rel_to == Some(id) { stroke = Stroke::new(2.5, Color32::from_rgb(255, 170, 60)); }
+                if self.create_rel_from == Some(id) { stroke = Stroke::new(2.5, pal.pick_from); }
+                if self.create_rel_to == Some(id) { stroke = Stroke::new(2.5, pal.pick_to); }
                 painter.circle_filled(pos_screen, node_radius_draw, fill);
                 painter.circle_stroke(pos_screen, node_radius_draw, stroke);
 
+                // Peer of the Connections row being hovered in a node window
+                if self.connection_preview.is_some_and(|(peer, _)| peer == id) {
+                    painter.circle_stroke(pos_screen, node_radius_draw + (6.0 * self.zoom).clamp(3.0, 12.0), Stroke::new(2.5, pal.query_match));
+                }
+
                 // Bulk select halo indicator (independent from popout selection)
                 if self.multi_selected_nodes.contains(&id) {
                     let halo_r = node_radius_draw + (3.0 * self.zoom).clamp(2.0, 8.0);
                     painter.circle_stroke(
                         pos_screen,
                         halo_r,
-                        Stroke::new(1.5, Color32::from_rgb(120, 200, 255)),
+                        Stroke::new(1.5, pal.multi_select),
                     );
                 }
 
@@ -2832,28 +7542,32 @@ impl eframe::App for GraphApp {
                 };
                 if show_label {
                     let text = format_short_node(&self.db, id);
-                    let label_color = GraphApp::color_for_label(&node.label);
+                    let mut label_color = self.color_for_label(&node.label);
+                    if self.app_settings.label_min_contrast > 1.0 {
+                        label_color = palette::ensure_contrast(label_color, ui.visuals().panel_fill, self.app_settings.label_min_contrast);
+                    }
                     let pos_text = pos_screen + Vec2::new(0.0, -node_radius_draw - 4.0);
+                    let label_font = egui::FontId::proportional(self.canvas_text_size(14.0, 10.0, 22.0));
                     // multi-direction halo for readability
                     painter.text(
                         pos_text + Vec2::new(0.0, 1.0),
                         egui::Align2::CENTER_BOTTOM,
                         &text,
-                        egui::FontId::proportional((14.0 * self.zoom).clamp(10.0, 22.0)),
+                        label_font.clone(),
                         Color32::BLACK,
                     );
                     painter.text(
                         pos_text + Vec2::new(1.0, 0.0),
                         egui::Align2::CENTER_BOTTOM,
                         &text,
-                        egui::FontId::proportional((14.0 * self.zoom).clamp(10.0, 22.0)),
+                        label_font.clone(),
                         Color32::BLACK,
                     );
                     painter.text(
                         pos_text,
                         egui::Align2::CENTER_BOTTOM,
                         text,
-                        egui::FontId::proportional((14.0 * self.zoom).clamp(10.0, 22.0)),
+                        label_font,
                         label_color,
                     );
                 }
@@ -2864,37 +7578,14 @@ impl eframe::App for GraphApp {
                     painter.circle_stroke(
                         pos_screen,
                         halo_r,
-                        Stroke::new(2.0, Color32::from_rgb(120, 220, 255)),
+                        Stroke::new(2.0, pal.query_match),
                     );
                 }
             }
 
             if let Some(id) = clicked_node {
                 if let Some(target) = self.pick_target {
-                    match target {
-                        PickTarget::From => { self.create_rel_from = Some(id); self.pick_target = None; }
-                        PickTarget::To => { self.create_rel_to = Some(id); self.pick_target = None; }
-                        PickTarget::NewNodeTarget => {
-                            // Set the target for pre-linking a new node
-                            self.create_node_rel_target = Some(id);
-                            if let Some(new_id) = self.pending_new_node_for_link {
-                                if new_id != id {
-                                    let rel_label = if self.create_node_rel_label.trim().is_empty() { "REL".to_string() } else { self.create_node_rel_label.trim().to_string() };
-                                    let rid_opt = match self.create_node_rel_direction {
-                                        NewNodeRelDir::NewToExisting => self.db.add_relationship(new_id, id, rel_label, HashMap::new()),
-                                        NewNodeRelDir::ExistingToNew => self.db.add_relationship(id, new_id, rel_label, HashMap::new()),
-                                    };
-                                    if let Some(rid) = rid_opt {
-                                        self.selected = Some(SelectedItem::Rel(rid));
-                                    }
-                                    self.mark_dirty();
-                                }
-                                // Clear pending regardless to end the flow
-                                self.pending_new_node_for_link = None;
-                            }
-                            self.pick_target = None;
-                        }
-                    }
+                    self.assign_pick(target, id);
                 } else if self.multi_select_active {
                     // Toggle membership in bulk selection; do not open popouts
                     if self.multi_selected_nodes.contains(&id) {
@@ -2926,81 +7617,43 @@ impl eframe::App for GraphApp {
 
                 // Background Panning: update pan based on background drag delta,
                 // if not in multi-select mode and no node was dragged this frame.
-                if !self.multi_select_active {
+                // Two-finger touch drags already panned above.
+                if !self.multi_select_active && self.annotation_tool.is_none() && touch.is_none() {
                     let delta = bg_resp.drag_delta();
+                    let now = ui.input(|i| i.time);
                     if delta != Vec2::ZERO {
                         self.pan += delta;
+                        self.pan_inertia.track(delta, now);
                         self.mark_dirty();
                     }
+                    if bg_resp.drag_stopped() {
+                        if self.app_settings.kinetic_panning { self.pan_inertia.release(now); } else { self.pan_inertia.stop(); }
+                    }
                 }
             }
             if any_node_dragged { self.mark_dirty(); }
 
-            // Edge hit testing and selection when background is clicked and not dragging nodes
-            if !self.multi_select_active && clicked_node.is_none() && !any_node_dragged && bg_resp.clicked() {
-                if let Some(pointer_pos) = ui.input(|i| i.pointer.latest_pos()) {
-                    // Helper: compute the same curved polyline used for drawing
-                    let compute_edge_points = |a: Pos2, b: Pos2, _rel_id: Uuid, from_id: NodeId, to_id: NodeId| -> (Pos2, Pos2, Pos2) {
-                        let dir = Vec2::new(b.x - a.x, b.y - a.y);
-                        let len = (dir.x * dir.x + dir.y * dir.y).sqrt();
-                        if len > 1.0 {
-                            let mid = Pos2::new((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
-                            let n = Vec2::new(-dir.y / len, dir.x / len);
-                            let mut seed = from_id.as_u128() ^ to_id.as_u128();
-                            seed ^= seed >> 33;
-                            let sign = if (seed & 1) == 0 { 1.0 } else { -1.0 };
-                            let mag = (8.0 * self.zoom).clamp(2.0, 16.0);
-                            let ctrl = mid + n * (mag * sign as f32);
-                            (a, ctrl, b)
-                        } else {
-                            // very short edge: treat as straight
-                            (a, a.lerp(b, 0.5), b)
-                        }
-                    };
+            // In multi-select mode a clicked edge joins or leaves the bulk selection
+            if self.multi_select_active && clicked_node.is_none() && !any_node_dragged && bg_resp.clicked()
+                && let Some(rid) = ui.input(|i| i.pointer.latest_pos()).and_then(|p| edge_under(self.shown_graph(), &self.node_positions, p, zoom, to_screen))
+                && !self.multi_selected_rels.remove(&rid)
+            {
+                self.multi_selected_rels.insert(rid);
+            }
 
-                    // Find nearest edge under cursor against the two drawn segments (a->ctrl, ctrl->b)
-                    let mut best: Option<(Uuid, f32)> = None; // (rel_id, distance)
-                    let tolerance_px = 8.0_f32; // selection slop in screen pixels
-                    for rel in self.db.relationships.values() {
-                        if let (Some(pa), Some(pb)) = (
-                            self.node_positions.get(&rel.from_node),
-                            self.node_positions.get(&rel.to_node),
-                        ) {
-                            let a = to_screen(*pa);
-                            let b = to_screen(*pb);
-                            // Quick AABB reject expanded by tolerance
-                            let minx = a.x.min(b.x) - tolerance_px;
-                            let maxx = a.x.max(b.x) + tolerance_px;
-                            let miny = a.y.min(b.y) - tolerance_px;
-                            let maxy = a.y.max(b.y) + tolerance_px;
-                            if pointer_pos.x < minx || pointer_pos.x > maxx || pointer_pos.y < miny || pointer_pos.y > maxy {
-                                // still continue because curved ctrl could extend beyond, but this is a good early out.
-                            }
-                            let (pa_s, pc_s, pb_s) = compute_edge_points(a, b, rel.id, rel.from_node, rel.to_node);
-                            let d1 = point_segment_distance(pointer_pos, pa_s, pc_s);
-                            let d2 = point_segment_distance(pointer_pos, pc_s, pb_s);
-                            let d = d1.min(d2);
-                            if d <= tolerance_px {
-                                match best {
-                                    None => best = Some((rel.id, d)),
-                                    Some((_, bd)) if d < bd => best = Some((rel.id, d)),
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
-                    if let Some((rid, _)) = best {
-                        // Toggle behavior: if re-clicking the same relationship and its window is open, close it
-                        if matches!(self.selected, Some(SelectedItem::Rel(sel_rid)) if sel_rid == rid)
-                            && self.open_rel_windows.contains(&rid)
-                        {
-                            self.open_rel_windows.remove(&rid);
-                            self.selected = None;
-                        } else {
-                            self.selected = Some(SelectedItem::Rel(rid));
-                            // Open (or keep) a separate window for this relationship
-                            self.open_rel_windows.insert(rid);
-                        }
+            // Edge hit testing and selection when background is clicked and not dragging nodes
+            if !self.multi_select_active && self.annotation_tool.is_none() && clicked_node.is_none() && !any_node_dragged && bg_resp.clicked() {
+                if let Some(rid) = ui.input(|i| i.pointer.latest_pos()).and_then(|p| edge_under(self.shown_graph(), &self.node_positions, p, zoom, to_screen)) {
+                    // Toggle behavior: if re-clicking the same relationship and its window is open, close it
+                    if matches!(self.selected, Some(SelectedItem::Rel(sel_rid)) if sel_rid == rid)
+                        && self.open_rel_windows.contains(&rid)
+                    {
+                        self.open_rel_windows.remove(&rid);
+                        self.selected = None;
+                    } else {
+                        self.selected = Some(SelectedItem::Rel(rid));
+                        // Open (or keep) a separate window for this relationship
+                        self.open_rel_windows.insert(rid);
                     }
                 }
             }
@@ -3014,28 +7667,50 @@ impl eframe::App for GraphApp {
                 painter.rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Inside);
             }
 
+            // Peer presence: share this user's pointer and selection, draw everyone else's
+            if let Some(peers) = &mut self.peer_sync {
+                let pointer = ui.ctx().pointer_hover_pos().filter(|p| available.contains(*p)).and_then(|p| {
+                    let at = from_screen(p);
+                    let (id, pos) = self.node_positions.iter().min_by(|a, b| a.1.distance_sq(at).total_cmp(&b.1.distance_sq(at)))?;
+                    Some(sync::Pointer { node: *id, dx: (at.x - pos.x).round(), dy: (at.y - pos.y).round() })
+                });
+                let mut selected: Vec<Uuid> = self.multi_selected_nodes.iter().copied().collect();
+                match self.selected {
+                    Some(SelectedItem::Node(id) | SelectedItem::Rel(id)) => selected.push(id),
+                    None => {}
+                }
+                selected.sort();
+                selected.dedup();
+                peers.share_presence(&self.sync_name, pointer, selected);
+                let font = egui::FontId::proportional(12.0);
+                for p in peers.participants() {
+                    let color = palette::label_color(self.app_settings.color_palette, &p.replica);
+                    for id in &p.selected {
+                        if let Some(pos) = self.node_positions.get(id) {
+                            painter.circle_stroke(to_screen(*pos), node_radius_draw + (7.0 * self.zoom).clamp(3.0, 12.0), Stroke::new(2.5, color));
+                        } else if let Some(rel) = self.db.relationships.get(id)
+                            && let (Some(a), Some(b)) = (self.node_positions.get(&rel.from_node), self.node_positions.get(&rel.to_node))
+                        {
+                            painter.line_segment([to_screen(*a), to_screen(*b)], Stroke::new(4.0, color.gamma_multiply(0.6)));
+                        }
+                    }
+                    let Some(ptr) = p.pointer else { continue };
+                    let Some(pos) = self.node_positions.get(&ptr.node) else { continue };
+                    let tip = to_screen(*pos + Vec2::new(ptr.dx, ptr.dy));
+                    let arrow = vec![tip, tip + Vec2::new(0.0, 16.0), tip + Vec2::new(11.0, 11.0)];
+                    painter.add(egui::Shape::convex_polygon(arrow, color, Stroke::new(1.0, Color32::BLACK)));
+                    let galley = painter.layout_no_wrap(p.name.clone(), font.clone(), Color32::BLACK);
+                    let tag = Rect::from_min_size(tip + Vec2::new(12.0, 14.0), galley.size() + Vec2::new(8.0, 4.0));
+                    painter.rect_filled(tag, 4.0, color);
+                    painter.galley(tag.min + Vec2::new(4.0, 2.0), galley, Color32::BLACK);
+                }
+            }
+
             // Smooth convergence using a simple spring-damper integration.
             // Neo4j-style aids for large graphs: center gravity and degree-aware repulsion.
             let active = match self.converge_start { Some(t0) => t0.elapsed() < Duration::from_secs(5), None => false };
             if active || any_node_dragged || self.dragging.is_some() {
-                // Nodes connected by relationships experience a spring force toward a target length.
-                // Nearby nodes experience a soft repulsive force to maintain spacing.
-                // We integrate per-node velocities with damping for fluid motion.
                 let dt = ctx.input(|i| i.stable_dt).clamp(0.001, 0.033);
-                let target_dist = 120.0_f32; // preferred edge length in world space
-                let spring_k = 4.0_f32;      // edge spring stiffness (units/s^2)
-                let damping = 6.0_f32;       // velocity damping (units/s)
-                let min_sep = 90.0_f32;      // minimum comfortable spacing
-                let repulse_k = 10.0_f32;    // repulsion strength
-                let max_speed = 600.0_f32;   // clamp velocity magnitude (units/s)
-                let max_step = 5.0_f32;      // clamp displacement per frame (units)
-                let mouse_k = 20.0_f32;      // drag-to-mouse spring stiffness
-
-                // Ensure velocity entries exist for all positioned nodes
-                for id in self.db.nodes.keys().copied() {
-                    self.node_positions.entry(id).or_insert_with(|| Pos2::new(0.0, 0.0));
-                    self.node_velocities.entry(id).or_insert(Vec2::ZERO);
-                }
 
                 // Pre-calculate dragged unit if we are in a multiselect drag
                 let mut dragged_unit: HashSet<NodeId> = HashSet::new();
@@ -3060,175 +7735,58 @@ impl eframe::App for GraphApp {
                         dragged_unit.insert(drag_id);
                     }
                 }
-
-                // Accumulate forces
-                let mut forces: HashMap<NodeId, Vec2> = HashMap::new();
-                // Relationship springs (bidirectional: attract if stretched, repel if compressed)
-                for rel in self.db.relationships.values() {
-                    let (a_id, b_id) = (rel.from_node, rel.to_node);
-                    
-                    // If we are dragging a multi-selection, and either node is part of the unit,
-                    // we "lock out" the physics for these nodes to prevent them from being pulled back.
-                    if !dragged_unit.is_empty() && self.dragging.is_some() && !self.multi_selected_nodes.is_empty() {
-                        if dragged_unit.contains(&a_id) || dragged_unit.contains(&b_id) {
-                            continue;
-                        }
-                    }
-
-                    let (pa_opt, pb_opt) = (self.node_positions.get(&a_id).copied(), self.node_positions.get(&b_id).copied());
-                    if let (Some(pa), Some(pb)) = (pa_opt, pb_opt) {
-                        let dx = pb.x - pa.x;
-                        let dy = pb.y - pa.y;
-                        let dist2 = dx * dx + dy * dy;
-                        if dist2 > 1e-6 {
-                            let dist = dist2.sqrt();
-                            let dir = Vec2::new(dx / dist, dy / dist);
-                            let stretch = dist - target_dist;
-                            let f = dir * (spring_k * stretch);
-                            *forces.entry(a_id).or_insert(Vec2::ZERO) += f;
-                            *forces.entry(b_id).or_insert(Vec2::ZERO) -= f;
-                        }
-                    }
-                }
-
-                // Gravity: prefer local center-of-mass (COM) attraction when nodes cluster off-center; otherwise pull to window center.
-                if self.gravity_enabled {
-                    let center_world = from_screen(available.center());
-                    let k_g = self.gravity_strength;
-                    let r2 = self.com_gravity_radius * self.com_gravity_radius;
-                    // Iterate over a snapshot to avoid borrow conflicts
-                    let snapshot: Vec<(NodeId, Pos2)> = self.node_positions.iter().map(|(k,v)| (*k, *v)).collect();
-                    for (id, pos) in snapshot.iter() {
-                        // If we are dragging a multi-selection, and this node is part of the unit,
-                        // we lock out gravity.
-                        if !dragged_unit.is_empty() && self.dragging.is_some() && !self.multi_selected_nodes.is_empty() {
-                            if dragged_unit.contains(id) {
-                                continue;
-                            }
-                        }
-
-                        // Compute local COM of neighbors within radius (excluding self)
-                        let mut sum_x = 0.0f32;
-                        let mut sum_y = 0.0f32;
-                        let mut count = 0usize;
-                        for (oid, opos) in snapshot.iter() {
-                            if oid == id { continue; }
-                            let dx = opos.x - pos.x;
-                            let dy = opos.y - pos.y;
-                            if dx*dx + dy*dy <= r2 {
-                                sum_x += opos.x;
-                                sum_y += opos.y;
-                                count += 1;
-                            }
-                        }
-                        let target = if count >= self.com_gravity_min_neighbors {
-                            Pos2 { x: sum_x / (count as f32), y: sum_y / (count as f32) }
-                        } else {
-                            center_world
-                        };
-                        let dir = Vec2::new(target.x - pos.x, target.y - pos.y);
-                        *forces.entry(*id).or_insert(Vec2::ZERO) += dir * k_g;
-                    }
-                }
-
-                // Degree-aware repulsive separation for close pairs (O(N^2) but small/med graphs are fine)
-                let mut deg: HashMap<NodeId, usize> = HashMap::new();
-                for rel in self.db.relationships.values() {
-                    *deg.entry(rel.from_node).or_insert(0) += 1;
-                    *deg.entry(rel.to_node).or_insert(0) += 1;
-                }
-                let ids: Vec<NodeId> = self.db.nodes.keys().copied().collect();
-                for i in 0..ids.len() {
-                    for j in (i + 1)..ids.len() {
-                        let a = ids[i];
-                        let b = ids[j];
-
-                        // If we are dragging a multi-selection, and either node is part of the unit,
-                        // we lock out repulsion for these nodes.
-                        if !dragged_unit.is_empty() && self.dragging.is_some() && !self.multi_selected_nodes.is_empty() {
-                            if dragged_unit.contains(&a) || dragged_unit.contains(&b) {
-                                continue;
-                            }
-                        }
-
-                        let (pa_opt, pb_opt) = (self.node_positions.get(&a).copied(), self.node_positions.get(&b).copied());
-                        let (pa, pb) = match (pa_opt, pb_opt) { (Some(pa), Some(pb)) => (pa, pb), _ => continue };
-                        let dx = pb.x - pa.x;
-                        let dy = pb.y - pa.y;
-                        let dist2 = dx * dx + dy * dy;
-                        if dist2 < 1e-6 { continue; }
-                        let dist = dist2.sqrt();
-                        if dist < min_sep {
-                            let dir = Vec2::new(dx / dist, dy / dist);
-                            let overlap = (min_sep - dist).max(0.0);
-                            // Scale by node degrees to spread hubs a bit more
-                            let da = *deg.get(&a).unwrap_or(&0) as f32;
-                            let db = *deg.get(&b).unwrap_or(&0) as f32;
-                            let scale_a = 1.0 + self.hub_repulsion_scale * (da + 1.0).ln();
-                            let scale_b = 1.0 + self.hub_repulsion_scale * (db + 1.0).ln();
-                            let f = dir * (repulse_k * overlap);
-                            // push opposite directions
-                            *forces.entry(a).or_insert(Vec2::ZERO) -= f * scale_a;
-                            *forces.entry(b).or_insert(Vec2::ZERO) += f * scale_b;
-                        }
-                    }
-                }
-
-                // Soft drag: apply a spring pulling the dragged node towards the mouse in world space
-                // If multiple nodes are selected, dragging one drags them all together by applying
-                // the same translation force vector to each selected node.
-                if let Some(drag_id) = self.dragging {
-                    if let Some(mouse_pos_screen) = ui.input(|i| i.pointer.latest_pos()) {
-                        let mouse_world = from_screen(mouse_pos_screen);
-                        if let Some(p_drag) = self.node_positions.get(&drag_id).copied() {
-                            let dir = Vec2::new(mouse_world.x - p_drag.x, mouse_world.y - p_drag.y);
-                            // Apply force to all nodes in the unit
-                            for nid in &dragged_unit {
-                                *forces.entry(*nid).or_insert(Vec2::ZERO) += dir * mouse_k;
-                            }
-                        }
-                    }
-                }
-
-                // Integrate velocities and positions
-                let mut any_move = false;
-                for (id, _pos) in self.node_positions.clone() {
-                    let mut v = *self.node_velocities.entry(id).or_insert(Vec2::ZERO);
-                    let f = *forces.get(&id).unwrap_or(&Vec2::ZERO);
-                    // a = f - c*v (unit mass)
-                    let a = f - v * damping;
-                    v += a * dt;
-                    // Clamp velocity
-                    let speed = v.length();
-                    if speed > max_speed { v *= max_speed / speed; }
-                    // Displacement this frame
-                    let mut step = v * dt;
-                    let step_len = step.length();
-                    if step_len > max_step { step *= max_step / step_len; }
-                    if step != Vec2::ZERO {
-                        if let Some(p) = self.node_positions.get_mut(&id) {
-                            p.x += step.x;
-                            p.y += step.y;
-                            any_move = true;
-                        }
-                    }
-                    self.node_velocities.insert(id, v);
-                }
+                // The dragged node is pulled toward the mouse in world space, and the rest of its unit with it
+                let drag = self.dragging.map(|drag_id| {
+                    let mouse = ui.input(|i| i.pointer.latest_pos()).map(from_screen);
+                    let pull = match (mouse, self.node_positions.get(&drag_id)) {
+                        (Some(m), Some(p)) => Vec2::new(m.x - p.x, m.y - p.y),
+                        _ => Vec2::ZERO,
+                    };
+                    physics::Drag { unit: &dragged_unit, pull, lock: !self.multi_selected_nodes.is_empty() }
+                });
+                let forces = physics::Forces {
+                    gravity: self.gravity_enabled.then(|| physics::Gravity {
+                        center: from_screen(available.center()),
+                        strength: self.gravity_strength,
+                        com_radius: self.com_gravity_radius,
+                        com_min_neighbors: self.com_gravity_min_neighbors,
+                    }),
+                    hub_repulsion_scale: self.hub_repulsion_scale,
+                };
+                let physics_started = Instant::now();
+                let graph = self.result_view.as_ref().map_or(&*self.db, |v| &v.graph);
+                let any_move = physics::step(graph, &mut self.node_positions, &mut self.node_velocities, &forces, drag, dt);
+                self.frame_stats.add(Phase::Physics, physics_started.elapsed());
                 if any_move { self.mark_dirty(); }
-            } else {
-                // Timeout reached: stop convergence by zeroing velocities
-                for v in self.node_velocities.values_mut() { *v = Vec2::ZERO; }
+                // Frames keep coming only while something moves; a settled layout sleeps
+                if !any_node_dragged && self.dragging.is_none() && physics::is_at_rest(&self.node_velocities) {
+                    self.sleep_physics();
+                } else {
+                    ctx.request_repaint();
+                }
+            } else if self.converge_start.is_some() {
+                // Timeout reached: stop convergence
+                self.sleep_physics();
+            }
+            // Swimlanes follow edits and hold each node in the lane of its value
+            if let Some(lanes) = &mut self.swimlanes && self.result_view.is_none() && self.lanes_generation != self.graph_generation {
+                lanes.refresh(&self.db);
+                lanes.confine(&mut self.node_positions, &mut self.node_velocities);
+                self.lanes_generation = self.graph_generation;
             }
         });
+        self.frame_stats.add(Phase::Painting, canvas_started.elapsed());
 
         // Render all open Node windows
         let mut nodes_to_close: Vec<NodeId> = Vec::new();
         let open_node_ids: Vec<NodeId> = self.open_node_windows.iter().copied().collect();
+        let mut connection_preview = None;
         for id in open_node_ids {
             // Snapshot node and editable state
             let node_snapshot = self.db.nodes.get(&id).cloned();
             if let Some(node_snapshot) = node_snapshot {
                 let mut open = true;
+                let computed_props = self.db.computed_properties.get(&node_snapshot.label).cloned().unwrap_or_default();
                 // Prepare editable buffers
                 let mut label_text = self
                     .node_label_edits
@@ -3244,7 +7802,28 @@ impl eframe::App for GraphApp {
                 let mut do_save_label = false;
                 let mut to_remove_keys: Vec<String> = Vec::new();
                 let mut upsert_kv: Option<(String, String)> = None;
+                let mut json_text = self.node_meta_json.get(&id).cloned();
+                let mut replace_meta: Option<HashMap<String, String>> = None;
                 let mut delete_node = false;
+                let mut desc_text = self.node_desc_edits.get(&id).cloned().unwrap_or_else(|| node_snapshot.description.clone());
+                let mut save_desc = false;
+                // Incident relationships as (id, label, arrow, peer, peer caption)
+                let connections: Vec<(Uuid, String, &str, NodeId, String)> = self
+                    .db
+                    .incident_relationships(id)
+                    .into_iter()
+                    .map(|r| {
+                        let (arrow, peer) = match (r.from_node == id, r.to_node == id) {
+                            (true, true) => ("↻", id),
+                            (true, false) => ("→", r.to_node),
+                            _ => ("←", r.from_node),
+                        };
+                        (r.id, r.label.clone(), arrow, peer, format_short_node(&self.db, peer))
+                    })
+                    .collect();
+                let mut center_peer: Option<NodeId> = None;
+                let mut open_peer: Option<NodeId> = None;
+                let mut delete_connection: Option<(Uuid, String)> = None;
 
                 egui::Window::new(format!("Node {} Details", id))
                     .id(egui::Id::new(("node_details", id)))
@@ -3252,6 +7831,7 @@ impl eframe::App for GraphApp {
                     .resizable(true)
                     .show(ctx, |ui| {
                         ui.label(format!("ID: {}", id));
+                        timestamps_ui(ui, &node_snapshot.created_at, &node_snapshot.updated_at);
                         // Label editing
                         ui.horizontal(|ui| {
                             ui.label("Label:");
@@ -3261,57 +7841,149 @@ impl eframe::App for GraphApp {
                             }
                         });
                         ui.separator();
+                        save_desc = description_editor(ui, &mut desc_text, &node_snapshot.description);
+                        ui.separator();
                         ui.heading("Metadata");
-                        if node_snapshot.metadata.is_empty() {
-                            ui.label("<no metadata>");
+                        ui.horizontal(|ui| {
+                            if ui.selectable_label(json_text.is_none(), "Fields").clicked() { json_text = None; }
+                            if ui.selectable_label(json_text.is_some(), "JSON").clicked() && json_text.is_none() {
+                                json_text = Some(import::metadata_to_json(&node_snapshot.metadata));
+                            }
+                        });
+                        if let Some(text) = json_text.as_mut() {
+                            replace_meta = metadata_json_editor(ui, text, &node_snapshot.metadata);
                         } else {
-                            // Present metadata with remove buttons
-                            let keys: Vec<String> = node_snapshot.metadata.keys().cloned().collect();
-                            for k in keys {
-                                let v = node_snapshot.metadata.get(&k).cloned().unwrap_or_default();
-                                ui.horizontal(|ui| {
-                                    ui.label(&k);
-                                    ui.label(":");
-                                    ui.monospace(&v);
-                                    if ui.button("Remove").clicked() { to_remove_keys.push(k.clone()); }
-                                });
+                            if node_snapshot.metadata.is_empty() {
+                                ui.label("<no metadata>");
+                            } else {
+                                // Present metadata with remove buttons
+                                let keys: Vec<String> = node_snapshot.metadata.keys().cloned().collect();
+                                for k in keys {
+                                    let v = node_snapshot.metadata.get(&k).cloned().unwrap_or_default();
+                                    ui.horizontal(|ui| {
+                                        ui.label(&k);
+                                        ui.label(":");
+                                        ui.monospace(&v);
+                                        match computed_props.iter().find(|p| p.key == k) {
+                                            Some(p) => { ui.weak("computed").on_hover_text(format!("= {}", p)); }
+                                            None => if ui.button("Remove").clicked() { to_remove_keys.push(k.clone()); },
+                                        }
+                                    });
+                                }
                             }
+                            // Add new metadata kv
+                            ui.separator();
+                            ui.label("Add/Update Metadata");
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut new_meta_kv.0).hint_text("key"));
+                                ui.label(":");
+                                ui.add(egui::TextEdit::singleline(&mut new_meta_kv.1).hint_text("value"));
+                                if ui.button("Upsert").clicked() {
+                                    if !new_meta_kv.0.trim().is_empty() {
+                                        upsert_kv = Some((new_meta_kv.0.trim().to_string(), new_meta_kv.1.trim().to_string()));
+                                        new_meta_kv.0.clear(); new_meta_kv.1.clear();
+                                    }
+                                }
+                            });
                         }
-                        // Add new metadata kv
                         ui.separator();
-                        ui.label("Add/Update Metadata");
-                        ui.horizontal(|ui| {
-                            ui.add(egui::TextEdit::singleline(&mut new_meta_kv.0).hint_text("key"));
-                            ui.label(":");
-                            ui.add(egui::TextEdit::singleline(&mut new_meta_kv.1).hint_text("value"));
-                            if ui.button("Upsert").clicked() {
-                                if !new_meta_kv.0.trim().is_empty() {
-                                    upsert_kv = Some((new_meta_kv.0.trim().to_string(), new_meta_kv.1.trim().to_string()));
-                                    new_meta_kv.0.clear(); new_meta_kv.1.clear();
-                                }
-                            }
-                        });
+                        egui::CollapsingHeader::new(format!("Connections ({})", connections.len()))
+                            .id_salt(("node_connections", id))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                if connections.is_empty() { ui.weak("No relationships"); }
+                                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                    for (rid, label, arrow, peer, peer_caption) in &connections {
+                                        let row = ui.horizontal(|ui| {
+                                            ui.label(format!("{} {} {}", arrow, label, peer_caption));
+                                            if ui.small_button("Center").on_hover_text("Center the canvas on this node").clicked() { center_peer = Some(*peer); }
+                                            if ui.small_button("Open").on_hover_text("Open this node's details and center on it").clicked() { open_peer = Some(*peer); }
+                                            if ui.small_button(egui::RichText::new("Delete").color(Color32::RED)).on_hover_text("Delete this relationship").clicked() {
+                                                delete_connection = Some((*rid, label.clone()));
+                                            }
+                                        });
+                                        if row.response.contains_pointer() { connection_preview = Some((*peer, *rid)); }
+                                    }
+                                });
+                            });
                         ui.separator();
                         if ui.button(egui::RichText::new("Delete Node").color(Color32::RED)).clicked() {
                             delete_node = true;
                         }
                     });
                 // Apply actions
-                if do_save_label {
-                    if self.db.update_node_label(id, label_text.clone()) { self.re_cluster_pending = true; self.mark_dirty(); }
+                let caption = format_short_node(&self.db, id);
+                let current = |db: &GraphDatabase| db.nodes.get(&id).map(|n| (n.label.clone(), n.metadata.clone())).unwrap_or_default();
+                if do_save_label && !self.refused_by_rules(&label_text, &current(&self.db).1) {
+                    if self.db.update_node_label(id, label_text.clone()) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Relabeled node {} to {}", caption, label_text));
+                    }
                 }
                 if !to_remove_keys.is_empty() {
-                    for k in to_remove_keys { if self.db.remove_node_metadata_key(id, &k) { self.re_cluster_pending = true; self.mark_dirty(); } }
+                    for k in to_remove_keys {
+                        let (label, before) = current(&self.db);
+                        let mut after = before.clone();
+                        after.remove(&k);
+                        if self.refused_by_rules(&label, &after) || self.refused_as_computed(&label, Some(&before), &after) { continue; }
+                        if self.db.remove_node_metadata_key(id, &k) {
+                            self.re_cluster_pending = true; self.mark_dirty();
+                            self.record_history(format!("Removed '{}' from node {}", k, caption));
+                        }
+                    }
+                }
+                if let Some((k, v)) = upsert_kv {
+                    let (label, before) = current(&self.db);
+                    let mut after = before.clone();
+                    after.insert(k.clone(), v.clone());
+                    if !self.refused_by_rules(&label, &after) && !self.refused_as_computed(&label, Some(&before), &after) && self.db.upsert_node_metadata(id, k.clone(), v) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Set '{}' on node {}", k, caption));
+                    }
+                }
+                if save_desc && self.db.set_node_description(id, desc_text.clone()) {
+                    self.mark_dirty();
+                    self.record_history(format!("Edited description of node {}", caption));
+                }
+                if let Some(meta) = replace_meta {
+                    json_text = Some(import::metadata_to_json(&meta));
+                    let (label, before) = current(&self.db);
+                    if !self.refused_by_rules(&label, &meta) && !self.refused_as_computed(&label, Some(&before), &meta) && self.db.set_node_metadata(id, meta) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Replaced metadata of node {}", caption));
+                    }
                 }
-                if let Some((k, v)) = upsert_kv { if self.db.upsert_node_metadata(id, k, v) { self.re_cluster_pending = true; self.mark_dirty(); } }
                 // persist editors
                 self.node_label_edits.insert(id, label_text);
                 self.node_meta_new_kv.insert(id, new_meta_kv);
+                if save_desc || desc_text == node_snapshot.description {
+                    self.node_desc_edits.remove(&id);
+                } else {
+                    self.node_desc_edits.insert(id, desc_text);
+                }
+                match json_text {
+                    Some(text) => { self.node_meta_json.insert(id, text); }
+                    None => { self.node_meta_json.remove(&id); }
+                }
+                if let Some(peer) = center_peer.or(open_peer) { self.center_on(peer); }
+                if let Some(peer) = open_peer {
+                    self.selected = Some(SelectedItem::Node(peer));
+                    self.open_node_windows.insert(peer);
+                }
+                if let Some((rid, label)) = delete_connection
+                    && self.db.remove_relationship(rid)
+                {
+                    self.open_rel_windows.remove(&rid);
+                    if self.selected == Some(SelectedItem::Rel(rid)) { self.selected = None; }
+                    self.re_cluster_pending = true; self.mark_dirty();
+                    self.record_history(format!("Deleted relationship {}", label));
+                }
                 if delete_node {
                     if self.db.remove_node(id) {
                         self.node_positions.remove(&id);
                         if self.selected == Some(SelectedItem::Node(id)) { self.selected = None; }
                         self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Deleted node {}", caption));
                     }
                 }
                 if !open { nodes_to_close.push(id); }
@@ -3319,6 +7991,7 @@ impl eframe::App for GraphApp {
                 nodes_to_close.push(id);
             }
         }
+        self.connection_preview = connection_preview;
         for id in nodes_to_close {
             self.open_node_windows.remove(&id);
             if matches!(self.selected, Some(SelectedItem::Node(nid)) if nid == id) {
@@ -3346,7 +8019,14 @@ impl eframe::App for GraphApp {
                 let mut save_label = false;
                 let mut remove_keys: Vec<String> = Vec::new();
                 let mut upsert_rel_kv: Option<(String, String)> = None;
+                let mut json_text = self.rel_meta_json.get(&rid).cloned();
+                let mut replace_meta: Option<HashMap<String, String>> = None;
                 let mut delete_rel = false;
+                let mut desc_text = self.rel_desc_edits.get(&rid).cloned().unwrap_or_else(|| rel_snapshot.description.clone());
+                let mut save_desc = false;
+                let mut reverse = false;
+                let mut pick_end: Option<Option<PickTarget>> = None;
+                let mut search_end: Option<PickTarget> = None;
 
                 egui::Window::new(format!("Relationship {} Details", rid))
                     .id(egui::Id::new(("rel_details", rid)))
@@ -3354,12 +8034,15 @@ impl eframe::App for GraphApp {
                     .resizable(true)
                     .show(ctx, |ui| {
                         ui.label(format!("ID: {}", rid));
+                        timestamps_ui(ui, &rel_snapshot.created_at, &rel_snapshot.updated_at);
                         ui.horizontal(|ui| {
                             ui.label("Label:");
                             ui.text_edit_singleline(&mut label_text);
                             if ui.button("Save").clicked() { save_label = true; }
                         });
                         ui.separator();
+                        save_desc = description_editor(ui, &mut desc_text, &rel_snapshot.description);
+                        ui.separator();
                         ui.heading("Endpoints");
                         ui.label(format!("from: {}", rel_snapshot.from_node));
                         ui.label(format!("to:   {}", rel_snapshot.to_node));
@@ -3370,48 +8053,111 @@ impl eframe::App for GraphApp {
                             ui.label(format!("from label: {}", a.label));
                             ui.label(format!("to label:   {}", b.label));
                         }
-                        ui.separator();
-                        ui.heading("Metadata");
-                        if rel_snapshot.metadata.is_empty() {
-                            ui.label("<no metadata>");
-                        } else {
-                            let keys: Vec<String> = rel_snapshot.metadata.keys().cloned().collect();
-                            for k in keys {
-                                let v = rel_snapshot.metadata.get(&k).cloned().unwrap_or_default();
-                                ui.horizontal(|ui| {
-                                    ui.label(&k);
-                                    ui.label(":");
-                                    ui.monospace(&v);
-                                    if ui.button("Remove").clicked() { remove_keys.push(k.clone()); }
-                                });
-                            }
+                        if ui.button("Reverse Direction").on_hover_text("Swap from and to, keeping the label and metadata").clicked() { reverse = true; }
+                        for (end, target) in [("From", PickTarget::RelFrom(rid)), ("To", PickTarget::RelTo(rid))] {
+                            ui.horizontal(|ui| {
+                                let active = self.pick_target == Some(target);
+                                let text = if active { format!("Cancel Pick {}", end) } else { format!("Pick {} on Canvas", end) };
+                                if ui.button(text).clicked() { pick_end = Some(if active { None } else { Some(target) }); }
+                                if ui.button("Search…").clicked() { search_end = Some(target); }
+                            });
+                        }
+                        if matches!(self.pick_target, Some(PickTarget::RelFrom(r) | PickTarget::RelTo(r)) if r == rid) {
+                            ui.colored_label(Color32::YELLOW, "Picking on canvas: click the new endpoint (Esc to cancel)");
                         }
-                        // Add/Upsert metadata
                         ui.separator();
-                        ui.label("Add/Update Metadata");
+                        ui.heading("Metadata");
                         ui.horizontal(|ui| {
-                            ui.add(egui::TextEdit::singleline(&mut new_meta_kv.0).hint_text("key"));
-                            ui.label(":");
-                            ui.add(egui::TextEdit::singleline(&mut new_meta_kv.1).hint_text("value"));
-                            if ui.button("Upsert").clicked() {
-                                if !new_meta_kv.0.trim().is_empty() {
-                                    upsert_rel_kv = Some((new_meta_kv.0.trim().to_string(), new_meta_kv.1.trim().to_string()));
-                                    new_meta_kv.0.clear(); new_meta_kv.1.clear();
-                                }
+                            if ui.selectable_label(json_text.is_none(), "Fields").clicked() { json_text = None; }
+                            if ui.selectable_label(json_text.is_some(), "JSON").clicked() && json_text.is_none() {
+                                json_text = Some(import::metadata_to_json(&rel_snapshot.metadata));
                             }
                         });
+                        if let Some(text) = json_text.as_mut() {
+                            replace_meta = metadata_json_editor(ui, text, &rel_snapshot.metadata);
+                        } else {
+                            if rel_snapshot.metadata.is_empty() {
+                                ui.label("<no metadata>");
+                            } else {
+                                let keys: Vec<String> = rel_snapshot.metadata.keys().cloned().collect();
+                                for k in keys {
+                                    let v = rel_snapshot.metadata.get(&k).cloned().unwrap_or_default();
+                                    ui.horizontal(|ui| {
+                                        ui.label(&k);
+                                        ui.label(":");
+                                        ui.monospace(&v);
+                                        if ui.button("Remove").clicked() { remove_keys.push(k.clone()); }
+                                    });
+                                }
+                            }
+                            // Add/Upsert metadata
+                            ui.separator();
+                            ui.label("Add/Update Metadata");
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut new_meta_kv.0).hint_text("key"));
+                                ui.label(":");
+                                ui.add(egui::TextEdit::singleline(&mut new_meta_kv.1).hint_text("value"));
+                                if ui.button("Upsert").clicked() {
+                                    if !new_meta_kv.0.trim().is_empty() {
+                                        upsert_rel_kv = Some((new_meta_kv.0.trim().to_string(), new_meta_kv.1.trim().to_string()));
+                                        new_meta_kv.0.clear(); new_meta_kv.1.clear();
+                                    }
+                                }
+                            });
+                        }
                         ui.separator();
                         if ui.button(egui::RichText::new("Delete Relationship").color(Color32::RED)).clicked() { delete_rel = true; }
                     });
-                if save_label { if self.db.update_relationship_label(rid, label_text.clone()) { self.re_cluster_pending = true; self.mark_dirty(); } }
-                for k in remove_keys { if self.db.remove_relationship_metadata_key(rid, &k) { self.re_cluster_pending = true; self.mark_dirty(); } }
-                if let Some((k, v)) = upsert_rel_kv { if self.db.upsert_relationship_metadata(rid, k, v) { self.re_cluster_pending = true; self.mark_dirty(); } }
+                if save_label {
+                    if self.db.update_relationship_label(rid, label_text.clone()) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Relabeled relationship {} to {}", rel_snapshot.label, label_text));
+                    }
+                }
+                for k in remove_keys {
+                    if self.db.remove_relationship_metadata_key(rid, &k) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Removed '{}' from relationship {}", k, rel_snapshot.label));
+                    }
+                }
+                if let Some((k, v)) = upsert_rel_kv {
+                    if self.db.upsert_relationship_metadata(rid, k.clone(), v) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Set '{}' on relationship {}", k, rel_snapshot.label));
+                    }
+                }
+                if reverse {
+                    self.move_relationship(rid, rel_snapshot.to_node, rel_snapshot.from_node, format!("Reversed relationship {}", rel_snapshot.label));
+                }
+                if let Some(target) = pick_end { self.pick_target = target; }
+                if let Some(target) = search_end { self.open_node_picker(target); }
+                if save_desc && self.db.set_relationship_description(rid, desc_text.clone()) {
+                    self.mark_dirty();
+                    self.record_history(format!("Edited description of relationship {}", rel_snapshot.label));
+                }
+                if let Some(meta) = replace_meta {
+                    json_text = Some(import::metadata_to_json(&meta));
+                    if self.db.set_relationship_metadata(rid, meta) {
+                        self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Replaced metadata of relationship {}", rel_snapshot.label));
+                    }
+                }
                 self.rel_label_edits.insert(rid, label_text);
                 self.rel_meta_new_kv.insert(rid, new_meta_kv);
+                if save_desc || desc_text == rel_snapshot.description {
+                    self.rel_desc_edits.remove(&rid);
+                } else {
+                    self.rel_desc_edits.insert(rid, desc_text);
+                }
+                match json_text {
+                    Some(text) => { self.rel_meta_json.insert(rid, text); }
+                    None => { self.rel_meta_json.remove(&rid); }
+                }
                 if delete_rel {
                     if self.db.remove_relationship(rid) {
                         if self.selected == Some(SelectedItem::Rel(rid)) { self.selected = None; }
                         self.re_cluster_pending = true; self.mark_dirty();
+                        self.record_history(format!("Deleted relationship {}", rel_snapshot.label));
                     }
                 }
                 if !open { rels_to_close.push(rid); }
@@ -3441,17 +8187,23 @@ impl eframe::App for GraphApp {
             None => {}
         }
 
+        // Computed properties follow edits; those refreshed on an interval wake the UI when due
+        if computed::refresh(&mut self.db) > 0 { self.mark_dirty(); }
+        if let Some(wait) = computed::next_refresh(&self.db) { ctx.request_repaint_after(wait); }
+
         // Autosave logic: only after edits (5 seconds after the last change, prominent)
         let now = Instant::now();
-        if self.dirty && now.duration_since(self.last_change) >= Duration::from_secs(5) {
-            self.save_now_with(NoticeStyle::Prominent);
+        self.heartbeat_state_lock();
+        self.maybe_backup(false);
+        self.maybe_scheduled_export(false);
+        if self.dirty && self.read_only_reason.is_none() && now.duration_since(self.last_change) >= Duration::from_secs(5) {
+            self.autosave(NoticeStyle::Prominent);
         }
 
         // Load Versions modal
         if self.show_load_versions {
             let mut open = true;
             let mut to_load: Option<std::path::PathBuf> = None;
-            let mut loaded_label: Option<String> = None;
             egui::Window::new("Load Version")
                 .collapsible(false)
                 .resizable(true)
@@ -3464,7 +8216,6 @@ impl eframe::App for GraphApp {
                                 let label = p.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
                                 if ui.button(label).clicked() {
                                     to_load = Some(p.clone());
-                                    loaded_label = Some(label.to_string());
                                 }
                             }
                         }
@@ -3472,20 +8223,8 @@ impl eframe::App for GraphApp {
                     }
                 });
             if let Some(p) = to_load {
-                match persist::load_from_path(&p) {
-                    Ok(state) => {
-                        let (db, pos, pan, zoom) = state.to_runtime();
-                        self.db = db; self.node_positions = pos; self.pan = pan; self.zoom = zoom;
-                        self.selected = None; self.open_node_windows.clear(); self.open_rel_windows.clear();
-                        self.dirty = false; self.last_change = Instant::now();
-                        if let Some(lbl) = loaded_label { 
-                            self.last_save_info = Some(format!("Loaded {}", lbl));
-                            self.last_info_time = Some(Instant::now());
-                            self.last_info_style = NoticeStyle::Prominent;
-                        }
-                        self.save_error = None;
-                        open = false;
-                    }
+                match self.load_version(&p) {
+                    Ok(()) => open = false,
                     Err(e) => { self.save_error = Some(format!("Failed to load {}: {}", p.display(), e)); }
                 }
             }
@@ -3530,15 +8269,96 @@ impl eframe::App for GraphApp {
                     });
             }
         }
+
+        if self.app_settings.perf_hud { self.show_perf_hud(ctx); }
+        self.show_tour(ctx);
     }
-    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        if self.app_settings.background_on_close && (self.app_settings.api_enabled || self.app_settings.grpc_enabled) {
-            eprintln!("[Graph-Loom] background_on_close is enabled. The API server will continue to run if the process persists.");
-            // Note: In standard eframe, on_exit is the last chance to do something before the process exits.
-            // If we want to truly background, we would need to have started as a background-capable process.
-            // For now, this serves as a hint/hook for future implementation of a persistent service.
+}
+
+impl eframe::App for GraphApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let started = Instant::now();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.update_frame(ctx)));
+        self.frame_stats.record(started, started.elapsed());
+        if let Err(payload) = res {
+            self.emergency_save();
+            std::panic::resume_unwind(payload);
         }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.flush_on_exit();
+    }
+}
+
+// Lane of every relationship among the edges joining the same two nodes (either direction):
+// (index, count), with members ordered by id so lanes stay stable between frames
+fn parallel_edge_lanes(db: &GraphDatabase) -> HashMap<Uuid, (usize, usize)> {
+    let mut groups: HashMap<(NodeId, NodeId), Vec<Uuid>> = HashMap::new();
+    for r in db.relationships.values() {
+        let key = if r.from_node <= r.to_node { (r.from_node, r.to_node) } else { (r.to_node, r.from_node) };
+        groups.entry(key).or_default().push(r.id);
+    }
+    let mut lanes = HashMap::with_capacity(db.relationships.len());
+    for mut ids in groups.into_values() {
+        ids.sort();
+        let count = ids.len();
+        for (i, id) in ids.into_iter().enumerate() { lanes.insert(id, (i, count)); }
+    }
+    lanes
+}
+
+// Bend point of the two-segment polyline an edge is drawn (and hit-tested) as. A lone edge gets a
+// small curvature whose side is hashed from its endpoints; parallel edges fan out in evenly spaced
+// lanes measured against the lower-id -> higher-id direction, so opposite edges do not overlap.
+fn edge_control_point(a: Pos2, b: Pos2, from_id: NodeId, to_id: NodeId, lane: (usize, usize), zoom: f32) -> Pos2 {
+    let dir = Vec2::new(b.x - a.x, b.y - a.y);
+    let len = (dir.x * dir.x + dir.y * dir.y).sqrt();
+    let mid = Pos2::new((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
+    if len <= 1.0 { return mid; }
+    let n = Vec2::new(-dir.y / len, dir.x / len);
+    let (index, count) = lane;
+    if count <= 1 {
+        let mut seed = from_id.as_u128() ^ to_id.as_u128();
+        seed ^= seed >> 33;
+        let sign = if (seed & 1) == 0 { 1.0 } else { -1.0 };
+        let mag = (8.0 * zoom).clamp(2.0, 16.0);
+        return mid + n * (mag * sign);
+    }
+    let canonical = if from_id <= to_id { n } else { -n };
+    let spacing = (14.0 * zoom).clamp(6.0, 28.0);
+    mid + canonical * (spacing * (index as f32 - (count - 1) as f32 * 0.5))
+}
+
+// Circle (center, radius) a self-loop is drawn as: it rests on top of the node and grows with the
+// lane index so several loops on one node nest instead of overlapping
+fn self_loop_circle(node: Pos2, lane: (usize, usize), zoom: f32) -> (Pos2, f32) {
+    let node_radius = 10.0 * zoom;
+    let r = (8.0 * zoom).clamp(4.0, 20.0) * (1.0 + 0.5 * lane.0 as f32);
+    (node - Vec2::new(0.0, node_radius * 0.6 + r), r)
+}
+
+// Relationship drawn nearest to `pointer` (screen space) within a few pixels, measured against the
+// two segments (a->ctrl, ctrl->b) or loop it is drawn as; parallel edges can be picked on their own
+fn edge_under(db: &GraphDatabase, positions: &HashMap<NodeId, Pos2>, pointer: Pos2, zoom: f32, to_screen: impl Fn(Pos2) -> Pos2) -> Option<Uuid> {
+    let tolerance_px = 8.0_f32; // selection slop in screen pixels
+    let lanes = parallel_edge_lanes(db);
+    let mut best: Option<(Uuid, f32)> = None; // (rel_id, distance)
+    for rel in db.relationships.values() {
+        let (Some(pa), Some(pb)) = (positions.get(&rel.from_node), positions.get(&rel.to_node)) else { continue };
+        let a = to_screen(*pa);
+        let b = to_screen(*pb);
+        let lane = lanes.get(&rel.id).copied().unwrap_or((0, 1));
+        let d = if rel.from_node == rel.to_node {
+            let (c, r) = self_loop_circle(a, lane, zoom);
+            (pointer.distance(c) - r).abs()
+        } else {
+            let ctrl = edge_control_point(a, b, rel.from_node, rel.to_node, lane, zoom);
+            point_segment_distance(pointer, a, ctrl).min(point_segment_distance(pointer, ctrl, b))
+        };
+        if d <= tolerance_px && best.is_none_or(|(_, bd)| d < bd) { best = Some((rel.id, d)); }
+    }
+    best.map(|(id, _)| id)
 }
 
 // Geometry helper: distance from point P to segment AB in screen space
@@ -3555,6 +8375,11 @@ fn point_segment_distance(p: Pos2, a: Pos2, b: Pos2) -> f32 {
 }
 
 // UI helpers
+fn timestamps_ui(ui: &mut egui::Ui, created_at: &str, updated_at: &str) {
+    let show = |s: &str| if s.is_empty() { "unknown".to_string() } else { s.to_string() };
+    ui.small(format!("Created: {}   Updated: {}", show(created_at), show(updated_at)));
+}
+
 fn _short_uuid(id: Uuid) -> String {
     let s = id.as_simple().to_string();
     s.chars().rev().take(8).collect::<Vec<char>>().into_iter().rev().collect()
@@ -3593,6 +8418,98 @@ fn format_short_node(db: &GraphDatabase, id: NodeId) -> String {
     "<unknown>".to_string()
 }
 
+// Paint one annotation; positions are in world space and mapped with `to_screen`
+fn draw_annotation(painter: &egui::Painter, shape: AnnotationShape, text: &str, color: Color32, selected: bool, text_size: f32, to_screen: impl Fn(Pos2) -> Pos2) {
+    let font = egui::FontId::proportional(text_size);
+    let stroke = Stroke::new(if selected { 3.0 } else { 2.0 }, color);
+    match shape {
+        AnnotationShape::Text { at } => {
+            let p = to_screen(Pos2::new(at.0, at.1));
+            let galley = painter.layout_no_wrap(text.to_string(), font, color);
+            let r = Rect::from_min_size(p, galley.size()).expand(4.0);
+            painter.rect_filled(r, 4.0, Color32::from_rgba_unmultiplied(0, 0, 0, 140));
+            if selected { painter.rect_stroke(r, 4.0, Stroke::new(1.0, color), egui::StrokeKind::Outside); }
+            painter.galley(p, galley, color);
+        }
+        AnnotationShape::Rect { min, max } => {
+            let r = Rect::from_two_pos(to_screen(Pos2::new(min.0, min.1)), to_screen(Pos2::new(max.0, max.1)));
+            painter.rect_filled(r, 6.0, Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), 24));
+            painter.rect_stroke(r, 6.0, stroke, egui::StrokeKind::Inside);
+            if !text.is_empty() {
+                painter.text(r.min + Vec2::splat(6.0), egui::Align2::LEFT_TOP, text, font, color);
+            }
+        }
+        AnnotationShape::Arrow { from, to } => {
+            let (a, b) = (to_screen(Pos2::new(from.0, from.1)), to_screen(Pos2::new(to.0, to.1)));
+            painter.arrow(a, b - a, stroke);
+            if !text.is_empty() {
+                painter.text(a, egui::Align2::CENTER_BOTTOM, text, font, color);
+            }
+        }
+    }
+}
+
+// JSON tab of the metadata editor: the whole map as one object, validated while typing.
+// Returns the new map when Apply is clicked.
+fn metadata_json_editor(ui: &mut egui::Ui, text: &mut String, current: &HashMap<String, String>) -> Option<HashMap<String, String>> {
+    ui.add(egui::TextEdit::multiline(text).code_editor().desired_rows(8).desired_width(f32::INFINITY));
+    let parsed = import::parse_metadata_json(text);
+    match &parsed {
+        Ok(m) => { ui.weak(format!("{} key(s)", m.len())); }
+        Err(e) => { ui.colored_label(Color32::RED, format!("Invalid: {}", e)); }
+    }
+    let mut apply = false;
+    ui.horizontal(|ui| {
+        let changed = parsed.as_ref().is_ok_and(|m| m != current);
+        apply = ui.add_enabled(changed, egui::Button::new("Apply")).clicked();
+        if ui.button("Reset").clicked() { *text = import::metadata_to_json(current); }
+    });
+    if apply { parsed.ok() } else { None }
+}
+
+// Free-form notes of a node or relationship. Returns true when Save is clicked.
+fn description_editor(ui: &mut egui::Ui, text: &mut String, current: &str) -> bool {
+    ui.heading("Description");
+    ui.add(egui::TextEdit::multiline(text).desired_rows(4).desired_width(f32::INFINITY).hint_text("Notes, context, links…"));
+    let mut save = false;
+    ui.horizontal(|ui| {
+        let changed = text.as_str() != current;
+        save = ui.add_enabled(changed, egui::Button::new("Save")).clicked();
+        if changed && ui.button("Reset").clicked() { *text = current.to_string(); }
+    });
+    save
+}
+
+// Stream an export to `path`, reporting elements written past `offset`
+// Write the nodes and relationships tables next to `path` as `<stem>_nodes.<ext>` and
+// `<stem>_relationships.<ext>`, shaped by `template`
+fn export_tables(db: &GraphDatabase, path: &std::path::Path, template: &ExportTemplate, format: ColumnarFormat, progress: &Progress) -> anyhow::Result<TaskOutput> {
+    progress.set_total(db.nodes.len() + db.relationships.len());
+    let [nodes, rels] = columnar::export_files(db, path, template, format, &mut |done| {
+        progress.check()?;
+        progress.set_done(done);
+        Ok(())
+    })?;
+    let kind = match format {
+        ColumnarFormat::Parquet => "Parquet",
+        ColumnarFormat::ArrowIpc => "Arrow",
+        ColumnarFormat::Csv => "CSV",
+    };
+    Ok(TaskOutput::Export(format!("Exported {} files: {} and {}", kind, nodes.display(), rels.display())))
+}
+
+fn write_export_file(mut chunks: ExportChunks, path: &std::path::Path, progress: &Progress, offset: usize) -> anyhow::Result<()> {
+    use std::io::Write;
+    let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+    while let Some(bytes) = chunks.next() {
+        progress.check()?;
+        w.write_all(&bytes)?;
+        progress.set_done(offset + chunks.progress().0);
+    }
+    w.flush()?;
+    Ok(())
+}
+
 // Golden-angle spiral placement around the provided center.
 // k is the 0-based index along the spiral.
 fn golden_spiral_position(center: Pos2, k: u32, rect: Rect) -> Pos2 {
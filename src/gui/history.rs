@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use eframe::egui::Pos2;
+
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+/// Maximum number of graph states kept for undo/redo.
+pub const HISTORY_CAPACITY: usize = 50;
+/// Maximum number of restore points kept; the oldest is dropped first.
+pub const RESTORE_POINT_CAPACITY: usize = 5;
+/// A query that changes at least this many nodes and relationships gets a restore point.
+pub const MASS_MUTATION_THRESHOLD: usize = 25;
+
+/// One recorded graph state and the operation that produced it.
+pub struct HistoryEntry {
+    pub label: String,
+    pub at: Instant,
+    snapshot: GraphDatabase,
+}
+
+/// Linear, snapshot-based edit history. Each entry holds the graph *after* its operation;
+/// `cursor` points at the entry matching the current graph.
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    cursor: usize,
+}
+
+impl History {
+    /// Start a new history whose only state is `db`.
+    pub fn new(db: &GraphDatabase, label: impl Into<String>) -> Self {
+        Self {
+            entries: vec![HistoryEntry { label: label.into(), at: Instant::now(), snapshot: db.clone() }],
+            cursor: 0,
+        }
+    }
+
+    /// Record the graph after an operation. Any redo states past the cursor are dropped.
+    pub fn record(&mut self, db: &GraphDatabase, label: impl Into<String>) {
+        self.entries.truncate(self.cursor + 1);
+        self.entries.push(HistoryEntry { label: label.into(), at: Instant::now(), snapshot: db.clone() });
+        if self.entries.len() > HISTORY_CAPACITY {
+            let excess = self.entries.len() - HISTORY_CAPACITY;
+            self.entries.drain(0..excess);
+        }
+        self.cursor = self.entries.len() - 1;
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.cursor + 1 < self.entries.len()
+    }
+
+    /// Move to entry `index` and return the graph state to restore.
+    pub fn jump_to(&mut self, index: usize) -> Option<GraphDatabase> {
+        if index >= self.entries.len() || index == self.cursor { return None; }
+        self.cursor = index;
+        Some(self.entries[index].snapshot.clone())
+    }
+
+    pub fn undo(&mut self) -> Option<GraphDatabase> {
+        if !self.can_undo() { return None; }
+        self.jump_to(self.cursor - 1)
+    }
+
+    pub fn redo(&mut self) -> Option<GraphDatabase> {
+        if !self.can_redo() { return None; }
+        self.jump_to(self.cursor + 1)
+    }
+}
+
+/// The graph and layout as they were right before a destructive operation. Unlike history
+/// entries, restore points survive operations that start the history over (New Graph,
+/// replacing imports).
+pub struct RestorePoint {
+    pub label: String,
+    pub at: Instant,
+    snapshot: GraphDatabase,
+    positions: HashMap<NodeId, Pos2>,
+}
+
+/// Most recent restore points, newest last.
+#[derive(Default)]
+pub struct RestorePoints {
+    points: Vec<RestorePoint>,
+}
+
+impl RestorePoints {
+    /// Keep a copy of `db` taken before `label`. An empty graph has nothing to restore and is
+    /// skipped.
+    pub fn create(&mut self, db: &GraphDatabase, positions: &HashMap<NodeId, Pos2>, label: impl Into<String>) {
+        if db.nodes.is_empty() && db.relationships.is_empty() { return; }
+        self.push(db.clone(), positions.clone(), label);
+    }
+
+    /// Like [`RestorePoints::create`] for a copy the caller already made.
+    pub fn push(&mut self, db: GraphDatabase, positions: HashMap<NodeId, Pos2>, label: impl Into<String>) {
+        self.points.push(RestorePoint { label: label.into(), at: Instant::now(), snapshot: db, positions });
+        if self.points.len() > RESTORE_POINT_CAPACITY {
+            let excess = self.points.len() - RESTORE_POINT_CAPACITY;
+            self.points.drain(0..excess);
+        }
+    }
+
+    pub fn latest(&self) -> Option<&RestorePoint> {
+        self.points.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Remove the newest restore point and return its label, graph and node positions.
+    pub fn take_latest(&mut self) -> Option<(String, GraphDatabase, HashMap<NodeId, Pos2>)> {
+        self.points.pop().map(|p| (p.label, p.snapshot, p.positions))
+    }
+}
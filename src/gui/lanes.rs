@@ -0,0 +1,169 @@
+//! Swimlanes: nodes grouped into bands by the value of one metadata key (e.g. `team` or
+//! `tier`), for architecture diagrams. Lanes live in world space; the canvas draws a header
+//! per lane and keeps each node inside the lane of its value while the layout moves.
+
+use std::collections::{BTreeMap, HashMap};
+
+use eframe::egui::{Pos2, Rect, Vec2};
+
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+/// Distance between neighboring nodes of a lane.
+const SPACING: f32 = 80.0;
+/// Room between a lane's edges and its nodes; the header sits in it.
+const PAD: f32 = 40.0;
+/// Thinnest lane, so a lane of one node still has room for its header.
+const MIN_THICKNESS: f32 = 140.0;
+/// Header of the lane for nodes without the key.
+pub const NO_VALUE: &str = "(none)";
+
+/// Which way lanes run.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LaneAxis {
+    /// Horizontal lanes stacked top to bottom
+    #[default]
+    Rows,
+    /// Vertical lanes side by side
+    Columns,
+}
+
+impl LaneAxis {
+    // (along the lane, across it)
+    fn split(self, p: Pos2) -> (f32, f32) {
+        match self {
+            LaneAxis::Rows => (p.x, p.y),
+            LaneAxis::Columns => (p.y, p.x),
+        }
+    }
+
+    fn point(self, along: f32, across: f32) -> Pos2 {
+        match self {
+            LaneAxis::Rows => Pos2::new(along, across),
+            LaneAxis::Columns => Pos2::new(across, along),
+        }
+    }
+}
+
+/// One lane: the nodes whose key has `value` (`None` when they lack it), between `start` and
+/// `end` across the lanes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lane {
+    pub value: Option<String>,
+    pub start: f32,
+    pub end: f32,
+    pub count: usize,
+}
+
+/// Lanes of a graph by one metadata key.
+#[derive(Clone, Debug)]
+pub struct Swimlanes {
+    pub key: String,
+    pub axis: LaneAxis,
+    pub lanes: Vec<Lane>,
+    lane_of: HashMap<NodeId, usize>,
+}
+
+// Node ids per value of `key`, values in order and nodes without the key last
+fn group(db: &GraphDatabase, key: &str) -> Vec<(Option<String>, Vec<NodeId>)> {
+    let mut groups: BTreeMap<Option<&str>, Vec<NodeId>> = BTreeMap::new();
+    for n in db.nodes.values() {
+        groups.entry(n.metadata.get(key).map(|v| v.as_str())).or_default().push(n.id);
+    }
+    let missing = groups.remove(&None);
+    groups
+        .into_iter()
+        .chain(missing.map(|ids| (None, ids)))
+        .map(|(value, mut ids)| {
+            // Same label side by side
+            ids.sort_by_key(|id| (db.nodes[id].label.as_str(), *id));
+            (value.map(str::to_string), ids)
+        })
+        .collect()
+}
+
+impl Swimlanes {
+    /// Lanes of `db` by `key` starting at `origin`, and a position in its lane for every node:
+    /// a grid a few times longer than it is thick.
+    pub fn arrange(db: &GraphDatabase, key: &str, axis: LaneAxis, origin: Pos2) -> (Self, HashMap<NodeId, Pos2>) {
+        let (along0, mut start) = axis.split(origin);
+        let mut lanes = Vec::new();
+        let mut lane_of = HashMap::new();
+        let mut positions = HashMap::new();
+        for (i, (value, ids)) in group(db, key).into_iter().enumerate() {
+            let per_line = ((ids.len() as f32 * 3.0).sqrt().ceil() as usize).max(1);
+            let lines = ids.len().div_ceil(per_line);
+            let spread = lines.saturating_sub(1) as f32 * SPACING;
+            let thickness = (spread + 2.0 * PAD).max(MIN_THICKNESS);
+            let first = start + (thickness - spread) / 2.0;
+            for (j, id) in ids.iter().enumerate() {
+                let along = along0 + PAD + (j % per_line) as f32 * SPACING;
+                positions.insert(*id, axis.point(along, first + (j / per_line) as f32 * SPACING));
+                lane_of.insert(*id, i);
+            }
+            lanes.push(Lane { value, start, end: start + thickness, count: ids.len() });
+            start += thickness;
+        }
+        (Swimlanes { key: key.to_string(), axis, lanes, lane_of }, positions)
+    }
+
+    /// Follow edits: nodes move to the lane of their current value, and a value without a lane
+    /// gets a new one after the others. Lanes keep their place even once empty.
+    pub fn refresh(&mut self, db: &GraphDatabase) {
+        self.lane_of.clear();
+        for lane in &mut self.lanes { lane.count = 0; }
+        for n in db.nodes.values() {
+            let value = n.metadata.get(&self.key);
+            let i = match self.lanes.iter().position(|l| l.value.as_ref() == value) {
+                Some(i) => i,
+                None => {
+                    let start = self.lanes.last().map_or(0.0, |l| l.end);
+                    self.lanes.push(Lane { value: value.cloned(), start, end: start + MIN_THICKNESS, count: 0 });
+                    self.lanes.len() - 1
+                }
+            };
+            self.lanes[i].count += 1;
+            self.lane_of.insert(n.id, i);
+        }
+    }
+
+    /// Pull nodes that drifted out of their lane back to its edge and stop them moving across.
+    pub fn confine(&self, positions: &mut HashMap<NodeId, Pos2>, velocities: &mut HashMap<NodeId, Vec2>) {
+        for (id, p) in positions.iter_mut() {
+            let Some(lane) = self.lane_of(*id) else { continue };
+            let (along, across) = self.axis.split(*p);
+            let inside = across.clamp(lane.start + PAD / 2.0, lane.end - PAD / 2.0);
+            if inside != across {
+                *p = self.axis.point(along, inside);
+                if let Some(v) = velocities.get_mut(id) {
+                    *v = match self.axis {
+                        LaneAxis::Rows => Vec2::new(v.x, 0.0),
+                        LaneAxis::Columns => Vec2::new(0.0, v.y),
+                    };
+                }
+            }
+        }
+    }
+
+    /// The lane `id` is kept in.
+    pub fn lane_of(&self, id: NodeId) -> Option<&Lane> {
+        self.lane_of.get(&id).map(|i| &self.lanes[*i])
+    }
+
+    /// World rectangle of each lane, long enough to span every node.
+    pub fn rects(&self, positions: &HashMap<NodeId, Pos2>) -> Vec<Rect> {
+        let (min, max) = positions
+            .values()
+            .map(|p| self.axis.split(*p).0)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), a| (lo.min(a), hi.max(a)));
+        let (min, max) = if min <= max { (min - PAD, max + PAD) } else { (0.0, 0.0) };
+        self.lanes
+            .iter()
+            .map(|l| Rect::from_two_pos(self.axis.point(min, l.start), self.axis.point(max, l.end)))
+            .collect()
+    }
+
+    /// Header drawn at the start of `lane`, e.g. `team: platform (4)`.
+    pub fn header(&self, lane: &Lane) -> String {
+        format!("{}: {} ({})", self.key, lane.value.as_deref().unwrap_or(NO_VALUE), lane.count)
+    }
+}
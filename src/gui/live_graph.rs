@@ -0,0 +1,43 @@
+//! The graph the window edits, kept behind an `Arc` so worker threads (autosave, exports)
+//! can take it without a copy. Writing goes through [`Arc::make_mut`]: the graph is copied
+//! only when a worker still holds an earlier snapshot, and only once until the next one.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::graph_utils::graph::GraphDatabase;
+
+#[derive(Clone)]
+pub struct LiveGraph(Arc<GraphDatabase>);
+
+impl LiveGraph {
+    /// The graph as of now, for another thread. Costs a reference count, not a copy.
+    pub fn snapshot(&self) -> Arc<GraphDatabase> {
+        Arc::clone(&self.0)
+    }
+
+    /// The graph itself, copied only if a snapshot of it is still held.
+    pub fn into_inner(self) -> GraphDatabase {
+        Arc::unwrap_or_clone(self.0)
+    }
+}
+
+impl From<GraphDatabase> for LiveGraph {
+    fn from(db: GraphDatabase) -> Self {
+        LiveGraph(Arc::new(db))
+    }
+}
+
+impl Deref for LiveGraph {
+    type Target = GraphDatabase;
+
+    fn deref(&self) -> &GraphDatabase {
+        &self.0
+    }
+}
+
+impl DerefMut for LiveGraph {
+    fn deref_mut(&mut self) -> &mut GraphDatabase {
+        Arc::make_mut(&mut self.0)
+    }
+}
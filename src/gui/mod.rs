@@ -1,6 +1,22 @@
+pub mod edge_text;
 pub mod frontend;
+pub mod history;
+pub mod lanes;
+pub mod live_graph;
+pub mod navigation;
+pub mod palette;
+pub mod physics;
+pub mod result_view;
+pub mod schema_view;
+pub mod session;
+pub mod status;
+pub mod tasks;
+pub mod tour;
+pub mod tray;
 pub mod win_utils;
 pub mod app_state {
     use std::sync::atomic::AtomicBool;
     pub static SHOW_WINDOW: AtomicBool = AtomicBool::new(true);
+    // Set by the tray Quit item or a termination signal: close for real instead of hiding
+    pub static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
 }
\ No newline at end of file
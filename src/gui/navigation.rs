@@ -0,0 +1,150 @@
+//! Canvas navigation beyond the mouse wheel: trackpad scrolling that pans, pinch zoom
+//! (delivered by egui as a zoom delta), zooming about the pointer, kinetic panning that lets
+//! the view glide on briefly after the background is flung, and eased camera moves for
+//! commands that take the view somewhere.
+
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{Event, MouseWheelUnit, Pos2, Rect, Vec2};
+
+/// Zoom levels the canvas allows.
+pub const ZOOM_RANGE: RangeInclusive<f32> = 0.25..=2.0;
+
+/// Glides stop below this speed, in points per second.
+const MIN_GLIDE_SPEED: f32 = 30.0;
+/// Exponential decay rate of a glide, per second.
+const GLIDE_FRICTION: f32 = 5.0;
+/// A drag released after resting this long does not glide, in seconds.
+const MAX_RELEASE_PAUSE: f64 = 0.08;
+
+/// Canvas scrolling of one frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScrollInput {
+    /// Pan from smooth (trackpad) scrolling
+    pub pan: Vec2,
+    /// Vertical wheel movement in points, which zooms
+    pub wheel: f32,
+}
+
+/// Sort this frame's scroll events into panning and zooming. With `trackpad_pans`, smooth
+/// scrolling reported in points (trackpads, touch pads) pans; wheel notches reported in lines
+/// or pages always zoom. Scrolling with Ctrl/Cmd held is left out: egui reports it as a zoom
+/// delta together with pinch gestures.
+pub fn split_scroll(events: &[Event], trackpad_pans: bool, line_speed: f32) -> ScrollInput {
+    let mut out = ScrollInput::default();
+    for e in events {
+        let Event::MouseWheel { unit, delta, modifiers } = e else { continue };
+        if modifiers.command || modifiers.ctrl { continue; }
+        match unit {
+            MouseWheelUnit::Point if trackpad_pans => out.pan += *delta,
+            MouseWheelUnit::Point => out.wheel += delta.y,
+            MouseWheelUnit::Line => out.wheel += delta.y * line_speed,
+            MouseWheelUnit::Page => out.wheel += delta.y * line_speed * 10.0,
+        }
+    }
+    out
+}
+
+/// Zoom and pan after scaling the view by `factor` about `anchor`, so the point under the
+/// anchor stays put. The canvas maps world to screen as `(p - center) * zoom + center + pan`.
+pub fn zoom_about(zoom: f32, pan: Vec2, center: Pos2, anchor: Pos2, factor: f32) -> (f32, Vec2) {
+    let new_zoom = (zoom * factor).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+    // Use the ratio after clamping, so zooming past a limit does not drift the view
+    let offset = anchor - center;
+    (new_zoom, offset - (offset - pan) * (new_zoom / zoom))
+}
+
+/// Length of a camera move.
+const CAMERA_MOVE: Duration = Duration::from_millis(400);
+/// Room left around nodes the view is fitted to, in world units.
+const FIT_MARGIN: f32 = 60.0;
+
+/// Zoom and pan that show the world rectangle `bounds` in the middle of `canvas`. Fitting never
+/// zooms in past 100%, so a lone node is centered rather than blown up.
+pub fn fit_view(bounds: Rect, canvas: Rect) -> (f32, Vec2) {
+    let size = bounds.expand(FIT_MARGIN).size();
+    let zoom = (canvas.width() / size.x).min(canvas.height() / size.y).clamp(*ZOOM_RANGE.start(), 1.0);
+    // Put the middle of `bounds` at the canvas center: (p - c) * zoom + c + pan = c
+    (zoom, (canvas.center() - bounds.center()) * zoom)
+}
+
+/// An eased transition of the view from one zoom and pan to another. The point at the canvas
+/// center travels in a straight line while the zoom changes at an even rate, so the target
+/// stays in sight instead of swinging out and back as it would if pan moved on its own.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraMove {
+    center: Pos2,
+    from: (f32, Pos2),
+    to: (f32, Pos2),
+    target_pan: Vec2,
+    started: Instant,
+}
+
+impl CameraMove {
+    /// Move from `zoom`/`pan` to `target`, starting at `now`, on a canvas centered at `center`.
+    pub fn new(center: Pos2, zoom: f32, pan: Vec2, target: (f32, Vec2), now: Instant) -> Self {
+        // World point at the canvas center: (p - c) * zoom + c + pan = c
+        let focus = |zoom: f32, pan: Vec2| center - pan / zoom;
+        CameraMove { center, from: (zoom, focus(zoom, pan)), to: (target.0, focus(target.0, target.1)), target_pan: target.1, started: now }
+    }
+
+    /// Zoom and pan at `now`, and whether the move is over.
+    pub fn at(&self, now: Instant) -> (f32, Vec2, bool) {
+        let t = now.saturating_duration_since(self.started).as_secs_f32() / CAMERA_MOVE.as_secs_f32();
+        if t >= 1.0 { return (self.to.0, self.target_pan, true); }
+        let e = ease_in_out(t);
+        let zoom = self.from.0 * (self.to.0 / self.from.0).powf(e);
+        let focus = self.from.1 + (self.to.1 - self.from.1) * e;
+        (zoom, (self.center - focus) * zoom, false)
+    }
+}
+
+// Cubic ease-in-out on [0, 1]
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+}
+
+/// Velocity of a background drag, continued as a decaying glide once the drag is released.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Inertia {
+    velocity: Vec2,
+    last_sample: Option<f64>,
+    gliding: bool,
+}
+
+impl Inertia {
+    /// The view was dragged by `delta` at `time` (seconds, as in egui input).
+    pub fn track(&mut self, delta: Vec2, time: f64) {
+        self.gliding = false;
+        if let Some(t0) = self.last_sample {
+            let dt = (time - t0) as f32;
+            if dt > 0.0 {
+                // Blend with earlier samples so one uneven frame does not decide the fling
+                self.velocity = self.velocity * 0.3 + (delta / dt) * 0.7;
+            }
+        }
+        self.last_sample = Some(time);
+    }
+
+    /// The drag ended at `time`. A fast drag keeps going; one that came to rest first stops.
+    pub fn release(&mut self, time: f64) {
+        let recent = self.last_sample.is_some_and(|t| time - t <= MAX_RELEASE_PAUSE);
+        self.gliding = recent && self.velocity.length() >= MIN_GLIDE_SPEED;
+        if !self.gliding { self.velocity = Vec2::ZERO; }
+        self.last_sample = None;
+    }
+
+    pub fn stop(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Pan for the next `dt` seconds of a glide, or `None` once it has come to rest.
+    pub fn step(&mut self, dt: f32) -> Option<Vec2> {
+        if !self.gliding { return None; }
+        let pan = self.velocity * dt;
+        self.velocity *= (-GLIDE_FRICTION * dt).exp();
+        if self.velocity.length() < MIN_GLIDE_SPEED { self.stop(); }
+        Some(pan)
+    }
+}
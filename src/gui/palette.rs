@@ -0,0 +1,145 @@
+//! Canvas colors: the per-label palette and the highlight colors for selections, query
+//! matches and relationship endpoints. Besides the original palette there are two schemes
+//! that stay distinguishable with common color-vision deficiencies.
+
+use eframe::egui::Color32;
+
+use crate::persistence::settings::ColorPalette;
+
+/// Colors for one [`ColorPalette`].
+#[derive(Debug)]
+pub struct Palette {
+    /// Label colors, picked by hashing the label
+    pub labels: &'static [Color32],
+    /// Fill of the node whose details window is open
+    pub selected: Color32,
+    /// Relationship whose details window is open
+    pub selected_rel: Color32,
+    /// Query matches and edges of the hovered node
+    pub query_match: Color32,
+    /// Halo of nodes and stroke of edges in the bulk selection
+    pub multi_select: Color32,
+    /// Outline of the From node of a new relationship
+    pub pick_from: Color32,
+    /// Outline of the To node of a new relationship
+    pub pick_to: Color32,
+}
+
+const STANDARD: Palette = Palette {
+    labels: &[
+        Color32::from_rgb(0x7b, 0xa3, 0xff), // blue
+        Color32::from_rgb(0xff, 0xa3, 0x7b), // orange
+        Color32::from_rgb(0x7b, 0xff, 0xa3), // green
+        Color32::from_rgb(0xff, 0x7b, 0xa3), // pink
+        Color32::from_rgb(0xa3, 0x7b, 0xff), // violet
+        Color32::from_rgb(0xff, 0xe0, 0x7b), // yellow
+        Color32::from_rgb(0x7b, 0xff, 0xe0), // teal
+        Color32::from_rgb(0xe0, 0x7b, 0xff), // purple
+        Color32::from_rgb(0x7b, 0xe0, 0xff), // cyan
+        Color32::from_rgb(0xff, 0x7b, 0xe0), // magenta
+        Color32::from_rgb(0x9a, 0xcd, 0x32), // yellowgreen
+        Color32::from_rgb(0xcd, 0x32, 0x9a), // fuchsia
+    ],
+    selected: Color32::from_rgb(80, 120, 255),
+    selected_rel: Color32::from_rgb(255, 200, 80),
+    query_match: Color32::from_rgb(120, 220, 255),
+    multi_select: Color32::from_rgb(120, 200, 255),
+    pick_from: Color32::from_rgb(80, 220, 120),
+    pick_to: Color32::from_rgb(255, 170, 60),
+};
+
+// Okabe & Ito, "Color Universal Design"; black is left out as it vanishes on the canvas
+const OKABE_ITO: Palette = Palette {
+    labels: &[
+        Color32::from_rgb(0xe6, 0x9f, 0x00), // orange
+        Color32::from_rgb(0x56, 0xb4, 0xe9), // sky blue
+        Color32::from_rgb(0x00, 0x9e, 0x73), // bluish green
+        Color32::from_rgb(0xf0, 0xe4, 0x42), // yellow
+        Color32::from_rgb(0x00, 0x72, 0xb2), // blue
+        Color32::from_rgb(0xd5, 0x5e, 0x00), // vermillion
+        Color32::from_rgb(0xcc, 0x79, 0xa7), // reddish purple
+        Color32::from_rgb(0x99, 0x99, 0x99), // grey
+    ],
+    selected: Color32::from_rgb(0x00, 0x72, 0xb2),
+    selected_rel: Color32::from_rgb(0xe6, 0x9f, 0x00),
+    query_match: Color32::from_rgb(0xf0, 0xe4, 0x42),
+    multi_select: Color32::from_rgb(0xcc, 0x79, 0xa7),
+    pick_from: Color32::from_rgb(0x56, 0xb4, 0xe9),
+    pick_to: Color32::from_rgb(0xd5, 0x5e, 0x00),
+};
+
+// Paul Tol's "bright" qualitative scheme
+const TOL_BRIGHT: Palette = Palette {
+    labels: &[
+        Color32::from_rgb(0x44, 0x77, 0xaa), // blue
+        Color32::from_rgb(0x66, 0xcc, 0xee), // cyan
+        Color32::from_rgb(0x22, 0x88, 0x33), // green
+        Color32::from_rgb(0xcc, 0xbb, 0x44), // yellow
+        Color32::from_rgb(0xee, 0x66, 0x77), // red
+        Color32::from_rgb(0xaa, 0x33, 0x77), // purple
+        Color32::from_rgb(0xbb, 0xbb, 0xbb), // grey
+    ],
+    selected: Color32::from_rgb(0x44, 0x77, 0xaa),
+    selected_rel: Color32::from_rgb(0xcc, 0xbb, 0x44),
+    query_match: Color32::from_rgb(0x66, 0xcc, 0xee),
+    multi_select: Color32::from_rgb(0xaa, 0x33, 0x77),
+    pick_from: Color32::from_rgb(0x22, 0x88, 0x33),
+    pick_to: Color32::from_rgb(0xee, 0x66, 0x77),
+};
+
+/// Every palette with the name shown in Preferences.
+pub const ALL: [(ColorPalette, &str); 3] = [
+    (ColorPalette::Standard, "Standard"),
+    (ColorPalette::OkabeIto, "Okabe–Ito (red-green safe)"),
+    (ColorPalette::TolBright, "Tol Bright (color-blind safe)"),
+];
+
+pub fn palette(kind: ColorPalette) -> &'static Palette {
+    match kind {
+        ColorPalette::Standard => &STANDARD,
+        ColorPalette::OkabeIto => &OKABE_ITO,
+        ColorPalette::TolBright => &TOL_BRIGHT,
+    }
+}
+
+pub fn name(kind: ColorPalette) -> &'static str {
+    ALL.iter().find(|(k, _)| *k == kind).map(|(_, n)| *n).unwrap_or("Standard")
+}
+
+/// Stable color for `label`, chosen from the palette by hashing.
+pub fn label_color(kind: ColorPalette, label: &str) -> Color32 {
+    use std::hash::{Hash, Hasher};
+    let labels = palette(kind).labels;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    label.hash(&mut hasher);
+    labels[hasher.finish() as usize % labels.len()]
+}
+
+/// Relative luminance as defined by WCAG 2.
+pub fn relative_luminance(c: Color32) -> f32 {
+    let channel = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.03928 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * channel(c.r()) + 0.7152 * channel(c.g()) + 0.0722 * channel(c.b())
+}
+
+/// WCAG contrast ratio between two colors, from 1 (identical) to 21 (black on white).
+pub fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// `fg` lightened or darkened just enough to reach `min_ratio` against `bg`. A ratio of 1 or
+/// less leaves it unchanged; ratios no color can reach give white or black.
+pub fn ensure_contrast(fg: Color32, bg: Color32, min_ratio: f32) -> Color32 {
+    if contrast_ratio(fg, bg) >= min_ratio { return fg; }
+    let target = if contrast_ratio(Color32::WHITE, bg) >= contrast_ratio(Color32::BLACK, bg) { Color32::WHITE } else { Color32::BLACK };
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    for step in 1..=20 {
+        let t = step as f32 / 20.0;
+        let c = Color32::from_rgb(mix(fg.r(), target.r(), t), mix(fg.g(), target.g(), t), mix(fg.b(), target.b(), t));
+        if contrast_ratio(c, bg) >= min_ratio { return c; }
+    }
+    target
+}
@@ -0,0 +1,188 @@
+//! Force-directed settling of the canvas: relationship springs, degree-aware repulsion between
+//! close nodes and optional gravity toward a local center of mass (or the view center). The
+//! canvas calls [`step`] once per frame while the layout converges and lets it sleep once
+//! [`is_at_rest`]; `bench` drives it headless.
+
+use std::collections::{HashMap, HashSet};
+
+use eframe::egui::{Pos2, Vec2};
+
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+const TARGET_DIST: f32 = 120.0; // preferred edge length in world space
+const SPRING_K: f32 = 4.0; // edge spring stiffness (units/s^2)
+const DAMPING: f32 = 6.0; // velocity damping (units/s)
+const MIN_SEP: f32 = 90.0; // minimum comfortable spacing
+const REPULSE_K: f32 = 10.0; // repulsion strength
+const MAX_SPEED: f32 = 600.0; // clamp velocity magnitude (units/s)
+const MAX_STEP: f32 = 5.0; // clamp displacement per frame (units)
+const MOUSE_K: f32 = 20.0; // drag-to-mouse spring stiffness
+
+/// Speed (world units/s) below which a node counts as at rest. Once every node is, the canvas
+/// stops stepping and stops asking for frames until an edit, a drag or a layout wakes it.
+pub const SLEEP_SPEED: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Gravity {
+    /// Fallback target for nodes without enough neighbors nearby, in world space
+    pub center: Pos2,
+    pub strength: f32,
+    pub com_radius: f32,
+    pub com_min_neighbors: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Forces {
+    pub gravity: Option<Gravity>,
+    pub hub_repulsion_scale: f32,
+}
+
+/// Nodes being dragged: all of them are pulled by `pull` (toward the mouse); with `lock` they
+/// are also left out of springs, repulsion and gravity so the rest of the graph cannot tug
+/// them back while a multi-selection moves as one unit.
+pub struct Drag<'a> {
+    pub unit: &'a HashSet<NodeId>,
+    pub pull: Vec2,
+    pub lock: bool,
+}
+
+/// Advance the layout by `dt` seconds. Nodes without a position start at the origin.
+/// Returns whether any node moved.
+pub fn step(
+    db: &GraphDatabase,
+    positions: &mut HashMap<NodeId, Pos2>,
+    velocities: &mut HashMap<NodeId, Vec2>,
+    forces_cfg: &Forces,
+    drag: Option<Drag>,
+    dt: f32,
+) -> bool {
+    // Ensure velocity entries exist for all positioned nodes
+    for id in db.nodes.keys().copied() {
+        positions.entry(id).or_insert_with(|| Pos2::new(0.0, 0.0));
+        velocities.entry(id).or_insert(Vec2::ZERO);
+    }
+    let locked = |id: &NodeId| drag.as_ref().is_some_and(|d| d.lock && d.unit.contains(id));
+
+    // Accumulate forces
+    let mut forces: HashMap<NodeId, Vec2> = HashMap::new();
+    // Relationship springs (bidirectional: attract if stretched, repel if compressed)
+    for rel in db.relationships.values() {
+        let (a_id, b_id) = (rel.from_node, rel.to_node);
+        if locked(&a_id) || locked(&b_id) { continue; }
+        let (pa_opt, pb_opt) = (positions.get(&a_id).copied(), positions.get(&b_id).copied());
+        if let (Some(pa), Some(pb)) = (pa_opt, pb_opt) {
+            let dx = pb.x - pa.x;
+            let dy = pb.y - pa.y;
+            let dist2 = dx * dx + dy * dy;
+            if dist2 > 1e-6 {
+                let dist = dist2.sqrt();
+                let dir = Vec2::new(dx / dist, dy / dist);
+                let stretch = dist - TARGET_DIST;
+                let f = dir * (SPRING_K * stretch);
+                *forces.entry(a_id).or_insert(Vec2::ZERO) += f;
+                *forces.entry(b_id).or_insert(Vec2::ZERO) -= f;
+            }
+        }
+    }
+
+    // Gravity: prefer local center-of-mass (COM) attraction when nodes cluster off-center; otherwise pull to window center.
+    if let Some(g) = forces_cfg.gravity {
+        let r2 = g.com_radius * g.com_radius;
+        let snapshot: Vec<(NodeId, Pos2)> = positions.iter().map(|(k, v)| (*k, *v)).collect();
+        for (id, pos) in snapshot.iter() {
+            if locked(id) { continue; }
+            // Compute local COM of neighbors within radius (excluding self)
+            let mut sum_x = 0.0f32;
+            let mut sum_y = 0.0f32;
+            let mut count = 0usize;
+            for (oid, opos) in snapshot.iter() {
+                if oid == id { continue; }
+                let dx = opos.x - pos.x;
+                let dy = opos.y - pos.y;
+                if dx * dx + dy * dy <= r2 {
+                    sum_x += opos.x;
+                    sum_y += opos.y;
+                    count += 1;
+                }
+            }
+            let target = if count >= g.com_min_neighbors {
+                Pos2 { x: sum_x / (count as f32), y: sum_y / (count as f32) }
+            } else {
+                g.center
+            };
+            let dir = Vec2::new(target.x - pos.x, target.y - pos.y);
+            *forces.entry(*id).or_insert(Vec2::ZERO) += dir * g.strength;
+        }
+    }
+
+    // Degree-aware repulsive separation for close pairs (O(N^2) but small/med graphs are fine)
+    let mut deg: HashMap<NodeId, usize> = HashMap::new();
+    for rel in db.relationships.values() {
+        *deg.entry(rel.from_node).or_insert(0) += 1;
+        *deg.entry(rel.to_node).or_insert(0) += 1;
+    }
+    let ids: Vec<NodeId> = db.nodes.keys().copied().collect();
+    for i in 0..ids.len() {
+        for j in (i + 1)..ids.len() {
+            let a = ids[i];
+            let b = ids[j];
+            if locked(&a) || locked(&b) { continue; }
+            let (pa_opt, pb_opt) = (positions.get(&a).copied(), positions.get(&b).copied());
+            let (pa, pb) = match (pa_opt, pb_opt) { (Some(pa), Some(pb)) => (pa, pb), _ => continue };
+            let dx = pb.x - pa.x;
+            let dy = pb.y - pa.y;
+            let dist2 = dx * dx + dy * dy;
+            if dist2 < 1e-6 { continue; }
+            let dist = dist2.sqrt();
+            if dist < MIN_SEP {
+                let dir = Vec2::new(dx / dist, dy / dist);
+                let overlap = (MIN_SEP - dist).max(0.0);
+                // Scale by node degrees to spread hubs a bit more
+                let da = *deg.get(&a).unwrap_or(&0) as f32;
+                let db = *deg.get(&b).unwrap_or(&0) as f32;
+                let scale_a = 1.0 + forces_cfg.hub_repulsion_scale * (da + 1.0).ln();
+                let scale_b = 1.0 + forces_cfg.hub_repulsion_scale * (db + 1.0).ln();
+                let f = dir * (REPULSE_K * overlap);
+                // push opposite directions
+                *forces.entry(a).or_insert(Vec2::ZERO) -= f * scale_a;
+                *forces.entry(b).or_insert(Vec2::ZERO) += f * scale_b;
+            }
+        }
+    }
+
+    // Soft drag: a spring pulling every node of the dragged unit toward the mouse
+    if let Some(d) = &drag {
+        for nid in d.unit {
+            *forces.entry(*nid).or_insert(Vec2::ZERO) += d.pull * MOUSE_K;
+        }
+    }
+
+    // Integrate velocities and positions
+    let mut any_move = false;
+    for (id, p) in positions.iter_mut() {
+        let mut v = *velocities.entry(*id).or_insert(Vec2::ZERO);
+        let f = *forces.get(id).unwrap_or(&Vec2::ZERO);
+        // a = f - c*v (unit mass)
+        let a = f - v * DAMPING;
+        v += a * dt;
+        // Clamp velocity
+        let speed = v.length();
+        if speed > MAX_SPEED { v *= MAX_SPEED / speed; }
+        // Displacement this frame
+        let mut step = v * dt;
+        let step_len = step.length();
+        if step_len > MAX_STEP { step *= MAX_STEP / step_len; }
+        if step != Vec2::ZERO {
+            p.x += step.x;
+            p.y += step.y;
+            any_move = true;
+        }
+        velocities.insert(*id, v);
+    }
+    any_move
+}
+
+/// Whether every node moves slower than [`SLEEP_SPEED`].
+pub fn is_at_rest(velocities: &HashMap<NodeId, Vec2>) -> bool {
+    velocities.values().all(|v| v.length_sq() < SLEEP_SPEED * SLEEP_SPEED)
+}
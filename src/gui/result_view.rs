@@ -0,0 +1,104 @@
+//! Result view: the canvas shows only what the last query matched, or a named view of the
+//! graph, laid out on its own, while the layout of the whole graph waits aside until the view
+//! closes. Saves keep writing the whole layout.
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::TAU;
+
+use eframe::egui::{Pos2, Vec2};
+use uuid::Uuid;
+
+use crate::graph_utils::graph::{GraphDatabase, NodeId};
+
+/// Distance between neighbors on the starting ring.
+const SPACING: f32 = 90.0;
+
+/// The matches shown and the canvas state they replaced.
+#[derive(Clone, Debug)]
+pub struct ResultView {
+    nodes: HashSet<NodeId>,
+    relationships: HashSet<Uuid>,
+    // The query matched no relationships; show the ones between its nodes
+    nodes_only: bool,
+    /// Named view shown instead of the query matches (see `gql::views`), and the change-log
+    /// sequence number it was evaluated at
+    pub name: Option<String>,
+    pub evaluated_at: u64,
+    /// The matches as a graph of their own: what the canvas draws and lays out
+    pub graph: GraphDatabase,
+    /// Layout, pan and zoom of the whole graph
+    pub saved_positions: HashMap<NodeId, Pos2>,
+    pub saved_pan: Vec2,
+    pub saved_zoom: f32,
+}
+
+impl ResultView {
+    /// Show `nodes` and `relationships` of `db` (with the ends of each relationship). A result
+    /// of nodes alone keeps the relationships between them, so it still has its shape. Returns
+    /// the view and a starting layout of the matches: a ring around `center`.
+    pub fn open(
+        db: &GraphDatabase,
+        nodes: &HashSet<NodeId>,
+        relationships: &HashSet<Uuid>,
+        saved_positions: HashMap<NodeId, Pos2>,
+        saved_pan: Vec2,
+        saved_zoom: f32,
+        center: Pos2,
+    ) -> (Self, HashMap<NodeId, Pos2>) {
+        let mut view = ResultView {
+            nodes: nodes.clone(),
+            relationships: relationships.clone(),
+            nodes_only: relationships.is_empty(),
+            name: None,
+            evaluated_at: 0,
+            graph: GraphDatabase::new(),
+            saved_positions,
+            saved_pan,
+            saved_zoom,
+        };
+        view.refresh(db);
+        let positions = ring(&view.graph, center);
+        (view, positions)
+    }
+
+    /// Show `graph`, the view `name` evaluated at change `seq`, laid out like [`ResultView::open`].
+    pub fn named(name: &str, seq: u64, graph: GraphDatabase, saved_positions: HashMap<NodeId, Pos2>, saved_pan: Vec2, saved_zoom: f32, center: Pos2) -> (Self, HashMap<NodeId, Pos2>) {
+        let positions = ring(&graph, center);
+        let view = ResultView {
+            nodes: HashSet::new(),
+            relationships: HashSet::new(),
+            nodes_only: false,
+            name: Some(name.to_string()),
+            evaluated_at: seq,
+            graph,
+            saved_positions,
+            saved_pan,
+            saved_zoom,
+        };
+        (view, positions)
+    }
+
+    /// Follow edits: deleted matches leave the view and shown elements take their new state.
+    /// A named view is evaluated again by its owner instead.
+    pub fn refresh(&mut self, db: &GraphDatabase) {
+        if self.name.is_some() { return; }
+        self.nodes.retain(|id| db.nodes.contains_key(id));
+        self.relationships.retain(|id| db.relationships.contains_key(id));
+        let relationships = if self.nodes_only { db.relationships_within(&self.nodes).into_iter().collect() } else { self.relationships.clone() };
+        self.graph = db.subgraph(&self.nodes, &relationships);
+    }
+}
+
+// Nodes of `graph` on a ring around `center`, same label side by side in a stable order
+fn ring(graph: &GraphDatabase, center: Pos2) -> HashMap<NodeId, Pos2> {
+    let mut ids: Vec<NodeId> = graph.nodes.keys().copied().collect();
+    ids.sort_by_key(|id| (graph.nodes[id].label.as_str(), *id));
+    let radius = (ids.len() as f32 * SPACING / TAU).max(SPACING);
+    ids.iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let angle = i as f32 / ids.len() as f32 * TAU;
+            (*id, center + Vec2::angled(angle) * radius)
+        })
+        .collect()
+}
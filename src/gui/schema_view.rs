@@ -0,0 +1,84 @@
+//! View → Schema View: the inferred schema drawn as a small graph with one circle per label
+//! (sized by its node count) and one arrow per relationship type between two labels.
+
+use std::collections::HashMap;
+
+use eframe::egui::{self, Color32, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::graph_utils::schema::Schema;
+
+const MIN_RADIUS: f32 = 16.0;
+// Gap between parallel arrows joining the same two labels
+const LANE: f32 = 18.0;
+
+// Labels evenly spaced on a circle (a single label sits in the middle), radius by node count
+fn layout(schema: &Schema, rect: Rect) -> HashMap<&str, (Pos2, f32)> {
+    let n = schema.labels.len();
+    let ring = (rect.width().min(rect.height()) * 0.5 - 3.0 * MIN_RADIUS).max(0.0);
+    schema
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let at = if n == 1 {
+                rect.center()
+            } else {
+                let angle = std::f32::consts::TAU * i as f32 / n as f32 - std::f32::consts::FRAC_PI_2;
+                rect.center() + Vec2::angled(angle) * ring
+            };
+            (l.label.as_str(), (at, MIN_RADIUS + 4.0 * (l.count as f32).ln_1p()))
+        })
+        .collect()
+}
+
+/// Draw `schema` filling the remaining space of `ui`. Returns the label that was clicked.
+pub fn show(ui: &mut egui::Ui, schema: &Schema, color_for: impl Fn(&str) -> Color32) -> Option<String> {
+    let (rect, _) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let text_color = ui.visuals().text_color();
+    let font = egui::FontId::proportional(12.0);
+    let places = layout(schema, rect);
+
+    // Arrows first so circles and their labels stay readable on top
+    let mut lanes: HashMap<(&str, &str), usize> = HashMap::new();
+    for r in &schema.relationships {
+        let (Some(&(a, ra)), Some(&(b, rb))) = (places.get(r.from.as_str()), places.get(r.to.as_str())) else { continue };
+        let caption = format!("{} ({})", r.label, r.count);
+        let stroke = Stroke::new(1.5, ui.visuals().weak_text_color());
+        // Parallel types between one pair of labels (in either direction) each get a lane
+        let pair = if r.from <= r.to { (r.from.as_str(), r.to.as_str()) } else { (r.to.as_str(), r.from.as_str()) };
+        let lane = lanes.entry(pair).or_default();
+        *lane += 1;
+        if r.from == r.to {
+            let loop_r = 4.0 + *lane as f32 * 6.0;
+            let c = a - Vec2::new(0.0, ra + loop_r - 4.0);
+            painter.circle_stroke(c, loop_r, stroke);
+            painter.text(c - Vec2::new(0.0, loop_r + 2.0), egui::Align2::CENTER_BOTTOM, caption, font.clone(), text_color);
+            continue;
+        }
+        // Lanes alternate sides of the line between the pair, measured in the pair's own order
+        // so opposite directions do not share a lane
+        let across = (places[pair.1].0 - places[pair.0].0).normalized().rot90();
+        let k = *lane - 1;
+        let offset = across * LANE * k.div_ceil(2) as f32 * if k % 2 == 1 { 1.0 } else { -1.0 };
+        let dir = (b - a).normalized();
+        let (start, end) = (a + dir * ra + offset, b - dir * rb + offset);
+        painter.arrow(start, end - start, stroke);
+        painter.text(start + (end - start) * 0.5, egui::Align2::CENTER_BOTTOM, caption, font.clone(), text_color);
+    }
+
+    let mut clicked = None;
+    for l in &schema.labels {
+        let Some(&(at, radius)) = places.get(l.label.as_str()) else { continue };
+        let id = ui.id().with(("schema_label", &l.label));
+        let resp = ui.interact(Rect::from_center_size(at, Vec2::splat(radius * 2.0)), id, Sense::click());
+        let fill = color_for(&l.label);
+        painter.circle(at, radius, fill, Stroke::new(if resp.hovered() { 2.5 } else { 1.0 }, text_color));
+        painter.text(at + Vec2::new(0.0, radius + 2.0), egui::Align2::CENTER_TOP, format!("{} ({})", l.label, l.count), font.clone(), text_color);
+        let keys = if l.keys.is_empty() { "no metadata keys".to_string() } else { l.keys.join(", ") };
+        if resp.on_hover_text(format!(":{}\n{} node(s)\nKeys: {}\nClick to query these nodes", l.label, l.count, keys)).clicked() {
+            clicked = Some(l.label.clone());
+        }
+    }
+    clicked
+}
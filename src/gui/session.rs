@@ -0,0 +1,95 @@
+//! UI state carried across restarts: open details windows, sidebar and Preferences tab,
+//! window geometry and scroll positions. It lives in `session.json` next to `settings.json`
+//! and is written on exit; a missing or unreadable file just means a fresh layout.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::persistence::settings::AppSettings;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SidebarMode {
+    #[default]
+    Tooling,
+    Query,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrefsTab {
+    #[default]
+    App,
+    Api,
+}
+
+/// Position and size of the main window in logical points.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub open_nodes: Vec<Uuid>,
+    pub open_relationships: Vec<Uuid>,
+    pub sidebar_open: bool,
+    pub sidebar_mode: SidebarMode,
+    pub prefs_tab: PrefsTab,
+    pub window: Option<WindowGeometry>,
+    /// Vertical offsets of the sidebar scroll areas, by name
+    pub scroll: BTreeMap<String, f32>,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        SessionState {
+            open_nodes: Vec::new(),
+            open_relationships: Vec::new(),
+            sidebar_open: true,
+            sidebar_mode: SidebarMode::default(),
+            prefs_tab: PrefsTab::default(),
+            window: None,
+            scroll: BTreeMap::new(),
+        }
+    }
+}
+
+impl SessionState {
+    pub fn path() -> PathBuf {
+        AppSettings::settings_dir().join("session.json")
+    }
+
+    /// The saved session, or the defaults when there is none or it cannot be read.
+    pub fn load() -> Self {
+        match Self::load_from(&Self::path()) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("Ignoring saved session: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() { return Ok(Self::default()); }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Frame timing for the status bar and the performance overlay: frames drawn in the last
+//! second, how long the last one took to build and where that time went. The window only
+//! redraws on input or animation, so a low count on an idle window is expected; a long build
+//! time is what points at a slow graph.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Parts of a frame timed separately.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// The force-directed layout step
+    Physics,
+    /// Building the canvas (nodes, edges, labels), physics excluded
+    Painting,
+    /// Console and API queries run on the UI thread
+    Queries,
+}
+
+/// Time spent per phase; `total` is the whole frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Breakdown {
+    pub physics: Duration,
+    pub painting: Duration,
+    pub queries: Duration,
+    pub total: Duration,
+}
+
+impl Breakdown {
+    /// Frame time not accounted for by a phase (sidebar, windows, input handling).
+    pub fn other(&self) -> Duration {
+        self.total.saturating_sub(self.physics + self.painting + self.queries)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    frames: VecDeque<(Instant, Breakdown)>,
+    // Phases of the frame being built
+    current: Breakdown,
+    last_build: Duration,
+}
+
+impl FrameStats {
+    /// `phase` took `spent` in the frame being built.
+    pub fn add(&mut self, phase: Phase, spent: Duration) {
+        match phase {
+            Phase::Physics => self.current.physics += spent,
+            Phase::Painting => self.current.painting += spent,
+            Phase::Queries => self.current.queries += spent,
+        }
+    }
+
+    /// A frame started at `started` and took `built` to build.
+    pub fn record(&mut self, started: Instant, built: Duration) {
+        let mut frame = std::mem::take(&mut self.current);
+        // The canvas is timed as a whole and runs the physics step inside it
+        frame.painting = frame.painting.saturating_sub(frame.physics);
+        frame.total = built;
+        self.frames.push_back((started, frame));
+        while self.frames.front().is_some_and(|(t, _)| started.duration_since(*t) > WINDOW) {
+            self.frames.pop_front();
+        }
+        self.last_build = built;
+    }
+
+    /// Frames started within the second before `now`.
+    pub fn fps(&self, now: Instant) -> usize {
+        self.recent(now).count()
+    }
+
+    pub fn last_build(&self) -> Duration {
+        self.last_build
+    }
+
+    /// Mean time per phase over the frames of the last second.
+    pub fn average(&self, now: Instant) -> Breakdown {
+        let mut sum = Breakdown::default();
+        let mut n = 0u32;
+        for b in self.recent(now) {
+            sum.physics += b.physics;
+            sum.painting += b.painting;
+            sum.queries += b.queries;
+            sum.total += b.total;
+            n += 1;
+        }
+        if n == 0 { return sum; }
+        Breakdown { physics: sum.physics / n, painting: sum.painting / n, queries: sum.queries / n, total: sum.total / n }
+    }
+
+    fn recent(&self, now: Instant) -> impl Iterator<Item = &Breakdown> {
+        self.frames.iter().filter(move |(t, _)| now.saturating_duration_since(*t) <= WINDOW).map(|(_, b)| b)
+    }
+}
@@ -0,0 +1,136 @@
+//! Long operations (imports, exports, layout and queries on large graphs) run on worker
+//! threads so the window keeps drawing. A task reports progress through its [`Progress`]
+//! and checks it between steps for cancellation; the status bar lists running tasks with a
+//! Cancel button.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Graphs with at least this many nodes plus relationships get layout and console queries
+/// in the background.
+pub const BACKGROUND_THRESHOLD: usize = 20_000;
+
+/// Error a task returns when it stopped because it was cancelled.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// True when `e` is the [`Cancelled`] error of a task.
+pub fn is_cancelled(e: &anyhow::Error) -> bool {
+    e.is::<Cancelled>()
+}
+
+#[derive(Default)]
+struct Shared {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+    stage: Mutex<String>,
+}
+
+/// Progress and cancel flag shared between a task and the UI.
+#[derive(Clone, Default)]
+pub struct Progress {
+    shared: Arc<Shared>,
+}
+
+impl Progress {
+    /// Number of steps the task expects; 0 leaves the progress indeterminate.
+    pub fn set_total(&self, total: usize) {
+        self.shared.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, steps: usize) {
+        self.shared.done.fetch_add(steps, Ordering::Relaxed);
+    }
+
+    pub fn set_done(&self, done: usize) {
+        self.shared.done.store(done, Ordering::Relaxed);
+    }
+
+    /// Short description of the current step, shown next to the bar.
+    pub fn set_stage(&self, stage: impl Into<String>) {
+        if let Ok(mut s) = self.shared.stage.lock() { *s = stage.into(); }
+    }
+
+    pub fn stage(&self) -> String {
+        self.shared.stage.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Completed fraction, or `None` while the total is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.shared.total.load(Ordering::Relaxed);
+        if total == 0 { return None; }
+        let done = self.shared.done.load(Ordering::Relaxed).min(total);
+        Some(done as f32 / total as f32)
+    }
+
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Cancelled)` once cancel was requested; tasks call this between steps.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() { return Err(Cancelled.into()); }
+        Ok(())
+    }
+}
+
+/// A running operation producing a `T`.
+pub struct Task<T> {
+    pub label: String,
+    pub started: Instant,
+    progress: Progress,
+    rx: Receiver<anyhow::Result<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Run `work` on a new thread. `on_done` runs on that thread when it finishes, e.g. to
+    /// wake the UI.
+    pub fn spawn(
+        label: impl Into<String>,
+        work: impl FnOnce(&Progress) -> anyhow::Result<T> + Send + 'static,
+        on_done: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        let progress = Progress::default();
+        let (tx, rx) = mpsc::channel();
+        let p = progress.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(work(&p));
+            on_done();
+        });
+        Task { label: label.into(), started: Instant::now(), progress, rx }
+    }
+
+    /// The result once the task finished. A task that panicked reports an error.
+    pub fn poll(&self) -> Option<anyhow::Result<T>> {
+        match self.rx.try_recv() {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow::anyhow!("{} stopped unexpectedly", self.label))),
+        }
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Ask the task to stop. Tasks that cannot stop midway finish, and their result is
+    /// discarded by the caller.
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+}
@@ -0,0 +1,95 @@
+//! First-run guided tour: a sequence of callouts pointing at the widgets for creating nodes,
+//! moving around the canvas, running a query and exporting. Widgets report where they were
+//! drawn each frame as a [`TourTarget`]; the callout is placed next to the current step's.
+
+/// Widgets a step can point at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TourTarget {
+    CreateNode,
+    Canvas,
+    QueryTab,
+    QueryEditor,
+    FileMenu,
+    HelpMenu,
+}
+
+#[derive(Debug)]
+pub struct TourStep {
+    pub title: &'static str,
+    pub body: &'static str,
+    /// Widget the callout points at; `None` centers it on the window
+    pub target: Option<TourTarget>,
+}
+
+pub const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Welcome to Graph-Loom",
+        body: "Graph-Loom keeps a graph of labeled nodes and the relationships between them. This short tour shows where to create nodes, run a query and export your work.",
+        target: None,
+    },
+    TourStep {
+        title: "Create nodes",
+        body: "Open Create Node in the Tooling sidebar, enter a label such as Person and click Create. Metadata can be added right away or later from the node's window.",
+        target: Some(TourTarget::CreateNode),
+    },
+    TourStep {
+        title: "Move around",
+        body: "Drag nodes to arrange them and drag the background to pan. Scroll or pinch to zoom. Click a node or relationship to open its details.",
+        target: Some(TourTarget::Canvas),
+    },
+    TourStep {
+        title: "Query the graph",
+        body: "The Query tab opens the console, which understands a Cypher subset.",
+        target: Some(TourTarget::QueryTab),
+    },
+    TourStep {
+        title: "Run a query",
+        body: "Type MATCH (n) RETURN n and press Ctrl/Cmd+Enter. Matching nodes are highlighted on the canvas and listed below.",
+        target: Some(TourTarget::QueryEditor),
+    },
+    TourStep {
+        title: "Export",
+        body: "File → Export writes the graph as JSON, CSV or GraphML. Everything is also saved automatically.",
+        target: Some(TourTarget::FileMenu),
+    },
+    TourStep {
+        title: "That's it",
+        body: "You can take this tour again from Help → Take the Tour.",
+        target: Some(TourTarget::HelpMenu),
+    },
+];
+
+/// Position in the tour.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tour {
+    step: usize,
+}
+
+impl Tour {
+    pub fn step(&self) -> &'static TourStep {
+        &STEPS[self.step]
+    }
+
+    pub fn index(&self) -> usize {
+        self.step
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.step == 0
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.step + 1 == STEPS.len()
+    }
+
+    /// Move to the next step; `false` once the tour is finished.
+    pub fn advance(&mut self) -> bool {
+        if self.is_last() { return false; }
+        self.step += 1;
+        true
+    }
+
+    pub fn back(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+}
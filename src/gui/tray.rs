@@ -0,0 +1,135 @@
+use std::path::Path;
+use std::time::Duration;
+
+use tray_icon::menu::{Menu, MenuId, MenuItem, PredefinedMenuItem};
+
+/// Something picked from the tray menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    Show,
+    /// Hide the window to the tray (clicking the icon while the window is shown)
+    Hide,
+    Quit,
+    /// Stop the HTTP and gRPC servers until resumed; settings are left alone
+    ToggleApi,
+    SaveNow,
+    OpenExportFolder,
+    CopyEndpoint,
+}
+
+/// What the status lines at the top of the menu report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayStatus {
+    /// Address clients connect to while a server is running
+    pub endpoint: Option<String>,
+    pub paused: bool,
+    pub nodes: usize,
+    pub relationships: usize,
+    /// Time since the graph was last written to disk in this session
+    pub since_save: Option<Duration>,
+}
+
+/// Short relative age, e.g. "3 min ago".
+pub fn ago(d: Duration) -> String {
+    let s = d.as_secs();
+    match s {
+        0..=4 => "just now".to_string(),
+        5..=59 => format!("{}s ago", s),
+        60..=3599 => format!("{} min ago", s / 60),
+        _ => format!("{} h ago", s / 3600),
+    }
+}
+
+impl TrayStatus {
+    /// The three status lines: API state, graph size, last save.
+    pub fn lines(&self) -> [String; 3] {
+        let api = match (&self.endpoint, self.paused) {
+            (_, true) => "API: paused".to_string(),
+            (Some(ep), false) => format!("API: on ({})", ep),
+            (None, false) => "API: off".to_string(),
+        };
+        let graph = format!("{} nodes, {} relationships", self.nodes, self.relationships);
+        let saved = match self.since_save {
+            Some(d) => format!("Last save: {}", ago(d)),
+            None => "Not saved this session".to_string(),
+        };
+        [api, graph, saved]
+    }
+}
+
+/// The tray menu. Its items are not `Send`, so it lives with the app on the UI thread; the
+/// menu-event thread only needs [`TrayMenu::actions`].
+pub struct TrayMenu {
+    pub menu: Menu,
+    status: [MenuItem; 3],
+    toggle_api: MenuItem,
+    actions: Vec<(MenuId, TrayAction)>,
+    // (paused, toggle enabled) and the status lines as last shown
+    shown: Option<((bool, bool), [String; 3])>,
+}
+
+impl TrayMenu {
+    pub fn new() -> Self {
+        let menu = Menu::new();
+        let status = [(); 3].map(|_| MenuItem::new("", false, None));
+        let show = MenuItem::new("Show Graph-Loom", true, None);
+        let toggle_api = MenuItem::new("Pause API", true, None);
+        let save = MenuItem::new("Save Now", true, None);
+        let export = MenuItem::new("Open Export Folder", true, None);
+        let copy = MenuItem::new("Copy API Endpoint", true, None);
+        let quit = MenuItem::new("Quit", true, None);
+        for item in &status { let _ = menu.append(item); }
+        let _ = menu.append(&PredefinedMenuItem::separator());
+        let _ = menu.append(&show);
+        let _ = menu.append(&toggle_api);
+        let _ = menu.append(&save);
+        let _ = menu.append(&export);
+        let _ = menu.append(&copy);
+        let _ = menu.append(&PredefinedMenuItem::separator());
+        let _ = menu.append(&quit);
+        let actions = vec![
+            (show.id().clone(), TrayAction::Show),
+            (toggle_api.id().clone(), TrayAction::ToggleApi),
+            (save.id().clone(), TrayAction::SaveNow),
+            (export.id().clone(), TrayAction::OpenExportFolder),
+            (copy.id().clone(), TrayAction::CopyEndpoint),
+            (quit.id().clone(), TrayAction::Quit),
+        ];
+        TrayMenu { menu, status, toggle_api, actions, shown: None }
+    }
+
+    /// Menu ids and the action each one triggers.
+    pub fn actions(&self) -> Vec<(MenuId, TrayAction)> {
+        self.actions.clone()
+    }
+
+    /// Refresh the status lines; items are only touched when their text changes.
+    pub fn update(&mut self, status: &TrayStatus) {
+        let lines = status.lines();
+        for (i, line) in lines.iter().enumerate() {
+            if self.shown.as_ref().is_none_or(|(_, p)| &p[i] != line) { self.status[i].set_text(line); }
+        }
+        let toggle = (status.paused, status.paused || status.endpoint.is_some());
+        if self.shown.as_ref().is_none_or(|(t, _)| *t != toggle) {
+            self.toggle_api.set_text(if toggle.0 { "Resume API" } else { "Pause API" });
+            self.toggle_api.set_enabled(toggle.1);
+        }
+        self.shown = Some((toggle, lines));
+    }
+}
+
+impl Default for TrayMenu {
+    fn default() -> Self { Self::new() }
+}
+
+/// Open `dir` in the platform file manager, creating it first.
+pub fn open_folder(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+    std::process::Command::new(program).arg(dir).spawn().map(|_| ())
+}
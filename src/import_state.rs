@@ -0,0 +1,73 @@
+//! `Graph-Loom import-state`: upgrade a state file saved by an older version, or turn a JSON,
+//! NDJSON or CSV export into a state file, without opening a window.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::persistence::migrate::{self, SCHEMA};
+use crate::persistence::persist;
+
+pub const USAGE: &str = "usage: Graph-Loom import-state <file> [--out FILE]";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub path: PathBuf,
+    /// From `--out`; see [`output_path`]
+    pub out: Option<PathBuf>,
+}
+
+/// Parse the arguments after `import-state`.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut path = None;
+    let mut out = None;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--out" => out = Some(PathBuf::from(it.next().ok_or_else(|| format!("--out expects a value\n{}", USAGE))?)),
+            other if other.starts_with("--") => return Err(format!("unknown import-state option '{}'\n{}", other, USAGE)),
+            other if path.is_none() => path = Some(PathBuf::from(other)),
+            other => return Err(format!("unexpected argument '{}'\n{}", other, USAGE)),
+        }
+    }
+    let path = path.ok_or_else(|| format!("missing the file to import\n{}", USAGE))?;
+    Ok(Options { path, out })
+}
+
+/// Where [`run`] writes: `--out`, else the file itself for a state file and a `.ron` next to
+/// it for an export.
+pub fn output_path(opts: &Options) -> PathBuf {
+    opts.out.clone().unwrap_or_else(|| opts.path.with_extension("ron"))
+}
+
+// `state.ron` -> `state.ron.bak`
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Read the file and write it as a current state file; returns what was done. A state file
+/// upgraded in place is kept as `<file>.bak` first.
+pub fn run(opts: &Options) -> Result<String> {
+    let upgraded = migrate::read_file(&opts.path)?;
+    let out = output_path(opts);
+    let in_place = out == opts.path;
+    if upgraded.steps.is_empty() && in_place {
+        return Ok(format!("{} is already at schema {}; nothing to do", opts.path.display(), SCHEMA));
+    }
+    let done = match (upgraded.from, upgraded.imported) {
+        (_, Some(summary)) => format!("Imported {} ({})", opts.path.display(), summary.describe()),
+        (Some(from), None) if !upgraded.steps.is_empty() => {
+            format!("Upgraded {} from schema {} to {} ({})", opts.path.display(), from, SCHEMA, upgraded.steps.join(", "))
+        }
+        _ => format!("Copied {}", opts.path.display()),
+    };
+    if in_place {
+        std::fs::copy(&opts.path, backup_path(&opts.path))?;
+    } else if out.exists() && opts.out.is_none() {
+        bail!("{} already exists; choose another file with --out", out.display());
+    }
+    persist::save_to_path(&upgraded.state, &out)?;
+    Ok(format!("{} to {}", done, out.display()))
+}
@@ -0,0 +1,180 @@
+//! One Graph-Loom process per user. The first process takes a lock file in the settings
+//! directory and listens on a loopback port; later launches find the lock taken and forward
+//! a command (`show`, `quit`, `status`) to it instead of opening the same state files twice.
+//!
+//! The port and a random token are written next to the lock. A client sends
+//! `<token> <command>\n` and reads back `ok <message>\n` or `err <message>\n`.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::persistence::settings::AppSettings;
+
+const LOCK_FILE: &str = "instance.lock";
+const ENDPOINT_FILE: &str = "instance.endpoint";
+const IO_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceCommand {
+    /// Bring the window to the foreground
+    Show,
+    /// Close cleanly: stop the servers and save
+    Quit,
+    /// Report the pid and mode of the running instance
+    Status,
+}
+
+impl InstanceCommand {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "show" => Some(Self::Show),
+            "quit" => Some(Self::Quit),
+            "status" => Some(Self::Status),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Quit => "quit",
+            Self::Status => "status",
+        }
+    }
+}
+
+/// Outcome of [`acquire`].
+pub enum Instance {
+    /// No other instance holds the lock; this process is the primary.
+    Primary(InstanceLock),
+    /// Another process is running; commands go to it through [`Remote::send`].
+    Secondary(Remote),
+}
+
+/// Handle on the instance that holds the lock.
+pub struct Remote {
+    dir: PathBuf,
+}
+
+/// The held lock. Dropping it releases the lock; [`InstanceLock::listen`] keeps it for the
+/// lifetime of the process.
+pub struct InstanceLock {
+    file: File,
+    dir: PathBuf,
+}
+
+/// Take the single-instance lock in the settings directory.
+pub fn acquire() -> anyhow::Result<Instance> {
+    acquire_in(&AppSettings::settings_dir())
+}
+
+pub fn acquire_in(dir: &Path) -> anyhow::Result<Instance> {
+    std::fs::create_dir_all(dir)?;
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Instance::Primary(InstanceLock { file, dir: dir.to_path_buf() })),
+        Err(TryLockError::WouldBlock) => Ok(Instance::Secondary(Remote { dir: dir.to_path_buf() })),
+        Err(TryLockError::Error(e)) => Err(anyhow::anyhow!("cannot lock {}: {}", dir.join(LOCK_FILE).display(), e)),
+    }
+}
+
+impl Remote {
+    /// Forward `cmd` to the running instance and return its reply message. The primary may
+    /// still be starting up, so a missing endpoint is retried for a short while.
+    pub fn send(&self, cmd: InstanceCommand) -> anyhow::Result<String> {
+        let deadline = Instant::now() + IO_TIMEOUT;
+        loop {
+            match try_send(&self.dir, cmd) {
+                Ok(reply) => return reply.map_err(|e| anyhow::anyhow!(e)),
+                Err(e) if Instant::now() >= deadline => {
+                    return Err(anyhow::anyhow!("another Graph-Loom instance is running but not answering: {}", e));
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+}
+
+// Outer error: could not reach the instance; inner: the instance refused the command
+fn try_send(dir: &Path, cmd: InstanceCommand) -> anyhow::Result<Result<String, String>> {
+    let endpoint = std::fs::read_to_string(dir.join(ENDPOINT_FILE))?;
+    let mut lines = endpoint.lines();
+    let addr: SocketAddr = lines.next().unwrap_or_default().parse()?;
+    let token = lines.next().unwrap_or_default();
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    writeln!(stream, "{} {}", token, cmd.as_str())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    let reply = reply.trim_end();
+    Ok(match reply.split_once(' ').unwrap_or((reply, "")) {
+        ("ok", msg) => Ok(msg.to_string()),
+        ("err", msg) => Err(msg.to_string()),
+        _ => anyhow::bail!("unexpected reply '{}'", reply),
+    })
+}
+
+impl InstanceLock {
+    /// Accept commands from later launches on a background thread, answering each with
+    /// `handler`. The lock is held until the process exits.
+    pub fn listen(self, handler: impl Fn(InstanceCommand) -> Result<String, String> + Send + 'static) -> anyhow::Result<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let token = uuid::Uuid::now_v7().simple().to_string();
+        let endpoint = format!("{}\n{}\n", listener.local_addr()?, token);
+        let path = self.dir.join(ENDPOINT_FILE);
+        write_private(&path, &endpoint)?;
+        std::thread::spawn(move || {
+            let _lock = self.file;
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(s) => {
+                        if let Err(e) = serve(s, &token, &handler) {
+                            log::warn!("Instance command failed: {}", e);
+                        }
+                    }
+                    Err(e) => log::warn!("Instance listener error: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+fn serve(stream: TcpStream, token: &str, handler: &impl Fn(InstanceCommand) -> Result<String, String>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut line = String::new();
+    // A request is a token and a short command; cap what a stray client can make us buffer
+    BufReader::new(Read::take(&stream, 256)).read_line(&mut line)?;
+    let reply = match line.trim_end().split_once(' ') {
+        Some((t, cmd)) if t == token => match InstanceCommand::parse(cmd) {
+            Some(cmd) => {
+                log::info!("Instance command '{}' from a new launch", cmd.as_str());
+                handler(cmd)
+            }
+            None => Err(format!("unknown command '{}'", cmd)),
+        },
+        _ => Err("bad token".to_string()),
+    };
+    let mut stream = &stream;
+    match reply {
+        Ok(msg) => writeln!(stream, "ok {}", msg),
+        Err(msg) => writeln!(stream, "err {}", msg),
+    }
+}
+
+// The token is the only thing keeping other local users from commanding this instance, so the
+// file is created afresh and readable by its owner alone before anything is written to it
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
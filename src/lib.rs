@@ -1,5 +1,12 @@
-pub mod graph_utils;
+// The engine lives in `graph-loom-core`; re-exported so the shell keeps its `crate::` paths
+pub use graph_loom_core::{gql, graph_utils, persistence};
 pub mod gui;
-pub mod persistence;
-pub mod gql;
 pub mod api;
+pub mod logging;
+pub mod instance;
+pub mod bench;
+pub mod export;
+pub mod import_state;
+pub mod connectors;
+#[cfg(feature = "api")]
+pub mod service;
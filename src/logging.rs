@@ -0,0 +1,102 @@
+//! Process-wide logger: forwards `log` records to stderr and keeps a bounded in-memory
+//! history that the GUI Logs window reads from.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+/// Number of recent log lines retained in memory.
+pub const CAPACITY: usize = 2000;
+
+/// Target used for API request/response traffic so it can be filtered separately.
+pub const API_TARGET: &str = "api";
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: String,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn format_line(&self) -> String {
+        format!("{} {:<5} [{}] {}", self.time, self.level, self.target, self.message)
+    }
+}
+
+static BUFFER: Lazy<Mutex<VecDeque<LogEntry>>> = Lazy::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+struct RingLogger {
+    level: LevelFilter,
+    // Root module of this crate; crates other than it and the core library only log at Warn
+    // and above
+    own_root: &'static str,
+}
+
+// Root module of the graph engine, persistence and query code
+const CORE_ROOT: &str = "graph_loom_core";
+
+impl RingLogger {
+    fn is_own(&self, target: &str) -> bool {
+        let root = target.split("::").next();
+        target == API_TARGET || root == Some(self.own_root) || root == Some(CORE_ROOT)
+    }
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if self.is_own(metadata.target()) {
+            metadata.level() <= self.level
+        } else {
+            metadata.level() <= Level::Warn
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) { return; }
+        let now = time::OffsetDateTime::now_utc();
+        let fmt = time::macros::format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+        let entry = LogEntry {
+            time: now.format(&fmt).unwrap_or_default(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        eprintln!("{}", entry.format_line());
+        push(entry);
+    }
+
+    fn flush(&self) {}
+}
+
+fn push(entry: LogEntry) {
+    if let Ok(mut buf) = BUFFER.lock() {
+        if buf.len() >= CAPACITY { buf.pop_front(); }
+        buf.push_back(entry);
+    }
+}
+
+/// Install the logger. The level comes from `RUST_LOG` (e.g. `debug`), defaulting to `info`.
+/// Calling this more than once is harmless.
+pub fn init() {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|v| v.trim().parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::Info);
+    let own_root = module_path!().split("::").next().unwrap_or("graph_loom");
+    if log::set_boxed_logger(Box::new(RingLogger { level, own_root })).is_ok() {
+        log::set_max_level(level.max(LevelFilter::Warn));
+    }
+}
+
+/// Copy of the retained log lines, oldest first.
+pub fn snapshot() -> Vec<LogEntry> {
+    BUFFER.lock().map(|b| b.iter().cloned().collect()).unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut buf) = BUFFER.lock() { buf.clear(); }
+}
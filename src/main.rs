@@ -1,32 +1,79 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
-mod gql;
-mod graph_utils;
+use graph_loom_core::{gql, graph_utils, persistence};
 mod gui;
-mod persistence;
 mod api;
+mod logging;
+mod instance;
+mod bench;
+mod export;
+mod import_state;
+mod connectors;
+#[cfg(feature = "api")]
+mod service;
 
-use std::collections::HashMap;
 use graph_utils::graph::GraphDatabase;
 use gui::frontend::GraphApp;
+use gui::session::SessionState;
 use persistence::persist;
 
 use eframe::egui;
 // All menus are now implemented within the egui window; no platform-specific menu code.
 
 use tray_icon::{
-    menu::{Menu, MenuEvent, MenuItem},
-    TrayIconBuilder,
+    menu::MenuEvent,
+    MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent,
 };
 use std::sync::atomic::Ordering;
 
 fn main() -> eframe::Result {
+    logging::init();
+    install_panic_hook();
+
+    // `bench ...` times layout, physics and queries on a generated graph and prints the results
     {
-        if let Some(pid) = gui::win_utils::find_running_instance() {
-            gui::win_utils::force_foreground_process(pid);
+        let args = std::env::args().skip(1).collect::<Vec<String>>();
+        if args.first().map(String::as_str) == Some("bench") {
+            run_bench(&args[1..]);
+            return Ok(());
+        }
+        // `export <path> ...` writes the saved graph in any File → Export Graph format
+        if args.first().map(String::as_str) == Some("export") {
+            run_export(&args[1..]);
+            return Ok(());
+        }
+        // `import-state <file>` upgrades a state file from an older version or converts an export
+        if args.first().map(String::as_str) == Some("import-state") {
+            run_import_state(&args[1..]);
             return Ok(());
         }
     }
 
+    // `service ...` talks to the service manager (or is started by it); it never opens a window
+    #[cfg(feature = "api")]
+    {
+        let args = std::env::args().skip(1).collect::<Vec<String>>();
+        if args.first().map(String::as_str) == Some("service") {
+            return run_service_command(&args[1..]);
+        }
+    }
+
+    // One process per user: a later launch hands its command to the running instance and exits
+    let instance_lock = claim_instance(&std::env::args().skip(1).collect::<Vec<String>>());
+
+    // Select a named settings profile first so the flags below can still override single values
+    {
+        let args = std::env::args().skip(1).collect::<Vec<String>>();
+        if let Some(name) = args.windows(2).find(|w| w[0] == "--profile").map(|w| w[1].clone()) {
+            let mut settings = persistence::settings::AppSettings::load().unwrap_or_default();
+            if settings.apply_profile(&name) {
+                let _ = settings.save();
+                log::info!("Using settings profile '{}'", name);
+            } else {
+                log::warn!("Unknown settings profile '{}'; using current settings", name);
+            }
+        }
+    }
+
     #[cfg(feature = "api")]
     let mut background_mode = false;
 
@@ -67,19 +114,21 @@ fn main() -> eframe::Result {
             }
             let _ = settings.save();
             persistence::persist::set_settings_override(settings.clone());
-            eprintln!("[Graph-Loom] API enabled on {}", settings.api_endpoint());
+            log::info!("API enabled on {}", settings.api_endpoint());
             if settings.grpc_enabled {
-                eprintln!("[Graph-Loom] gRPC enabled on {}:{}", settings.api_bind_addr, settings.grpc_port);
+                log::info!("gRPC enabled on {}:{}", settings.api_bind_addr, settings.grpc_port);
             }
         }
     }
 
     let settings = persistence::settings::AppSettings::load().unwrap_or_default();
     persistence::persist::set_settings_override(settings.clone());
+    gql::collation::set_session_options(settings.query_options());
+    api::telemetry::init(&settings);
 
     #[cfg(feature = "api")]
     if background_mode {
-        return run_background(settings);
+        return run_background(settings, instance_lock);
     }
 
     // If background_on_close is enabled and we have a server that could run,
@@ -104,7 +153,7 @@ fn main() -> eframe::Result {
     let icon = match eframe::icon_data::from_png_bytes(icon_bytes) {
         Ok(i) => i,
         Err(e) => {
-            eprintln!("[Graph-Loom] Failed to load window icon: {}", e);
+            log::error!("Failed to load window icon: {}", e);
             // Fallback: we could return an error, but eframe::run_native needs eframe::Result which is specific.
             // Let's just panic here with a clear message or use a simpler error.
             panic!("Icon load failed: {}", e);
@@ -112,89 +161,130 @@ fn main() -> eframe::Result {
     };
 
     // Initialize Tray Icon
-    let tray_menu = Menu::new();
-    let show_item = MenuItem::new("Show Graph-Loom", true, None);
-    let quit_item = MenuItem::new("Quit", true, None);
-    let _ = tray_menu.append(&show_item);
-    let _ = tray_menu.append(&quit_item);
+    let tray_menu = gui::tray::TrayMenu::new();
 
     let tray_icon_data = match tray_icon::Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height) {
         Ok(i) => i,
         Err(e) => {
-            eprintln!("[Graph-Loom] Failed to create tray icon: {}", e);
+            log::error!("Failed to create tray icon: {}", e);
             panic!("Tray icon creation failed: {}", e);
         }
     };
 
     let mut _tray_icon = match TrayIconBuilder::new()
-        .with_menu(Box::new(tray_menu))
+        .with_menu(Box::new(tray_menu.menu.clone()))
         .with_tooltip("Graph-Loom")
         .with_icon(tray_icon_data)
         .build() {
             Ok(i) => Some(i),
             Err(e) => {
-                eprintln!("[Graph-Loom] Failed to build tray icon: {}", e);
+                log::error!("Failed to build tray icon: {}", e);
                 None // Non-fatal if we can't show tray? Actually user might want it.
             }
         };
 
     let loaded_state = persist::load_active().ok().flatten();
 
-    env_logger::init();
+    let session = SessionState::load();
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([1300.0, 710.0])
+        // Provide sensible bounds so the UI stays usable on small screens
+        .with_min_inner_size([700.0, 420.0])
+        .with_resizable(true)
+        .with_icon(icon);
+    // Reopen where the window was last time
+    if let Some(w) = session.window {
+        viewport = viewport
+            .with_inner_size([w.width.max(700.0), w.height.max(420.0)])
+            .with_position([w.x, w.y])
+            .with_maximized(w.maximized);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1300.0, 710.0])
-            // Provide sensible bounds so the UI stays usable on small screens
-            .with_min_inner_size([700.0, 420.0])
-            .with_resizable(true)
-            .with_icon(icon),
+        viewport,
         ..Default::default()
     };
 
-    let show_item_id = show_item.id().clone();
-    let quit_item_id = quit_item.id().clone();
+    let tray_actions = tray_menu.actions();
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Graph-Loom",
         options,
         Box::new(move |cc| {
-            // Setup tray event listener
-            let ctx = cc.egui_ctx.clone();
-            std::thread::spawn(move || {
-                let menu_channel = MenuEvent::receiver();
-                loop {
-                    if let Ok(event) = menu_channel.recv() {
-                        if event.id == show_item_id {
-                            #[cfg(target_os = "windows")]
-                            unsafe {
-                                let _ = windows::Win32::UI::WindowsAndMessaging::AllowSetForegroundWindow(windows::Win32::UI::WindowsAndMessaging::ASFW_ANY);
-                            }
-
-                            crate::gui::app_state::SHOW_WINDOW.store(true, Ordering::SeqCst);
-                            
-                            // Send multiple commands to ensure visibility and focus
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
-                            
-                            // Use Win32 API to force foreground on Windows
-                            crate::gui::win_utils::force_foreground_window();
-
-                            // Repaint to ensure viewport commands are processed
-                            ctx.request_repaint();
-                            
-                            // Reset window level after a delay, but also re-assert focus
-                            // We now rely on GraphApp's update loop to handle the persistent restoration cycle
-                            // by reacting to the SHOW_WINDOW state change.
-                        } else if event.id == quit_item_id {
-                            std::process::exit(0);
-                        }
+            #[cfg(feature = "api")]
+            {
+                let ctx = cc.egui_ctx.clone();
+                service::install_stop_signals(move || {
+                    crate::gui::app_state::QUIT_REQUESTED.store(true, Ordering::SeqCst);
+                    ctx.request_repaint();
+                });
+            }
+
+            if let Some(lock) = instance_lock {
+                let ctx = cc.egui_ctx.clone();
+                let served = lock.listen(move |cmd| match cmd {
+                    instance::InstanceCommand::Show => {
+                        show_main_window(&ctx);
+                        Ok(String::new())
+                    }
+                    instance::InstanceCommand::Quit => {
+                        crate::gui::app_state::QUIT_REQUESTED.store(true, Ordering::SeqCst);
+                        ctx.request_repaint();
+                        Ok("closing".to_string())
                     }
+                    instance::InstanceCommand::Status => Ok(format!("pid={} mode=gui", std::process::id())),
+                });
+                if let Err(e) = served {
+                    log::warn!("Later launches cannot reach this instance: {}", e);
                 }
-            });
+            }
+
+            // Tray events arrive on the UI event loop; handlers act right away or hand the
+            // action to the app and wake it, so nothing polls while the window is hidden
+            let (tray_tx, tray_rx) = std::sync::mpsc::channel();
+            {
+                use gui::tray::TrayAction;
+                let ctx = cc.egui_ctx.clone();
+                let run = move |action: TrayAction| match action {
+                    TrayAction::Show => {
+                        #[cfg(target_os = "windows")]
+                        unsafe {
+                            let _ = windows::Win32::UI::WindowsAndMessaging::AllowSetForegroundWindow(windows::Win32::UI::WindowsAndMessaging::ASFW_ANY);
+                        }
+
+                        show_main_window(&ctx);
+                    }
+                    TrayAction::Quit => {
+                        // Let the app close normally so it can stop the servers and save
+                        crate::gui::app_state::QUIT_REQUESTED.store(true, Ordering::SeqCst);
+                        ctx.request_repaint();
+                    }
+                    other => {
+                        let _ = tray_tx.send(other);
+                        ctx.request_repaint();
+                    }
+                };
+                let run = std::sync::Arc::new(run);
+                let on_menu = run.clone();
+                MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                    if let Some((_, action)) = tray_actions.iter().find(|(id, _)| *id == event.id) {
+                        on_menu(*action);
+                    }
+                }));
+                // Left click on the icon toggles the window (Windows and macOS; Linux trays only have the menu)
+                TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+                    if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                        let shown = crate::gui::app_state::SHOW_WINDOW.load(Ordering::SeqCst);
+                        run(if shown { TrayAction::Hide } else { TrayAction::Show });
+                    }
+                }));
+            }
 
             if let Some(state) = loaded_state {
-                let app = GraphApp::from_state(state);
+                let mut app = GraphApp::from_state(state);
+                app.log_changes_to(persist::changes_log_path());
+                app.restore_session(session);
+                app.attach_tray(tray_menu, tray_rx);
+                app.wake_on_api_requests(cc.egui_ctx.clone());
                 #[cfg(feature = "api")]
                 if let Some(storage) = cc.storage {
                     if storage.get_string("background_on_close").as_deref() == Some("true") {
@@ -204,61 +294,432 @@ fn main() -> eframe::Result {
                 Ok(Box::new(app) as Box<dyn eframe::App>)
             } else {
                 // No prior state: start with an empty graph
-                let app = GraphApp::new(GraphDatabase::new());
+                let mut app = GraphApp::new(GraphDatabase::new());
+                app.log_changes_to(persist::changes_log_path());
+                app.restore_session(session);
+                app.attach_tray(tray_menu, tray_rx);
+                app.wake_on_api_requests(cc.egui_ctx.clone());
                 Ok(Box::new(app) as Box<dyn eframe::App>)
             }
         }),
-    )
+    );
+    api::telemetry::shutdown();
+    result
+}
+
+// Raise the (possibly hidden) main window; used by the tray and by later launches
+fn show_main_window(ctx: &egui::Context) {
+    crate::gui::app_state::SHOW_WINDOW.store(true, Ordering::SeqCst);
+
+    // Send multiple commands to ensure visibility and focus
+    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+
+    // Use Win32 API to force foreground on Windows
+    crate::gui::win_utils::force_foreground_window();
+
+    // Repaint to ensure viewport commands are processed
+    ctx.request_repaint();
+
+    // Reset window level after a delay, but also re-assert focus
+    // We now rely on GraphApp's update loop to handle the persistent restoration cycle
+    // by reacting to the SHOW_WINDOW state change.
 }
 
+// Take the single-instance lock, or forward this launch's command (`show`, `quit` or `status`;
+// `show` when none is given) to the instance holding it and exit
+fn claim_instance(args: &[String]) -> Option<instance::InstanceLock> {
+    use instance::{Instance, InstanceCommand};
+
+    let cmd = args.first().and_then(|a| InstanceCommand::parse(a));
+    match instance::acquire() {
+        Ok(Instance::Primary(lock)) => {
+            if matches!(cmd, Some(InstanceCommand::Quit | InstanceCommand::Status)) {
+                eprintln!("Graph-Loom is not running");
+                std::process::exit(1);
+            }
+            Some(lock)
+        }
+        Ok(Instance::Secondary(remote)) => {
+            let cmd = cmd.unwrap_or(InstanceCommand::Show);
+            // The running instance may only take the foreground if this (foreground) process allows it
+            #[cfg(target_os = "windows")]
+            unsafe {
+                let _ = windows::Win32::UI::WindowsAndMessaging::AllowSetForegroundWindow(windows::Win32::UI::WindowsAndMessaging::ASFW_ANY);
+            }
+            match remote.send(cmd) {
+                Ok(msg) => {
+                    if !msg.is_empty() { println!("{}", msg); }
+                    if cmd == InstanceCommand::Show && let Some(pid) = gui::win_utils::find_running_instance() {
+                        gui::win_utils::force_foreground_process(pid);
+                    }
+                    std::process::exit(0);
+                }
+                Err(e) => {
+                    log::error!("Graph-Loom is already running: {}", e);
+                    eprintln!("Graph-Loom is already running: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(e) => {
+            log::warn!("Single-instance check failed, continuing: {}", e);
+            None
+        }
+    }
+}
+
+// Log panics (with location) through the logger before the default hook prints them.
+// The GUI and background loops catch the unwind on their own thread to write an emergency save.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let msg = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "<non-string panic payload>".to_string());
+        let loc = info.location().map(|l| format!("{}:{}", l.file(), l.line())).unwrap_or_default();
+        log::error!("Panic at {}: {}", loc, msg);
+        default_hook(info);
+    }));
+}
+
+// Append the graph's pending changes to the change log, ahead of the state file that records
+// how far the numbering got
 #[cfg(feature = "api")]
-fn run_background(settings: persistence::settings::AppSettings) -> eframe::Result {
+fn flush_changes(db: &mut GraphDatabase) {
+    if let Err(e) = db.changes_mut().flush() { log::error!("Change log write failed: {}", e); }
+}
+
+// Save/load requested through the admin API while running without a GUI. Loading replaces
+// the graph (and the layout kept for the GUI), so afterwards the graph matches a file on disk
+// either way.
+#[cfg(feature = "api")]
+fn run_admin_background(db: &mut GraphDatabase, layout: &mut persist::Layout, task: api::AdminTask) -> anyhow::Result<gql::query_interface::QueryOutcome> {
+    let state = |db: &GraphDatabase| persist::AppStateFile::from_db_with_layout(db.clone(), layout);
+    let path = match task {
+        api::AdminTask::Save => {
+            flush_changes(db);
+            persist::save_active(&state(db))?
+        }
+        api::AdminTask::SaveVersion => persist::save_versioned(&state(db))?,
+        api::AdminTask::LoadVersion(path) => {
+            let (loaded, loaded_layout) = persist::load_from_path(&path)?.into_parts();
+            db.replace_with(loaded);
+            *layout = loaded_layout;
+            path
+        }
+    };
+    log::info!("Admin request completed for {}", path.display());
+    Ok(api::path_outcome(&path))
+}
+
+fn run_bench(args: &[String]) {
+    let opts = match bench::parse_args(args) {
+        Ok(o) => o,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+    match bench::run(&opts) {
+        Ok(report) => print!("{}", report),
+        Err(e) => {
+            eprintln!("Graph-Loom bench: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "api")]
+fn run_scheduled_export(schedule: &mut persistence::export_schedule::ExportSchedule, db: &GraphDatabase, settings: &persistence::settings::AppSettings) {
+    schedule.started(std::time::SystemTime::now());
+    match persistence::export_schedule::run(db, &settings.scheduled_export, settings) {
+        Ok(paths) => log::info!("Scheduled export wrote {} file(s) to {}", paths.len(), settings.scheduled_export.dir.trim()),
+        Err(e) => log::error!("Scheduled export failed: {:#}", e),
+    }
+}
+
+fn run_export(args: &[String]) {
+    let opts = match export::parse_args(args) {
+        Ok(o) => o,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+    match export::run(&opts) {
+        Ok(paths) => paths.iter().for_each(|p| println!("{}", p.display())),
+        Err(e) => {
+            eprintln!("Graph-Loom export: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_import_state(args: &[String]) {
+    let opts = match import_state::parse_args(args) {
+        Ok(o) => o,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+    match import_state::run(&opts) {
+        Ok(done) => println!("{}", done),
+        Err(e) => {
+            eprintln!("Graph-Loom import-state: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "api")]
+fn run_service_command(args: &[String]) -> eframe::Result {
+    let cmd = match service::parse_args(args) {
+        Ok(c) => c,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+    if cmd != service::Command::Run {
+        if let Err(e) = service::manage(&cmd) {
+            eprintln!("Graph-Loom service: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut settings = persistence::settings::AppSettings::load().unwrap_or_default();
+    if let Some(name) = args.windows(2).find(|w| w[0] == "--profile").map(|w| w[1].clone())
+        && !settings.apply_profile(&name)
+    {
+        log::warn!("Unknown settings profile '{}'; using current settings", name);
+    }
+    persistence::persist::set_settings_override(settings.clone());
+    gql::collation::set_session_options(settings.query_options());
+    api::telemetry::init(&settings);
+    let lock = match instance::acquire() {
+        Ok(instance::Instance::Primary(lock)) => Some(lock),
+        Ok(instance::Instance::Secondary(_)) => {
+            log::error!("Another Graph-Loom instance is running; stop it before starting the service");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            log::warn!("Single-instance check failed, continuing: {}", e);
+            None
+        }
+    };
+    if let Err(e) = service::run(move || {
+        let _ = run_background(settings, lock);
+    }) {
+        log::error!("Service failed: {:#}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "api")]
+enum RequestEffect {
+    None,
+    Mutated,
+    /// The graph was written to (or reloaded from) disk
+    Saved,
+}
+
+// Answer one broker request in background mode; a panic writes an emergency save first
+#[cfg(feature = "api")]
+fn serve_background_request(db: &mut GraphDatabase, layout: &mut persist::Layout, mut req: api::ApiRequest) -> RequestEffect {
+    use std::time::Instant;
+
+    let t0 = Instant::now();
+    if let Some(api::ApiTask::Admin(task)) = req.task.take() {
+        let res = run_admin_background(db, layout, task);
+        match &res {
+            Ok(_) => log::info!(target: logging::API_TARGET, "RID={} background admin done", req.request_id),
+            Err(e) => log::error!(target: logging::API_TARGET, "RID={} background admin failed: {}", req.request_id, e),
+        }
+        let effect = if res.is_ok() { RequestEffect::Saved } else { RequestEffect::None };
+        let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+        return effect;
+    }
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| api::execute(db, &mut req)));
+    let res = match res {
+        Ok(r) => r,
+        Err(payload) => {
+            let state = persist::AppStateFile::from_db_with_layout(db.clone(), layout);
+            match persist::save_recovery(&state) {
+                Ok(path) => log::error!("Emergency save written to {}", path.display()),
+                Err(e) => log::error!("Emergency save failed: {}", e),
+            }
+            std::panic::resume_unwind(payload);
+        }
+    };
+    let dt = t0.elapsed();
+
+    let mutated = res.as_ref().map(|o| o.mutated).unwrap_or(false);
+    log::info!(
+        target: logging::API_TARGET,
+        "RID={} background done mutated={} dt_ms={}",
+        req.request_id,
+        mutated,
+        dt.as_millis()
+    );
+    let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+    if mutated { RequestEffect::Mutated } else { RequestEffect::None }
+}
+
+#[cfg(feature = "api")]
+fn run_background(settings: persistence::settings::AppSettings, instance_lock: Option<instance::InstanceLock>) -> eframe::Result {
     use std::time::{Duration, Instant};
     use crate::api;
-    use crate::gql::query_interface;
 
-    eprintln!("[Graph-Loom] Running in BACKGROUND mode. No GUI will be shown.");
-    eprintln!("[Graph-Loom] Press Ctrl+C to stop.");
+    log::info!("Running in BACKGROUND mode. No GUI will be shown.");
+    log::info!("Press Ctrl+C to stop.");
+    service::install_stop_signals(|| {});
+    if let Some(lock) = instance_lock {
+        let served = lock.listen(|cmd| match cmd {
+            instance::InstanceCommand::Show => Err("running in background mode without a window".to_string()),
+            instance::InstanceCommand::Quit => {
+                service::request_stop();
+                Ok("stopping".to_string())
+            }
+            instance::InstanceCommand::Status => Ok(format!("pid={} mode=background", std::process::id())),
+        });
+        if let Err(e) = served {
+            log::warn!("Later launches cannot reach this instance: {}", e);
+        }
+    }
 
-    let mut db = if let Ok(Some(state)) = persist::load_active() {
-        eprintln!("[Graph-Loom] Loaded existing state.");
-        state.db
+    if persist::recovery_state_path().exists() {
+        log::warn!(
+            "An emergency save from a previous crash exists at {}; open the GUI to restore or discard it.",
+            persist::recovery_state_path().display()
+        );
+    }
+
+    // Another process (GUI, service, or another machine on a shared folder) may be saving to the
+    // same state file; background mode has no one to ask, so it refuses to start
+    let mut state_lock = match persistence::lock::StateLock::acquire(&persist::active_state_path()) {
+        Ok(Ok(l)) => Some(l),
+        Ok(Err(owner)) => {
+            log::error!("The graph is locked by {}; not starting background mode.", owner.describe());
+            std::process::exit(1);
+        }
+        Err(e) => {
+            log::warn!("Could not lock the state file: {}", e);
+            None
+        }
+    };
+    let mut last_heartbeat = Instant::now();
+    let mut lost_lock = false;
+
+    // The GUI's layout is kept and written back with every save, so opening the window later
+    // does not lay the graph out from scratch
+    let (mut db, mut layout) = if let Ok(Some(state)) = persist::load_active() {
+        log::info!("Loaded existing state.");
+        state.into_parts()
     } else {
-        eprintln!("[Graph-Loom] Starting with empty database.");
-        GraphDatabase::new()
+        log::info!("Starting with empty database.");
+        (GraphDatabase::new(), persist::Layout::default())
     };
+    db.changes_mut().attach(persist::changes_log_path());
 
     let rx = api::init_broker();
     
     // Start servers
     if settings.api_enabled {
         if let Err(e) = api::server::start_server(&settings) {
-            eprintln!("[Graph-Loom] Failed to start API server: {}", e);
+            log::error!("Failed to start API server: {}", e);
         }
     }
     if settings.grpc_enabled {
         if let Err(e) = api::grpc::start_grpc_server(&settings) {
-            eprintln!("[Graph-Loom] Failed to start gRPC server: {}", e);
+            log::error!("Failed to start gRPC server: {}", e);
         }
     }
 
+    let watcher = if settings.watch_folder_enabled {
+        match persistence::watch::FolderWatcher::start(settings.watch_folder_dir(), Duration::from_secs(2)) {
+            Ok(w) => {
+                log::info!("Watching {} for imports.", w.dir().display());
+                Some(w)
+            }
+            Err(e) => {
+                log::error!("Failed to start watch folder: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     let mut last_save = Instant::now();
     let mut dirty = false;
+    let mut backups = persistence::backup::BackupSchedule::new();
+    let mut exports = persistence::export_schedule::ExportSchedule::new(&settings.scheduled_export);
+    let mut last_backup_check = Instant::now();
+
+    while !service::stop_requested() {
+        // Keep the state lock fresh; if another process took it over, stop before overwriting its saves
+        if last_heartbeat.elapsed() >= persistence::lock::HEARTBEAT_INTERVAL && let Some(l) = &mut state_lock {
+            last_heartbeat = Instant::now();
+            match l.heartbeat() {
+                Ok(Ok(())) => {}
+                Ok(Err(owner)) => {
+                    log::error!("{} took over the graph lock; stopping without saving.", owner.describe());
+                    lost_lock = true;
+                    state_lock = None;
+                    service::request_stop();
+                    break;
+                }
+                Err(e) => log::warn!("State lock heartbeat failed: {}", e),
+            }
+        }
+
+        // Merge files dropped into the watch folder
+        if let Some(w) = &watcher {
+            while let Some(p) = w.try_recv() {
+                let res = persistence::import::merge_file_matching(&mut db, &p, &settings.import_match);
+                let _ = persistence::watch::finish(&p, res.is_ok());
+                match res {
+                    Ok(summary) => {
+                        if !summary.is_empty() {
+                            dirty = true;
+                            backups.note_change();
+                            exports.note_change();
+                        }
+                        log::info!("Imported {}: {}", p.display(), summary.describe());
+                    }
+                    Err(e) => log::error!("Import of {} failed: {}", p.display(), e),
+                }
+            }
+        }
+
+        // Scheduled versioned snapshot
+        if last_backup_check.elapsed() >= Duration::from_secs(60) {
+            last_backup_check = Instant::now();
+            if backups.is_due(&settings, std::time::SystemTime::now()) {
+                let state = persist::AppStateFile::from_db_with_layout(db.clone(), &layout);
+                match backups.run(&state, &settings) {
+                    Ok(path) => log::info!("Backup written to {}", path.display()),
+                    Err(e) => log::error!("Backup failed: {}", e),
+                }
+            }
+            if exports.is_due(&settings.scheduled_export, std::time::SystemTime::now()) {
+                run_scheduled_export(&mut exports, &db, &settings);
+            }
+        }
 
-    loop {
         // Periodic save
         if dirty && last_save.elapsed() > Duration::from_secs(5) {
             // Note: in background mode, db is local so we can use it to create owned state
-            let state = persist::AppStateFile::from_runtime_owned(
-                db.clone(),
-                &HashMap::new(), // positions not easily available/needed in background?
-                egui::Vec2::ZERO,
-                1.0,
-            );
+            flush_changes(&mut db);
+            let state = persist::AppStateFile::from_db_with_layout(db.clone(), &layout);
             if let Err(e) = persist::save_active(&state) {
-                eprintln!("[Graph-Loom] Background save failed: {}", e);
+                log::error!("Background save failed: {}", e);
             } else {
-                eprintln!("[Graph-Loom] Background state autosaved.");
+                log::info!("Background state autosaved.");
                 dirty = false;
                 last_save = Instant::now();
             }
@@ -266,25 +727,60 @@ fn run_background(settings: persistence::settings::AppSettings) -> eframe::Resul
 
         // Use recv_timeout to wait for requests instead of busy-looping
         if let Ok(req) = rx.recv_timeout(Duration::from_millis(500)) {
-            let t0 = Instant::now();
-            let res = match &req.params {
-                Some(p) => query_interface::execute_query_with_params(&mut db, &req.query, p),
-                None => query_interface::execute_and_log(&mut db, &req.query),
-            };
-            let dt = t0.elapsed();
-            
-            let mutated = res.as_ref().map(|o| o.mutated).unwrap_or(false);
-            if mutated {
-                dirty = true;
+            match serve_background_request(&mut db, &mut layout, req) {
+                RequestEffect::Mutated => {
+                    dirty = true;
+                    backups.note_change();
+                    exports.note_change();
+                }
+                RequestEffect::Saved => {
+                    dirty = false;
+                    last_save = Instant::now();
+                }
+                RequestEffect::None => {}
             }
+        }
+    }
 
-            eprintln!(
-                "[API Background] RID={} done mutated={} dt_ms={}",
-                req.request_id,
-                mutated,
-                dt.as_millis()
-            );
-            let _ = req.respond_to.send(res.map_err(|e| e.to_string()));
+    // Asked to stop (signal or service manager): refuse new connections, answer the requests
+    // that were already accepted, then write the graph one last time
+    log::info!("Stopping background mode.");
+    api::server::stop_accepting();
+    api::grpc::stop_grpc_server();
+    let drained = api::drain_requests(&rx, Duration::from_millis(500), Duration::from_secs(10), |req| {
+        match serve_background_request(&mut db, &mut layout, req) {
+            RequestEffect::Mutated => {
+                dirty = true;
+                backups.note_change();
+                exports.note_change();
+            }
+            RequestEffect::Saved => dirty = false,
+            RequestEffect::None => {}
+        }
+    });
+    if drained > 0 {
+        log::info!("Answered {} in-flight request(s) before shutdown.", drained);
+    }
+    api::server::stop_server();
+    if settings.backup_on_close && backups.has_changes() {
+        let state = persist::AppStateFile::from_db_with_layout(db.clone(), &layout);
+        match backups.run(&state, &settings) {
+            Ok(path) => log::info!("Backup written to {}", path.display()),
+            Err(e) => log::error!("Backup failed: {}", e),
+        }
+    }
+    if exports.is_due_on_close(&settings.scheduled_export) {
+        run_scheduled_export(&mut exports, &db, &settings);
+    }
+    if dirty && !lost_lock {
+        flush_changes(&mut db);
+        let state = persist::AppStateFile::from_db_with_layout(db, &layout);
+        match persist::save_active(&state) {
+            Ok(path) => log::info!("Final state saved to {}", path.display()),
+            Err(e) => log::error!("Final save failed: {}", e),
         }
     }
+    drop(state_lock);
+    api::telemetry::shutdown();
+    Ok(())
 }
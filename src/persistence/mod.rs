@@ -1,2 +0,0 @@
-pub mod persist;
-pub mod settings;
\ No newline at end of file
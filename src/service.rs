@@ -0,0 +1,317 @@
+//! `Graph-Loom service <command>`: run background mode under the OS service manager (a systemd
+//! user unit on Linux, a Windows service) and stop it cleanly when the manager asks.
+//!
+//! The service process runs the same loop as `--background`; a stop request only sets a flag
+//! that the loop polls, so it can shut the servers down and save the graph before exiting.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Name of the systemd unit (without `.service`) and of the Windows service.
+pub const SERVICE_NAME: &str = "graph-loom";
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Ask the background loop to shut down.
+pub fn request_stop() {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Register the service (`--profile NAME` is passed on to every start).
+    Install { profile: Option<String> },
+    Uninstall,
+    Start,
+    Stop,
+    Status,
+    /// Entry point used by the service manager itself.
+    Run,
+}
+
+pub const USAGE: &str = "usage: Graph-Loom service <install [--profile NAME]|uninstall|start|stop|status>";
+
+/// Parse the arguments after `service`.
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    let profile = args.windows(2).find(|w| w[0] == "--profile").map(|w| w[1].clone());
+    match args.first().map(String::as_str) {
+        Some("install") => Ok(Command::Install { profile }),
+        Some("uninstall") => Ok(Command::Uninstall),
+        Some("start") => Ok(Command::Start),
+        Some("stop") => Ok(Command::Stop),
+        Some("status") => Ok(Command::Status),
+        Some("run") => Ok(Command::Run),
+        Some(other) => Err(format!("unknown service command '{}'\n{}", other, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+/// Contents of the systemd user unit that starts `exe` in service mode.
+pub fn systemd_unit(exe: &Path, profile: Option<&str>) -> String {
+    let mut exec = format!("\"{}\" service run", exe.display());
+    if let Some(p) = profile {
+        exec.push_str(&format!(" --profile \"{}\"", p.replace('"', "\\\"")));
+    }
+    format!(
+        "[Unit]\n\
+         Description=Graph-Loom graph service\n\
+         After=network.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         # Time to save the graph after SIGTERM before systemd kills the process\n\
+         TimeoutStopSec=30\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exec
+    )
+}
+
+/// The command line the Windows service manager runs (`binPath=` of `sc.exe create`), each
+/// argument quoted the way Windows programs split their command line.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn windows_bin_path(exe: &Path, profile: Option<&str>) -> String {
+    let mut args = vec![exe.display().to_string(), "service".to_string(), "run".to_string()];
+    if let Some(p) = profile { args.extend(["--profile".to_string(), p.to_string()]); }
+    args.iter().map(|a| quote_windows_arg(a)).collect::<Vec<_>>().join(" ")
+}
+
+// Backslashes are literal unless they precede a quote, so only those runs are doubled
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) { return arg.to_string(); }
+    let mut out = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        out.extend(std::iter::repeat_n('\\', if c == '"' { backslashes * 2 + 1 } else { backslashes }));
+        out.push(c);
+        backslashes = 0;
+    }
+    out.extend(std::iter::repeat_n('\\', backslashes * 2));
+    out.push('"');
+    out
+}
+
+/// Where `install` writes the systemd user unit.
+pub fn systemd_unit_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(config.join("systemd").join("user").join(format!("{}.service", SERVICE_NAME)))
+}
+
+fn run_tool(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let status = std::process::Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| anyhow::anyhow!("could not run {}: {}", program, e))?;
+    anyhow::ensure!(status.success(), "{} {} failed ({})", program, args.join(" "), status);
+    Ok(())
+}
+
+/// Carry out a management command (everything but [`Command::Run`]).
+#[cfg(target_os = "linux")]
+pub fn manage(cmd: &Command) -> anyhow::Result<()> {
+    let unit = format!("{}.service", SERVICE_NAME);
+    let systemctl = |args: &[&str]| {
+        let mut all = vec!["--user"];
+        all.extend_from_slice(args);
+        run_tool("systemctl", &all)
+    };
+    match cmd {
+        Command::Install { profile } => {
+            let path = systemd_unit_path().ok_or_else(|| anyhow::anyhow!("cannot locate the systemd user directory (HOME is not set)"))?;
+            let exe = std::env::current_exe()?;
+            if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+            std::fs::write(&path, systemd_unit(&exe, profile.as_deref()))?;
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", &unit])?;
+            println!("Installed {}; start it with `Graph-Loom service start`.", path.display());
+            println!("To keep it running after logout: loginctl enable-linger $USER");
+            Ok(())
+        }
+        Command::Uninstall => {
+            let _ = systemctl(&["disable", "--now", &unit]);
+            if let Some(path) = systemd_unit_path().filter(|p| p.exists()) {
+                std::fs::remove_file(&path)?;
+            }
+            systemctl(&["daemon-reload"])
+        }
+        Command::Start => systemctl(&["start", &unit]),
+        Command::Stop => systemctl(&["stop", &unit]),
+        Command::Status => systemctl(&["status", "--no-pager", &unit]),
+        Command::Run => anyhow::bail!("`service run` is started by the service manager"),
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn manage(cmd: &Command) -> anyhow::Result<()> {
+    match cmd {
+        Command::Install { profile } => {
+            let bin = windows_bin_path(&std::env::current_exe()?, profile.as_deref());
+            run_tool("sc.exe", &["create", SERVICE_NAME, "binPath=", &bin, "start=", "auto", "DisplayName=", "Graph-Loom"])?;
+            let _ = run_tool("sc.exe", &["description", SERVICE_NAME, "Graph-Loom graph service (HTTP/gRPC API)"]);
+            // Services run as LocalSystem unless given an account and its password
+            let root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+            println!("Installed the {} service; start it with `Graph-Loom service start`.", SERVICE_NAME);
+            println!("It runs as LocalSystem, so it reads its settings and graph from {}\\System32\\config\\systemprofile\\AppData\\Roaming\\Graph-Loom, not from your profile.", root);
+            println!("To run it under your account instead: sc.exe config {} obj= .\\%USERNAME% password= <your password>", SERVICE_NAME);
+            Ok(())
+        }
+        Command::Uninstall => {
+            let _ = run_tool("sc.exe", &["stop", SERVICE_NAME]);
+            run_tool("sc.exe", &["delete", SERVICE_NAME])
+        }
+        Command::Start => run_tool("sc.exe", &["start", SERVICE_NAME]),
+        Command::Stop => run_tool("sc.exe", &["stop", SERVICE_NAME]),
+        Command::Status => run_tool("sc.exe", &["query", SERVICE_NAME]),
+        Command::Run => anyhow::bail!("`service run` is started by the service manager"),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn manage(_cmd: &Command) -> anyhow::Result<()> {
+    anyhow::bail!("service install is only supported on Linux (systemd) and Windows; run `Graph-Loom --background` under your own supervisor")
+}
+
+/// Call [`request_stop`] and then `on_stop` when the process is asked to terminate: Ctrl+C,
+/// SIGTERM on Unix, closing the console or logging off on Windows. Watches on its own thread.
+pub fn install_stop_signals(on_stop: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || {
+        let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(rt) => rt,
+            Err(e) => return log::error!("Failed to create signal runtime: {}", e),
+        };
+        let which = rt.block_on(wait_for_stop_signal());
+        match which {
+            Ok(name) => log::info!("{} received; shutting down", name),
+            Err(e) => return log::error!("Failed to install stop signal handlers: {}", e),
+        }
+        request_stop();
+        on_stop();
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_stop_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut term = signal(SignalKind::terminate())?;
+    tokio::select! {
+        r = tokio::signal::ctrl_c() => r.map(|_| "Ctrl+C"),
+        _ = term.recv() => Ok("SIGTERM"),
+    }
+}
+
+#[cfg(windows)]
+async fn wait_for_stop_signal() -> std::io::Result<&'static str> {
+    use tokio::signal::windows::{ctrl_close, ctrl_logoff, ctrl_shutdown};
+    let (mut close, mut logoff, mut shutdown) = (ctrl_close()?, ctrl_logoff()?, ctrl_shutdown()?);
+    tokio::select! {
+        r = tokio::signal::ctrl_c() => r.map(|_| "Ctrl+C"),
+        _ = close.recv() => Ok("Console close"),
+        _ = logoff.recv() => Ok("Logoff"),
+        _ = shutdown.recv() => Ok("Shutdown"),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn wait_for_stop_signal() -> std::io::Result<&'static str> {
+    tokio::signal::ctrl_c().await.map(|_| "Ctrl+C")
+}
+
+/// Run `body` (the background loop) as the service. Stop requests from the service manager
+/// call [`request_stop`]; `body` is expected to return once it sees [`stop_requested`].
+#[cfg(not(target_os = "windows"))]
+pub fn run(body: impl FnOnce() + Send + 'static) -> anyhow::Result<()> {
+    // systemd stops a unit with SIGTERM, which the background loop's own handlers turn into a stop request
+    body();
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn run(body: impl FnOnce() + Send + 'static) -> anyhow::Result<()> {
+    windows_service::run(Box::new(body))
+}
+
+#[cfg(target_os = "windows")]
+mod windows_service {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::System::Services::{
+        RegisterServiceCtrlHandlerExW, SetServiceStatus, StartServiceCtrlDispatcherW, SERVICE_ACCEPT_SHUTDOWN,
+        SERVICE_ACCEPT_STOP, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP, SERVICE_RUNNING, SERVICE_STATUS,
+        SERVICE_STATUS_CURRENT_STATE, SERVICE_STATUS_HANDLE, SERVICE_STOPPED, SERVICE_STOP_PENDING,
+        SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS,
+    };
+
+    // The SCM calls `service_main` on its own thread, so the loop is handed over through here
+    static BODY: Mutex<Option<Box<dyn FnOnce() + Send>>> = Mutex::new(None);
+    static STATUS_HANDLE: AtomicUsize = AtomicUsize::new(0);
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn report(state: SERVICE_STATUS_CURRENT_STATE, wait_hint_ms: u32) {
+        let handle = SERVICE_STATUS_HANDLE(STATUS_HANDLE.load(Ordering::SeqCst) as *mut core::ffi::c_void);
+        let accepted = if state == SERVICE_RUNNING { SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN } else { 0 };
+        let status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: state,
+            dwControlsAccepted: accepted,
+            dwWin32ExitCode: 0,
+            dwServiceSpecificExitCode: 0,
+            dwCheckPoint: 0,
+            dwWaitHint: wait_hint_ms,
+        };
+        if let Err(e) = unsafe { SetServiceStatus(handle, &status) } {
+            log::error!("SetServiceStatus failed: {}", e);
+        }
+    }
+
+    unsafe extern "system" fn control_handler(control: u32, _event: u32, _data: *mut core::ffi::c_void, _ctx: *mut core::ffi::c_void) -> u32 {
+        if control == SERVICE_CONTROL_STOP || control == SERVICE_CONTROL_SHUTDOWN {
+            report(SERVICE_STOP_PENDING, 30_000);
+            super::request_stop();
+        }
+        0
+    }
+
+    unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+        let name = wide(super::SERVICE_NAME);
+        match unsafe { RegisterServiceCtrlHandlerExW(PCWSTR(name.as_ptr()), Some(control_handler), None) } {
+            Ok(h) => STATUS_HANDLE.store(h.0 as usize, Ordering::SeqCst),
+            Err(e) => return log::error!("RegisterServiceCtrlHandlerExW failed: {}", e),
+        }
+        report(SERVICE_RUNNING, 0);
+        if let Some(body) = BODY.lock().ok().and_then(|mut b| b.take()) {
+            body();
+        }
+        report(SERVICE_STOPPED, 0);
+    }
+
+    pub fn run(body: Box<dyn FnOnce() + Send>) -> anyhow::Result<()> {
+        if let Ok(mut b) = BODY.lock() { *b = Some(body); }
+        let mut name = wide(super::SERVICE_NAME);
+        let table = [
+            SERVICE_TABLE_ENTRYW { lpServiceName: PWSTR(name.as_mut_ptr()), lpServiceProc: Some(service_main) },
+            SERVICE_TABLE_ENTRYW { lpServiceName: PWSTR::null(), lpServiceProc: None },
+        ];
+        // Blocks until the service has stopped
+        unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) }
+            .map_err(|e| anyhow::anyhow!("not started by the Service Control Manager: {}", e))
+    }
+}